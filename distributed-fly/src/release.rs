@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::RwLock;
 
 use rmp_serde::Deserializer;
@@ -32,6 +32,8 @@ pub struct Release {
   pub config: Value,
   pub secrets: Value,
   pub libs: Option<Vec<String>>,
+  /// Named module specifiers to warm up once the release's runtime is started.
+  pub entry_points: Option<BTreeMap<String, String>>,
 }
 
 impl Release {
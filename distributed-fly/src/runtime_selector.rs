@@ -1,7 +1,7 @@
 use futures::Future;
 
 use fly::{
-    runtime::{Runtime, RuntimeConfig},
+    runtime::{EntryPoint, Runtime},
     RuntimeSelector, SelectorError,
 };
 
@@ -120,19 +120,67 @@ impl RuntimeSelector for DistributedRuntimeSelector {
                         url: global_settings.redis_url.clone(),
                         namespace: None,
                     })),
+                    module_source_cache: None,
+                    module_release_compiled_sources: None,
+                    module_disk_cache: None,
+                    module_fetch_network: None,
+                    module_fetch_max_redirects: None,
+                    module_fetch_allow_insecure_redirects: None,
+                    // releases are bundled; tenants don't fetch code at runtime
+                    module_remote_imports: Some(false),
+                    module_load_scheduler: Some(
+                        fly::module_resolver::LoadSchedulerConfig::default(),
+                    ),
+                    module_base_urls: None,
+                    module_wasm_imports: None,
+                    module_import_map: None,
+                    module_workspace_root: None,
+                    module_node_modules_root: None,
+                    module_index_files: None,
+                    module_extensions: None,
+                    module_commonjs: None,
+                    module_text_assets: None,
+                    module_binary_assets: None,
+                    module_binary_asset_max_bytes: None,
+                    module_strict_resolution: None,
+                    module_allow_outside_root: None,
+                    module_canonicalize_paths: None,
+                    module_case_sensitivity: None,
+                    module_transpile: None,
+                    module_compile_cache: None,
+                    module_wasm_cache: None,
+                    module_hot_reload: None,
+                    module_env_allowlist: None,
+                    module_dev_server: None,
+                    module_cassette: None,
+                    module_lockfile: None,
+                    // tenants share the host, so none of them gets to hook module resolution
+                    module_guest_resolvers: Some(fly::guest_resolver::GuestResolversConfig {
+                        enabled: false,
+                        ..Default::default()
+                    }),
+                    op_limits: None,
                 }
             };
 
-            let mut rt = Runtime::new(RuntimeConfig {
-                name: Some(rel.app_id.to_string()),
-                version: Some(rel.version.to_string()),
-                settings: &settings,
-                module_resolvers: Some(vec![]),
-                app_logger: &slog_scope::logger(),
-                msg_handler: None,
-                permissions: None,
-                dev_tools: false,
-            });
+            let entry_points = rel
+                .entry_points
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, specifier)| EntryPoint { name, specifier })
+                .collect();
+            let mut rt = Runtime::builder(&settings, &slog_scope::logger())
+                .name(&rel.app_id.to_string())
+                .version(&rel.version.to_string())
+                .module_resolvers(vec![])
+                .entry_points(entry_points)
+                .build_info(fly::module_resolver::BuildInfo {
+                    version: Some(rel.version.to_string()),
+                    ..Default::default()
+                })
+                .build()
+                .map_err(|e| SelectorError::Failure(format!("{}", e)))?;
             let merged_conf = rel.clone().parsed_config().unwrap();
             rt.eval(
                 "<app config>",
@@ -159,6 +207,10 @@ impl RuntimeSelector for DistributedRuntimeSelector {
             }
 
             rt.eval("app.js", &rel.source);
+            // a release serves its requests even when an entry point's graph doesn't load
+            if let Err(e) = rt.warmup_entry_points() {
+                warn!("error warming up entry points of app {}: {}", &rel.app, e);
+            }
             let app = rel.app;
             let app_id = rel.app_id;
             let version = rel.version;
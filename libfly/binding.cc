@@ -397,9 +397,20 @@ js_compile_module_result CompileV8Module(v8::Local<v8::Context> context, js_modu
                           v8::Boolean::New(isolate, module_data.is_wasm), // is WASM
                           True(isolate));                                 // is ES6 module
 
-  v8::ScriptCompiler::Source module_source(v8_str_from_fly_simple_buf(isolate, module_data.source_code), origin);
+  // Source owns the cached data it's given.
+  v8::ScriptCompiler::CachedData *cached_data = NULL;
+  auto options = v8::ScriptCompiler::kNoCompileOptions;
+  if (module_data.code_cache.ptr != NULL)
+  {
+    cached_data = new v8::ScriptCompiler::CachedData(
+        reinterpret_cast<const uint8_t *>(module_data.code_cache.ptr),
+        module_data.code_cache.len);
+    options = v8::ScriptCompiler::kConsumeCodeCache;
+  }
 
-  auto module = v8::ScriptCompiler::CompileModule(isolate, &module_source);
+  v8::ScriptCompiler::Source module_source(v8_str_from_fly_simple_buf(isolate, module_data.source_code), origin, cached_data);
+
+  auto module = v8::ScriptCompiler::CompileModule(isolate, &module_source, options);
 
   if (module.IsEmpty())
   {
@@ -415,10 +426,25 @@ js_compile_module_result CompileV8Module(v8::Local<v8::Context> context, js_modu
 
   v8::Persistent<v8::Module> module_persistent(isolate, module_local);
 
+  // Made when there was no cache or V8 rejected it, so the next compile can skip parsing.
+  fly_simple_buf code_cache{NULL, 0};
+  if (!module_data.is_wasm && (cached_data == NULL || cached_data->rejected))
+  {
+    auto created = v8::ScriptCompiler::CreateCodeCache(module_local->GetUnboundModuleScript());
+    if (created != NULL)
+    {
+      auto bytes = new char[created->length];
+      memcpy(bytes, created->data, created->length);
+      code_cache = fly_simple_buf{bytes, created->length};
+      delete created;
+    }
+  }
+
   auto compiled_module = js_compiled_module{
       module_local->GetIdentityHash(),
       module_data,
       &module_persistent,
+      code_cache,
   };
 
   return js_compile_module_result{
@@ -510,12 +536,62 @@ void SetGlobalErrorHandler(const v8::FunctionCallbackInfo<v8::Value> &args)
   rt->global_error_handler.Reset(isolate, func);
 }
 
+// Compiles a WebAssembly module from its wire bytes, args[0], or deserializes it from what
+// V8 serialized compiling it before, args[1], falling back to compiling when that's refused.
+// Returns [module, serialized], serialized being undefined unless it was compiled afresh.
+void CompileWasm(const v8::FunctionCallbackInfo<v8::Value> &args)
+{
+  v8::Isolate *isolate = args.GetIsolate();
+  v8::EscapableHandleScope handle_scope(isolate);
+  auto context = isolate->GetCurrentContext();
+
+  if (!args[0]->IsArrayBufferView())
+  {
+    isolate->ThrowException(v8::Exception::TypeError(v8_str("libfly.compileWasm needs the module's bytes")));
+    return;
+  }
+  auto wire_view = v8::Local<v8::ArrayBufferView>::Cast(args[0]);
+  auto wire_data = static_cast<const uint8_t *>(wire_view->Buffer()->GetContents().Data()) + wire_view->ByteOffset();
+  v8::WasmModuleObject::BufferReference wire_bytes(wire_data, wire_view->ByteLength());
+
+  // an empty serialized module makes V8 compile
+  bool consumed = args[1]->IsArrayBufferView();
+  v8::WasmModuleObject::BufferReference serialized(nullptr, 0);
+  if (consumed)
+  {
+    auto view = v8::Local<v8::ArrayBufferView>::Cast(args[1]);
+    auto data = static_cast<const uint8_t *>(view->Buffer()->GetContents().Data()) + view->ByteOffset();
+    serialized = v8::WasmModuleObject::BufferReference(data, view->ByteLength());
+  }
+
+  v8::TryCatch try_catch(isolate);
+  auto maybe_module = v8::WasmModuleObject::DeserializeOrCompile(isolate, serialized, wire_bytes);
+  v8::Local<v8::WasmModuleObject> module;
+  if (!maybe_module.ToLocal(&module))
+  {
+    try_catch.ReThrow();
+    return;
+  }
+
+  auto result = v8::Array::New(isolate, 2);
+  result->Set(context, 0, module).FromJust();
+  if (!consumed)
+  {
+    auto made = module->Serialize();
+    auto ab = v8::ArrayBuffer::New(isolate, made.second);
+    memcpy(ab->GetContents().Data(), made.first.get(), made.second);
+    result->Set(context, 1, v8::Uint8Array::New(ab, 0, made.second)).FromJust();
+  }
+  args.GetReturnValue().Set(handle_scope.Escape(result));
+}
+
 intptr_t ext_refs[] = {
     reinterpret_cast<intptr_t>(Print),
     reinterpret_cast<intptr_t>(Send),
     reinterpret_cast<intptr_t>(Recv),
     reinterpret_cast<intptr_t>(SetGlobalErrorHandler),
     reinterpret_cast<intptr_t>(GetNextStreamId),
+    reinterpret_cast<intptr_t>(CompileWasm),
     0};
 
 void InitContext(v8::Isolate *isolate, v8::Local<v8::Context> context)
@@ -545,6 +621,10 @@ void InitContext(v8::Isolate *isolate, v8::Local<v8::Context> context)
   auto gnsi_tmpl = v8::FunctionTemplate::New(isolate, GetNextStreamId);
   auto gnsi_val = gnsi_tmpl->GetFunction(context).ToLocalChecked();
   fly->Set(context, v8_str(isolate, "getNextStreamId"), gnsi_val).FromJust();
+
+  auto cw_tmpl = v8::FunctionTemplate::New(isolate, CompileWasm);
+  auto cw_val = cw_tmpl->GetFunction(context).ToLocalChecked();
+  fly->Set(context, v8_str(isolate, "compileWasm"), cw_val).FromJust();
 }
 
 extern "C"
@@ -880,4 +960,9 @@ extern "C"
     VALUE_SCOPE(rt->isolate, rt->context);
     return CompileV8Module(ctx, module_data);
   }
+
+  void js_free_code_cache(fly_simple_buf code_cache)
+  {
+    delete[] code_cache.ptr;
+  }
 }
@@ -40,12 +40,14 @@ struct js_module_data {
   const char *source_map_url;
   bool is_wasm;
   fly_simple_buf source_code;
+  fly_simple_buf code_cache; // ptr is NULL when there's none to consume
 };
 
 struct js_compiled_module {
   int hash;
   js_module_data data;
-  void *ptr; // pointer to v8::Persistent<v8::Module>
+  void *ptr;                 // pointer to v8::Persistent<v8::Module>
+  fly_simple_buf code_cache; // made when none was consumed, free with js_free_code_cache
 };
 
 struct js_compile_module_result {
@@ -103,6 +105,7 @@ extern "C"
   extern const char *js_version();
 
   extern js_compile_module_result js_compile_module(const runtime *rt, js_module_data module_data);
+  extern void js_free_code_cache(fly_simple_buf code_cache);
 
 } // extern "C"
 
@@ -45,6 +45,8 @@ pub struct js_module_data {
     pub source_map_url: *const c_char,
     pub is_wasm: bool,
     pub source_code: fly_simple_buf,
+    /// A code cache for V8 to consume, or a NULL `ptr` for none.
+    pub code_cache: fly_simple_buf,
 }
 
 #[repr(C)]
@@ -52,6 +54,8 @@ pub struct js_compiled_module {
     pub hash: c_int,
     pub data: js_module_data,
     pub ptr: *mut c_void,
+    /// The code cache V8 made, when it consumed none; free it with `js_free_code_cache`.
+    pub code_cache: fly_simple_buf,
 }
 
 #[repr(C)]
@@ -111,6 +115,7 @@ extern "C" {
     pub fn js_eval(rt: *const js_runtime, filename: *const c_char, code: *const c_char) -> bool;
     pub fn js_run_module(rt: *const js_runtime, module_data: js_compiled_module) -> bool;
     pub fn js_compile_module(rt: *const js_runtime, module_data: js_module_data) -> js_compile_module_result;
+    pub fn js_free_code_cache(code_cache: fly_simple_buf);
 }
 
 #[no_mangle]
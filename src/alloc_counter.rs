@@ -0,0 +1,69 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/**
+ * The allocator of the test binary: the system's, counting the allocations that could be a
+ * copy of a buffer a test watches, so it can check the buffer is shared instead.
+ */
+struct CountingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static WATCHED_MIN: AtomicUsize = AtomicUsize::new(std::usize::MAX);
+static WATCHED_MAX: AtomicUsize = AtomicUsize::new(0);
+static COUNTED: AtomicUsize = AtomicUsize::new(0);
+
+/** Room over the watched length for what holds a copy: an `Arc`'s counts, a `Bytes`' header. */
+const HEADER_BYTES: usize = 32;
+
+lazy_static! {
+    static ref WATCHING: Mutex<()> = Mutex::new(());
+}
+
+fn count(size: usize) {
+    if size >= WATCHED_MIN.load(Ordering::Relaxed) && size <= WATCHED_MAX.load(Ordering::Relaxed) {
+        COUNTED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/**
+ * Runs `f`, counting the allocations of `len` bytes, give or take a header, that it or any
+ * other thread makes meanwhile: the copies of a `len` byte buffer. Pick an odd `len` so other
+ * tests don't add to it; one count runs at a time.
+ */
+pub fn copies_of<T, F>(len: usize, f: F) -> (usize, T)
+where
+    F: FnOnce() -> T,
+{
+    let _watching = WATCHING.lock().unwrap_or_else(|e| e.into_inner());
+    COUNTED.store(0, Ordering::SeqCst);
+    WATCHED_MAX.store(len + HEADER_BYTES, Ordering::SeqCst);
+    WATCHED_MIN.store(len, Ordering::SeqCst);
+    let result = f();
+    WATCHED_MIN.store(std::usize::MAX, Ordering::SeqCst);
+    WATCHED_MAX.store(0, Ordering::SeqCst);
+    (COUNTED.load(Ordering::SeqCst), result)
+}
@@ -0,0 +1,105 @@
+use crate::errors::*;
+use crate::util::*;
+use clap::{Arg, ArgMatches, SubCommand};
+use fly::module_resolver::{GcOptions, RemoteModuleCache, RemoteModuleCacheConfig};
+use fly::settings::SETTINGS;
+use std::time::Duration;
+
+pub fn cli() -> App {
+    subcommand("cache")
+        .about("Inspect and clean the remote module disk cache")
+        .subcommand(SubCommand::with_name("info").about("Show cache usage by tenant"))
+        .subcommand(
+            SubCommand::with_name("gc")
+                .about("Remove entries that haven't been used recently")
+                .arg(
+                    Arg::with_name("max-idle")
+                        .long("max-idle")
+                        .value_name("SECONDS")
+                        .help("Remove entries not read within this many seconds")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Report what would be removed without removing it"),
+                ),
+        )
+}
+
+pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
+    let settings = SETTINGS.read().unwrap();
+    let config = match settings.module_disk_cache {
+        Some(ref config) => config,
+        None => return Err(FlyCliError::from("no module_disk_cache is configured")),
+    };
+    // open without the configured startup gc so `info` never deletes anything
+    let cache = RemoteModuleCache::open(
+        config.dir.as_str(),
+        RemoteModuleCacheConfig {
+            gc_max_idle_secs: None,
+            ..config.cache.clone()
+        },
+    )
+    .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))?;
+
+    match args.subcommand() {
+        ("gc", Some(gc_args)) => {
+            let max_idle = match gc_args.value_of("max-idle") {
+                Some(secs) => Some(secs.parse::<u64>().map_err(|_| {
+                    FlyCliError::from(format!("invalid --max-idle {}", secs).as_str())
+                })?),
+                None => config.cache.gc_max_idle_secs,
+            };
+            let report = cache
+                .gc(&GcOptions {
+                    max_idle: max_idle.map(Duration::from_secs),
+                    dry_run: gc_args.is_present("dry-run"),
+                    ..GcOptions::default()
+                })
+                .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))?;
+            for removal in &report.removed {
+                println!(
+                    "{} {:?} {} bytes {}",
+                    if report.dry_run {
+                        "would remove"
+                    } else {
+                        "removed"
+                    },
+                    removal.reason,
+                    removal.bytes,
+                    removal.url.as_ref().unwrap_or(&removal.path)
+                );
+            }
+            println!(
+                "{} {} bytes",
+                if report.dry_run {
+                    "Would free"
+                } else {
+                    "Freed"
+                },
+                report.freed_bytes
+            );
+        }
+        _ => {
+            let info = cache.info();
+            println!("Module cache: {}", info.root);
+            println!("  {} entries, {} bytes", info.entries, info.bytes);
+            for (tenant, usage) in &info.tenants {
+                println!(
+                    "  {}: {} entries, {} bytes",
+                    tenant, usage.entries, usage.bytes
+                );
+            }
+            match info.last_gc {
+                Some(gc) => println!(
+                    "Last gc: freed {} bytes in {} files ({} ms since epoch)",
+                    gc.freed_bytes, gc.removed_entries, gc.finished_at_ms
+                ),
+                None => println!("Last gc: never"),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -83,7 +83,9 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
         }
     };
 
-    module_resolvers.push(Box::new(LocalDiskModuleResolver::new(None)));
+    module_resolvers.push(Box::new(
+        LocalDiskModuleResolver::new(None)?.with_allow_outside_root(true),
+    ));
 
     info!(
         "Module resolvers length {}",
@@ -91,16 +93,10 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
     );
 
     let entry_file = args.value_of("path").unwrap();
-    let mut runtime = Runtime::new(RuntimeConfig {
-        name: None,
-        version: None,
-        settings: &SETTINGS.read().unwrap(),
-        module_resolvers: Some(module_resolvers),
-        app_logger: &slog_scope::logger(),
-        msg_handler: None,
-        permissions: None,
-        dev_tools: true,
-    });
+    let mut runtime = Runtime::builder(&SETTINGS.read().unwrap(), &slog_scope::logger())
+        .module_resolvers(module_resolvers)
+        .dev_tools(true)
+        .build()?;
 
     runtime.eval_file_with_dev_tools(entry_file);
 
@@ -0,0 +1,27 @@
+use crate::errors::*;
+use crate::util::*;
+use clap::{Arg, ArgMatches};
+use fly::runtime::Runtime;
+use fly::settings::SETTINGS;
+
+pub fn cli() -> App {
+    subcommand("doctor")
+        .about("Show how module resolution is configured")
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the description as JSON"),
+        )
+}
+
+pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
+    let runtime = Runtime::builder(&SETTINGS.read().unwrap(), &slog_scope::logger()).build()?;
+
+    match runtime.module_resolver_manager.describe() {
+        Some(description) if args.is_present("json") => println!("{}", description.to_json()),
+        Some(description) => print!("{}", description),
+        None => println!("The module resolver manager can't describe itself"),
+    }
+
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use crate::errors::*;
 use crate::util::*;
 use clap::{Arg, ArgMatches};
-use fly::runtime::{Runtime, RuntimeConfig};
+use fly::module_resolver::remote_entry_url;
+use fly::runtime::Runtime;
 use fly::settings::SETTINGS;
 use futures::Future;
 
@@ -15,19 +16,22 @@ pub fn cli() -> App {
 }
 
 pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
-    let mut runtime = Runtime::new(RuntimeConfig {
-        name: None,
-        version: None,
-        settings: &SETTINGS.read().unwrap(),
-        module_resolvers: None,
-        app_logger: &slog_scope::logger(),
-        msg_handler: None,
-        permissions: None,
-        dev_tools: true,
-    });
+    let entry = args.value_of("input").unwrap();
+    let remote_entry = remote_entry_url(entry);
+    let mut runtime = {
+        let settings = SETTINGS.read().unwrap();
+        let logger = slog_scope::logger();
+        let mut builder = Runtime::builder(&settings, &logger).dev_tools(true);
+        if let Some(ref url) = remote_entry {
+            builder = builder.remote_entry(url);
+        }
+        builder.build()?
+    };
 
-    let entry_file = args.value_of("input").unwrap();
-    runtime.eval_file_with_dev_tools(entry_file);
+    match remote_entry {
+        Some(url) => runtime.eval_module_with_dev_tools(url.as_str())?,
+        None => runtime.eval_file_with_dev_tools(entry),
+    }
     runtime.run().wait().unwrap();
 
     Ok(())
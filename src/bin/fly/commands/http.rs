@@ -11,6 +11,7 @@ use tokio::prelude::*;
 
 use fly::fixed_runtime_selector::FixedRuntimeSelector;
 use fly::http_server::serve_http;
+use fly::module_resolver::{remote_entry_url, BuildInfo};
 use fly::runtime::*;
 use fly::settings::SETTINGS;
 
@@ -21,7 +22,7 @@ pub fn cli() -> App {
         .about("Fly HTTP server")
         .arg(
             Arg::with_name("path")
-                .help("The app to run")
+                .help("The app to run, a path or an http(s) url")
                 .default_value("./index.{ts,js}")
                 .index(1),
         )
@@ -37,6 +38,40 @@ pub fn cli() -> App {
                 .long("bind")
                 .takes_value(true),
         )
+        .arg(
+            clap::Arg::with_name("entry")
+                .short("e")
+                .long("entry")
+                .help("Named entry point as name=path, loaded instead of the app path")
+                .takes_value(true)
+                .multiple(true),
+        )
+        .arg(
+            clap::Arg::with_name("profile-startup")
+                .long("profile-startup")
+                .help("Print module resolution and load times once the app is loaded")
+                .takes_value(true)
+                .min_values(0)
+                .possible_values(&["text", "json"]),
+        )
+        .arg(
+            clap::Arg::with_name("build-version")
+                .long("build-version")
+                .help("Version exported to the app by fly:build")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("git-sha")
+                .long("git-sha")
+                .help("Git sha exported to the app by fly:build")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("build-timestamp")
+                .long("build-timestamp")
+                .help("Build timestamp exported to the app by fly:build")
+                .takes_value(true),
+        )
         .arg(
             clap::Arg::with_name("lib")
                 .short("l")
@@ -50,16 +85,35 @@ pub fn cli() -> App {
 pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
     info!("V8 version: {}", libfly::version());
 
-    let mut runtime = Runtime::new(RuntimeConfig {
-        name: None,
-        version: None,
-        settings: &SETTINGS.read().unwrap(),
-        module_resolvers: None,
-        app_logger: &slog_scope::logger(),
-        msg_handler: None,
-        permissions: None,
-        dev_tools: true,
-    });
+    let entry_points = match args.values_of("entry") {
+        Some(values) => Some(parse_entry_points(values.collect())?),
+        None => None,
+    };
+    let has_entry_points = entry_points.is_some();
+    let remote_entry = match has_entry_points {
+        true => None,
+        false => remote_entry_url(args.value_of("path").unwrap()),
+    };
+    let build_info = BuildInfo {
+        version: args.value_of("build-version").map(|v| v.to_string()),
+        git_sha: args.value_of("git-sha").map(|v| v.to_string()),
+        build_timestamp: args.value_of("build-timestamp").map(|v| v.to_string()),
+    };
+
+    let mut runtime = {
+        let settings = SETTINGS.read().unwrap();
+        let logger = slog_scope::logger();
+        let mut builder = Runtime::builder(&settings, &logger)
+            .dev_tools(true)
+            .build_info(build_info);
+        if let Some(entry_points) = entry_points {
+            builder = builder.entry_points(entry_points);
+        }
+        if let Some(ref url) = remote_entry {
+            builder = builder.remote_entry(url);
+        }
+        builder.build()?
+    };
 
     if args.is_present("lib") {
         for lib_path in glob(args.values_of("lib").unwrap().collect(), None)? {
@@ -67,13 +121,29 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
         }
     }
 
-    if let Some(path) = glob(vec![args.value_of("path").unwrap()], Some(1))?.first() {
+    if has_entry_points {
+        if let Err(e) = runtime.warmup_entry_points() {
+            error!("{}", e);
+        }
+    } else if let Some(url) = remote_entry {
+        println!("Running app {}", url);
+        runtime.eval_module_with_dev_tools(url.as_str())?;
+    } else if let Some(path) = glob(vec![args.value_of("path").unwrap()], Some(1))?.first() {
         println!("Running app {}", path);
         runtime.eval_file_with_dev_tools(path);
     } else {
         return Err(FlyCliError::from("No source code found"));
     }
 
+    if args.is_present("profile-startup") {
+        if let Some(report) = runtime.module_resolver_manager.startup_report() {
+            match args.value_of("profile-startup") {
+                Some("json") => println!("{}", report.to_json()),
+                _ => println!("{}", report),
+            }
+        }
+    }
+
     let bind = match args.value_of("bind") {
         Some(b) => b,
         None => "127.0.0.1",
@@ -124,3 +194,51 @@ pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
 
     Ok(())
 }
+
+fn parse_entry_points(values: Vec<&str>) -> FlyCliResult<Vec<EntryPoint>> {
+    values
+        .iter()
+        .map(|v| {
+            let mut parts = v.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(specifier)) if !name.is_empty() && !specifier.is_empty() => {
+                    Ok(EntryPoint {
+                        name: name.to_string(),
+                        specifier: specifier.to_string(),
+                    })
+                }
+                _ => Err(FlyCliError::from(
+                    format!("Invalid entry point '{}', expected name=path", v).as_str(),
+                )),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_named_entry_points() {
+        let entries =
+            parse_entry_points(vec!["http=./http.ts", "cron=https://cdn.test/a=b.js"]).unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!("http", entries[0].name);
+        assert_eq!("./http.ts", entries[0].specifier);
+        // only the first `=` separates the name
+        assert_eq!("cron", entries[1].name);
+        assert_eq!("https://cdn.test/a=b.js", entries[1].specifier);
+    }
+
+    #[test]
+    fn test_refuses_entry_points_without_a_name_or_path() {
+        for value in &["./http.ts", "=./http.ts", "http="] {
+            let err = parse_entry_points(vec![*value]).err().unwrap();
+            assert_eq!(
+                format!("Invalid entry point '{}', expected name=path", value),
+                err.to_string()
+            );
+        }
+    }
+}
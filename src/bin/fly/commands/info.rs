@@ -0,0 +1,66 @@
+use crate::errors::*;
+use crate::util::*;
+use clap::{Arg, ArgMatches};
+use fly::module_resolver::{RemoteModuleCache, RemoteModuleCacheConfig};
+use fly::settings::SETTINGS;
+
+pub fn cli() -> App {
+    subcommand("info")
+        .about("Show runtime and environment information")
+        .arg(
+            Arg::with_name("module")
+                .long("module")
+                .value_name("URL")
+                .help("Show the HTTP response a cached remote module was fetched from")
+                .takes_value(true),
+        )
+}
+
+pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
+    let settings = SETTINGS.read().unwrap();
+
+    println!("fly {}", env!("CARGO_PKG_VERSION"));
+    println!("V8 {}", libfly::version());
+
+    match settings.module_base_urls {
+        Some(ref base_urls) if !base_urls.is_empty() => {
+            println!("Module base URLs:");
+            let mut base_urls: Vec<_> = base_urls.iter().collect();
+            base_urls.sort();
+            for (placeholder, base_url) in base_urls {
+                println!("  {} -> {}", placeholder, base_url);
+            }
+        }
+        _ => println!("Module base URLs: none"),
+    }
+
+    if let Some(url) = args.value_of("module") {
+        let config = match settings.module_disk_cache {
+            Some(ref config) => config,
+            None => return Err(FlyCliError::from("no module_disk_cache is configured")),
+        };
+        let cache = RemoteModuleCache::open(
+            config.dir.as_str(),
+            RemoteModuleCacheConfig {
+                gc_max_idle_secs: None,
+                ..config.cache.clone()
+            },
+        )
+        .map_err(|e| FlyCliError::from(format!("{}", e).as_str()))?;
+        let responses = cache.responses(url);
+        if responses.is_empty() {
+            println!("{}: no response metadata cached", url);
+        }
+        for (tenant, response) in responses {
+            println!("{} (tenant {}):", url, tenant);
+            println!("  final url: {}", response.final_url);
+            println!("  status: {}", response.status);
+            println!("  fetched in: {}ms", response.fetch_ms);
+            for (name, value) in &response.headers {
+                println!("  {}: {}", name, value);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,14 +1,27 @@
 use crate::util::*;
 
 pub fn commands() -> Vec<App> {
-  vec![http::cli(), test::cli(), dns::cli(), eval::cli()]
+  vec![
+    http::cli(),
+    cache::cli(),
+    test::cli(),
+    dns::cli(),
+    doctor::cli(),
+    eval::cli(),
+    info::cli(),
+    resolve::cli(),
+  ]
 }
 
 pub fn command_exec(name: &str) -> Option<ExecFn> {
   let exec = match name {
+    "cache" => cache::exec,
     "dns" => dns::exec,
+    "doctor" => doctor::exec,
     "eval" => eval::exec,
     "http" => http::exec,
+    "info" => info::exec,
+    "resolve" => resolve::exec,
     "test" => test::exec,
     _ => return None,
   };
@@ -16,7 +29,11 @@ pub fn command_exec(name: &str) -> Option<ExecFn> {
   Some(exec)
 }
 
+pub mod cache;
 pub mod dns;
+pub mod doctor;
 pub mod eval;
 pub mod http;
+pub mod info;
+pub mod resolve;
 pub mod test;
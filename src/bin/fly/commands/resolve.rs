@@ -0,0 +1,46 @@
+use crate::errors::*;
+use crate::util::*;
+use clap::{Arg, ArgMatches};
+use fly::module_resolver::debug_resolve;
+use fly::runtime::Runtime;
+use fly::settings::SETTINGS;
+
+pub fn cli() -> App {
+    subcommand("resolve")
+        .about("Explain how an import resolves, without running anything")
+        .arg(
+            Arg::with_name("specifier")
+                .help("The specifier to resolve, as it would be imported")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("referer")
+                .long("referer")
+                .value_name("URL")
+                .help("The url of the module importing it; the working directory by default")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the report as JSON"),
+        )
+}
+
+pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
+    let runtime = Runtime::builder(&SETTINGS.read().unwrap(), &slog_scope::logger()).build()?;
+
+    let report = debug_resolve(
+        &*runtime.module_resolver_manager,
+        args.value_of("specifier").unwrap(),
+        args.value_of("referer"),
+    );
+    if args.is_present("json") {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use crate::errors::*;
 use crate::util::*;
 use clap::{Arg, ArgMatches};
-use fly::runtime::{Runtime, RuntimeConfig};
+use fly::runtime::Runtime;
 use fly::runtime_permissions::RuntimePermissions;
 use fly::settings::SETTINGS;
 use futures::Future;
@@ -29,16 +29,10 @@ pub fn cli() -> App {
 }
 
 pub fn exec(args: &ArgMatches<'_>) -> FlyCliResult<()> {
-    let mut rt = Runtime::new(RuntimeConfig {
-        name: None,
-        version: None,
-        settings: &SETTINGS.read().unwrap(),
-        module_resolvers: None,
-        app_logger: &slog_scope::logger(),
-        msg_handler: None,
-        permissions: Some(RuntimePermissions::new(true)),
-        dev_tools: true,
-    });
+    let mut rt = Runtime::builder(&SETTINGS.read().unwrap(), &slog_scope::logger())
+        .permissions(RuntimePermissions::new(true))
+        .dev_tools(true)
+        .build()?;
 
     if args.is_present("lib") {
         for lib_path in glob(args.values_of("lib").unwrap().collect(), None)? {
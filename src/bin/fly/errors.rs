@@ -79,3 +79,12 @@ impl From<()> for FlyCliError {
     }
   }
 }
+
+impl From<fly::errors::FlyError> for FlyCliError {
+  #[inline]
+  fn from(err: fly::errors::FlyError) -> FlyCliError {
+    FlyCliError {
+      repr: Repr::Simple(err.to_string()),
+    }
+  }
+}
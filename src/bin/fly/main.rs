@@ -13,6 +13,8 @@ fn main() -> FlyCliResult<()> {
   let logger = build_logger();
   let _guard = slog_scope::set_global_logger(logger);
   slog_stdlog::init().unwrap();
+  discover_node_modules();
+  allow_local_imports_outside_root();
 
   let args = cli().get_matches();
   let (cmd, subcommand_args) = args.subcommand();
@@ -33,6 +35,25 @@ fn cli() -> App {
     .subcommands(commands::commands())
 }
 
+/// Lets bare imports work from inside an npm/pnpm project without configuring its root.
+fn discover_node_modules() {
+  let mut settings = fly::settings::SETTINGS.write().unwrap();
+  if settings.module_node_modules_root.is_none() {
+    settings.module_node_modules_root = std::env::current_dir()
+      .ok()
+      .and_then(|dir| fly::module_resolver::discover_node_modules_root(&dir))
+      .map(|root| root.display().to_string());
+  }
+}
+
+/// The CLI runs whatever the user points it at, so `fly run ../app/index.js` should work.
+fn allow_local_imports_outside_root() {
+  let mut settings = fly::settings::SETTINGS.write().unwrap();
+  if settings.module_allow_outside_root.is_none() {
+    settings.module_allow_outside_root = Some(true);
+  }
+}
+
 fn build_logger() -> slog::Logger {
   fly::logging::build_routing_logger(
     slog_term::term_full()
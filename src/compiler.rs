@@ -75,7 +75,7 @@ mod tests {
       ),
     ];
     let current_dir = std::env::current_dir().expect("current_dir failed");
-    let local_disk_resolver = LocalDiskModuleResolver::new(None);
+    let local_disk_resolver = LocalDiskModuleResolver::new(None).unwrap();
     let resolvers = vec![Box::new(local_disk_resolver) as Box<ModuleResolver>];
     let compiler = Compiler::new(resolvers);
 
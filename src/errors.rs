@@ -1,3 +1,4 @@
+pub use crate::module_resolver::ModuleResolutionError;
 pub use crate::msg::ErrorKind;
 use hyper;
 use std;
@@ -18,6 +19,7 @@ enum Repr {
   IoErr(io::Error),
   UrlErr(url::ParseError),
   HyperErr(hyper::Error),
+  Resolution(Box<ModuleResolutionError>),
 }
 
 pub fn new(kind: ErrorKind, msg: String) -> FlyError {
@@ -27,6 +29,14 @@ pub fn new(kind: ErrorKind, msg: String) -> FlyError {
 }
 
 impl FlyError {
+  /// Why a module couldn't be resolved or loaded, when that's what this error is.
+  pub fn resolution_error(&self) -> Option<&ModuleResolutionError> {
+    match self.repr {
+      Repr::Resolution(ref err) => Some(err),
+      _ => None,
+    }
+  }
+
   pub fn kind(&self) -> ErrorKind {
     match self.repr {
       Repr::Simple(kind, ref _msg) => kind,
@@ -51,7 +61,9 @@ impl FlyError {
           WriteZero => ErrorKind::WriteZero,
           Other => ErrorKind::Other,
           UnexpectedEof => ErrorKind::UnexpectedEof,
-          _ => unreachable!(),
+          IsADirectory => ErrorKind::IsADirectory,
+          // kinds std added since, which have no counterpart of their own
+          _ => ErrorKind::Other,
         }
       }
       Repr::UrlErr(ref err) => {
@@ -83,6 +95,23 @@ impl FlyError {
           ErrorKind::HttpOther
         }
       }
+      Repr::Resolution(ref err) => err.kind(),
+    }
+  }
+}
+
+/// io and hyper errors can't be cloned, so their clones are errors of the same kind and
+/// message.
+impl Clone for FlyError {
+  fn clone(&self) -> FlyError {
+    match self.repr {
+      Repr::Simple(kind, ref msg) => new(kind, msg.clone()),
+      Repr::IoErr(ref err) => io::Error::new(err.kind(), err.to_string()).into(),
+      Repr::UrlErr(err) => err.into(),
+      Repr::HyperErr(ref err) => new(self.kind(), err.to_string()),
+      Repr::Resolution(ref err) => FlyError {
+        repr: Repr::Resolution(err.clone()),
+      },
     }
   }
 }
@@ -93,6 +122,7 @@ impl fmt::Display for FlyError {
       Repr::IoErr(ref err) => err.fmt(f),
       Repr::UrlErr(ref err) => err.fmt(f),
       Repr::HyperErr(ref err) => err.fmt(f),
+      Repr::Resolution(ref err) => err.fmt(f),
       Repr::Simple(_kind, ref s) => write!(f, "{}", s),
     }
   }
@@ -104,6 +134,7 @@ impl std::error::Error for FlyError {
       Repr::IoErr(ref err) => err.description(),
       Repr::UrlErr(ref err) => err.description(),
       Repr::HyperErr(ref err) => err.description(),
+      Repr::Resolution(ref err) => err.description(),
       Repr::Simple(_kind, ref s) => s.as_str(),
     }
   }
@@ -113,6 +144,7 @@ impl std::error::Error for FlyError {
       Repr::IoErr(ref err) => Some(err),
       Repr::UrlErr(ref err) => Some(err),
       Repr::HyperErr(ref err) => Some(err),
+      Repr::Resolution(ref err) => Some(&**err),
       Repr::Simple(_, _) => None,
     }
   }
@@ -145,6 +177,15 @@ impl From<hyper::Error> for FlyError {
   }
 }
 
+impl From<ModuleResolutionError> for FlyError {
+  #[inline]
+  fn from(err: ModuleResolutionError) -> FlyError {
+    FlyError {
+      repr: Repr::Resolution(Box::new(err)),
+    }
+  }
+}
+
 impl From<String> for FlyError {
   #[inline]
   fn from(err: String) -> FlyError {
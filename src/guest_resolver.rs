@@ -0,0 +1,491 @@
+use crate::errors::*;
+use crate::module_resolver::{
+    LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+use crate::msg;
+use crate::runtime::JsRuntime;
+use crate::utils::*;
+
+use flatbuffers::FlatBufferBuilder;
+use libfly::js_runtime_run_micro_tasks;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+/**
+ * Whether guests may register their own module resolvers, and how long and how deeply
+ * their callbacks may run. Off unless a host turns them on.
+ */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct GuestResolversConfig {
+    pub enabled: bool,
+    pub timeout_ms: u64,
+    /** Guest resolutions that may be in progress inside one another's callbacks. */
+    pub max_depth: usize,
+}
+
+impl Default for GuestResolversConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: 5000,
+            max_depth: 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GuestResolution {
+    /** A module's source, and the url it's known by if not the one that was requested. */
+    Source {
+        source: String,
+        origin_url: Option<String>,
+    },
+    /** Resolve this specifier instead, e.g. a url the guest's manifest points to. */
+    Redirect(String),
+    Rejected(String),
+}
+
+impl GuestResolution {
+    pub fn from_result(msg: &msg::GuestResolveResult) -> Self {
+        if let Some(source) = msg.source() {
+            GuestResolution::Source {
+                source: source.to_string(),
+                origin_url: msg.origin_url().map(|url| url.to_string()),
+            }
+        } else if let Some(redirect) = msg.redirect() {
+            GuestResolution::Redirect(redirect.to_string())
+        } else {
+            GuestResolution::Rejected(msg.error().unwrap_or("rejected").to_string())
+        }
+    }
+}
+
+/**
+ * A runtime's in-flight guest resolutions. Requests go to the guest as `GuestResolve`
+ * events and are answered with `GuestResolveResult` ops, which land here by id.
+ */
+pub struct GuestResolvers {
+    config: GuestResolversConfig,
+    next_id: AtomicUsize,
+    depth: AtomicUsize,
+    pending: Mutex<HashMap<u32, Sender<GuestResolution>>>,
+}
+
+/**
+ * Counts one level of nesting for as long as a resolution is in progress.
+ */
+struct DepthGuard<'a>(&'a AtomicUsize);
+
+impl<'a> Drop for DepthGuard<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl GuestResolvers {
+    pub fn new(config: GuestResolversConfig) -> Self {
+        Self {
+            config,
+            next_id: AtomicUsize::new(1),
+            depth: AtomicUsize::new(0),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /**
+     * Hands `resolution` to whoever is waiting on `id`. An answer no one is waiting on any
+     * more is dropped.
+     */
+    pub fn complete(&self, id: u32, resolution: GuestResolution) {
+        if let Some(waiting) = self.pending.lock().unwrap().remove(&id) {
+            let _ = waiting.send(resolution);
+        }
+    }
+
+    /** Where the answer to `id` will arrive, once `complete` is called with it. */
+    fn expect(&self, id: u32) -> Receiver<GuestResolution> {
+        let (tx, rx) = channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        rx
+    }
+
+    fn enter(&self) -> FlyResult<DepthGuard> {
+        let depth = self.depth.fetch_add(1, Ordering::SeqCst);
+        let guard = DepthGuard(&self.depth);
+        if depth >= self.config.max_depth {
+            return Err(FlyError::from(format!(
+                "guest module resolvers nested more than {} deep",
+                self.config.max_depth
+            )));
+        }
+        Ok(guard)
+    }
+
+    /**
+     * Waits for the answer to `id` to arrive on `answer`, after running `pump` once to
+     * settle the callback's microtasks. The runtime's thread is ours until this returns, so
+     * only callbacks that settle through microtasks can finish; one waiting on anything else
+     * runs out the timeout, blocked rather than polling.
+     */
+    fn wait_for<F: FnOnce()>(
+        &self,
+        id: u32,
+        answer: Receiver<GuestResolution>,
+        pump: F,
+    ) -> FlyResult<GuestResolution> {
+        pump();
+        let result = match answer.recv_timeout(Duration::from_millis(self.config.timeout_ms)) {
+            Ok(resolution) => Ok(resolution),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => Err(new(
+                ErrorKind::TimedOut,
+                format!(
+                    "guest module resolver didn't answer within {}ms",
+                    self.config.timeout_ms
+                ),
+            )),
+        };
+        // an answer arriving after the timeout has no one left to read it
+        self.pending.lock().unwrap().remove(&id);
+        result
+    }
+
+    fn request(
+        &self,
+        rt: JsRuntime,
+        protocol: &str,
+        specifier: &str,
+        referer: &str,
+    ) -> FlyResult<GuestResolution> {
+        let _depth = self.enter()?;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) as u32;
+        // the callback may answer before `send` returns
+        let answer = self.expect(id);
+
+        let builder = &mut FlatBufferBuilder::new();
+        let protocol = builder.create_string(protocol);
+        let specifier = builder.create_string(specifier);
+        let referer = builder.create_string(referer);
+        let msg = msg::GuestResolve::create(
+            builder,
+            &msg::GuestResolveArgs {
+                id,
+                protocol: Some(protocol),
+                specifier: Some(specifier),
+                referer: Some(referer),
+            },
+        );
+        let buf = serialize_response(
+            0,
+            builder,
+            msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::GuestResolve,
+                ..Default::default()
+            },
+        );
+        // runs the guest's callback right here, on this thread
+        rt.send(fly_buf_from(buf.unwrap()), None);
+
+        self.wait_for(id, answer, || unsafe {
+            js_runtime_run_micro_tasks(rt.0);
+        })
+    }
+}
+
+/**
+ * Serves one protocol from a guest's callback. Registered after the host's resolvers, so it
+ * never shadows them. Has to be called on the runtime's own thread: the callback runs
+ * synchronously inside whichever op is resolving, which is also what lets it import modules
+ * itself.
+ *
+ * Such an import re-enters the isolate from inside the callback: its `LoadModule` op
+ * resolves, maybe back through this resolver, sends another `GuestResolve` and waits on its
+ * answer, all before the outer callback returns. Nothing is locked across a callback, so the
+ * inner resolution finishes first and the outer one picks its answer up afterwards; how
+ * deep this may go is `GuestResolversConfig::max_depth`.
+ */
+pub struct GuestModuleResolver {
+    protocol: String,
+    rt: JsRuntime,
+    thread: ThreadId,
+    resolvers: Arc<GuestResolvers>,
+}
+
+impl GuestModuleResolver {
+    pub fn new(protocol: &str, rt: JsRuntime, resolvers: Arc<GuestResolvers>) -> Self {
+        Self {
+            protocol: protocol.to_string(),
+            rt,
+            thread: thread::current().id(),
+            resolvers,
+        }
+    }
+
+    /**
+     * The origin url a callback gave the module it served for `url`, resolved against
+     * `url`. It has to stay in the guest's protocol: an `https://` one would let the guest
+     * pass its code off as a module the host loaded, pins and caches included.
+     */
+    fn check_origin_url(&self, url: &url::Url, origin_url: &str) -> FlyResult<String> {
+        let origin_url = url.join(origin_url)?;
+        if origin_url.scheme() != self.protocol {
+            return Err(new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "guest resolver for {} answered {} with {}, which isn't a {} url",
+                    self.protocol, url, origin_url, self.protocol
+                ),
+            ));
+        }
+        Ok(origin_url.into_string())
+    }
+}
+
+impl ModuleResolver for GuestModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        if thread::current().id() != self.thread {
+            return Err(FlyError::from(format!(
+                "guest resolver for {} can only resolve on its runtime's thread",
+                self.protocol
+            )));
+        }
+        let referer = referer_info.as_ref().map(|r| r.origin_url.clone());
+        let url = match url::Url::parse(module_specifier) {
+            Ok(url) => url,
+            Err(url::ParseError::RelativeUrlWithoutBase) if referer.is_some() => {
+                url::Url::parse(referer.as_ref().unwrap())?.join(module_specifier)?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if url.scheme() != self.protocol {
+            return Err(FlyError::from(format!(
+                "Could not resolve {}: not a {} url",
+                module_specifier, self.protocol
+            )));
+        }
+        let resolution = self.resolvers.request(
+            self.rt,
+            &self.protocol,
+            url.as_str(),
+            referer.as_ref().map(|r| r.as_str()).unwrap_or(""),
+        )?;
+        match resolution {
+            GuestResolution::Source { source, origin_url } => Ok(ModuleSourceData {
+                origin_url: match origin_url {
+                    Some(origin_url) => self.check_origin_url(&url, &origin_url)?,
+                    None => url.into_string(),
+                },
+                source_loader: Box::new(GuestSourceLoader {
+                    source: LoadedSourceCode {
+                        kind: ModuleKind::JavaScript,
+                        source_map: None,
+                        source: source.into(),
+                        response: None,
+                        provenance: None,
+                        code_cache: None,
+                        compiled_wasm: None,
+                        wasm: None,
+                    },
+                }),
+            }),
+            GuestResolution::Redirect(specifier) => {
+                // back through the whole stack, which can land on this resolver again; the
+                // depth limit is what stops a redirect loop
+                let _depth = self.resolvers.enter()?;
+                let module = self
+                    .rt
+                    .to_runtime()
+                    .module_resolver_manager
+                    .resolve_blocking(specifier, referer_info)?;
+                Ok(ModuleSourceData {
+                    origin_url: module.origin_url,
+                    source_loader: Box::new(GuestSourceLoader {
+                        source: module.loaded_source,
+                    }),
+                })
+            }
+            GuestResolution::Rejected(reason) => Err(new(
+                ErrorKind::NotFound,
+                format!("guest resolver rejected {}: {}", url, reason),
+            )),
+        }
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn name(&self) -> String {
+        format!("GuestModuleResolver({})", self.protocol)
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("guest", &self.protocol).named(&self.name())
+    }
+    fn inheritable(&self) -> bool {
+        false
+    }
+    fn needs_runtime_thread(&self) -> bool {
+        true
+    }
+}
+
+struct GuestSourceLoader {
+    source: LoadedSourceCode,
+}
+
+impl SourceLoader for GuestSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        Ok(self.source.clone())
+    }
+}
+
+/**
+ * Protocols a guest may claim: a plain scheme, and none the host serves itself.
+ */
+pub fn check_guest_protocol(protocol: &str) -> FlyResult<()> {
+    let valid = protocol
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_lowercase())
+        && protocol
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c));
+    if !valid {
+        return Err(new(
+            ErrorKind::InvalidInput,
+            format!(
+                "'{}' is not a valid protocol for a module resolver",
+                protocol
+            ),
+        ));
+    }
+    match protocol {
+        "file" | "http" | "https" | "fly" | "data" => Err(permission_denied()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waits_for_the_answer_and_times_out_otherwise() {
+        let resolvers = Arc::new(GuestResolvers::new(GuestResolversConfig {
+            timeout_ms: 1000,
+            ..Default::default()
+        }));
+        let resolution = resolvers
+            .wait_for(7, resolvers.expect(7), || {
+                resolvers.complete(7, GuestResolution::Redirect("https://x/y.js".into()));
+            })
+            .unwrap();
+        assert_eq!(
+            GuestResolution::Redirect("https://x/y.js".into()),
+            resolution
+        );
+
+        // answered from elsewhere while waiting
+        let answer = resolvers.expect(8);
+        let answering = resolvers.clone();
+        let answerer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            answering.complete(8, GuestResolution::Rejected("late".into()));
+        });
+        let resolution = resolvers.wait_for(8, answer, || {}).unwrap();
+        assert_eq!(GuestResolution::Rejected("late".into()), resolution);
+        answerer.join().unwrap();
+
+        let resolvers = GuestResolvers::new(GuestResolversConfig {
+            timeout_ms: 20,
+            ..Default::default()
+        });
+        let err = resolvers
+            .wait_for(9, resolvers.expect(9), || {})
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::TimedOut, err.kind());
+        // nothing is left waiting for its answer
+        resolvers.complete(9, GuestResolution::Rejected("too late".into()));
+        assert!(resolvers.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_guest_resolvers_are_off_by_default() {
+        assert!(!GuestResolvers::new(GuestResolversConfig::default()).enabled());
+    }
+
+    #[test]
+    fn test_callbacks_can_wait_on_their_own_imports() {
+        // what a callback importing a module of its own protocol does: the inner resolution
+        // is asked for and answered while the outer one is still waiting
+        let resolvers = GuestResolvers::new(GuestResolversConfig {
+            timeout_ms: 1000,
+            ..Default::default()
+        });
+        let _outer = resolvers.enter().unwrap();
+        let mut inner = None;
+        let outer = resolvers
+            .wait_for(1, resolvers.expect(1), || {
+                let _depth = resolvers.enter().unwrap();
+                let answer = resolvers.expect(2);
+                resolvers.complete(2, GuestResolution::Rejected("inner".into()));
+                inner = Some(resolvers.wait_for(2, answer, || {}).unwrap());
+                resolvers.complete(
+                    1,
+                    GuestResolution::Source {
+                        source: "export default 1;".into(),
+                        origin_url: Some("cdn://app/a.js".into()),
+                    },
+                );
+            })
+            .unwrap();
+        assert_eq!(Some(GuestResolution::Rejected("inner".into())), inner);
+        assert_eq!(
+            GuestResolution::Source {
+                source: "export default 1;".into(),
+                origin_url: Some("cdn://app/a.js".into()),
+            },
+            outer
+        );
+    }
+
+    #[test]
+    fn test_nesting_is_bounded() {
+        let resolvers = GuestResolvers::new(GuestResolversConfig {
+            max_depth: 2,
+            ..Default::default()
+        });
+        {
+            let _outer = resolvers.enter().unwrap();
+            let _inner = resolvers.enter().unwrap();
+            assert!(resolvers.enter().is_err());
+        }
+        assert!(resolvers.enter().is_ok());
+    }
+
+    #[test]
+    fn test_guests_cant_claim_host_protocols() {
+        assert!(check_guest_protocol("manifest").is_ok());
+        assert!(check_guest_protocol("app+v2").is_ok());
+        assert_eq!(
+            ErrorKind::PermissionDenied,
+            check_guest_protocol("https").err().unwrap().kind()
+        );
+        assert!(check_guest_protocol("Bad:").is_err());
+        assert!(check_guest_protocol("").is_err());
+    }
+}
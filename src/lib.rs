@@ -51,6 +51,8 @@ pub use crate::runtime_selector::{RuntimeSelector, SelectorError};
 
 pub mod dns_server;
 pub mod fixed_runtime_selector;
+pub mod guest_resolver;
+pub mod module_chunks;
 pub mod http_server;
 
 pub mod metrics;
@@ -59,6 +61,8 @@ pub mod module_resolver;
 pub use crate::module_resolver::{JsonSecretsResolver, LocalDiskModuleResolver, ModuleResolver};
 
 pub mod msg_handler;
+pub mod op_limits;
+pub mod op_metrics;
 
 mod disk_fs;
 mod postgres_data;
@@ -72,3 +76,6 @@ mod sqlite_data;
 mod redis_pool;
 
 pub mod logging;
+
+#[cfg(test)]
+mod alloc_counter;
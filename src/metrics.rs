@@ -2,7 +2,9 @@ use futures::{future, Canceled, Future};
 use http::header;
 use hyper::{Body, Request, Response, StatusCode};
 
-use prometheus::{Encoder, HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder};
+use prometheus::{
+    Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
 
 pub fn serve_metrics_http(
     _req: Request<Body>,
@@ -174,4 +176,66 @@ lazy_static! {
         vec![0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 1.0, 5.0, 10.0]
     )
     .unwrap();
+    pub static ref MODULE_SOURCE_CACHE_LOOKUPS: IntCounterVec = register_int_counter_vec!(
+        "fly_module_source_cache_lookups_total",
+        "Shared module source cache lookups, by result (hit, cross_runtime_hit, miss).",
+        &["result"]
+    )
+    .unwrap();
+    pub static ref MODULE_SOURCE_CACHE_EVICTIONS: IntCounter = register_int_counter!(
+        "fly_module_source_cache_evictions_total",
+        "Modules evicted from the shared module source cache to stay under its size limit."
+    )
+    .unwrap();
+    pub static ref MODULE_SOURCE_CACHE_BYTES: IntGauge = register_int_gauge!(
+        "fly_module_source_cache_size_bytes",
+        "Bytes of module source held in shared module source caches."
+    )
+    .unwrap();
+    pub static ref MODULE_SOURCE_CACHE_COMPRESSION_SAVED_BYTES: IntGauge = register_int_gauge!(
+        "fly_module_source_cache_compression_saved_bytes",
+        "Bytes saved by keeping large sources compressed in shared module source caches."
+    )
+    .unwrap();
+    pub static ref MODULE_SOURCES_RELEASED_BYTES: IntCounter = register_int_counter!(
+        "fly_module_sources_released_bytes_total",
+        "Bytes of module source dropped from runtimes after compiling."
+    )
+    .unwrap();
+    pub static ref MODULE_SOURCE_RELOADS: IntCounter = register_int_counter!(
+        "fly_module_source_reloads_total",
+        "Released module sources loaded again because something needed them."
+    )
+    .unwrap();
+    pub static ref MODULE_DISK_CACHE_EVICTIONS: IntCounterVec = register_int_counter_vec!(
+        "fly_module_disk_cache_evictions_total",
+        "Remote modules evicted from the disk cache to keep a tenant under its quota, by tenant.",
+        &["tenant"]
+    )
+    .unwrap();
+    pub static ref MODULE_DISK_CACHE_STALE_SERVED: IntCounterVec = register_int_counter_vec!(
+        "fly_module_disk_cache_stale_served_total",
+        "Expired remote modules served from the disk cache because refreshing them failed, by tenant.",
+        &["tenant"]
+    )
+    .unwrap();
+    pub static ref MODULE_FETCH_BLOCKED: IntCounter = register_int_counter!(
+        "fly_module_fetch_blocked_total",
+        "Module fetches refused because the host resolved to a blocked address."
+    )
+    .unwrap();
+    pub static ref MODULE_LOAD_QUEUE_WAIT_HISTOGRAM: HistogramVec = register_histogram_vec!(
+        "fly_module_load_queue_wait_histogram_seconds",
+        "Time module loads waited for a load slot, by tenant, in seconds.",
+        &["tenant"],
+        vec![0.0001, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0]
+    )
+    .unwrap();
+    pub static ref MODULE_RESOLUTION_TIME_HISTOGRAM: HistogramVec = register_histogram_vec!(
+        "fly_module_resolution_time_histogram_seconds",
+        "Time each resolver took to resolve a module, by protocol, resolver and outcome (resolved, failed, cache_hit), in seconds.",
+        &["protocol", "resolver", "outcome"],
+        vec![0.0001, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0]
+    )
+    .unwrap();
 }
@@ -0,0 +1,175 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+
+/**
+ * When `op_load_module` splits a module's source over several messages, so a big generated
+ * bundle never has to be copied into one buffer on its way to V8.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModuleChunking {
+    /** Sources of up to this many bytes go in a single `LoadModuleResp`. */
+    pub threshold_bytes: usize,
+    /** How many bytes each `LoadModuleChunkResp` carries, the last one excepted. */
+    pub chunk_bytes: usize,
+}
+
+impl Default for ModuleChunking {
+    fn default() -> Self {
+        ModuleChunking {
+            threshold_bytes: 4 * 1024 * 1024,
+            chunk_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl ModuleChunking {
+    pub fn validate(&self) -> FlyResult<()> {
+        if self.chunk_bytes == 0 {
+            return Err(FlyError::from(
+                "module chunks must be at least one byte".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /** Whether a source of `size` bytes is sent in chunks. */
+    pub fn chunks(&self, size: usize) -> bool {
+        size > self.threshold_bytes
+    }
+}
+
+/** The bytes a chunked module is sent as. */
+pub enum ChunkedSource {
+    /** JavaScript, sent as its UTF-8, shared with whoever else holds the source. */
+    Text(Arc<str>),
+    /** A WebAssembly module's binary, shared with the module it came from. */
+    Binary(Bytes),
+}
+
+impl ChunkedSource {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            ChunkedSource::Text(text) => text.as_bytes(),
+            ChunkedSource::Binary(bytes) => bytes,
+        }
+    }
+}
+
+/**
+ * Sources waiting to be fetched chunk by chunk, by transfer id. A source is dropped once its
+ * last chunk was fetched.
+ */
+pub struct ModuleChunks {
+    chunking: ModuleChunking,
+    next_id: AtomicUsize,
+    pending: Mutex<HashMap<u32, ChunkedSource>>,
+}
+
+impl ModuleChunks {
+    pub fn new(chunking: ModuleChunking) -> Self {
+        ModuleChunks {
+            chunking,
+            next_id: AtomicUsize::new(1),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn chunking(&self) -> ModuleChunking {
+        self.chunking
+    }
+
+    /**
+     * Holds on to `source` until it's fetched; returns its transfer id and how many chunks
+     * it's split into.
+     */
+    pub fn start(&self, source: ChunkedSource) -> (u32, u32) {
+        let chunks =
+            (source.as_bytes().len() + self.chunking.chunk_bytes - 1) / self.chunking.chunk_bytes;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) as u32;
+        self.pending.lock().unwrap().insert(id, source);
+        (id, chunks as u32)
+    }
+
+    /**
+     * A copy of the `index`th chunk of transfer `id`. Chunks can be fetched in any order and
+     * more than once, until the last one was.
+     */
+    pub fn chunk(&self, id: u32, index: u32) -> FlyResult<Vec<u8>> {
+        let mut pending = self.pending.lock().unwrap();
+        let (chunk, last) = {
+            let source = pending.get(&id).ok_or_else(|| {
+                new(
+                    ErrorKind::NotFound,
+                    format!("no module transfer {} is pending", id),
+                )
+            })?;
+            let bytes = source.as_bytes();
+            let start = index as usize * self.chunking.chunk_bytes;
+            if start >= bytes.len() {
+                return Err(new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "module transfer {} has no chunk {}, it's {} bytes",
+                        id,
+                        index,
+                        bytes.len()
+                    ),
+                ));
+            }
+            let end = (start + self.chunking.chunk_bytes).min(bytes.len());
+            (bytes[start..end].to_vec(), end == bytes.len())
+        };
+        if last {
+            pending.remove(&id);
+        }
+        Ok(chunk)
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_reassemble_byte_for_byte() {
+        let chunks = ModuleChunks::new(ModuleChunking {
+            threshold_bytes: 16,
+            chunk_bytes: 1000,
+        });
+        // multi-byte characters straddle chunk boundaries
+        let source: String = (0..3000).map(|i| format!("/*é{}*/", i)).collect();
+        let (id, count) = chunks.start(ChunkedSource::Text(source.clone().into()));
+        assert_eq!((source.len() + 999) / 1000, count as usize);
+        let mut bytes = vec![];
+        for index in 0..count {
+            bytes.extend(chunks.chunk(id, index).unwrap());
+        }
+        assert_eq!(source.as_bytes(), &bytes[..]);
+        assert_eq!(0, chunks.pending());
+        assert_eq!(
+            ErrorKind::NotFound,
+            chunks.chunk(id, 0).err().unwrap().kind()
+        );
+
+        let (id, _) = chunks.start(ChunkedSource::Binary(vec![0; 10].into()));
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            chunks.chunk(id, 1).err().unwrap().kind()
+        );
+        assert!(ModuleChunking {
+            threshold_bytes: 0,
+            chunk_bytes: 0
+        }
+        .validate()
+        .is_err());
+    }
+}
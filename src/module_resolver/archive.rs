@@ -0,0 +1,848 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{
+    parse_url, typescript_flavor, DataFormats, LoadedSourceCode, ModuleKind, ModuleResolver,
+    ModuleSourceData, RefererInfo, ResolverDescription, SourceLoader,
+};
+
+const TAR_BLOCK: usize = 512;
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/**
+ * Suffixes tried after the exact path, in order, like the release bundle resolver.
+ */
+pub(super) const PROBE_SUFFIXES: &[&str] = &["", ".ts", ".js", "/index.ts", "/index.js"];
+
+/**
+ * Bounds on what an archive may unpack to, checked against the sizes its index declares
+ * when it's opened and against the bytes actually inflated when an entry is read.
+ */
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ArchiveLimits {
+    pub max_entries: usize,
+    pub max_entry_bytes: u64,
+    pub max_total_bytes: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_entry_bytes: 32 * 1024 * 1024,
+            max_total_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Method {
+    Stored,
+    Deflated,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    // where the entry's data starts; for zip, where its local header does
+    offset: usize,
+    stored_size: u64,
+    declared_size: u64,
+    method: Method,
+    zip: bool,
+}
+
+/**
+ * A tar, gzip'd tar or zip (stored or deflated) archive of app files, held in memory. A
+ * gzip'd tar is inflated once, when it's opened. Entry names must
+ * be relative and stay inside the archive: absolute paths, `..`, backslashes and drive
+ * letters fail opening, as do more entries or bigger declared sizes than `ArchiveLimits`
+ * allow. An entry inflating to more (or fewer) bytes than declared fails when it's read.
+ *
+ * When a name appears more than once the last entry wins, as it does when tar extracts or
+ * updates an archive.
+ */
+pub struct ArchiveBundle {
+    name: String,
+    bytes: Vec<u8>,
+    entries: HashMap<String, Entry>,
+}
+
+fn archive_error(archive: &str, msg: impl std::fmt::Display) -> FlyError {
+    FlyError::from(format!("archive {}: {}", archive, msg))
+}
+
+/**
+ * `name` as a relative path inside the archive, or why it isn't one.
+ */
+pub(super) fn normalize_entry_name(name: &str) -> Result<String, &'static str> {
+    if name.starts_with('/') {
+        return Err("is an absolute path");
+    }
+    if name.contains('\\') {
+        return Err("uses backslashes, which could hide .. or an absolute path");
+    }
+    if name.len() >= 2 && name.as_bytes()[1] == b':' {
+        return Err("has a drive letter");
+    }
+    let mut parts = vec![];
+    for part in name.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return Err("leaves the archive through .."),
+            part => parts.push(part),
+        }
+    }
+    if parts.is_empty() {
+        return Err("is empty");
+    }
+    Ok(parts.join("/"))
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    bytes
+        .get(at..at + 2)
+        .map(|b| u16::from(b[0]) | u16::from(b[1]) << 8)
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    bytes.get(at..at + 4).map(|b| {
+        u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+    })
+}
+
+fn tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn tar_size(field: &[u8]) -> Option<u64> {
+    let digits = tar_string(field);
+    let digits = digits.trim_matches(|c| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+/**
+ * The `path` record of a pax extended header, which overrides the next entry's name.
+ */
+fn pax_path(records: &[u8]) -> Option<String> {
+    let records = String::from_utf8_lossy(records);
+    let mut rest: &str = &records;
+    while !rest.is_empty() {
+        let space = rest.find(' ')?;
+        let len: usize = rest[..space].parse().ok()?;
+        let record = rest.get(space + 1..len)?.trim_end_matches('\n');
+        if record.starts_with("path=") {
+            return Some(record["path=".len()..].to_string());
+        }
+        rest = rest.get(len..)?;
+    }
+    None
+}
+
+impl ArchiveBundle {
+    pub fn open(path: &Path, limits: &ArchiveLimits) -> FlyResult<Self> {
+        let bytes =
+            std::fs::read(path).map_err(|e| archive_error(&path.display().to_string(), e))?;
+        Self::from_bytes(&path.display().to_string(), bytes, limits)
+    }
+
+    /**
+     * Reads the index of `bytes`, a tar, gzip'd tar or zip archive identified as `name` in
+     * errors.
+     */
+    pub fn from_bytes(name: &str, bytes: Vec<u8>, limits: &ArchiveLimits) -> FlyResult<Self> {
+        let bytes = if bytes.starts_with(GZIP_MAGIC) {
+            gunzip(name, &bytes, limits)?
+        } else {
+            bytes
+        };
+        let entries = if le_u32(&bytes, 0) == Some(ZIP_LOCAL_HEADER) {
+            zip_entries(name, &bytes, limits)?
+        } else if bytes.get(257..262) == Some(b"ustar") {
+            tar_entries(name, &bytes, limits)?
+        } else {
+            return Err(archive_error(name, "not a tar or zip archive"));
+        };
+        let mut total = 0u64;
+        let mut by_name = HashMap::new();
+        for (entry_name, entry) in entries {
+            if entry.declared_size > limits.max_entry_bytes {
+                return Err(archive_error(
+                    name,
+                    format!(
+                        "entry {} is {} bytes, more than the limit of {}",
+                        entry_name, entry.declared_size, limits.max_entry_bytes
+                    ),
+                ));
+            }
+            total += entry.declared_size;
+            if total > limits.max_total_bytes {
+                return Err(archive_error(
+                    name,
+                    format!(
+                        "entries up to {} add up to more than the limit of {} bytes",
+                        entry_name, limits.max_total_bytes
+                    ),
+                ));
+            }
+            if by_name.insert(entry_name.clone(), entry).is_some() {
+                warn!(
+                    "archive {} has entry {} more than once, using the last",
+                    name, entry_name
+                );
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            bytes,
+            entries: by_name,
+        })
+    }
+
+    /** What the archive was opened as, its path for `open`. */
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn contains(&self, entry_name: &str) -> bool {
+        self.entries.contains_key(entry_name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /** The name of every entry, sorted. */
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.entries.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /**
+     * The contents of `entry_name`, inflating no more than it declared.
+     */
+    pub fn read(&self, entry_name: &str) -> FlyResult<Option<Vec<u8>>> {
+        let entry = match self.entries.get(entry_name) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let err = |msg: &str| archive_error(&self.name, format!("entry {} {}", entry_name, msg));
+        let offset = if entry.zip {
+            let name_len = le_u16(&self.bytes, entry.offset + 26);
+            let extra_len = le_u16(&self.bytes, entry.offset + 28);
+            match (le_u32(&self.bytes, entry.offset), name_len, extra_len) {
+                (Some(ZIP_LOCAL_HEADER), Some(name_len), Some(extra_len)) => {
+                    entry.offset + 30 + name_len as usize + extra_len as usize
+                }
+                _ => return Err(err("has no valid local header")),
+            }
+        } else {
+            entry.offset
+        };
+        let data = self
+            .bytes
+            .get(offset..offset + entry.stored_size as usize)
+            .ok_or_else(|| err("extends past the end of the archive"))?;
+        let contents = match entry.method {
+            Method::Stored => data.to_vec(),
+            Method::Deflated => {
+                let mut contents = vec![];
+                libflate::deflate::Decoder::new(data)
+                    .take(entry.declared_size + 1)
+                    .read_to_end(&mut contents)
+                    .map_err(|e| err(&format!("is corrupt: {}", e)))?;
+                contents
+            }
+        };
+        if contents.len() as u64 > entry.declared_size {
+            return Err(err(&format!(
+                "inflates past its declared size of {} bytes",
+                entry.declared_size
+            )));
+        }
+        if (contents.len() as u64) < entry.declared_size {
+            return Err(err(&format!(
+                "is {} bytes, short of its declared {}",
+                contents.len(),
+                entry.declared_size
+            )));
+        }
+        Ok(Some(contents))
+    }
+}
+
+/**
+ * The tar inside a gzip'd one, inflating no more than its entries may add up to plus their
+ * headers and padding.
+ */
+fn gunzip(archive: &str, bytes: &[u8], limits: &ArchiveLimits) -> FlyResult<Vec<u8>> {
+    let max_bytes = limits.max_total_bytes + (limits.max_entries as u64 + 2) * 4 * TAR_BLOCK as u64;
+    let mut tar = vec![];
+    libflate::gzip::Decoder::new(bytes)
+        .and_then(|decoder| decoder.take(max_bytes + 1).read_to_end(&mut tar))
+        .map_err(|e| archive_error(archive, format!("gzip is corrupt: {}", e)))?;
+    if tar.len() as u64 > max_bytes {
+        return Err(archive_error(
+            archive,
+            format!("gzip inflates past the limit of {} bytes", max_bytes),
+        ));
+    }
+    if tar.get(257..262) != Some(b"ustar") {
+        return Err(archive_error(archive, "gzip doesn't hold a tar archive"));
+    }
+    Ok(tar)
+}
+
+fn checked_name(archive: &str, raw: &str) -> FlyResult<String> {
+    normalize_entry_name(raw)
+        .map_err(|why| archive_error(archive, format!("entry {} {}", raw, why)))
+}
+
+fn tar_entries(
+    archive: &str,
+    bytes: &[u8],
+    limits: &ArchiveLimits,
+) -> FlyResult<Vec<(String, Entry)>> {
+    let mut entries = vec![];
+    let mut pos = 0;
+    let mut long_name = None;
+    while let Some(header) = bytes.get(pos..pos + TAR_BLOCK) {
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let raw_name = {
+            let prefix = tar_string(&header[345..500]);
+            let name = tar_string(&header[..100]);
+            if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            }
+        };
+        let size = tar_size(&header[124..136]).ok_or_else(|| {
+            archive_error(archive, format!("entry {} has an invalid size", raw_name))
+        })?;
+        let data_start = pos + TAR_BLOCK;
+        let padded = (size as usize + TAR_BLOCK - 1) / TAR_BLOCK * TAR_BLOCK;
+        match header[156] {
+            b'0' | 0 | b'7' => {
+                let raw_name = long_name.take().unwrap_or(raw_name);
+                entries.push((
+                    checked_name(archive, &raw_name)?,
+                    Entry {
+                        offset: data_start,
+                        stored_size: size,
+                        declared_size: size,
+                        method: Method::Stored,
+                        zip: false,
+                    },
+                ));
+                if entries.len() > limits.max_entries {
+                    return Err(archive_error(
+                        archive,
+                        format!("has more than {} entries", limits.max_entries),
+                    ));
+                }
+            }
+            b'x' => {
+                let records = bytes
+                    .get(data_start..data_start + size as usize)
+                    .ok_or_else(|| archive_error(archive, "pax header extends past the end"))?;
+                long_name = pax_path(records);
+            }
+            // directories and global pax headers
+            b'5' | b'g' => {}
+            b'1' | b'2' => {
+                return Err(archive_error(
+                    archive,
+                    format!(
+                        "entry {} is a link, which bundles may not contain",
+                        raw_name
+                    ),
+                ))
+            }
+            kind => {
+                return Err(archive_error(
+                    archive,
+                    format!("entry {} has unsupported type {}", raw_name, kind as char),
+                ))
+            }
+        }
+        pos = data_start + padded;
+    }
+    Ok(entries)
+}
+
+fn zip_entries(
+    archive: &str,
+    bytes: &[u8],
+    limits: &ArchiveLimits,
+) -> FlyResult<Vec<(String, Entry)>> {
+    // the end record is 22 bytes plus a comment of up to 64KiB
+    let search_from = bytes.len().saturating_sub(22 + 0xffff);
+    let end = (search_from..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|at| le_u32(bytes, *at) == Some(ZIP_END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| archive_error(archive, "zip has no central directory"))?;
+    let count = le_u16(bytes, end + 10).unwrap() as usize;
+    if count > limits.max_entries {
+        return Err(archive_error(
+            archive,
+            format!("has more than {} entries", limits.max_entries),
+        ));
+    }
+    let mut pos = le_u32(bytes, end + 16).unwrap() as usize;
+    let mut entries = vec![];
+    for _ in 0..count {
+        if le_u32(bytes, pos) != Some(ZIP_CENTRAL_HEADER) {
+            return Err(archive_error(archive, "zip central directory is corrupt"));
+        }
+        let field = |at: usize| le_u32(bytes, pos + at).unwrap_or(0);
+        let method = le_u16(bytes, pos + 10).unwrap_or(0);
+        let stored_size = field(20);
+        let declared_size = field(24);
+        let name_len = le_u16(bytes, pos + 28).unwrap_or(0) as usize;
+        let extra_len = le_u16(bytes, pos + 30).unwrap_or(0) as usize;
+        let comment_len = le_u16(bytes, pos + 32).unwrap_or(0) as usize;
+        let offset = field(42) as usize;
+        let raw_name = bytes
+            .get(pos + 46..pos + 46 + name_len)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .ok_or_else(|| archive_error(archive, "zip central directory is corrupt"))?;
+        pos += 46 + name_len + extra_len + comment_len;
+        if raw_name.ends_with('/') {
+            continue;
+        }
+        let method = match method {
+            0 => Method::Stored,
+            8 => Method::Deflated,
+            other => {
+                return Err(archive_error(
+                    archive,
+                    format!(
+                        "entry {} uses unsupported compression method {}",
+                        raw_name, other
+                    ),
+                ))
+            }
+        };
+        if stored_size == 0xffff_ffff || declared_size == 0xffff_ffff {
+            return Err(archive_error(
+                archive,
+                format!("entry {} needs zip64, which bundles may not use", raw_name),
+            ));
+        }
+        entries.push((
+            checked_name(archive, &raw_name)?,
+            Entry {
+                offset,
+                stored_size: u64::from(stored_size),
+                declared_size: u64::from(declared_size),
+                method,
+                zip: true,
+            },
+        ));
+    }
+    Ok(entries)
+}
+
+/**
+ * Serves `<protocol>:///<path>` from an `ArchiveBundle`, probing extensions and index files.
+ */
+pub struct ArchiveModuleResolver {
+    protocol: String,
+    bundle: Arc<ArchiveBundle>,
+    data_formats: DataFormats,
+}
+
+impl ArchiveModuleResolver {
+    pub fn new(bundle: ArchiveBundle, protocol: &str) -> Self {
+        Self {
+            protocol: protocol.to_string(),
+            bundle: Arc::new(bundle),
+            data_formats: DataFormats::default(),
+        }
+    }
+
+    /** How entries that aren't scripts are turned into modules, picked by their extension. */
+    pub fn with_data_formats(mut self, data_formats: DataFormats) -> Self {
+        self.data_formats = data_formats;
+        self
+    }
+}
+
+impl ModuleResolver for ArchiveModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => format!("{}:///", self.protocol),
+        };
+        let url = parse_url(module_specifier, &referer_origin_url)?;
+        let path = url.path().trim_matches('/');
+        let entry_name = match PROBE_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{}{}", path, suffix))
+            .find(|candidate| self.bundle.contains(candidate))
+        {
+            Some(entry_name) => entry_name,
+            None => {
+                return Err(FlyError::from(format!(
+                    "Could not resolve {} from {}: not in archive {}",
+                    module_specifier, referer_origin_url, self.bundle.name
+                )))
+            }
+        };
+        let origin_url = format!("{}:///{}", self.protocol, entry_name);
+        let source_loader = self.data_formats.loader_for(
+            &origin_url,
+            Box::new(ArchiveEntryLoader {
+                bundle: self.bundle.clone(),
+                entry_name,
+            }),
+        );
+        Ok(ModuleSourceData {
+            origin_url,
+            source_loader,
+        })
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("archive", &self.protocol)
+            .named(&self.name())
+            .with("archive", &self.bundle.name)
+            .with("entries", &self.bundle.len().to_string())
+    }
+}
+
+pub struct ArchiveEntryLoader {
+    bundle: Arc<ArchiveBundle>,
+    entry_name: String,
+}
+
+impl SourceLoader for ArchiveEntryLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let not_utf8 = |entry_name: &str| {
+            archive_error(
+                &self.bundle.name,
+                format!("entry {} is not utf-8 text", entry_name),
+            )
+        };
+        let source = self.bundle.read(&self.entry_name)?.ok_or_else(|| {
+            archive_error(&self.bundle.name, format!("no entry {}", self.entry_name))
+        })?;
+        let source = String::from_utf8(source).map_err(|_| not_utf8(&self.entry_name))?;
+        let map_name = format!("{}.map", self.entry_name);
+        let source_map = match self.bundle.read(&map_name)? {
+            Some(map) => Some(String::from_utf8(map).map_err(|_| not_utf8(&map_name))?),
+            None => None,
+        };
+        // data entries get theirs from the loaders `DataFormats` wraps this in
+        let kind = match typescript_flavor(&self.entry_name) {
+            Some(_) => ModuleKind::TypeScript,
+            None => ModuleKind::JavaScript,
+        };
+        Ok(LoadedSourceCode {
+            kind,
+            source_map: source_map.map(Into::into),
+            source: source.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn tar_header(name: &str, size: usize, kind: u8) -> Vec<u8> {
+        let mut header = vec![0u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}\0", size);
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header
+    }
+
+    fn tar(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut tar = vec![];
+        for (name, contents) in entries {
+            tar.extend(tar_header(name, contents.len(), b'0'));
+            tar.extend_from_slice(contents.as_bytes());
+            tar.resize((tar.len() + TAR_BLOCK - 1) / TAR_BLOCK * TAR_BLOCK, 0);
+        }
+        tar.extend(vec![0u8; TAR_BLOCK * 2]);
+        tar
+    }
+
+    fn push_u16(out: &mut Vec<u8>, v: u16) {
+        out.extend_from_slice(&[v as u8, (v >> 8) as u8]);
+    }
+
+    fn push_u32(out: &mut Vec<u8>, v: u32) {
+        push_u16(out, v as u16);
+        push_u16(out, (v >> 16) as u16);
+    }
+
+    // stored deflate blocks: libflate's lz77 matcher isn't sound under debug assertions
+    fn deflate(contents: &[u8]) -> Vec<u8> {
+        let options = libflate::deflate::EncodeOptions::new().no_compression();
+        let mut encoder = libflate::deflate::Encoder::with_options(vec![], options);
+        encoder.write_all(contents).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    fn gzip(contents: &[u8]) -> Vec<u8> {
+        let options = libflate::gzip::EncodeOptions::new().no_compression();
+        let mut encoder = libflate::gzip::Encoder::with_options(vec![], options).unwrap();
+        encoder.write_all(contents).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    // (name, contents, the size to declare)
+    fn zip(entries: &[(&str, &[u8], u32)]) -> Vec<u8> {
+        let mut zip = vec![];
+        let mut central = vec![];
+        for (name, contents, declared) in entries {
+            let data = deflate(contents);
+            let offset = zip.len() as u32;
+            push_u32(&mut zip, ZIP_LOCAL_HEADER);
+            zip.extend_from_slice(&[20, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            push_u32(&mut zip, data.len() as u32);
+            push_u32(&mut zip, *declared);
+            push_u16(&mut zip, name.len() as u16);
+            push_u16(&mut zip, 0);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(&data);
+
+            push_u32(&mut central, ZIP_CENTRAL_HEADER);
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            push_u32(&mut central, data.len() as u32);
+            push_u32(&mut central, *declared);
+            push_u16(&mut central, name.len() as u16);
+            central.extend_from_slice(&[0; 12]);
+            push_u32(&mut central, offset);
+            central.extend_from_slice(name.as_bytes());
+        }
+        let central_offset = zip.len() as u32;
+        zip.extend_from_slice(&central);
+        push_u32(&mut zip, ZIP_END_OF_CENTRAL_DIRECTORY);
+        zip.extend_from_slice(&[0, 0, 0, 0]);
+        push_u16(&mut zip, entries.len() as u16);
+        push_u16(&mut zip, entries.len() as u16);
+        push_u32(&mut zip, central.len() as u32);
+        push_u32(&mut zip, central_offset);
+        push_u16(&mut zip, 0);
+        zip
+    }
+
+    fn open(bytes: Vec<u8>, limits: &ArchiveLimits) -> FlyResult<ArchiveBundle> {
+        ArchiveBundle::from_bytes("app.bundle", bytes, limits)
+    }
+
+    fn open_err(bytes: Vec<u8>, limits: &ArchiveLimits) -> String {
+        format!("{}", open(bytes, limits).err().unwrap())
+    }
+
+    #[test]
+    fn test_serves_modules_from_tar_and_zip() {
+        let tar = open(
+            tar(&[
+                ("./index.ts", "export default 1;"),
+                ("index.ts.map", "{\"version\":3}"),
+                ("lib/index.js", "export default 2;"),
+            ]),
+            &ArchiveLimits::default(),
+        )
+        .unwrap();
+        let resolver = ArchiveModuleResolver::new(tar, "bundle");
+        let index = resolver.resolve_module("bundle:///index", None).unwrap();
+        assert_eq!("bundle:///index.ts", index.origin_url);
+        let source = index.source_loader.load_source().unwrap();
+        assert_eq!(ModuleKind::TypeScript, source.kind);
+        assert_eq!("export default 1;", &*source.source);
+        assert_eq!(Some("{\"version\":3}".into()), source.source_map);
+        assert_eq!(
+            "bundle:///lib/index.js",
+            resolver.resolve_module("./lib", None).unwrap().origin_url
+        );
+
+        let source = b"export const answer = 42;";
+        let zip = open(
+            zip(&[("src/main.js", source, source.len() as u32)]),
+            &ArchiveLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(source.to_vec(), zip.read("src/main.js").unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_entries_load_by_their_extension() {
+        let tar = open(
+            tar(&[
+                ("main.js", "export default 1;"),
+                ("util.ts", "export const one: number = 1;"),
+                ("view.tsx", "export const view = <b />;"),
+                ("config.json", "{\"port\": 8080}"),
+            ]),
+            &ArchiveLimits::default(),
+        )
+        .unwrap();
+        let resolver = ArchiveModuleResolver::new(tar, "bundle");
+        let load = |specifier: &str| {
+            resolver
+                .resolve_module(specifier, None)
+                .unwrap()
+                .source_loader
+                .load_source()
+                .unwrap()
+        };
+        assert_eq!(ModuleKind::JavaScript, load("bundle:///main.js").kind);
+        assert_eq!(ModuleKind::TypeScript, load("bundle:///util").kind);
+        assert_eq!(ModuleKind::TypeScript, load("bundle:///view.tsx").kind);
+        let config = load("bundle:///config.json");
+        assert_eq!(ModuleKind::Json, config.kind);
+        assert!(
+            config.source.contains("export default"),
+            "{}",
+            config.source
+        );
+    }
+
+    #[test]
+    fn test_serves_gzipped_tarballs_end_to_end() {
+        use crate::module_resolver::{ModuleResolverManager, StandardModuleResolverManager};
+        let bundle = open(
+            gzip(&tar(&[
+                ("src/main.js", "import { util } from './lib/util';"),
+                ("src/lib/util.ts", "export { helper } from '../../shared';"),
+                ("shared/index.js", "export const helper = 1;"),
+            ])),
+            &ArchiveLimits::default(),
+        )
+        .unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(ArchiveModuleResolver::new(bundle, "bundle")) as Box<ModuleResolver>],
+            Some("bundle:///".to_string()),
+        );
+        let mut origin_url = "bundle:///src/main.js".to_string();
+        let mut chain = vec![];
+        for specifier in &["bundle:///src/main.js", "./lib/util", "../../shared"] {
+            let referer = RefererInfo {
+                origin_url: origin_url.clone(),
+                is_wasm: Some(false),
+                source_code: None,
+                identifier_hash: None,
+                line: None,
+                column: None,
+                app_id: None,
+                deadline: None,
+            };
+            let module = manager
+                .resolve_blocking(specifier.to_string(), Some(referer))
+                .unwrap();
+            origin_url = module.origin_url;
+            chain.push(origin_url.clone());
+        }
+        assert_eq!(
+            vec![
+                "bundle:///src/main.js",
+                "bundle:///src/lib/util.ts",
+                "bundle:///shared/index.js"
+            ],
+            chain
+        );
+
+        // corrupt archives fail opening, not importing
+        let mut corrupt = gzip(&tar(&[("index.js", "")]));
+        let len = corrupt.len();
+        corrupt.truncate(len / 2);
+        assert!(open_err(corrupt, &ArchiveLimits::default()).contains("gzip is corrupt"));
+    }
+
+    #[test]
+    fn test_rejects_entries_escaping_the_archive() {
+        let limits = ArchiveLimits::default();
+        assert_eq!(
+            "archive app.bundle: entry ../../etc/cron.d/evil leaves the archive through ..",
+            open_err(tar(&[("../../etc/cron.d/evil", "")]), &limits)
+        );
+        assert!(open_err(tar(&[("/etc/passwd", "")]), &limits).contains("is an absolute path"));
+        assert!(open_err(zip(&[("C:/evil.js", b"", 0)]), &limits).contains("drive letter"));
+        assert!(open_err(zip(&[("lib\\..\\..\\evil.js", b"", 0)]), &limits)
+            .contains("uses backslashes"));
+        let mut link = tar_header("lib/link.js", 0, b'2');
+        link.extend(vec![0u8; TAR_BLOCK * 2]);
+        assert!(open_err(link, &limits).contains("entry lib/link.js is a link"));
+    }
+
+    #[test]
+    fn test_limits_entries_and_sizes() {
+        let limits = ArchiveLimits {
+            max_entries: 2,
+            max_entry_bytes: 100,
+            max_total_bytes: 150,
+        };
+        assert!(
+            open_err(tar(&[("a.js", ""), ("b.js", ""), ("c.js", "")]), &limits)
+                .contains("more than 2 entries")
+        );
+        let big = "x".repeat(101);
+        assert!(open_err(tar(&[("big.js", &big)]), &limits)
+            .contains("entry big.js is 101 bytes, more than the limit of 100"));
+        let eighty = "x".repeat(80);
+        assert!(
+            open_err(tar(&[("a.js", &eighty), ("b.js", &eighty)]), &limits)
+                .contains("entries up to b.js add up to more than the limit of 150 bytes")
+        );
+
+        // a bomb that lies about its size opens, but fails once it inflates past the lie
+        let bomb = vec![0u8; 1 << 20];
+        let bundle = open(zip(&[("bomb.js", &bomb, 10)]), &limits).unwrap();
+        assert_eq!(
+            "archive app.bundle: entry bomb.js inflates past its declared size of 10 bytes",
+            format!("{}", bundle.read("bomb.js").err().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_last_duplicate_wins() {
+        let bundle = open(
+            tar(&[("index.js", "first"), ("./index.js", "second")]),
+            &ArchiveLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(1, bundle.len());
+        assert_eq!(
+            b"second".to_vec(),
+            bundle.read("index.js").unwrap().unwrap()
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::data_module::js_string_literal;
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+pub const DEFAULT_TEXT_ASSETS: &[&str] = &["css", "txt", "html", "md"];
+
+pub const DEFAULT_BINARY_ASSETS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "ico", "woff", "woff2", "ttf", "otf",
+];
+
+/**
+ * The largest binary asset imported by default. Every byte takes more than one in the module's
+ * source, so bigger files are better fetched.
+ */
+pub const DEFAULT_BINARY_ASSET_MAX_BYTES: u64 = 1024 * 1024;
+
+/**
+ * Builds the loader for an asset at the origin url it's given, around the loader of the
+ * asset's raw contents.
+ */
+pub type AssetLoaderFactory = Arc<Fn(&str, Box<SourceLoader>) -> Box<SourceLoader> + Send + Sync>;
+
+/**
+ * Loaders for files imported as assets rather than code, like `import css from
+ * "./styles.css"`, by file extension. The text assets of `DEFAULT_TEXT_ASSETS` and binary ones
+ * of `DEFAULT_BINARY_ASSETS` are registered by default; `with_loader` registers a loader of the
+ * host's own for an extension, or replaces one.
+ */
+#[derive(Clone)]
+pub struct AssetLoaders {
+    factories: BTreeMap<String, AssetLoaderFactory>,
+}
+
+impl Default for AssetLoaders {
+    fn default() -> Self {
+        Self::text(DEFAULT_TEXT_ASSETS)
+            .with_binary_assets(DEFAULT_BINARY_ASSETS, DEFAULT_BINARY_ASSET_MAX_BYTES)
+    }
+}
+
+impl AssetLoaders {
+    /** Without any assets. */
+    pub fn new() -> Self {
+        Self {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /** Files with any of `extensions` imported as text. */
+    pub fn text<S: AsRef<str>>(extensions: &[S]) -> Self {
+        extensions
+            .iter()
+            .fold(Self::new(), |assets, ext| assets.with_text(ext.as_ref()))
+    }
+
+    /**
+     * Files with any of `extensions` imported as `Uint8Array`s, if they're at most `max_bytes`
+     * long.
+     */
+    pub fn with_binary_assets<S: AsRef<str>>(self, extensions: &[S], max_bytes: u64) -> Self {
+        extensions.iter().fold(self, |assets, ext| {
+            assets.with_loader(
+                ext.as_ref(),
+                Arc::new(move |origin_url, inner| {
+                    match url::Url::parse(origin_url).map(|url| url.to_file_path()) {
+                        Ok(Ok(path)) => {
+                            Box::new(BinaryAssetLoader::new(origin_url, path, max_bytes))
+                        }
+                        // only files are read as bytes
+                        _ => inner,
+                    }
+                }),
+            )
+        })
+    }
+
+    pub fn with_text(self, extension: &str) -> Self {
+        self.with_loader(
+            extension,
+            Arc::new(|origin_url, inner| Box::new(TextAssetLoader::new(origin_url, inner))),
+        )
+    }
+
+    pub fn with_loader(mut self, extension: &str, factory: AssetLoaderFactory) -> Self {
+        self.factories
+            .insert(extension.trim_start_matches('.').to_lowercase(), factory);
+        self
+    }
+
+    pub fn without(mut self, extension: &str) -> Self {
+        self.factories
+            .remove(&extension.trim_start_matches('.').to_lowercase());
+        self
+    }
+
+    pub fn extensions(&self) -> Vec<&str> {
+        self.factories.keys().map(|ext| ext.as_str()).collect()
+    }
+
+    /**
+     * Wraps `loader` in the loader registered for the extension of `origin_url`, or hands it
+     * back if there's none.
+     */
+    pub fn loader_for(
+        &self,
+        origin_url: &str,
+        loader: Box<SourceLoader>,
+    ) -> Result<Box<SourceLoader>, Box<SourceLoader>> {
+        let path = origin_url.split(|c| c == '?' || c == '#').next().unwrap();
+        let file_name = path.rsplit('/').next().unwrap();
+        let extension = match file_name.rfind('.') {
+            Some(dot) if dot > 0 => file_name[dot + 1..].to_lowercase(),
+            _ => return Err(loader),
+        };
+        match self.factories.get(&extension) {
+            Some(factory) => Ok(factory(origin_url, loader)),
+            None => Err(loader),
+        }
+    }
+}
+
+impl fmt::Debug for AssetLoaders {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.factories.keys()).finish()
+    }
+}
+
+/**
+ * Turns a text file into a module whose default export is its contents, exactly. Files that
+ * look binary, with NUL bytes, or that aren't utf-8 fail to load rather than importing as
+ * mojibake.
+ */
+pub struct TextAssetLoader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+}
+
+impl TextAssetLoader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+        }
+    }
+}
+
+impl SourceLoader for TextAssetLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let raw = self.inner.load_source().map_err(|e| match e.kind() {
+            ErrorKind::InvalidData => new(
+                ErrorKind::InvalidData,
+                format!("{} is not utf-8 text: {}", self.origin_url, e),
+            ),
+            _ => e,
+        })?;
+        if raw.is_wasm() || raw.source.contains('\0') {
+            return Err(new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} looks binary, it can't be imported as text",
+                    self.origin_url
+                ),
+            ));
+        }
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::Text,
+            source_map: None,
+            source: text_to_module(&raw.source).into(),
+            response: raw.response,
+            provenance: raw.provenance,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+/**
+ * Turns a file into a module whose default export is a `Uint8Array` of its bytes, decoded from
+ * base64 as the module is evaluated. Files over `max_bytes` fail to load.
+ */
+pub struct BinaryAssetLoader {
+    pub origin_url: String,
+    pub path: PathBuf,
+    pub max_bytes: u64,
+}
+
+impl BinaryAssetLoader {
+    pub fn new(origin_url: &str, path: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            path,
+            max_bytes,
+        }
+    }
+}
+
+impl SourceLoader for BinaryAssetLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let size = std::fs::metadata(&self.path)?.len();
+        if size > self.max_bytes {
+            return Err(new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} is {} bytes, over the {} a binary asset may be; use fetch instead",
+                    self.origin_url, size, self.max_bytes
+                ),
+            ));
+        }
+        let bytes = std::fs::read(&self.path)?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::Bytes,
+            source_map: None,
+            source: bytes_to_module(&bytes).into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+/** A module exporting `bytes` as its default `Uint8Array`. */
+pub fn bytes_to_module(bytes: &[u8]) -> String {
+    format!(
+        concat!(
+            "const __base64 = \"{}\";\n",
+            "const __bytes = new Uint8Array({});\n",
+            "{{\n",
+            "  const digits = \"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/\";\n",
+            "  const values = new Uint8Array(128);\n",
+            "  for (let i = 0; i < 64; i++) values[digits.charCodeAt(i)] = i;\n",
+            "  const at = (i) => values[__base64.charCodeAt(i)];\n",
+            "  for (let i = 0, j = 0; j < __bytes.length; i += 4) {{\n",
+            "    const n = (at(i) << 18) | (at(i + 1) << 12) | (at(i + 2) << 6) | at(i + 3);\n",
+            "    __bytes[j++] = n >> 16;\n",
+            "    if (j < __bytes.length) __bytes[j++] = n >> 8;\n",
+            "    if (j < __bytes.length) __bytes[j++] = n;\n",
+            "  }}\n",
+            "}}\n",
+            "export default __bytes;\n",
+        ),
+        base64::encode(bytes),
+        bytes.len()
+    )
+}
+
+/** A module exporting `text` as its default, escaped into a string literal. */
+pub fn text_to_module(text: &str) -> String {
+    format!("export default {};\n", js_string_literal(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::LocalDiskRawLoader;
+
+    #[test]
+    fn test_text_round_trips_exactly() {
+        let dir = tempfile::tempdir().unwrap();
+        let css = "a::before { content: `${x}` \"\\\\\" '\\u2028'; }\r\n/* \u{2028} */\n";
+        std::fs::write(dir.path().join("styles.css"), css).unwrap();
+        std::fs::write(dir.path().join("logo.css"), b"\x89PNG\r\n\x1a\n").unwrap();
+        std::fs::write(dir.path().join("blob.txt"), "GIF89a\0\0").unwrap();
+        let load = |name: &str| {
+            let path = dir.path().join(name);
+            let origin_url = url::Url::from_file_path(&path).unwrap().to_string();
+            AssetLoaders::default()
+                .loader_for(&origin_url, Box::new(LocalDiskRawLoader::new(path, None)))
+                .ok()
+                .unwrap()
+                .load_source()
+        };
+
+        let module = load("styles.css").unwrap();
+        let literal = module
+            .source
+            .trim_start_matches("export default ")
+            .trim_end_matches(";\n");
+        assert_eq!(css, serde_json::from_str::<String>(literal).unwrap());
+        assert!(!module.source.contains('\u{2028}'));
+
+        let err = load("logo.css").err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert!(err.to_string().contains("is not utf-8 text"), "{}", err);
+        let err = load("blob.txt").err().unwrap();
+        assert!(err
+            .to_string()
+            .ends_with("looks binary, it can't be imported as text"));
+
+        assert_eq!(
+            vec!["html", "md", "txt"],
+            AssetLoaders::text(DEFAULT_TEXT_ASSETS)
+                .without(".css")
+                .extensions()
+        );
+    }
+
+    #[test]
+    fn test_binary_round_trips_exactly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("all.png");
+        let bytes: Vec<u8> = (0..=255).collect();
+        std::fs::write(&path, &bytes).unwrap();
+        let origin_url = url::Url::from_file_path(&path).unwrap().to_string();
+        let load = |assets: AssetLoaders| {
+            assets
+                .loader_for(
+                    &origin_url,
+                    Box::new(LocalDiskRawLoader::new(path.clone(), None)),
+                )
+                .ok()
+                .unwrap()
+                .load_source()
+        };
+
+        let module = load(AssetLoaders::default()).unwrap();
+        let base64 = module
+            .source
+            .lines()
+            .next()
+            .unwrap()
+            .trim_start_matches("const __base64 = \"")
+            .trim_end_matches("\";");
+        assert_eq!(bytes, base64::decode(base64).unwrap());
+        assert!(module
+            .source
+            .contains("const __bytes = new Uint8Array(256);"));
+
+        let err = load(AssetLoaders::new().with_binary_assets(&["png"], 255))
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+        assert!(err.to_string().ends_with(
+            "all.png is 256 bytes, over the 255 a binary asset may be; use fetch instead"
+        ));
+    }
+}
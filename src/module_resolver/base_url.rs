@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+/**
+ * Placeholder origins for first-party modules, each mapped to the base url the current
+ * environment serves them from. A placeholder is either a scheme (`app-cdn:`) or a logical
+ * origin (`https://modules.fly.internal`); anything after it is appended to the base url,
+ * so `app-cdn:/lib/util.ts` runs unmodified against staging and prod CDNs.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct BaseUrlSubstitutions {
+    bases: BTreeMap<String, String>,
+}
+
+impl BaseUrlSubstitutions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base_url(mut self, placeholder: &str, base_url: &str) -> Self {
+        self.bases
+            .insert(placeholder.to_string(), base_url.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.bases.iter()
+    }
+
+    /**
+     * The specifier with its placeholder origin swapped for the configured base url, or
+     * `None` when it doesn't use a placeholder. The longest matching placeholder wins.
+     */
+    pub fn rewrite(&self, specifier: &str) -> Option<String> {
+        let (placeholder, base_url) = self
+            .bases
+            .iter()
+            .filter(|(placeholder, _)| matches_placeholder(specifier, placeholder))
+            .max_by_key(|(placeholder, _)| placeholder.len())?;
+        let rest = specifier[placeholder.len()..].trim_start_matches('/');
+        Some(format!("{}/{}", base_url.trim_end_matches('/'), rest))
+    }
+}
+
+fn matches_placeholder(specifier: &str, placeholder: &str) -> bool {
+    if !specifier.starts_with(placeholder) {
+        return false;
+    }
+    // `https://cdn.internal` must not match `https://cdn.internal.evil.com/...`
+    placeholder.ends_with(':')
+        || placeholder.ends_with('/')
+        || specifier[placeholder.len()..]
+            .chars()
+            .next()
+            .map(|c| c == '/')
+            .unwrap_or(true)
+}
+
+impl<'a> From<&'a std::collections::HashMap<String, String>> for BaseUrlSubstitutions {
+    fn from(bases: &'a std::collections::HashMap<String, String>) -> Self {
+        Self {
+            bases: bases.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staging() -> BaseUrlSubstitutions {
+        BaseUrlSubstitutions::new()
+            .with_base_url("app-cdn:", "https://staging-cdn.example.com/modules/")
+            .with_base_url(
+                "https://modules.fly.internal",
+                "https://staging.example.com",
+            )
+    }
+
+    #[test]
+    fn test_scheme_placeholder() {
+        assert_eq!(
+            Some("https://staging-cdn.example.com/modules/lib/util.ts".to_string()),
+            staging().rewrite("app-cdn:/lib/util.ts")
+        );
+    }
+
+    #[test]
+    fn test_logical_host_placeholder() {
+        let substitutions = staging();
+        assert_eq!(
+            Some("https://staging.example.com/lib/util.ts".to_string()),
+            substitutions.rewrite("https://modules.fly.internal/lib/util.ts")
+        );
+        assert_eq!(
+            None,
+            substitutions.rewrite("https://modules.fly.internal.evil.com/lib/util.ts")
+        );
+        assert_eq!(None, substitutions.rewrite("./lib/util.ts"));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::errors::*;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+use futures::sync::oneshot;
+use futures::{future, Async, Future};
+use tokio::timer::Timeout;
+
+use super::trace::{current_trace, traced};
+use crate::runtime::EVENT_LOOP;
+
+/**
+ * Runs `work`, which may block on disk or network IO, on the shared event loop's blocking
+ * pool. Neither the runtime's own thread nor the event loop's workers wait on it meanwhile.
+ */
+pub fn spawn_blocking<T, F>(work: F) -> Box<Future<Item = T, Error = FlyError> + Send>
+where
+    T: Send + 'static,
+    F: FnOnce() -> FlyResult<T> + Send + 'static,
+{
+    Box::new(oneshot::spawn(on_blocking_pool(work), &EVENT_LOOP.0))
+}
+
+/** `work`, run on the blocking pool once the event loop polls it and there's room. */
+fn on_blocking_pool<T, F>(work: F) -> impl Future<Item = T, Error = FlyError> + Send
+where
+    T: Send + 'static,
+    F: FnOnce() -> FlyResult<T> + Send + 'static,
+{
+    let mut work = Some(work);
+    future::poll_fn(move || {
+        // only called once there's room on the pool; until then `work` stays put
+        match tokio_threadpool::blocking(|| (work.take().expect("blocking work ran twice"))()) {
+            Ok(Async::Ready(result)) => result.map(Async::Ready),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(e) => Err(FlyError::from(format!(
+                "could not run on the blocking pool: {}",
+                e
+            ))),
+        }
+    })
+}
+
+/**
+ * Runs `work` on the blocking pool, recording into this thread's trace, and gives up on it
+ * with `ErrorKind::TimedOut` once `timeout` passes. The timeout is the event loop's, which
+ * drops work given up on: if it was still waiting for room on the pool it never runs, and if
+ * it was running, what it comes back with is dropped. Without a timeout `work` runs right here.
+ */
+pub fn run_with_deadline<T, F>(what: &str, timeout: Option<Duration>, work: F) -> FlyResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> FlyResult<T> + Send + 'static,
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return work(),
+    };
+    let trace = current_trace();
+    let what = what.to_string();
+    let panicked = format!("{} panicked", what);
+    let working = on_blocking_pool(move || {
+        panic::catch_unwind(AssertUnwindSafe(|| traced(&trace, work)))
+            .unwrap_or_else(|_| Err(FlyError::from(panicked)))
+    });
+    let bounded = Timeout::new(working, timeout).map_err(move |e| {
+        if e.is_elapsed() {
+            new(
+                ErrorKind::TimedOut,
+                format!("{} timed out after {:?}", what, timeout),
+            )
+        } else if e.is_inner() {
+            e.into_inner().unwrap()
+        } else {
+            FlyError::from(format!(
+                "could not time {}: {}",
+                what,
+                e.into_timer().unwrap()
+            ))
+        }
+    });
+    oneshot::spawn(bounded, &EVENT_LOOP.0).wait()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_runs_off_the_calling_thread() {
+        let caller = thread::current().id();
+        let ran_on = spawn_blocking(move || Ok(thread::current().id()))
+            .wait()
+            .unwrap();
+        assert_ne!(caller, ran_on);
+        let err = spawn_blocking(|| -> FlyResult<()> { Err(FlyError::from("nope".to_string())) })
+            .wait()
+            .err()
+            .unwrap();
+        assert_eq!("nope", format!("{}", err));
+    }
+
+    #[test]
+    fn test_gives_up_on_work_past_its_deadline() {
+        let slow = run_with_deadline("sleeping", Some(Duration::from_millis(20)), || {
+            thread::sleep(Duration::from_secs(2));
+            Ok(())
+        });
+        let err = slow.err().unwrap();
+        assert_eq!(ErrorKind::TimedOut, err.kind());
+        assert_eq!("sleeping timed out after 20ms", err.to_string());
+        let quick = run_with_deadline("adding", Some(Duration::from_secs(5)), || Ok(1 + 1));
+        assert_eq!(2, quick.unwrap());
+    }
+
+    #[test]
+    fn test_bounded_work_runs_on_the_blocking_pool() {
+        let ran_on = run_with_deadline("checking", Some(Duration::from_secs(5)), || {
+            Ok(thread::current().name().map(|name| name.to_string()))
+        })
+        .unwrap();
+        // the event loop's workers, not a thread started for it
+        assert!(ran_on.unwrap().starts_with("tokio-runtime-worker"));
+
+        let err = run_with_deadline(
+            "failing",
+            Some(Duration::from_secs(5)),
+            || -> FlyResult<()> { panic!("oops") },
+        )
+        .err()
+        .unwrap();
+        assert_eq!("failing panicked", err.to_string());
+    }
+}
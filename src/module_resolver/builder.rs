@@ -0,0 +1,315 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+
+use super::{
+    CodeCache, ImportMap, ModuleResolver, ResolutionCache, ResolverOptions, ResolverPolicy,
+    SharedSourceCache, StandardModuleResolverManager, WasmCompileCache,
+};
+
+/**
+ * Assembles a `StandardModuleResolverManager` one piece at a time, checking at `build` that
+ * the pieces fit together. Resolvers for a protocol are tried by priority, highest first, and
+ * in the order they were added when priorities tie; `with_resolver` uses the resolver's own
+ * `priority()`.
+ *
+ * ```
+ * use fly::module_resolver::{
+ *     HttpModuleResolver, ImportMap, JsonSecretsResolver, LocalDiskModuleResolver,
+ *     ModuleResolverManager, ModuleResolverManagerBuilder, ResolutionCache,
+ * };
+ *
+ * let import_map = ImportMap::from_json(
+ *     r#"{"imports": {"std/": "https://deno.land/std@0.50.0/"}}"#,
+ *     "file:///app/import_map.json",
+ * )
+ * .unwrap();
+ * let manager = ModuleResolverManagerBuilder::new()
+ *     .with_default_root("file:///app/")
+ *     .with_resolver(LocalDiskModuleResolver::new(Some("file:///app/".to_string())).unwrap())
+ *     .with_resolver(JsonSecretsResolver::new(
+ *         serde_json::json!({"api": {"key": "s3cret"}}),
+ *     ))
+ *     .with_resolver_priority(HttpModuleResolver::new("https"), 1)
+ *     .with_import_map(import_map)
+ *     .with_cache(ResolutionCache::new())
+ *     .with_protocol_allowlist(&["file", "secrets", "https"])
+ *     .build()
+ *     .unwrap();
+ *
+ * let protocols: Vec<_> = manager
+ *     .describe()
+ *     .unwrap()
+ *     .protocols
+ *     .into_iter()
+ *     .map(|p| p.protocol)
+ *     .collect();
+ * assert_eq!(vec!["file", "https", "secrets"], protocols);
+ * ```
+ */
+#[derive(Default)]
+pub struct ModuleResolverManagerBuilder {
+    resolvers: Vec<(Box<ModuleResolver>, Option<i32>)>,
+    default_root: Option<String>,
+    import_map: Option<ImportMap>,
+    cache: Option<ResolutionCache>,
+    shared_cache: Option<SharedSourceCache>,
+    code_cache: Option<CodeCache>,
+    wasm_cache: Option<WasmCompileCache>,
+    allowed_protocols: Option<Vec<String>>,
+    allowed_hosts: BTreeMap<String, Vec<String>>,
+    options: ResolverOptions,
+}
+
+impl ModuleResolverManagerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_resolver<R: ModuleResolver + 'static>(self, resolver: R) -> Self {
+        self.with_boxed_resolver(Box::new(resolver), None)
+    }
+
+    /** Adds `resolver` at `priority`, overriding its own. */
+    pub fn with_resolver_priority<R: ModuleResolver + 'static>(
+        self,
+        resolver: R,
+        priority: i32,
+    ) -> Self {
+        self.with_boxed_resolver(Box::new(resolver), Some(priority))
+    }
+
+    /** Adds `resolver` at `priority`, or its own priority if `None`. */
+    pub fn with_boxed_resolver(
+        mut self,
+        resolver: Box<ModuleResolver>,
+        priority: Option<i32>,
+    ) -> Self {
+        self.resolvers.push((resolver, priority));
+        self
+    }
+
+    /**
+     * What entry points and other loads without a referer resolve against, `file://<cwd>/`
+     * unless given.
+     */
+    pub fn with_default_root(mut self, url: &str) -> Self {
+        self.default_root = Some(url.to_string());
+        self
+    }
+
+    pub fn with_import_map(mut self, import_map: ImportMap) -> Self {
+        self.import_map = Some(import_map);
+        self
+    }
+
+    /** Keeps what's resolved in `cache`, as `with_resolution_cache` does. */
+    pub fn with_cache(mut self, cache: ResolutionCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /** Consults `cache` before loading any source, as `with_shared_cache` does. */
+    pub fn with_shared_cache(mut self, cache: SharedSourceCache) -> Self {
+        self.shared_cache = Some(cache);
+        self
+    }
+
+    /** Hands out the code caches runtimes store, as `with_code_cache` does. */
+    pub fn with_code_cache(mut self, cache: CodeCache) -> Self {
+        self.code_cache = Some(cache);
+        self
+    }
+
+    /** Hands out the compiled wasm runtimes store, as `with_wasm_cache` does. */
+    pub fn with_wasm_cache(mut self, cache: WasmCompileCache) -> Self {
+        self.wasm_cache = Some(cache);
+        self
+    }
+
+    /** Only `protocols` may be resolved; every resolver added must serve one of them. */
+    pub fn with_protocol_allowlist<I, S>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.allowed_protocols = Some(
+            protocols
+                .into_iter()
+                .map(|p| p.as_ref().trim_end_matches(':').to_string())
+                .collect(),
+        );
+        self
+    }
+
+    /** Only urls of `protocol` whose host matches one of `patterns`, like `*.deno.land`. */
+    pub fn with_allowed_hosts<I, S>(mut self, protocol: &str, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.allowed_hosts
+            .entry(protocol.trim_end_matches(':').to_string())
+            .or_insert_with(Vec::new)
+            .extend(patterns.into_iter().map(|p| p.as_ref().to_string()));
+        self
+    }
+
+    /** Timeouts, retries, strategy and limits for the resolvers' attempts. */
+    pub fn with_options(mut self, options: ResolverOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /**
+     * The manager, unless the configuration contradicts itself: the default root isn't a
+     * url, a resolver serves a protocol the allowlist leaves out, or the import map maps a
+     * specifier to a protocol no resolver serves, which could never load.
+     */
+    pub fn build(self) -> FlyResult<StandardModuleResolverManager> {
+        if let Some(ref root) = self.default_root {
+            url::Url::parse(root)
+                .map_err(|e| FlyError::from(format!("Invalid default root {}: {}", root, e)))?;
+        }
+        let mut manager =
+            StandardModuleResolverManager::with_options(vec![], self.default_root, self.options);
+        for (resolver, priority) in self.resolvers {
+            let protocol = resolver.get_protocol();
+            if let Some(ref allowed) = self.allowed_protocols {
+                if !allowed.contains(&protocol) {
+                    return Err(FlyError::from(format!(
+                        "{} resolver serves protocol {}, which the protocol allowlist [{}] leaves out",
+                        resolver.describe().kind,
+                        protocol,
+                        allowed.join(", ")
+                    )));
+                }
+            }
+            let priority = priority.unwrap_or_else(|| resolver.priority());
+            manager.add_resolver(resolver, priority)?;
+        }
+        let policy = match self.allowed_protocols {
+            Some(allowed) => ResolverPolicy::allow_protocols(allowed),
+            None => ResolverPolicy::allow_all(),
+        };
+        let policy = self
+            .allowed_hosts
+            .into_iter()
+            .fold(policy, |policy, (protocol, patterns)| {
+                policy.with_hosts(&protocol, patterns)
+            });
+        if policy != ResolverPolicy::allow_all() {
+            manager.tighten_policy(policy)?;
+        }
+        if let Some(import_map) = self.import_map {
+            for (key, url) in import_map.mappings() {
+                if key.starts_with("fly:") {
+                    return Err(FlyError::from(format!(
+                        "The import map maps {} to {}, but fly: builtins can't be remapped",
+                        key, url
+                    )));
+                }
+                if !manager.protocol_resolver_map.contains_key(url.scheme()) {
+                    return Err(FlyError::from(format!(
+                        "The import map maps {} to {}, but no resolver is registered for protocol {}",
+                        key,
+                        url,
+                        url.scheme()
+                    )));
+                }
+            }
+            manager = manager.with_import_map(import_map);
+        }
+        if let Some(cache) = self.cache {
+            manager = manager.with_resolution_cache(cache);
+        }
+        if let Some(cache) = self.shared_cache {
+            manager = manager.with_shared_cache(cache);
+        }
+        if let Some(cache) = self.code_cache {
+            manager = manager.with_code_cache(cache);
+        }
+        if let Some(cache) = self.wasm_cache {
+            manager = manager.with_wasm_cache(cache);
+        }
+        Ok(manager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{ModuleResolverManager, StaticModuleResolver};
+
+    fn cdn() -> StaticModuleResolver {
+        StaticModuleResolver::new("https").insert("https://cdn.test/lib.js", "export default 1;")
+    }
+
+    #[test]
+    fn test_orders_resolvers_by_priority() {
+        let manager = ModuleResolverManagerBuilder::new()
+            .with_default_root("https://cdn.test/")
+            .with_resolver(
+                StaticModuleResolver::new("https")
+                    .insert("https://cdn.test/lib.js", "export default 'default';"),
+            )
+            .with_resolver_priority(
+                StaticModuleResolver::new("https")
+                    .insert("https://cdn.test/lib.js", "export default 'override';"),
+                1,
+            )
+            .build()
+            .unwrap();
+        let module = manager.resolve_entry_point("./lib.js".to_string()).unwrap();
+        assert_eq!("export default 'override';", &*module.loaded_source.source);
+    }
+
+    #[test]
+    fn test_validates_at_build() {
+        let err = ModuleResolverManagerBuilder::new()
+            .with_default_root("not a url")
+            .build()
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid default root not a url"));
+
+        let err = ModuleResolverManagerBuilder::new()
+            .with_resolver(cdn())
+            .with_protocol_allowlist(&["file"])
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "static resolver serves protocol https, which the protocol allowlist [file] leaves out",
+            err.to_string()
+        );
+
+        let import_map = ImportMap::from_json(
+            r#"{"scopes": {"/app/": {"lib": "gopher://mirror.test/lib.js"}}}"#,
+            "https://cdn.test/",
+        )
+        .unwrap();
+        let err = ModuleResolverManagerBuilder::new()
+            .with_resolver(cdn())
+            .with_import_map(import_map)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "The import map maps lib to gopher://mirror.test/lib.js, but no resolver is registered for protocol gopher",
+            err.to_string()
+        );
+
+        let manager = ModuleResolverManagerBuilder::new()
+            .with_resolver(cdn())
+            .with_protocol_allowlist(&["https:"])
+            .build()
+            .unwrap();
+        assert!(!manager.policy().permits("file"));
+        assert!(manager
+            .resolve_entry_point("https://cdn.test/lib.js".to_string())
+            .is_ok());
+    }
+}
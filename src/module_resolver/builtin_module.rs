@@ -0,0 +1,243 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::{
+    LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+pub const BUILTIN_PROTOCOL: &str = "fly";
+
+/**
+ * The runtime's own APIs as modules, `fly:<name>`, alongside the globals they wrap, and the
+ * polyfills of node's builtins under `fly:node/`, see `NodeBuiltinResolver`. Adding one is an
+ * entry here plus a file in `builtins/fly`.
+ */
+const FLY_BUILTINS: &[(&str, &str)] = &[
+    ("cache", include_str!("builtins/fly/cache.js")),
+    ("data", include_str!("builtins/fly/data.js")),
+    ("http", include_str!("builtins/fly/http.js")),
+    ("image", include_str!("builtins/fly/image.js")),
+    ("node/buffer", include_str!("builtins/fly/node/buffer.js")),
+    ("node/events", include_str!("builtins/fly/node/events.js")),
+    ("node/path", include_str!("builtins/fly/node/path.js")),
+    ("node/util", include_str!("builtins/fly/node/util.js")),
+    (
+        "response-cache",
+        include_str!("builtins/fly/response-cache.js"),
+    ),
+    ("runtime", include_str!("builtins/fly/runtime.js")),
+];
+
+/** The source of the bundled builtin `fly:<name>`. */
+pub(super) fn bundled_builtin(name: &str) -> Option<&'static str> {
+    FLY_BUILTINS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, source)| *source)
+}
+
+struct BuiltinLoader {
+    source: Arc<str>,
+}
+
+impl SourceLoader for BuiltinLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: None,
+            source: self.source.clone(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+/**
+ * Serves `fly:<name>` builtins, the bundled ones and any the embedder adds with
+ * `with_builtin` before the runtime starts. It's the only resolver a manager lets serve the
+ * `fly` protocol, so no disk or remote resolver can stand in for a builtin.
+ */
+pub struct BuiltinModuleResolver {
+    builtins: BTreeMap<String, Arc<str>>,
+}
+
+impl BuiltinModuleResolver {
+    pub fn new() -> Self {
+        Self {
+            builtins: FLY_BUILTINS
+                .iter()
+                .map(|(name, source)| (name.to_string(), Arc::from(*source)))
+                .collect(),
+        }
+    }
+
+    /** Serves `source` as `fly:<name>`, in place of any bundled builtin of that name. */
+    pub fn with_builtin(mut self, name: &str, source: &str) -> Self {
+        let name = name.trim_start_matches("fly:");
+        self.builtins.insert(name.to_string(), Arc::from(source));
+        self
+    }
+
+    /** The names of every builtin, in order. */
+    pub fn names(&self) -> Vec<&str> {
+        self.builtins.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+impl Default for BuiltinModuleResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleResolver for BuiltinModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => "<entry point>".to_string(),
+        };
+        let name = module_specifier.trim_start_matches("fly:");
+        match self.builtins.get(name) {
+            Some(source) => Ok(ModuleSourceData {
+                origin_url: format!("{}:{}", BUILTIN_PROTOCOL, name),
+                source_loader: Box::new(BuiltinLoader {
+                    source: source.clone(),
+                }),
+            }),
+            None => Err(new(
+                ErrorKind::NotFound,
+                format!(
+                    "Cannot import unknown builtin {} from {}: the builtins are {}",
+                    module_specifier,
+                    referer_origin_url,
+                    self.names()
+                        .iter()
+                        .map(|name| format!("fly:{}", name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            )),
+        }
+    }
+    fn get_protocol(&self) -> String {
+        BUILTIN_PROTOCOL.to_string()
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("fly_builtin", BUILTIN_PROTOCOL)
+            .named(&self.name())
+            .with("builtins", &self.names().join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        ImportMap, ModuleResolverManager, ModuleResolverManagerBuilder,
+        StandardModuleResolverManager, StaticModuleResolver,
+    };
+
+    #[test]
+    fn test_imports_registered_builtins_and_names_the_rest() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                BuiltinModuleResolver::new().with_builtin("kv", "export const kv = fly.kv;"),
+            ) as Box<ModuleResolver>],
+            Some("file:///app/".to_string()),
+        );
+        let cache = manager
+            .resolve_entry_point("fly:cache".to_string())
+            .unwrap();
+        assert_eq!("fly:cache", cache.origin_url);
+        assert!(cache
+            .loaded_source
+            .source
+            .contains("export const cache = fly.cache;"));
+        let kv = manager.resolve_entry_point("fly:kv".to_string()).unwrap();
+        assert_eq!("export const kv = fly.kv;", &*kv.loaded_source.source);
+
+        let err = manager
+            .resolve_entry_point("fly:kafka".to_string())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ModuleNotFound, err.kind());
+        assert!(
+            err.to_string().contains(
+                "the builtins are fly:cache, fly:data, fly:http, fly:image, fly:kv, fly:node/buffer, fly:node/events, fly:node/path, fly:node/util, fly:response-cache, fly:runtime"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_nothing_else_serves_the_fly_protocol() {
+        let mut manager = StandardModuleResolverManager::new(
+            vec![Box::new(BuiltinModuleResolver::new()) as Box<ModuleResolver>],
+            None,
+        );
+        let err = manager
+            .register_resolver(Box::new(
+                StaticModuleResolver::new("fly").insert("fly:cache", "export default 'fake';"),
+            ))
+            .err()
+            .unwrap();
+        assert_eq!(
+            "Cannot register a static resolver for protocol fly: only the builtin resolver serves fly: modules",
+            err.to_string()
+        );
+
+        // nor one that describes itself as the builtin resolver
+        struct Impostor;
+        impl ModuleResolver for Impostor {
+            fn resolve_module(
+                &self,
+                _module_specifier: &str,
+                _referer_info: Option<RefererInfo>,
+            ) -> FlyResult<ModuleSourceData> {
+                Err(FlyError::from("fake"))
+            }
+            fn get_protocol(&self) -> String {
+                BUILTIN_PROTOCOL.to_string()
+            }
+            fn describe(&self) -> ResolverDescription {
+                ResolverDescription::new("fly_builtin", BUILTIN_PROTOCOL)
+            }
+        }
+        assert!(manager.register_resolver(Box::new(Impostor)).is_err());
+        assert!(manager
+            .register_resolver(Box::new(BuiltinModuleResolver::new()))
+            .is_ok());
+
+        // nor can an import map point a builtin elsewhere
+        let import_map = ImportMap::from_json(
+            r#"{"imports": {"fly:cache": "https://cdn.test/cache.js"}}"#,
+            "file:///app/",
+        )
+        .unwrap();
+        let err = ModuleResolverManagerBuilder::new()
+            .with_resolver(BuiltinModuleResolver::new())
+            .with_resolver(
+                StaticModuleResolver::new("https")
+                    .insert("https://cdn.test/cache.js", "export default 'fake';"),
+            )
+            .with_import_map(import_map)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "The import map maps fly:cache to https://cdn.test/cache.js, but fly: builtins can't be remapped",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,3 @@
+// `fly:cache`, the global `fly.cache` as a module.
+export const cache = fly.cache;
+export default cache;
@@ -0,0 +1,3 @@
+// `fly:data`, the global `fly.data` as a module.
+export const data = fly.data;
+export default data;
@@ -0,0 +1,3 @@
+// `fly:http`, the global `fly.http` as a module.
+export const http = fly.http;
+export default http;
@@ -0,0 +1,3 @@
+// `fly:image`, the global `fly.Image` as a module.
+export const Image = fly.Image;
+export default Image;
@@ -0,0 +1,95 @@
+// Minimal implementation of node's "buffer" module on top of Uint8Array.
+const HEX = "0123456789abcdef";
+const B64 = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+function toBase64(bytes) {
+  let out = "";
+  for (let i = 0; i < bytes.length; i += 3) {
+    const n = (bytes[i] << 16) | ((bytes[i + 1] || 0) << 8) | (bytes[i + 2] || 0);
+    out += B64[(n >> 18) & 63] + B64[(n >> 12) & 63];
+    out += i + 1 < bytes.length ? B64[(n >> 6) & 63] : "=";
+    out += i + 2 < bytes.length ? B64[n & 63] : "=";
+  }
+  return out;
+}
+
+function fromBase64(str) {
+  const clean = str.replace(/[^A-Za-z0-9+/]/g, "");
+  const out = [];
+  for (let i = 0; i < clean.length; i += 4) {
+    const n = (B64.indexOf(clean[i]) << 18) | (B64.indexOf(clean[i + 1]) << 12) |
+      ((B64.indexOf(clean[i + 2]) & 63) << 6) | (B64.indexOf(clean[i + 3]) & 63);
+    out.push((n >> 16) & 255);
+    if (i + 2 < clean.length) out.push((n >> 8) & 255);
+    if (i + 3 < clean.length) out.push(n & 255);
+  }
+  return out;
+}
+
+export class Buffer extends Uint8Array {
+  static from(value, encoding) {
+    if (typeof value === "string") {
+      switch (encoding || "utf8") {
+        case "hex": {
+          const bytes = [];
+          for (let i = 0; i + 1 < value.length; i += 2) {
+            bytes.push(parseInt(value.substr(i, 2), 16));
+          }
+          return new Buffer(bytes);
+        }
+        case "base64":
+          return new Buffer(fromBase64(value));
+        default:
+          return new Buffer(new TextEncoder().encode(value));
+      }
+    }
+    if (value instanceof ArrayBuffer) {
+      return new Buffer(new Uint8Array(value));
+    }
+    return new Buffer(value);
+  }
+
+  static alloc(size, fill) {
+    const buf = new Buffer(size);
+    if (fill !== undefined) {
+      buf.fill(typeof fill === "number" ? fill : Buffer.from(fill)[0]);
+    }
+    return buf;
+  }
+
+  static isBuffer(value) {
+    return value instanceof Buffer;
+  }
+
+  static byteLength(value, encoding) {
+    return Buffer.from(value, encoding).length;
+  }
+
+  static concat(list) {
+    const total = list.reduce((n, b) => n + b.length, 0);
+    const out = new Buffer(total);
+    let offset = 0;
+    for (const b of list) {
+      out.set(b, offset);
+      offset += b.length;
+    }
+    return out;
+  }
+
+  toString(encoding) {
+    switch (encoding || "utf8") {
+      case "hex":
+        return Array.from(this, b => HEX[b >> 4] + HEX[b & 15]).join("");
+      case "base64":
+        return toBase64(this);
+      default:
+        return new TextDecoder().decode(this);
+    }
+  }
+
+  equals(other) {
+    return this.length === other.length && this.every((b, i) => b === other[i]);
+  }
+}
+
+export default { Buffer };
@@ -0,0 +1,68 @@
+// Minimal implementation of node's "events" module.
+export class EventEmitter {
+  constructor() {
+    this._events = new Map();
+  }
+
+  on(name, listener) {
+    if (!this._events.has(name)) {
+      this._events.set(name, []);
+    }
+    this._events.get(name).push(listener);
+    return this;
+  }
+
+  once(name, listener) {
+    const wrapped = (...args) => {
+      this.off(name, wrapped);
+      listener.apply(this, args);
+    };
+    wrapped.listener = listener;
+    return this.on(name, wrapped);
+  }
+
+  off(name, listener) {
+    const listeners = this._events.get(name);
+    if (listeners) {
+      const idx = listeners.findIndex(l => l === listener || l.listener === listener);
+      if (idx !== -1) {
+        listeners.splice(idx, 1);
+      }
+    }
+    return this;
+  }
+
+  removeAllListeners(name) {
+    if (name === undefined) {
+      this._events.clear();
+    } else {
+      this._events.delete(name);
+    }
+    return this;
+  }
+
+  emit(name, ...args) {
+    const listeners = this._events.get(name);
+    if (!listeners || listeners.length === 0) {
+      if (name === "error") {
+        throw args[0] instanceof Error ? args[0] : new Error("Unhandled error event");
+      }
+      return false;
+    }
+    for (const listener of listeners.slice()) {
+      listener.apply(this, args);
+    }
+    return true;
+  }
+
+  listenerCount(name) {
+    const listeners = this._events.get(name);
+    return listeners ? listeners.length : 0;
+  }
+}
+
+EventEmitter.prototype.addListener = EventEmitter.prototype.on;
+EventEmitter.prototype.removeListener = EventEmitter.prototype.off;
+EventEmitter.EventEmitter = EventEmitter;
+
+export default EventEmitter;
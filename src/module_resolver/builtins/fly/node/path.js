@@ -0,0 +1,100 @@
+// Minimal POSIX implementation of node's "path" module.
+export const sep = "/";
+export const delimiter = ":";
+
+function normalizeSegments(segments, allowAboveRoot) {
+  const out = [];
+  for (const seg of segments) {
+    if (!seg || seg === ".") {
+      continue;
+    }
+    if (seg === "..") {
+      if (out.length > 0 && out[out.length - 1] !== "..") {
+        out.pop();
+      } else if (allowAboveRoot) {
+        out.push("..");
+      }
+      continue;
+    }
+    out.push(seg);
+  }
+  return out;
+}
+
+export function isAbsolute(p) {
+  return p.charAt(0) === "/";
+}
+
+export function normalize(p) {
+  if (p === "") {
+    return ".";
+  }
+  const absolute = isAbsolute(p);
+  const trailing = p.charAt(p.length - 1) === "/";
+  let out = normalizeSegments(p.split("/"), !absolute).join("/");
+  if (!out && !absolute) {
+    out = ".";
+  }
+  if (out && trailing) {
+    out += "/";
+  }
+  return (absolute ? "/" : "") + out;
+}
+
+export function join(...parts) {
+  const joined = parts.filter(p => p !== "").join("/");
+  return joined === "" ? "." : normalize(joined);
+}
+
+export function resolve(...parts) {
+  let resolved = "";
+  for (let i = parts.length - 1; i >= 0 && !isAbsolute(resolved); i--) {
+    if (parts[i]) {
+      resolved = parts[i] + (resolved ? "/" + resolved : "");
+    }
+  }
+  return "/" + normalizeSegments(resolved.split("/"), false).join("/");
+}
+
+export function relative(from, to) {
+  const f = resolve(from).split("/").filter(Boolean);
+  const t = resolve(to).split("/").filter(Boolean);
+  let i = 0;
+  while (i < f.length && i < t.length && f[i] === t[i]) {
+    i++;
+  }
+  return f.slice(i).map(() => "..").concat(t.slice(i)).join("/");
+}
+
+export function dirname(p) {
+  if (p === "") {
+    return ".";
+  }
+  const stripped = p.length > 1 ? p.replace(/\/+$/, "") : p;
+  const idx = stripped.lastIndexOf("/");
+  if (idx === -1) {
+    return ".";
+  }
+  if (idx === 0) {
+    return "/";
+  }
+  return stripped.slice(0, idx);
+}
+
+export function basename(p, ext) {
+  const stripped = p.length > 1 ? p.replace(/\/+$/, "") : p;
+  let base = stripped.slice(stripped.lastIndexOf("/") + 1);
+  if (ext && base.endsWith(ext) && base !== ext) {
+    base = base.slice(0, base.length - ext.length);
+  }
+  return base;
+}
+
+export function extname(p) {
+  const base = basename(p);
+  const idx = base.lastIndexOf(".");
+  return idx <= 0 ? "" : base.slice(idx);
+}
+
+export const posix = { sep, delimiter, isAbsolute, normalize, join, resolve, relative, dirname, basename, extname };
+export default posix;
@@ -0,0 +1,61 @@
+// Minimal implementation of node's "util" module.
+export function inspect(value) {
+  if (typeof value === "string") {
+    return value;
+  }
+  try {
+    return JSON.stringify(value);
+  } catch (_e) {
+    return String(value);
+  }
+}
+
+export function format(fmt, ...args) {
+  if (typeof fmt !== "string") {
+    return [fmt, ...args].map(inspect).join(" ");
+  }
+  let i = 0;
+  const out = fmt.replace(/%[sdifjo%]/g, token => {
+    if (token === "%%") {
+      return "%";
+    }
+    if (i >= args.length) {
+      return token;
+    }
+    const arg = args[i++];
+    switch (token) {
+      case "%d":
+      case "%i":
+        return String(parseInt(arg, 10));
+      case "%f":
+        return String(parseFloat(arg));
+      case "%j":
+      case "%o":
+        return inspect(arg);
+      default:
+        return String(arg);
+    }
+  });
+  return [out, ...args.slice(i).map(inspect)].join(" ");
+}
+
+export function inherits(ctor, superCtor) {
+  Object.setPrototypeOf(ctor.prototype, superCtor.prototype);
+  Object.setPrototypeOf(ctor, superCtor);
+}
+
+export function promisify(fn) {
+  return (...args) =>
+    new Promise((resolve, reject) => {
+      fn(...args, (err, value) => (err ? reject(err) : resolve(value)));
+    });
+}
+
+export function deprecate(fn, _msg) {
+  return fn;
+}
+
+export const TextEncoder = globalThis.TextEncoder;
+export const TextDecoder = globalThis.TextDecoder;
+
+export default { format, inspect, inherits, promisify, deprecate, TextEncoder, TextDecoder };
@@ -0,0 +1,3 @@
+// `fly:response-cache`, the global `fly.responseCache` as a module.
+export const responseCache = fly.responseCache;
+export default responseCache;
@@ -0,0 +1,3 @@
+// `fly:runtime`, the global `fly.runtime` as a module.
+export const runtime = fly.runtime;
+export default runtime;
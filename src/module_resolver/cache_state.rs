@@ -0,0 +1,78 @@
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheLocation {
+    /** The process-wide source cache, or a runtime's virtual modules. */
+    Memory,
+    /** A resolver's on-disk cache of fetched modules. */
+    Disk,
+}
+
+impl CacheLocation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheLocation::Memory => "memory",
+            CacheLocation::Disk => "disk",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheFreshness {
+    /** Importing it now would be served from the cache. */
+    Fresh,
+    /** Cached, but past its max age: importing it now would refetch it first. */
+    Stale,
+}
+
+impl CacheFreshness {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheFreshness::Fresh => "fresh",
+            CacheFreshness::Stale => "stale",
+        }
+    }
+}
+
+/**
+ * Whether the module a specifier resolves to is already cached, found without loading or
+ * fetching it.
+ */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ModuleCacheState {
+    pub origin_url: String,
+    /** `None` when importing it would have to load it from its source. */
+    pub location: Option<CacheLocation>,
+    /** Bytes of source held, 0 when not cached. */
+    pub size: usize,
+    pub freshness: Option<CacheFreshness>,
+}
+
+impl ModuleCacheState {
+    pub fn uncached(origin_url: &str) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            location: None,
+            size: 0,
+            freshness: None,
+        }
+    }
+
+    pub fn cached(
+        origin_url: &str,
+        location: CacheLocation,
+        size: usize,
+        freshness: CacheFreshness,
+    ) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            location: Some(location),
+            size,
+            freshness: Some(freshness),
+        }
+    }
+
+    pub fn is_cached(&self) -> bool {
+        self.location.is_some()
+    }
+}
@@ -0,0 +1,452 @@
+use crate::errors::*;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Loop};
+use futures::Future;
+
+use super::trace::{push_step, traced, TraceHandle};
+use super::{
+    parse_url, spawn_blocking, CancellationToken, ImportOptions, LoadedModule, LoadedModuleFuture,
+    ModuleResolutionError, ModuleResolver, ModuleSourceData, RefererInfo, ResolutionObserver,
+    ResolutionOutcome, ResolutionStrategy, ResolveEnd, ResolveStart, ResolverAttempt,
+    ResolverOptions, StandardModuleResolverManager, TraceStep,
+};
+
+/**
+ * Where a specifier resolved to, before any source is loaded.
+ */
+pub(super) enum Located {
+    Virtual(ModuleSourceData),
+    Resolved {
+        module_loader: ModuleSourceData,
+        resolver: Arc<ModuleResolver>,
+        tenant: String,
+        resolve_time: Duration,
+        /** The resolvers after the one that resolved it, to try if loading times out. */
+        fallthrough: Option<Candidates>,
+    },
+}
+
+impl Located {
+    pub(super) fn origin_url(&self) -> &str {
+        match self {
+            Located::Virtual(module_loader) => &module_loader.origin_url,
+            Located::Resolved { module_loader, .. } => &module_loader.origin_url,
+        }
+    }
+}
+
+/**
+ * A specifier after every mapping and rewrite: either located already, or with the
+ * resolvers for its protocol still to try in turn.
+ */
+pub(super) enum Prepared {
+    Located(Located),
+    Candidates(Candidates),
+}
+
+/**
+ * A specifier with the resolvers to try for it, in order, and what trying them takes.
+ */
+pub(super) struct Candidates {
+    pub(super) specifier: String,
+    pub(super) referer_info: Option<RefererInfo>,
+    pub(super) referer_origin_url: String,
+    pub(super) resolvers: Vec<Arc<ModuleResolver>>,
+    /** A resolver that claimed a bare specifier, whose error is final. */
+    pub(super) claimed: bool,
+    /** How many of the resolvers, at the end, are fallbacks. */
+    pub(super) fallbacks: usize,
+    pub(super) tenant: String,
+    pub(super) resolve_started: Instant,
+    pub(super) options: Arc<ResolverOptions>,
+}
+
+/**
+ * One attempt of `resolver` at `specifier`, reported to `observers` as it starts and ends:
+ * on the polling thread for resolvers that need the runtime's thread, which guest resolvers
+ * rely on being their runtime's, and on the blocking pool for the rest, within the deadline
+ * and retries of `options`.
+ */
+fn attempt_resolver(
+    resolver: &Arc<ModuleResolver>,
+    specifier: &str,
+    referer_info: &Option<RefererInfo>,
+    observers: &[Arc<ResolutionObserver>],
+    trace: &Option<TraceHandle>,
+    options: &Arc<ResolverOptions>,
+) -> Box<Future<Item = ModuleSourceData, Error = FlyError> + Send> {
+    let observed = if observers.is_empty() {
+        None
+    } else {
+        let (protocol, kind, name) = (
+            resolver.get_protocol(),
+            resolver.describe().kind,
+            resolver.name(),
+        );
+        let start = ResolveStart {
+            specifier,
+            protocol: &protocol,
+            resolver: &kind,
+            name: &name,
+        };
+        for observer in observers {
+            observer.on_resolve_start(&start);
+        }
+        Some((observers.to_vec(), protocol, kind, name, Instant::now()))
+    };
+    push_step(trace, || TraceStep::ResolverTried {
+        resolver: resolver.describe().kind,
+        protocol: resolver.get_protocol(),
+    });
+    let resolved: Box<Future<Item = ModuleSourceData, Error = FlyError> + Send> =
+        if resolver.needs_runtime_thread() {
+            Box::new(future::result(traced(trace, || {
+                resolver.resolve_module(specifier, referer_info.clone())
+            })))
+        } else {
+            let (resolver, specifier, referer_info, trace, options) = (
+                resolver.clone(),
+                specifier.to_string(),
+                referer_info.clone(),
+                trace.clone(),
+                options.clone(),
+            );
+            spawn_blocking(move || {
+                let what = format!("resolving {} with {}", specifier, resolver.describe().kind);
+                let attempting = resolver.clone();
+                traced(&trace, || {
+                    options.attempt(&*attempting, &what, move || {
+                        resolver.resolve_module(&specifier, referer_info.clone())
+                    })
+                })
+            })
+        };
+    let specifier = specifier.to_string();
+    Box::new(resolved.then(move |result| {
+        if let Some((observers, protocol, kind, name, started)) = observed {
+            let end = ResolveEnd {
+                specifier: &specifier,
+                protocol: &protocol,
+                resolver: &kind,
+                name: &name,
+                duration: started.elapsed(),
+                outcome: match result {
+                    Ok(_) => ResolutionOutcome::Resolved,
+                    Err(ref e) => ResolutionOutcome::Failed(e.kind()),
+                },
+            };
+            for observer in &observers {
+                observer.on_resolve_end(&end);
+            }
+        }
+        result
+    }))
+}
+
+impl Candidates {
+    /**
+     * Tries the resolvers as the manager's `ResolutionStrategy` says: each in turn once polled
+     * until one resolves the specifier, or, racing, all but the fallbacks at once. If the last
+     * resolver timed out, so does resolving.
+     */
+    pub(super) fn try_resolvers(
+        self,
+        observers: Vec<Arc<ResolutionObserver>>,
+        trace: Option<TraceHandle>,
+    ) -> Box<Future<Item = Located, Error = FlyError> + Send> {
+        let racing = self.resolvers.len() - self.fallbacks;
+        if self.options.strategy == ResolutionStrategy::Race && !self.claimed && racing > 1 {
+            self.race(observers, trace)
+        } else {
+            self.try_in_turn(observers, trace, vec![])
+        }
+    }
+
+    /**
+     * What resolutions joining each other have in common: the tenant, the specifier as a url
+     * when it isn't bare, the directory of the referer, since resolvers may look next to it,
+     * and the type the import asserted, if any.
+     */
+    fn flight_key(&self, import_options: &ImportOptions) -> String {
+        let referer_root = match self.referer_origin_url.rfind('/') {
+            Some(i) => &self.referer_origin_url[..=i],
+            None => &self.referer_origin_url,
+        };
+        let specifier = match parse_url(&self.specifier, &self.referer_origin_url) {
+            Ok(url) if !self.claimed => url.into_string(),
+            _ => self.specifier.clone(),
+        };
+        let asserted_type = import_options.asserted_type.map_or("", |t| t.as_str());
+        format!(
+            "{} {} {} {}",
+            self.tenant, referer_root, specifier, asserted_type
+        )
+    }
+
+    fn failed(&self, attempts: Vec<ResolverAttempt>) -> FlyError {
+        let timed_out = attempts
+            .last()
+            .map_or(false, |a| a.error.kind() == ErrorKind::TimedOut);
+        let failed = if timed_out {
+            ModuleResolutionError::TimedOut {
+                specifier: self.specifier.clone(),
+                referer: self.referer_origin_url.clone(),
+                attempts,
+            }
+        } else {
+            ModuleResolutionError::NotFound {
+                specifier: self.specifier.clone(),
+                referer: self.referer_origin_url.clone(),
+                fallback: self.fallbacks > 0,
+                attempts,
+            }
+        };
+        failed.into()
+    }
+
+    /**
+     * These candidates with only `resolvers`, which are some of them in order, left to try.
+     */
+    fn with_resolvers(&self, resolvers: Vec<Arc<ModuleResolver>>) -> Candidates {
+        Candidates {
+            specifier: self.specifier.clone(),
+            referer_info: self.referer_info.clone(),
+            referer_origin_url: self.referer_origin_url.clone(),
+            fallbacks: self.fallbacks.min(resolvers.len()),
+            resolvers,
+            claimed: self.claimed,
+            tenant: self.tenant.clone(),
+            resolve_started: self.resolve_started,
+            options: self.options.clone(),
+        }
+    }
+
+    fn located(
+        &self,
+        module_loader: ModuleSourceData,
+        resolver: Arc<ModuleResolver>,
+        rest: Vec<Arc<ModuleResolver>>,
+    ) -> Located {
+        let fallthrough = if rest.is_empty() || self.claimed {
+            None
+        } else {
+            Some(self.with_resolvers(rest))
+        };
+        Located::Resolved {
+            module_loader,
+            resolver,
+            tenant: self.tenant.clone(),
+            resolve_time: self.resolve_started.elapsed(),
+            fallthrough,
+        }
+    }
+
+    /**
+     * Tries each resolver once polled until one resolves the specifier, after the ones that
+     * failed already with `attempts`.
+     */
+    fn try_in_turn(
+        self,
+        observers: Vec<Arc<ResolutionObserver>>,
+        trace: Option<TraceHandle>,
+        attempts: Vec<ResolverAttempt>,
+    ) -> Box<Future<Item = Located, Error = FlyError> + Send> {
+        Box::new(future::lazy(move || {
+            let resolvers = self.resolvers.clone();
+            let candidates = Arc::new(self);
+            future::loop_fn(
+                (resolvers.into_iter(), attempts),
+                move |(mut remaining, mut attempts): (_, Vec<ResolverAttempt>)| {
+                    let resolver: Arc<ModuleResolver> = match remaining.next() {
+                        Some(resolver) => resolver,
+                        None => {
+                            return Box::new(future::err(candidates.failed(attempts)))
+                                as Box<Future<Item = _, Error = _> + Send>;
+                        }
+                    };
+                    let resolved = attempt_resolver(
+                        &resolver,
+                        &candidates.specifier,
+                        &candidates.referer_info,
+                        &observers,
+                        &trace,
+                        &candidates.options,
+                    );
+                    let candidates = candidates.clone();
+                    let trace = trace.clone();
+                    Box::new(resolved.then(move |result| match result {
+                        Ok(module_loader) => {
+                            let rest: Vec<_> = remaining.collect();
+                            Ok(Loop::Break(candidates.located(
+                                module_loader,
+                                resolver,
+                                rest,
+                            )))
+                        }
+                        Err(e) if candidates.claimed => Err(e),
+                        Err(e) => {
+                            info!("Resolver failed trying the next one: {}", e);
+                            push_step(&trace, || TraceStep::ResolverFailed {
+                                resolver: resolver.describe().kind,
+                                error: e.to_string(),
+                            });
+                            attempts.push(ResolverAttempt {
+                                resolver: resolver.describe(),
+                                error: e,
+                            });
+                            Ok(Loop::Continue((remaining, attempts)))
+                        }
+                    }))
+                },
+            )
+        }))
+    }
+
+    /**
+     * Tries every resolver but the fallbacks at once, once polled. The first to resolve the
+     * specifier wins, and the others' attempts are dropped, which cancels those still waiting
+     * for the blocking pool; ones already running finish, unheard. If they all fail, the
+     * fallbacks are tried in turn.
+     */
+    fn race(
+        self,
+        observers: Vec<Arc<ResolutionObserver>>,
+        trace: Option<TraceHandle>,
+    ) -> Box<Future<Item = Located, Error = FlyError> + Send> {
+        Box::new(future::lazy(move || {
+            let mut racers = self.resolvers.clone();
+            let fallbacks = racers.split_off(racers.len() - self.fallbacks);
+            let attempts: Vec<_> = racers
+                .iter()
+                .map(|resolver| {
+                    let resolver = resolver.clone();
+                    attempt_resolver(
+                        &resolver,
+                        &self.specifier,
+                        &self.referer_info,
+                        &observers,
+                        &trace,
+                        &self.options,
+                    )
+                    .then(move |result| Ok::<_, FlyError>((resolver, result)))
+                })
+                .collect();
+            let race_trace = trace.clone();
+            future::loop_fn(
+                (attempts, vec![]),
+                move |(attempts, mut failed): (_, Vec<ResolverAttempt>)| {
+                    let trace = race_trace.clone();
+                    future::select_all(attempts).map_err(|(e, _, _)| e).map(
+                        move |((resolver, result), _, attempts)| match result {
+                            // dropping the other attempts cancels them
+                            Ok(module_loader) => Loop::Break(Ok((resolver, module_loader))),
+                            Err(e) => {
+                                info!("Racing resolver failed: {}", e);
+                                push_step(&trace, || TraceStep::ResolverFailed {
+                                    resolver: resolver.describe().kind,
+                                    error: e.to_string(),
+                                });
+                                failed.push(ResolverAttempt {
+                                    resolver: resolver.describe(),
+                                    error: e,
+                                });
+                                if attempts.is_empty() {
+                                    Loop::Break(Err(failed))
+                                } else {
+                                    Loop::Continue((attempts, failed))
+                                }
+                            }
+                        },
+                    )
+                },
+            )
+            .and_then(move |raced| match raced {
+                Ok((resolver, module_loader)) => {
+                    let rest = self
+                        .resolvers
+                        .iter()
+                        .filter(|r| !Arc::ptr_eq(r, &resolver))
+                        .cloned()
+                        .collect();
+                    Box::new(future::ok(self.located(module_loader, resolver, rest)))
+                        as Box<Future<Item = _, Error = _> + Send>
+                }
+                Err(failed) => self
+                    .with_resolvers(fallbacks)
+                    .try_in_turn(observers, trace, failed),
+            })
+        }))
+    }
+}
+
+impl StandardModuleResolverManager {
+    /**
+     * Resolves `candidates` and loads what they resolve to with `load`, unless the same
+     * resolution is already running, in which case this waits for it. Resolvers only run
+     * once the flight is polled, so starting one never holds up other resolutions.
+     */
+    pub(super) fn single_flight<L, F>(
+        &self,
+        mut candidates: Candidates,
+        import_options: &ImportOptions,
+        trace: Option<TraceHandle>,
+        load: L,
+    ) -> LoadedModuleFuture
+    where
+        L: FnOnce(Box<Future<Item = Located, Error = FlyError> + Send>) -> F,
+        F: Future<Item = LoadedModule, Error = FlyError> + Send + 'static,
+    {
+        let key = candidates.flight_key(import_options);
+        // whoever starts the flight resolves for everyone joining it, so its deadline stays
+        // out of it: each waiter bounds its own wait, and the flight is dropped once none is
+        // left
+        if let Some(ref mut referer_info) = candidates.referer_info {
+            referer_info.deadline = None;
+        }
+        let observers = self.observers.clone();
+        let resolving_trace = trace.clone();
+        let located = future::lazy(move || candidates.try_resolvers(observers, resolving_trace));
+        let resolution = load(Box::new(located)).then(Ok);
+        let started = std::cell::Cell::new(false);
+        let scope = CancellationToken::new();
+        let joined = self.flights.load(&key, &scope, |_| {
+            started.set(true);
+            resolution
+        });
+        if !started.get() {
+            push_step(&trace, || TraceStep::Joined);
+        }
+        Box::new(joined.and_then(move |result| {
+            // held until the resolution is done, or it'd count as abandoned
+            drop(scope);
+            result
+        }))
+    }
+
+    pub(super) fn locate(
+        &self,
+        specifier: String,
+        referer_info: &Option<RefererInfo>,
+    ) -> FlyResult<Located> {
+        self.locate_traced(specifier, referer_info, &None)
+    }
+
+    /** `locate`, recording the steps it takes into `trace`. */
+    pub(super) fn locate_traced(
+        &self,
+        specifier: String,
+        referer_info: &Option<RefererInfo>,
+        trace: &Option<TraceHandle>,
+    ) -> FlyResult<Located> {
+        match traced(trace, || self.prepare(specifier, referer_info))? {
+            Prepared::Located(located) => Ok(located),
+            // only probing, not resolving anything
+            Prepared::Candidates(candidates) => {
+                candidates.try_resolvers(vec![], trace.clone()).wait()
+            }
+        }
+    }
+}
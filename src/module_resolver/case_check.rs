@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use super::suggest::MAX_SCANNED_ENTRIES;
+
+/**
+ * What `LocalDiskModuleResolver` does about a specifier whose file name differs in case from
+ * the file on disk. Case-insensitive filesystems, like macOS's by default, find `utils.ts` for
+ * `./Utils.ts`; deployed onto a case-sensitive one, the same import breaks.
+ */
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseSensitivityMode {
+    Ignore,
+    /** Logs the mismatch, and resolves the file anyway. */
+    Warn,
+    /** Fails the resolution, the way a case-sensitive filesystem would. */
+    Error,
+}
+
+impl Default for CaseSensitivityMode {
+    fn default() -> Self {
+        CaseSensitivityMode::Warn
+    }
+}
+
+/**
+ * The name the file `path` asks for has in its directory, if that differs in case from the
+ * name asked for. `None` when the names match exactly, there's no such file, or the directory
+ * is too large to scan.
+ */
+pub fn case_mismatch(path: &Path) -> Option<String> {
+    let dir = path.parent()?;
+    let wanted = path.file_name()?.to_str()?;
+    let mut mismatch = None;
+    for (i, entry) in std::fs::read_dir(dir).ok()?.enumerate() {
+        if i >= MAX_SCANNED_ENTRIES {
+            return None;
+        }
+        let name = match entry.ok().and_then(|e| e.file_name().into_string().ok()) {
+            Some(name) => name,
+            None => continue,
+        };
+        if name == wanted {
+            return None;
+        }
+        if mismatch.is_none() && name.eq_ignore_ascii_case(wanted) {
+            mismatch = Some(name);
+        }
+    }
+    mismatch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_the_spelling_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("utils.ts"), "").unwrap();
+        assert_eq!(
+            Some("utils.ts".to_string()),
+            case_mismatch(&dir.path().join("Utils.ts"))
+        );
+        assert_eq!(None, case_mismatch(&dir.path().join("utils.ts")));
+        assert_eq!(None, case_mismatch(&dir.path().join("other.ts")));
+    }
+}
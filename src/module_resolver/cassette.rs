@@ -0,0 +1,384 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use super::{
+    spawn_blocking, LoadedModule, LoadedModuleFuture, LoadedSourceCode, ModuleKind,
+    ModuleResolverManager, RefererInfo, ResolverPolicy, ResponseMetadata,
+};
+
+const INDEX_FILE: &str = "index.json";
+const BODIES_DIR: &str = "bodies";
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CassetteConfig {
+    pub mode: CassetteMode,
+    pub dir: String,
+}
+
+/**
+ * One recorded resolution: what was asked for, from where, and what came back. Bodies are
+ * stored by hash next to the index.
+ */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CassetteEntry {
+    pub specifier: String,
+    /** `None` for entry points. */
+    pub referer: Option<String>,
+    pub origin_url: String,
+    pub is_wasm: bool,
+    /** `None` in cassettes recorded before modules had kinds, which go by `is_wasm`. */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ModuleKind>,
+    pub passthrough: bool,
+    pub source_hash: String,
+    pub source_map_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<ResponseMetadata>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CassetteIndex {
+    resolutions: Vec<CassetteEntry>,
+}
+
+type Key = (String, String);
+
+fn key(specifier: &str, referer: Option<&str>) -> Key {
+    (referer.unwrap_or("").to_string(), specifier.to_string())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn cassette_error(dir: &Path, msg: impl std::fmt::Display) -> FlyError {
+    FlyError::from(format!("cassette {}: {}", dir.display(), msg))
+}
+
+/**
+ * Writes every resolution a manager makes into a cassette directory: `index.json`, sorted by
+ * referer then specifier, and `bodies/<sha256>` for each distinct source and source map. The
+ * index is rewritten after each new resolution, so a crashed run still leaves a usable
+ * cassette, and the same run always produces the same files.
+ */
+pub struct CassetteRecorder {
+    dir: PathBuf,
+    entries: Mutex<BTreeMap<Key, CassetteEntry>>,
+}
+
+impl CassetteRecorder {
+    pub fn create(dir: &Path) -> FlyResult<Self> {
+        std::fs::create_dir_all(dir.join(BODIES_DIR)).map_err(|e| cassette_error(dir, e))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            entries: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    fn write_body(&self, body: &[u8]) -> FlyResult<String> {
+        let hash = sha256_hex(body);
+        let path = self.dir.join(BODIES_DIR).join(&hash);
+        if !path.is_file() {
+            std::fs::write(&path, body).map_err(|e| cassette_error(&self.dir, e))?;
+        }
+        Ok(hash)
+    }
+
+    pub fn record(
+        &self,
+        specifier: &str,
+        referer: Option<&str>,
+        module: &LoadedModule,
+    ) -> FlyResult<()> {
+        let source = &module.loaded_source;
+        let entry = CassetteEntry {
+            specifier: specifier.to_string(),
+            referer: referer.map(|r| r.to_string()),
+            origin_url: module.origin_url.clone(),
+            is_wasm: source.is_wasm(),
+            kind: Some(source.kind),
+            passthrough: module.passthrough,
+            source_hash: self.write_body(source.source.as_bytes())?,
+            source_map_hash: match source.source_map {
+                Some(ref map) => Some(self.write_body(map.as_bytes())?),
+                None => None,
+            },
+            response: source.response.clone(),
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let key = key(specifier, referer);
+        if entries.get(&key) == Some(&entry) {
+            return Ok(());
+        }
+        entries.insert(key, entry);
+        let index = CassetteIndex {
+            resolutions: entries.values().cloned().collect(),
+        };
+        let tmp = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        std::fs::write(&tmp, serde_json::to_string_pretty(&index).unwrap() + "\n")
+            .and_then(|_| std::fs::rename(&tmp, self.dir.join(INDEX_FILE)))
+            .map_err(|e| cassette_error(&self.dir, e))
+    }
+}
+
+/**
+ * Replays a cassette: serves exactly the recorded resolutions, byte for byte, and fails any
+ * other. Nothing is fetched or read outside the cassette.
+ */
+#[derive(Clone)]
+pub struct CassetteResolver {
+    dir: PathBuf,
+    entries: BTreeMap<Key, CassetteEntry>,
+}
+
+impl CassetteResolver {
+    pub fn open(dir: &Path) -> FlyResult<Self> {
+        let index_path = dir.join(INDEX_FILE);
+        let index = std::fs::read_to_string(&index_path).map_err(|e| cassette_error(dir, e))?;
+        let index: CassetteIndex = serde_json::from_str(&index)
+            .map_err(|e| cassette_error(dir, format!("invalid {}: {}", INDEX_FILE, e)))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            entries: index
+                .resolutions
+                .into_iter()
+                .map(|entry| {
+                    (
+                        key(&entry.specifier, entry.referer.as_ref().map(|r| r.as_str())),
+                        entry,
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn read_body(&self, hash: &str) -> FlyResult<String> {
+        let path = self.dir.join(BODIES_DIR).join(hash);
+        let body = std::fs::read(&path).map_err(|e| cassette_error(&self.dir, e))?;
+        if sha256_hex(&body) != hash {
+            return Err(cassette_error(
+                &self.dir,
+                format!("body {} doesn't match its hash", hash),
+            ));
+        }
+        String::from_utf8(body)
+            .map_err(|_| cassette_error(&self.dir, format!("body {} is not utf-8", hash)))
+    }
+
+    fn replay(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<LoadedModule> {
+        let referer = referer_info.map(|r| r.origin_url);
+        let entry = self
+            .entries
+            .get(&key(&specifier, referer.as_ref().map(|r| r.as_str())))
+            .ok_or_else(|| {
+                cassette_error(
+                    &self.dir,
+                    format!(
+                        "resolving {} from {} was never recorded",
+                        specifier,
+                        referer
+                            .as_ref()
+                            .map(|r| r.as_str())
+                            .unwrap_or("<entry point>")
+                    ),
+                )
+            })?;
+        Ok(LoadedModule {
+            loaded_source: LoadedSourceCode {
+                kind: entry
+                    .kind
+                    .unwrap_or_else(|| ModuleKind::from_is_wasm(entry.is_wasm)),
+                source_map: match entry.source_map_hash {
+                    Some(ref hash) => Some(self.read_body(hash)?.into()),
+                    None => None,
+                },
+                source: self.read_body(&entry.source_hash)?.into(),
+                response: entry.response.clone(),
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            },
+            origin_url: entry.origin_url.clone(),
+            passthrough: entry.passthrough,
+            resolver: None,
+        })
+    }
+}
+
+impl ModuleResolverManager for CassetteResolver {
+    fn resolve_module(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> LoadedModuleFuture {
+        let cassette = self.clone();
+        spawn_blocking(move || cassette.replay(specifier, referer_info))
+    }
+
+    /**
+     * Children replay the same cassette; there's nothing to narrow.
+     */
+    fn inherit_for_child(
+        &self,
+        _policy: Option<ResolverPolicy>,
+    ) -> FlyResult<Box<ModuleResolverManager>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolver, StandardModuleResolverManager,
+    };
+    use std::sync::Arc;
+
+    fn referer(origin_url: &str) -> Option<RefererInfo> {
+        Some(RefererInfo {
+            origin_url: origin_url.to_string(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        })
+    }
+
+    #[test]
+    fn test_replays_recorded_resolutions() {
+        let app = tempfile::tempdir().unwrap();
+        std::fs::write(app.path().join("index.ts"), "import \"./dep\";").unwrap();
+        std::fs::write(app.path().join("dep.ts"), "export default 1;").unwrap();
+        let cassette = tempfile::tempdir().unwrap();
+        let working_url = url::Url::from_directory_path(app.path())
+            .unwrap()
+            .into_string();
+        let recorder = Arc::new(CassetteRecorder::create(cassette.path()).unwrap());
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(working_url.clone())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(working_url),
+        )
+        .with_cassette_recorder(recorder);
+        let index = manager
+            .resolve_blocking("./index".to_string(), None)
+            .unwrap();
+        let dep = manager
+            .resolve_blocking("./dep".to_string(), referer(&index.origin_url))
+            .unwrap();
+
+        // the app can change or go away; the cassette still has it
+        drop(app);
+        let replay = CassetteResolver::open(cassette.path()).unwrap();
+        assert_eq!(2, replay.len());
+        let replayed = replay
+            .resolve_blocking("./dep".to_string(), referer(&index.origin_url))
+            .unwrap();
+        assert_eq!(dep.origin_url, replayed.origin_url);
+        assert_eq!(dep.loaded_source.source, replayed.loaded_source.source);
+        let err = replay
+            .resolve_blocking("./dep".to_string(), None)
+            .err()
+            .unwrap();
+        assert!(
+            format!("{}", err).contains("resolving ./dep from <entry point> was never recorded")
+        );
+    }
+
+    #[test]
+    fn test_cassettes_are_deterministic() {
+        let record = |order: &[&str]| {
+            let dir = tempfile::tempdir().unwrap();
+            let recorder = CassetteRecorder::create(dir.path()).unwrap();
+            for specifier in order {
+                let module = LoadedModule {
+                    loaded_source: LoadedSourceCode {
+                        kind: ModuleKind::JavaScript,
+                        source_map: None,
+                        source: format!("export const name = {:?};", specifier).into(),
+                        response: None,
+                        provenance: None,
+                        code_cache: None,
+                        compiled_wasm: None,
+                        wasm: None,
+                    },
+                    origin_url: format!("file:///app/{}.js", specifier),
+                    passthrough: false,
+                    resolver: None,
+                };
+                recorder.record(specifier, None, &module).unwrap();
+            }
+            std::fs::read_to_string(dir.path().join(INDEX_FILE)).unwrap()
+        };
+        let index = record(&["b", "a"]);
+        assert_eq!(index, record(&["a", "b"]));
+        assert!(
+            index.find("\"specifier\": \"a\"").unwrap()
+                < index.find("\"specifier\": \"b\"").unwrap()
+        );
+        assert!(!index.contains("export const"));
+    }
+
+    #[test]
+    fn test_tampered_bodies_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = CassetteRecorder::create(dir.path()).unwrap();
+        let module = LoadedModule {
+            loaded_source: LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: None,
+                source: "export default 1;".into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            },
+            origin_url: "file:///app/index.js".to_string(),
+            passthrough: false,
+            resolver: None,
+        };
+        recorder.record("./index.js", None, &module).unwrap();
+        let hash = sha256_hex(b"export default 1;");
+        std::fs::write(dir.path().join(BODIES_DIR).join(&hash), "export default 2;").unwrap();
+        let err = CassetteResolver::open(dir.path())
+            .unwrap()
+            .resolve_blocking("./index.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("doesn't match its hash"));
+    }
+}
@@ -0,0 +1,300 @@
+use crate::errors::*;
+
+use std::sync::Arc;
+
+use super::{ModuleResolver, ModuleSourceData, RefererInfo, ResolverDescription};
+
+const CDN_PROTOCOL: &str = "cdn";
+
+pub const DEFAULT_CDN_TEMPLATE: &str = "https://esm.sh/{pkg}@{version}";
+
+/**
+ * A bare `<package>@<version>[/<path>]` specifier split into its parts.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageSpecifier<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub path: Option<&'a str>,
+}
+
+impl<'a> PackageSpecifier<'a> {
+    /**
+     * `None` unless `specifier` is a valid npm package name followed by `@` and something;
+     * the version itself is checked separately so floating versions get a useful error.
+     */
+    pub fn parse(specifier: &'a str) -> Option<Self> {
+        let name_end = if specifier.starts_with('@') {
+            let slash = specifier.find('/')?;
+            slash + 1 + specifier[slash + 1..].find('@')?
+        } else {
+            specifier.find('@')?
+        };
+        let name = &specifier[..name_end];
+        let rest = &specifier[name_end + 1..];
+        let (version, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+            None => (rest, None),
+        };
+        if !is_package_name(name) || version.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name,
+            version,
+            path: path.filter(|p| !p.is_empty()),
+        })
+    }
+}
+
+/**
+ * Preset for ESM CDNs like esm.sh and unpkg: claims bare `react@18.2.0` style specifiers,
+ * expands them with a url template and hands the url to `inner`, the resolver that already
+ * serves (and caches) remote modules. `{pkg}` and `{version}` are replaced in the template;
+ * a subpath goes in `{path}` or, without one, is appended. Versions must be exact, so the
+ * same specifier always means the same code.
+ */
+pub struct CdnResolver {
+    template: String,
+    inner: Option<Arc<ModuleResolver>>,
+}
+
+impl CdnResolver {
+    pub fn new(template: &str, inner: Arc<ModuleResolver>) -> FlyResult<Self> {
+        if !template.contains("{pkg}") || !template.contains("{version}") {
+            return Err(FlyError::from(format!(
+                "CDN template {} must contain {{pkg}} and {{version}}",
+                template
+            )));
+        }
+        Ok(Self {
+            template: template.to_string(),
+            inner: Some(inner),
+        })
+    }
+
+    /**
+     * A resolver that claims no specifiers, for teams resolving packages some other way.
+     */
+    pub fn disabled() -> Self {
+        Self {
+            template: String::new(),
+            inner: None,
+        }
+    }
+
+    pub fn url_for(&self, package: &PackageSpecifier) -> FlyResult<String> {
+        if !is_exact_version(package.version) {
+            return Err(FlyError::from(format!(
+                "{}@{} must use an exact version like {}@1.2.3, not a tag or range",
+                package.name, package.version, package.name
+            )));
+        }
+        let mut url = self
+            .template
+            .replace("{pkg}", package.name)
+            .replace("{version}", package.version);
+        let path = package.path.unwrap_or("");
+        if url.contains("{path}") {
+            url = url.replace("{path}", path);
+        } else if !path.is_empty() {
+            url = format!("{}/{}", url.trim_end_matches('/'), path);
+        }
+        Ok(url)
+    }
+}
+
+impl ModuleResolver for CdnResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(ref v) => v.origin_url.clone(),
+            None => "<entry point>".to_string(),
+        };
+        let (package, inner) = match (PackageSpecifier::parse(module_specifier), &self.inner) {
+            (Some(package), Some(inner)) => (package, inner),
+            _ => {
+                return Err(FlyError::from(format!(
+                    "Cannot import {} from {}: not a CDN package specifier",
+                    module_specifier, referer_origin_url
+                )))
+            }
+        };
+        let url = self.url_for(&package).map_err(|e| {
+            FlyError::from(format!(
+                "Cannot import {} from {}: {}",
+                module_specifier, referer_origin_url, e
+            ))
+        })?;
+        debug!("resolved {} to {}", module_specifier, url);
+        inner.resolve_module(&url, referer_info)
+    }
+    fn get_protocol(&self) -> String {
+        return CDN_PROTOCOL.to_string();
+    }
+    fn is_remote(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> ResolverDescription {
+        let description = ResolverDescription::new("cdn", CDN_PROTOCOL).named(&self.name());
+        match self.inner {
+            Some(ref inner) => description
+                .with("template", &self.template)
+                .with("inner", &inner.describe().kind),
+            None => description.with("enabled", "false"),
+        }
+    }
+    fn handles_bare_specifier(&self, specifier: &str) -> bool {
+        self.inner.is_some() && PackageSpecifier::parse(specifier).is_some()
+    }
+}
+
+fn is_package_name(name: &str) -> bool {
+    let unscoped = if name.starts_with('@') {
+        let mut parts = name[1..].splitn(2, '/');
+        match (parts.next(), parts.next()) {
+            (Some(scope), Some(rest)) if is_name_part(scope) => rest,
+            _ => return false,
+        }
+    } else {
+        name
+    };
+    is_name_part(unscoped)
+}
+
+fn is_name_part(part: &str) -> bool {
+    !part.is_empty()
+        && !part.starts_with('.')
+        && !part.starts_with('_')
+        && part.chars().all(|c| {
+            c.is_ascii_lowercase()
+                || c.is_ascii_digit()
+                || c == '-'
+                || c == '.'
+                || c == '_'
+                || c == '~'
+        })
+}
+
+/**
+ * `MAJOR.MINOR.PATCH` with an optional `-prerelease` and `+build`; no tags, ranges or `x`.
+ */
+pub(super) fn is_exact_version(version: &str) -> bool {
+    let core = version.split('+').next().unwrap();
+    let mut parts = core.splitn(2, '-');
+    let numbers = parts.next().unwrap();
+    if let Some(prerelease) = parts.next() {
+        if prerelease.is_empty()
+            || !prerelease
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        {
+            return false;
+        }
+    }
+    let numbers: Vec<&str> = numbers.split('.').collect();
+    numbers.len() == 3
+        && numbers
+            .iter()
+            .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        FunctionModuleResolver, LoadedSourceCode, ModuleKind, SourceLoader,
+    };
+
+    struct EmptyLoader;
+
+    impl SourceLoader for EmptyLoader {
+        fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+            Ok(LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: None,
+                source: "".into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            })
+        }
+    }
+
+    fn echo() -> Arc<ModuleResolver> {
+        Arc::new(FunctionModuleResolver::new(
+            "function",
+            Box::new(|specifier, _| {
+                Ok(ModuleSourceData {
+                    origin_url: specifier.to_string(),
+                    source_loader: Box::new(EmptyLoader),
+                })
+            }),
+        ))
+    }
+
+    fn resolve(resolver: &CdnResolver, specifier: &str) -> FlyResult<String> {
+        resolver
+            .resolve_module(specifier, None)
+            .map(|data| data.origin_url)
+    }
+
+    #[test]
+    fn test_parse_package_specifiers() {
+        assert_eq!(
+            Some(PackageSpecifier {
+                name: "@ourco/ui",
+                version: "1.0.0",
+                path: Some("button.js")
+            }),
+            PackageSpecifier::parse("@ourco/ui@1.0.0/button.js")
+        );
+        assert_eq!(None, PackageSpecifier::parse("react"));
+        assert_eq!(None, PackageSpecifier::parse("React@18.2.0"));
+        assert_eq!(None, PackageSpecifier::parse("@ourco@1.0.0"));
+        assert_eq!(None, PackageSpecifier::parse("react@"));
+    }
+
+    #[test]
+    fn test_templates_exact_versions() {
+        let resolver = CdnResolver::new(DEFAULT_CDN_TEMPLATE, echo()).unwrap();
+        assert_eq!(
+            "https://esm.sh/react@18.2.0",
+            resolve(&resolver, "react@18.2.0").unwrap()
+        );
+        assert_eq!(
+            "https://esm.sh/react-dom@18.2.0/client",
+            resolve(&resolver, "react-dom@18.2.0/client").unwrap()
+        );
+        let unpkg =
+            CdnResolver::new("https://unpkg.com/{pkg}@{version}/{path}?module", echo()).unwrap();
+        assert_eq!(
+            "https://unpkg.com/@ourco/ui@1.0.0-beta.1/index.js?module",
+            resolve(&unpkg, "@ourco/ui@1.0.0-beta.1/index.js").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_floating_versions_are_rejected() {
+        let resolver = CdnResolver::new(DEFAULT_CDN_TEMPLATE, echo()).unwrap();
+        for specifier in &["react@latest", "react@^18.2.0", "react@18", "react@18.x.0"] {
+            assert!(resolver.handles_bare_specifier(specifier));
+            let err = resolve(&resolver, specifier).err().unwrap();
+            assert!(format!("{}", err).contains("exact version"), specifier);
+        }
+        assert!(!resolver.handles_bare_specifier("react"));
+    }
+
+    #[test]
+    fn test_disabled_and_invalid_templates() {
+        let resolver = CdnResolver::disabled();
+        assert!(!resolver.handles_bare_specifier("react@18.2.0"));
+        assert!(resolve(&resolver, "react@18.2.0").is_err());
+        assert!(CdnResolver::new("https://esm.sh/{pkg}", echo()).is_err());
+    }
+}
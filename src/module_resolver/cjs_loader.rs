@@ -0,0 +1,346 @@
+use crate::errors::*;
+
+use super::data_module::js_string_literal;
+use super::{LoadedSourceCode, SourceLoader};
+
+/**
+ * Loads CommonJS scripts as ES modules. A source with no `import` or `export` statement that
+ * uses `module.exports`, `exports` or `require` is run inside a function given its own
+ * `module`, `exports` and `require`, and `module.exports` becomes the default export. Every
+ * `require` of a relative path given as a string literal is imported statically, up front;
+ * any other `require` throws when it's called. Everything else passes through untouched.
+ *
+ * The wrapper goes on the source's first line, so line numbers, and the source map, still
+ * match. The wrapped code runs in strict mode, as all module code does.
+ */
+pub struct CjsWrappingLoader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+}
+
+impl CjsWrappingLoader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+        }
+    }
+}
+
+impl SourceLoader for CjsWrappingLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let raw = self.inner.load_source()?;
+        if raw.is_wasm() {
+            return Ok(raw);
+        }
+        let wrapped = match commonjs_requires(&raw.source) {
+            Some(required) => {
+                debug!("wrapping CommonJS module {}", self.origin_url);
+                wrap_commonjs(&self.origin_url, &raw.source, &required)
+            }
+            None => return Ok(raw),
+        };
+        Ok(LoadedSourceCode {
+            source: wrapped.into(),
+            ..raw
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Token<'a> {
+    Ident(&'a str),
+    /** A string literal's contents, `None` if it has escapes. */
+    Str(Option<&'a str>),
+    Punct(u8),
+    /** Numbers, templates and regular expressions. */
+    Other,
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+/** Whether a `/` after `prev` starts a regular expression rather than dividing. */
+fn regex_allowed(prev: Option<&Token>) -> bool {
+    match prev {
+        None => true,
+        Some(Token::Ident(word)) => [
+            "return",
+            "typeof",
+            "instanceof",
+            "in",
+            "of",
+            "new",
+            "delete",
+            "void",
+            "throw",
+            "case",
+            "do",
+            "else",
+            "yield",
+            "await",
+        ]
+        .contains(word),
+        Some(Token::Punct(b')')) | Some(Token::Punct(b']')) | Some(Token::Punct(b'}')) => false,
+        Some(Token::Punct(_)) => true,
+        Some(_) => false,
+    }
+}
+
+/**
+ * Just enough of a JavaScript tokenizer to tell statements from what's in comments, strings,
+ * templates and regular expressions.
+ */
+pub(super) fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = match source[i + 2..].find("*/") {
+                    Some(end) => i + 2 + end + 2,
+                    None => bytes.len(),
+                };
+            }
+            b'/' if regex_allowed(tokens.last()) => {
+                i += 1;
+                let mut in_class = false;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'\\' => i += 1,
+                        b'[' => in_class = true,
+                        b']' => in_class = false,
+                        b'/' if !in_class => break,
+                        b'\n' => break,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+                while i < bytes.len() && is_ident_byte(bytes[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Other);
+            }
+            b'"' | b'\'' | b'`' => {
+                let start = i + 1;
+                let mut escaped = false;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b {
+                    if bytes[i] == b'\\' {
+                        escaped = true;
+                        i += 1;
+                    } else if bytes[i] == b'\n' && b != b'`' {
+                        break;
+                    }
+                    i += 1;
+                }
+                let end = i.min(bytes.len());
+                i += 1;
+                tokens.push(match b {
+                    b'`' => Token::Other,
+                    _ if escaped || end == bytes.len() || bytes[end] != b => Token::Str(None),
+                    _ => Token::Str(Some(&source[start..end])),
+                });
+            }
+            b if b.is_ascii_digit() => {
+                while i < bytes.len() && (is_ident_byte(bytes[i]) || bytes[i] == b'.') {
+                    i += 1;
+                }
+                tokens.push(Token::Other);
+            }
+            b if is_ident_byte(b) => {
+                let start = i;
+                while i < bytes.len() && is_ident_byte(bytes[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(&source[start..i]));
+            }
+            b if b.is_ascii_whitespace() => i += 1,
+            b => {
+                tokens.push(Token::Punct(b));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/**
+ * The string literals `source` passes to `require`, if it's a CommonJS script: one without
+ * `import` or `export` statements, `import.meta` or any, that uses `module.exports`,
+ * `exports` or `require`. `None` for anything else, ES modules above all.
+ */
+pub fn commonjs_requires(source: &str) -> Option<Vec<&str>> {
+    let tokens = tokenize(source);
+    let at = |i: usize| tokens.get(i);
+    let mut is_commonjs = false;
+    let mut required = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        let word = match token {
+            Token::Ident(word) => *word,
+            _ => continue,
+        };
+        if i > 0 && at(i - 1) == Some(&Token::Punct(b'.')) {
+            continue;
+        }
+        match word {
+            "import" if at(i + 1) != Some(&Token::Punct(b'(')) => return None,
+            "export" => match at(i + 1) {
+                Some(Token::Ident(_)) | Some(Token::Punct(b'{')) | Some(Token::Punct(b'*')) => {
+                    return None
+                }
+                _ => {}
+            },
+            "module"
+                if at(i + 1) == Some(&Token::Punct(b'.'))
+                    && at(i + 2) == Some(&Token::Ident("exports")) =>
+            {
+                is_commonjs = true
+            }
+            "exports" => match at(i + 1) {
+                Some(Token::Punct(b'.')) | Some(Token::Punct(b'[')) | Some(Token::Punct(b'=')) => {
+                    is_commonjs = true
+                }
+                _ => {}
+            },
+            "require" if at(i + 1) == Some(&Token::Punct(b'(')) => {
+                is_commonjs = true;
+                if let (Some(Token::Str(Some(specifier))), Some(Token::Punct(b')'))) =
+                    (at(i + 2), at(i + 3))
+                {
+                    if !required.contains(specifier) {
+                        required.push(*specifier);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if is_commonjs {
+        Some(required)
+    } else {
+        None
+    }
+}
+
+fn is_relative(specifier: &str) -> bool {
+    specifier.starts_with("./") || specifier.starts_with("../")
+}
+
+/**
+ * `source`, a CommonJS script at `origin_url`, as an ES module importing the relative ones of
+ * the specifiers it `require`s.
+ */
+pub fn wrap_commonjs(origin_url: &str, source: &str, required: &[&str]) -> String {
+    let imported: Vec<&str> = required
+        .iter()
+        .cloned()
+        .filter(|specifier| is_relative(specifier))
+        .collect();
+    let mut module = String::new();
+    for (i, specifier) in imported.iter().enumerate() {
+        module.push_str(&format!(
+            "import * as __cjs_dep{} from {};",
+            i,
+            js_string_literal(specifier)
+        ));
+    }
+    module.push_str("const __cjs_deps = {");
+    for (i, specifier) in imported.iter().enumerate() {
+        module.push_str(&format!(
+            "{}: __cjs_dep{},",
+            js_string_literal(specifier),
+            i
+        ));
+    }
+    module.push_str("};");
+    module.push_str(&format!(
+        concat!(
+            "function __cjs_require(specifier) {{",
+            "if (!Object.prototype.hasOwnProperty.call(__cjs_deps, specifier)) {{",
+            "throw new Error(\"require(\" + JSON.stringify(specifier) + \") from \" + {} + ",
+            "\" is unsupported: only relative paths given as string literals can be required\");",
+            "}}",
+            "const namespace = __cjs_deps[specifier];",
+            "const names = Object.keys(namespace);",
+            "return names.length === 1 && names[0] === \"default\" ? namespace.default : namespace;",
+            "}}",
+        ),
+        js_string_literal(origin_url)
+    ));
+    module.push_str("const __cjs_module = { exports: {} };");
+    module.push_str("(function (exports, require, module) {");
+    // the runtime only strips a shebang that starts the source
+    if source.starts_with("#!") {
+        module.push_str("//");
+    }
+    module.push_str(source);
+    module.push_str(
+        "\n}).call(__cjs_module.exports, __cjs_module.exports, __cjs_require, __cjs_module);\n",
+    );
+    module.push_str("export default __cjs_module.exports;\n");
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_commonjs_conservatively() {
+        assert_eq!(
+            Some(vec!["./a", "lodash"]),
+            commonjs_requires(
+                "#!/usr/bin/env node\nconst a = require('./a');\nconst _ = require(\"lodash\");\nrequire('./a');\nmodule.exports = { a };"
+            )
+        );
+        assert_eq!(
+            Some(vec![]),
+            commonjs_requires("exports.answer = 42; // import x from 'y'")
+        );
+        assert_eq!(
+            Some(vec![]),
+            commonjs_requires("const load = () => import('./lazy.js'); module.exports = load;")
+        );
+        // real ES modules, whatever they mention
+        for esm in &[
+            "import a from './a';\nconst b = require('./b');",
+            "export default function () { return module.exports; }",
+            "export { x };\nexports.y = 1;",
+            "console.log(import.meta.url, typeof require);",
+        ] {
+            assert_eq!(None, commonjs_requires(esm), "{}", esm);
+        }
+        // no CommonJS outside comments, strings, templates and regular expressions
+        for script in &[
+            "/* module.exports = 1 */ const s = 'require(\"x\")';",
+            "const t = `exports.x = ${1}`; const r = /module.exports/g;",
+            "const o = { exports: 1 }; o.require('x'); a.module.exports;",
+        ] {
+            assert_eq!(None, commonjs_requires(script), "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_wraps_on_the_first_line() {
+        let source = "#!/usr/bin/env node\nconst b = require('./b');\nmodule.exports = b + 1;";
+        let required = commonjs_requires(source).unwrap();
+        let module = wrap_commonjs("file:///app/a.js", source, &required);
+        let lines: Vec<&str> = module.lines().collect();
+        assert!(lines[0].starts_with("import * as __cjs_dep0 from \"./b\";"));
+        assert!(lines[0].ends_with("(function (exports, require, module) {//#!/usr/bin/env node"));
+        assert_eq!("const b = require('./b');", lines[1]);
+        assert_eq!("module.exports = b + 1;", lines[2]);
+        assert_eq!("export default __cjs_module.exports;", lines[4]);
+        assert!(module.contains("\"file:///app/a.js\""));
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use sha2::{Digest, Sha256};
+
+/** Default ceiling for a code cache: 64MB of V8 code caches. */
+pub const DEFAULT_CODE_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+struct CodeCacheEntry {
+    source_hash: Vec<u8>,
+    bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_origin: HashMap<String, CodeCacheEntry>,
+    bytes: usize,
+}
+
+/**
+ * The code caches V8 made compiling modules, by origin url, each for the exact source it was
+ * compiled from. A module whose source has changed since gets none, and the stale cache is
+ * dropped. Clones share the caches, so every runtime a manager is shared with skips parsing
+ * what the first one compiled.
+ */
+#[derive(Clone)]
+pub struct CodeCache {
+    entries: Arc<RwLock<Entries>>,
+    max_bytes: usize,
+}
+
+/** How big a `CodeCache` is. */
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CodeCacheStats {
+    pub modules: usize,
+    pub bytes: usize,
+}
+
+fn source_hash(source: &str) -> Vec<u8> {
+    Sha256::digest(source.as_bytes()).to_vec()
+}
+
+impl CodeCache {
+    /** Caches that would take it over `max_bytes` aren't kept. */
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(Entries::default())),
+            max_bytes,
+        }
+    }
+
+    /** The code cache of `origin_url`, if there's one for exactly `source`. */
+    pub fn get(&self, origin_url: &str, source: &str) -> Option<Vec<u8>> {
+        {
+            let entries = self.entries.read().unwrap();
+            let entry = entries.by_origin.get(origin_url)?;
+            if entry.source_hash == source_hash(source) {
+                return Some(entry.bytes.clone());
+            }
+        }
+        debug!("dropping the stale code cache of {}", origin_url);
+        self.remove(origin_url);
+        None
+    }
+
+    /** Keeps `bytes`, V8's code cache of `source`, for `origin_url`. */
+    pub fn insert(&self, origin_url: &str, source: &str, bytes: Vec<u8>) {
+        let mut entries = self.entries.write().unwrap();
+        let replaced = entries
+            .by_origin
+            .get(origin_url)
+            .map_or(0, |entry| entry.bytes.len());
+        if entries.bytes - replaced + bytes.len() > self.max_bytes {
+            debug!(
+                "not keeping the code cache of {}: the cache is full",
+                origin_url
+            );
+            return;
+        }
+        entries.bytes = entries.bytes - replaced + bytes.len();
+        entries.by_origin.insert(
+            origin_url.to_string(),
+            CodeCacheEntry {
+                source_hash: source_hash(source),
+                bytes,
+            },
+        );
+    }
+
+    pub fn remove(&self, origin_url: &str) -> bool {
+        let mut entries = self.entries.write().unwrap();
+        match entries.by_origin.remove(origin_url) {
+            Some(entry) => {
+                entries.bytes -= entry.bytes.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stats(&self) -> CodeCacheStats {
+        let entries = self.entries.read().unwrap();
+        CodeCacheStats {
+            modules: entries.by_origin.len(),
+            bytes: entries.bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caches_are_for_the_exact_source() {
+        let cache = CodeCache::new(10);
+        cache.insert("file:///app/main.ts", "export default 1;", vec![1, 2, 3]);
+        assert_eq!(
+            Some(vec![1, 2, 3]),
+            cache.get("file:///app/main.ts", "export default 1;")
+        );
+        assert_eq!(None, cache.get("file:///app/other.ts", "export default 1;"));
+
+        // edited since: the stale cache goes
+        assert_eq!(None, cache.get("file:///app/main.ts", "export default 2;"));
+        assert_eq!(CodeCacheStats::default(), cache.stats());
+    }
+
+    #[test]
+    fn test_keeps_to_its_size() {
+        let cache = CodeCache::new(10);
+        cache.insert("file:///app/a.ts", "a", vec![0; 6]);
+        cache.insert("file:///app/b.ts", "b", vec![0; 6]);
+        assert_eq!(None, cache.get("file:///app/b.ts", "b"));
+        // replacing counts the new bytes instead of the old
+        cache.insert("file:///app/a.ts", "a2", vec![0; 9]);
+        assert_eq!(
+            CodeCacheStats {
+                modules: 1,
+                bytes: 9
+            },
+            cache.stats()
+        );
+    }
+}
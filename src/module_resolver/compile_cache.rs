@@ -0,0 +1,503 @@
+use crate::errors::*;
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::ToSql;
+use rusqlite::NO_PARAMS;
+use sha2::{Digest, Sha256};
+
+use super::{
+    source_flavor, transpile_source, transpile_typescript, LoadedSourceCode, ModuleKind, TRANSPILES,
+};
+
+/**
+ * Bumped whenever the table changes, or the compiler's output does for the same source;
+ * databases with another version are rebuilt.
+ */
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS compiled_modules (
+    origin_url TEXT NOT NULL,
+    source_hash TEXT NOT NULL,
+    compiled_source TEXT NOT NULL,
+    source_map TEXT,
+    created_at INTEGER NOT NULL,
+    last_used INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (origin_url, source_hash)
+);
+CREATE INDEX IF NOT EXISTS compiled_modules_last_used ON compiled_modules (last_used);";
+
+/** How much compiled output a cache keeps unless told otherwise. */
+pub const DEFAULT_COMPILE_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+type Pool = r2d2::Pool<SqliteConnectionManager>;
+
+fn cache_error(db_path: &Path, e: impl std::fmt::Display) -> FlyError {
+    FlyError::from(format!("compile cache {}: {}", db_path.display(), e))
+}
+
+fn source_hash(source: &str, tsx: bool) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(if tsx { b"tsx:" } else { b"ts: " });
+    hasher.input(source.as_bytes());
+    hasher
+        .result()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/**
+ * TypeScript compiled ahead of now, kept in a SQLite database so it outlives the process.
+ * Entries are keyed by origin url and a hash of the source as loaded, so an edited module is
+ * compiled again, and the least recently used are evicted once their compiled source and
+ * source maps take more than `max_bytes`.
+ *
+ * A database that's corrupt or was written by another version is thrown away and rebuilt
+ * when opened. Failing to read or write it later only costs the compile it would have saved.
+ */
+#[derive(Clone)]
+pub struct SqliteModuleCache {
+    db_path: PathBuf,
+    max_bytes: usize,
+    pool: Pool,
+}
+
+impl SqliteModuleCache {
+    pub fn open(db_path: &Path, max_bytes: usize) -> FlyResult<Self> {
+        let cache = match Self::open_existing(db_path, max_bytes) {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("rebuilding {}", e);
+                std::fs::remove_file(db_path).map_err(|e| cache_error(db_path, e))?;
+                Self::open_existing(db_path, max_bytes)?
+            }
+        };
+        cache.evict()?;
+        Ok(cache)
+    }
+
+    fn open_existing(db_path: &Path, max_bytes: usize) -> FlyResult<Self> {
+        let pool = r2d2::Pool::builder()
+            .max_size(4)
+            .build(SqliteConnectionManager::file(db_path))
+            .map_err(|e| cache_error(db_path, e))?;
+        {
+            let conn = pool.get().map_err(|e| cache_error(db_path, e))?;
+            let check: String = conn
+                .query_row("PRAGMA quick_check", NO_PARAMS, |row| row.get_checked(0))
+                .and_then(|check| check)
+                .map_err(|e| cache_error(db_path, e))?;
+            if check != "ok" {
+                return Err(cache_error(db_path, format!("corrupt: {}", check)));
+            }
+            let version: i64 = conn
+                .query_row("PRAGMA user_version", NO_PARAMS, |row| row.get_checked(0))
+                .and_then(|version| version)
+                .map_err(|e| cache_error(db_path, e))?;
+            let tables: i64 = conn
+                .query_row(
+                    "SELECT count(*) FROM sqlite_master WHERE type = 'table'",
+                    NO_PARAMS,
+                    |row| row.get_checked(0),
+                )
+                .and_then(|tables| tables)
+                .map_err(|e| cache_error(db_path, e))?;
+            if tables > 0 && version != SCHEMA_VERSION {
+                return Err(cache_error(
+                    db_path,
+                    format!(
+                        "schema version {} where {} was expected",
+                        version, SCHEMA_VERSION
+                    ),
+                ));
+            }
+            conn.execute_batch(SCHEMA)
+                .and_then(|_| {
+                    conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+                })
+                .map_err(|e| cache_error(db_path, e))?;
+        }
+        Ok(Self {
+            db_path: db_path.to_path_buf(),
+            max_bytes,
+            pool,
+        })
+    }
+
+    /** The compiled source and source map cached for `source_hash` of `origin_url`. */
+    fn get(&self, origin_url: &str, source_hash: &str) -> FlyResult<Option<(String, String)>> {
+        let conn = self.pool.get().map_err(|e| cache_error(&self.db_path, e))?;
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT compiled_source, source_map FROM compiled_modules
+                WHERE origin_url = ?1 AND source_hash = ?2",
+            )
+            .map_err(|e| cache_error(&self.db_path, e))?;
+        let mut rows = stmt
+            .query(&[&origin_url as &ToSql, &source_hash])
+            .map_err(|e| cache_error(&self.db_path, e))?;
+        let row = match rows.next() {
+            Some(row) => row.map_err(|e| cache_error(&self.db_path, e))?,
+            None => return Ok(None),
+        };
+        let compiled: String = row
+            .get_checked(0)
+            .map_err(|e| cache_error(&self.db_path, e))?;
+        let source_map: String = row
+            .get_checked(1)
+            .map_err(|e| cache_error(&self.db_path, e))?;
+        self.tick(&conn)
+            .and_then(|tick| {
+                conn.execute(
+                    "UPDATE compiled_modules SET last_used = ?3
+                    WHERE origin_url = ?1 AND source_hash = ?2",
+                    &[&origin_url as &ToSql, &source_hash, &tick],
+                )
+            })
+            .map_err(|e| cache_error(&self.db_path, e))?;
+        Ok(Some((compiled, source_map)))
+    }
+
+    fn insert(
+        &self,
+        origin_url: &str,
+        source_hash: &str,
+        compiled: &str,
+        source_map: &str,
+    ) -> FlyResult<()> {
+        let conn = self.pool.get().map_err(|e| cache_error(&self.db_path, e))?;
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let size = (compiled.len() + source_map.len()) as i64;
+        // older compiles of the module won't be asked for again
+        conn.execute(
+            "DELETE FROM compiled_modules WHERE origin_url = ?1",
+            &[&origin_url as &ToSql],
+        )
+        .and_then(|_| {
+            conn.execute(
+                "INSERT INTO compiled_modules VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                &[
+                    &origin_url as &ToSql,
+                    &source_hash,
+                    &compiled,
+                    &source_map,
+                    &created_at,
+                    &self.tick(&conn)?,
+                    &size,
+                ],
+            )
+        })
+        .map_err(|e| cache_error(&self.db_path, e))?;
+        drop(conn);
+        self.evict()
+    }
+
+    /** The next `last_used`, after every entry's; a counter, so equal clocks keep order. */
+    fn tick(&self, conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+        conn.query_row(
+            "SELECT COALESCE(MAX(last_used), 0) + 1 FROM compiled_modules",
+            NO_PARAMS,
+            |row| row.get_checked(0),
+        )
+        .and_then(|tick| tick)
+    }
+
+    /** Drops the least recently used entries until the rest fit in `max_bytes`. */
+    fn evict(&self) -> FlyResult<()> {
+        let conn = self.pool.get().map_err(|e| cache_error(&self.db_path, e))?;
+        let total: i64 = conn
+            .query_row(
+                "SELECT COALESCE(SUM(size), 0) FROM compiled_modules",
+                NO_PARAMS,
+                |row| row.get_checked(0),
+            )
+            .and_then(|total| total)
+            .map_err(|e| cache_error(&self.db_path, e))?;
+        let mut excess = total - self.max_bytes as i64;
+        if excess <= 0 {
+            return Ok(());
+        }
+        let mut stmt = conn
+            .prepare("SELECT rowid, size FROM compiled_modules ORDER BY last_used")
+            .map_err(|e| cache_error(&self.db_path, e))?;
+        let entries: Vec<(i64, i64)> = stmt
+            .query_map(NO_PARAMS, |row| (row.get(0), row.get(1)))
+            .map_err(|e| cache_error(&self.db_path, e))?
+            .filter_map(Result::ok)
+            .collect();
+        for (rowid, size) in entries {
+            if excess <= 0 {
+                break;
+            }
+            conn.execute(
+                "DELETE FROM compiled_modules WHERE rowid = ?1",
+                &[&rowid as &ToSql],
+            )
+            .map_err(|e| cache_error(&self.db_path, e))?;
+            excess -= size;
+        }
+        Ok(())
+    }
+
+    /**
+     * `source` compiled like `transpile_source` would, from the cache when it was compiled
+     * before. If the cache can't be read or written, the source is compiled all the same.
+     */
+    pub fn transpile(
+        &self,
+        origin_url: &str,
+        source: LoadedSourceCode,
+    ) -> FlyResult<LoadedSourceCode> {
+        if !TRANSPILES {
+            return transpile_source(origin_url, source);
+        }
+        let tsx = match source_flavor(origin_url, &source) {
+            Some(tsx) => tsx,
+            None => return Ok(source),
+        };
+        let hash = source_hash(&source.source, tsx);
+        let cached = self.get(origin_url, &hash).unwrap_or_else(|e| {
+            warn!("{}", e);
+            None
+        });
+        let (code, source_map) = match cached {
+            Some(compiled) => compiled,
+            None => {
+                let (code, source_map) = transpile_typescript(origin_url, &source.source, tsx)?;
+                if let Err(e) = self.insert(origin_url, &hash, &code, &source_map) {
+                    warn!("{}", e);
+                }
+                (code, source_map)
+            }
+        };
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::TypeScript,
+            source: code.into(),
+            source_map: Some(source_map.into()),
+            ..source
+        })
+    }
+}
+
+#[cfg(all(test, feature = "transpile"))]
+mod tests {
+    use super::*;
+
+    fn typescript(source: &str) -> LoadedSourceCode {
+        LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: None,
+            source: source.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        }
+    }
+
+    fn cached_urls(cache: &SqliteModuleCache) -> Vec<String> {
+        let conn = cache.pool.get().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT origin_url FROM compiled_modules ORDER BY origin_url")
+            .unwrap();
+        let urls = stmt
+            .query_map(NO_PARAMS, |row| row.get(0))
+            .unwrap()
+            .filter_map(Result::ok)
+            .collect();
+        urls
+    }
+
+    #[test]
+    fn test_reuses_compiles_across_opens() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("compiled.db");
+        let cache = SqliteModuleCache::open(&db, DEFAULT_COMPILE_CACHE_BYTES).unwrap();
+        let compiled = cache
+            .transpile(
+                "file:///app/a.ts",
+                typescript("export const a: number = 1;"),
+            )
+            .unwrap();
+        assert_eq!("export const a = 1;\n", &*compiled.source);
+        drop(cache);
+
+        // what's returned is what was stored, not compiled again
+        let cache = SqliteModuleCache::open(&db, DEFAULT_COMPILE_CACHE_BYTES).unwrap();
+        cache
+            .pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE compiled_modules SET compiled_source = 'cached'",
+                NO_PARAMS,
+            )
+            .unwrap();
+        let compiled = cache
+            .transpile(
+                "file:///app/a.ts",
+                typescript("export const a: number = 1;"),
+            )
+            .unwrap();
+        assert_eq!("cached", &*compiled.source);
+        assert!(compiled.source_map.is_some());
+        // an edit misses
+        let compiled = cache
+            .transpile(
+                "file:///app/a.ts",
+                typescript("export const a: number = 2;"),
+            )
+            .unwrap();
+        assert_eq!("export const a = 2;\n", &*compiled.source);
+        assert_eq!(vec!["file:///app/a.ts"], cached_urls(&cache));
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let (code, map) =
+            transpile_typescript("file:///app/a.ts", "export let x = 1;", false).unwrap();
+        // room for two modules, not three
+        let max_bytes = (code.len() + map.len()) * 2 + 1;
+        let cache = SqliteModuleCache::open(&dir.path().join("compiled.db"), max_bytes).unwrap();
+        for name in &["a", "b"] {
+            let url = format!("file:///app/{}.ts", name);
+            cache
+                .transpile(&url, typescript("export let x = 1;"))
+                .unwrap();
+        }
+        // a is used again, so b is the one evicted
+        cache
+            .transpile("file:///app/a.ts", typescript("export let x = 1;"))
+            .unwrap();
+        cache
+            .transpile("file:///app/c.ts", typescript("export let x = 1;"))
+            .unwrap();
+        assert_eq!(
+            vec!["file:///app/a.ts", "file:///app/c.ts"],
+            cached_urls(&cache)
+        );
+    }
+
+    #[test]
+    fn test_rebuilds_unusable_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let garbage = dir.path().join("garbage.db");
+        std::fs::write(&garbage, vec![7u8; 8192]).unwrap();
+        let stale = dir.path().join("stale.db");
+        rusqlite::Connection::open(&stale)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE compiled_modules (origin_url TEXT); PRAGMA user_version = 0;",
+            )
+            .unwrap();
+        for db in &[garbage, stale] {
+            let cache = SqliteModuleCache::open(db, DEFAULT_COMPILE_CACHE_BYTES).unwrap();
+            cache
+                .transpile("file:///app/a.ts", typescript("export const a = 1;"))
+                .unwrap();
+            assert_eq!(vec!["file:///app/a.ts"], cached_urls(&cache));
+        }
+    }
+
+    /** About 12KB of TypeScript: an enum, an interface and functions typed with them. */
+    fn typed_module(i: usize) -> String {
+        let mut source = format!(
+            "export enum Color{0} {{ Red, Green, Blue }}\n\
+             export interface Point{0} {{ x: number; y: number; color?: Color{0} }}\n",
+            i
+        );
+        for j in 0..60 {
+            source.push_str(&format!(
+                "export function distance{0}_{1}(a: Point{0}, b: Point{0}): number {{\n\
+                 \x20   const dx: number = a.x - b.x;\n\
+                 \x20   const dy: number = a.y - b.y;\n\
+                 \x20   return Math.sqrt(dx * dx + dy * dy) + {1} as number;\n\
+                 }}\n",
+                i, j
+            ));
+        }
+        source
+    }
+
+    /**
+     * How long a manager takes to load an app of 50 TypeScript modules, compiling each one,
+     * filling an empty cache and then, restarted, from the cache:
+     * `cargo test --release bench_cold_starts_with_compile_cache -- --ignored --nocapture`
+     */
+    #[test]
+    #[ignore]
+    fn bench_cold_starts_with_compile_cache() {
+        use super::super::{
+            LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager,
+            StandardModuleResolverManager,
+        };
+        use std::time::{Duration, Instant};
+
+        let app = tempfile::tempdir().unwrap();
+        let modules = 50;
+        let mut main = String::new();
+        for i in 0..modules {
+            let source = typed_module(i);
+            std::fs::write(app.path().join(format!("part{}.ts", i)), source).unwrap();
+            main.push_str(&format!("export * from './part{}.ts';\n", i));
+        }
+        std::fs::write(app.path().join("main.ts"), main).unwrap();
+        let app_url = url::Url::from_directory_path(app.path())
+            .unwrap()
+            .into_string();
+        let db = app.path().join("compiled.db");
+
+        // a manager as a runtime starting up has it, with nothing loaded yet
+        let start = |cache: Option<SqliteModuleCache>| {
+            let started = Instant::now();
+            let resolver = LocalDiskModuleResolver::new(Some(app_url.clone())).unwrap();
+            let mut manager = StandardModuleResolverManager::new(
+                vec![Box::new(resolver) as Box<ModuleResolver>],
+                Some(app_url.clone()),
+            )
+            .with_transpiling(true);
+            if let Some(cache) = cache {
+                manager = manager.with_compile_cache(cache);
+            }
+            let summary = manager.preload("./main.ts").unwrap();
+            assert_eq!(modules + 1, summary.modules);
+            assert!(summary.failures.is_empty(), "{:?}", summary.failures);
+            started.elapsed()
+        };
+        let open = || SqliteModuleCache::open(&db, DEFAULT_COMPILE_CACHE_BYTES).unwrap();
+
+        let runs = 5;
+        let average = |run: &Fn() -> Duration| {
+            (0..runs)
+                .map(|_| run())
+                .fold(Duration::from_secs(0), |a, b| a + b)
+                / runs
+        };
+        let uncached = average(&|| start(None));
+        let filling = average(&|| {
+            std::fs::remove_file(&db).ok();
+            start(Some(open()))
+        });
+        let cached = average(&|| start(Some(open())));
+        assert_eq!(modules + 1, cached_urls(&open()).len());
+
+        println!(
+            "loading {} TypeScript modules of about {}KB: {:?} compiling, {:?} filling an empty cache, {:?} from the cache",
+            modules,
+            typed_module(0).len() / 1024,
+            uncached,
+            filling,
+            cached
+        );
+        assert!(cached < uncached);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::errors::*;
+use crate::metrics::*;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+use super::{LoadedModule, LoadedSourceCode, ModuleKind};
+
+/**
+ * What a runtime remembers about a module it compiled, keyed by V8's identity hash.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledModuleInfo {
+    pub origin_url: String,
+    pub kind: ModuleKind,
+    pub source_map: Option<Arc<str>>,
+    pub content_hash: u64,
+    pub passthrough: bool,
+    /** Whether the source is still held in memory. */
+    pub resident: bool,
+}
+
+struct Entry {
+    info: CompiledModuleInfo,
+    source: Option<Arc<str>>,
+}
+
+/**
+ * Registry of modules compiled into an isolate. With `release_sources`, a module's source
+ * is dropped once it's compiled, keeping only its metadata; the rare reader that needs the
+ * source again (error frames, source map regeneration) reloads it through `source`, which
+ * refuses a reload whose content no longer matches what was compiled.
+ */
+pub struct CompiledModuleRegistry {
+    release_sources: bool,
+    modules: RwLock<HashMap<i32, Entry>>,
+}
+
+fn source_hash(source: &LoadedSourceCode) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.is_wasm().hash(&mut hasher);
+    source.source.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl CompiledModuleRegistry {
+    pub fn new(release_sources: bool) -> Self {
+        Self {
+            release_sources,
+            modules: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn releases_sources(&self) -> bool {
+        self.release_sources
+    }
+
+    pub fn insert(&self, hash: i32, module: LoadedModule) {
+        let mut modules = self.modules.write().unwrap();
+        if modules.contains_key(&hash) {
+            error!("Attempted to overwrite entry in module metadata cache.");
+            return;
+        }
+        let info = CompiledModuleInfo {
+            content_hash: source_hash(&module.loaded_source),
+            origin_url: module.origin_url,
+            kind: module.loaded_source.kind,
+            source_map: module.loaded_source.source_map,
+            passthrough: module.passthrough,
+            resident: true,
+        };
+        modules.insert(
+            hash,
+            Entry {
+                info,
+                source: Some(module.loaded_source.source),
+            },
+        );
+    }
+
+    /**
+     * Called once `hash` compiled successfully; drops its source in release mode.
+     */
+    pub fn compiled(&self, hash: i32) {
+        if !self.release_sources {
+            return;
+        }
+        let mut modules = self.modules.write().unwrap();
+        if let Some(entry) = modules.get_mut(&hash) {
+            if let Some(source) = entry.source.take() {
+                entry.info.resident = false;
+                MODULE_SOURCES_RELEASED_BYTES.inc_by(source.len() as i64);
+            }
+        }
+    }
+
+    pub fn info(&self, hash: i32) -> Option<CompiledModuleInfo> {
+        self.modules
+            .read()
+            .unwrap()
+            .get(&hash)
+            .map(|entry| entry.info.clone())
+    }
+
+    /**
+     * The source `hash` was compiled from, reloading it with `reload` (given the origin url)
+     * if it was released. Reloaded sources aren't kept resident again.
+     */
+    pub fn source<F>(&self, hash: i32, reload: F) -> FlyResult<Arc<str>>
+    where
+        F: FnOnce(&str) -> FlyResult<LoadedSourceCode>,
+    {
+        let info = {
+            let modules = self.modules.read().unwrap();
+            let entry = modules.get(&hash).ok_or_else(|| {
+                FlyError::from(format!("no compiled module with identity hash {}", hash))
+            })?;
+            if let Some(ref source) = entry.source {
+                return Ok(source.clone());
+            }
+            entry.info.clone()
+        };
+        debug!("reloading released source of {}", info.origin_url);
+        MODULE_SOURCE_RELOADS.inc();
+        let reloaded = reload(&info.origin_url)?;
+        if source_hash(&reloaded) != info.content_hash {
+            return Err(FlyError::from(format!(
+                "source of {} changed since it was compiled",
+                info.origin_url
+            )));
+        }
+        Ok(reloaded.source)
+    }
+
+    /**
+     * The identity hash a module was compiled under, looked up by its origin url.
+     */
+    pub fn hash_of(&self, origin_url: &str) -> Option<i32> {
+        self.modules
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, entry)| entry.info.origin_url == origin_url)
+            .map(|(hash, _)| *hash)
+    }
+
+    /** The origin urls of every module compiled. */
+    pub fn origin_urls(&self) -> Vec<String> {
+        self.modules
+            .read()
+            .unwrap()
+            .values()
+            .map(|entry| entry.info.origin_url.clone())
+            .collect()
+    }
+
+    pub fn invalidate(&self, origin_url: &str) {
+        match self.modules.write() {
+            Ok(mut modules) => modules.retain(|_, entry| entry.info.origin_url != origin_url),
+            Err(_) => error!("error acquiring lock to invalidate module metadata"),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(origin_url: &str, code: &str) -> LoadedModule {
+        LoadedModule {
+            origin_url: origin_url.to_string(),
+            loaded_source: LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: Some("{\"version\":3}".into()),
+                source: code.into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            },
+            passthrough: false,
+            resolver: None,
+        }
+    }
+
+    #[test]
+    fn test_keeps_sources_by_default() {
+        let registry = CompiledModuleRegistry::new(false);
+        registry.insert(1, module("file:///app/index.js", "export default 1;"));
+        registry.compiled(1);
+        assert!(registry.info(1).unwrap().resident);
+        let source = registry
+            .source(1, |_| panic!("reloaded a resident source"))
+            .unwrap();
+        assert_eq!("export default 1;", &*source);
+    }
+
+    #[test]
+    fn test_releases_and_reloads_sources() {
+        let registry = CompiledModuleRegistry::new(true);
+        registry.insert(1, module("file:///app/index.js", "export default 1;"));
+        registry.compiled(1);
+        let info = registry.info(1).unwrap();
+        assert!(!info.resident);
+        assert_eq!(Some(1), registry.hash_of("file:///app/index.js"));
+        assert_eq!("file:///app/index.js", info.origin_url);
+        assert_eq!(Some("{\"version\":3}".into()), info.source_map);
+
+        let source = registry
+            .source(1, |url| Ok(module(url, "export default 1;").loaded_source))
+            .unwrap();
+        assert_eq!("export default 1;", &*source);
+        let err = registry
+            .source(1, |url| Ok(module(url, "export default 2;").loaded_source))
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("changed since it was compiled"));
+
+        registry.invalidate("file:///app/index.js");
+        assert_eq!(None, registry.info(1));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::errors::*;
+
+use encoding_rs::Encoding;
+
+use super::json_loader::{json_to_module, JsonOptions};
+use super::wasm_loader::WASM_MAGIC;
+use super::{wasm_source, LoadedSourceCode, ModuleKind, ResponseMetadata};
+
+/**
+ * What a remotely served module is, by the `Content-Type` it came with rather than its
+ * url, which for something like `/api/module?id=42` says nothing.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RemoteMediaType {
+    JavaScript,
+    /** `true` for TSX. */
+    TypeScript(bool),
+    /** Loaded as a module with the document as its default export. */
+    Json,
+    Wasm,
+    /**
+     * Served as `text/plain` or `application/octet-stream`, or without a type, so left to
+     * the url's extension the way local files are.
+     */
+    Unknown,
+}
+
+/**
+ * `content_type` split into its lowercased essence, `type/subtype`, and its charset
+ * parameter, if any.
+ */
+pub fn parse_content_type(content_type: &str) -> (String, Option<String>) {
+    let mut parts = content_type.split(';');
+    let essence = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+    let charset = parts
+        .filter_map(|param| {
+            let mut kv = param.splitn(2, '=');
+            let name = kv.next()?.trim();
+            let value = kv.next()?.trim().trim_matches('"');
+            if name.eq_ignore_ascii_case("charset") && !value.is_empty() {
+                Some(value.to_string())
+            } else {
+                None
+            }
+        })
+        .next();
+    (essence, charset)
+}
+
+impl RemoteMediaType {
+    /**
+     * The media type `content_type` names for `url`'s module. Fails with `InvalidData` on
+     * one no module can be loaded as.
+     */
+    pub fn from_content_type(url: &str, content_type: &str) -> FlyResult<Self> {
+        let (essence, _) = parse_content_type(content_type);
+        Ok(match essence.as_str() {
+            "application/javascript"
+            | "text/javascript"
+            | "application/ecmascript"
+            | "text/ecmascript"
+            | "application/x-javascript"
+            | "text/jsx" => RemoteMediaType::JavaScript,
+            "application/typescript"
+            | "text/typescript"
+            | "application/x-typescript"
+            | "text/x-typescript"
+            // what plenty of servers call `.ts` files, taking them for MPEG transport streams
+            | "video/mp2t"
+            | "video/vnd.dlna.mpeg-tts" => RemoteMediaType::TypeScript(false),
+            "text/tsx" => RemoteMediaType::TypeScript(true),
+            "application/json" | "text/json" => RemoteMediaType::Json,
+            json if json.ends_with("+json") => RemoteMediaType::Json,
+            "application/wasm" => RemoteMediaType::Wasm,
+            "" | "text/plain" | "application/octet-stream" => RemoteMediaType::Unknown,
+            _ => {
+                return Err(new(
+                    ErrorKind::InvalidData,
+                    format!("unsupported media type {} for {}", essence, url),
+                ))
+            }
+        })
+    }
+
+    /** The media type of the module `response` served, `None` without one. */
+    pub fn of_response(response: &Option<ResponseMetadata>) -> Option<Self> {
+        let content_type = response.as_ref()?.headers.get("content-type")?;
+        Self::from_content_type("", content_type).ok()
+    }
+
+    /**
+     * `self`, or wasm for an `Unknown` one at a `url` ending in `.wasm`, the way sibling
+     * binaries like wasm-bindgen's `_bg.wasm` are often served. Other untyped sources are
+     * text, told apart by their extension once loaded; a binary has to be known as one
+     * before it's decoded.
+     */
+    pub fn or_wasm_extension(self, url: &str) -> Self {
+        let path = match url::Url::parse(url) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => url
+                .split(|c| c == '?' || c == '#')
+                .next()
+                .unwrap_or("")
+                .to_string(),
+        };
+        match self {
+            RemoteMediaType::Unknown if path.ends_with(".wasm") => RemoteMediaType::Wasm,
+            media_type => media_type,
+        }
+    }
+}
+
+/**
+ * `body` as text, decoded from the charset `content_type` declares, or utf-8 without one.
+ * Malformed bytes fail the load rather than turning into replacement characters.
+ */
+pub fn decode_text(url: &str, body: Vec<u8>, content_type: Option<&str>) -> FlyResult<String> {
+    let charset = content_type.and_then(|ct| parse_content_type(ct).1);
+    let encoding = match charset {
+        Some(ref label) => Encoding::for_label(label.as_bytes()).ok_or_else(|| {
+            new(
+                ErrorKind::InvalidData,
+                format!("unsupported charset {} for {}", label, url),
+            )
+        })?,
+        None => encoding_rs::UTF_8,
+    };
+    if encoding == encoding_rs::UTF_8 {
+        return String::from_utf8(body)
+            .map_err(|_| new(ErrorKind::InvalidData, format!("{} is not utf-8 text", url)));
+    }
+    match encoding.decode_without_bom_handling_and_without_replacement(&body) {
+        Some(text) => Ok(text.into_owned()),
+        None => Err(new(
+            ErrorKind::InvalidData,
+            format!("{} is not {} text", url, encoding.name()),
+        )),
+    }
+}
+
+/**
+ * A source fetched from `url`, as text, or base64 for wasm, turned into what its media type
+ * loads as. TypeScript is left for the manager to transpile, which goes by the same type.
+ */
+pub fn load_as(
+    url: &str,
+    media_type: RemoteMediaType,
+    source: LoadedSourceCode,
+) -> FlyResult<LoadedSourceCode> {
+    let kind = ModuleKind::of_media_type(media_type).unwrap_or(source.kind);
+    Ok(match media_type {
+        RemoteMediaType::Json => LoadedSourceCode {
+            kind,
+            source: json_to_module(url, &source.source, &JsonOptions::default())?.into(),
+            source_map: None,
+            ..source
+        },
+        _ => LoadedSourceCode { kind, ..source },
+    })
+}
+
+/**
+ * `body` as the text, or base64 for wasm, that a `media_type` module is stored as. Wasm has
+ * to be a wasm binary, whatever it was served as.
+ */
+pub fn body_source(
+    url: &str,
+    media_type: RemoteMediaType,
+    body: Vec<u8>,
+    content_type: Option<&str>,
+) -> FlyResult<String> {
+    match media_type {
+        RemoteMediaType::Wasm if !body.starts_with(WASM_MAGIC) => Err(new(
+            ErrorKind::InvalidData,
+            format!("{} is not a wasm module", url),
+        )),
+        RemoteMediaType::Wasm => Ok(wasm_source(&body).source.to_string()),
+        _ => decode_text(url, body, content_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_types_pick_the_loader() {
+        let of = |ct| RemoteMediaType::from_content_type("https://a.test/m", ct).unwrap();
+        assert_eq!(RemoteMediaType::JavaScript, of("text/javascript"));
+        assert_eq!(
+            RemoteMediaType::JavaScript,
+            of("Application/JavaScript; charset=UTF-8")
+        );
+        assert_eq!(RemoteMediaType::TypeScript(false), of("video/mp2t"));
+        assert_eq!(RemoteMediaType::Json, of("application/manifest+json"));
+        assert_eq!(RemoteMediaType::Unknown, of("text/plain"));
+        let err = RemoteMediaType::from_content_type("https://a.test/m", "image/png")
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert_eq!(
+            "unsupported media type image/png for https://a.test/m",
+            err.to_string()
+        );
+        assert_eq!(
+            ("text/javascript".to_string(), Some("latin1".to_string())),
+            parse_content_type("text/javascript; foo=bar; charset=\"latin1\"")
+        );
+
+        let untyped = RemoteMediaType::Unknown;
+        assert_eq!(
+            RemoteMediaType::Wasm,
+            untyped.or_wasm_extension("https://a.test/pkg/thing_bg.wasm?v=2")
+        );
+        assert_eq!(
+            RemoteMediaType::Unknown,
+            untyped.or_wasm_extension("https://a.test/wasm?path=thing.wasm")
+        );
+        assert_eq!(
+            RemoteMediaType::JavaScript,
+            RemoteMediaType::JavaScript.or_wasm_extension("https://a.test/thing.wasm")
+        );
+    }
+}
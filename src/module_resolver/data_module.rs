@@ -0,0 +1,208 @@
+use crate::errors::*;
+
+/**
+ * Largest integer JavaScript numbers represent exactly (`Number.MAX_SAFE_INTEGER`). Data
+ * files can hold 64 bit integers; anything outside this range is rejected rather than
+ * silently rounded.
+ */
+pub const MAX_SAFE_INTEGER: i64 = 9_007_199_254_740_991;
+
+/**
+ * How deeply the arrays and objects of a data file nest by default, as deep as `serde_json`
+ * parses JSON. Parsers refuse deeper files before building them, and `data_to_module` never
+ * converts deeper values, so neither recurses as deep as a file asks.
+ */
+pub const MAX_DATA_DEPTH: usize = 128;
+
+#[rustfmt::skip]
+const RESERVED_WORDS: &[&str] = &[
+    "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+    "delete", "do", "else", "enum", "export", "extends", "false", "finally", "for", "function",
+    "if", "implements", "import", "in", "instanceof", "interface", "let", "new", "null",
+    "package", "private", "protected", "public", "return", "static", "super", "switch", "this",
+    "throw", "true", "try", "typeof", "var", "void", "while", "with", "yield",
+];
+
+/**
+ * Format independent tree parsed out of a data file (TOML, YAML, JSON5), turned into module
+ * source by `data_to_module`.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    /** An integer too large for 64 bits, as its decimal digits. */
+    BigInteger(String),
+    Float(f64),
+    String(String),
+    Array(Vec<DataValue>),
+    Object(Vec<(String, DataValue)>),
+}
+
+impl DataValue {
+    pub fn is_object(&self) -> bool {
+        match self {
+            DataValue::Object(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/**
+ * How integers JavaScript numbers can't represent exactly end up in the module.
+ */
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnsafeIntegers {
+    /** Fail to load the module. */
+    Reject,
+    /** A plain number, rounded the way `JSON.parse` rounds it. */
+    Number,
+    BigInt,
+    String,
+}
+
+/**
+ * Module source with `value` as the default export and each top-level object member whose
+ * key is an identifier-safe name and whose value is itself an object as a named export.
+ */
+pub fn data_to_module(value: &DataValue) -> FlyResult<String> {
+    data_to_module_with(value, UnsafeIntegers::Reject)
+}
+
+pub fn data_to_module_with(
+    value: &DataValue,
+    unsafe_integers: UnsafeIntegers,
+) -> FlyResult<String> {
+    let mut module = format!(
+        "const __data = {};\nexport default __data;\n",
+        to_js(value, "", unsafe_integers, 0)?
+    );
+    if let DataValue::Object(ref members) = value {
+        for (key, member) in members {
+            // `__data.__proto__` would read the prototype, not the member
+            if member.is_object() && is_identifier(key) && key != "__proto__" {
+                module.push_str(&format!("export const {} = __data.{};\n", key, key));
+            }
+        }
+    }
+    Ok(module)
+}
+
+fn unsafe_integer_to_js(digits: &str, path: &str, mode: UnsafeIntegers) -> FlyResult<String> {
+    Ok(match mode {
+        UnsafeIntegers::Reject => {
+            return Err(FlyError::from(format!(
+                "integer {} at {} is outside the range JavaScript numbers represent exactly",
+                digits,
+                if path.is_empty() { "<root>" } else { path }
+            )))
+        }
+        UnsafeIntegers::Number => digits.to_string(),
+        UnsafeIntegers::BigInt => format!("{}n", digits),
+        UnsafeIntegers::String => format!("\"{}\"", digits),
+    })
+}
+
+fn too_deep(path: &str) -> FlyError {
+    FlyError::from(format!(
+        "{} nests deeper than {} levels",
+        if path.is_empty() { "<root>" } else { path },
+        MAX_DATA_DEPTH
+    ))
+}
+
+fn to_js(
+    value: &DataValue,
+    path: &str,
+    unsafe_integers: UnsafeIntegers,
+    depth: usize,
+) -> FlyResult<String> {
+    Ok(match value {
+        DataValue::Null => "null".to_string(),
+        DataValue::Bool(b) => b.to_string(),
+        DataValue::Integer(i) => {
+            if *i > MAX_SAFE_INTEGER || *i < -MAX_SAFE_INTEGER {
+                return unsafe_integer_to_js(&i.to_string(), path, unsafe_integers);
+            }
+            i.to_string()
+        }
+        DataValue::BigInteger(digits) => unsafe_integer_to_js(digits, path, unsafe_integers)?,
+        DataValue::Float(f) => {
+            if f.is_nan() {
+                "NaN".to_string()
+            } else if f.is_infinite() {
+                if *f > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+            } else {
+                format!("{:?}", f)
+            }
+        }
+        DataValue::String(s) => serde_json::to_string(s).unwrap(),
+        DataValue::Array(_) | DataValue::Object(_) if depth == MAX_DATA_DEPTH => {
+            return Err(too_deep(path))
+        }
+        DataValue::Array(items) => {
+            let mut elements = Vec::with_capacity(items.len());
+            for (i, item) in items.iter().enumerate() {
+                let item_path = format!("{}[{}]", path, i);
+                elements.push(to_js(item, &item_path, unsafe_integers, depth + 1)?);
+            }
+            format!("[{}]", elements.join(","))
+        }
+        DataValue::Object(members) => {
+            let mut entries = Vec::with_capacity(members.len());
+            for (key, member) in members {
+                let member_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                // a literal `"__proto__":` sets the prototype; computed keys make a plain
+                // property, like JSON.parse does
+                let key_js = if key == "__proto__" {
+                    "[\"__proto__\"]".to_string()
+                } else {
+                    serde_json::to_string(key).unwrap()
+                };
+                entries.push(format!(
+                    "{}:{}",
+                    key_js,
+                    to_js(member, &member_path, unsafe_integers, depth + 1)?
+                ));
+            }
+            format!("{{{}}}", entries.join(","))
+        }
+    })
+}
+
+/**
+ * `s` as a JavaScript string literal. JSON strings are ones too, once the line and paragraph
+ * separators JSON allows unescaped are escaped.
+ */
+pub fn js_string_literal(s: &str) -> String {
+    serde_json::to_string(s)
+        .unwrap()
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !RESERVED_WORDS.contains(&name)
+}
+
+/**
+ * Error for a data file that failed to parse, pointing at 1-based `line` and `col`.
+ */
+pub fn parse_error(origin_url: &str, format: &str, line: usize, col: usize, msg: &str) -> FlyError {
+    FlyError::from(format!(
+        "{}:{}:{}: invalid {}: {}",
+        origin_url, line, col, format, msg
+    ))
+}
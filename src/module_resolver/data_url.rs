@@ -0,0 +1,224 @@
+use crate::errors::*;
+
+use url::percent_encoding::percent_decode;
+
+use super::json_loader::json_to_module;
+use super::{
+    parse_url, wasm_source, JsonOptions, LoadedSourceCode, ModuleKind, ModuleResolver,
+    ModuleSourceData, RefererInfo, ResolverDescription, StaticSourceLoader,
+};
+
+/** How much of a data url errors show; the rest is usually a long payload. */
+const SHOWN_URL_CHARS: usize = 48;
+
+/**
+ * `url` cut down to its first few characters, for messages.
+ */
+pub fn abbreviate_data_url(url: &str) -> String {
+    match url.char_indices().nth(SHOWN_URL_CHARS) {
+        Some((end, _)) => format!("{}...", &url[..end]),
+        None => url.to_string(),
+    }
+}
+
+fn data_url_error(url: &str, msg: impl std::fmt::Display) -> FlyError {
+    new(
+        ErrorKind::InvalidData,
+        format!("Invalid data url {}: {}", abbreviate_data_url(url), msg),
+    )
+}
+
+/**
+ * The module a `data:[<media type>][;base64],<payload>` url holds: JavaScript
+ * (`text/javascript` or `application/javascript`), JSON (`application/json`), which becomes
+ * a module exporting it the way `.json` files do, or wasm (`application/wasm`).
+ */
+pub fn data_url_source(url: &str) -> FlyResult<LoadedSourceCode> {
+    if !url.starts_with("data:") {
+        return Err(data_url_error(url, "it doesn't start with data:"));
+    }
+    let comma = url[5..]
+        .find(',')
+        .ok_or_else(|| data_url_error(url, "there's no , before the payload"))?;
+    let header = &url[5..5 + comma];
+    let payload = &url[5 + comma + 1..];
+    let mut params = header.split(';');
+    let media_type = params.next().unwrap_or("").trim().to_lowercase();
+    let is_base64 = params.any(|param| param.trim().eq_ignore_ascii_case("base64"));
+    let bytes: Vec<u8> = percent_decode(payload.as_bytes()).collect();
+    let bytes = if is_base64 {
+        // base64 can be split over lines when written out by hand
+        let encoded: Vec<u8> = bytes
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        base64::decode(&encoded).map_err(|e| data_url_error(url, format!("bad base64: {}", e)))?
+    } else {
+        bytes
+    };
+    if media_type == "application/wasm" {
+        return Ok(wasm_source(&bytes));
+    }
+    let text =
+        String::from_utf8(bytes).map_err(|e| data_url_error(url, format!("not UTF-8: {}", e)))?;
+    let (kind, source) = match &media_type[..] {
+        "text/javascript" | "application/javascript" => (ModuleKind::JavaScript, text),
+        "application/json" => (
+            ModuleKind::Json,
+            json_to_module(&abbreviate_data_url(url), &text, &JsonOptions::default())?,
+        ),
+        "" => return Err(data_url_error(url, "it has no media type")),
+        other => {
+            return Err(data_url_error(
+                url,
+                format!(
+                    "unsupported media type {}; use text/javascript, application/json or application/wasm",
+                    other
+                ),
+            ))
+        }
+    };
+    Ok(LoadedSourceCode {
+        kind,
+        source_map: None,
+        source: source.into(),
+        response: None,
+        provenance: None,
+        code_cache: None,
+        compiled_wasm: None,
+        wasm: None,
+    })
+}
+
+/**
+ * Resolves `data:` urls to the module they hold, e.g. code generated at runtime and
+ * imported with `import("data:text/javascript;base64,...")`. A data module's origin url is
+ * its own url, which isn't a base for relative imports, so it can only import by absolute
+ * url or bare specifier.
+ */
+#[derive(Default)]
+pub struct DataUrlResolver {}
+
+impl DataUrlResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ModuleResolver for DataUrlResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => "data:".to_string(),
+        };
+        let url = parse_url(module_specifier, &referer_origin_url)?.into_string();
+        // checked here, so a malformed url fails with its own error rather than as a miss
+        let source = data_url_source(&url)?;
+        Ok(ModuleSourceData {
+            origin_url: url,
+            source_loader: Box::new(StaticSourceLoader { source }),
+        })
+    }
+    fn get_protocol(&self) -> String {
+        "data".to_string()
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("data_url", "data").named(&self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        wasm_bytes, ModuleResolverManager, StandardModuleResolverManager,
+    };
+
+    #[test]
+    fn test_decodes_each_media_type() {
+        let js = data_url_source("data:text/javascript,export%20default%201;").unwrap();
+        assert_eq!("export default 1;", &*js.source);
+        assert_eq!(ModuleKind::JavaScript, js.kind);
+        let js = data_url_source(&format!(
+            "data:application/javascript;charset=utf-8;base64,{}",
+            base64::encode("export const a = 'é';")
+        ))
+        .unwrap();
+        assert_eq!("export const a = 'é';", &*js.source);
+
+        let json = data_url_source(r#"data:application/json,{"a":1}"#).unwrap();
+        assert_eq!(ModuleKind::Json, json.kind);
+        assert!(!json.is_wasm());
+        assert!(json.source.contains("export default"));
+
+        let wasm = b"\0asm\x01\0\0\0";
+        let source = data_url_source(&format!(
+            "data:application/wasm;base64,{}",
+            base64::encode(&wasm[..])
+        ))
+        .unwrap();
+        assert_eq!(ModuleKind::Wasm, source.kind);
+        assert!(source.is_wasm());
+        assert_eq!(&wasm[..], &wasm_bytes(&source).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_malformed_data_urls_fail_to_parse() {
+        for url in &[
+            "data:text/javascript;base64",
+            "data:text/javascript;base64,not base64!",
+            "data:text/plain,hello",
+            "data:,export default 1",
+            "data:text/javascript;base64,/w==",
+        ] {
+            let err = data_url_source(url).err().unwrap();
+            assert_eq!(ErrorKind::InvalidData, err.kind(), "{}", url);
+            assert!(
+                format!("{}", err).starts_with("Invalid data url"),
+                "{}",
+                url
+            );
+        }
+        let long = format!("data:text/plain,{}", "x".repeat(1000));
+        assert!(format!("{}", data_url_source(&long).err().unwrap()).len() < 200);
+    }
+
+    #[test]
+    fn test_data_modules_cannot_import_relatively() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(DataUrlResolver::new()) as Box<ModuleResolver>],
+            None,
+        );
+        let main = manager
+            .resolve_blocking("data:text/javascript,import%20'./a.js';".to_string(), None)
+            .unwrap();
+        assert_eq!("data:text/javascript,import%20'./a.js';", main.origin_url);
+        assert_eq!("import './a.js';", &*main.loaded_source.source);
+
+        let referer = Some(RefererInfo {
+            origin_url: main.origin_url,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        });
+        let err = manager
+            .resolve_blocking("./a.js".to_string(), referer.clone())
+            .err()
+            .unwrap();
+        let msg = format!("{}", err);
+        assert!(msg.contains("relative imports"), "{}", msg);
+        assert!(msg.contains("data: urls have no base"), "{}", msg);
+        // absolute ones still work
+        manager
+            .resolve_blocking("data:text/javascript,".to_string(), referer)
+            .unwrap();
+    }
+}
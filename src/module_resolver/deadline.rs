@@ -0,0 +1,363 @@
+use crate::errors::*;
+use crate::runtime::EVENT_LOOP;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Either};
+use futures::sync::oneshot::{self, SpawnHandle};
+use futures::Future;
+use tokio::timer::Delay;
+
+use super::{CancellationToken, LoadedModuleFuture, ModuleResolutionError};
+
+struct DeadlineState {
+    budget: Duration,
+    expires_at: Instant,
+    completed: AtomicUsize,
+    /** Imports still loading, as `<specifier> from <referer>`, in the order they started. */
+    in_flight: Mutex<Vec<String>>,
+    /** Cancelled once the budget runs out. */
+    expired: CancellationToken,
+    /** Dropped with the last clone of the deadline, which cancels its timer. */
+    _timer: Mutex<SpawnHandle<(), ()>>,
+}
+
+/**
+ * The time one top-level load, an import the runtime runs or a `preload`, has for every
+ * module it resolves along the way, however deep. It travels on the `RefererInfo` of each
+ * import under that load, so resolvers resolving further modules through the manager stay
+ * within it by passing it on. Once it runs out, every load still going under it is dropped,
+ * cancelling it, and fails with `DeadlineExceeded`. Clones share the same budget.
+ */
+#[derive(Clone)]
+pub struct ResolutionDeadline {
+    state: Arc<DeadlineState>,
+}
+
+impl ResolutionDeadline {
+    pub fn new(budget: Duration) -> Self {
+        let expired = CancellationToken::new();
+        let expires_at = Instant::now() + budget;
+        let timer = expired.clone();
+        let timer = oneshot::spawn(
+            Delay::new(expires_at).then(move |fired| {
+                match fired {
+                    Ok(()) => timer.cancel(),
+                    // loads still check the budget as they finish
+                    Err(e) => error!("the module resolution budget's timer failed: {}", e),
+                }
+                Ok::<(), ()>(())
+            }),
+            &EVENT_LOOP.0,
+        );
+        Self {
+            state: Arc::new(DeadlineState {
+                budget,
+                expires_at,
+                completed: AtomicUsize::new(0),
+                in_flight: Mutex::new(vec![]),
+                expired,
+                _timer: Mutex::new(timer),
+            }),
+        }
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.state.budget
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.state.expired.is_cancelled() || Instant::now() >= self.state.expires_at
+    }
+
+    /** How many modules loaded under this deadline so far. */
+    pub fn completed(&self) -> usize {
+        self.state.completed.load(Ordering::SeqCst)
+    }
+
+    /**
+     * `loading`, of `import` (`<specifier> from <referer>`), failing with `DeadlineExceeded`
+     * and dropped once the deadline passes. A load finishing after it passed fails all the
+     * same; one started after it doesn't start at all.
+     */
+    pub fn bound(&self, import: String, loading: LoadedModuleFuture) -> LoadedModuleFuture {
+        if self.is_expired() {
+            return Box::new(future::err(self.exceeded()));
+        }
+        self.state.in_flight.lock().unwrap().push(import.clone());
+        let deadline = self.clone();
+        Box::new(
+            loading
+                .select2(self.state.expired.cancelled())
+                .then(move |result| {
+                    let result = match result {
+                        _ if deadline.is_expired() => return Err(deadline.exceeded()),
+                        Ok(Either::A((module, _))) => Ok(module),
+                        Err(Either::A((e, _))) => Err(e),
+                        // only resolves once the budget ran out, or with the timer gone
+                        Ok(Either::B(_)) | Err(Either::B(_)) => return Err(deadline.exceeded()),
+                    };
+                    let mut in_flight = deadline.state.in_flight.lock().unwrap();
+                    if let Some(i) = in_flight.iter().position(|started| *started == import) {
+                        in_flight.remove(i);
+                    }
+                    if result.is_ok() {
+                        deadline.state.completed.fetch_add(1, Ordering::SeqCst);
+                    }
+                    result
+                }),
+        )
+    }
+
+    /** The error loads under this deadline fail with once it passed. */
+    pub fn exceeded(&self) -> FlyError {
+        ModuleResolutionError::DeadlineExceeded {
+            deadline: self.state.budget,
+            completed: self.completed(),
+            // the innermost, when imports were resolving imports of their own
+            in_flight: self.state.in_flight.lock().unwrap().last().cloned(),
+        }
+        .into()
+    }
+}
+
+impl fmt::Debug for ResolutionDeadline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResolutionDeadline")
+            .field("budget", &self.state.budget)
+            .field("completed", &self.completed())
+            .field("expired", &self.is_expired())
+            .finish()
+    }
+}
+
+/**
+ * The deadlines of a runtime's top-level loads, by the id `op_load_module` answers the first
+ * load of each with. The JS side names it on every import under that load, so they share its
+ * deadline however many ops they take. Deadlines are forgotten once they passed.
+ */
+pub struct ResolutionDeadlines {
+    next_id: AtomicUsize,
+    running: Mutex<HashMap<u32, ResolutionDeadline>>,
+}
+
+impl ResolutionDeadlines {
+    pub fn new() -> Self {
+        ResolutionDeadlines {
+            next_id: AtomicUsize::new(1),
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /** Starts a top-level load with `budget` for everything under it; returns its id. */
+    pub fn start(&self, budget: Duration) -> (u32, ResolutionDeadline) {
+        let deadline = ResolutionDeadline::new(budget);
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) as u32;
+        let mut running = self.running.lock().unwrap();
+        running.retain(|_, deadline| !deadline.is_expired());
+        running.insert(id, deadline.clone());
+        (id, deadline)
+    }
+
+    /**
+     * The deadline of top-level load `id`. One that was forgotten has passed, so an import
+     * naming it fails with `DeadlineExceeded` as the load's other imports did.
+     */
+    pub fn get(&self, id: u32) -> FlyResult<ResolutionDeadline> {
+        self.running
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| {
+                new(
+                    ErrorKind::DeadlineExceeded,
+                    format!("the load importing this module ran out of time ({})", id),
+                )
+            })
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.lock().unwrap().len()
+    }
+}
+
+impl Default for ResolutionDeadlines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        parse_url, FunctionModuleResolver, LoadedSourceCode, ModuleKind, ModuleResolver,
+        ModuleResolverManager, ModuleSourceData, ResolutionCache, ResolverOptions,
+        StandardModuleResolverManager, StaticSourceLoader,
+    };
+    use std::thread;
+
+    /**
+     * `main.js` importing `a.js`, importing `b.js`, importing `c.js`, from a resolver that
+     * takes `delay` to find each one.
+     */
+    fn slow_graph(delay: Duration, total_deadline: Duration) -> StandardModuleResolverManager {
+        let resolved = Arc::new(Mutex::new(vec![]));
+        slow_graph_recording(delay, total_deadline, resolved)
+    }
+
+    fn slow_graph_recording(
+        delay: Duration,
+        total_deadline: Duration,
+        resolved: Arc<Mutex<Vec<String>>>,
+    ) -> StandardModuleResolverManager {
+        let resolver = FunctionModuleResolver::new(
+            "function",
+            Box::new(move |specifier, referer_info| {
+                let referer = referer_info.map_or("function:///app/".to_string(), |r| r.origin_url);
+                let url = parse_url(specifier, &referer)?.into_string();
+                thread::sleep(delay);
+                let source = match url.rsplit('/').next().unwrap() {
+                    "main.js" => "import a from './a.js';",
+                    "a.js" => "import b from './b.js';",
+                    "b.js" => "import c from './c.js';",
+                    _ => "export default 1;",
+                };
+                resolved.lock().unwrap().push(url.clone());
+                Ok(ModuleSourceData {
+                    origin_url: url,
+                    source_loader: Box::new(StaticSourceLoader {
+                        source: LoadedSourceCode {
+                            kind: ModuleKind::JavaScript,
+                            source_map: None,
+                            source: source.into(),
+                            response: None,
+                            provenance: None,
+                            code_cache: None,
+                            compiled_wasm: None,
+                            wasm: None,
+                        },
+                    }),
+                })
+            }),
+        );
+        StandardModuleResolverManager::with_options(
+            vec![Box::new(resolver) as Box<ModuleResolver>],
+            Some("function:///app/".to_string()),
+            ResolverOptions {
+                total_deadline: Some(total_deadline),
+                ..ResolverOptions::default()
+            },
+        )
+        .with_resolution_cache(ResolutionCache::new())
+    }
+
+    #[test]
+    fn test_preloads_stop_where_the_deadline_runs_out() {
+        let resolved = Arc::new(Mutex::new(vec![]));
+        let manager = slow_graph_recording(
+            Duration::from_millis(150),
+            Duration::from_millis(375),
+            resolved.clone(),
+        );
+        let started = Instant::now();
+        let err = manager.preload("./main.js").err().unwrap();
+        // given up on when it ran out, not once `b.js` was found
+        assert!(started.elapsed() < Duration::from_millis(450));
+        assert_eq!(ErrorKind::DeadlineExceeded, err.kind());
+        assert_eq!(
+            "Could not load modules within the total deadline of 375ms: 2 loaded, ./b.js from function:///app/a.js still loading",
+            err.to_string()
+        );
+        match err.resolution_error() {
+            Some(ModuleResolutionError::DeadlineExceeded {
+                completed,
+                in_flight,
+                ..
+            }) => {
+                assert_eq!(2, *completed);
+                assert_eq!(
+                    Some("./b.js from function:///app/a.js"),
+                    in_flight.as_ref().map(|s| s.as_str())
+                );
+            }
+            other => panic!("{:?}", other),
+        }
+
+        // nothing goes on to import `c.js` from what was cancelled
+        thread::sleep(Duration::from_millis(200));
+        assert!(!resolved
+            .lock()
+            .unwrap()
+            .contains(&"function:///app/c.js".to_string()));
+    }
+
+    #[test]
+    fn test_deadlines_are_per_top_level_load() {
+        let manager = slow_graph(Duration::from_millis(20), Duration::from_millis(250));
+        let summary = manager.preload("./main.js").unwrap();
+        assert_eq!(4, summary.modules);
+        // a second preload has a budget of its own
+        assert!(manager.preload("./main.js").is_ok());
+
+        // and it only bounds what's imported under it
+        let deadline = ResolutionDeadline::new(Duration::from_millis(250));
+        let main = deadline
+            .bound(
+                "./main.js".to_string(),
+                manager.load_entry_point("./main.js".to_string()),
+            )
+            .wait()
+            .unwrap();
+        assert_eq!("function:///app/main.js", main.origin_url);
+        assert_eq!(1, deadline.completed());
+        thread::sleep(Duration::from_millis(300));
+        assert!(deadline.is_expired());
+        let err = deadline
+            .bound(
+                "./a.js from function:///app/main.js".to_string(),
+                manager.load_entry_point("./a.js".to_string()),
+            )
+            .wait()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::DeadlineExceeded, err.kind());
+        assert!(manager.resolve_blocking("./a.js".to_string(), None).is_ok());
+    }
+
+    #[test]
+    fn test_loads_naming_a_deadline_share_it() {
+        let deadlines = ResolutionDeadlines::new();
+        let (id, deadline) = deadlines.start(Duration::from_millis(100));
+        let (other, _) = deadlines.start(Duration::from_secs(60));
+        assert_ne!(id, other);
+        let shared = deadlines.get(id).unwrap();
+        let err = shared
+            .bound(
+                "./a.js from function:///app/main.js".to_string(),
+                Box::new(future::lazy(|| {
+                    thread::sleep(Duration::from_millis(150));
+                    Err(FlyError::from("never gets this far".to_string()))
+                })),
+            )
+            .wait()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::DeadlineExceeded, err.kind());
+        assert!(deadline.is_expired());
+
+        // forgotten once it passed, with the loads still naming it turned away
+        deadlines.start(Duration::from_secs(60));
+        assert_eq!(2, deadlines.running());
+        assert_eq!(
+            ErrorKind::DeadlineExceeded,
+            deadlines.get(id).err().unwrap().kind()
+        );
+        assert!(deadlines.get(other).is_ok());
+    }
+}
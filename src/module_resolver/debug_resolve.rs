@@ -0,0 +1,324 @@
+use std::fmt;
+
+use super::{wasm_bytes, ModuleKind, ModuleResolverManager, RefererInfo, TraceStep};
+
+/** A file or url a resolver looked for. */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProbeReport {
+    pub candidate: String,
+    pub found: bool,
+}
+
+/** One resolver tried, what it looked for and, if it didn't resolve the specifier, why. */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AttemptReport {
+    /** The kind of resolver, as `describe` names it. */
+    pub resolver: String,
+    pub protocol: String,
+    pub probes: Vec<ProbeReport>,
+    pub error: Option<String>,
+}
+
+/**
+ * Everything resolving one specifier took, from a dry run: how it was parsed, the resolvers
+ * tried and what each looked for, and the module it resolved to, if any.
+ */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResolutionReport {
+    pub specifier: String,
+    pub referer: String,
+    /** What an import map, wasm import map or base url rewrote the specifier to. */
+    pub rewritten: Option<String>,
+    /** The specifier joined to its referer's url; `None` for bare specifiers and virtual modules. */
+    pub url: Option<String>,
+    pub protocol: Option<String>,
+    /** In the order tried. */
+    pub attempts: Vec<AttemptReport>,
+    /** The `name` of the resolver that resolved it; `None` for virtual modules. */
+    pub resolver: Option<String>,
+    pub origin_url: Option<String>,
+    pub kind: Option<ModuleKind>,
+    /** Of the source loaded, or of the binary for wasm. */
+    pub size: Option<usize>,
+    /** Why it didn't resolve, or didn't load. */
+    pub error: Option<String>,
+}
+
+impl ResolutionReport {
+    fn new(specifier: &str, referer: &str) -> Self {
+        Self {
+            specifier: specifier.to_string(),
+            referer: referer.to_string(),
+            rewritten: None,
+            url: None,
+            protocol: None,
+            attempts: vec![],
+            resolver: None,
+            origin_url: None,
+            kind: None,
+            size: None,
+            error: None,
+        }
+    }
+
+    fn record(&mut self, step: TraceStep) {
+        match step {
+            TraceStep::Rewritten { specifier } => self.rewritten = Some(specifier),
+            TraceStep::Parsed { url } => self.url = Some(url),
+            TraceStep::Protocol { protocol, .. } => self.protocol = Some(protocol),
+            TraceStep::ResolverTried { resolver, protocol } => self.attempts.push(AttemptReport {
+                resolver,
+                protocol,
+                probes: vec![],
+                error: None,
+            }),
+            TraceStep::Probed { candidate, found } => {
+                if let Some(attempt) = self.attempts.last_mut() {
+                    attempt.probes.push(ProbeReport { candidate, found });
+                }
+            }
+            TraceStep::ResolverFailed { resolver, error } => {
+                let failed = self
+                    .attempts
+                    .iter_mut()
+                    .rev()
+                    .find(|attempt| attempt.resolver == resolver && attempt.error.is_none());
+                if let Some(attempt) = failed {
+                    attempt.error = Some(error);
+                }
+            }
+            TraceStep::Resolved {
+                origin_url,
+                resolver,
+            } => {
+                self.origin_url = Some(origin_url);
+                self.resolver = resolver;
+            }
+            TraceStep::Failed { error } => self.error = Some(error),
+            _ => {}
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/**
+ * The first line of `error`. Resolution errors go on to list the resolvers tried, which the
+ * report lists already.
+ */
+fn first_line(error: &str) -> &str {
+    error
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim_end()
+        .trim_end_matches(" Tried, in order:")
+}
+
+impl fmt::Display for ResolutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Resolving {} from {}", self.specifier, self.referer)?;
+        if let Some(ref rewritten) = self.rewritten {
+            writeln!(f, "Rewritten to: {}", rewritten)?;
+        }
+        if let Some(ref url) = self.url {
+            writeln!(f, "Parsed as: {}", url)?;
+        }
+        if let Some(ref protocol) = self.protocol {
+            writeln!(f, "Protocol: {}", protocol)?;
+        }
+        if !self.attempts.is_empty() {
+            writeln!(f, "Tried:")?;
+        }
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            writeln!(
+                f,
+                "  {}. {} for {}",
+                i + 1,
+                attempt.resolver,
+                attempt.protocol
+            )?;
+            for probe in &attempt.probes {
+                writeln!(
+                    f,
+                    "       probed {}: {}",
+                    probe.candidate,
+                    if probe.found { "found" } else { "missing" }
+                )?;
+            }
+            if let Some(ref error) = attempt.error {
+                writeln!(f, "       failed: {}", first_line(error))?;
+            }
+        }
+        if let Some(ref origin_url) = self.origin_url {
+            if let Some(ref resolver) = self.resolver {
+                writeln!(f, "Resolved by: {}", resolver)?;
+            }
+            writeln!(f, "Origin url: {}", origin_url)?;
+        }
+        if let Some(kind) = self.kind {
+            writeln!(f, "Kind: {}", kind.as_str())?;
+        }
+        if let Some(size) = self.size {
+            writeln!(f, "Size: {} bytes", size)?;
+        }
+        if let Some(ref error) = self.error {
+            writeln!(f, "Failed: {}", first_line(error))?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Resolves `specifier`, imported from `referer` or from the manager's working url, as the
+ * runtime would, and reports every step it took. A dry run: the module's source is loaded to
+ * tell its kind and size, but it's neither cached nor recorded in the module graph. Managers
+ * that can't trace resolutions only report where it resolved to.
+ */
+pub fn debug_resolve(
+    manager: &ModuleResolverManager,
+    specifier: &str,
+    referer: Option<&str>,
+) -> ResolutionReport {
+    let referer_info = referer.map(|origin_url| RefererInfo {
+        origin_url: origin_url.to_string(),
+        is_wasm: Some(false),
+        source_code: None,
+        identifier_hash: None,
+        line: None,
+        column: None,
+        app_id: None,
+        deadline: None,
+    });
+    let (resolved, mut report) =
+        match manager.trace_resolution(specifier.to_string(), referer_info.clone()) {
+            Some((resolved, trace)) => {
+                let mut report = ResolutionReport::new(&trace.specifier, &trace.referer);
+                for step in trace.steps {
+                    report.record(step);
+                }
+                (resolved, report)
+            }
+            None => {
+                let report = ResolutionReport::new(specifier, referer.unwrap_or(""));
+                (manager.resolve(specifier.to_string(), referer_info), report)
+            }
+        };
+    let module = match resolved {
+        Ok(module) => module,
+        Err(e) => {
+            report.error = Some(e.to_string());
+            return report;
+        }
+    };
+    report.origin_url = Some(module.origin_url.clone());
+    match manager.load(&module) {
+        Ok(loaded) => {
+            report.kind = Some(loaded.kind);
+            report.size = Some(match wasm_bytes(&loaded) {
+                Ok(ref wasm) if loaded.is_wasm() => wasm.len(),
+                _ => loaded.source.len(),
+            });
+        }
+        Err(e) => report.error = Some(e.to_string()),
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolver, StandardModuleResolverManager,
+    };
+
+    fn manager(root: &str) -> StandardModuleResolverManager {
+        StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(root.to_string())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(root.to_string()),
+        )
+    }
+
+    /** `report` printed with `dir` as `/app`, so it reads the same wherever it ran. */
+    fn snapshot(report: &ResolutionReport, dir: &tempfile::TempDir) -> String {
+        let path = dir.path().canonicalize().unwrap().display().to_string();
+        format!("{}", report).replace(&path, "/app")
+    }
+
+    #[test]
+    fn test_reports_every_candidate_of_a_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("widgets")).unwrap();
+        let root = url::Url::from_directory_path(dir.path().canonicalize().unwrap())
+            .unwrap()
+            .into_string();
+
+        let report = debug_resolve(&manager(&root), "./widgets", None);
+        assert_eq!(
+            "Resolving ./widgets from file:///app/
+Parsed as: file:///app/widgets
+Protocol: file
+Tried:
+  1. local_disk for file
+       probed /app/widgets: missing
+       probed /app/widgets.ts: missing
+       probed /app/widgets.tsx: missing
+       probed /app/widgets.js: missing
+       probed /app/widgets.jsx: missing
+       probed /app/widgets.mjs: missing
+       probed /app/widgets.json: missing
+       probed /app/widgets.wasm: missing
+       probed /app/widgets/index.ts: missing
+       probed /app/widgets/index.js: missing
+       probed /app/widgets/index.wasm: missing
+       failed: Could not resolve ./widgets from file:///app/
+Failed: Could not resolve ./widgets from file:///app/: exhausted all resolvers, no fallback resolver is registered.
+",
+            snapshot(&report, &dir)
+        );
+        assert_eq!(None, report.origin_url);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(11, json["attempts"][0]["probes"].as_array().unwrap().len());
+        assert!(json["error"]
+            .as_str()
+            .unwrap()
+            .contains("exhausted all resolvers"));
+    }
+
+    #[test]
+    fn test_reports_the_module_of_a_hit() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("util.js"), "export default 1;").unwrap();
+        let root = url::Url::from_directory_path(dir.path().canonicalize().unwrap())
+            .unwrap()
+            .into_string();
+
+        let report = debug_resolve(&manager(&root), "./util", Some(&format!("{}main.ts", root)));
+        assert_eq!(
+            "Resolving ./util from file:///app/main.ts
+Parsed as: file:///app/util
+Protocol: file
+Tried:
+  1. local_disk for file
+       probed /app/util: missing
+       probed /app/util.ts: missing
+       probed /app/util.tsx: missing
+       probed /app/util.js: found
+Resolved by: LocalDiskModuleResolver
+Origin url: file:///app/util.js
+Kind: javascript
+Size: 17 bytes
+",
+            snapshot(&report, &dir)
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!("javascript", json["kind"]);
+        assert_eq!(17, json["size"]);
+        assert_eq!(serde_json::Value::Null, json["error"]);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use sha2::Digest; // puts trait in scope
+use sha2::Sha256;
+
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+const MAX_REDIRECTS: usize = 5;
+
+/**
+ * A remote module found in a Deno cache directory.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct DenoCachedModule {
+    /** The url the source was served from, after following cached redirects. */
+    pub url: String,
+    pub source: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl DenoCachedModule {
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("content-type").map(|v| v.as_str())
+    }
+}
+
+/**
+ * Read-only view of an existing `DENO_DIR`, so remote modules a user already downloaded with
+ * Deno don't have to be fetched again. Understands the current
+ * `deps/<scheme>/<host>/<sha256 of path>` layout with `.metadata.json` files and the older
+ * plain-path layout with `.headers.json` files. Anything unexpected, such as a missing or
+ * mismatched metadata file, is a cache miss; nothing is ever written here.
+ */
+#[derive(Clone, Debug)]
+pub struct DenoDirCache {
+    root: PathBuf,
+}
+
+impl DenoDirCache {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /**
+     * The cache Deno itself would use: `$DENO_DIR`, else `$XDG_CACHE_HOME/deno`, else
+     * `$HOME/.cache/deno`. `None` if that directory doesn't exist.
+     */
+    pub fn from_env() -> Option<Self> {
+        let root = match std::env::var_os("DENO_DIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => match std::env::var_os("XDG_CACHE_HOME") {
+                Some(dir) => PathBuf::from(dir).join("deno"),
+                None => PathBuf::from(std::env::var_os("HOME")?)
+                    .join(".cache")
+                    .join("deno"),
+            },
+        };
+        if root.join("deps").is_dir() {
+            Some(Self::new(root))
+        } else {
+            None
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn lookup(&self, url: &str) -> Option<DenoCachedModule> {
+        let mut url = url::Url::parse(url).ok()?;
+        for _ in 0..=MAX_REDIRECTS {
+            let (source, headers) = match self.read_hashed(&url) {
+                Some(entry) => entry,
+                None => self.read_legacy(&url)?,
+            };
+            match headers.get("location") {
+                Some(location) => url = url.join(location).ok()?,
+                None => {
+                    return Some(DenoCachedModule {
+                        url: url.as_str().to_string(),
+                        source,
+                        headers,
+                    })
+                }
+            }
+        }
+        debug!(
+            "too many cached redirects for {} in {}",
+            url,
+            self.root.display()
+        );
+        None
+    }
+
+    fn host_dir(&self, url: &url::Url) -> Option<PathBuf> {
+        let mut host = url.host_str()?.to_string();
+        if let Some(port) = url.port() {
+            host = format!("{}_PORT{}", host, port);
+        }
+        Some(self.root.join("deps").join(url.scheme()).join(host))
+    }
+
+    fn read_hashed(&self, url: &url::Url) -> Option<(String, HashMap<String, String>)> {
+        let mut rest = url.path().to_string();
+        if let Some(query) = url.query() {
+            rest = format!("{}?{}", rest, query);
+        }
+        let mut hasher = Sha256::default();
+        hasher.input(rest.as_bytes());
+        let name: String = hasher
+            .result()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let path = self.host_dir(url)?.join(&name);
+
+        let metadata: serde_json::Value = read_json(&path.with_extension("metadata.json"))?;
+        // a hash collision or a layout we don't understand
+        if metadata.get("url").and_then(|u| u.as_str()) != Some(url.as_str()) {
+            debug!("ignoring {}: metadata is for another url", path.display());
+            return None;
+        }
+        let headers = metadata
+            .get("headers")
+            .and_then(|h| h.as_object())
+            .map(|h| {
+                h.iter()
+                    .filter_map(|(k, v)| Some((k.to_lowercase(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some((std::fs::read_to_string(&path).ok()?, headers))
+    }
+
+    fn read_legacy(&self, url: &url::Url) -> Option<(String, HashMap<String, String>)> {
+        let mut path = self.host_dir(url)?;
+        for segment in url.path_segments()? {
+            if segment.is_empty() || segment == "." || segment == ".." {
+                return None;
+            }
+            path.push(segment);
+        }
+        let mut headers_path = path.clone().into_os_string();
+        headers_path.push(".headers.json");
+        let mut headers = HashMap::new();
+        if let Some(legacy) = read_json(Path::new(&headers_path)) {
+            if let Some(mime_type) = legacy.get("mime_type").and_then(|m| m.as_str()) {
+                headers.insert("content-type".to_string(), mime_type.to_string());
+            }
+            if let Some(redirect) = legacy.get("redirect_to").and_then(|r| r.as_str()) {
+                headers.insert("location".to_string(), redirect.to_string());
+                return Some((String::new(), headers));
+            }
+        }
+        Some((std::fs::read_to_string(&path).ok()?, headers))
+    }
+}
+
+/**
+ * Loads one url out of a `DenoDirCache`, failing with `NotFound` when it isn't there.
+ */
+pub struct DenoDirLoader {
+    pub cache: DenoDirCache,
+    pub url: String,
+}
+
+impl SourceLoader for DenoDirLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        match self.cache.lookup(&self.url) {
+            Some(module) => Ok(LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: None,
+                source: module.source.into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            }),
+            None => Err(new(
+                ErrorKind::NotFound,
+                format!("{} is not in {}", self.url, self.cache.root.display()),
+            )),
+        }
+    }
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    let source = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&source).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> DenoDirCache {
+        DenoDirCache::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/deno_dir"
+        ))
+    }
+
+    #[test]
+    fn test_reads_hashed_layout() {
+        let module = fixture()
+            .lookup("https://deno.land/std@0.50.0/path/mod.ts")
+            .unwrap();
+        assert_eq!("export const SEP = \"/\";\n", module.source);
+        assert_eq!(
+            Some("application/typescript; charset=utf-8"),
+            module.content_type()
+        );
+    }
+
+    #[test]
+    fn test_follows_cached_redirects() {
+        let module = fixture()
+            .lookup("https://deno.land/std/path/mod.ts")
+            .unwrap();
+        assert_eq!("https://deno.land/std@0.50.0/path/mod.ts", module.url);
+        assert_eq!("export const SEP = \"/\";\n", module.source);
+    }
+
+    #[test]
+    fn test_reads_legacy_layout() {
+        let module = fixture()
+            .lookup("https://deno.land/x/legacy/mod.js")
+            .unwrap();
+        assert_eq!("export const legacy = true;\n", module.source);
+        assert_eq!(Some("application/javascript"), module.content_type());
+    }
+
+    #[test]
+    fn test_mismatches_are_misses() {
+        let cache = fixture();
+        assert_eq!(None, cache.lookup("https://deno.land/x/mismatch.ts"));
+        assert_eq!(None, cache.lookup("https://deno.land/x/missing.ts"));
+        assert_eq!(None, cache.lookup("https://example.com/mod.ts"));
+        assert_eq!(None, cache.lookup("not a url"));
+        assert_eq!(
+            None,
+            DenoDirCache::new("/does/not/exist").lookup("https://deno.land/std/path/mod.ts")
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use crate::errors::*;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{
+    fetch_with_options, fetcher_allowing, parse_url, FetchOptions, FetchRequest, HttpFetcher,
+    LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+pub const DEFAULT_DEV_SERVER_PROTOCOL: &str = "dev";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DevServerConfig {
+    /** Where the dev server listens, e.g. `http://localhost:5173/`. */
+    pub base_url: String,
+    /** Defaults to `dev`. */
+    pub protocol: Option<String>,
+    /** Only forward urls whose path starts with this, e.g. `/app/web/` under `file`. */
+    pub path_prefix: Option<String>,
+}
+
+/**
+ * Forwards a protocol, or a path prefix within one, to a local dev server, so modules come
+ * from the front-end toolchain exactly as it transformed them. `dev:///src/main.ts` loads
+ * `<base_url>src/main.ts`, query included, and keeps its `dev:` origin so the root-relative
+ * imports dev servers write (`/@vite/client`, `/src/App.tsx`) come back here too.
+ *
+ * These modules are passthrough: the manager doesn't cache them and the runtime doesn't
+ * transpile them, so the dev server stays authoritative.
+ */
+pub struct DevServerResolver {
+    protocol: String,
+    path_prefix: String,
+    base_url: url::Url,
+    fetcher: Option<Arc<HttpFetcher>>,
+    options: FetchOptions,
+}
+
+impl DevServerResolver {
+    pub fn new(base_url: &str) -> FlyResult<Self> {
+        let mut base_url = url::Url::parse(base_url)
+            .map_err(|e| FlyError::from(format!("invalid dev server url {}: {}", base_url, e)))?;
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Ok(Self {
+            protocol: DEFAULT_DEV_SERVER_PROTOCOL.to_string(),
+            path_prefix: "/".to_string(),
+            base_url,
+            fetcher: None,
+            // The server is local: fail fast rather than retry, it's either up or it isn't.
+            options: FetchOptions {
+                timeout: Duration::from_secs(10),
+                retries: 0,
+                ..FetchOptions::default()
+            },
+        })
+    }
+
+    pub fn from_config(config: &DevServerConfig) -> FlyResult<Self> {
+        let mut resolver = Self::new(&config.base_url)?;
+        if let Some(ref protocol) = config.protocol {
+            resolver = resolver.with_protocol(protocol);
+        }
+        if let Some(ref path_prefix) = config.path_prefix {
+            resolver = resolver.with_path_prefix(path_prefix);
+        }
+        Ok(resolver)
+    }
+
+    pub fn with_protocol(mut self, protocol: &str) -> Self {
+        self.protocol = protocol.to_string();
+        self
+    }
+
+    /**
+     * Claim only urls under `path_prefix`; the rest of the path is what's asked of the dev
+     * server. Other urls fail to resolve, so the protocol's next resolver gets them.
+     */
+    pub fn with_path_prefix(mut self, path_prefix: &str) -> Self {
+        self.path_prefix = format!("/{}/", path_prefix.trim_matches('/')).replace("//", "/");
+        self
+    }
+
+    /**
+     * Fetch through `fetcher` instead of one allowed to reach the dev server's host.
+     */
+    pub fn with_fetcher(mut self, fetcher: Arc<HttpFetcher>) -> Self {
+        self.fetcher = Some(fetcher);
+        self
+    }
+
+    fn upstream_url(&self, url: &url::Url) -> Option<String> {
+        if url.scheme() != self.protocol || !url.path().starts_with(&self.path_prefix) {
+            return None;
+        }
+        let mut upstream = self
+            .base_url
+            .join(&url.path()[self.path_prefix.len()..])
+            .ok()?;
+        upstream.set_query(url.query());
+        Some(upstream.into_string())
+    }
+}
+
+impl ModuleResolver for DevServerResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => format!("{}://{}", self.protocol, self.path_prefix),
+        };
+        let url = parse_url(module_specifier, &referer_origin_url)?;
+        let upstream_url = match self.upstream_url(&url) {
+            Some(upstream_url) => upstream_url,
+            None => {
+                return Err(FlyError::from(format!(
+                    "Could not resolve {} from {}: not served by the dev server",
+                    module_specifier, referer_origin_url
+                )))
+            }
+        };
+        debug!("forwarding {} to dev server at {}", url, upstream_url);
+        Ok(ModuleSourceData {
+            origin_url: url.into_string(),
+            source_loader: Box::new(DevServerLoader {
+                upstream_url,
+                base_url: self.base_url.to_string(),
+                base_host: self.base_url.host_str().unwrap_or_default().to_string(),
+                fetcher: self.fetcher.clone(),
+                options: self.options.clone(),
+            }),
+        })
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn is_remote(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("dev_server", &self.protocol)
+            .named(&self.name())
+            .with("base_url", self.base_url.as_str())
+            .with("path_prefix", &self.path_prefix)
+    }
+    fn is_passthrough(&self) -> bool {
+        true
+    }
+}
+
+pub struct DevServerLoader {
+    upstream_url: String,
+    base_url: String,
+    base_host: String,
+    fetcher: Option<Arc<HttpFetcher>>,
+    options: FetchOptions,
+}
+
+impl SourceLoader for DevServerLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let fetcher = match self.fetcher {
+            Some(ref fetcher) => fetcher.clone(),
+            // configured by the host, so allowed though it's local
+            None => fetcher_allowing(&self.base_host)?,
+        };
+        let (_, res) = fetch_with_options(
+            &*fetcher,
+            FetchRequest::get(&self.upstream_url),
+            &self.options,
+        )
+        .map_err(|e| match e.kind() {
+            ErrorKind::ConnectionRefused => FlyError::from(format!(
+                "Could not load {}: nothing is listening at {}. Is your dev server running?",
+                self.upstream_url, self.base_url
+            )),
+            _ => e,
+        })?;
+        let source = String::from_utf8(res.body)
+            .map_err(|_| FlyError::from(format!("{} is not utf-8 text", self.upstream_url)))?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: None,
+            source: source.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{FetchResponse, FunctionFetcher};
+    use std::sync::Mutex;
+
+    fn dev_server(requests: Arc<Mutex<Vec<String>>>) -> Arc<HttpFetcher> {
+        Arc::new(FunctionFetcher::new(Box::new(move |req| {
+            requests.lock().unwrap().push(req.url.clone());
+            Ok(FetchResponse {
+                status: 200,
+                headers: vec![],
+                body: format!("// {}", req.url).into_bytes(),
+            })
+        })))
+    }
+
+    #[test]
+    fn test_forwards_protocol_to_dev_server() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let resolver = DevServerResolver::new("http://localhost:5173")
+            .unwrap()
+            .with_fetcher(dev_server(requests.clone()));
+        let main = resolver.resolve_module("dev:///src/main.ts", None).unwrap();
+        assert_eq!("dev:///src/main.ts", main.origin_url);
+        assert_eq!(
+            "// http://localhost:5173/src/main.ts",
+            &*main.source_loader.load_source().unwrap().source
+        );
+        let referer = RefererInfo {
+            origin_url: main.origin_url,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let client = resolver
+            .resolve_module("/@vite/client?t=1", Some(referer))
+            .unwrap();
+        assert_eq!("dev:///@vite/client?t=1", client.origin_url);
+        client.source_loader.load_source().unwrap();
+        assert_eq!(
+            vec![
+                "http://localhost:5173/src/main.ts",
+                "http://localhost:5173/@vite/client?t=1"
+            ],
+            *requests.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_manager_neither_caches_nor_transpiles_dev_modules() {
+        use crate::module_resolver::{
+            ModuleResolverManager, SharedSourceCache, StandardModuleResolverManager,
+        };
+        let requests = Arc::new(Mutex::new(vec![]));
+        let resolver = DevServerResolver::new("http://localhost:5173/")
+            .unwrap()
+            .with_fetcher(dev_server(requests.clone()));
+        let cache = SharedSourceCache::new(1 << 20);
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver) as Box<ModuleResolver>],
+            Some("dev:///".to_string()),
+        )
+        .with_shared_cache(cache.clone());
+        for _ in 0..2 {
+            let module = manager
+                .resolve_blocking("dev:///src/main.ts".to_string(), None)
+                .unwrap();
+            assert!(module.passthrough);
+        }
+        assert_eq!(2, requests.lock().unwrap().len());
+        assert_eq!(0, cache.stats().bytes);
+    }
+
+    #[test]
+    fn test_path_prefix_leaves_other_paths_alone() {
+        let resolver = DevServerResolver::new("http://localhost:5173/")
+            .unwrap()
+            .with_protocol("file")
+            .with_path_prefix("/app/web")
+            .with_fetcher(dev_server(Arc::new(Mutex::new(vec![]))));
+        let module = resolver
+            .resolve_module("file:///app/web/src/main.ts", None)
+            .unwrap();
+        assert_eq!(
+            "// http://localhost:5173/src/main.ts",
+            &*module.source_loader.load_source().unwrap().source
+        );
+        assert!(resolver
+            .resolve_module("file:///app/server.ts", None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_connection_refused_asks_about_dev_server() {
+        let resolver = DevServerResolver::new("http://localhost:5173/")
+            .unwrap()
+            .with_fetcher(Arc::new(FunctionFetcher::new(Box::new(|req| {
+                Err(new(
+                    ErrorKind::ConnectionRefused,
+                    format!("error fetching {}: connection refused", req.url),
+                ))
+            }))));
+        let err = resolver
+            .resolve_module("dev:///src/main.ts", None)
+            .unwrap()
+            .source_loader
+            .load_source()
+            .err()
+            .unwrap();
+        assert_eq!(
+            "Could not load http://localhost:5173/src/main.ts: nothing is listening at http://localhost:5173/. Is your dev server running?",
+            format!("{}", err)
+        );
+    }
+}
@@ -0,0 +1,1119 @@
+use crate::errors::*;
+use crate::metrics::*;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::Digest; // puts trait in scope
+use sha2::Sha256;
+
+use super::ResponseMetadata;
+
+/**
+ * Files that look half written (a source without metadata, a leftover `.tmp`) are only
+ * collected once they're this old, so a writer in another process can finish first.
+ */
+const INCOMPLETE_GRACE: Duration = Duration::from_secs(10 * 60);
+
+const LAST_GC_FILE: &str = "last_gc.json";
+
+/**
+ * Limits on how much of the cache one tenant (app) may use. Past `tenant_quota_bytes` the
+ * tenant's own least recently used entries are evicted; a fetch that would still leave the
+ * tenant above `tenant_hard_cap_bytes` fails instead. Other tenants' entries are never
+ * evicted to make room. With `gc_max_idle_secs` set, entries not read for that long are
+ * collected when the cache is opened.
+ *
+ * `serve_stale_on_error_secs` lets `get_or_refresh` fall back to an expired copy, up to that
+ * long past its expiry, when refreshing it fails. `offline` never refreshes and serves
+ * whatever is cached, however old.
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RemoteModuleCacheConfig {
+    pub tenant_quota_bytes: Option<u64>,
+    pub tenant_hard_cap_bytes: Option<u64>,
+    pub gc_max_idle_secs: Option<u64>,
+    pub serve_stale_on_error_secs: Option<u64>,
+    #[serde(default)]
+    pub offline: bool,
+}
+
+/**
+ * When a cached copy of a remote module is checked with its origin again, revalidating it
+ * with the `ETag` and `Last-Modified` it was served with where the loader supports that.
+ */
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Revalidation {
+    /** On every load, for development against origins that change underneath. */
+    Always,
+    /**
+     * Once the copy is older than the max age, unless its `Cache-Control` says it's
+     * `immutable` or gives it a longer `max-age`.
+     */
+    AfterMaxAge,
+    /** Never, for pinned urls: any cached copy is served, however old. */
+    Never,
+}
+
+impl Default for Revalidation {
+    fn default() -> Self {
+        Revalidation::AfterMaxAge
+    }
+}
+
+/** What revalidating a cached copy with its origin came back with. */
+#[derive(Clone, Debug)]
+pub enum Revalidated {
+    /**
+     * A 304: the cached source is still current. Its headers update the recorded ones and
+     * the copy counts as fetched now.
+     */
+    NotModified(ResponseMetadata),
+    /** A new source, replacing the cached one. */
+    Fetched(String, ResponseMetadata),
+}
+
+enum Refreshed {
+    NotModified(ResponseMetadata),
+    Replaced(String, HashMap<String, String>, Option<ResponseMetadata>),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GcOptions {
+    /** Remove entries not read within this window. */
+    pub max_idle: Option<Duration>,
+    /**
+     * Urls still referenced by lockfiles or vendor directories; with this set, entries for
+     * any other url are orphans and removed.
+     */
+    pub keep_urls: Option<HashSet<String>>,
+    /** Report what would be removed without removing anything. */
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GcReason {
+    Stale,
+    Orphaned,
+    Incomplete,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GcRemoval {
+    pub path: String,
+    pub tenant: Option<String>,
+    pub url: Option<String>,
+    pub bytes: u64,
+    pub reason: GcReason,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub removed: Vec<GcRemoval>,
+    pub freed_bytes: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GcSummary {
+    pub finished_at_ms: u64,
+    pub removed_entries: usize,
+    pub freed_bytes: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct EntryMeta {
+    url: String,
+    tenant: String,
+    size: u64,
+    fetched_at_ms: u64,
+    last_used_ms: u64,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    sha256: Option<String>,
+    #[serde(default)]
+    response: Option<ResponseMetadata>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedRemoteModule {
+    pub url: String,
+    pub source: String,
+    pub headers: HashMap<String, String>,
+    pub fetched_at: SystemTime,
+    /** How the response looked, for entries stored with `put_fetched`. */
+    pub response: Option<ResponseMetadata>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TenantCacheUsage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RemoteModuleCacheInfo {
+    pub root: String,
+    pub entries: usize,
+    pub bytes: u64,
+    pub tenants: BTreeMap<String, TenantCacheUsage>,
+    pub last_gc: Option<GcSummary>,
+}
+
+/**
+ * On-disk cache of fetched remote module sources, one directory per tenant. Every entry is
+ * a source file plus a `.meta.json` sidecar recording the url, the tenant that fetched it
+ * and when it was last used; the index is rebuilt from the sidecars when the cache is opened.
+ * Other processes may share the directory: files are replaced by rename, and a missing or
+ * vanished file is always just a cache miss.
+ */
+pub struct RemoteModuleCache {
+    root: PathBuf,
+    config: RemoteModuleCacheConfig,
+    // (tenant, url) -> entry
+    index: Mutex<HashMap<(String, String), EntryMeta>>,
+}
+
+impl RemoteModuleCache {
+    pub fn open<P: Into<PathBuf>>(root: P, config: RemoteModuleCacheConfig) -> FlyResult<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)?;
+        let mut index = HashMap::new();
+        for tenant_dir in std::fs::read_dir(&root)? {
+            let tenant_dir = tenant_dir?.path();
+            if !tenant_dir.is_dir() {
+                continue;
+            }
+            for file in std::fs::read_dir(&tenant_dir)? {
+                let file = file?.path();
+                if !file.to_string_lossy().ends_with(".meta.json") {
+                    continue;
+                }
+                let meta: Option<EntryMeta> = std::fs::read_to_string(&file)
+                    .ok()
+                    .and_then(|json| serde_json::from_str(&json).ok());
+                match meta {
+                    Some(meta) => {
+                        index.insert((meta.tenant.clone(), meta.url.clone()), meta);
+                    }
+                    None => warn!("ignoring unreadable cache metadata {}", file.display()),
+                }
+            }
+        }
+        let cache = Self {
+            root,
+            config,
+            index: Mutex::new(index),
+        };
+        if let Some(secs) = cache.config.gc_max_idle_secs {
+            cache.gc(&GcOptions {
+                max_idle: Some(Duration::from_secs(secs)),
+                ..GcOptions::default()
+            })?;
+        }
+        Ok(cache)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn get(&self, tenant: &str, url: &str) -> Option<CachedRemoteModule> {
+        let mut index = self.index.lock().unwrap();
+        let key = (tenant.to_string(), url.to_string());
+        let source = {
+            let meta = index.get_mut(&key)?;
+            let source = std::fs::read_to_string(self.source_path(tenant, url)).ok();
+            if source.is_some() {
+                meta.last_used_ms = now_ms();
+                // last use only orders evictions; losing it isn't worth failing the load for
+                let _ = self.write_meta(meta);
+            }
+            source
+        };
+        match source {
+            Some(source) => {
+                let meta = &index[&key];
+                Some(CachedRemoteModule {
+                    url: meta.url.clone(),
+                    source,
+                    headers: meta.headers.clone(),
+                    fetched_at: UNIX_EPOCH + Duration::from_millis(meta.fetched_at_ms),
+                    response: meta.response.clone(),
+                })
+            }
+            None => {
+                // removed from disk behind our back
+                index.remove(&key);
+                None
+            }
+        }
+    }
+
+    /**
+     * The size and fetch time of `tenant`'s copy of `url`, from the index alone. The source
+     * isn't read and the entry isn't marked as used.
+     */
+    pub fn peek(&self, tenant: &str, url: &str) -> Option<(u64, SystemTime)> {
+        let index = self.index.lock().unwrap();
+        let meta = index.get(&(tenant.to_string(), url.to_string()))?;
+        if !self.source_path(tenant, url).is_file() {
+            return None;
+        }
+        Some((
+            meta.size,
+            UNIX_EPOCH + Duration::from_millis(meta.fetched_at_ms),
+        ))
+    }
+
+    /**
+     * Stores `source` as `tenant`'s copy of `url`, first evicting the tenant's least recently
+     * used entries if that takes it over quota.
+     */
+    pub fn put(
+        &self,
+        tenant: &str,
+        url: &str,
+        source: &str,
+        headers: HashMap<String, String>,
+    ) -> FlyResult<()> {
+        self.store(tenant, url, source, headers, None)
+    }
+
+    /**
+     * Like `put`, keeping the response `source` was fetched from alongside it. Only its
+     * already allowlisted headers are stored.
+     */
+    pub fn put_fetched(
+        &self,
+        tenant: &str,
+        url: &str,
+        source: &str,
+        response: ResponseMetadata,
+    ) -> FlyResult<()> {
+        let headers = recorded_headers(&response);
+        self.store(tenant, url, source, headers, Some(response))
+    }
+
+    fn store(
+        &self,
+        tenant: &str,
+        url: &str,
+        source: &str,
+        headers: HashMap<String, String>,
+        response: Option<ResponseMetadata>,
+    ) -> FlyResult<()> {
+        let mut index = self.index.lock().unwrap();
+        let size = source.len() as u64;
+        let key = (tenant.to_string(), url.to_string());
+
+        let mut others: Vec<(u64, String, u64)> = index
+            .values()
+            .filter(|m| m.tenant == tenant && m.url != url)
+            .map(|m| (m.last_used_ms, m.url.clone(), m.size))
+            .collect();
+        let mut used: u64 = others.iter().map(|(_, _, s)| s).sum::<u64>() + size;
+
+        let mut evict = vec![];
+        if let Some(quota) = self.config.tenant_quota_bytes {
+            others.sort();
+            for (_, other_url, other_size) in others {
+                if used <= quota {
+                    break;
+                }
+                used -= other_size;
+                evict.push(other_url);
+            }
+        }
+        if let Some(cap) = self.config.tenant_hard_cap_bytes {
+            if used > cap {
+                return Err(FlyError::from(format!(
+                    "tenant {} exceeded its module cache quota: caching {} ({} bytes) needs {} bytes, the cap is {} bytes",
+                    tenant, url, size, used, cap
+                )));
+            }
+        }
+
+        for other_url in evict {
+            debug!("evicting {} from tenant {} cache", other_url, tenant);
+            self.remove_files(tenant, &other_url);
+            index.remove(&(tenant.to_string(), other_url));
+            MODULE_DISK_CACHE_EVICTIONS
+                .with_label_values(&[tenant])
+                .inc();
+        }
+
+        std::fs::create_dir_all(self.tenant_dir(tenant))?;
+        write_atomically(&self.source_path(tenant, url), source)?;
+        let now = now_ms();
+        let meta = EntryMeta {
+            url: url.to_string(),
+            tenant: tenant.to_string(),
+            size,
+            fetched_at_ms: now,
+            last_used_ms: now,
+            headers,
+            sha256: Some(sha256_hex(source)),
+            response,
+        };
+        self.write_meta(&meta)?;
+        index.insert(key, meta);
+        Ok(())
+    }
+
+    /**
+     * Returns `tenant`'s copy of `url` if it was fetched within `max_age`, or the longer
+     * `max-age` of its `Cache-Control`, or is `immutable`, otherwise calls `refresh` and
+     * caches what it returns. If refreshing fails and the config allows it, an
+     * expired copy is served instead, provided it still matches the digest recorded when it
+     * was stored. Entries from before digests were recorded are never served stale.
+     */
+    pub fn get_or_refresh<F>(
+        &self,
+        tenant: &str,
+        url: &str,
+        max_age: Duration,
+        refresh: F,
+    ) -> FlyResult<CachedRemoteModule>
+    where
+        F: FnOnce() -> FlyResult<(String, HashMap<String, String>)>,
+    {
+        self.get_or_store(tenant, url, max_age, Revalidation::AfterMaxAge, |_| {
+            refresh().map(|(source, headers)| Refreshed::Replaced(source, headers, None))
+        })
+    }
+
+    /**
+     * `get_or_refresh` for sources fetched over HTTP, storing the response metadata that
+     * `fetch` returns with them.
+     */
+    pub fn get_or_fetch<F>(
+        &self,
+        tenant: &str,
+        url: &str,
+        max_age: Duration,
+        fetch: F,
+    ) -> FlyResult<CachedRemoteModule>
+    where
+        F: FnOnce() -> FlyResult<(String, ResponseMetadata)>,
+    {
+        self.get_or_revalidate(tenant, url, max_age, Revalidation::AfterMaxAge, |_| {
+            fetch().map(|(source, response)| Revalidated::Fetched(source, response))
+        })
+    }
+
+    /**
+     * `get_or_fetch` for origins that revalidate: once `revalidation` says the cached copy
+     * is due, `revalidate` is handed it, to send its validators along, and says whether it
+     * changed. A copy that didn't is kept, its source never read from the origin again.
+     */
+    pub fn get_or_revalidate<F>(
+        &self,
+        tenant: &str,
+        url: &str,
+        max_age: Duration,
+        revalidation: Revalidation,
+        revalidate: F,
+    ) -> FlyResult<CachedRemoteModule>
+    where
+        F: FnOnce(Option<&CachedRemoteModule>) -> FlyResult<Revalidated>,
+    {
+        self.get_or_store(tenant, url, max_age, revalidation, |cached| {
+            revalidate(cached).map(|revalidated| match revalidated {
+                Revalidated::NotModified(response) => Refreshed::NotModified(response),
+                Revalidated::Fetched(source, response) => {
+                    Refreshed::Replaced(source, recorded_headers(&response), Some(response))
+                }
+            })
+        })
+    }
+
+    fn get_or_store<F>(
+        &self,
+        tenant: &str,
+        url: &str,
+        max_age: Duration,
+        revalidation: Revalidation,
+        refresh: F,
+    ) -> FlyResult<CachedRemoteModule>
+    where
+        F: FnOnce(Option<&CachedRemoteModule>) -> FlyResult<Refreshed>,
+    {
+        let cached = self
+            .get(tenant, url)
+            .filter(|module| self.is_intact(tenant, module));
+        let age = |module: &CachedRemoteModule| {
+            SystemTime::now()
+                .duration_since(module.fetched_at)
+                .unwrap_or_default()
+        };
+
+        if self.config.offline {
+            return cached.ok_or_else(|| {
+                FlyError::from(format!(
+                    "{} is not in the module cache and fetching is disabled (offline)",
+                    url
+                ))
+            });
+        }
+        if let Some(module) = cached.as_ref() {
+            if is_fresh(module, age(module), max_age, revalidation) {
+                return Ok(module.clone());
+            }
+        }
+
+        let err = match refresh(cached.as_ref()) {
+            Ok(refreshed) => {
+                match refreshed {
+                    Refreshed::NotModified(response) => self.touch(tenant, url, response)?,
+                    Refreshed::Replaced(source, headers, response) => {
+                        self.store(tenant, url, &source, headers, response)?
+                    }
+                }
+                return self.get(tenant, url).ok_or_else(|| {
+                    FlyError::from(format!("{} vanished from the module cache", url))
+                });
+            }
+            Err(e) => e,
+        };
+        let max_stale = match self.config.serve_stale_on_error_secs {
+            Some(secs) => Duration::from_secs(secs),
+            None => return Err(err),
+        };
+        match cached {
+            Some(module) if age(&module) <= max_age + max_stale => {
+                let stale_for = age(&module).checked_sub(max_age).unwrap_or_default();
+                warn!(target: "audit",
+                    "serving stale {} to tenant {} ({}s past expiry) after refresh failed: {}",
+                    url, tenant, stale_for.as_secs(), err
+                );
+                MODULE_DISK_CACHE_STALE_SERVED
+                    .with_label_values(&[tenant])
+                    .inc();
+                Ok(module)
+            }
+            _ => Err(err),
+        }
+    }
+
+    /**
+     * Marks `tenant`'s copy of `url` as fetched now, after its origin said it hadn't
+     * changed, updating the headers recorded with it from the 304 `not_modified`.
+     */
+    fn touch(&self, tenant: &str, url: &str, not_modified: ResponseMetadata) -> FlyResult<()> {
+        let mut index = self.index.lock().unwrap();
+        let meta = index
+            .get_mut(&(tenant.to_string(), url.to_string()))
+            .ok_or_else(|| FlyError::from(format!("{} vanished from the module cache", url)))?;
+        let now = now_ms();
+        meta.fetched_at_ms = now;
+        meta.last_used_ms = now;
+        meta.headers.extend(recorded_headers(&not_modified));
+        match meta.response {
+            // the status and final url stay those of the response the source came from
+            Some(ref mut recorded) => {
+                recorded.headers.extend(not_modified.headers);
+                recorded.fetch_ms = not_modified.fetch_ms;
+            }
+            None => meta.response = Some(not_modified),
+        }
+        self.write_meta(meta)
+    }
+
+    fn is_intact(&self, tenant: &str, module: &CachedRemoteModule) -> bool {
+        let index = self.index.lock().unwrap();
+        let expected = index
+            .get(&(tenant.to_string(), module.url.clone()))
+            .and_then(|meta| meta.sha256.clone());
+        match expected {
+            Some(expected) if expected == sha256_hex(&module.source) => true,
+            Some(_) => {
+                warn!(target: "audit",
+                    "cached {} for tenant {} does not match its recorded digest, ignoring it",
+                    module.url, tenant
+                );
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&self, tenant: &str, url: &str) -> bool {
+        let mut index = self.index.lock().unwrap();
+        self.remove_files(tenant, url);
+        index
+            .remove(&(tenant.to_string(), url.to_string()))
+            .is_some()
+    }
+
+    pub fn tenant_usage(&self, tenant: &str) -> TenantCacheUsage {
+        self.info().tenants.remove(tenant).unwrap_or_default()
+    }
+
+    pub fn info(&self) -> RemoteModuleCacheInfo {
+        let index = self.index.lock().unwrap();
+        let mut info = RemoteModuleCacheInfo {
+            root: self.root.display().to_string(),
+            last_gc: std::fs::read_to_string(self.root.join(LAST_GC_FILE))
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok()),
+            ..RemoteModuleCacheInfo::default()
+        };
+        for meta in index.values() {
+            info.entries += 1;
+            info.bytes += meta.size;
+            let usage = info
+                .tenants
+                .entry(meta.tenant.clone())
+                .or_insert_with(TenantCacheUsage::default);
+            usage.entries += 1;
+            usage.bytes += meta.size;
+        }
+        info
+    }
+
+    /**
+     * The recorded response of each tenant's copy of `url`, by tenant. Reading them doesn't
+     * count as using the entries.
+     */
+    pub fn responses(&self, url: &str) -> BTreeMap<String, ResponseMetadata> {
+        self.index
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|meta| meta.url == url)
+            .filter_map(|meta| Some((meta.tenant.clone(), meta.response.clone()?)))
+            .collect()
+    }
+
+    /**
+     * Removes stale, orphaned and half-written entries. Works from the files on disk rather
+     * than this process's index so entries written by other processes are collected too, and
+     * holds the index lock so nothing in this process reads an entry while it's removed.
+     */
+    pub fn gc(&self, options: &GcOptions) -> FlyResult<GcReport> {
+        let mut index = self.index.lock().unwrap();
+        let now = now_ms();
+        let idle_cutoff = options
+            .max_idle
+            .map(|idle| now.saturating_sub(duration_ms(idle)));
+        let grace_cutoff = SystemTime::now() - INCOMPLETE_GRACE;
+        let mut report = GcReport {
+            dry_run: options.dry_run,
+            ..GcReport::default()
+        };
+
+        for tenant_dir in std::fs::read_dir(&self.root)? {
+            let tenant_dir = match tenant_dir {
+                Ok(entry) => entry.path(),
+                Err(_) => continue,
+            };
+            if !tenant_dir.is_dir() {
+                continue;
+            }
+            let files = match std::fs::read_dir(&tenant_dir) {
+                Ok(files) => files,
+                // removed by a racing process
+                Err(_) => continue,
+            };
+            for file in files.filter_map(|f| f.ok()).map(|f| f.path()) {
+                let name = file.to_string_lossy().to_string();
+                if name.ends_with(".meta.json") {
+                    let source = PathBuf::from(&name[..name.len() - ".meta.json".len()]);
+                    let meta: Option<EntryMeta> = std::fs::read_to_string(&file)
+                        .ok()
+                        .and_then(|json| serde_json::from_str(&json).ok());
+                    let (reason, meta) = match meta {
+                        None => (GcReason::Incomplete, None),
+                        Some(meta) => {
+                            let reason = if idle_cutoff.map_or(false, |c| meta.last_used_ms < c) {
+                                GcReason::Stale
+                            } else if options
+                                .keep_urls
+                                .as_ref()
+                                .map_or(false, |keep| !keep.contains(&meta.url))
+                            {
+                                GcReason::Orphaned
+                            } else {
+                                continue;
+                            };
+                            (reason, Some(meta))
+                        }
+                    };
+                    let bytes = match meta {
+                        Some(ref meta) => meta.size,
+                        None => std::fs::metadata(&source).map(|m| m.len()).unwrap_or(0),
+                    };
+                    report.removed.push(GcRemoval {
+                        path: source.display().to_string(),
+                        tenant: meta.as_ref().map(|m| m.tenant.clone()),
+                        url: meta.as_ref().map(|m| m.url.clone()),
+                        bytes,
+                        reason,
+                    });
+                    if !options.dry_run {
+                        // metadata first: without it the entry is a miss for every reader
+                        let _ = std::fs::remove_file(&file);
+                        let _ = std::fs::remove_file(&source);
+                        if let Some(meta) = meta {
+                            index.remove(&(meta.tenant, meta.url));
+                        }
+                    }
+                    continue;
+                }
+
+                let is_source_without_meta =
+                    !name.ends_with(".tmp") && !Path::new(&format!("{}.meta.json", name)).exists();
+                if !name.ends_with(".tmp") && !is_source_without_meta {
+                    continue;
+                }
+                let metadata = match std::fs::metadata(&file) {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                if metadata.modified().map_or(true, |m| m > grace_cutoff) {
+                    continue;
+                }
+                report.removed.push(GcRemoval {
+                    path: name,
+                    tenant: None,
+                    url: None,
+                    bytes: metadata.len(),
+                    reason: GcReason::Incomplete,
+                });
+                if !options.dry_run {
+                    let _ = std::fs::remove_file(&file);
+                }
+            }
+        }
+
+        report.freed_bytes = report.removed.iter().map(|r| r.bytes).sum();
+        if options.dry_run {
+            info!(
+                "module cache gc (dry run) would free {} bytes in {} files under {}",
+                report.freed_bytes,
+                report.removed.len(),
+                self.root.display()
+            );
+        } else {
+            info!(
+                "module cache gc freed {} bytes in {} files under {}",
+                report.freed_bytes,
+                report.removed.len(),
+                self.root.display()
+            );
+            let summary = GcSummary {
+                finished_at_ms: now_ms(),
+                removed_entries: report.removed.len(),
+                freed_bytes: report.freed_bytes,
+            };
+            write_atomically(
+                &self.root.join(LAST_GC_FILE),
+                &serde_json::to_string(&summary).unwrap(),
+            )?;
+        }
+        Ok(report)
+    }
+
+    fn tenant_dir(&self, tenant: &str) -> PathBuf {
+        self.root.join(&sha256_hex(tenant)[..16])
+    }
+
+    fn source_path(&self, tenant: &str, url: &str) -> PathBuf {
+        self.tenant_dir(tenant).join(sha256_hex(url))
+    }
+
+    fn write_meta(&self, meta: &EntryMeta) -> FlyResult<()> {
+        let path = self
+            .source_path(&meta.tenant, &meta.url)
+            .with_extension("meta.json");
+        write_atomically(&path, &serde_json::to_string(meta).unwrap())
+    }
+
+    fn remove_files(&self, tenant: &str, url: &str) {
+        let path = self.source_path(tenant, url);
+        let _ = std::fs::remove_file(path.with_extension("meta.json"));
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/**
+ * Whether `module`, `age` old, can be served without going back to its origin. `Cache-Control`
+ * only counts for `AfterMaxAge`: it may extend the max age, never shorten it.
+ */
+fn is_fresh(
+    module: &CachedRemoteModule,
+    age: Duration,
+    max_age: Duration,
+    revalidation: Revalidation,
+) -> bool {
+    match revalidation {
+        Revalidation::Always => false,
+        Revalidation::Never => true,
+        Revalidation::AfterMaxAge => {
+            if age <= max_age {
+                return true;
+            }
+            let cache_control = match module.headers.get("cache-control") {
+                Some(cache_control) => cache_control.to_ascii_lowercase(),
+                None => return false,
+            };
+            cache_control.split(',').map(|d| d.trim()).any(|directive| {
+                if directive == "immutable" {
+                    return true;
+                }
+                directive.starts_with("max-age=")
+                    && directive["max-age=".len()..]
+                        .parse::<u64>()
+                        .map_or(false, |secs| age <= Duration::from_secs(secs))
+            })
+        }
+    }
+}
+
+fn recorded_headers(response: &ResponseMetadata) -> HashMap<String, String> {
+    response
+        .headers
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+fn write_atomically(path: &Path, contents: &str) -> FlyResult<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn duration_ms(d: Duration) -> u64 {
+    d.as_secs() * 1000 + u64::from(d.subsec_millis())
+}
+
+fn sha256_hex(s: &str) -> String {
+    let mut hasher = Sha256::default();
+    hasher.input(s.as_bytes());
+    hasher
+        .result()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn now_ms() -> u64 {
+    duration_ms(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(dir: &Path, quota: Option<u64>, cap: Option<u64>) -> RemoteModuleCache {
+        RemoteModuleCache::open(
+            dir,
+            RemoteModuleCacheConfig {
+                tenant_quota_bytes: quota,
+                tenant_hard_cap_bytes: cap,
+                ..RemoteModuleCacheConfig::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn put(cache: &RemoteModuleCache, tenant: &str, url: &str, size: usize) -> FlyResult<()> {
+        cache.put(tenant, url, &"x".repeat(size), HashMap::new())
+    }
+
+    #[test]
+    fn test_entries_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), "text/javascript".to_string());
+        cache(dir.path(), None, None)
+            .put(
+                "app-a",
+                "https://example.com/a.js",
+                "export default 1;",
+                headers.clone(),
+            )
+            .unwrap();
+        let reopened = cache(dir.path(), None, None);
+        let module = reopened.get("app-a", "https://example.com/a.js").unwrap();
+        assert_eq!("export default 1;", module.source);
+        assert_eq!(headers, module.headers);
+        assert_eq!(None, reopened.get("app-b", "https://example.com/a.js"));
+    }
+
+    #[test]
+    fn test_quota_evicts_within_tenant_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), Some(100), None);
+        put(&cache, "small", "https://example.com/s.js", 40).unwrap();
+        put(&cache, "big", "https://example.com/1.js", 40).unwrap();
+        put(&cache, "big", "https://example.com/2.js", 40).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(cache.get("big", "https://example.com/1.js").is_some());
+        put(&cache, "big", "https://example.com/3.js", 40).unwrap();
+
+        // 2.js was the least recently used of big's entries
+        assert!(cache.get("big", "https://example.com/2.js").is_none());
+        assert!(cache.get("big", "https://example.com/1.js").is_some());
+        assert!(cache.get("small", "https://example.com/s.js").is_some());
+        assert_eq!(
+            TenantCacheUsage {
+                entries: 2,
+                bytes: 80
+            },
+            cache.tenant_usage("big")
+        );
+        let info = cache.info();
+        assert_eq!(3, info.entries);
+        assert_eq!(120, info.bytes);
+    }
+
+    #[test]
+    fn test_hard_cap_fails_with_tenant_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), None, Some(100));
+        put(&cache, "other", "https://example.com/o.js", 90).unwrap();
+        put(&cache, "big", "https://example.com/1.js", 60).unwrap();
+        let err = put(&cache, "big", "https://example.com/2.js", 60)
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).starts_with("tenant big exceeded its module cache quota"));
+        assert!(cache.get("big", "https://example.com/1.js").is_some());
+        assert!(cache.get("other", "https://example.com/o.js").is_some());
+
+        // replacing an entry only counts the new size
+        put(&cache, "big", "https://example.com/1.js", 100).unwrap();
+    }
+
+    #[test]
+    fn test_gc_removes_stale_and_orphaned_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), None, None);
+        put(&cache, "a", "https://example.com/old.js", 10).unwrap();
+        put(&cache, "a", "https://example.com/unlocked.js", 20).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        put(&cache, "a", "https://example.com/new.js", 30).unwrap();
+
+        let options = GcOptions {
+            max_idle: Some(Duration::from_millis(25)),
+            keep_urls: Some(
+                vec!["https://example.com/old.js", "https://example.com/new.js"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            ),
+            dry_run: true,
+        };
+        let report = cache.gc(&options).unwrap();
+        assert_eq!(30, report.freed_bytes);
+        assert_eq!(3, cache.info().entries);
+        assert_eq!(None, cache.info().last_gc);
+
+        let report = cache
+            .gc(&GcOptions {
+                max_idle: None,
+                dry_run: false,
+                ..options
+            })
+            .unwrap();
+        assert_eq!(20, report.freed_bytes);
+        assert_eq!(GcReason::Orphaned, report.removed[0].reason);
+        assert!(cache.get("a", "https://example.com/unlocked.js").is_none());
+        assert!(cache.get("a", "https://example.com/old.js").is_some());
+        assert_eq!(Some(20), cache.info().last_gc.map(|gc| gc.freed_bytes));
+
+        // reading old.js above counts as a use
+        let report = cache
+            .gc(&GcOptions {
+                max_idle: Some(Duration::from_secs(60)),
+                ..GcOptions::default()
+            })
+            .unwrap();
+        assert!(report.removed.is_empty());
+    }
+
+    #[test]
+    fn test_gc_leaves_files_of_writers_in_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), None, None);
+        put(&cache, "a", "https://example.com/a.js", 10).unwrap();
+        let tenant_dir = cache.tenant_dir("a");
+        std::fs::write(tenant_dir.join("0123.1234.tmp"), "partial").unwrap();
+        std::fs::write(tenant_dir.join("4567"), "source without meta yet").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let report = cache
+            .gc(&GcOptions {
+                max_idle: Some(Duration::from_secs(0)),
+                ..GcOptions::default()
+            })
+            .unwrap();
+        assert_eq!(1, report.removed.len());
+        assert_eq!(GcReason::Stale, report.removed[0].reason);
+        assert!(tenant_dir.join("0123.1234.tmp").exists());
+        assert!(tenant_dir.join("4567").exists());
+    }
+
+    fn refreshed(source: &str) -> FlyResult<(String, HashMap<String, String>)> {
+        Ok((source.to_string(), HashMap::new()))
+    }
+
+    fn origin_down() -> FlyResult<(String, HashMap<String, String>)> {
+        Err(FlyError::from("connection refused".to_string()))
+    }
+
+    #[test]
+    fn test_serves_stale_copy_when_refresh_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://example.com/a.js";
+        let strict = cache(dir.path(), None, None);
+        strict
+            .get_or_refresh("a", url, Duration::from_secs(0), || refreshed("v1"))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(strict
+            .get_or_refresh("a", url, Duration::from_secs(0), origin_down)
+            .is_err());
+
+        let lenient = RemoteModuleCache::open(
+            dir.path(),
+            RemoteModuleCacheConfig {
+                serve_stale_on_error_secs: Some(60),
+                ..RemoteModuleCacheConfig::default()
+            },
+        )
+        .unwrap();
+        let module = lenient
+            .get_or_refresh("a", url, Duration::from_secs(0), origin_down)
+            .unwrap();
+        assert_eq!("v1", module.source);
+        let module = lenient
+            .get_or_refresh("a", url, Duration::from_secs(0), || refreshed("v2"))
+            .unwrap();
+        assert_eq!("v2", module.source);
+
+        // a copy that no longer matches its digest is never served
+        std::fs::write(lenient.source_path("a", url), "tampered").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(lenient
+            .get_or_refresh("a", url, Duration::from_secs(0), origin_down)
+            .is_err());
+    }
+
+    #[test]
+    fn test_offline_serves_any_cached_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://example.com/a.js";
+        cache(dir.path(), None, None)
+            .put("a", url, "v1", HashMap::new())
+            .unwrap();
+        let offline = RemoteModuleCache::open(
+            dir.path(),
+            RemoteModuleCacheConfig {
+                offline: true,
+                ..RemoteModuleCacheConfig::default()
+            },
+        )
+        .unwrap();
+        let module = offline
+            .get_or_refresh("a", url, Duration::from_secs(0), || {
+                panic!("fetched while offline")
+            })
+            .unwrap();
+        assert_eq!("v1", module.source);
+        let err = offline
+            .get_or_refresh(
+                "a",
+                "https://example.com/b.js",
+                Duration::from_secs(0),
+                || panic!("fetched while offline"),
+            )
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("offline"));
+    }
+
+    #[test]
+    fn test_cache_control_skips_revalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), None, None);
+        let served_with = |cache_control: &str| {
+            let mut response = ResponseMetadata::default();
+            response.status = 200;
+            response
+                .headers
+                .insert("cache-control".to_string(), cache_control.to_string());
+            response
+        };
+        let expired = Duration::from_secs(0);
+        let revalidate = |url, revalidation| {
+            cache.get_or_revalidate("a", url, expired, revalidation, |_| {
+                Err(FlyError::from(format!("revalidated {}", url)))
+            })
+        };
+        cache
+            .put_fetched(
+                "a",
+                "https://example.com/a.js",
+                "a",
+                served_with("public, immutable"),
+            )
+            .unwrap();
+        cache
+            .put_fetched(
+                "a",
+                "https://example.com/b.js",
+                "b",
+                served_with("max-age=3600"),
+            )
+            .unwrap();
+        cache
+            .put_fetched(
+                "a",
+                "https://example.com/c.js",
+                "c",
+                served_with("no-cache"),
+            )
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+
+        let after_max_age = Revalidation::AfterMaxAge;
+        assert!(revalidate("https://example.com/a.js", after_max_age).is_ok());
+        assert!(revalidate("https://example.com/b.js", after_max_age).is_ok());
+        assert!(revalidate("https://example.com/c.js", after_max_age).is_err());
+        assert!(revalidate("https://example.com/a.js", Revalidation::Always).is_err());
+        assert!(revalidate("https://example.com/c.js", Revalidation::Never).is_ok());
+
+        // a 304 keeps the source and restarts its max age
+        let module = cache
+            .get_or_revalidate(
+                "a",
+                "https://example.com/c.js",
+                expired,
+                after_max_age,
+                |cached| {
+                    assert_eq!("c", cached.unwrap().source);
+                    Ok(Revalidated::NotModified(served_with("max-age=60")))
+                },
+            )
+            .unwrap();
+        assert_eq!("c", module.source);
+        assert_eq!(
+            Some(&"max-age=60".to_string()),
+            module.headers.get("cache-control")
+        );
+        assert!(revalidate("https://example.com/c.js", after_max_age).is_ok());
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+
+/**
+ * The canonical origin urls a runtime was started from, which are the modules whose
+ * `import.meta.main` is true. Every runtime, child runtimes included, keeps its own, so a
+ * module can be the entry of one runtime and an ordinary dependency in another.
+ */
+#[derive(Debug, Default)]
+pub struct EntryModules {
+    origins: HashSet<String>,
+}
+
+impl EntryModules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Records that `origin_url` was just loaded, as an entry or imported by another module,
+     * and says whether it's main. Only entry loads add to the set; importing an entry from
+     * elsewhere afterwards doesn't change its answer.
+     */
+    pub fn loaded(&mut self, origin_url: &str, as_entry: bool) -> bool {
+        if as_entry {
+            self.origins.insert(origin_url.to_string());
+            return true;
+        }
+        self.is_main(origin_url)
+    }
+
+    pub fn is_main(&self, origin_url: &str) -> bool {
+        self.origins.contains(origin_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_entries_are_main() {
+        let mut entries = EntryModules::new();
+        assert!(entries.loaded("file:///app/index.ts", true));
+        assert!(!entries.loaded("file:///app/shared.ts", false));
+        // the shared module importing the entry back, statically or with import()
+        assert!(entries.loaded("file:///app/index.ts", false));
+        assert!(!entries.is_main("file:///app/shared.ts"));
+    }
+
+    #[test]
+    fn test_each_runtime_has_its_own_entries() {
+        let mut app = EntryModules::new();
+        let mut worker = EntryModules::new();
+        app.loaded("file:///app/index.ts", true);
+        app.loaded("file:///app/shared.ts", false);
+        assert!(worker.loaded("file:///app/worker.ts", true));
+        assert!(!worker.loaded("file:///app/shared.ts", false));
+        assert!(!worker.loaded("file:///app/index.ts", false));
+        assert!(app.is_main("file:///app/index.ts"));
+        assert!(!app.is_main("file:///app/worker.ts"));
+    }
+}
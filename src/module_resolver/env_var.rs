@@ -0,0 +1,146 @@
+use crate::errors::*;
+
+use std::collections::BTreeSet;
+
+use super::{
+    js_string_literal, LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, StaticSourceLoader,
+};
+
+/**
+ * Resolves `env://NAME` to a module default-exporting the environment variable `NAME` as a
+ * string, read when it's resolved. Only the variables named at construction can be
+ * imported, so guest code can't read the rest of the environment. A listed variable that
+ * isn't set exports `undefined`, or fails to resolve when strict.
+ */
+pub struct EnvVarResolver {
+    allow: BTreeSet<String>,
+    strict: bool,
+}
+
+impl EnvVarResolver {
+    pub fn new(allow: Vec<String>) -> Self {
+        Self {
+            allow: allow.into_iter().collect(),
+            strict: false,
+        }
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+impl ModuleResolver for EnvVarResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        _referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        // not parsed as a url, where the name would be a host and could be lowercased
+        if !module_specifier.starts_with("env:") {
+            return Err(FlyError::from(format!(
+                "Could not resolve {}: not an env:// url",
+                module_specifier
+            )));
+        }
+        let name = module_specifier["env:".len()..].trim_start_matches('/');
+        if !self.allow.contains(name) {
+            return Err(new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "Could not resolve {}: {} isn't an allowed environment variable",
+                    module_specifier, name
+                ),
+            ));
+        }
+        let value = match std::env::var(name) {
+            Ok(value) => js_string_literal(&value),
+            Err(std::env::VarError::NotPresent) if !self.strict => "undefined".to_string(),
+            Err(e) => {
+                return Err(new(
+                    ErrorKind::NotFound,
+                    format!("Could not resolve {}: {}", module_specifier, e),
+                ))
+            }
+        };
+        Ok(ModuleSourceData {
+            origin_url: format!("env://{}", name),
+            source_loader: Box::new(StaticSourceLoader {
+                source: LoadedSourceCode {
+                    kind: ModuleKind::Text,
+                    source_map: None,
+                    source: format!("export default {};", value).into(),
+                    response: None,
+                    provenance: None,
+                    code_cache: None,
+                    compiled_wasm: None,
+                    wasm: None,
+                },
+            }),
+        })
+    }
+    fn get_protocol(&self) -> String {
+        "env".to_string()
+    }
+    fn describe(&self) -> ResolverDescription {
+        let allow: Vec<&str> = self.allow.iter().map(|name| name.as_str()).collect();
+        ResolverDescription::new("env", "env")
+            .named(&self.name())
+            .with("allow", &allow.join(","))
+            .with("strict", &self.strict.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{ModuleResolverManager, StandardModuleResolverManager};
+
+    fn load(resolver: &EnvVarResolver, specifier: &str) -> FlyResult<String> {
+        let module = resolver.resolve_module(specifier, None)?;
+        Ok(module.source_loader.load_source()?.source.to_string())
+    }
+
+    #[test]
+    fn test_exports_allowed_variables_escaped() {
+        std::env::set_var("FLY_TEST_ENV_PORT", "8080");
+        std::env::set_var("FLY_TEST_ENV_QUOTED", "a\"b`c\n\u{2028}é");
+        std::env::set_var("FLY_TEST_ENV_HIDDEN", "hidden");
+        let resolver = EnvVarResolver::new(vec![
+            "FLY_TEST_ENV_PORT".to_string(),
+            "FLY_TEST_ENV_QUOTED".to_string(),
+            "FLY_TEST_ENV_UNSET".to_string(),
+        ]);
+        assert_eq!(
+            "export default \"8080\";",
+            load(&resolver, "env://FLY_TEST_ENV_PORT").unwrap()
+        );
+        assert_eq!(
+            "export default \"a\\\"b`c\\n\\u2028é\";",
+            load(&resolver, "env:FLY_TEST_ENV_QUOTED").unwrap()
+        );
+        assert_eq!(
+            "export default undefined;",
+            load(&resolver, "env://FLY_TEST_ENV_UNSET").unwrap()
+        );
+
+        let err = load(&resolver, "env://FLY_TEST_ENV_HIDDEN").err().unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+        assert!(!format!("{}", err).contains("hidden"));
+        let strict = EnvVarResolver::new(vec!["FLY_TEST_ENV_UNSET".to_string()]).with_strict(true);
+        let err = load(&strict, "env://FLY_TEST_ENV_UNSET").err().unwrap();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+
+        // names keep their case through the manager
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver) as Box<ModuleResolver>],
+            None,
+        );
+        let module = manager
+            .resolve_blocking("env://FLY_TEST_ENV_PORT".to_string(), None)
+            .unwrap();
+        assert_eq!("env://FLY_TEST_ENV_PORT", module.origin_url);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::errors::*;
+
+use super::{LoadedSourceCode, SourceLoader};
+
+/**
+ * Errors that mean the module was found but must not be used, e.g. a failed integrity check
+ * (`InvalidData`) or a denied read. A later copy would only paper over them, so they end a
+ * `FallbackSourceLoader`'s chain instead of moving on.
+ */
+pub fn is_fatal_load_error(kind: ErrorKind) -> bool {
+    match kind {
+        ErrorKind::PermissionDenied | ErrorKind::InvalidData => true,
+        _ => false,
+    }
+}
+
+/**
+ * Loads the same module from the first of several places that has it, e.g. a local copy
+ * before its remote url. Each step is labelled; the label of the one that succeeded becomes
+ * the source's `provenance`. If every step misses, the error lists why each one did.
+ */
+pub struct FallbackSourceLoader {
+    steps: Vec<(String, Box<SourceLoader>)>,
+}
+
+impl FallbackSourceLoader {
+    pub fn new() -> Self {
+        Self { steps: vec![] }
+    }
+
+    /**
+     * Tries `loader` after every step added before it.
+     */
+    pub fn then(mut self, label: &str, loader: Box<SourceLoader>) -> Self {
+        self.steps.push((label.to_string(), loader));
+        self
+    }
+}
+
+impl Default for FallbackSourceLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceLoader for FallbackSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let mut misses = vec![];
+        for (label, loader) in &self.steps {
+            match loader.load_source() {
+                Ok(mut source) => {
+                    source.provenance = Some(label.clone());
+                    return Ok(source);
+                }
+                Err(e) if is_fatal_load_error(e.kind()) => {
+                    return Err(new(e.kind(), format!("{}: {}", label, e)));
+                }
+                Err(e) => {
+                    debug!(
+                        "{} didn't have the module, trying the next source: {}",
+                        label, e
+                    );
+                    misses.push((label.as_str(), e));
+                }
+            }
+        }
+        // NotFound only if nobody had it; otherwise the last real failure, e.g. a timeout
+        let kind = misses
+            .iter()
+            .rev()
+            .map(|(_, e)| e.kind())
+            .find(|kind| *kind != ErrorKind::NotFound)
+            .unwrap_or(ErrorKind::NotFound);
+        let reasons: Vec<String> = misses
+            .iter()
+            .map(|(label, e)| format!("{}: {}", label, e))
+            .collect();
+        Err(new(
+            kind,
+            format!(
+                "no source could load the module ({})",
+                if reasons.is_empty() {
+                    "none configured".to_string()
+                } else {
+                    reasons.join("; ")
+                }
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::ModuleKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Step {
+        result: Result<&'static str, ErrorKind>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl SourceLoader for Step {
+        fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.result {
+                Ok(source) => Ok(LoadedSourceCode {
+                    kind: ModuleKind::JavaScript,
+                    source_map: None,
+                    source: source.into(),
+                    response: None,
+                    provenance: None,
+                    code_cache: None,
+                    compiled_wasm: None,
+                    wasm: None,
+                }),
+                Err(kind) => Err(new(kind, format!("{:?}", kind))),
+            }
+        }
+    }
+
+    fn step(
+        result: Result<&'static str, ErrorKind>,
+        calls: &Arc<AtomicUsize>,
+    ) -> Box<SourceLoader> {
+        Box::new(Step {
+            result,
+            calls: calls.clone(),
+        })
+    }
+
+    #[test]
+    fn test_falls_through_misses_and_records_the_step_used() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let source = FallbackSourceLoader::new()
+            .then("vendored", step(Err(ErrorKind::NotFound), &calls))
+            .then("primary", step(Err(ErrorKind::TimedOut), &calls))
+            .then("replica", step(Ok("export default 1;"), &calls))
+            .then("never", step(Ok("unused"), &calls))
+            .load_source()
+            .unwrap();
+        assert_eq!("export default 1;", &*source.source);
+        assert_eq!(Some("replica".to_string()), source.provenance);
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_fatal_errors_stop_the_chain() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let err = FallbackSourceLoader::new()
+            .then("vendored", step(Err(ErrorKind::InvalidData), &calls))
+            .then("remote", step(Ok("export default 1;"), &calls))
+            .load_source()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert_eq!("vendored: InvalidData", format!("{}", err));
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_aggregates_every_miss() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let err = FallbackSourceLoader::new()
+            .then("vendored", step(Err(ErrorKind::NotFound), &calls))
+            .then("remote", step(Err(ErrorKind::ConnectionRefused), &calls))
+            .then("replica", step(Err(ErrorKind::NotFound), &calls))
+            .load_source()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ConnectionRefused, err.kind());
+        assert_eq!(
+            "no source could load the module (vendored: NotFound; remote: ConnectionRefused; replica: NotFound)",
+            format!("{}", err)
+        );
+
+        let err = FallbackSourceLoader::new().load_source().err().unwrap();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+    }
+}
@@ -0,0 +1,349 @@
+use crate::errors::*;
+use crate::metrics::*;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures::Future;
+use hyper::client::connect::dns::{GaiResolver, Name, Resolve};
+use hyper::client::HttpConnector;
+
+/**
+ * Loopback, link-local (including the 169.254.169.254 metadata endpoint), private, shared
+ * and unspecified ranges. IPv4-mapped IPv6 addresses are checked as IPv4.
+ */
+pub const DEFAULT_BLOCKED_RANGES: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "::/128",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = mask_u32(self.prefix);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = mask_u128(self.prefix);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn mask_u32(prefix: u8) -> u32 {
+    if prefix == 0 {
+        0
+    } else {
+        !0u32 << (32 - u32::from(prefix))
+    }
+}
+
+fn mask_u128(prefix: u8) -> u128 {
+    if prefix == 0 {
+        0
+    } else {
+        !0u128 << (128 - u32::from(prefix))
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = FlyError;
+
+    fn from_str(s: &str) -> FlyResult<Self> {
+        let invalid = || FlyError::from(format!("invalid address range {}", s));
+        let mut parts = s.splitn(2, '/');
+        let network: IpAddr = parts.next().unwrap().parse().map_err(|_| invalid())?;
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None => max_prefix,
+        };
+        if prefix > max_prefix {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix })
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct FetchNetworkPolicyConfig {
+    /** Replaces `DEFAULT_BLOCKED_RANGES` when set. */
+    pub blocked_ranges: Option<Vec<String>>,
+    pub allowed_hosts: Option<Vec<String>>,
+    pub allowed_ranges: Option<Vec<String>>,
+}
+
+/**
+ * Which addresses module fetches may connect to. Checked against the addresses a host
+ * resolved to, not its name, so an import can't reach internal services through a public
+ * name pointing at a private address.
+ */
+#[derive(Clone, Debug)]
+pub struct FetchNetworkPolicy {
+    blocked: Vec<IpRange>,
+    allowed_hosts: HashSet<String>,
+    allowed_ranges: Vec<IpRange>,
+}
+
+impl Default for FetchNetworkPolicy {
+    fn default() -> Self {
+        Self {
+            blocked: DEFAULT_BLOCKED_RANGES
+                .iter()
+                .map(|r| r.parse().unwrap())
+                .collect(),
+            allowed_hosts: HashSet::new(),
+            allowed_ranges: vec![],
+        }
+    }
+}
+
+impl FetchNetworkPolicy {
+    pub fn from_config(config: &FetchNetworkPolicyConfig) -> FlyResult<Self> {
+        let mut policy = Self::default();
+        if let Some(ref blocked) = config.blocked_ranges {
+            policy.blocked = parse_ranges(blocked)?;
+        }
+        if let Some(ref allowed) = config.allowed_ranges {
+            policy.allowed_ranges = parse_ranges(allowed)?;
+        }
+        if let Some(ref hosts) = config.allowed_hosts {
+            policy.allowed_hosts = hosts.iter().map(|h| h.to_lowercase()).collect();
+        }
+        Ok(policy)
+    }
+
+    pub fn allow_host(mut self, host: &str) -> Self {
+        self.allowed_hosts.insert(host.to_lowercase());
+        self
+    }
+
+    pub fn allow_range(mut self, range: IpRange) -> Self {
+        self.allowed_ranges.push(range);
+        self
+    }
+
+    pub fn check_address(&self, host: &str, ip: IpAddr) -> FlyResult<()> {
+        let ip = match ip {
+            IpAddr::V6(v6) => match ipv4_mapped(v6) {
+                Some(v4) => IpAddr::V4(v4),
+                None => ip,
+            },
+            v4 => v4,
+        };
+        if self.allowed_hosts.contains(&host.to_lowercase())
+            || self.allowed_ranges.iter().any(|r| r.contains(ip))
+        {
+            return Ok(());
+        }
+        match self.blocked.iter().find(|r| r.contains(ip)) {
+            Some(range) => {
+                warn!(target: "audit",
+                    "blocked module fetch from {}: resolved address {} is in blocked range {}",
+                    host, ip, range
+                );
+                MODULE_FETCH_BLOCKED.inc();
+                Err(new(
+                    ErrorKind::PermissionDenied,
+                    format!(
+                        "module fetch from {} is not allowed by policy: resolved address {} is in blocked range {}",
+                        host, ip, range
+                    ),
+                ))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /**
+     * Checks hosts written as IP addresses, which connectors use without resolving.
+     */
+    pub fn check_url(&self, url: &url::Url) -> FlyResult<()> {
+        match url.host() {
+            Some(url::Host::Ipv4(ip)) => self.check_address(&ip.to_string(), IpAddr::V4(ip)),
+            Some(url::Host::Ipv6(ip)) => self.check_address(&ip.to_string(), IpAddr::V6(ip)),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * The addresses `host` resolved to, if every one of them is allowed. Any blocked address
+     * rejects the host, so a mixed answer can't be used to reach an internal one.
+     */
+    pub fn filter_resolved(&self, host: &str, addrs: Vec<IpAddr>) -> FlyResult<Vec<IpAddr>> {
+        for ip in &addrs {
+            self.check_address(host, *ip)?;
+        }
+        Ok(addrs)
+    }
+}
+
+fn parse_ranges(ranges: &[String]) -> FlyResult<Vec<IpRange>> {
+    ranges.iter().map(|r| r.parse()).collect()
+}
+
+fn ipv4_mapped(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0xffff, hi, lo] => Some(Ipv4Addr::new(
+            (hi >> 8) as u8,
+            hi as u8,
+            (lo >> 8) as u8,
+            lo as u8,
+        )),
+        _ => None,
+    }
+}
+
+/**
+ * DNS resolver for module fetch connections that applies a `FetchNetworkPolicy` to every
+ * answer. The connector dials exactly the addresses checked here, so the resolved address is
+ * pinned and a rebinding DNS server can't swap in another between check and use.
+ */
+#[derive(Clone)]
+pub struct PolicyResolver<R> {
+    inner: R,
+    policy: Arc<FetchNetworkPolicy>,
+}
+
+impl<R> PolicyResolver<R> {
+    pub fn new(inner: R, policy: Arc<FetchNetworkPolicy>) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl<R> Resolve for PolicyResolver<R>
+where
+    R: Resolve,
+    R::Future: Send + 'static,
+{
+    type Addrs = std::vec::IntoIter<IpAddr>;
+    type Future = Box<Future<Item = Self::Addrs, Error = io::Error> + Send>;
+
+    fn resolve(&self, name: Name) -> Self::Future {
+        let policy = self.policy.clone();
+        let host = name.as_str().to_string();
+        Box::new(self.inner.resolve(name).and_then(move |addrs| {
+            policy
+                .filter_resolved(&host, addrs.collect())
+                .map(|addrs| addrs.into_iter())
+                .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, format!("{}", e)))
+        }))
+    }
+}
+
+/**
+ * An HTTP connector for module fetches enforcing `policy`. It doesn't resolve hosts given as
+ * IP addresses, so those are checked with `check_url`: `fetch_with_options` does, for every
+ * url and redirect, against its fetcher's `network_policy`.
+ */
+pub fn policy_connector(
+    policy: Arc<FetchNetworkPolicy>,
+) -> HttpConnector<PolicyResolver<GaiResolver>> {
+    let mut connector =
+        HttpConnector::new_with_resolver(PolicyResolver::new(GaiResolver::new(4), policy));
+    connector.enforce_http(false);
+    connector
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ranges() {
+        let range: IpRange = "172.16.0.0/12".parse().unwrap();
+        assert!(range.contains(ip("172.31.255.255")));
+        assert!(!range.contains(ip("172.32.0.0")));
+        assert!(!range.contains(ip("::1")));
+        let range: IpRange = "fe80::/10".parse().unwrap();
+        assert!(range.contains(ip("fe80::1")));
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("nope".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn test_default_policy_blocks_internal_addresses() {
+        let policy = FetchNetworkPolicy::default();
+        for blocked in &[
+            "169.254.169.254",
+            "10.1.2.3",
+            "127.0.0.1",
+            "::1",
+            "::ffff:192.168.0.1",
+        ] {
+            let err = policy
+                .check_address("evil.example.com", ip(blocked))
+                .err()
+                .unwrap();
+            let msg = format!("{}", err);
+            assert!(msg.contains("evil.example.com"), msg);
+            assert!(msg.contains("blocked range"), msg);
+        }
+        assert!(policy
+            .check_address("deno.land", ip("93.184.216.34"))
+            .is_ok());
+        assert!(policy
+            .filter_resolved(
+                "mixed.example.com",
+                vec![ip("93.184.216.34"), ip("10.0.0.1")]
+            )
+            .is_err());
+        assert!(policy
+            .check_url(&url::Url::parse("http://169.254.169.254/latest/meta-data").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_allowlist_and_custom_ranges() {
+        let policy = FetchNetworkPolicy::from_config(&FetchNetworkPolicyConfig {
+            blocked_ranges: Some(vec!["203.0.113.0/24".to_string()]),
+            allowed_hosts: Some(vec!["Modules.Internal".to_string()]),
+            allowed_ranges: Some(vec!["203.0.113.7".to_string()]),
+        })
+        .unwrap();
+        assert!(policy
+            .check_address("a.example.com", ip("10.0.0.1"))
+            .is_ok());
+        assert!(policy
+            .check_address("a.example.com", ip("203.0.113.1"))
+            .is_err());
+        assert!(policy
+            .check_address("a.example.com", ip("203.0.113.7"))
+            .is_ok());
+        assert!(policy
+            .check_address("modules.internal", ip("203.0.113.1"))
+            .is_ok());
+    }
+}
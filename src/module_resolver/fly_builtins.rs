@@ -0,0 +1,119 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+
+use super::{MediaType, VirtualModuleRegistry};
+
+pub const FLY_BUILD_SPECIFIER: &str = "fly:build";
+pub const FLY_ENV_SPECIFIER: &str = "fly:env";
+
+/**
+ * Build and deploy metadata exposed to apps as `fly:build`. Supplied by whoever constructs
+ * the runtime; unknown values are exported as `null`.
+ */
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub version: Option<String>,
+    pub git_sha: Option<String>,
+    pub build_timestamp: Option<String>,
+}
+
+/**
+ * The process environment values named in `allow`. Names that aren't set are left out.
+ */
+pub fn allowlisted_env(allow: &[String]) -> BTreeMap<String, String> {
+    allow
+        .iter()
+        .filter_map(|name| std::env::var(name).ok().map(|value| (name.clone(), value)))
+        .collect()
+}
+
+/**
+ * Registers `fly:build` and `fly:env` in `registry`. Both default-export a frozen object;
+ * `fly:build` also exports each field by name. `env` should already be limited to what the
+ * app may see.
+ */
+pub fn register_fly_builtins(
+    registry: &VirtualModuleRegistry,
+    build: &BuildInfo,
+    env: &BTreeMap<String, String>,
+) -> FlyResult<()> {
+    let build_json = serde_json::to_string(build)
+        .map_err(|e| FlyError::from(format!("could not serialize build info: {}", e)))?;
+    registry.insert(
+        FLY_BUILD_SPECIFIER,
+        &format!(
+            "const build = Object.freeze({});\n\
+             export const version = build.version;\n\
+             export const gitSha = build.gitSha;\n\
+             export const buildTimestamp = build.buildTimestamp;\n\
+             export default build;\n",
+            build_json
+        ),
+        MediaType::JavaScript,
+    )?;
+    let env_json = serde_json::to_string(env)
+        .map_err(|e| FlyError::from(format!("could not serialize env: {}", e)))?;
+    registry.insert(
+        FLY_ENV_SPECIFIER,
+        &format!(
+            "const env = Object.freeze({});\nexport default env;\n",
+            env_json
+        ),
+        MediaType::JavaScript,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{ModuleResolverManager, StandardModuleResolverManager};
+
+    fn load(registry: &VirtualModuleRegistry, specifier: &str) -> String {
+        StandardModuleResolverManager::new(vec![], Some("file:///app/".to_string()))
+            .with_virtual_modules(registry.clone())
+            .resolve_blocking(specifier.to_string(), None)
+            .unwrap()
+            .loaded_source
+            .source
+            .to_string()
+    }
+
+    #[test]
+    fn test_build_module_exports_configured_values() {
+        let registry = VirtualModuleRegistry::new();
+        let build = BuildInfo {
+            version: Some("42".to_string()),
+            git_sha: Some("abc123".to_string()),
+            build_timestamp: None,
+        };
+        register_fly_builtins(&registry, &build, &BTreeMap::new()).unwrap();
+        let source = load(&registry, "fly:build");
+        assert!(source.starts_with(
+            "const build = Object.freeze({\"version\":\"42\",\"gitSha\":\"abc123\",\"buildTimestamp\":null});"
+        ));
+        assert!(source.contains("export const gitSha = build.gitSha;"));
+        assert_eq!(
+            "const env = Object.freeze({});\nexport default env;\n",
+            load(&registry, "fly:env")
+        );
+    }
+
+    #[test]
+    fn test_env_module_only_exposes_allowlisted_names() {
+        std::env::set_var("FLY_BUILTINS_TEST_REGION", "ord");
+        std::env::set_var("FLY_BUILTINS_TEST_SECRET", "hunter2");
+        let env = allowlisted_env(&[
+            "FLY_BUILTINS_TEST_REGION".to_string(),
+            "FLY_BUILTINS_TEST_UNSET".to_string(),
+        ]);
+        let registry = VirtualModuleRegistry::new();
+        register_fly_builtins(&registry, &BuildInfo::default(), &env).unwrap();
+        assert_eq!(
+            "const env = Object.freeze({\"FLY_BUILTINS_TEST_REGION\":\"ord\"});\nexport default env;\n",
+            load(&registry, "fly:env")
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use crate::errors::*;
+
+use futures::Future;
+
+use super::{
+    parse_url, LoadedSourceCode, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+pub type ResolveFn =
+    Box<Fn(&str, Option<RefererInfo>) -> FlyResult<ModuleSourceData> + Send + Sync>;
+
+pub type LoadedSourceFuture = Box<Future<Item = LoadedSourceCode, Error = FlyError> + Send>;
+
+/**
+ * Resolves through a closure, for protocols of the host's own, say `app://`. The closure may
+ * be called from several runtimes' threads at once, so state it changes has to sit behind a
+ * `Mutex` or atomics.
+ */
+pub struct FunctionModuleResolver {
+    protocol: String,
+    resolve_fn: ResolveFn,
+}
+
+impl FunctionModuleResolver {
+    pub fn new(protocol: &str, resolve_fn: ResolveFn) -> Self {
+        Self {
+            protocol: protocol.to_string(),
+            resolve_fn,
+        }
+    }
+}
+
+impl ModuleResolver for FunctionModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info.clone() {
+            Some(v) => v.origin_url,
+            None => "".to_string(),
+        };
+        trace!(
+            "resolve_module {} from {}",
+            module_specifier,
+            referer_origin_url
+        );
+        (self.resolve_fn)(module_specifier, referer_info)
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("function", &self.protocol).named(&self.name())
+    }
+}
+
+/**
+ * Like `FunctionModuleResolver`, but the closure returns a future of the module's source, so
+ * it can fetch it over the network without blocking. It's handed the module's origin url,
+ * the specifier joined to its referer. The future runs on the shared event loop once the
+ * module loads.
+ */
+pub struct AsyncFunctionModuleResolver {
+    protocol: String,
+    load_fn: Box<Fn(&str, Option<RefererInfo>) -> LoadedSourceFuture + Send + Sync>,
+}
+
+impl AsyncFunctionModuleResolver {
+    pub fn new(
+        protocol: &str,
+        load_fn: Box<Fn(&str, Option<RefererInfo>) -> LoadedSourceFuture + Send + Sync>,
+    ) -> Self {
+        Self {
+            protocol: protocol.to_string(),
+            load_fn,
+        }
+    }
+}
+
+impl ModuleResolver for AsyncFunctionModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(ref v) => v.origin_url.clone(),
+            None => format!("{}://", self.protocol),
+        };
+        let origin_url = parse_url(module_specifier, &referer_origin_url)?.into_string();
+        let loading = (self.load_fn)(&origin_url, referer_info);
+        Ok(ModuleSourceData {
+            origin_url,
+            source_loader: Box::new(FutureSourceLoader {
+                loading: std::sync::Mutex::new(Some(loading)),
+            }),
+        })
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("async_function", &self.protocol).named(&self.name())
+    }
+}
+
+/**
+ * Waits for a source that's still loading. Loaders run on the blocking pool, so the wait
+ * holds neither a runtime's thread nor the event loop the future runs on.
+ */
+struct FutureSourceLoader {
+    loading: std::sync::Mutex<Option<LoadedSourceFuture>>,
+}
+
+impl SourceLoader for FutureSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let loading = self
+            .loading
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| FlyError::from("module source was loaded already".to_string()))?;
+        futures::sync::oneshot::spawn(loading, &crate::runtime::EVENT_LOOP.0).wait()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        ModuleKind, ModuleResolverManager, StandardModuleResolverManager,
+    };
+    use futures::future;
+
+    #[test]
+    fn test_async_function_resolver_loads_its_future() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(AsyncFunctionModuleResolver::new(
+                "remote",
+                Box::new(|origin_url: &str, _| {
+                    let source = format!("export default {:?};", origin_url);
+                    Box::new(future::lazy(move || {
+                        Ok(LoadedSourceCode {
+                            kind: ModuleKind::JavaScript,
+                            source_map: None,
+                            source: source.into(),
+                            response: None,
+                            provenance: None,
+                            code_cache: None,
+                            compiled_wasm: None,
+                            wasm: None,
+                        })
+                    })) as LoadedSourceFuture
+                }),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        let main = manager
+            .resolve_blocking("remote://app/main.js".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            "export default \"remote://app/main.js\";",
+            &*main.loaded_source.source
+        );
+        let referer = Some(RefererInfo {
+            origin_url: main.origin_url,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        });
+        let dep = manager
+            .resolve_blocking("./dep.js".to_string(), referer)
+            .unwrap();
+        assert_eq!("remote://app/dep.js", dep.origin_url);
+    }
+}
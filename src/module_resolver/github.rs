@@ -0,0 +1,458 @@
+use crate::errors::*;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{
+    fetch_with_options, parse_url, process_wide_fetcher, FetchOptions, FetchRequest, HttpFetcher,
+    LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo, RemoteModuleCache,
+    ResolverDescription, ResponseMetadata, SourceLoader, DEFAULT_TENANT,
+};
+
+const GITHUB_PROTOCOL: &str = "github";
+
+/**
+ * Where a `GitHubModuleResolver` fetches from and as whom. `raw_url` serves public repos;
+ * with a `token`, files are fetched through `api_url` instead, which private repos need.
+ */
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GitHubResolverConfig {
+    pub token: Option<String>,
+    /** Only allow refs that can't move: full commit shas and version tags like `v1.2.0`. */
+    pub strict: bool,
+    pub raw_url: String,
+    pub api_url: String,
+}
+
+impl Default for GitHubResolverConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            strict: false,
+            raw_url: "https://raw.githubusercontent.com".to_string(),
+            api_url: "https://api.github.com".to_string(),
+        }
+    }
+}
+
+/**
+ * A `github://<owner>/<repo>/<path>?ref=<ref>` specifier split into its parts.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitHubSpecifier {
+    pub owner: String,
+    pub repo: String,
+    pub path: String,
+    pub git_ref: String,
+}
+
+impl GitHubSpecifier {
+    /** The origin url of the file, keeping its ref so relative imports stay pinned. */
+    pub fn origin_url(&self) -> String {
+        format!(
+            "{}://{}/{}/{}?ref={}",
+            GITHUB_PROTOCOL, self.owner, self.repo, self.path, self.git_ref
+        )
+    }
+}
+
+/**
+ * Imports files straight from GitHub repos, at the ref the specifier names. Relative imports
+ * without a ref of their own get their referer's when it's in the same repo, so a whole
+ * library loads from one commit or tag. Missing files, refused credentials and exhausted
+ * rate limits fail with `NotFound`, `PermissionDenied` and `RateLimited` respectively.
+ *
+ * With a cache, fetched files are stored under their origin url, ref included, the way
+ * `HttpModuleResolver` stores remote modules.
+ */
+pub struct GitHubModuleResolver {
+    config: GitHubResolverConfig,
+    fetcher: Option<Arc<HttpFetcher>>,
+    options: FetchOptions,
+    cache: Option<(Arc<RemoteModuleCache>, Duration)>,
+}
+
+impl GitHubModuleResolver {
+    pub fn new(config: GitHubResolverConfig) -> Self {
+        Self {
+            config,
+            fetcher: None,
+            options: FetchOptions::default(),
+            cache: None,
+        }
+    }
+
+    /**
+     * Fetch through `fetcher` instead of the process-wide one.
+     */
+    pub fn with_fetcher(mut self, fetcher: Arc<HttpFetcher>) -> Self {
+        self.fetcher = Some(fetcher);
+        self
+    }
+
+    pub fn with_options(mut self, options: FetchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Arc<RemoteModuleCache>, max_age: Duration) -> Self {
+        self.cache = Some((cache, max_age));
+        self
+    }
+
+    /**
+     * Parses `module_specifier` against `referer_origin_url`, taking the referer's ref when
+     * the specifier has none and points into the same repo.
+     */
+    pub fn parse(
+        &self,
+        module_specifier: &str,
+        referer_origin_url: &str,
+    ) -> FlyResult<GitHubSpecifier> {
+        let url = parse_url(module_specifier, referer_origin_url)?;
+        let invalid = |why: &str| {
+            new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Could not resolve {} from {}: {}",
+                    module_specifier, referer_origin_url, why
+                ),
+            )
+        };
+        if url.scheme() != GITHUB_PROTOCOL {
+            return Err(invalid("not a github url"));
+        }
+        let owner = url.host_str().unwrap_or("").to_string();
+        let mut segments = url.path().trim_start_matches('/').splitn(2, '/');
+        let repo = segments.next().unwrap_or("").to_string();
+        let path = segments.next().unwrap_or("").to_string();
+        if owner.is_empty() || repo.is_empty() || path.is_empty() || path.ends_with('/') {
+            return Err(invalid(
+                "expected github://<owner>/<repo>/<path to a file>?ref=<ref>",
+            ));
+        }
+        let git_ref = url
+            .query_pairs()
+            .find(|(key, _)| key == "ref")
+            .map(|(_, value)| value.into_owned())
+            .or_else(|| {
+                let referer = url::Url::parse(referer_origin_url).ok()?;
+                let same_repo = referer.scheme() == GITHUB_PROTOCOL
+                    && referer.host_str() == Some(owner.as_str())
+                    && referer.path().trim_start_matches('/').split('/').next()
+                        == Some(repo.as_str());
+                if !same_repo {
+                    return None;
+                }
+                referer
+                    .query_pairs()
+                    .find(|(key, _)| key == "ref")
+                    .map(|(_, value)| value.into_owned())
+            });
+        let git_ref = match git_ref {
+            Some(ref git_ref) if !git_ref.is_empty() => git_ref.clone(),
+            _ => return Err(invalid("a ref is required, e.g. ?ref=v1.2.0")),
+        };
+        if !git_ref
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' || c == '/')
+            || git_ref.contains("..")
+        {
+            return Err(invalid(&format!("{} is not a valid ref", git_ref)));
+        }
+        if self.config.strict && !is_pinned_ref(&git_ref) {
+            return Err(invalid(&format!(
+                "{} may move, and strict mode only allows commit shas and version tags",
+                git_ref
+            )));
+        }
+        Ok(GitHubSpecifier {
+            owner,
+            repo,
+            path,
+            git_ref,
+        })
+    }
+
+    /** The request fetching `specifier`'s contents. */
+    pub fn request_for(&self, specifier: &GitHubSpecifier) -> FetchRequest {
+        match self.config.token {
+            None => FetchRequest::get(&format!(
+                "{}/{}/{}/{}/{}",
+                self.config.raw_url.trim_end_matches('/'),
+                specifier.owner,
+                specifier.repo,
+                specifier.git_ref,
+                specifier.path
+            )),
+            Some(ref token) => FetchRequest {
+                url: format!(
+                    "{}/repos/{}/{}/contents/{}?ref={}",
+                    self.config.api_url.trim_end_matches('/'),
+                    specifier.owner,
+                    specifier.repo,
+                    specifier.path,
+                    specifier.git_ref
+                ),
+                headers: vec![
+                    ("Authorization".to_string(), format!("token {}", token)),
+                    (
+                        "Accept".to_string(),
+                        "application/vnd.github.v3.raw".to_string(),
+                    ),
+                    ("User-Agent".to_string(), "fly".to_string()),
+                ],
+            },
+        }
+    }
+}
+
+/**
+ * A full commit sha, or a version tag like `v1.2.0` or `2.0`.
+ */
+fn is_pinned_ref(git_ref: &str) -> bool {
+    if git_ref.len() == 40 && git_ref.chars().all(|c| c.is_ascii_hexdigit()) {
+        return true;
+    }
+    let version = git_ref.trim_start_matches('v');
+    let core = version.split('-').next().unwrap();
+    !core.is_empty()
+        && core
+            .split('.')
+            .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+impl ModuleResolver for GitHubModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let (referer_origin_url, tenant) = match referer_info {
+            Some(v) => (v.origin_url, v.app_id),
+            None => (format!("{}://", GITHUB_PROTOCOL), None),
+        };
+        let specifier = self.parse(module_specifier, &referer_origin_url)?;
+        let origin_url = specifier.origin_url();
+        debug!("resolved {} to {}", module_specifier, origin_url);
+        Ok(ModuleSourceData {
+            origin_url: origin_url.clone(),
+            source_loader: Box::new(GitHubSourceLoader {
+                origin_url,
+                request: self.request_for(&specifier),
+                tenant: tenant.unwrap_or_else(|| DEFAULT_TENANT.to_string()),
+                fetcher: self.fetcher.clone(),
+                options: self.options.clone(),
+                cache: self.cache.clone(),
+            }),
+        })
+    }
+    fn get_protocol(&self) -> String {
+        GITHUB_PROTOCOL.to_string()
+    }
+    fn is_remote(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> ResolverDescription {
+        let description = ResolverDescription::new("github", GITHUB_PROTOCOL)
+            .named(&self.name())
+            .with("strict", &self.config.strict.to_string());
+        let description = match self.config.token {
+            Some(_) => description.with("api_url", &self.config.api_url),
+            None => description.with("raw_url", &self.config.raw_url),
+        };
+        match self.cache {
+            Some((ref cache, max_age)) => description
+                .with("cache", &cache.root().display().to_string())
+                .with("max_age_secs", &max_age.as_secs().to_string()),
+            None => description,
+        }
+    }
+}
+
+pub struct GitHubSourceLoader {
+    origin_url: String,
+    request: FetchRequest,
+    tenant: String,
+    fetcher: Option<Arc<HttpFetcher>>,
+    options: FetchOptions,
+    cache: Option<(Arc<RemoteModuleCache>, Duration)>,
+}
+
+impl GitHubSourceLoader {
+    fn fetch(&self) -> FlyResult<(String, ResponseMetadata)> {
+        let fetcher = match self.fetcher {
+            Some(ref fetcher) => fetcher.clone(),
+            None => process_wide_fetcher()?,
+        };
+        let started = Instant::now();
+        let (_, res) = fetch_with_options(&*fetcher, self.request.clone(), &self.options)?;
+        // relative imports resolve against the pinned origin, not wherever GitHub served it
+        let response = ResponseMetadata::capture(&self.origin_url, &res, started.elapsed());
+        let source = String::from_utf8(res.body)
+            .map_err(|_| FlyError::from(format!("{} is not utf-8 text", self.origin_url)))?;
+        Ok((source, response))
+    }
+}
+
+impl SourceLoader for GitHubSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let (source, response) = match self.cache {
+            Some((ref cache, max_age)) => {
+                let module =
+                    cache.get_or_fetch(&self.tenant, &self.origin_url, max_age, || self.fetch())?;
+                (module.source, module.response)
+            }
+            None => {
+                let (source, response) = self.fetch()?;
+                (source, Some(response))
+            }
+        };
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: None,
+            source: source.into(),
+            response,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        FetchResponse, FunctionFetcher, ModuleResolverManager, RemoteModuleCacheConfig,
+        StandardModuleResolverManager,
+    };
+    use std::sync::Mutex;
+
+    fn github(requests: Arc<Mutex<Vec<FetchRequest>>>) -> Arc<HttpFetcher> {
+        Arc::new(FunctionFetcher::new(Box::new(move |req: FetchRequest| {
+            requests.lock().unwrap().push(req.clone());
+            let path = req.url.split('?').next().unwrap();
+            let status = match path.rsplit('/').next().unwrap() {
+                "missing.ts" => 404,
+                "private.ts" => 401,
+                "busy.ts" => 429,
+                _ => 200,
+            };
+            Ok(FetchResponse {
+                status,
+                headers: vec![],
+                body: b"export default 1;".to_vec(),
+            })
+        })))
+    }
+
+    #[test]
+    fn test_relative_imports_stay_pinned() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let dir = tempfile::tempdir().unwrap();
+        let cache =
+            RemoteModuleCache::open(dir.path(), RemoteModuleCacheConfig::default()).unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                GitHubModuleResolver::new(GitHubResolverConfig::default())
+                    .with_fetcher(github(requests.clone()))
+                    .with_cache(Arc::new(cache), Duration::from_secs(60)),
+            ) as Box<ModuleResolver>],
+            None,
+        );
+        let index = manager
+            .resolve_blocking(
+                "github://acme/lib/src/index.ts?ref=v1.2.0".to_string(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            "github://acme/lib/src/index.ts?ref=v1.2.0",
+            index.origin_url
+        );
+        let referer = RefererInfo {
+            origin_url: index.origin_url.clone(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        for _ in 0..2 {
+            let util = manager
+                .resolve_blocking("./util.ts".to_string(), Some(referer.clone()))
+                .unwrap();
+            assert_eq!("github://acme/lib/src/util.ts?ref=v1.2.0", util.origin_url);
+        }
+        let urls: Vec<String> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|r| r.url.clone())
+            .collect();
+        // the second import of util.ts came from the cache
+        assert_eq!(
+            vec![
+                "https://raw.githubusercontent.com/acme/lib/v1.2.0/src/index.ts",
+                "https://raw.githubusercontent.com/acme/lib/v1.2.0/src/util.ts",
+            ],
+            urls
+        );
+    }
+
+    #[test]
+    fn test_refs_and_errors() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let strict = GitHubModuleResolver::new(GitHubResolverConfig {
+            token: Some("s3cret".to_string()),
+            strict: true,
+            ..Default::default()
+        })
+        .with_fetcher(github(requests.clone()));
+        let kind = |specifier: &str| {
+            let result = strict
+                .resolve_module(specifier, None)
+                .and_then(|data| data.source_loader.load_source());
+            result.err().map(|e| e.kind())
+        };
+        assert_eq!(
+            Some(ErrorKind::InvalidInput),
+            kind("github://acme/lib/a.ts")
+        );
+        assert_eq!(
+            Some(ErrorKind::InvalidInput),
+            kind("github://acme/lib/a.ts?ref=main")
+        );
+        assert_eq!(None, kind("github://acme/lib/a.ts?ref=v1.2.0"));
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(None, kind(&format!("github://acme/lib/a.ts?ref={}", sha)));
+        assert_eq!(
+            Some(ErrorKind::NotFound),
+            kind("github://acme/lib/missing.ts?ref=v1")
+        );
+        assert_eq!(
+            Some(ErrorKind::PermissionDenied),
+            kind("github://acme/lib/private.ts?ref=v1")
+        );
+        assert_eq!(
+            Some(ErrorKind::RateLimited),
+            kind("github://acme/lib/busy.ts?ref=v1")
+        );
+
+        // a token goes through the API
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(
+            "https://api.github.com/repos/acme/lib/contents/a.ts?ref=v1.2.0",
+            request.url
+        );
+        assert!(request
+            .headers
+            .contains(&("Authorization".to_string(), "token s3cret".to_string())));
+        assert!(!format!("{:?}", strict.describe()).contains("s3cret"));
+    }
+}
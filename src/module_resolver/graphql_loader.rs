@@ -0,0 +1,206 @@
+use crate::errors::*;
+
+use std::collections::{HashMap, HashSet};
+
+use graphql_parser::query::{
+    parse_query, Definition, Document, FragmentDefinition, OperationDefinition, Selection,
+    SelectionSet,
+};
+use graphql_parser::Style;
+
+use super::data_module::{is_identifier, parse_error};
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+/**
+ * Turns a `.graphql`/`.gql` file into a module. The file text is the default export and every
+ * named operation is a named export holding that operation plus the fragments it spreads from
+ * the same file. Fragments from other files aren't resolved.
+ */
+pub struct GraphqlLoader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+}
+
+impl GraphqlLoader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+        }
+    }
+}
+
+impl SourceLoader for GraphqlLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let raw = self.inner.load_source()?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::Text,
+            source_map: None,
+            source: graphql_to_module(&self.origin_url, &raw.source)?.into(),
+            response: raw.response,
+            provenance: raw.provenance,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+pub fn graphql_to_module(origin_url: &str, graphql_source: &str) -> FlyResult<String> {
+    let document = match parse_query(graphql_source) {
+        Ok(document) => document,
+        Err(e) => {
+            let msg = format!("{}", e);
+            let (line, col) = error_position(&msg).unwrap_or((1, 1));
+            return Err(parse_error(origin_url, "GraphQL", line, col, &msg));
+        }
+    };
+
+    let fragments: HashMap<&str, &FragmentDefinition> = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Fragment(fragment) => Some((fragment.name.as_str(), fragment)),
+            _ => None,
+        })
+        .collect();
+
+    let mut module = format!(
+        "export default {};\n",
+        serde_json::to_string(graphql_source).unwrap()
+    );
+    let mut exported = HashSet::new();
+    for definition in &document.definitions {
+        let operation = match definition {
+            Definition::Operation(operation) => operation,
+            _ => continue,
+        };
+        let (name, position, selection_set) = match operation {
+            OperationDefinition::Query(q) => (&q.name, q.position, &q.selection_set),
+            OperationDefinition::Mutation(m) => (&m.name, m.position, &m.selection_set),
+            OperationDefinition::Subscription(s) => (&s.name, s.position, &s.selection_set),
+            OperationDefinition::SelectionSet(_) => continue,
+        };
+        let name = match name {
+            Some(name) => name,
+            None => continue,
+        };
+        if !exported.insert(name.clone()) {
+            return Err(parse_error(
+                origin_url,
+                "GraphQL",
+                position.line,
+                position.column,
+                &format!("operation {} is defined more than once", name),
+            ));
+        }
+        if !is_identifier(name) {
+            continue;
+        }
+
+        let mut used = vec![];
+        collect_fragments(selection_set, &fragments, &mut used);
+        let mut definitions = vec![definition.clone()];
+        for fragment_name in used {
+            definitions.push(Definition::Fragment(fragments[fragment_name].clone()));
+        }
+        let text = Document { definitions }.format(&Style::default());
+        module.push_str(&format!(
+            "export const {} = {};\n",
+            name,
+            serde_json::to_string(&text).unwrap()
+        ));
+    }
+    Ok(module)
+}
+
+/**
+ * Adds the names of fragments spread inside `selection_set`, directly or through other
+ * fragments of the same file, to `used` in first-use order.
+ */
+fn collect_fragments<'a>(
+    selection_set: &'a SelectionSet,
+    fragments: &HashMap<&str, &'a FragmentDefinition>,
+    used: &mut Vec<&'a str>,
+) {
+    for selection in &selection_set.items {
+        match selection {
+            Selection::Field(field) => collect_fragments(&field.selection_set, fragments, used),
+            Selection::InlineFragment(inline) => {
+                collect_fragments(&inline.selection_set, fragments, used)
+            }
+            Selection::FragmentSpread(spread) => {
+                let name = spread.fragment_name.as_str();
+                if used.contains(&name) {
+                    continue;
+                }
+                // spreads of fragments defined in other files are left for the server to reject
+                if let Some(fragment) = fragments.get(name) {
+                    used.push(&fragment.name);
+                    collect_fragments(&fragment.selection_set, fragments, used);
+                }
+            }
+        }
+    }
+}
+
+/**
+ * The parser only reports errors as text; its messages start with `Parse error at LINE:COL`.
+ */
+fn error_position(msg: &str) -> Option<(usize, usize)> {
+    let rest = &msg[msg.find(" at ")? + 4..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or_else(|| rest.len());
+    let mut parts = rest[..end].split(':');
+    let line = parts.next()?.parse().ok()?;
+    let col = parts.next()?.parse().ok()?;
+    Some((line, col))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUERIES: &str = "query GetUser($id: ID!) {\n  user(id: $id) { ...UserFields }\n}\n\
+                           mutation RenameUser($id: ID!, $name: String!) {\n  renameUser(id: $id, name: $name) { id }\n}\n\
+                           fragment UserFields on User { id name }\n";
+
+    #[test]
+    fn test_named_export_per_operation() {
+        let module = graphql_to_module("file:///app/queries.graphql", QUERIES).unwrap();
+        assert!(module.starts_with(&format!(
+            "export default {};\n",
+            serde_json::to_string(QUERIES).unwrap()
+        )));
+        let get_user = module
+            .lines()
+            .find(|l| l.starts_with("export const GetUser = "))
+            .unwrap();
+        assert!(get_user.contains("query GetUser"));
+        assert!(get_user.contains("fragment UserFields on User"));
+        assert!(!get_user.contains("RenameUser"));
+        let rename = module
+            .lines()
+            .find(|l| l.starts_with("export const RenameUser = "))
+            .unwrap();
+        assert!(!rename.contains("fragment UserFields"));
+    }
+
+    #[test]
+    fn test_duplicate_operation_is_rejected() {
+        let err = graphql_to_module("file:///app/q.gql", "query A { a }\nquery A { b }\n")
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).starts_with("file:///app/q.gql:2:1:"));
+    }
+
+    #[test]
+    fn test_parse_error_has_position() {
+        let err = graphql_to_module("file:///app/q.gql", "query A {\n  a(\n}\n")
+            .err()
+            .unwrap();
+        let msg = format!("{}", err);
+        assert!(msg.starts_with("file:///app/q.gql:3:1:"), msg);
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::RwLock;
+
+/**
+ * Which modules accept hot updates. Patterns are exact origin urls or prefixes ending in `*`;
+ * modules can also opt in at runtime with `ModuleGraph::set_accepts`.
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct HotReloadConfig {
+    #[serde(default)]
+    pub accept: Vec<String>,
+}
+
+impl HotReloadConfig {
+    fn matches(&self, origin_url: &str) -> bool {
+        self.accept.iter().any(|pattern| {
+            if pattern.ends_with('*') {
+                origin_url.starts_with(&pattern[..pattern.len() - 1])
+            } else {
+                pattern == origin_url
+            }
+        })
+    }
+}
+
+/**
+ * The outcome of a changed module. `reload` lists every module whose instance is discarded
+ * and re-run, starting with the changed one and ending with the boundaries that accepted the
+ * update; their state is lost. Anything outside `reload` keeps running untouched.
+ */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum HotUpdate {
+    Update {
+        changed: String,
+        reload: Vec<String>,
+        boundaries: Vec<String>,
+    },
+    /** Some importer chain reached a root without passing an accepting module. */
+    FullRestart { changed: String, reason: String },
+}
+
+/**
+ * A module loaded into the runtime, as the graph last saw it.
+ */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LoadedModuleInfo {
+    pub origin_url: String,
+    /** Bytes of source, a wasm module's base64. */
+    pub size: usize,
+    pub is_wasm: bool,
+    /** The module that first imported it; `None` for entry modules. */
+    pub referer: Option<String>,
+}
+
+/**
+ * The modules loaded so far and the imports between them, by origin url, copied out of the
+ * graph so it isn't held while they're looked through.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ModuleGraphSnapshot {
+    pub modules: Vec<LoadedModuleInfo>,
+    /** (importer, dependency) pairs. */
+    pub imports: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct GraphState {
+    // dependency -> modules importing it
+    importers: HashMap<String, BTreeSet<String>>,
+    // importer -> modules it imports
+    imports: HashMap<String, BTreeSet<String>>,
+    accepting: HashSet<String>,
+    modules: BTreeMap<String, LoadedModuleInfo>,
+}
+
+impl GraphState {
+    /**
+     * The modules along the shortest chain of imports from `from` to `to`, both included.
+     * Nothing is walked for a module that imports nothing, which most new imports are.
+     */
+    fn path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut came_from: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        came_from.insert(from, from);
+        while let Some(url) = queue.pop_front() {
+            if url == to {
+                let mut path = vec![url.to_string()];
+                let mut at = url;
+                while at != from {
+                    at = came_from[at];
+                    path.push(at.to_string());
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for dependency in self.imports.get(url).into_iter().flatten() {
+                if !came_from.contains_key(dependency.as_str()) {
+                    came_from.insert(dependency, url);
+                    queue.push_back(dependency);
+                }
+            }
+        }
+        None
+    }
+}
+
+/**
+ * Import edges between the modules a runtime has loaded, by canonical origin url, kept so a
+ * change to one module can be traced to everything that would see it and so import cycles
+ * can be pointed out.
+ */
+#[derive(Default)]
+pub struct ModuleGraph {
+    config: HotReloadConfig,
+    state: RwLock<GraphState>,
+}
+
+impl ModuleGraph {
+    pub fn new(config: HotReloadConfig) -> Self {
+        Self {
+            config,
+            state: RwLock::new(GraphState::default()),
+        }
+    }
+
+    /**
+     * Records that `importer` imports `dependency`. Returns the cycle the import closes, if
+     * any, as the modules along it from `importer` back to itself.
+     */
+    pub fn record_import(&self, importer: &str, dependency: &str) -> Option<Vec<String>> {
+        let mut state = self.state.write().unwrap();
+        state
+            .importers
+            .entry(dependency.to_string())
+            .or_insert_with(BTreeSet::new)
+            .insert(importer.to_string());
+        let added = state
+            .imports
+            .entry(importer.to_string())
+            .or_insert_with(BTreeSet::new)
+            .insert(dependency.to_string());
+        if !added {
+            return None;
+        }
+        state.path(dependency, importer).map(|path| {
+            let mut cycle = vec![importer.to_string()];
+            cycle.extend(path);
+            cycle
+        })
+    }
+
+    /**
+     * Records that the module at `origin_url` was loaded, imported from `referer`. A module
+     * keeps the referer it was first loaded from.
+     */
+    pub fn record_module(
+        &self,
+        origin_url: &str,
+        size: usize,
+        is_wasm: bool,
+        referer: Option<&str>,
+    ) {
+        let mut state = self.state.write().unwrap();
+        let module = state
+            .modules
+            .entry(origin_url.to_string())
+            .or_insert_with(|| LoadedModuleInfo {
+                origin_url: origin_url.to_string(),
+                size,
+                is_wasm,
+                referer: referer.map(|r| r.to_string()),
+            });
+        module.size = size;
+        module.is_wasm = is_wasm;
+    }
+
+    pub fn contains(&self, origin_url: &str) -> bool {
+        self.state.read().unwrap().modules.contains_key(origin_url)
+    }
+
+    pub fn module_count(&self) -> usize {
+        self.state.read().unwrap().modules.len()
+    }
+
+    /**
+     * How many imports deep `origin_url` is, following each module's first referer back to
+     * one loaded without a referer, which is 0 deep, or to one that wasn't loaded here.
+     */
+    pub fn depth_of(&self, origin_url: &str) -> usize {
+        let state = self.state.read().unwrap();
+        let mut seen = HashSet::new();
+        let mut depth = 0;
+        let mut at = origin_url;
+        while let Some(referer) = state.modules.get(at).and_then(|m| m.referer.as_ref()) {
+            if !seen.insert(referer.as_str()) {
+                break;
+            }
+            depth += 1;
+            at = referer;
+        }
+        depth
+    }
+
+    /**
+     * Every module loaded so far and every import recorded, each sorted by origin url.
+     */
+    pub fn snapshot(&self) -> ModuleGraphSnapshot {
+        let state = self.state.read().unwrap();
+        let mut imports: Vec<(String, String)> = state
+            .imports
+            .iter()
+            .flat_map(|(importer, dependencies)| {
+                dependencies
+                    .iter()
+                    .map(move |dependency| (importer.clone(), dependency.clone()))
+            })
+            .collect();
+        imports.sort();
+        ModuleGraphSnapshot {
+            modules: state.modules.values().cloned().collect(),
+            imports,
+        }
+    }
+
+    pub fn set_accepts(&self, origin_url: &str, accepts: bool) {
+        let mut state = self.state.write().unwrap();
+        if accepts {
+            state.accepting.insert(origin_url.to_string());
+        } else {
+            state.accepting.remove(origin_url);
+        }
+    }
+
+    pub fn imports_of(&self, origin_url: &str) -> Vec<String> {
+        self.state
+            .read()
+            .unwrap()
+            .imports
+            .get(origin_url)
+            .map(|i| i.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn importers_of(&self, origin_url: &str) -> Vec<String> {
+        self.state
+            .read()
+            .unwrap()
+            .importers
+            .get(origin_url)
+            .map(|i| i.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /**
+     * Walks up from `changed` through its importers, stopping each chain at the first module
+     * that accepts updates. A chain that runs out of importers first means the change can't
+     * be contained and the runtime has to restart.
+     */
+    pub fn plan_update(&self, changed: &str) -> HotUpdate {
+        let state = self.state.read().unwrap();
+        let accepts = |url: &str| state.accepting.contains(url) || self.config.matches(url);
+
+        let mut reload = vec![];
+        let mut boundaries = vec![];
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(changed.to_string());
+        seen.insert(changed.to_string());
+        while let Some(url) = queue.pop_front() {
+            reload.push(url.clone());
+            if accepts(&url) {
+                boundaries.push(url);
+                continue;
+            }
+            let importers = match state.importers.get(&url) {
+                Some(importers) if !importers.is_empty() => importers,
+                _ => {
+                    return HotUpdate::FullRestart {
+                        changed: changed.to_string(),
+                        reason: format!("{} is not imported by any module accepting updates", url),
+                    }
+                }
+            };
+            for importer in importers {
+                if seen.insert(importer.clone()) {
+                    queue.push_back(importer.clone());
+                }
+            }
+        }
+        HotUpdate::Update {
+            changed: changed.to_string(),
+            reload,
+            boundaries,
+        }
+    }
+
+    /**
+     * Forgets the edges out of `origin_url`; it records them again when re-evaluated.
+     */
+    pub fn forget_imports_of(&self, origin_url: &str) {
+        let mut state = self.state.write().unwrap();
+        for importers in state.importers.values_mut() {
+            importers.remove(origin_url);
+        }
+        state.imports.remove(origin_url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // app.js imports view.js imports util.js
+    fn chain(config: HotReloadConfig) -> ModuleGraph {
+        let graph = ModuleGraph::new(config);
+        graph.record_import("file:///app.js", "file:///view.js");
+        graph.record_import("file:///view.js", "file:///util.js");
+        graph
+    }
+
+    fn update(reload: &[&str], boundaries: &[&str]) -> HotUpdate {
+        HotUpdate::Update {
+            changed: reload[0].to_string(),
+            reload: reload.iter().map(|s| s.to_string()).collect(),
+            boundaries: boundaries.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_update_stops_at_accepting_importer() {
+        let graph = chain(HotReloadConfig::default());
+        graph.set_accepts("file:///view.js", true);
+        assert_eq!(
+            update(
+                &["file:///util.js", "file:///view.js"],
+                &["file:///view.js"]
+            ),
+            graph.plan_update("file:///util.js")
+        );
+        // a self-accepting module only reloads itself
+        assert_eq!(
+            update(&["file:///view.js"], &["file:///view.js"]),
+            graph.plan_update("file:///view.js")
+        );
+    }
+
+    #[test]
+    fn test_update_escaping_boundaries_restarts() {
+        let graph = chain(HotReloadConfig::default());
+        match graph.plan_update("file:///util.js") {
+            HotUpdate::FullRestart { reason, .. } => assert!(reason.contains("file:///app.js")),
+            plan => panic!("expected a restart, got {:?}", plan),
+        }
+
+        // configured boundaries work like runtime ones
+        let graph = chain(HotReloadConfig {
+            accept: vec!["file:///app*".to_string()],
+        });
+        assert_eq!(
+            update(
+                &["file:///util.js", "file:///view.js", "file:///app.js"],
+                &["file:///app.js"]
+            ),
+            graph.plan_update("file:///util.js")
+        );
+    }
+
+    #[test]
+    fn test_every_importer_chain_needs_a_boundary() {
+        let graph = chain(HotReloadConfig::default());
+        graph.record_import("file:///worker.js", "file:///util.js");
+        graph.set_accepts("file:///view.js", true);
+        assert!(match graph.plan_update("file:///util.js") {
+            HotUpdate::FullRestart { .. } => true,
+            _ => false,
+        });
+        graph.set_accepts("file:///worker.js", true);
+        assert_eq!(
+            update(
+                &["file:///util.js", "file:///view.js", "file:///worker.js"],
+                &["file:///view.js", "file:///worker.js"]
+            ),
+            graph.plan_update("file:///util.js")
+        );
+    }
+
+    #[test]
+    fn test_diamonds_are_not_cycles() {
+        // main imports a and b, which both import c
+        let graph = ModuleGraph::default();
+        for (importer, dependency) in &[("main", "a"), ("main", "b"), ("a", "c"), ("b", "c")] {
+            assert_eq!(None, graph.record_import(importer, dependency));
+        }
+        assert_eq!(vec!["a", "b"], graph.importers_of("c"));
+        assert_eq!(vec!["a", "b"], graph.imports_of("main"));
+
+        assert_eq!(
+            Some(
+                vec!["c", "main", "a", "c"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            ),
+            graph.record_import("c", "main")
+        );
+        // reported once, when the import closing it is first seen
+        assert_eq!(None, graph.record_import("c", "main"));
+        let self_import = graph.record_import("c", "c").unwrap();
+        assert_eq!(vec!["c", "c"], self_import);
+    }
+}
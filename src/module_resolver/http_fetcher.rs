@@ -0,0 +1,846 @@
+use crate::errors::*;
+use crate::runtime::EVENT_LOOP;
+
+use super::fetch_policy::{policy_connector, FetchNetworkPolicy, PolicyResolver};
+use super::policy::host_matches;
+use super::progress::{
+    process_wide_progress, DownloadProgressReporter, ProgressObserver, ProgressOptions,
+};
+use super::proxy::{proxy_authorization, ProxyConfig, ProxyConnector};
+use super::trace::record_step;
+use super::{module_byte_limit, ModuleLimits, TraceStep};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use futures::future::Either;
+use futures::{Future, Stream};
+use hyper::client::connect::dns::GaiResolver;
+use hyper::client::connect::Connect;
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Request};
+use hyper_tls::HttpsConnector;
+
+#[derive(Clone, Default)]
+pub struct FetchRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl FetchRequest {
+    pub fn get(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            headers: vec![],
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /** Whether the request only wants the body if it changed since a cached copy. */
+    pub fn is_conditional(&self) -> bool {
+        self.headers.iter().any(|(k, _)| {
+            k.eq_ignore_ascii_case("if-none-match") || k.eq_ignore_ascii_case("if-modified-since")
+        })
+    }
+}
+
+/** Header values can be credentials, so they're never printed. */
+impl fmt::Debug for FetchRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FetchRequest")
+            .field("url", &self.url)
+            .field("headers", &redacted_headers(&self.headers))
+            .finish()
+    }
+}
+
+fn redacted_headers(headers: &[(String, String)]) -> Vec<String> {
+    headers
+        .iter()
+        .map(|(name, _)| format!("{}: [redacted]", name))
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FetchResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl FetchResponse {
+    /** The first header named `name`, compared case-insensitively. */
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/**
+ * Response headers kept with a fetched module. Anything else, credentials and cookies
+ * included, is dropped before the response is stored or logged.
+ */
+pub const RECORDED_RESPONSE_HEADERS: &[&str] = &[
+    "content-type",
+    "etag",
+    "last-modified",
+    "cache-control",
+    "x-typescript-types",
+];
+
+/**
+ * What the response a module came from looked like: where redirects ended up, its status,
+ * the `RECORDED_RESPONSE_HEADERS` it had and how long fetching took.
+ */
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ResponseMetadata {
+    pub final_url: String,
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub fetch_ms: u64,
+}
+
+impl ResponseMetadata {
+    pub fn capture(final_url: &str, res: &FetchResponse, elapsed: Duration) -> Self {
+        Self {
+            final_url: final_url.to_string(),
+            status: res.status,
+            headers: RECORDED_RESPONSE_HEADERS
+                .iter()
+                .filter_map(|name| res.header(name).map(|v| (name.to_string(), v.to_string())))
+                .collect(),
+            fetch_ms: elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()),
+        }
+    }
+}
+
+pub type FetchFuture = Box<Future<Item = FetchResponse, Error = FlyError> + Send>;
+
+/**
+ * Makes one HTTP request for module resolvers. Implementations don't follow redirects, retry
+ * or time out; `fetch_with_options` does that on top, so every fetcher gets the same behavior.
+ */
+pub trait HttpFetcher: Send + Sync {
+    fn fetch(&self, request: FetchRequest) -> FetchFuture;
+
+    /**
+     * What `fetch_with_options` checks every url against, redirects included, before it's
+     * fetched. Connectors only check the addresses hosts resolve to, so this is what keeps
+     * urls naming an IP address in check.
+     */
+    fn network_policy(&self) -> Option<Arc<FetchNetworkPolicy>> {
+        None
+    }
+}
+
+/**
+ * The default fetcher, a hyper client on the runtime's event loop going through the proxy
+ * the environment names, see `ProxyConfig::from_env`, and connecting only where the default
+ * `FetchNetworkPolicy` allows. `from_client` takes a client with any connector, e.g. one
+ * built around `policy_connector`, checking urls against no policy unless given one with
+ * `with_network_policy`. Downloads report progress to the process-wide `ProgressHub` unless
+ * `with_progress` says otherwise.
+ */
+pub struct HyperFetcher<C> {
+    client: Client<C, Body>,
+    progress: Option<(Arc<ProgressObserver>, ProgressOptions)>,
+    proxy: Option<ProxyConfig>,
+    policy: Option<Arc<FetchNetworkPolicy>>,
+}
+
+impl HyperFetcher<HttpsConnector<ProxyConnector<HttpConnector<PolicyResolver<GaiResolver>>>>> {
+    pub fn new() -> FlyResult<Self> {
+        Self::proxied(ProxyConfig::from_env()?)
+    }
+
+    /** Fetches through `proxy` rather than the environment's, directly given `None`. */
+    pub fn proxied(proxy: Option<ProxyConfig>) -> FlyResult<Self> {
+        Self::with_policy(proxy, FetchNetworkPolicy::default())
+    }
+
+    /**
+     * Fetches through `proxy`, connecting only where `policy` allows. The proxy's own hosts
+     * are configured by the host, not named by modules, so they're connected to whatever
+     * they resolve to.
+     */
+    pub fn with_policy(proxy: Option<ProxyConfig>, policy: FetchNetworkPolicy) -> FlyResult<Self> {
+        let tls = native_tls::TlsConnector::new()
+            .map_err(|e| FlyError::from(format!("could not create TLS connector: {}", e)))?;
+        let proxy_hosts = proxy
+            .iter()
+            .flat_map(|proxy| proxy.http.iter().chain(proxy.https.iter()))
+            .map(|server| server.host().to_string())
+            .collect::<Vec<_>>();
+        let connecting = proxy_hosts
+            .iter()
+            .fold(policy.clone(), |policy, host| policy.allow_host(host));
+        let connector = HttpsConnector::from((
+            ProxyConnector::new(policy_connector(Arc::new(connecting)), proxy.clone()),
+            tls,
+        ));
+        let mut fetcher = Self::from_client(
+            Client::builder()
+                .executor(EVENT_LOOP.0.clone())
+                .build(connector),
+        )
+        .with_network_policy(Some(Arc::new(policy)));
+        fetcher.proxy = proxy;
+        Ok(fetcher)
+    }
+}
+
+impl<C> HyperFetcher<C> {
+    pub fn from_client(client: Client<C, Body>) -> Self {
+        Self {
+            client,
+            progress: Some((process_wide_progress(), ProgressOptions::default())),
+            proxy: None,
+            policy: None,
+        }
+    }
+
+    /** The policy urls are checked against, see `HttpFetcher::network_policy`. */
+    pub fn with_network_policy(mut self, policy: Option<Arc<FetchNetworkPolicy>>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_progress(
+        mut self,
+        progress: Option<(Arc<ProgressObserver>, ProgressOptions)>,
+    ) -> Self {
+        self.progress = progress;
+        self
+    }
+}
+
+impl<C> HttpFetcher for HyperFetcher<C>
+where
+    C: Connect + Sync + 'static,
+    C::Transport: 'static,
+    C::Future: 'static,
+{
+    fn fetch(&self, request: FetchRequest) -> FetchFuture {
+        let mut builder = Request::get(request.url.as_str());
+        for (name, value) in &request.headers {
+            builder.header(name.as_str(), value.as_str());
+        }
+        // a tunnel's is sent with its CONNECT instead
+        if let Ok(url) = url::Url::parse(&request.url) {
+            if let Some(authorization) = proxy_authorization(self.proxy.as_ref(), &url) {
+                builder.header("Proxy-Authorization", authorization);
+            }
+        }
+        let req = match builder.body(Body::empty()) {
+            Ok(req) => req,
+            Err(e) => {
+                return Box::new(futures::future::err(FlyError::from(format!(
+                    "invalid request for {}: {}",
+                    request.url, e
+                ))))
+            }
+        };
+        let url = request.url;
+        let progress = self.progress.clone();
+        // read here, on the thread the byte limit is in effect on
+        let limits = module_byte_limit().map(|max_module_bytes| ModuleLimits {
+            max_module_bytes,
+            ..ModuleLimits::default()
+        });
+        let check_bytes = move |url: &str, bytes: u64| {
+            limits.map_or(Ok(()), |limits| limits.check_bytes(url, bytes))
+        };
+        let request_url = url.clone();
+        Box::new(
+            self.client
+                .request(req)
+                .map_err(move |e| hyper_error(&request_url, e))
+                .and_then(move |res| {
+                    let status = res.status().as_u16();
+                    let headers = res
+                        .headers()
+                        .iter()
+                        .filter_map(|(k, v)| {
+                            v.to_str()
+                                .ok()
+                                .map(|v| (k.as_str().to_string(), v.to_string()))
+                        })
+                        .collect();
+                    let total = res
+                        .headers()
+                        .get(hyper::header::CONTENT_LENGTH)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok());
+                    // refused before any of the body is read
+                    if let Err(e) = check_bytes(&url, total.unwrap_or(0)) {
+                        return Either::A(futures::future::err(e));
+                    }
+                    let reporter = progress.map(|(observer, options)| {
+                        DownloadProgressReporter::new(&url, total, observer, options)
+                    });
+                    let body_url = url.clone();
+                    Either::B(
+                        res.into_body()
+                            .map_err(move |e| hyper_error(&body_url, e))
+                            .fold(
+                                (Vec::new(), reporter),
+                                move |(mut body, mut reporter), chunk| {
+                                    if let Some(ref mut reporter) = reporter {
+                                        reporter.advance(chunk.len() as u64);
+                                    }
+                                    body.extend_from_slice(&chunk);
+                                    // a missing or lying Content-Length is caught as the body comes
+                                    check_bytes(&url, body.len() as u64)?;
+                                    Ok::<_, FlyError>((body, reporter))
+                                },
+                            )
+                            .map(move |(body, reporter)| {
+                                if let Some(reporter) = reporter {
+                                    reporter.finish();
+                                }
+                                FetchResponse {
+                                    status,
+                                    headers,
+                                    body,
+                                }
+                            }),
+                    )
+                }),
+        )
+    }
+
+    fn network_policy(&self) -> Option<Arc<FetchNetworkPolicy>> {
+        self.policy.clone()
+    }
+}
+
+fn hyper_error(url: &str, e: hyper::Error) -> FlyError {
+    let msg = format!("error fetching {}: {}", url, e);
+    let refused = e
+        .cause2()
+        .and_then(|cause| cause.downcast_ref::<std::io::Error>())
+        .map(|io| io.kind() == std::io::ErrorKind::ConnectionRefused)
+        .unwrap_or(false);
+    if refused {
+        new(ErrorKind::ConnectionRefused, msg)
+    } else {
+        FlyError::from(msg)
+    }
+}
+
+/**
+ * Wraps a closure, for stubbing the network in tests.
+ */
+pub struct FunctionFetcher {
+    fetch_fn: Box<Fn(FetchRequest) -> FlyResult<FetchResponse> + Send + Sync>,
+}
+
+impl FunctionFetcher {
+    pub fn new(fetch_fn: Box<Fn(FetchRequest) -> FlyResult<FetchResponse> + Send + Sync>) -> Self {
+        Self { fetch_fn }
+    }
+}
+
+impl HttpFetcher for FunctionFetcher {
+    fn fetch(&self, request: FetchRequest) -> FetchFuture {
+        Box::new(futures::future::result((self.fetch_fn)(request)))
+    }
+}
+
+lazy_static! {
+    static ref PROCESS_WIDE: RwLock<Option<Arc<HttpFetcher>>> = RwLock::new(None);
+}
+
+/**
+ * Replaces the fetcher remote resolvers use when none is given to them, so embedders can
+ * share their own connection pool or egress rules.
+ */
+pub fn set_process_wide_fetcher(fetcher: Arc<HttpFetcher>) {
+    *PROCESS_WIDE.write().unwrap() = Some(fetcher);
+}
+
+/**
+ * The fetcher set with `set_process_wide_fetcher`, or a `HyperFetcher` created on first use.
+ */
+pub fn process_wide_fetcher() -> FlyResult<Arc<HttpFetcher>> {
+    if let Some(ref fetcher) = *PROCESS_WIDE.read().unwrap() {
+        return Ok(fetcher.clone());
+    }
+    let mut fetcher = PROCESS_WIDE.write().unwrap();
+    if fetcher.is_none() {
+        *fetcher = Some(Arc::new(HyperFetcher::new()?));
+    }
+    Ok(fetcher.as_ref().unwrap().clone())
+}
+
+lazy_static! {
+    static ref ALLOWING: Mutex<HashMap<String, Arc<HttpFetcher>>> = Mutex::new(HashMap::new());
+}
+
+/**
+ * A fetcher like the process-wide default one, but allowed to connect to `host` whatever it
+ * resolves to, for servers the host's configuration names, like a local dev server. One per
+ * host, shared by every resolver using it.
+ */
+pub fn fetcher_allowing(host: &str) -> FlyResult<Arc<HttpFetcher>> {
+    let mut allowing = ALLOWING.lock().unwrap();
+    if let Some(fetcher) = allowing.get(host) {
+        return Ok(fetcher.clone());
+    }
+    let fetcher: Arc<HttpFetcher> = Arc::new(HyperFetcher::with_policy(
+        ProxyConfig::from_env()?,
+        FetchNetworkPolicy::default().allow_host(host),
+    )?);
+    allowing.insert(host.to_string(), fetcher.clone());
+    Ok(fetcher)
+}
+
+lazy_static! {
+    static ref PROXIED: Mutex<HashMap<ProxyConfig, Arc<HttpFetcher>>> = Mutex::new(HashMap::new());
+}
+
+/**
+ * The fetcher for resolvers without one of their own: `options.proxy`'s, one per proxy and
+ * shared by every resolver using it, or the process-wide one.
+ */
+pub fn fetcher_for(options: &FetchOptions) -> FlyResult<Arc<HttpFetcher>> {
+    let proxy = match options.proxy {
+        Some(ref proxy) => proxy,
+        None => return process_wide_fetcher(),
+    };
+    let mut proxied = PROXIED.lock().unwrap();
+    if let Some(fetcher) = proxied.get(proxy) {
+        return Ok(fetcher.clone());
+    }
+    let fetcher: Arc<HttpFetcher> = Arc::new(HyperFetcher::proxied(Some(proxy.clone()))?);
+    proxied.insert(proxy.clone(), fetcher.clone());
+    Ok(fetcher)
+}
+
+/** How many redirects a fetch follows unless told otherwise. */
+pub const DEFAULT_MAX_REDIRECTS: u32 = 10;
+
+#[derive(Clone)]
+pub struct FetchOptions {
+    /** Per attempt, including the redirects it follows. */
+    pub timeout: Duration,
+    /** Extra attempts after a network error or a 5xx response. */
+    pub retries: u32,
+    pub max_redirects: u32,
+    pub retry_backoff: Duration,
+    /** Follow redirects from `https` to `http`, which are refused otherwise. */
+    pub allow_insecure_redirects: bool,
+    /**
+     * Headers, like a registry's `Authorization`, sent only to hosts matching their pattern:
+     * `registry.example.com`, or `*.example.com` for the hosts under it. They're dropped as
+     * soon as a redirect leaves the origin the fetch started at.
+     */
+    pub host_headers: HashMap<String, Vec<(String, String)>>,
+    /**
+     * The proxy to fetch through instead of the environment's, for resolvers without a
+     * fetcher of their own; see `fetcher_for`.
+     */
+    pub proxy: Option<ProxyConfig>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 2,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            retry_backoff: Duration::from_millis(200),
+            allow_insecure_redirects: false,
+            host_headers: HashMap::new(),
+            proxy: None,
+        }
+    }
+}
+
+/** Like `FetchRequest`'s, with `host_headers` by name only. */
+impl fmt::Debug for FetchOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let host_headers: BTreeMap<&String, Vec<String>> = self
+            .host_headers
+            .iter()
+            .map(|(pattern, headers)| (pattern, redacted_headers(headers)))
+            .collect();
+        f.debug_struct("FetchOptions")
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("max_redirects", &self.max_redirects)
+            .field("retry_backoff", &self.retry_backoff)
+            .field("allow_insecure_redirects", &self.allow_insecure_redirects)
+            .field("host_headers", &host_headers)
+            .field("proxy", &self.proxy)
+            .finish()
+    }
+}
+
+impl FetchOptions {
+    /** The `host_headers` for `url`, those of more than one matching pattern in their order. */
+    pub fn headers_for(&self, url: &url::Url) -> Vec<(String, String)> {
+        let host = match url.host_str() {
+            Some(host) => host.to_lowercase(),
+            None => return vec![],
+        };
+        let mut patterns: Vec<&String> = self
+            .host_headers
+            .keys()
+            .filter(|pattern| host_matches(&pattern.to_lowercase(), &host))
+            .collect();
+        patterns.sort();
+        patterns
+            .into_iter()
+            .flat_map(|pattern| self.host_headers[pattern].iter().cloned())
+            .collect()
+    }
+}
+
+/**
+ * Fetches `request` through `fetcher`, following redirects and retrying failures. The
+ * response's final url is returned with it; any non-2xx status left at the end is an error,
+ * except a 304 to a conditional request.
+ */
+pub fn fetch_with_options(
+    fetcher: &HttpFetcher,
+    request: FetchRequest,
+    options: &FetchOptions,
+) -> FlyResult<(String, FetchResponse)> {
+    let mut attempt = 0;
+    loop {
+        let result = fetch_following_redirects(fetcher, request.clone(), options);
+        let retryable = match result {
+            Ok((_, ref res)) => res.status >= 500,
+            // it would be just as big, or just as refused, the next time
+            Err(ref e) => {
+                e.kind() != ErrorKind::ModuleLimitExceeded
+                    && e.kind() != ErrorKind::PermissionDenied
+            }
+        };
+        if !retryable || attempt >= options.retries {
+            let (url, res) = result?;
+            let not_modified = res.status == 304 && request.is_conditional();
+            if (res.status < 200 || res.status >= 300) && !not_modified {
+                return Err(status_error(&url, &res));
+            }
+            return Ok((url, res));
+        }
+        attempt += 1;
+        match result {
+            Ok((url, res)) => warn!(
+                "fetching {} returned status {}, retrying ({}/{})",
+                url, res.status, attempt, options.retries
+            ),
+            Err(e) => warn!("{}, retrying ({}/{})", e, attempt, options.retries),
+        }
+        thread::sleep(options.retry_backoff * attempt);
+    }
+}
+
+/**
+ * The error for `res`, a non-2xx response from `url`, its kind telling a missing module from
+ * a refused or rate-limited request, or a failing server.
+ */
+pub fn status_error(url: &str, res: &FetchResponse) -> FlyError {
+    let msg = format!("fetching {} returned status {}", url, res.status);
+    match res.status {
+        404 | 410 => new(ErrorKind::NotFound, msg),
+        429 => new(ErrorKind::RateLimited, msg),
+        // GitHub and others refuse with a 403 once the quota is used up
+        403 if res.header("x-ratelimit-remaining") == Some("0") => new(ErrorKind::RateLimited, msg),
+        401 | 403 => new(ErrorKind::PermissionDenied, msg),
+        500..=599 => new(ErrorKind::UpstreamError, msg),
+        _ => FlyError::from(msg),
+    }
+}
+
+/**
+ * Fetches `request`, following up to `options.max_redirects` redirects, each recorded in
+ * the resolution trace if there's one. A redirect back to a url already visited fails with
+ * `RedirectLoop`, one too many with `TooManyRedirects`, and one leaving `https`, unless
+ * allowed, or `http` altogether with `PolicyDenied`. The `host_headers` for the url are sent
+ * until a redirect leaves its origin. Every url, the first and each redirect's, is checked
+ * against the fetcher's network policy before it's fetched.
+ */
+fn fetch_following_redirects(
+    fetcher: &HttpFetcher,
+    mut request: FetchRequest,
+    options: &FetchOptions,
+) -> FlyResult<(String, FetchResponse)> {
+    let mut chain = vec![request.url.clone()];
+    let origin = url::Url::parse(&request.url).ok().map(|url| url.origin());
+    let mut host_headers = match url::Url::parse(&request.url) {
+        Ok(ref url) => options.headers_for(url),
+        Err(_) => vec![],
+    };
+    let policy = fetcher.network_policy();
+    loop {
+        if let (Some(policy), Ok(url)) = (policy.as_ref(), url::Url::parse(&request.url)) {
+            policy.check_url(&url)?;
+        }
+        let mut attempt = request.clone();
+        attempt.headers.extend(host_headers.iter().cloned());
+        let res = wait_with_timeout(fetcher.fetch(attempt), &request.url, options.timeout)?;
+        let location = match (res.status, res.header("location")) {
+            (301, Some(location))
+            | (302, Some(location))
+            | (303, Some(location))
+            | (307, Some(location))
+            | (308, Some(location)) => location.to_string(),
+            _ => return Ok((request.url, res)),
+        };
+        let invalid = |e: url::ParseError| {
+            FlyError::from(format!(
+                "invalid redirect from {} to {}: {}",
+                request.url, location, e
+            ))
+        };
+        let current = url::Url::parse(&request.url).map_err(invalid)?;
+        let next = current.join(&location).map_err(invalid)?;
+        record_step(|| TraceStep::Redirected {
+            from: request.url.clone(),
+            to: next.to_string(),
+            status: res.status,
+        });
+        let seen = chain.iter().any(|url| url == next.as_str());
+        chain.push(next.to_string());
+        if seen {
+            return Err(new(
+                ErrorKind::RedirectLoop,
+                format!(
+                    "fetching {} redirected in a loop: {}",
+                    chain[0],
+                    chain.join(" -> ")
+                ),
+            ));
+        }
+        if chain.len() - 1 > options.max_redirects as usize {
+            return Err(new(
+                ErrorKind::TooManyRedirects,
+                format!(
+                    "fetching {} exceeded {} redirects: {}",
+                    chain[0],
+                    options.max_redirects,
+                    chain.join(" -> ")
+                ),
+            ));
+        }
+        match (current.scheme(), next.scheme()) {
+            (_, "https") | ("http", "http") => {}
+            ("https", "http") if options.allow_insecure_redirects => {}
+            (from, to) => {
+                return Err(new(
+                    ErrorKind::PolicyDenied,
+                    format!(
+                        "refusing to follow the redirect from {} to {}: {} to {} redirects aren't allowed",
+                        request.url, next, from, to
+                    ),
+                ))
+            }
+        }
+        if !host_headers.is_empty() && origin.as_ref() != Some(&next.origin()) {
+            debug!(
+                "dropping {:?} for the redirect from {} to another origin, {}",
+                redacted_headers(&host_headers),
+                request.url,
+                next
+            );
+            host_headers.clear();
+        }
+        debug!("following redirect from {} to {}", request.url, next);
+        request.url = next.into_string();
+    }
+}
+
+fn wait_with_timeout(
+    future: FetchFuture,
+    url: &str,
+    timeout: Duration,
+) -> FlyResult<FetchResponse> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(future.wait());
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(_) => Err(new(
+            ErrorKind::TimedOut,
+            format!("fetching {} timed out after {:?}", url, timeout),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> FetchResponse {
+        FetchResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn options() -> FetchOptions {
+        FetchOptions {
+            retry_backoff: Duration::from_millis(1),
+            ..FetchOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_follows_redirects() {
+        let fetcher = FunctionFetcher::new(Box::new(|req| match req.url.as_str() {
+            "https://deno.land/std/http/server.ts" => Ok(response(
+                302,
+                &[("Location", "/std@0.50.0/http/server.ts")],
+                "",
+            )),
+            "https://deno.land/std@0.50.0/http/server.ts" => {
+                Ok(response(200, &[], "export const serve = 1;"))
+            }
+            _ => Ok(response(404, &[], "")),
+        }));
+        let (url, res) = fetch_with_options(
+            &fetcher,
+            FetchRequest::get("https://deno.land/std/http/server.ts"),
+            &options(),
+        )
+        .unwrap();
+        assert_eq!("https://deno.land/std@0.50.0/http/server.ts", url);
+        assert_eq!(b"export const serve = 1;".to_vec(), res.body);
+
+        let looping = FunctionFetcher::new(Box::new(|req| {
+            let next = if req.url.ends_with("/a") { "/b" } else { "/a" };
+            Ok(response(301, &[("location", next)], ""))
+        }));
+        let err = fetch_with_options(&looping, FetchRequest::get("https://a.test/a"), &options())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::RedirectLoop, err.kind());
+        assert!(
+            format!("{}", err).contains("https://a.test/a -> https://a.test/b -> https://a.test/a"),
+            "{}",
+            err
+        );
+
+        let endless = FunctionFetcher::new(Box::new(|req| {
+            Ok(response(302, &[("location", &format!("{}x", req.url))], ""))
+        }));
+        let err = fetch_with_options(&endless, FetchRequest::get("https://a.test/x"), &options())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::TooManyRedirects, err.kind());
+        assert!(format!("{}", err).contains("exceeded 10 redirects"));
+    }
+
+    #[test]
+    fn test_refuses_redirects_leaving_https() {
+        let downgrading = FunctionFetcher::new(Box::new(|req| {
+            Ok(match req.url.as_str() {
+                "https://a.test/mod.ts" => {
+                    response(307, &[("location", "http://a.test/mod.ts")], "")
+                }
+                _ => response(200, &[], "export default 1;"),
+            })
+        }));
+        let fetch = |options: &FetchOptions| {
+            fetch_with_options(
+                &downgrading,
+                FetchRequest::get("https://a.test/mod.ts"),
+                options,
+            )
+        };
+        let err = fetch(&options()).err().unwrap();
+        assert_eq!(ErrorKind::PolicyDenied, err.kind());
+        let allowed = FetchOptions {
+            allow_insecure_redirects: true,
+            ..options()
+        };
+        assert_eq!("http://a.test/mod.ts", fetch(&allowed).unwrap().0);
+    }
+
+    #[test]
+    fn test_metadata_keeps_only_recorded_headers() {
+        let res = response(
+            200,
+            &[
+                ("Content-Type", "application/typescript"),
+                ("ETag", "\"v1\""),
+                ("Set-Cookie", "session=secret"),
+                ("Authorization", "Bearer secret"),
+                ("X-TypeScript-Types", "./mod.d.ts"),
+            ],
+            "",
+        );
+        let meta =
+            ResponseMetadata::capture("https://a.test/mod.ts", &res, Duration::from_millis(1500));
+        assert_eq!(1500, meta.fetch_ms);
+        assert_eq!(
+            vec!["content-type", "etag", "x-typescript-types"],
+            meta.headers.keys().collect::<Vec<_>>()
+        );
+        assert!(!format!("{:?}", meta).contains("secret"));
+    }
+
+    #[test]
+    fn test_retries_server_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let fetcher = FunctionFetcher::new(Box::new(move |_| {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(FlyError::from("connection reset".to_string())),
+                1 => Ok(response(503, &[], "")),
+                _ => Ok(response(200, &[], "ok")),
+            }
+        }));
+        let (_, res) =
+            fetch_with_options(&fetcher, FetchRequest::get("https://a.test/x"), &options())
+                .unwrap();
+        assert_eq!(200, res.status);
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+
+        let missing = FunctionFetcher::new(Box::new(|_| Ok(response(404, &[], ""))));
+        let err = fetch_with_options(&missing, FetchRequest::get("https://a.test/x"), &options())
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("status 404"));
+    }
+
+    #[test]
+    fn test_times_out() {
+        struct Hanging;
+        impl HttpFetcher for Hanging {
+            fn fetch(&self, _request: FetchRequest) -> FetchFuture {
+                Box::new(futures::future::empty())
+            }
+        }
+        let err = fetch_with_options(
+            &Hanging,
+            FetchRequest::get("https://a.test/x"),
+            &FetchOptions {
+                timeout: Duration::from_millis(10),
+                retries: 0,
+                ..options()
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(format!("{}", err).contains("timed out"));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{is_bare_specifier, parse_url};
+
+/**
+ * Specifier remappings in the browser import map format: `{"imports": {...}, "scopes": {...}}`.
+ * Keys are bare specifiers like `lodash`, or urls; a key ending in `/`, like `app/`, maps
+ * every specifier under it. Addresses, and url keys, are relative to the map's own url.
+ * Mappings under a scope only apply to modules whose url starts with the scope, and the most
+ * specific scope is tried first.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct ImportMap {
+    imports: BTreeMap<String, url::Url>,
+    scopes: Vec<(String, BTreeMap<String, url::Url>)>,
+}
+
+fn import_map_error(msg: impl std::fmt::Display) -> FlyError {
+    FlyError::from(format!("invalid import map: {}", msg))
+}
+
+fn normalize_key(key: &str, base_url: &url::Url) -> String {
+    if is_bare_specifier(key) {
+        return key.to_string();
+    }
+    match base_url.join(key) {
+        Ok(url) => url.into_string(),
+        Err(_) => key.to_string(),
+    }
+}
+
+fn parse_mappings(
+    value: &serde_json::Value,
+    base_url: &url::Url,
+) -> FlyResult<BTreeMap<String, url::Url>> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| import_map_error("mappings must be an object"))?;
+    let mut mappings = BTreeMap::new();
+    for (key, address) in object {
+        let address = address
+            .as_str()
+            .ok_or_else(|| import_map_error(format!("{} must map to a string", key)))?;
+        let address = base_url
+            .join(address)
+            .map_err(|e| import_map_error(format!("{} maps to {}: {}", key, address, e)))?;
+        if key.ends_with('/') && !address.as_str().ends_with('/') {
+            return Err(import_map_error(format!(
+                "{} maps to {}, which doesn't end in /",
+                key, address
+            )));
+        }
+        mappings.insert(normalize_key(key, base_url), address);
+    }
+    Ok(mappings)
+}
+
+fn map_specifier(
+    mappings: &BTreeMap<String, url::Url>,
+    specifier: &str,
+) -> FlyResult<Option<String>> {
+    if let Some(address) = mappings.get(specifier) {
+        if !specifier.ends_with('/') {
+            return Ok(Some(address.as_str().to_string()));
+        }
+    }
+    // `app/` only maps what continues past it, never `app/` itself or `apple`
+    let prefix = mappings
+        .iter()
+        .filter(|(key, _)| {
+            key.ends_with('/') && specifier.len() > key.len() && specifier.starts_with(&key[..])
+        })
+        .max_by_key(|(key, _)| key.len());
+    let (key, address) = match prefix {
+        Some(prefix) => prefix,
+        None => return Ok(None),
+    };
+    let rest = &specifier[key.len()..];
+    let mapped = address.join(rest)?;
+    if !mapped.as_str().starts_with(address.as_str()) {
+        return Err(FlyError::from(format!(
+            "Could not map {}: it backtracks above {}",
+            specifier, address
+        )));
+    }
+    Ok(Some(mapped.into_string()))
+}
+
+impl ImportMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Parses an import map served from, or read at, `base_url`.
+     */
+    pub fn from_json(json: &str, base_url: &str) -> FlyResult<Self> {
+        let base_url = url::Url::parse(base_url)?;
+        let value: serde_json::Value = serde_json::from_str(json).map_err(import_map_error)?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| import_map_error("must be an object"))?;
+        let imports = match object.get("imports") {
+            Some(imports) => parse_mappings(imports, &base_url)?,
+            None => BTreeMap::new(),
+        };
+        let mut scopes = vec![];
+        if let Some(value) = object.get("scopes") {
+            let value = value
+                .as_object()
+                .ok_or_else(|| import_map_error("scopes must be an object"))?;
+            for (scope, mappings) in value {
+                let scope = base_url
+                    .join(scope)
+                    .map_err(|e| import_map_error(format!("scope {}: {}", scope, e)))?;
+                scopes.push((scope.into_string(), parse_mappings(mappings, &base_url)?));
+            }
+        }
+        scopes.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+        Ok(Self { imports, scopes })
+    }
+
+    /**
+     * Reads the import map at `path`; its relative addresses are relative to the file.
+     */
+    pub fn from_file(path: &Path) -> FlyResult<Self> {
+        let path = path.canonicalize()?;
+        let json = std::fs::read_to_string(&path)?;
+        let base_url = url::Url::from_file_path(&path)
+            .map_err(|_| import_map_error(format!("{} is not an absolute path", path.display())))?;
+        Self::from_json(&json, base_url.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.imports.is_empty() && self.scopes.is_empty()
+    }
+
+    /**
+     * The top level mappings, for diagnostics.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &str)> {
+        self.imports.iter().map(|(key, url)| (key, url.as_str()))
+    }
+
+    /**
+     * Every mapping, top level and scoped, as key and the url it maps to.
+     */
+    pub fn mappings(&self) -> impl Iterator<Item = (&str, &url::Url)> {
+        self.imports
+            .iter()
+            .chain(self.scopes.iter().flat_map(|(_, mappings)| mappings.iter()))
+            .map(|(key, url)| (key.as_str(), url))
+    }
+
+    /**
+     * The url `specifier`, imported by `referer_url`, is mapped to, or `None` when no
+     * mapping covers it.
+     */
+    pub fn resolve(&self, specifier: &str, referer_url: &str) -> FlyResult<Option<String>> {
+        let key = if is_bare_specifier(specifier) {
+            specifier.to_string()
+        } else {
+            match parse_url(specifier, referer_url) {
+                Ok(url) => url.into_string(),
+                Err(_) => return Ok(None),
+            }
+        };
+        for (scope, mappings) in &self.scopes {
+            if referer_url.starts_with(&scope[..]) {
+                if let Some(mapped) = map_specifier(mappings, &key)? {
+                    return Ok(Some(mapped));
+                }
+            }
+        }
+        map_specifier(&self.imports, &key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager,
+        StandardModuleResolverManager,
+    };
+
+    fn import_map() -> ImportMap {
+        ImportMap::from_json(
+            r#"{
+                "imports": {
+                    "lodash": "https://cdn.example.com/lodash.js",
+                    "app/": "file:///srv/app/src/",
+                    "app/legacy/": "./legacy/"
+                },
+                "scopes": {
+                    "/vendor/": { "lodash": "https://cdn.example.com/lodash@3.js" }
+                }
+            }"#,
+            "file:///srv/import_map.json",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_maps_exact_specifiers() {
+        let map = import_map();
+        let referer = "file:///srv/app/src/index.ts";
+        assert_eq!(
+            Some("https://cdn.example.com/lodash.js".to_string()),
+            map.resolve("lodash", referer).unwrap()
+        );
+        assert_eq!(None, map.resolve("lodash/fp", referer).unwrap());
+        assert_eq!(None, map.resolve("./lodash", referer).unwrap());
+        assert_eq!(
+            Some("https://cdn.example.com/lodash@3.js".to_string()),
+            map.resolve("lodash", "file:///vendor/old.js").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_maps_prefixes_only_past_the_prefix() {
+        let map = import_map();
+        let referer = "file:///srv/app/src/index.ts";
+        assert_eq!(
+            Some("file:///srv/app/src/util/strings.ts".to_string()),
+            map.resolve("app/util/strings.ts", referer).unwrap()
+        );
+        // the longest prefix wins, relative to the map's url
+        assert_eq!(
+            Some("file:///srv/legacy/old.js".to_string()),
+            map.resolve("app/legacy/old.js", referer).unwrap()
+        );
+        assert_eq!(None, map.resolve("app/", referer).unwrap());
+        assert_eq!(None, map.resolve("app", referer).unwrap());
+        assert_eq!(None, map.resolve("apple/x.js", referer).unwrap());
+        assert!(map.resolve("app/../../etc/passwd", referer).is_err());
+
+        assert!(ImportMap::from_json(r#"{"imports": {"app/": "./app.js"}}"#, "file:///").is_err());
+    }
+
+    #[test]
+    fn test_mapped_specifiers_resolve_through_the_file_resolver() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/util.ts"), "export default 1;").unwrap();
+        let root = url::Url::from_directory_path(dir.path()).unwrap();
+        let map =
+            ImportMap::from_json(r#"{"imports": {"app/": "./src/"}}"#, root.as_str()).unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(root.to_string())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(root.to_string()),
+        )
+        .with_import_map(map);
+        let module = manager
+            .resolve_blocking("app/util".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            root.join("src/util.ts").unwrap().as_str(),
+            module.origin_url
+        );
+        assert_eq!("export default 1;", &*module.loaded_source.source);
+    }
+}
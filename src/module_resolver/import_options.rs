@@ -0,0 +1,242 @@
+use crate::errors::*;
+
+use super::json_loader::{json_to_module, JsonOptions};
+use super::transpile::source_flavor;
+use super::{LoadedModule, LoadedSourceCode, ModuleKind, ModuleResolutionError, RemoteMediaType};
+
+/** Extensions of files that are JavaScript whatever they hold. */
+const SCRIPT_EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "jsx"];
+
+/**
+ * The type an import asserted its module to be, as in `with { type: "json" }`.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportType {
+    Json,
+}
+
+impl ImportType {
+    /** The type `asserted` names. Fails with `InvalidInput` on one no module loads as. */
+    pub fn parse(asserted: &str) -> FlyResult<Self> {
+        match asserted {
+            "json" => Ok(ImportType::Json),
+            other => Err(new(
+                ErrorKind::InvalidInput,
+                format!("unsupported import type \"{}\"", other),
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportType::Json => "json",
+        }
+    }
+}
+
+/**
+ * What an import asks of its module besides where it is. The default asks nothing, so the
+ * module loads as whatever its loader made of it.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportOptions {
+    pub asserted_type: Option<ImportType>,
+}
+
+impl ImportOptions {
+    pub fn with_type(asserted_type: ImportType) -> Self {
+        Self {
+            asserted_type: Some(asserted_type),
+        }
+    }
+
+    /** The options of an import that asserted `asserted_type`, if any, by its name. */
+    pub fn from_asserted(asserted_type: Option<&str>) -> FlyResult<Self> {
+        Ok(Self {
+            asserted_type: match asserted_type {
+                Some(asserted) => Some(ImportType::parse(asserted)?),
+                None => None,
+            },
+        })
+    }
+
+    /**
+     * `module` as its import asserted it to be. Modules a loader already made of JSON pass
+     * as they are. Sources nothing says are scripts, like a remote one served as `text/plain`
+     * or a local file without an extension, are read as the JSON document a `json`
+     * assertion says they are, failing if they aren't one. Scripts, by their media type or
+     * extension, and any other kind fail with `ModuleTypeMismatch`.
+     */
+    pub fn apply(&self, module: LoadedModule) -> FlyResult<LoadedModule> {
+        let asserted = match self.asserted_type {
+            Some(asserted) => asserted,
+            None => return Ok(module),
+        };
+        let loaded_source = as_type(&module.origin_url, asserted, module.loaded_source)?;
+        Ok(LoadedModule {
+            loaded_source,
+            ..module
+        })
+    }
+}
+
+fn as_type(
+    origin_url: &str,
+    asserted: ImportType,
+    source: LoadedSourceCode,
+) -> FlyResult<LoadedSourceCode> {
+    let kind = match source.kind {
+        ModuleKind::JavaScript => script_kind(origin_url, &source),
+        kind => Some(kind),
+    };
+    match (asserted, kind) {
+        (ImportType::Json, Some(ModuleKind::Json)) => Ok(source),
+        (ImportType::Json, None) => {
+            let module = json_to_module(origin_url, &source.source, &JsonOptions::default())
+                .map_err(|e| ModuleResolutionError::loader_failed(origin_url, e))?;
+            Ok(LoadedSourceCode {
+                kind: ModuleKind::Json,
+                source: module.into(),
+                source_map: None,
+                // made for the source as JavaScript
+                code_cache: None,
+                ..source
+            })
+        }
+        (asserted, Some(kind)) => Err(ModuleResolutionError::TypeMismatch {
+            origin_url: origin_url.to_string(),
+            asserted,
+            kind,
+        }
+        .into()),
+    }
+}
+
+/**
+ * What a source loaded as JavaScript is known to be, by the media type it was served with,
+ * or without one by its url's extension. `None` when neither says.
+ */
+fn script_kind(origin_url: &str, source: &LoadedSourceCode) -> Option<ModuleKind> {
+    if source_flavor(origin_url, source).is_some() {
+        return Some(ModuleKind::TypeScript);
+    }
+    match RemoteMediaType::of_response(&source.response) {
+        None | Some(RemoteMediaType::Unknown) => {}
+        Some(media_type) => return ModuleKind::of_media_type(media_type),
+    }
+    let path = match url::Url::parse(origin_url) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => origin_url.to_string(),
+    };
+    let name = path.rsplit('/').next().unwrap_or("");
+    let extension = &name[name.rfind('.')? + 1..];
+    if SCRIPT_EXTENSIONS.contains(&extension) {
+        Some(ModuleKind::JavaScript)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(kind: ModuleKind, source: &str) -> LoadedModule {
+        LoadedModule {
+            loaded_source: LoadedSourceCode {
+                kind,
+                source_map: Some("{}".into()),
+                source: source.into(),
+                response: None,
+                provenance: None,
+                code_cache: Some(vec![1, 2, 3]),
+                compiled_wasm: None,
+                wasm: None,
+            },
+            origin_url: "https://example.com/data".to_string(),
+            passthrough: false,
+            resolver: None,
+        }
+    }
+
+    #[test]
+    fn test_json_assertions_load_json_or_fail() {
+        let json = ImportOptions::with_type(ImportType::Json);
+
+        let loaded = json
+            .apply(module(ModuleKind::Json, "export default 1;"))
+            .unwrap()
+            .loaded_source;
+        assert_eq!("export default 1;", &*loaded.source);
+
+        // sources left as JavaScript are read as the document asserted
+        let forced = json
+            .apply(module(ModuleKind::JavaScript, r#"{"a": 1}"#))
+            .unwrap()
+            .loaded_source;
+        assert_eq!(ModuleKind::Json, forced.kind);
+        assert!(
+            forced.source.contains("export default"),
+            "{}",
+            forced.source
+        );
+        assert!(forced.source_map.is_none() && forced.code_cache.is_none());
+
+        let err = json
+            .apply(module(ModuleKind::JavaScript, "export default 1;"))
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ModuleLoadFailed, err.kind());
+        assert!(
+            err.to_string()
+                .starts_with("https://example.com/data:1:1: invalid JSON"),
+            "{}",
+            err
+        );
+
+        let err = json
+            .apply(module(ModuleKind::TypeScript, "export default 1;"))
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ModuleTypeMismatch, err.kind());
+        assert_eq!(
+            "Could not import https://example.com/data with type: \"json\": it is a typescript module",
+            err.to_string()
+        );
+
+        // nor are scripts not yet compiled, by their extension
+        for (name, kind) in &[
+            ("main.ts", ModuleKind::TypeScript),
+            ("main.js", ModuleKind::JavaScript),
+        ] {
+            let mut script = module(ModuleKind::JavaScript, r#"{"a": 1}"#);
+            script.origin_url = format!("file:///app/{}", name);
+            match json.apply(script).err().unwrap().resolution_error() {
+                Some(ModuleResolutionError::TypeMismatch { kind: actual, .. }) => {
+                    assert_eq!(kind, actual, "{}", name)
+                }
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_absent_assertions_leave_modules_alone() {
+        let options = ImportOptions::from_asserted(None).unwrap();
+        assert_eq!(ImportOptions::default(), options);
+        let loaded = options
+            .apply(module(ModuleKind::TypeScript, "export default 1;"))
+            .unwrap()
+            .loaded_source;
+        assert_eq!(ModuleKind::TypeScript, loaded.kind);
+        assert_eq!(Some(vec![1, 2, 3]), loaded.code_cache);
+
+        assert_eq!(
+            ImportOptions::with_type(ImportType::Json),
+            ImportOptions::from_asserted(Some("json")).unwrap()
+        );
+        let err = ImportOptions::from_asserted(Some("css")).err().unwrap();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+    }
+}
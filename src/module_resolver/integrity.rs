@@ -0,0 +1,265 @@
+use crate::errors::*;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+use super::{wasm_bytes, LoadedSourceCode};
+
+const SRI_PREFIX: &str = "sha256-";
+
+/**
+ * What the integrity checks found so far. Hashes are in the `sha256-...` form of
+ * subresource integrity, so they can be pasted into a pin as they are.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct IntegrityReport {
+    /** Modules whose source matched their pin, with the hash. */
+    pub verified: BTreeMap<String, String>,
+    /** Modules loaded without a pin, which only a strict check rejects. */
+    pub unpinned: BTreeSet<String>,
+    /** Modules rejected, with the hash of what was loaded instead. */
+    pub rejected: BTreeMap<String, String>,
+}
+
+fn integrity_error(msg: impl std::fmt::Display) -> FlyError {
+    FlyError::from(format!("invalid integrity hash: {}", msg))
+}
+
+/**
+ * A sha256 digest from either 64 hex digits or an SRI string like `sha256-<base64>`.
+ */
+pub(super) fn parse_digest(expected: &str) -> FlyResult<Vec<u8>> {
+    let expected = expected.trim();
+    let digest = if expected.starts_with(SRI_PREFIX) {
+        base64::decode(&expected[SRI_PREFIX.len()..]).map_err(integrity_error)?
+    } else if expected.len() == 64 && expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..expected.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&expected[i..i + 2], 16).unwrap())
+            .collect()
+    } else {
+        return Err(integrity_error(format!(
+            "{} is neither sha256 hex nor a sha256- SRI string",
+            expected
+        )));
+    };
+    if digest.len() != 32 {
+        return Err(integrity_error(format!(
+            "{} is {} bytes, not a sha256 digest",
+            expected,
+            digest.len()
+        )));
+    }
+    Ok(digest)
+}
+
+pub(super) fn sri(digest: &[u8]) -> String {
+    format!("{}{}", SRI_PREFIX, base64::encode(digest))
+}
+
+/** The sha256 of `source`, of the binary for wasm modules rather than its base64. */
+pub(super) fn source_digest(source: &LoadedSourceCode) -> FlyResult<Vec<u8>> {
+    Ok(if source.is_wasm() {
+        Sha256::digest(&wasm_bytes(source)?).to_vec()
+    } else {
+        Sha256::digest(source.source.as_bytes()).to_vec()
+    })
+}
+
+/**
+ * Pins modules, by the origin url they resolve to, to the sha256 of their source, so only
+ * the code that was reviewed gets loaded. Wasm modules are hashed as the binary that was
+ * loaded, not its base64. A strict check also rejects every module without a pin. Clones
+ * share the same report.
+ */
+#[derive(Clone, Default)]
+pub struct ModuleIntegrity {
+    pins: HashMap<String, Vec<u8>>,
+    strict: bool,
+    report: Arc<Mutex<IntegrityReport>>,
+}
+
+impl ModuleIntegrity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Pins each origin url in `pins` to its hash, in hex or as an SRI string.
+     */
+    pub fn from_map(pins: &HashMap<String, String>) -> FlyResult<Self> {
+        pins.iter()
+            .try_fold(Self::new(), |integrity, (origin_url, expected)| {
+                integrity.with_pin(origin_url, expected)
+            })
+    }
+
+    pub fn with_pin(mut self, origin_url: &str, expected: &str) -> FlyResult<Self> {
+        let digest = parse_digest(expected)
+            .map_err(|e| FlyError::from(format!("{} for {}", e, origin_url)))?;
+        // pinned the way origin urls are spelled, e.g. with `./` segments resolved
+        let origin_url = url::Url::parse(origin_url)
+            .map(|u| u.into_string())
+            .unwrap_or_else(|_| origin_url.to_string());
+        self.pins.insert(origin_url, digest);
+        Ok(self)
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn pinned(&self) -> usize {
+        self.pins.len()
+    }
+
+    /**
+     * Fails with `ErrorKind::IntegrityMismatch` unless `source`, loaded for `origin_url`,
+     * is what it's pinned to, or it isn't pinned and the check isn't strict.
+     */
+    pub fn verify(&self, origin_url: &str, source: &LoadedSourceCode) -> FlyResult<()> {
+        let digest = source_digest(source)?;
+        let actual = sri(&digest);
+        let mut report = self.report.lock().unwrap();
+        let msg = match self.pins.get(origin_url) {
+            Some(expected) if expected[..] == digest[..] => {
+                report.verified.insert(origin_url.to_string(), actual);
+                return Ok(());
+            }
+            Some(expected) => format!(
+                "Integrity check failed for {}: expected {} but loaded {}",
+                origin_url,
+                sri(expected),
+                actual
+            ),
+            None if self.strict => format!(
+                "Integrity check failed for {}: no hash is pinned for it, loaded {}",
+                origin_url, actual
+            ),
+            None => {
+                report.unpinned.insert(origin_url.to_string());
+                return Ok(());
+            }
+        };
+        report.rejected.insert(origin_url.to_string(), actual);
+        Err(new(ErrorKind::IntegrityMismatch, msg))
+    }
+
+    pub fn report(&self) -> IntegrityReport {
+        self.report.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        wasm_source, ModuleResolver, ModuleResolverManager, StandardModuleResolverManager,
+        StaticModuleResolver,
+    };
+
+    const MAIN: &str = "export default 1;";
+
+    fn manager(integrity: ModuleIntegrity) -> StandardModuleResolverManager {
+        StandardModuleResolverManager::new(
+            vec![Box::new(
+                StaticModuleResolver::new("static")
+                    .insert("static://app/main.js", MAIN)
+                    .insert("static://app/other.js", "export default 2;")
+                    .insert_source("static://app/answer.wasm", wasm_source(b"\0asm\x01\0\0\0")),
+            ) as Box<ModuleResolver>],
+            Some("static://app/".to_string()),
+        )
+        .with_integrity(integrity)
+    }
+
+    #[test]
+    fn test_accepts_hex_and_sri_pins() {
+        let digest = Sha256::digest(MAIN.as_bytes());
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(&digest[..], &parse_digest(&hex).unwrap()[..]);
+        assert_eq!(&digest[..], &parse_digest(&sri(&digest)).unwrap()[..]);
+        assert!(parse_digest("sha256-AAAA").is_err());
+        assert!(parse_digest("sha384-AAAA").is_err());
+        assert!(parse_digest(&hex[1..]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_modules_that_dont_match_their_pin() {
+        let main_sri = sri(&Sha256::digest(MAIN.as_bytes()));
+        let main_hex: String = Sha256::digest(MAIN.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let wasm_sri = sri(&Sha256::digest(b"\0asm\x01\0\0\0"));
+        // other.js pinned to what main.js was reviewed as
+        let integrity = ModuleIntegrity::new()
+            .with_pin("static://app/./main.js", &main_sri)
+            .unwrap()
+            .with_pin("static://app/other.js", &main_hex)
+            .unwrap()
+            .with_pin("static://app/answer.wasm", &wasm_sri)
+            .unwrap();
+        let manager = manager(integrity.clone());
+        manager
+            .resolve_blocking("./main.js".to_string(), None)
+            .unwrap();
+        manager
+            .resolve_blocking("./answer.wasm".to_string(), None)
+            .unwrap();
+        let err = manager
+            .resolve_blocking("./other.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::IntegrityMismatch, err.kind());
+        assert!(format!("{}", err).contains("expected sha256-"));
+
+        let report = integrity.report();
+        assert_eq!(
+            vec![
+                ("static://app/answer.wasm".to_string(), wasm_sri),
+                ("static://app/main.js".to_string(), main_sri),
+            ],
+            report.verified.into_iter().collect::<Vec<_>>()
+        );
+        assert!(report.unpinned.is_empty());
+        assert_eq!(
+            vec!["static://app/other.js"],
+            report.rejected.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_strict_checks_reject_unpinned_modules() {
+        let main_sri = sri(&Sha256::digest(MAIN.as_bytes()));
+        let pins: HashMap<String, String> =
+            vec![("static://app/main.js".to_string(), main_sri.clone())]
+                .into_iter()
+                .collect();
+        let lenient = ModuleIntegrity::from_map(&pins).unwrap();
+        manager(lenient.clone())
+            .resolve_blocking("./other.js".to_string(), None)
+            .unwrap();
+        assert!(lenient.report().unpinned.contains("static://app/other.js"));
+
+        let strict = ModuleIntegrity::from_map(&pins).unwrap().with_strict(true);
+        let manager = manager(strict.clone());
+        manager
+            .resolve_blocking("./main.js".to_string(), None)
+            .unwrap();
+        let err = manager
+            .resolve_blocking("./other.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::IntegrityMismatch, err.kind());
+        assert!(format!("{}", err).contains("no hash is pinned"));
+        assert_eq!(1, strict.report().verified.len());
+    }
+}
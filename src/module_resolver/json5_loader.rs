@@ -0,0 +1,461 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+
+use super::data_module::{data_to_module, parse_error, DataValue, MAX_DATA_DEPTH};
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+/**
+ * Turns a JSON5 file into a module the same way `TomlLoader` does for TOML. Integers that
+ * don't fit a JavaScript number exactly are rejected; `NaN` and `Infinity` are kept.
+ */
+pub struct Json5Loader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+}
+
+impl Json5Loader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+        }
+    }
+}
+
+impl SourceLoader for Json5Loader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let raw = self.inner.load_source()?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::Json,
+            source_map: None,
+            source: json5_to_module(&self.origin_url, &raw.source)?.into(),
+            response: raw.response,
+            provenance: raw.provenance,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+pub fn json5_to_module(origin_url: &str, json5_source: &str) -> FlyResult<String> {
+    data_to_module(&parse_json5(origin_url, json5_source)?)
+}
+
+/**
+ * `json5_source` as data, keys in the order they're first written. Takes JSON with comments
+ * too. Arrays and objects nested deeper than `MAX_DATA_DEPTH` are refused.
+ */
+pub fn parse_json5(origin_url: &str, json5_source: &str) -> FlyResult<DataValue> {
+    Json5Parser::new(json5_source, MAX_DATA_DEPTH)
+        .parse_document()
+        .map_err(|(line, col, msg)| parse_error(origin_url, "JSON5", line, col, &msg))
+}
+
+type ParseResult<T> = Result<T, (usize, usize, String)>;
+
+struct Json5Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /** Arrays and objects open around `pos`. */
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Json5Parser {
+    fn new(source: &str, max_depth: usize) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            depth: 0,
+            max_depth,
+        }
+    }
+
+    fn error<T>(&self, msg: &str) -> ParseResult<T> {
+        let consumed = &self.chars[..self.pos.min(self.chars.len())];
+        let line = consumed.iter().filter(|c| **c == '\n').count() + 1;
+        let col = consumed.iter().rev().take_while(|c| **c != '\n').count() + 1;
+        Err((line, col, msg.to_string()))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        let end = self.pos + expected.chars().count();
+        if end <= self.chars.len()
+            && self.chars[self.pos..end]
+                .iter()
+                .cloned()
+                .eq(expected.chars())
+        {
+            self.pos = end;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> ParseResult<()> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() || c == '\u{feff}' => self.pos += 1,
+                Some('/') if self.eat("//") => {
+                    while let Some(c) = self.next() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                Some('/') if self.eat("/*") => loop {
+                    if self.eat("*/") {
+                        break;
+                    }
+                    if self.next().is_none() {
+                        return self.error("unterminated block comment");
+                    }
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn parse_document(&mut self) -> ParseResult<DataValue> {
+        self.skip_whitespace()?;
+        let value = self.parse_value()?;
+        self.skip_whitespace()?;
+        if self.peek().is_some() {
+            return self.error("unexpected content after the value");
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> ParseResult<DataValue> {
+        match self.peek() {
+            Some(c @ '{') | Some(c @ '[') => {
+                if self.depth == self.max_depth {
+                    return self.error(&format!("nested deeper than {} levels", self.max_depth));
+                }
+                self.depth += 1;
+                let value = if c == '{' {
+                    self.parse_object()
+                } else {
+                    self.parse_array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some('"') | Some('\'') => Ok(DataValue::String(self.parse_string()?)),
+            Some(c) if c == '-' || c == '+' || c == '.' || c.is_ascii_digit() => {
+                self.parse_number()
+            }
+            Some('I') | Some('N') => self.parse_number(),
+            Some(_) => {
+                if self.eat("true") {
+                    Ok(DataValue::Bool(true))
+                } else if self.eat("false") {
+                    Ok(DataValue::Bool(false))
+                } else if self.eat("null") {
+                    Ok(DataValue::Null)
+                } else {
+                    self.error("unexpected character")
+                }
+            }
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    fn parse_object(&mut self) -> ParseResult<DataValue> {
+        self.pos += 1;
+        let mut members: Vec<(String, DataValue)> = vec![];
+        let mut positions = HashMap::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some('}') {
+                self.pos += 1;
+                return Ok(DataValue::Object(members));
+            }
+            let key = match self.peek() {
+                Some('"') | Some('\'') => self.parse_string()?,
+                _ => self.parse_identifier()?,
+            };
+            self.skip_whitespace()?;
+            if self.next() != Some(':') {
+                self.pos -= 1;
+                return self.error("expected ':' after object key");
+            }
+            self.skip_whitespace()?;
+            let value = self.parse_value()?;
+            // a repeated key keeps its place and takes the later value, as in JavaScript
+            match positions.get(&key) {
+                Some(&i) => members[i].1 = value,
+                None => {
+                    positions.insert(key.clone(), members.len());
+                    members.push((key, value));
+                }
+            }
+            self.skip_whitespace()?;
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(DataValue::Object(members)),
+                _ => {
+                    self.pos -= 1;
+                    return self.error("expected ',' or '}'");
+                }
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> ParseResult<DataValue> {
+        self.pos += 1;
+        let mut items = vec![];
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(DataValue::Array(items));
+            }
+            items.push(self.parse_value()?);
+            self.skip_whitespace()?;
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(DataValue::Array(items)),
+                _ => {
+                    self.pos -= 1;
+                    return self.error("expected ',' or ']'");
+                }
+            }
+        }
+    }
+
+    fn parse_identifier(&mut self) -> ParseResult<String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '$' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if self.pos == start || self.chars[start].is_ascii_digit() {
+            self.pos = start;
+            return self.error("expected an object key");
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn parse_string(&mut self) -> ParseResult<String> {
+        let quote = self.next().unwrap();
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                None => return self.error("unterminated string"),
+                Some(c) if c == quote => return Ok(s),
+                Some('\n') => {
+                    self.pos -= 1;
+                    return self.error("unescaped line break in string");
+                }
+                Some('\\') => match self.next() {
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('v') => s.push('\u{b}'),
+                    Some('0') => s.push('\0'),
+                    Some('\n') => {}
+                    Some('\r') => {
+                        self.eat("\n");
+                    }
+                    Some('x') => s.push(self.parse_hex_escape(2)?),
+                    Some('u') => s.push(self.parse_hex_escape(4)?),
+                    Some(c) => s.push(c),
+                    None => return self.error("unterminated string"),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn parse_hex_escape(&mut self, digits: usize) -> ParseResult<char> {
+        let end = self.pos + digits;
+        if end > self.chars.len() {
+            return self.error("invalid escape sequence");
+        }
+        let hex: String = self.chars[self.pos..end].iter().collect();
+        match u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(std::char::from_u32)
+        {
+            Some(c) => {
+                self.pos = end;
+                Ok(c)
+            }
+            None => self.error("invalid escape sequence"),
+        }
+    }
+
+    fn parse_number(&mut self) -> ParseResult<DataValue> {
+        let negative = match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                true
+            }
+            Some('+') => {
+                self.pos += 1;
+                false
+            }
+            _ => false,
+        };
+        if self.eat("Infinity") {
+            return Ok(DataValue::Float(if negative {
+                std::f64::NEG_INFINITY
+            } else {
+                std::f64::INFINITY
+            }));
+        }
+        if self.eat("NaN") {
+            return Ok(DataValue::Float(std::f64::NAN));
+        }
+        if self.eat("0x") || self.eat("0X") {
+            let start = self.pos;
+            while self.peek().map(|c| c.is_ascii_hexdigit()).unwrap_or(false) {
+                self.pos += 1;
+            }
+            let hex: String = self.chars[start..self.pos].iter().collect();
+            return match i64::from_str_radix(&hex, 16) {
+                Ok(i) => Ok(DataValue::Integer(if negative { -i } else { i })),
+                Err(_) => self.error("invalid hexadecimal number"),
+            };
+        }
+        let start = self.pos;
+        let mut is_float = false;
+        while let Some(c) = self.peek() {
+            match c {
+                '0'...'9' => {}
+                '.' | 'e' | 'E' => is_float = true,
+                '+' | '-' if is_float => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        if digits.is_empty() {
+            return self.error("invalid number");
+        }
+        let signed = if negative {
+            format!("-{}", digits)
+        } else {
+            digits.clone()
+        };
+        if !is_float {
+            return match signed.parse::<i64>() {
+                Ok(i) => Ok(DataValue::Integer(i)),
+                // too large for 64 bits; let the safe integer check report it
+                Err(_) => Ok(DataValue::Integer(if negative {
+                    std::i64::MIN
+                } else {
+                    std::i64::MAX
+                })),
+            };
+        }
+        let normalized = if signed.ends_with('.') {
+            format!("{}0", signed)
+        } else {
+            signed
+        };
+        match normalized.parse::<f64>() {
+            Ok(f) => Ok(DataValue::Float(f)),
+            Err(_) => self.error("invalid number"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json5_syntax() {
+        let module = json5_to_module(
+            "file:///app/config.json5",
+            "// settings\n{\n  name: 'app',\n  server: { port: 0x1F90, },\n  ratio: .5,\n  big: Infinity,\n  /* trailing */\n}\n",
+        )
+        .unwrap();
+        assert!(module.contains("\"name\":\"app\""));
+        assert!(module.contains("\"server\":{\"port\":8080}"));
+        assert!(module.contains("\"ratio\":0.5"));
+        assert!(module.contains("\"big\":Infinity"));
+        assert!(module.contains("export const server = __data.server;"));
+    }
+
+    #[test]
+    fn test_repeated_keys_keep_their_place() {
+        let data = parse_json5("file:///app/a.json5", "{a: 1, b: 2, a: 3}").unwrap();
+        assert_eq!(
+            DataValue::Object(vec![
+                ("a".to_string(), DataValue::Integer(3)),
+                ("b".to_string(), DataValue::Integer(2)),
+            ]),
+            data
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting_is_rejected() {
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        let err = parse_json5("file:///app/a.json5", &deep).err().unwrap();
+        let msg = format!("{}", err);
+        assert!(msg.starts_with("file:///app/a.json5:1:129:"), msg);
+        assert!(msg.ends_with("nested deeper than 128 levels"), msg);
+
+        let nested = "{a: {b: [1]}}";
+        assert!(Json5Parser::new(nested, 3).parse_document().is_ok());
+        assert!(Json5Parser::new(nested, 2).parse_document().is_err());
+        let at_limit = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        assert!(json5_to_module("file:///app/a.json5", &at_limit).is_ok());
+    }
+
+    #[test]
+    fn test_unsafe_integers_are_rejected() {
+        assert!(json5_to_module("file:///app/a.json5", "{n: 12345678901234567890}").is_err());
+        assert!(json5_to_module("file:///app/a.json5", "{n: 9007199254740993}").is_err());
+        assert!(json5_to_module("file:///app/a.json5", "{n: 9007199254740991}").is_ok());
+    }
+
+    #[test]
+    fn test_parse_error_has_position() {
+        let err = json5_to_module("file:///app/a.json5", "{\n  a: 1\n  b: 2\n}")
+            .err()
+            .unwrap();
+        let msg = format!("{}", err);
+        assert!(msg.starts_with("file:///app/a.json5:3:3:"), msg);
+    }
+
+    #[test]
+    fn test_only_enabled_formats_are_converted() {
+        use crate::module_resolver::{DataFormats, LocalDiskRawLoader};
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json5");
+        std::fs::write(&path, "{a: 1}").unwrap();
+        let load = |formats: DataFormats| {
+            let raw = Box::new(LocalDiskRawLoader::new(path.clone(), None));
+            formats
+                .loader_for("file:///app/config.json5", raw)
+                .load_source()
+                .unwrap()
+                .source
+        };
+        assert_eq!("{a: 1}", &*load(DataFormats::default()));
+        assert!(load(DataFormats::default().with_json5()).contains("\"a\":1"));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::errors::*;
+
+use std::fmt;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use super::data_module::{data_to_module_with, parse_error, DataValue, UnsafeIntegers};
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+/**
+ * What to do with `__proto__` and `constructor` keys.
+ */
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrototypeKeys {
+    /** Keep them as plain properties, the way `JSON.parse` does. */
+    Keep,
+    Reject,
+    /** Append an underscore, so `__proto__` becomes `__proto___`. */
+    Rename,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct JsonOptions {
+    pub unsafe_integers: UnsafeIntegers,
+    pub prototype_keys: PrototypeKeys,
+    /** Sort object keys, so the module (and its content hash) doesn't depend on key order. */
+    pub sort_keys: bool,
+}
+
+/**
+ * Behaves like `JSON.parse`.
+ */
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self {
+            unsafe_integers: UnsafeIntegers::Number,
+            prototype_keys: PrototypeKeys::Keep,
+            sort_keys: false,
+        }
+    }
+}
+
+/**
+ * Turns a JSON file into a module the same way `TomlLoader` does for TOML, handling large
+ * integers and prototype keys according to its options.
+ */
+pub struct JsonLoader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+    pub options: JsonOptions,
+}
+
+impl JsonLoader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>, options: JsonOptions) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+            options,
+        }
+    }
+}
+
+impl SourceLoader for JsonLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let raw = self.inner.load_source()?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::Json,
+            source_map: None,
+            source: json_to_module(&self.origin_url, &raw.source, &self.options)?.into(),
+            response: raw.response,
+            provenance: raw.provenance,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+pub fn json_to_module(origin_url: &str, json: &str, options: &JsonOptions) -> FlyResult<String> {
+    let JsonValue(value) = serde_json::from_str(json)
+        .map_err(|e| parse_error(origin_url, "JSON", e.line(), e.column(), &e.to_string()))?;
+    let value = normalize(value, options, origin_url)?;
+    data_to_module_with(&value, options.unsafe_integers)
+}
+
+fn normalize(value: DataValue, options: &JsonOptions, origin_url: &str) -> FlyResult<DataValue> {
+    Ok(match value {
+        DataValue::Array(items) => DataValue::Array(
+            items
+                .into_iter()
+                .map(|item| normalize(item, options, origin_url))
+                .collect::<FlyResult<_>>()?,
+        ),
+        DataValue::Object(members) => {
+            let mut normalized = Vec::with_capacity(members.len());
+            for (key, member) in members {
+                let key = if key == "__proto__" || key == "constructor" {
+                    match options.prototype_keys {
+                        PrototypeKeys::Keep => key,
+                        PrototypeKeys::Rename => format!("{}_", key),
+                        PrototypeKeys::Reject => {
+                            return Err(FlyError::from(format!(
+                                "{}: object key {} is not allowed",
+                                origin_url, key
+                            )))
+                        }
+                    }
+                } else {
+                    key
+                };
+                normalized.push((key, normalize(member, options, origin_url)?));
+            }
+            if options.sort_keys {
+                normalized.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            DataValue::Object(normalized)
+        }
+        value => value,
+    })
+}
+
+/**
+ * Deserializes straight into a `DataValue`, keeping object keys in file order and integers
+ * exact where `serde_json::Value` would not.
+ */
+struct JsonValue(DataValue);
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(JsonValueVisitor)
+            .map(JsonValue)
+    }
+}
+
+struct JsonValueVisitor;
+
+impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = DataValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<DataValue, E> {
+        Ok(DataValue::Null)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<DataValue, E> {
+        Ok(DataValue::Bool(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<DataValue, E> {
+        Ok(DataValue::Integer(i))
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<DataValue, E> {
+        Ok(if u > std::i64::MAX as u64 {
+            DataValue::BigInteger(u.to_string())
+        } else {
+            DataValue::Integer(u as i64)
+        })
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<DataValue, E> {
+        Ok(DataValue::Float(f))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<DataValue, E> {
+        Ok(DataValue::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<DataValue, E> {
+        Ok(DataValue::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DataValue, A::Error> {
+        let mut items = vec![];
+        while let Some(JsonValue(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(DataValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<DataValue, A::Error> {
+        let mut members = vec![];
+        while let Some((key, JsonValue(member))) = map.next_entry::<String, JsonValue>()? {
+            members.push((key, member));
+        }
+        Ok(DataValue::Object(members))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER: &str =
+        r#"{"id": 1234567890123456789, "name": "edge", "big": 18446744073709551615}"#;
+
+    fn module(json: &str, options: JsonOptions) -> String {
+        json_to_module("file:///app/config.json", json, &options).unwrap()
+    }
+
+    #[test]
+    fn test_unsafe_integers() {
+        assert_eq!(
+            "const __data = {\"id\":1234567890123456789,\"name\":\"edge\",\"big\":18446744073709551615};\nexport default __data;\n",
+            module(ORDER, JsonOptions::default())
+        );
+        let bigint = module(
+            ORDER,
+            JsonOptions {
+                unsafe_integers: UnsafeIntegers::BigInt,
+                ..JsonOptions::default()
+            },
+        );
+        assert!(bigint.contains("\"id\":1234567890123456789n"));
+        assert!(bigint.contains("\"big\":18446744073709551615n"));
+        let string = module(
+            ORDER,
+            JsonOptions {
+                unsafe_integers: UnsafeIntegers::String,
+                ..JsonOptions::default()
+            },
+        );
+        assert!(string.contains("\"id\":\"1234567890123456789\""));
+        let err = json_to_module(
+            "file:///app/config.json",
+            ORDER,
+            &JsonOptions {
+                unsafe_integers: UnsafeIntegers::Reject,
+                ..JsonOptions::default()
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(format!("{}", err).contains("integer 1234567890123456789 at id"));
+    }
+
+    #[test]
+    fn test_prototype_keys() {
+        let json = r#"{"__proto__": {"admin": true}, "constructor": 1}"#;
+        assert_eq!(
+            "const __data = {[\"__proto__\"]:{\"admin\":true},\"constructor\":1};\nexport default __data;\n",
+            module(json, JsonOptions::default())
+        );
+        let renamed = module(
+            json,
+            JsonOptions {
+                prototype_keys: PrototypeKeys::Rename,
+                ..JsonOptions::default()
+            },
+        );
+        assert!(renamed.contains("{\"__proto___\":{\"admin\":true},\"constructor_\":1}"));
+        assert!(json_to_module(
+            "file:///app/config.json",
+            json,
+            &JsonOptions {
+                prototype_keys: PrototypeKeys::Reject,
+                ..JsonOptions::default()
+            },
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sorted_keys_ignore_file_order() {
+        let options = JsonOptions {
+            sort_keys: true,
+            ..JsonOptions::default()
+        };
+        assert_eq!(
+            module(r#"{"b": {"y": 1, "x": 2}, "a": [3]}"#, options.clone()),
+            module(r#"{"a": [3], "b": {"x": 2, "y": 1}}"#, options)
+        );
+        // file order is kept otherwise
+        assert!(module(r#"{"b": 1, "a": 2}"#, JsonOptions::default()).contains("{\"b\":1,\"a\":2}"));
+        let err = json_to_module(
+            "file:///app/config.json",
+            "{\"a\": }",
+            &JsonOptions::default(),
+        )
+        .err()
+        .unwrap();
+        assert!(format!("{}", err).starts_with("file:///app/config.json:1:7: invalid JSON"));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::errors::*;
+
+use std::cell::Cell;
+use std::fmt;
+
+use super::{ModuleGraph, ModuleResolutionError};
+
+/** The largest module source loaded unless a manager's limits say otherwise. */
+pub const DEFAULT_MAX_MODULE_BYTES: u64 = 64 * 1024 * 1024;
+
+/** How many imports deep a module may be unless a manager's limits say otherwise. */
+pub const DEFAULT_MAX_GRAPH_DEPTH: usize = 512;
+
+/** How many modules a manager loads at most unless its limits say otherwise. */
+pub const DEFAULT_MAX_TOTAL_MODULES: usize = 50_000;
+
+/**
+ * What a `StandardModuleResolverManager` loads at most, so a pathological import fails
+ * instead of exhausting memory. A source over `max_module_bytes` is refused as soon as it's
+ * known to be, from a `Content-Length` before any of it is read where there's one; the depth
+ * and the count of modules are those of the manager's module graph.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ModuleLimits {
+    pub max_module_bytes: u64,
+    /** Imports deep, counting from an entry point, which is 0 deep. */
+    pub max_graph_depth: usize,
+    pub max_total_modules: usize,
+}
+
+impl Default for ModuleLimits {
+    fn default() -> Self {
+        Self {
+            max_module_bytes: DEFAULT_MAX_MODULE_BYTES,
+            max_graph_depth: DEFAULT_MAX_GRAPH_DEPTH,
+            max_total_modules: DEFAULT_MAX_TOTAL_MODULES,
+        }
+    }
+}
+
+/** Which of `ModuleLimits` a module is over. */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModuleLimit {
+    ModuleBytes,
+    GraphDepth,
+    TotalModules,
+}
+
+impl fmt::Display for ModuleLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ModuleLimit::ModuleBytes => "max_module_bytes",
+            ModuleLimit::GraphDepth => "max_graph_depth",
+            ModuleLimit::TotalModules => "max_total_modules",
+        })
+    }
+}
+
+fn exceeded(origin_url: &str, limit: ModuleLimit, max: u64, actual: u64) -> FlyError {
+    ModuleResolutionError::LimitExceeded {
+        origin_url: origin_url.to_string(),
+        limit,
+        max,
+        actual,
+    }
+    .into()
+}
+
+impl ModuleLimits {
+    /** Fails if `bytes` of `origin_url` are over `max_module_bytes`. */
+    pub fn check_bytes(&self, origin_url: &str, bytes: u64) -> FlyResult<()> {
+        if bytes > self.max_module_bytes {
+            return Err(exceeded(
+                origin_url,
+                ModuleLimit::ModuleBytes,
+                self.max_module_bytes,
+                bytes,
+            ));
+        }
+        Ok(())
+    }
+
+    /**
+     * Fails if recording `origin_url`, imported from `referer`, in `graph` would make it too
+     * deep or hold too many modules. Modules already in the graph always pass.
+     */
+    pub fn check_graph(
+        &self,
+        graph: &ModuleGraph,
+        origin_url: &str,
+        referer: Option<&str>,
+    ) -> FlyResult<()> {
+        if graph.contains(origin_url) {
+            return Ok(());
+        }
+        let count = graph.module_count() + 1;
+        if count > self.max_total_modules {
+            return Err(exceeded(
+                origin_url,
+                ModuleLimit::TotalModules,
+                self.max_total_modules as u64,
+                count as u64,
+            ));
+        }
+        let depth = referer.map_or(0, |referer| graph.depth_of(referer) + 1);
+        if depth > self.max_graph_depth {
+            return Err(exceeded(
+                origin_url,
+                ModuleLimit::GraphDepth,
+                self.max_graph_depth as u64,
+                depth as u64,
+            ));
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static MAX_MODULE_BYTES: Cell<Option<u64>> = Cell::new(None);
+}
+
+/** Puts back the byte limit in effect before `with_module_byte_limit`, even if it panics. */
+struct Restore(Option<u64>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        MAX_MODULE_BYTES.with(|max| max.set(self.0));
+    }
+}
+
+/**
+ * Runs `f` with `max` module bytes in effect on this thread, for loaders and fetchers that
+ * can tell early, like from a `Content-Length`, that a module is too big.
+ */
+pub fn with_module_byte_limit<T>(max: u64, f: impl FnOnce() -> T) -> T {
+    let _restore = Restore(MAX_MODULE_BYTES.with(|current| current.replace(Some(max))));
+    f()
+}
+
+/** The module byte limit `with_module_byte_limit` put in effect on this thread, if any. */
+pub fn module_byte_limit() -> Option<u64> {
+    MAX_MODULE_BYTES.with(|max| max.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::HotReloadConfig;
+
+    #[test]
+    fn test_byte_limit_applies_within_its_scope() {
+        assert_eq!(None, module_byte_limit());
+        assert_eq!(Some(10), with_module_byte_limit(10, module_byte_limit));
+        assert_eq!(None, module_byte_limit());
+
+        let err = ModuleLimits {
+            max_module_bytes: 10,
+            ..ModuleLimits::default()
+        }
+        .check_bytes("https://cdn.test/big.js", 11)
+        .err()
+        .unwrap();
+        assert_eq!(ErrorKind::ModuleLimitExceeded, err.kind());
+        assert_eq!(
+            "Could not load https://cdn.test/big.js: it is 11 bytes, over the max_module_bytes limit of 10",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_graph_limits() {
+        let graph = ModuleGraph::new(HotReloadConfig::default());
+        graph.record_module("file:///app/main.ts", 1, false, None);
+        graph.record_module("file:///app/a.ts", 1, false, Some("file:///app/main.ts"));
+        let limits = ModuleLimits {
+            max_graph_depth: 1,
+            max_total_modules: 3,
+            ..ModuleLimits::default()
+        };
+        assert!(limits
+            .check_graph(&graph, "file:///app/b.ts", Some("file:///app/main.ts"))
+            .is_ok());
+        let err = limits
+            .check_graph(&graph, "file:///app/c.ts", Some("file:///app/a.ts"))
+            .err()
+            .unwrap();
+        match err.resolution_error() {
+            Some(ModuleResolutionError::LimitExceeded { limit, actual, .. }) => {
+                assert_eq!((ModuleLimit::GraphDepth, 2), (*limit, *actual))
+            }
+            other => panic!("{:?}", other),
+        }
+        graph.record_module("file:///app/b.ts", 1, false, Some("file:///app/main.ts"));
+        let err = limits
+            .check_graph(&graph, "file:///app/d.ts", Some("file:///app/main.ts"))
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("over the max_total_modules limit of 3"));
+        // already loaded
+        assert!(limits
+            .check_graph(&graph, "file:///app/a.ts", Some("file:///app/b.ts"))
+            .is_ok());
+    }
+}
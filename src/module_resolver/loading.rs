@@ -0,0 +1,338 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Either};
+use futures::Future;
+
+use super::candidates::Located;
+use super::trace::{push_step, traced, TraceHandle};
+use super::{
+    absolutize_source_map, duration_ms, spawn_blocking, strip_shebang, transpile_module,
+    LoadScheduler, LoadedModule, LoadedSourceCode, ModuleIntegrity, ModuleLoadProfile,
+    ModuleResolutionError, ModuleResolver, ModuleSourceData, RegisteredResolver,
+    ResolutionObserver, ResolverOptions, ResolverPolicy, SharedSourceCache, SourceLoader,
+    SqliteModuleCache, StandardModuleResolverManager, StartupProfiler, TraceStep,
+};
+
+/**
+ * What loading a located module's source needs from its manager, cloned off it so the load
+ * can run on the blocking pool.
+ */
+#[derive(Clone)]
+pub(super) struct ModuleLoading {
+    shared_cache: Option<(SharedSourceCache, usize)>,
+    profiler: Arc<StartupProfiler>,
+    load_scheduler: Option<LoadScheduler>,
+    integrity: Option<ModuleIntegrity>,
+    transpile: bool,
+    compile_cache: Option<SqliteModuleCache>,
+    /** What loads the sources source maps name, if they're to be inlined. */
+    original_sources: Option<(
+        Arc<HashMap<String, Vec<RegisteredResolver>>>,
+        ResolverPolicy,
+    )>,
+    options: Arc<ResolverOptions>,
+}
+
+impl ModuleLoading {
+    /** Loads `located`, recording what loading it takes, e.g. redirects, into `trace`. */
+    fn load(
+        self,
+        located: Located,
+        trace: Option<TraceHandle>,
+    ) -> Box<Future<Item = LoadedModule, Error = FlyError> + Send> {
+        match located {
+            // virtual modules are in memory already
+            Located::Virtual(module_loader) => Box::new(future::result(
+                module_loader
+                    .source_loader
+                    .load_source()
+                    .map_err(|e| ModuleResolutionError::loader_failed(&module_loader.origin_url, e))
+                    .map(|loaded_source| LoadedModule {
+                        loaded_source,
+                        origin_url: module_loader.origin_url,
+                        passthrough: false,
+                        resolver: None,
+                    }),
+            )),
+            Located::Resolved {
+                module_loader,
+                resolver,
+                tenant,
+                resolve_time,
+                ..
+            } => {
+                // queued for a slot without holding up any thread, the blocking pool's included
+                let permit = match self.load_scheduler {
+                    Some(ref scheduler) => Either::A(scheduler.acquire(&tenant).map(Some)),
+                    None => Either::B(future::ok(None)),
+                };
+                Box::new(permit.and_then(move |permit| {
+                    spawn_blocking(move || {
+                        traced(&trace, || {
+                            let _permit = permit;
+                            // shared with the thread loading it, which may outlive its deadline
+                            let source_loader = Arc::from(module_loader.source_loader);
+                            self.load_resolved(
+                                &module_loader.origin_url,
+                                source_loader,
+                                &*resolver,
+                                resolve_time,
+                            )
+                        })
+                    })
+                }))
+            }
+        }
+    }
+
+    /**
+     * `load`, going on to the resolvers after the one that resolved `located` if loading
+     * times out, until one loads it or none are left.
+     */
+    pub(super) fn load_falling_through(
+        self,
+        mut located: Located,
+        observers: Vec<Arc<ResolutionObserver>>,
+        trace: Option<TraceHandle>,
+    ) -> Box<Future<Item = LoadedModule, Error = FlyError> + Send> {
+        let (fallthrough, kind) = match located {
+            Located::Resolved {
+                ref mut fallthrough,
+                ref resolver,
+                ..
+            } => (fallthrough.take(), resolver.describe().kind),
+            Located::Virtual(_) => (None, String::new()),
+        };
+        let origin_url = located.origin_url().to_string();
+        let loading = self.clone();
+        Box::new(self.load(located, trace.clone()).or_else(move |e| {
+            let candidates = match fallthrough {
+                Some(candidates) if e.kind() == ErrorKind::TimedOut => candidates,
+                _ => return Box::new(future::err(e)) as Box<Future<Item = _, Error = _> + Send>,
+            };
+            info!(
+                "Loading {} timed out trying the next resolver: {}",
+                origin_url, e
+            );
+            push_step(&trace, || TraceStep::ResolverFailed {
+                resolver: kind,
+                error: e.to_string(),
+            });
+            Box::new(
+                candidates
+                    .try_resolvers(observers.clone(), trace.clone())
+                    .and_then(move |located| {
+                        loading.load_falling_through(located, observers, trace)
+                    }),
+            )
+        }))
+    }
+
+    /** `source` with its source map made absolute, or dropped if it isn't one. */
+    fn absolutize_source_map(
+        &self,
+        origin_url: &str,
+        source: LoadedSourceCode,
+    ) -> LoadedSourceCode {
+        let source_map = match source.source_map {
+            Some(ref source_map) => source_map,
+            None => return source,
+        };
+        let load = |url: &str| self.load_original_source(url);
+        let load: Option<&Fn(&str) -> Result<String, String>> = match self.original_sources {
+            Some(_) => Some(&load),
+            None => None,
+        };
+        LoadedSourceCode {
+            source_map: absolutize_source_map(source_map, origin_url, load).map(Into::into),
+            ..source
+        }
+    }
+
+    /** The source at `url`, as its resolver loads it, for a source map to inline. */
+    fn load_original_source(&self, url: &str) -> Result<String, String> {
+        let (resolvers, policy) = match self.original_sources {
+            Some((ref resolvers, ref policy)) => (resolvers, policy),
+            None => return Err("inlining sources is off".to_string()),
+        };
+        let parsed = url::Url::parse(url).map_err(|e| e.to_string())?;
+        if !policy.permits(parsed.scheme()) || !policy.permits_host(&parsed) {
+            return Err(format!("not allowed by policy {}", policy));
+        }
+        let resolvers = resolvers
+            .get(parsed.scheme())
+            .ok_or_else(|| format!("no resolver for protocol {}", parsed.scheme()))?;
+        let mut errors = vec![];
+        for registered in resolvers {
+            match registered
+                .resolver
+                .resolve_module(url, None)
+                .and_then(|data| data.source_loader.load_source())
+            {
+                Ok(loaded) => return Ok(loaded.source.to_string()),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        Err(errors.join("; "))
+    }
+
+    /** Loads a located module, with a load slot already taken if there's a scheduler. */
+    fn load_resolved(
+        &self,
+        origin_url: &str,
+        source_loader: Arc<SourceLoader>,
+        resolver: &ModuleResolver,
+        resolve_time: Duration,
+    ) -> FlyResult<LoadedModule> {
+        let load_started = Instant::now();
+        let passthrough = resolver.is_passthrough();
+        let mut cache_hit = false;
+        let transform_time = std::cell::Cell::new(Duration::default());
+        let load = || -> FlyResult<LoadedSourceCode> {
+            let source_loader = source_loader.clone();
+            let source =
+                self.options
+                    .attempt(resolver, &format!("loading {}", origin_url), move || {
+                        source_loader.load_source()
+                    })?;
+            // loaders that can't tell early are held to it once they're done
+            self.options
+                .limits
+                .check_bytes(origin_url, source.source.len() as u64)?;
+            // hashed as loaded, before anything is stripped
+            if let Some(ref integrity) = self.integrity {
+                integrity.verify(origin_url, &source)?;
+            }
+            let source = strip_shebang(origin_url, source);
+            let source = if !self.transpile || passthrough {
+                source
+            } else {
+                let transform_started = Instant::now();
+                let source = transpile_module(&self.compile_cache, origin_url, source)?;
+                transform_time.set(transform_started.elapsed());
+                source
+            };
+            Ok(self.absolutize_source_map(origin_url, source))
+        };
+        let load_source =
+            || load().map_err(|e| ModuleResolutionError::loader_failed(origin_url, e));
+        let loaded_source = match self.shared_cache {
+            Some((ref cache, consumer)) if !passthrough && self.integrity.is_none() => {
+                match cache.get(origin_url, consumer) {
+                    Some(loaded_source) => {
+                        cache_hit = true;
+                        loaded_source
+                    }
+                    None => {
+                        let loaded_source = load_source()?;
+                        cache.insert(origin_url, loaded_source.clone(), consumer);
+                        loaded_source
+                    }
+                }
+            }
+            _ => load_source()?,
+        };
+        self.profiler.record(ModuleLoadProfile {
+            origin_url: origin_url.to_string(),
+            protocol: resolver.get_protocol(),
+            resolver: resolver.name(),
+            resolve_ms: duration_ms(resolve_time),
+            load_ms: duration_ms(load_started.elapsed() - transform_time.get()),
+            transform_ms: duration_ms(transform_time.get()),
+            transfer_bytes: loaded_source.source.len(),
+            cache_hit,
+        });
+        // relative imports inside a redirected module resolve against where it ended up
+        let origin_url = match loaded_source.response {
+            Some(ref response) => response.final_url.clone(),
+            None => origin_url.to_string(),
+        };
+        Ok(LoadedModule {
+            loaded_source,
+            origin_url,
+            passthrough,
+            resolver: Some(resolver.name()),
+        })
+    }
+}
+
+/**
+ * A located module's loader, loading it as its manager's imports load modules: within the
+ * resolver's deadline and retries, through the shared cache, checked and transpiled.
+ */
+struct ManagedSourceLoader {
+    loading: ModuleLoading,
+    origin_url: String,
+    source_loader: Arc<SourceLoader>,
+    resolver: Arc<ModuleResolver>,
+    tenant: String,
+    resolve_time: Duration,
+}
+
+impl SourceLoader for ManagedSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        // synchronous like every source loader, it waits its turn as it waits for the source
+        let _permit = match self.loading.load_scheduler {
+            Some(ref scheduler) => Some(scheduler.acquire(&self.tenant).wait()?),
+            None => None,
+        };
+        self.loading
+            .load_resolved(
+                &self.origin_url,
+                self.source_loader.clone(),
+                &*self.resolver,
+                self.resolve_time,
+            )
+            .map(|module| module.loaded_source)
+    }
+}
+
+impl StandardModuleResolverManager {
+    pub(super) fn loading(&self) -> ModuleLoading {
+        ModuleLoading {
+            shared_cache: self.shared_cache.clone(),
+            profiler: self.profiler.clone(),
+            load_scheduler: self.load_scheduler.clone(),
+            integrity: self.integrity.clone(),
+            transpile: self.transpile,
+            compile_cache: self.compile_cache.clone(),
+            original_sources: if self.inline_source_contents {
+                Some((
+                    self.protocol_resolver_map.clone(),
+                    self.policy.read().unwrap().clone(),
+                ))
+            } else {
+                None
+            },
+            options: self.options.clone(),
+        }
+    }
+
+    /** The loader of `located`, loading it as `resolve_module` would. */
+    pub(super) fn source_data(&self, located: Located) -> ModuleSourceData {
+        match located {
+            Located::Virtual(module_loader) => module_loader,
+            Located::Resolved {
+                module_loader,
+                resolver,
+                tenant,
+                resolve_time,
+                ..
+            } => ModuleSourceData {
+                origin_url: module_loader.origin_url.clone(),
+                source_loader: Box::new(ManagedSourceLoader {
+                    loading: self.loading(),
+                    origin_url: module_loader.origin_url,
+                    source_loader: Arc::from(module_loader.source_loader),
+                    resolver,
+                    tenant,
+                    resolve_time,
+                }),
+            },
+        }
+    }
+}
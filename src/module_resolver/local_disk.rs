@@ -0,0 +1,1627 @@
+use crate::errors::*;
+
+use std::path::PathBuf;
+
+use super::suggest::{nearby_files, respecify};
+use super::trace::record_step;
+use super::{
+    case_mismatch, check_local_path, discover_source_map, file_origin_url,
+    inline_source_map_sources, is_absolute_path, is_bare_specifier, is_wasm_file, match_path_alias,
+    parse_url, read_source_text, read_tsconfig_paths, CaseSensitivityMode, DataFormats,
+    LoadedSourceCode, LocalDiskWasmLoader, ModuleKind, ModuleResolver, ModuleSourceData,
+    ModuleWatcher, PathAliases, RefererInfo, ResolverDescription, SourceLoader, TraceStep,
+};
+
+pub struct LocalDiskRawLoader {
+    pub source_file_path: PathBuf,
+    pub source_map_path: Option<PathBuf>,
+    /** Source map `sources` outside this directory aren't inlined. */
+    pub sandbox: Option<PathBuf>,
+}
+
+impl LocalDiskRawLoader {
+    pub fn new(source_file_path: PathBuf, source_map_path: Option<PathBuf>) -> Self {
+        Self {
+            source_file_path,
+            source_map_path,
+            sandbox: None,
+        }
+    }
+
+    pub fn with_sandbox(mut self, sandbox: PathBuf) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+}
+
+impl SourceLoader for LocalDiskRawLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        check_local_path(&self.source_file_path)?;
+        let source = read_source_text(&self.source_file_path)?;
+        let source_map = match &self.source_map_path {
+            Some(v) => match read_source_text(v) {
+                Ok(map) => Some(inline_source_map_sources(
+                    &map,
+                    v,
+                    self.sandbox.as_ref().map(|p| p.as_path()),
+                )),
+                Err(_err) => None,
+            },
+            None => discover_source_map(
+                &source,
+                &self.source_file_path,
+                self.sandbox.as_ref().map(|p| p.as_path()),
+            ),
+        };
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: source_map.map(Into::into),
+            source: source.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+pub struct LocalDiskModuleResolver {
+    pub default_working_url: String,
+    pub data_formats: DataFormats,
+    /**
+     * Only resolve specifiers naming an existing file exactly, without trying extensions.
+     * Resolution is then deterministic and skips the probing stats on cold starts.
+     */
+    pub strict: bool,
+    /**
+     * Files tried, in order, inside a directory a specifier names, like `./utils` for
+     * `./utils/index.ts`. Extensions on the path itself are tried first.
+     */
+    pub index_files: Vec<String>,
+    /**
+     * Extensions tried, in order, for a specifier naming no file exactly. They're appended to
+     * the file name, never replace part of it, so `./config.prod` finds `config.prod.ts`.
+     */
+    pub extensions: Vec<String>,
+    /**
+     * Serve files anywhere on disk, not just under `default_working_url`. Only for tools like
+     * the CLI, which run code the user pointed them at.
+     */
+    pub allow_outside_root: bool,
+    /**
+     * Build origin urls from real paths, symlinks resolved, so a file reached through several
+     * paths is one module. Off, symlinks are kept in origin urls, as imported; either way the
+     * sandbox root confines where they lead.
+     */
+    pub canonicalize: bool,
+    /**
+     * What to do when a specifier's file name differs in case from the file's. Whatever it
+     * says, a file not found because of its case gets an error naming both spellings.
+     */
+    pub case_sensitivity: CaseSensitivityMode,
+    /** Told about every file resolved, to report when it changes. */
+    pub watcher: Option<ModuleWatcher>,
+    /**
+     * Directories searched in order, like an include path, for specifiers resolved against a
+     * directory rather than a module, as entry points are. Modules may come from any of them.
+     * Empty, `default_working_url` is the only root.
+     */
+    pub roots: Vec<PathBuf>,
+    /**
+     * Aliases for bare specifiers, as in tsconfig's `paths`: patterns with at most one `*`,
+     * which stands for the same text in each of their targets, tried in order. Relative
+     * targets are relative to `default_working_url`.
+     */
+    pub paths: PathAliases,
+}
+
+pub const DEFAULT_INDEX_FILES: &[&str] = &["index.ts", "index.js", "index.wasm"];
+
+pub const DEFAULT_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "json", "wasm"];
+
+fn case_mismatch_message(path: &std::path::Path, actual: &str) -> String {
+    format!(
+        "{} differs in case from {} on disk",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        actual
+    )
+}
+
+/** Where `path` points, if it's a symlink to nothing. */
+fn broken_symlink(path: &std::path::Path) -> Option<std::path::PathBuf> {
+    let is_symlink = path
+        .symlink_metadata()
+        .map(|meta| meta.file_type().is_symlink())
+        .unwrap_or(false);
+    if !is_symlink || path.exists() {
+        return None;
+    }
+    std::fs::read_link(path).ok()
+}
+
+/** Whether `candidate` is a file, logged and recorded into the trace being recorded. */
+fn probe(candidate: &std::path::Path) -> bool {
+    let found = candidate.is_file();
+    debug!(
+        "probing module candidate={} found={}",
+        candidate.display(),
+        found
+    );
+    record_step(|| TraceStep::Probed {
+        candidate: candidate.display().to_string(),
+        found,
+    });
+    found
+}
+
+impl LocalDiskModuleResolver {
+    /**
+     * Resolves against `default_working_url`, or the current directory. Fails if that's
+     * needed but gone, or isn't an absolute path.
+     */
+    pub fn new(default_working_url: Option<String>) -> FlyResult<Self> {
+        let default_working_url = match default_working_url {
+            None => {
+                let cwd = std::env::current_dir().map_err(|e| {
+                    FlyError::from(std::io::Error::new(
+                        e.kind(),
+                        format!("Could not find the current directory: {}", e),
+                    ))
+                })?;
+                url::Url::from_directory_path(&cwd)
+                    .map_err(|_| {
+                        FlyError::from(format!("{} is not an absolute path", cwd.to_string_lossy()))
+                    })?
+                    .into_string()
+            }
+            Some(default_working_url) => default_working_url,
+        };
+        Ok(Self {
+            default_working_url,
+            data_formats: DataFormats::default(),
+            strict: false,
+            index_files: DEFAULT_INDEX_FILES.iter().map(|f| f.to_string()).collect(),
+            extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            allow_outside_root: false,
+            canonicalize: false,
+            case_sensitivity: CaseSensitivityMode::default(),
+            watcher: None,
+            roots: vec![],
+            paths: vec![],
+        })
+    }
+
+    pub fn with_data_formats(mut self, data_formats: DataFormats) -> Self {
+        self.data_formats = data_formats;
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn with_index_files(mut self, index_files: Vec<String>) -> Self {
+        self.index_files = index_files;
+        self
+    }
+
+    pub fn with_extensions(mut self, extensions: Vec<String>) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    pub fn with_allow_outside_root(mut self, allow_outside_root: bool) -> Self {
+        self.allow_outside_root = allow_outside_root;
+        self
+    }
+
+    pub fn with_canonicalize(mut self, canonicalize: bool) -> Self {
+        self.canonicalize = canonicalize;
+        self
+    }
+
+    pub fn with_case_sensitivity(mut self, case_sensitivity: CaseSensitivityMode) -> Self {
+        self.case_sensitivity = case_sensitivity;
+        self
+    }
+
+    pub fn with_watcher(mut self, watcher: ModuleWatcher) -> Self {
+        self.watcher = Some(watcher);
+        self
+    }
+
+    pub fn with_paths(mut self, paths: PathAliases) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /** Takes the `paths` aliases of the tsconfig.json at `path`, see `read_tsconfig_paths`. */
+    pub fn with_tsconfig(self, path: &std::path::Path) -> FlyResult<Self> {
+        Ok(self.with_paths(read_tsconfig_paths(path)?))
+    }
+
+    /**
+     * Searches `roots` in order, the first of them becoming the default working url. Relative
+     * roots are relative to the working directory.
+     */
+    pub fn with_roots(mut self, roots: Vec<PathBuf>) -> Self {
+        // relative roots stay relative without one, and never become the working url
+        let cwd = std::env::current_dir().unwrap_or_default();
+        self.roots = roots.into_iter().map(|root| cwd.join(root)).collect();
+        if let Some(first) = self.roots.first() {
+            if let Ok(url) = url::Url::from_directory_path(first) {
+                self.default_working_url = url.into_string();
+            }
+        }
+        self
+    }
+
+    /**
+     * The module at `path`, watched if there's a watcher. A file that can't be watched still
+     * resolves; it just won't be reported when it changes. A `query` is kept in the origin
+     * url, so `./widget.js?v=3` and `./widget.js?v=4` are two instances of one file, as they
+     * are in browsers.
+     */
+    fn module_at(&self, path: PathBuf, query: Option<&str>) -> FlyResult<ModuleSourceData> {
+        let path = if self.canonicalize {
+            path.canonicalize()?
+        } else {
+            path
+        };
+        let mut origin_url = file_origin_url(&path)?;
+        if let Some(query) = query {
+            origin_url = format!("{}?{}", origin_url, query);
+        }
+        if let Some(ref watcher) = self.watcher {
+            if let Err(e) = watcher.watch(&path, &origin_url) {
+                warn!("changes to {} won't be noticed: {}", origin_url, e);
+            }
+        }
+        let source_loader = self.loader_for(&origin_url, path);
+        Ok(ModuleSourceData {
+            origin_url,
+            source_loader,
+        })
+    }
+
+    /**
+     * The directories modules are confined to, with symlinks resolved.
+     */
+    fn roots(&self) -> Vec<PathBuf> {
+        let roots = if self.roots.is_empty() {
+            url::Url::parse(&self.default_working_url)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+                .into_iter()
+                .collect()
+        } else {
+            self.roots.clone()
+        };
+        roots
+            .into_iter()
+            .map(|root| root.canonicalize().unwrap_or(root))
+            .collect()
+    }
+
+    /**
+     * Refuses `path` unless it, once `..` segments and symlinks are resolved, is under the
+     * root.
+     */
+    fn confine(
+        &self,
+        path: &std::path::Path,
+        module_specifier: &str,
+        referer_origin_url: &str,
+    ) -> FlyResult<()> {
+        if self.allow_outside_root {
+            return Ok(());
+        }
+        let real_path = path.canonicalize()?;
+        let roots = self.roots();
+        if roots.iter().any(|root| real_path.starts_with(root)) {
+            return Ok(());
+        }
+        let roots = match roots.len() {
+            0 => self.default_working_url.clone(),
+            _ => roots
+                .iter()
+                .map(|root| root.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        Err(new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "Denied {} from {}: {} is outside the sandbox root {}",
+                module_specifier,
+                referer_origin_url,
+                real_path.display(),
+                roots
+            ),
+        ))
+    }
+
+    /**
+     * Warns about, or refuses, `path` if its file name differs in case from the file found
+     * for it, which only a case-insensitive filesystem finds.
+     */
+    fn check_case(
+        &self,
+        path: &std::path::Path,
+        module_specifier: &str,
+        referer_origin_url: &str,
+    ) -> FlyResult<()> {
+        if self.case_sensitivity == CaseSensitivityMode::Ignore {
+            return Ok(());
+        }
+        let actual = match case_mismatch(path) {
+            Some(actual) => actual,
+            None => return Ok(()),
+        };
+        let mismatch = case_mismatch_message(path, &actual);
+        if self.case_sensitivity == CaseSensitivityMode::Error {
+            return Err(new(
+                ErrorKind::NotFound,
+                format!(
+                    "Could not resolve {} from {}: {}",
+                    module_specifier, referer_origin_url, mismatch
+                ),
+            ));
+        }
+        warn!(
+            "{} from {} only resolves on case-insensitive filesystems: {}",
+            module_specifier, referer_origin_url, mismatch
+        );
+        Ok(())
+    }
+
+    /**
+     * Wasm binaries, by their magic number, are loaded as such; anything else as text.
+     */
+    fn loader_for(&self, origin_url: &str, path: PathBuf) -> Box<SourceLoader> {
+        if is_wasm_file(&path) {
+            return Box::new(LocalDiskWasmLoader::new(path));
+        }
+        let real_path = path.canonicalize().unwrap_or_else(|_| path.clone());
+        let mut loader = LocalDiskRawLoader::new(path, None);
+        if !self.allow_outside_root {
+            if let Some(root) = self
+                .roots()
+                .into_iter()
+                .find(|root| real_path.starts_with(root))
+            {
+                loader = loader.with_sandbox(root);
+            }
+        }
+        self.data_formats.loader_for(origin_url, Box::new(loader))
+    }
+
+    /** The file a specifier naming none exactly resolves to outside strict mode. */
+    fn probe_extensions(&self, path: &std::path::Path) -> Option<std::path::PathBuf> {
+        self.extension_candidates(path)
+            .into_iter()
+            .find(|candidate| probe(candidate))
+    }
+
+    fn extension_candidates(&self, path: &std::path::Path) -> Vec<std::path::PathBuf> {
+        self.extensions
+            .iter()
+            .map(|ext| {
+                let mut candidate = path.as_os_str().to_owned();
+                candidate.push(".");
+                candidate.push(ext);
+                PathBuf::from(candidate)
+            })
+            .collect()
+    }
+
+    fn probe_index(&self, dir: &std::path::Path) -> Option<std::path::PathBuf> {
+        if !dir.is_dir() {
+            return None;
+        }
+        self.index_files
+            .iter()
+            .map(|file| dir.join(file))
+            .find(|candidate| probe(candidate))
+    }
+
+    /**
+     * The file `path` names, exactly or, outside strict mode, by extensions and index files.
+     * Every candidate that isn't it goes into `tried`.
+     */
+    fn find(&self, path: &std::path::Path, tried: &mut Vec<String>) -> Option<PathBuf> {
+        let mut candidates = vec![path.to_path_buf()];
+        if !self.strict {
+            candidates.extend(self.extension_candidates(path));
+            if path.is_dir() {
+                candidates.extend(self.index_files.iter().map(|file| path.join(file)));
+            }
+        }
+        for candidate in candidates {
+            if probe(&candidate) {
+                return Some(candidate);
+            }
+            tried.push(candidate.display().to_string());
+        }
+        None
+    }
+
+    /** `check_local_path`, saying which import it failed. */
+    fn check_path(
+        &self,
+        path: &std::path::Path,
+        module_specifier: &str,
+        referer_origin_url: &str,
+    ) -> FlyResult<()> {
+        check_local_path(path).map_err(|e| {
+            new(
+                e.kind(),
+                format!(
+                    "Could not resolve {} from {}: {}",
+                    module_specifier, referer_origin_url, e
+                ),
+            )
+        })
+    }
+
+    /**
+     * `module_specifier`, a bare specifier matching `pattern`, at the first of `targets` that
+     * exists, with the error naming every path tried.
+     */
+    fn resolve_alias(
+        &self,
+        module_specifier: &str,
+        referer_origin_url: &str,
+        pattern: &str,
+        targets: Vec<String>,
+    ) -> FlyResult<ModuleSourceData> {
+        let mut tried = vec![];
+        for target in targets {
+            let mut url = parse_url(&target, &self.default_working_url)?;
+            url.set_fragment(None);
+            let query = url.query().map(|query| query.to_string());
+            let path = url
+                .to_file_path()
+                .map_err(|_| FlyError::from(format!("{} is not a local file", url)))?;
+            self.check_path(&path, module_specifier, referer_origin_url)?;
+            if let Some(found) = self.find(&path, &mut tried) {
+                self.confine(&found, module_specifier, referer_origin_url)?;
+                self.check_case(&found, module_specifier, referer_origin_url)?;
+                return self.module_at(found, query.as_deref());
+            }
+        }
+        Err(new(
+            ErrorKind::NotFound,
+            format!(
+                "Could not resolve {} from {}: no target of paths alias {} exists, tried {}",
+                module_specifier,
+                referer_origin_url,
+                pattern,
+                tried.join(", ")
+            ),
+        ))
+    }
+
+    /**
+     * `module_specifier` in the first of the roots it's found in, by the same extensions and
+     * index files, and strictness, as a single root. The error names every path tried.
+     */
+    fn search_roots(
+        &self,
+        module_specifier: &str,
+        referer_origin_url: &str,
+    ) -> FlyResult<ModuleSourceData> {
+        let mut tried = vec![];
+        for root in &self.roots {
+            let root_url = url::Url::from_directory_path(root)
+                .map_err(|_| FlyError::from(format!("{} is not a directory", root.display())))?;
+            let mut url = parse_url(module_specifier, root_url.as_str())?;
+            url.set_fragment(None);
+            let query = url.query().map(|query| query.to_string());
+            let path = url
+                .to_file_path()
+                .map_err(|_| FlyError::from(format!("{} is not a local file", url)))?;
+            self.check_path(&path, module_specifier, referer_origin_url)?;
+            if let Some(found) = self.find(&path, &mut tried) {
+                self.confine(&found, module_specifier, referer_origin_url)?;
+                self.check_case(&found, module_specifier, referer_origin_url)?;
+                return self.module_at(found, query.as_deref());
+            }
+        }
+        Err(new(
+            ErrorKind::NotFound,
+            format!(
+                "Could not resolve {} from {}: tried {}",
+                module_specifier,
+                referer_origin_url,
+                tried.join(", ")
+            ),
+        ))
+    }
+}
+
+/** A specifier naming a path relative to whatever it's resolved against, not a url. */
+fn is_relative_path(specifier: &str) -> bool {
+    !is_absolute_path(specifier)
+        && match url::Url::parse(specifier) {
+            Err(url::ParseError::RelativeUrlWithoutBase) => true,
+            _ => false,
+        }
+}
+
+impl ModuleResolver for LocalDiskModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => self.default_working_url.clone(),
+        };
+        debug!(
+            "probing module specifier={} referer={}",
+            module_specifier, referer_origin_url
+        );
+        if is_bare_specifier(module_specifier) {
+            if let Some((pattern, targets)) = match_path_alias(&self.paths, module_specifier) {
+                return self.resolve_alias(module_specifier, &referer_origin_url, pattern, targets);
+            }
+        }
+        // Imports from a module are relative to where it is, whichever root that's in.
+        if !self.roots.is_empty()
+            && referer_origin_url.ends_with('/')
+            && is_relative_path(module_specifier)
+        {
+            return self.search_roots(module_specifier, &referer_origin_url);
+        }
+
+        let mut module_specifier_url = parse_url(module_specifier, referer_origin_url.as_str())?;
+        // a fragment names nothing on disk, nor a different module
+        module_specifier_url.set_fragment(None);
+        let query = module_specifier_url.query().map(|query| query.to_string());
+
+        let module_file_path = module_specifier_url
+            .to_file_path()
+            .map_err(|_| FlyError::from(format!("{} is not a local file", module_specifier_url)))?;
+        self.check_path(&module_file_path, module_specifier, &referer_origin_url)?;
+
+        if probe(&module_file_path) {
+            self.confine(&module_file_path, module_specifier, &referer_origin_url)?;
+            self.check_case(&module_file_path, module_specifier, &referer_origin_url)?;
+            return self.module_at(module_file_path, query.as_deref());
+        }
+        let probed = self
+            .probe_extensions(&module_file_path)
+            .or_else(|| self.probe_index(&module_file_path));
+        if self.strict {
+            return Err(FlyError::from(match probed {
+                Some(path) => format!(
+                    "Could not resolve {} from {}: strict resolution is on, so the specifier must name the file exactly (probing would have found {})",
+                    module_specifier,
+                    referer_origin_url,
+                    path.display()
+                ),
+                None => format!(
+                    "Could not resolve {} from {}: strict resolution is on, so the specifier must name the file exactly",
+                    module_specifier, referer_origin_url
+                ),
+            }));
+        }
+        if let Some(module_file_path) = probed {
+            self.confine(&module_file_path, module_specifier, &referer_origin_url)?;
+            self.check_case(&module_file_path, module_specifier, &referer_origin_url)?;
+            // the index file itself, so its relative imports resolve inside the directory
+            return self.module_at(module_file_path, query.as_deref());
+        }
+        let mut candidates = vec![module_file_path.clone()];
+        candidates.extend(self.extension_candidates(&module_file_path));
+        let broken = candidates
+            .iter()
+            .find_map(|candidate| Some((candidate, broken_symlink(candidate)?)));
+        if let Some((link, target)) = broken {
+            return Err(new(
+                ErrorKind::NotFound,
+                format!(
+                    "Could not resolve {} from {}: {} is a broken symlink to {}",
+                    module_specifier,
+                    referer_origin_url,
+                    link.display(),
+                    target.display()
+                ),
+            ));
+        }
+        // On a case-sensitive filesystem, a file only a case-insensitive one would find
+        let miscased = candidates
+            .iter()
+            .find_map(|candidate| Some((candidate, case_mismatch(candidate)?)));
+        if let Some((path, actual)) = miscased {
+            return Err(new(
+                ErrorKind::NotFound,
+                format!(
+                    "Could not resolve {} from {}: {}",
+                    module_specifier,
+                    referer_origin_url,
+                    case_mismatch_message(path, &actual)
+                ),
+            ));
+        }
+        // Suggestions never reach outside the working directory.
+        let suggestions = match url::Url::parse(&self.default_working_url)
+            .ok()
+            .and_then(|root| root.to_file_path().ok())
+        {
+            Some(root) => nearby_files(&module_file_path, &root),
+            None => vec![],
+        };
+        if suggestions.is_empty() {
+            return Err(FlyError::from(format!(
+                "Could not resolve {} from {} ",
+                module_specifier, referer_origin_url
+            )));
+        }
+        Err(new(
+            ErrorKind::NotFound,
+            format!(
+                "Could not resolve {} from {}: did you mean {}?",
+                module_specifier,
+                referer_origin_url,
+                suggestions
+                    .iter()
+                    .map(|name| respecify(module_specifier, name))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        ))
+    }
+    fn get_protocol(&self) -> String {
+        return "file".to_string();
+    }
+    fn handles_bare_specifier(&self, specifier: &str) -> bool {
+        match_path_alias(&self.paths, specifier).is_some()
+    }
+    fn describe(&self) -> ResolverDescription {
+        let mut description = ResolverDescription::new("local_disk", "file")
+            .named(&self.name())
+            .with("root", &self.default_working_url)
+            .with("strict", &self.strict.to_string())
+            .with("index_files", &self.index_files.join(", "))
+            .with("extensions", &self.extensions.join(", "))
+            .with("data_formats", &self.data_formats.to_string())
+            .with("allow_outside_root", &self.allow_outside_root.to_string())
+            .with("canonicalize", &self.canonicalize.to_string())
+            .with("case_sensitivity", &format!("{:?}", self.case_sensitivity));
+        if !self.roots.is_empty() {
+            let roots: Vec<_> = self
+                .roots
+                .iter()
+                .map(|root| root.display().to_string())
+                .collect();
+            description = description.with("roots", &roots.join(", "));
+        }
+        if !self.paths.is_empty() {
+            let paths: Vec<_> = self
+                .paths
+                .iter()
+                .map(|(pattern, targets)| format!("{} -> {}", pattern, targets.join(" | ")))
+                .collect();
+            description = description.with("paths", &paths.join(", "));
+        }
+        match self.watcher {
+            Some(ref watcher) => {
+                description.with("watched_files", &watcher.watched_files().to_string())
+            }
+            None => description,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        wasm_bytes, AssetLoaders, ImportOptions, LoadedModule, ModuleResolverManager,
+        ResolutionCache, StandardModuleResolverManager,
+    };
+
+    fn app_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("util.ts"), "export default 1;").unwrap();
+        dir
+    }
+
+    fn resolver(dir: &tempfile::TempDir) -> LocalDiskModuleResolver {
+        LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(dir.path())
+                .unwrap()
+                .as_str()
+                .to_string(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_local_disk_probes_extensions() {
+        let dir = app_dir();
+        let data = resolver(&dir).resolve_module("./util", None).unwrap();
+        assert!(data.origin_url.ends_with("/util.ts"));
+    }
+
+    #[test]
+    fn test_local_disk_denies_paths_outside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("app");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("main.ts"), "").unwrap();
+        std::fs::write(dir.path().join("secret.ts"), "").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret.ts"), root.join("link.ts")).unwrap();
+        std::os::unix::fs::symlink(root.join("main.ts"), root.join("alias.ts")).unwrap();
+        let resolver = LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(&root).unwrap().into_string(),
+        ))
+        .unwrap();
+
+        let outside = url::Url::from_file_path(dir.path().join("secret.ts")).unwrap();
+        for specifier in &["../secret.ts", "../secret", "./link.ts", outside.as_str()] {
+            let err = resolver.resolve_module(specifier, None).err().unwrap();
+            assert_eq!(ErrorKind::PermissionDenied, err.kind(), "{}", specifier);
+            assert!(
+                format!("{}", err).contains("outside the sandbox root"),
+                "{}",
+                err
+            );
+        }
+        // `..` and symlinks that stay inside are fine
+        assert!(resolver.resolve_module("../app/main.ts", None).is_ok());
+        assert!(resolver.resolve_module("./alias.ts", None).is_ok());
+
+        let anywhere = resolver.with_allow_outside_root(true);
+        assert!(anywhere.resolve_module("../secret.ts", None).is_ok());
+        assert!(anywhere.resolve_module("./link.ts", None).is_ok());
+    }
+
+    #[test]
+    fn test_local_disk_reports_case_mismatched_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("utils.ts"), "export default 1;").unwrap();
+        let resolver = LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(&root).unwrap().into_string(),
+        ))
+        .unwrap();
+
+        // what a case-sensitive filesystem makes of them
+        for specifier in &["./Utils.ts", "./Utils"] {
+            let err = resolver.resolve_module(specifier, None).err().unwrap();
+            assert_eq!(ErrorKind::NotFound, err.kind());
+            assert!(
+                err.to_string()
+                    .ends_with(": Utils.ts differs in case from utils.ts on disk"),
+                "{}",
+                err
+            );
+        }
+
+        // what a case-insensitive one finds
+        let found = root.join("Utils.ts");
+        let check = |mode| {
+            LocalDiskModuleResolver::new(None)
+                .unwrap()
+                .with_case_sensitivity(mode)
+                .check_case(&found, "./Utils.ts", "file:///app/main.ts")
+        };
+        assert!(check(CaseSensitivityMode::Ignore).is_ok());
+        assert!(check(CaseSensitivityMode::Warn).is_ok());
+        assert_eq!(
+            "Could not resolve ./Utils.ts from file:///app/main.ts: Utils.ts differs in case from utils.ts on disk",
+            check(CaseSensitivityMode::Error).err().unwrap().to_string()
+        );
+        assert!(resolver
+            .check_case(&root.join("utils.ts"), "./utils.ts", "file:///app/main.ts")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_local_disk_reads_bommed_and_utf16_sources() {
+        let dir = app_dir();
+        std::fs::write(dir.path().join("bom.ts"), b"\xEF\xBB\xBFexport default 1;").unwrap();
+        let utf16: Vec<u8> = std::iter::once(0xFEFF)
+            .chain("export default 2;".encode_utf16())
+            .flat_map(|unit| unit.to_le_bytes().to_vec())
+            .collect();
+        std::fs::write(dir.path().join("utf16.ts"), utf16).unwrap();
+        let load = |specifier: &str| {
+            resolver(&dir)
+                .resolve_module(specifier, None)
+                .unwrap()
+                .source_loader
+                .load_source()
+        };
+        assert_eq!("export default 1;", &*load("./bom.ts").unwrap().source);
+        assert_eq!("export default 2;", &*load("./utf16.ts").unwrap().source);
+    }
+
+    #[test]
+    fn test_file_queries_name_instances_and_fragments_nothing() {
+        let dir = app_dir();
+        std::fs::write(dir.path().join("widget.js"), "export default {};").unwrap();
+        std::fs::write(dir.path().join("config.json"), "{\"answer\": 42}").unwrap();
+        let working_url = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+            Some(working_url.clone()),
+        )
+        .with_resolution_cache(ResolutionCache::new());
+        let load = |specifier: &str| manager.resolve_entry_point(specifier.to_string()).unwrap();
+
+        let v3 = load("./widget.js?v=3");
+        assert_eq!(format!("{}widget.js?v=3", working_url), v3.origin_url);
+        assert_eq!("export default {};", &*v3.loaded_source.source);
+        assert_eq!(v3, load("./widget?v=3"));
+        let v4 = load("./widget.js?v=4");
+        assert_eq!(format!("{}widget.js?v=4", working_url), v4.origin_url);
+        assert_eq!(
+            format!("{}widget.js", working_url),
+            load("./widget.js#frag").origin_url
+        );
+        assert_eq!(3, manager.resolution_cache().unwrap().stats().modules);
+
+        // formats still go by the file's extension
+        let config = load("./config.json?v=1");
+        assert_ne!("{\"answer\": 42}", &*config.loaded_source.source);
+        assert_eq!(
+            load("./config.json").loaded_source.source,
+            config.loaded_source.source
+        );
+    }
+
+    #[test]
+    fn test_roots_are_searched_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, source: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        };
+        write("src/main.ts", "import './peer';");
+        write("src/peer.ts", "export const from = 'src';");
+        write("vendor/lib/index.ts", "import './peer';");
+        write("vendor/lib/peer.ts", "export const from = 'vendor';");
+        write("shared/util.js", "export default 1;");
+        let roots = vec![
+            dir.path().join("src"),
+            dir.path().join("vendor"),
+            dir.path().join("shared"),
+        ];
+        let resolver = LocalDiskModuleResolver::new(None)
+            .unwrap()
+            .with_roots(roots.clone());
+        let resolve = |specifier: &str, referer: Option<&str>| {
+            resolver.resolve_module(
+                specifier,
+                referer.map(|origin_url| RefererInfo {
+                    origin_url: origin_url.to_string(),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+        };
+        let url_of = |path: &str| {
+            url::Url::from_file_path(dir.path().join(path))
+                .unwrap()
+                .into_string()
+        };
+
+        assert_eq!(
+            url_of("src/main.ts"),
+            resolve("main", None).unwrap().origin_url
+        );
+        // the first root with it, by extensions and index files
+        let lib = resolve("lib", None).unwrap();
+        assert_eq!(url_of("vendor/lib/index.ts"), lib.origin_url);
+        assert_eq!(
+            url_of("shared/util.js"),
+            resolve("./util", None).unwrap().origin_url
+        );
+
+        // relative to where the importing module is, not searched from the first root
+        assert_eq!(
+            url_of("vendor/lib/peer.ts"),
+            resolve("./peer", Some(&lib.origin_url)).unwrap().origin_url
+        );
+        assert_eq!(
+            "export const from = 'vendor';",
+            &*resolve("./peer", Some(&lib.origin_url))
+                .unwrap()
+                .source_loader
+                .load_source()
+                .unwrap()
+                .source
+        );
+
+        let err = resolve("missing", None).err().unwrap();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+        let msg = err.to_string();
+        for root in &roots {
+            for candidate in &["missing", "missing.ts", "missing.wasm"] {
+                let path = root.join(candidate).display().to_string();
+                assert!(msg.contains(&path), "{} lacks {}", msg, path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_paths_aliases_resolve_bare_specifiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "export default 1;").unwrap();
+        };
+        write("src/main.ts");
+        write("src/widgets/button.tsx");
+        write("packages/lib/dist/format.js");
+        write("lodash.ts");
+        let root = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                LocalDiskModuleResolver::new(Some(root.clone()))
+                    .unwrap()
+                    .with_paths(vec![
+                        ("@app/*".to_string(), vec!["src/*".to_string()]),
+                        (
+                            "@lib/*".to_string(),
+                            vec![
+                                "packages/lib/src/*".to_string(),
+                                "packages/lib/dist/*".to_string(),
+                            ],
+                        ),
+                    ]),
+            ) as Box<ModuleResolver>],
+            Some(root.clone()),
+        );
+        let main = format!("{}src/main.ts", root);
+        let resolve = |specifier: &str| {
+            manager.resolve_blocking(
+                specifier.to_string(),
+                Some(RefererInfo {
+                    origin_url: main.clone(),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+        };
+
+        assert_eq!(
+            format!("{}src/widgets/button.tsx", root),
+            resolve("@app/widgets/button").unwrap().origin_url
+        );
+        // the second target, as the first has no format
+        assert_eq!(
+            format!("{}packages/lib/dist/format.js", root),
+            resolve("@lib/format").unwrap().origin_url
+        );
+        let err = resolve("@lib/missing").err().unwrap();
+        let msg = err.to_string();
+        assert!(
+            msg.contains("no target of paths alias @lib/* exists"),
+            "{}",
+            msg
+        );
+        assert!(
+            msg.contains(
+                &dir.path()
+                    .join("packages/lib/src/missing.ts")
+                    .display()
+                    .to_string()
+            ) && msg.contains(
+                &dir.path()
+                    .join("packages/lib/dist/missing.ts")
+                    .display()
+                    .to_string()
+            ),
+            "{}",
+            msg
+        );
+
+        // anything else is joined to the referer as it always was
+        assert_eq!(
+            format!("{}src/widgets/button.tsx", root),
+            resolve("./widgets/button.tsx").unwrap().origin_url
+        );
+        assert!(resolve("lodash").is_err());
+    }
+
+    #[test]
+    fn test_awkward_file_names_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let working_url = url::Url::from_directory_path(&root).unwrap().into_string();
+        let resolver = LocalDiskModuleResolver::new(Some(working_url.clone())).unwrap();
+        let referer = |origin_url: &str| RefererInfo {
+            origin_url: origin_url.to_string(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        for name in &[
+            "módulos/hëllo world.ts",
+            "with spaces/a b.ts",
+            "hash#tag.ts",
+            "what?.ts",
+            "100%.ts",
+            "literal%20.ts",
+            "喂/你好.ts",
+        ] {
+            let path = root.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, format!("export default {:?};", name)).unwrap();
+            std::fs::write(path.with_file_name("sibling.ts"), "export default 1;").unwrap();
+
+            let module = resolver
+                .resolve_module(&path.display().to_string(), None)
+                .unwrap();
+            let origin_url = module.origin_url.clone();
+            let url = url::Url::parse(&origin_url).unwrap();
+            assert_eq!(path, url.to_file_path().unwrap(), "{}", name);
+            assert_eq!(
+                format!("export default {:?};", name),
+                &*module.source_loader.load_source().unwrap().source
+            );
+
+            // the origin as a specifier, and relative to the root, is the same module
+            let relative = format!("./{}", &origin_url[working_url.len()..]);
+            for specifier in &[origin_url.as_str(), relative.as_str()] {
+                let again = resolver.resolve_module(specifier, None).unwrap();
+                assert_eq!(origin_url, again.origin_url, "{}", specifier);
+            }
+
+            // and as a referer, its relative imports land beside it
+            let sibling = resolver
+                .resolve_module("./sibling.ts", Some(referer(&origin_url)))
+                .unwrap();
+            assert_eq!(
+                path.with_file_name("sibling.ts"),
+                url::Url::parse(&sibling.origin_url)
+                    .unwrap()
+                    .to_file_path()
+                    .unwrap()
+            );
+        }
+        assert!(resolver
+            .resolve_module(&format!("{}literal%2520.ts", working_url), None)
+            .is_ok());
+        assert!(resolver
+            .resolve_module(&format!("{}literal%20.ts", working_url), None)
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_and_huge_paths_fail_without_panicking() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let working_url = url::Url::from_directory_path(&root).unwrap().into_string();
+        let resolver = LocalDiskModuleResolver::new(Some(working_url)).unwrap();
+
+        let path = root.join(std::ffi::OsStr::from_bytes(b"\xFFmod.js"));
+        std::fs::write(&path, "export default 1;").unwrap();
+        let specifier = url::Url::from_file_path(&path).unwrap().into_string();
+        let err = resolver.resolve_module(&specifier, None).err().unwrap();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+        assert!(err.to_string().contains("\u{FFFD}mod.js"), "{}", err);
+        assert!(LocalDiskRawLoader::new(path, None).load_source().is_err());
+
+        let huge = format!("./{}x.js", "a/".repeat(3000));
+        let err = resolver.resolve_module(&huge, None).err().unwrap();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+        assert!(err.to_string().len() < 1024, "{}", err);
+
+        assert!(LocalDiskModuleResolver::new(None).is_ok());
+    }
+
+    #[test]
+    fn test_aliases_of_a_module_resolve_to_one_origin() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("lib")).unwrap();
+        std::fs::write(
+            root.join("registry.ts"),
+            "export const registry = new Map();",
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(root.join("registry.ts"), root.join("lib/registry.ts")).unwrap();
+        let working_url = url::Url::from_directory_path(&root).unwrap().into_string();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                LocalDiskModuleResolver::new(Some(working_url.clone()))
+                    .unwrap()
+                    .with_canonicalize(true),
+            ) as Box<ModuleResolver>],
+            Some(working_url.clone()),
+        )
+        .with_resolution_cache(ResolutionCache::new());
+        let absolute = root.join("registry.ts").display().to_string();
+        let dotted = format!("{}lib/./../registry", working_url);
+        let spellings = [
+            "./registry",
+            "./registry.ts",
+            absolute.as_str(),
+            "./lib/../registry.ts",
+            dotted.as_str(),
+            "./lib/registry",
+        ];
+        let modules: Vec<LoadedModule> = spellings
+            .iter()
+            .map(|specifier| {
+                manager
+                    .resolve_blocking(
+                        specifier.to_string(),
+                        Some(RefererInfo {
+                            origin_url: format!("{}main.ts", working_url),
+                            is_wasm: Some(false),
+                            source_code: None,
+                            identifier_hash: None,
+                            line: None,
+                            column: None,
+                            app_id: None,
+                            deadline: None,
+                        }),
+                    )
+                    .unwrap()
+            })
+            .collect();
+        let origins: std::collections::HashSet<&str> =
+            modules.iter().map(|m| m.origin_url.as_str()).collect();
+        assert_eq!(
+            vec![format!("{}registry.ts", working_url).as_str()],
+            origins.into_iter().collect::<Vec<_>>()
+        );
+        assert!(modules.iter().all(|module| *module == modules[0]));
+        assert_eq!(1, manager.resolution_cache().unwrap().stats().modules);
+    }
+
+    #[test]
+    fn test_local_disk_canonicalizes_symlinked_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("packages/util")).unwrap();
+        std::fs::create_dir(root.join("workspace")).unwrap();
+        std::fs::write(root.join("packages/util/index.ts"), "export default 1;").unwrap();
+        std::os::unix::fs::symlink(root.join("packages/util"), root.join("workspace/util"))
+            .unwrap();
+        std::os::unix::fs::symlink(root.join("missing.ts"), root.join("dangling.ts")).unwrap();
+        let resolver = LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(&root).unwrap().into_string(),
+        ))
+        .unwrap();
+        let origins = |resolver: &LocalDiskModuleResolver| {
+            ["./packages/util/index.ts", "./workspace/util/index.ts"]
+                .iter()
+                .map(|specifier| resolver.resolve_module(specifier, None).unwrap().origin_url)
+                .collect::<Vec<_>>()
+        };
+
+        // kept as imported
+        let preserved = origins(&resolver);
+        assert!(preserved[1].ends_with("/workspace/util/index.ts"));
+        assert_ne!(preserved[0], preserved[1]);
+
+        let canonical = origins(&resolver.with_canonicalize(true));
+        let physical = url::Url::from_file_path(root.join("packages/util/index.ts")).unwrap();
+        assert_eq!(vec![physical.to_string(), physical.to_string()], canonical);
+
+        let resolver = LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(&root).unwrap().into_string(),
+        ))
+        .unwrap();
+        for specifier in &["./dangling.ts", "./dangling"] {
+            let err = resolver.resolve_module(specifier, None).err().unwrap();
+            assert_eq!(ErrorKind::NotFound, err.kind());
+            let msg = err.to_string();
+            assert!(
+                msg.contains("dangling.ts is a broken symlink to"),
+                "{}",
+                msg
+            );
+            assert!(msg.contains("missing.ts"), "{}", msg);
+        }
+    }
+
+    #[test]
+    fn test_local_disk_origins_round_trip_through_referers() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("my app").join("ünïcödé");
+        std::fs::create_dir_all(app.join("lib")).unwrap();
+        std::fs::write(app.join("main.ts"), "import './lib/util.ts';").unwrap();
+        std::fs::write(app.join("lib/util.ts"), "export default 1;").unwrap();
+        let resolver = LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(dir.path())
+                .unwrap()
+                .into_string(),
+        ))
+        .unwrap();
+
+        let main_url = url::Url::from_file_path(app.join("main.ts")).unwrap();
+        let main = resolver.resolve_module(main_url.as_str(), None).unwrap();
+        assert!(
+            main.origin_url.contains("/my%20app/"),
+            "{}",
+            main.origin_url
+        );
+        let referer = RefererInfo {
+            origin_url: main.origin_url,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let util = resolver
+            .resolve_module("./lib/util.ts", Some(referer))
+            .unwrap();
+        assert_eq!(
+            app.join("lib/util.ts"),
+            url::Url::parse(&util.origin_url)
+                .unwrap()
+                .to_file_path()
+                .unwrap()
+        );
+        assert_eq!(
+            "export default 1;",
+            &*util.source_loader.load_source().unwrap().source
+        );
+
+        // names that aren't UTF-8 load too, rather than panicking
+        let raw = dir.path().join(std::ffi::OsStr::from_bytes(b"caf\xe9.js"));
+        std::fs::write(&raw, "export default 2;").unwrap();
+        let raw_url = url::Url::from_file_path(&raw).unwrap();
+        let loaded = resolver
+            .resolve_module(raw_url.as_str(), None)
+            .unwrap()
+            .source_loader
+            .load_source()
+            .unwrap();
+        assert_eq!("export default 2;", &*loaded.source);
+    }
+
+    #[test]
+    fn test_local_disk_resolves_directory_indexes() {
+        let dir = app_dir();
+        let utils = dir.path().join("utils");
+        std::fs::create_dir(&utils).unwrap();
+        std::fs::write(utils.join("index.ts"), "export * from './strings';").unwrap();
+        std::fs::write(utils.join("index.js"), "").unwrap();
+        std::fs::write(utils.join("strings.ts"), "").unwrap();
+
+        let index = resolver(&dir).resolve_module("./utils", None).unwrap();
+        assert!(index.origin_url.ends_with("/utils/index.ts"));
+        let referer = RefererInfo {
+            origin_url: index.origin_url,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let strings = resolver(&dir)
+            .resolve_module("./strings", Some(referer))
+            .unwrap();
+        assert!(strings.origin_url.ends_with("/utils/strings.ts"));
+
+        let js_first = resolver(&dir).with_index_files(vec!["index.js".into(), "index.ts".into()]);
+        let index = js_first.resolve_module("./utils/", None).unwrap();
+        assert!(index.origin_url.ends_with("/utils/index.js"));
+        // a file next to the directory wins, as in Node
+        std::fs::write(dir.path().join("utils.js"), "").unwrap();
+        let file = js_first.resolve_module("./utils", None).unwrap();
+        assert!(file.origin_url.ends_with("/utils.js"));
+        assert!(resolver(&dir)
+            .with_strict(true)
+            .resolve_module("./utils/", None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_local_disk_appends_extensions_in_order() {
+        let dir = app_dir();
+        for file in &[
+            "config.prod.ts",
+            "foo.min.js",
+            "view.tsx",
+            "both.ts",
+            "both.js",
+        ] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        let resolve = |resolver: &LocalDiskModuleResolver, specifier| {
+            resolver.resolve_module(specifier, None).unwrap().origin_url
+        };
+        assert!(resolve(&resolver(&dir), "./config.prod").ends_with("/config.prod.ts"));
+        assert!(resolve(&resolver(&dir), "./foo.min").ends_with("/foo.min.js"));
+        assert!(resolve(&resolver(&dir), "./view").ends_with("/view.tsx"));
+        assert!(resolve(&resolver(&dir), "./both").ends_with("/both.ts"));
+        // the exact file first, whatever the extensions
+        assert!(resolve(&resolver(&dir), "./both.js").ends_with("/both.js"));
+
+        let js_first = resolver(&dir).with_extensions(vec!["js".into(), "ts".into()]);
+        assert!(resolve(&js_first, "./both").ends_with("/both.js"));
+        assert!(js_first.resolve_module("./view", None).is_err());
+    }
+
+    #[test]
+    fn test_local_disk_never_replaces_extensions() {
+        let dir = app_dir();
+        for file in &[
+            "data.prod",
+            "data.ts",
+            "theme.ts",
+            "theme.dark.ts",
+            "palette.ts",
+            "lib.v2.js",
+        ] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        let resolve = |specifier| {
+            resolver(&dir)
+                .resolve_module(specifier, None)
+                .map(|module| module.origin_url)
+        };
+        // an extensionless real file wins over a sibling with the same stem
+        assert!(resolve("./data.prod").unwrap().ends_with("/data.prod"));
+        // the dotted name is kept whole, its last part isn't an extension to swap
+        assert!(resolve("./theme.dark").unwrap().ends_with("/theme.dark.ts"));
+        // each candidate starts from the name, so .js is tried on lib.v2 after .ts missed
+        assert!(resolve("./lib.v2").unwrap().ends_with("/lib.v2.js"));
+        // nor does palette.ts stand in for palette.light, it's only suggested
+        let err = resolve("./palette.light").err().unwrap();
+        assert!(
+            err.to_string().ends_with("did you mean ./palette.ts?"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_local_disk_turns_json_into_modules() {
+        let dir = app_dir();
+        std::fs::write(dir.path().join("config.json"), r#"{"port": 8080}"#).unwrap();
+        std::fs::write(dir.path().join("broken.json"), "{\"port\": }").unwrap();
+        std::fs::write(dir.path().join("data.ts"), "").unwrap();
+        std::fs::write(dir.path().join("app.config.ts"), "").unwrap();
+
+        let load = |specifier| {
+            resolver(&dir)
+                .resolve_module(specifier, None)?
+                .source_loader
+                .load_source()
+        };
+        let config = load("./config.json").unwrap();
+        assert_eq!(
+            "const __data = {\"port\":8080};\nexport default __data;\n",
+            &*config.source
+        );
+        let err = format!("{}", load("./broken.json").err().unwrap());
+        assert!(err.contains("/broken.json:1:10: invalid JSON"), "{}", err);
+        // only script extensions are swapped
+        assert!(resolver(&dir).resolve_module("./data.json", None).is_err());
+        let data = resolver(&dir).resolve_module("./app.config", None).unwrap();
+        assert!(data.origin_url.ends_with("/app.config.ts"));
+    }
+
+    #[test]
+    fn test_import_assertions_are_honored_on_disk() {
+        let dir = app_dir();
+        std::fs::write(dir.path().join("config.json"), r#"{"port": 8080}"#).unwrap();
+        std::fs::write(dir.path().join("settings"), r#"{"debug": true}"#).unwrap();
+        std::fs::write(dir.path().join("notes"), "not json").unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+            None,
+        );
+        let import = |specifier: &str, asserted: Option<&str>| {
+            manager
+                .resolve_import(
+                    specifier.to_string(),
+                    None,
+                    ImportOptions::from_asserted(asserted).unwrap(),
+                )
+                .wait()
+        };
+
+        // matching
+        let config = import("./config.json", Some("json")).unwrap().loaded_source;
+        assert_eq!(ModuleKind::Json, config.kind);
+        assert!(
+            config.source.contains("{\"port\":8080}"),
+            "{}",
+            config.source
+        );
+        let settings = import("./settings", Some("json")).unwrap().loaded_source;
+        assert_eq!(ModuleKind::Json, settings.kind);
+        assert!(
+            settings.source.contains("export default"),
+            "{}",
+            settings.source
+        );
+        let err = import("./notes", Some("json")).err().unwrap();
+        let msg = err.to_string();
+        assert!(
+            msg.contains("/notes:1:") && msg.contains("invalid JSON"),
+            "{}",
+            msg
+        );
+
+        // mismatching
+        let err = import("./util.ts", Some("json")).err().unwrap();
+        assert_eq!(ErrorKind::ModuleTypeMismatch, err.kind());
+        assert!(
+            err.to_string()
+                .ends_with("/util.ts with type: \"json\": it is a typescript module"),
+            "{}",
+            err
+        );
+
+        // absent
+        assert_eq!(
+            ModuleKind::Json,
+            import("./config.json", None).unwrap().loaded_source.kind
+        );
+        let settings = import("./settings", None).unwrap().loaded_source;
+        assert_eq!(ModuleKind::JavaScript, settings.kind);
+        assert_eq!(r#"{"debug": true}"#, &*settings.source);
+        assert!(import("./util.ts", None).is_ok());
+    }
+
+    #[test]
+    fn test_local_disk_wraps_commonjs_require_chains() {
+        let root = url::Url::from_directory_path(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/commonjs"
+        ))
+        .unwrap()
+        .into_string();
+        let resolver = LocalDiskModuleResolver::new(Some(root.clone())).unwrap();
+        let load = |resolver: &LocalDiskModuleResolver, specifier, referer: &str| {
+            let referer = RefererInfo {
+                origin_url: referer.to_string(),
+                is_wasm: Some(false),
+                source_code: None,
+                identifier_hash: None,
+                line: None,
+                column: None,
+                app_id: None,
+                deadline: None,
+            };
+            let module = resolver.resolve_module(specifier, Some(referer)).unwrap();
+            let source = module.source_loader.load_source().unwrap();
+            (module.origin_url, source.source.to_string())
+        };
+
+        let (main, source) = load(&resolver, "./main.js", &root);
+        assert!(source.starts_with("import * as __cjs_dep0 from \"./lib/greet\";"));
+        assert!(source.ends_with("export default __cjs_module.exports;\n"));
+        let (greet, source) = load(&resolver, "./lib/greet", &main);
+        assert!(greet.ends_with("/commonjs/lib/greet.js"));
+        assert!(source.starts_with("import * as __cjs_dep0 from \"../strings\";"));
+        let (strings, source) = load(&resolver, "../strings", &greet);
+        assert!(strings.ends_with("/commonjs/strings.js"));
+        assert!(!source.contains("import * as"));
+        assert!(source.contains("exports.punctuate"));
+        assert!(source.ends_with("export default __cjs_module.exports;\n"));
+
+        let (_, source) = load(&resolver, "./esm.js", &root);
+        assert!(source.starts_with("// not to be confused"));
+        let plain = LocalDiskModuleResolver::new(Some(root.clone()))
+            .unwrap()
+            .with_data_formats(DataFormats::default().without_commonjs());
+        let (_, source) = load(&plain, "./main.js", &root);
+        assert!(source.starts_with("const greet = require"));
+    }
+
+    #[test]
+    fn test_local_disk_imports_text_assets() {
+        let dir = app_dir();
+        std::fs::write(dir.path().join("email.txt"), "Hi ${name},\n`see you`\n").unwrap();
+        let load = |resolver: LocalDiskModuleResolver| {
+            let module = resolver.resolve_module("./email.txt", None).unwrap();
+            module
+                .source_loader
+                .load_source()
+                .unwrap()
+                .source
+                .to_string()
+        };
+        assert_eq!(
+            "export default \"Hi ${name},\\n`see you`\\n\";\n",
+            load(resolver(&dir))
+        );
+        let without = DataFormats::default().with_assets(AssetLoaders::text(&["css"]));
+        assert_eq!(
+            "Hi ${name},\n`see you`\n",
+            load(resolver(&dir).with_data_formats(without))
+        );
+    }
+
+    #[test]
+    fn test_local_disk_loads_wasm_binaries() {
+        let resolver = LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/wasm"
+            ))
+            .unwrap()
+            .into_string(),
+        ))
+        .unwrap();
+        let data = resolver.resolve_module("./answer", None).unwrap();
+        assert!(data.origin_url.ends_with("/answer.wasm"));
+        let source = data.source_loader.load_source().unwrap();
+        assert!(source.is_wasm());
+        let bytes = wasm_bytes(&source).unwrap();
+        assert_eq!(b"\0asm", &bytes[..4]);
+        assert_eq!(39, bytes.len());
+    }
+
+    #[test]
+    fn test_strict_local_disk_requires_exact_file() {
+        let dir = app_dir();
+        let strict = resolver(&dir).with_strict(true);
+        let data = strict.resolve_module("./util.ts", None).unwrap();
+        assert!(data.origin_url.ends_with("/util.ts"));
+
+        let err = format!("{}", strict.resolve_module("./util", None).err().unwrap());
+        assert!(err.contains("strict resolution is on"));
+        assert!(err.contains("probing would have found"));
+        assert!(err.contains("util.ts"));
+
+        let err = format!(
+            "{}",
+            strict.resolve_module("./missing", None).err().unwrap()
+        );
+        assert!(err.contains("strict resolution is on"));
+        assert!(!err.contains("probing would have found"));
+    }
+
+    #[test]
+    fn test_local_disk_failures_suggest_nearby_files() {
+        let dir = app_dir();
+        let err = resolver(&dir)
+            .resolve_module("./utl.ts", None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+        assert!(format!("{}", err).ends_with(": did you mean ./util.ts?"));
+        let err = resolver(&dir)
+            .resolve_module("./server.ts", None)
+            .err()
+            .unwrap();
+        assert!(!format!("{}", err).contains("did you mean"));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::integrity::{source_digest, sri};
+use super::LoadedModule;
+
+const LOCKFILE_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LockfileMode {
+    /** Nothing is recorded or checked. */
+    Off,
+    /** Every resolution is recorded, replacing what was locked for it before. */
+    Write,
+    /** Every resolution must be exactly what's locked. */
+    #[serde(alias = "locked")]
+    Verify,
+}
+
+impl Default for LockfileMode {
+    fn default() -> Self {
+        LockfileMode::Off
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LockfileConfig {
+    pub path: String,
+    #[serde(default)]
+    pub mode: LockfileMode,
+}
+
+/**
+ * What one specifier, imported from one referer, resolved to. Urls under the lockfile's
+ * directory are kept relative to it, as `./...`, so the lockfile holds wherever the app is
+ * checked out.
+ */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LockedModule {
+    pub specifier: String,
+    /** `None` for entry points. */
+    pub referer: Option<String>,
+    pub origin_url: String,
+    /** The sha256 of the module as it's run, in the `sha256-...` form of an integrity pin. */
+    pub integrity: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LockfileContents {
+    version: u32,
+    modules: Vec<LockedModule>,
+}
+
+type Key = (String, String);
+
+fn lockfile_error(path: &Path, msg: impl std::fmt::Display) -> FlyError {
+    FlyError::from(format!("lockfile {}: {}", path.display(), msg))
+}
+
+/**
+ * Locks every module a manager resolves to the origin url it resolved to and the hash of
+ * its source, for deploys that run exactly what was tested. Writing records resolutions as
+ * they happen, saving after each new one, sorted by referer then specifier so the same graph
+ * always writes the same file. Verifying fails any resolution that isn't in the lockfile,
+ * resolved elsewhere or loaded something else, with `ErrorKind::IntegrityMismatch`.
+ */
+pub struct Lockfile {
+    path: PathBuf,
+    mode: LockfileMode,
+    /** What urls are kept relative to: the lockfile's directory, as a url and a path. */
+    root: (String, String),
+    entries: Mutex<BTreeMap<Key, LockedModule>>,
+}
+
+impl Lockfile {
+    /**
+     * The lockfile at `path`, which must exist unless `mode` is `Write`: writing starts one
+     * from scratch, or adds to one already there.
+     */
+    pub fn open(path: &Path, mode: LockfileMode) -> FlyResult<Self> {
+        if mode == LockfileMode::Write && !path.exists() {
+            return Self::empty(path, mode);
+        }
+        let contents = std::fs::read_to_string(path).map_err(|e| lockfile_error(path, e))?;
+        let contents: LockfileContents =
+            serde_json::from_str(&contents).map_err(|e| lockfile_error(path, e))?;
+        if contents.version != LOCKFILE_VERSION {
+            return Err(lockfile_error(
+                path,
+                format!("unsupported version {}", contents.version),
+            ));
+        }
+        let lockfile = Self::empty(path, mode)?;
+        lockfile
+            .entries
+            .lock()
+            .unwrap()
+            .extend(contents.modules.into_iter().map(|module| {
+                let key = (
+                    module.referer.clone().unwrap_or_default(),
+                    module.specifier.clone(),
+                );
+                (key, module)
+            }));
+        Ok(lockfile)
+    }
+
+    /** Reads the lockfile at `path` to verify against. */
+    pub fn load(path: &Path) -> FlyResult<Self> {
+        Self::open(path, LockfileMode::Verify)
+    }
+
+    fn empty(path: &Path, mode: LockfileMode) -> FlyResult<Self> {
+        let dir = match path.parent() {
+            Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let dir = dir.canonicalize().map_err(|e| lockfile_error(path, e))?;
+        let root_url = url::Url::from_directory_path(&dir)
+            .map_err(|_| lockfile_error(path, "not in a local directory"))?
+            .into_string();
+        let root_path = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+        Ok(Self {
+            path: path.to_path_buf(),
+            mode,
+            root: (root_url, root_path),
+            entries: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    pub fn mode(&self) -> LockfileMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /** Everything locked, sorted by referer then specifier. */
+    pub fn modules(&self) -> Vec<LockedModule> {
+        self.entries.lock().unwrap().values().cloned().collect()
+    }
+
+    fn relative(&self, url: &str) -> String {
+        let (ref root_url, ref root_path) = self.root;
+        if url.starts_with(root_url.as_str()) {
+            format!("./{}", &url[root_url.len()..])
+        } else if url.starts_with(root_path.as_str()) {
+            format!("./{}", &url[root_path.len()..])
+        } else {
+            url.to_string()
+        }
+    }
+
+    /** Writes everything locked to the lockfile, replacing it whole. */
+    pub fn save(&self) -> FlyResult<()> {
+        let entries = self.entries.lock().unwrap();
+        self.write(&entries)
+    }
+
+    fn write(&self, entries: &BTreeMap<Key, LockedModule>) -> FlyResult<()> {
+        let contents = LockfileContents {
+            version: LOCKFILE_VERSION,
+            modules: entries.values().cloned().collect(),
+        };
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(
+            &tmp,
+            serde_json::to_string_pretty(&contents).unwrap() + "\n",
+        )
+        .and_then(|_| std::fs::rename(&tmp, &self.path))
+        .map_err(|e| lockfile_error(&self.path, e))
+    }
+
+    /**
+     * Records or verifies, depending on the mode, that `specifier` imported from `referer`
+     * resolved to `module`.
+     */
+    pub fn check(
+        &self,
+        specifier: &str,
+        referer: Option<&str>,
+        module: &LoadedModule,
+    ) -> FlyResult<()> {
+        if self.mode == LockfileMode::Off {
+            return Ok(());
+        }
+        let locked = LockedModule {
+            specifier: self.relative(specifier),
+            referer: referer.map(|r| self.relative(r)),
+            origin_url: self.relative(&module.origin_url),
+            integrity: sri(&source_digest(&module.loaded_source)?),
+        };
+        let key = (
+            locked.referer.clone().unwrap_or_default(),
+            locked.specifier.clone(),
+        );
+        let mut entries = self.entries.lock().unwrap();
+        if self.mode == LockfileMode::Write {
+            if entries.get(&key) == Some(&locked) {
+                return Ok(());
+            }
+            entries.insert(key, locked);
+            return self.write(&entries);
+        }
+        let from = referer.map_or(String::new(), |r| format!(" from {}", r));
+        let msg = match entries.get(&key) {
+            None => format!(
+                "Lockfile check failed for {}{}: it isn't in {}, resolved to {} with {}",
+                specifier,
+                from,
+                self.path.display(),
+                module.origin_url,
+                locked.integrity
+            ),
+            Some(expected) if expected.origin_url != locked.origin_url => format!(
+                "Lockfile check failed for {}{}: expected it to resolve to {} but it resolved to {}",
+                specifier, from, expected.origin_url, locked.origin_url
+            ),
+            Some(expected) if expected.integrity != locked.integrity => format!(
+                "Lockfile check failed for {}{} ({}): expected {} but loaded {}",
+                specifier, from, module.origin_url, expected.integrity, locked.integrity
+            ),
+            Some(_) => return Ok(()),
+        };
+        Err(new(ErrorKind::IntegrityMismatch, msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager, RefererInfo,
+        StandardModuleResolverManager,
+    };
+    use std::sync::Arc;
+
+    fn manager(dir: &Path, lockfile: Lockfile) -> StandardModuleResolverManager {
+        let root = url::Url::from_directory_path(dir).unwrap().into_string();
+        StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(root.clone())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(root),
+        )
+        .with_lockfile(Arc::new(lockfile))
+    }
+
+    /** Resolves `main.js`, then `specifier` from it. */
+    fn resolve(manager: &StandardModuleResolverManager, specifier: &str) -> FlyResult<()> {
+        let main = manager.resolve_blocking("./main.js".to_string(), None)?;
+        let referer = RefererInfo {
+            origin_url: main.origin_url,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        manager
+            .resolve_blocking(specifier.to_string(), Some(referer))
+            .map(|_| ())
+    }
+
+    #[test]
+    fn test_verify_rejects_modules_changed_since_locking() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        std::fs::write(dir.join("main.js"), "import './util';").unwrap();
+        std::fs::write(dir.join("util.js"), "export default 1;").unwrap();
+        let path = dir.join("fly.lock");
+
+        let writing = manager(&dir, Lockfile::open(&path, LockfileMode::Write).unwrap());
+        resolve(&writing, "./util").unwrap();
+        let locked = Lockfile::load(&path).unwrap().modules();
+        assert_eq!(
+            vec![
+                ("", "./main.js", "./main.js"),
+                ("./main.js", "./util", "./util.js")
+            ],
+            locked
+                .iter()
+                .map(|m| (
+                    m.referer.as_ref().map_or("", |r| r.as_str()),
+                    &m.specifier[..],
+                    &m.origin_url[..]
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        // the same graph verifies
+        let verifying = manager(&dir, Lockfile::load(&path).unwrap());
+        resolve(&verifying, "./util").unwrap();
+
+        std::fs::write(dir.join("util.js"), "export default 2;").unwrap();
+        let verifying = manager(&dir, Lockfile::load(&path).unwrap());
+        let err = resolve(&verifying, "./util").err().unwrap();
+        assert_eq!(ErrorKind::IntegrityMismatch, err.kind());
+        let msg = err.to_string();
+        let util = locked.iter().find(|m| m.specifier == "./util").unwrap();
+        assert!(msg.contains("./util"), "{}", msg);
+        assert!(
+            msg.contains(&format!("expected {}", util.integrity)),
+            "{}",
+            msg
+        );
+
+        // a module that was never locked
+        std::fs::write(dir.join("other.js"), "export default 3;").unwrap();
+        let err = resolve(&verifying, "./other").err().unwrap();
+        assert_eq!(ErrorKind::IntegrityMismatch, err.kind());
+        assert!(err.to_string().contains("isn't in"), "{}", err);
+
+        // and one that resolves elsewhere now
+        std::fs::remove_file(dir.join("util.js")).unwrap();
+        std::fs::write(dir.join("util.ts"), "export default 1;").unwrap();
+        let err = resolve(&verifying, "./util").err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("expected it to resolve to ./util.js"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_rejects_missing_lockfiles_unless_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fly.lock");
+        assert!(Lockfile::load(&path).is_err());
+        let lockfile = Lockfile::open(&path, LockfileMode::Write).unwrap();
+        assert!(lockfile.modules().is_empty());
+        lockfile.save().unwrap();
+        assert!(Lockfile::load(&path).unwrap().modules().is_empty());
+        let mode: LockfileMode = serde_json::from_str("\"locked\"").unwrap();
+        assert_eq!(LockfileMode::Verify, mode);
+    }
+}
@@ -0,0 +1,3938 @@
+use crate::errors::*;
+
+use std::any::{Any, TypeId};
+use std::marker::Send;
+
+use std::clone::Clone;
+
+use std::collections::HashMap;
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use std::time::{Duration, Instant};
+
+use futures::future;
+use futures::Future;
+
+use bytes::Bytes;
+
+mod archive;
+mod asset_loader;
+mod base_url;
+mod blocking;
+mod builder;
+mod builtin_module;
+mod cache_state;
+mod candidates;
+mod case_check;
+mod cassette;
+mod cdn;
+mod cjs_loader;
+mod code_cache;
+mod compile_cache;
+mod compiled_registry;
+mod content_type;
+mod data_module;
+mod data_url;
+mod deadline;
+mod debug_resolve;
+mod deno_dir;
+mod describe;
+mod dev_server;
+mod disk_cache;
+mod entry_modules;
+mod env_var;
+mod fallback;
+mod fetch_policy;
+mod fly_builtins;
+mod function_module;
+mod github;
+mod graphql_loader;
+mod hmr;
+mod http_fetcher;
+mod http_module;
+mod import_map;
+mod import_options;
+mod integrity;
+mod json5_loader;
+mod json_loader;
+mod limits;
+mod loading;
+mod local_disk;
+mod lockfile;
+mod module_kind;
+mod multi_tenant;
+mod node_builtin;
+mod node_modules;
+mod npm;
+mod observer;
+mod path_alias;
+mod policy;
+mod preload;
+mod profile;
+mod progress;
+mod proxy;
+mod redis_module;
+mod remote_entry;
+mod resolution_cache;
+mod resolution_error;
+mod resolver_config;
+mod resolver_options;
+mod scheduler;
+mod secrets;
+mod shared_cache;
+mod signed;
+mod single_flight;
+mod source_map;
+mod source_text;
+mod sqlite_bundle;
+mod static_module;
+mod suggest;
+mod toml_loader;
+mod trace;
+mod transpile;
+mod vendor_cache;
+mod virtual_module;
+mod wasm_cache;
+mod wasm_imports;
+mod wasm_loader;
+mod watch;
+mod workspace;
+mod yaml_loader;
+mod zip_bundle;
+pub use self::archive::{ArchiveBundle, ArchiveEntryLoader, ArchiveLimits, ArchiveModuleResolver};
+pub use self::asset_loader::{
+    bytes_to_module, text_to_module, AssetLoaderFactory, AssetLoaders, BinaryAssetLoader,
+    TextAssetLoader, DEFAULT_BINARY_ASSETS, DEFAULT_BINARY_ASSET_MAX_BYTES, DEFAULT_TEXT_ASSETS,
+};
+pub use self::base_url::BaseUrlSubstitutions;
+pub use self::blocking::spawn_blocking;
+pub use self::builder::ModuleResolverManagerBuilder;
+pub use self::builtin_module::{BuiltinModuleResolver, BUILTIN_PROTOCOL};
+pub use self::cache_state::{CacheFreshness, CacheLocation, ModuleCacheState};
+use self::candidates::{Candidates, Located, Prepared};
+pub use self::case_check::{case_mismatch, CaseSensitivityMode};
+pub use self::cassette::{
+    CassetteConfig, CassetteEntry, CassetteMode, CassetteRecorder, CassetteResolver,
+};
+pub use self::cdn::{CdnResolver, PackageSpecifier, DEFAULT_CDN_TEMPLATE};
+pub use self::cjs_loader::{commonjs_requires, wrap_commonjs, CjsWrappingLoader};
+pub use self::code_cache::{CodeCache, CodeCacheStats, DEFAULT_CODE_CACHE_BYTES};
+pub use self::compile_cache::{SqliteModuleCache, DEFAULT_COMPILE_CACHE_BYTES};
+pub use self::compiled_registry::{CompiledModuleInfo, CompiledModuleRegistry};
+pub use self::content_type::{decode_text, parse_content_type, RemoteMediaType};
+pub use self::data_module::{js_string_literal, UnsafeIntegers};
+pub use self::data_url::{abbreviate_data_url, data_url_source, DataUrlResolver};
+pub use self::deadline::{ResolutionDeadline, ResolutionDeadlines};
+pub use self::debug_resolve::{debug_resolve, AttemptReport, ProbeReport, ResolutionReport};
+pub use self::deno_dir::{DenoCachedModule, DenoDirCache, DenoDirLoader};
+use self::describe::short_type_name;
+pub use self::describe::{ManagerDescription, ProtocolDescription, ResolverDescription};
+pub use self::dev_server::{
+    DevServerConfig, DevServerLoader, DevServerResolver, DEFAULT_DEV_SERVER_PROTOCOL,
+};
+pub use self::disk_cache::{
+    CachedRemoteModule, GcOptions, GcReason, GcRemoval, GcReport, GcSummary, RemoteModuleCache,
+    RemoteModuleCacheConfig, RemoteModuleCacheInfo, Revalidated, Revalidation, TenantCacheUsage,
+};
+pub use self::entry_modules::EntryModules;
+pub use self::env_var::EnvVarResolver;
+pub use self::fallback::{is_fatal_load_error, FallbackSourceLoader};
+pub use self::fetch_policy::{
+    policy_connector, FetchNetworkPolicy, FetchNetworkPolicyConfig, IpRange, PolicyResolver,
+    DEFAULT_BLOCKED_RANGES,
+};
+pub use self::fly_builtins::{
+    allowlisted_env, register_fly_builtins, BuildInfo, FLY_BUILD_SPECIFIER, FLY_ENV_SPECIFIER,
+};
+pub use self::function_module::{
+    AsyncFunctionModuleResolver, FunctionModuleResolver, LoadedSourceFuture, ResolveFn,
+};
+pub use self::github::{
+    GitHubModuleResolver, GitHubResolverConfig, GitHubSourceLoader, GitHubSpecifier,
+};
+pub use self::graphql_loader::GraphqlLoader;
+pub use self::hmr::{
+    HotReloadConfig, HotUpdate, LoadedModuleInfo, ModuleGraph, ModuleGraphSnapshot,
+};
+pub use self::http_fetcher::{
+    fetch_with_options, fetcher_allowing, fetcher_for, process_wide_fetcher,
+    set_process_wide_fetcher, status_error, FetchFuture, FetchOptions, FetchRequest, FetchResponse,
+    FunctionFetcher, HttpFetcher, HyperFetcher, ResponseMetadata, DEFAULT_MAX_REDIRECTS,
+    RECORDED_RESPONSE_HEADERS,
+};
+pub use self::http_module::{HttpModuleResolver, HttpSourceLoader};
+pub use self::import_map::ImportMap;
+pub use self::import_options::{ImportOptions, ImportType};
+pub use self::integrity::{IntegrityReport, ModuleIntegrity};
+pub use self::json5_loader::Json5Loader;
+pub use self::json_loader::{JsonLoader, JsonOptions, PrototypeKeys};
+pub use self::limits::{
+    module_byte_limit, with_module_byte_limit, ModuleLimit, ModuleLimits, DEFAULT_MAX_GRAPH_DEPTH,
+    DEFAULT_MAX_MODULE_BYTES, DEFAULT_MAX_TOTAL_MODULES,
+};
+pub use self::local_disk::{
+    LocalDiskModuleResolver, LocalDiskRawLoader, DEFAULT_EXTENSIONS, DEFAULT_INDEX_FILES,
+};
+pub use self::lockfile::{LockedModule, Lockfile, LockfileConfig, LockfileMode};
+pub use self::module_kind::ModuleKind;
+pub use self::multi_tenant::MultiTenantResolverManager;
+pub use self::node_builtin::{NodeBuiltinLoader, NodeBuiltinResolver};
+pub use self::node_modules::{discover_node_modules_root, NodeModulesResolver};
+pub use self::npm::{NpmResolver, NpmResolverConfig};
+pub use self::observer::{
+    PrometheusResolutionObserver, ProtocolResolutionStats, ResolutionMetrics, ResolutionObserver,
+    ResolutionOutcome, ResolveEnd, ResolveStart, RESOLUTION_LATENCY_BUCKETS_MS,
+};
+pub use self::path_alias::{match_path_alias, read_tsconfig_paths, PathAliases};
+pub use self::policy::ResolverPolicy;
+pub use self::preload::{
+    preload_graph, static_import_specifiers, wasm_url_references, PreloadSummary,
+};
+pub use self::profile::{
+    duration_ms, ModuleLoadProfile, ProtocolTotals, StartupProfiler, StartupReport,
+};
+pub use self::progress::{
+    format_progress, process_wide_progress, DownloadProgress, DownloadProgressReporter,
+    ProgressHub, ProgressObserver, ProgressOptions,
+};
+pub use self::proxy::{ProxyConfig, ProxyConnector, ProxyServer};
+pub use self::redis_module::{
+    RedisModuleResolver, RedisSourceLoader, DEFAULT_REDIS_CONNECT_TIMEOUT,
+};
+pub use self::remote_entry::{
+    check_remote_entry, network_policy, remote_entry_url, remote_module_resolvers,
+};
+pub use self::resolution_cache::{ResolutionCache, ResolutionCacheStats};
+pub use self::resolution_error::{ModuleResolutionError, ResolverAttempt};
+pub use self::resolver_config::{ResolverCacheConfig, ResolverConfig, ResolverKind, ResolverSpec};
+pub use self::resolver_options::{
+    ResolutionStrategy, ResolverOptions, RetryPolicy, DEFAULT_RESOLVER_TIMEOUT,
+};
+pub use self::scheduler::{
+    process_wide_scheduler, Acquiring, LoadPermit, LoadScheduler, LoadSchedulerConfig,
+    DEFAULT_TENANT,
+};
+pub use self::secrets::{JsonSecretsLoader, JsonSecretsResolver, SecretsHandle};
+pub use self::shared_cache::{
+    process_wide_cache, CompressionAlgorithm, SharedSourceCache, SharedSourceCacheStats,
+    SourceCompression, DEFAULT_SHARED_CACHE_BYTES,
+};
+pub use self::signed::{SignatureLocation, SignedModuleResolver, SIGNATURE_PREFIX};
+pub use self::single_flight::{CancellationToken, SingleFlight};
+pub use self::source_map::{absolutize_source_map, discover_source_map, inline_source_map_sources};
+pub use self::source_text::{decode_source_text, read_source_text};
+pub use self::sqlite_bundle::{SqliteBundleLoader, SqliteBundleResolver};
+pub use self::static_module::{StaticModuleResolver, StaticSourceLoader};
+pub use self::toml_loader::TomlLoader;
+use self::trace::{push_step, record_step, traced, TraceHandle, TraceLog};
+pub use self::trace::{ResolutionTrace, TraceStep, TRACE_CAPACITY};
+pub use self::transpile::{
+    source_flavor, transpile_source, transpile_typescript, typescript_flavor, TranspilingLoader,
+    TRANSPILES,
+};
+pub use self::vendor_cache::{CachingResolverWrapper, DEFAULT_VENDOR_CACHE_DIR};
+pub use self::virtual_module::{
+    MediaType, VirtualModule, VirtualModuleRegistry, VirtualModuleResolver, VirtualSourceLoader,
+};
+pub use self::wasm_cache::{WasmCompileCache, WasmCompileCacheStats};
+pub use self::wasm_imports::{declared_imports, WasmImport, WasmImportMap};
+pub use self::wasm_loader::{is_wasm_file, wasm_bytes, wasm_source, LocalDiskWasmLoader};
+pub use self::watch::{InvalidationCallback, ModuleWatcher, DEFAULT_WATCH_DEBOUNCE};
+pub use self::workspace::{WorkspaceMember, WorkspaceResolver, DEFAULT_ENTRY_CANDIDATES};
+pub use self::yaml_loader::{YamlLimits, YamlLoader};
+pub use self::zip_bundle::{BundleManifest, ZipBundleResolver, ZipMemberLoader};
+
+#[derive(Clone, Debug)]
+pub struct RefererInfo {
+    pub origin_url: String,
+    pub is_wasm: Option<bool>,
+    /**
+     * The referer's own source, for referers with no file to re-read: eval'd snippets,
+     * virtual modules, generated code. At most `MAX_REFERER_SOURCE_BYTES`, see
+     * `cap_referer_source`.
+     */
+    pub source_code: Option<Arc<str>>,
+    /**
+     * V8's identity hash of the referer's compiled module, the key the runtime's module
+     * registry knows it by. Stable for as long as the module stays compiled in that runtime,
+     * so it keys per-runtime caches, though not across runtimes or restarts. `None` when the
+     * referer isn't a compiled module, or the caller didn't know it.
+     */
+    pub identifier_hash: Option<i32>,
+    /** The 1-based line of the import in the referer, when the caller knew it. */
+    pub line: Option<u32>,
+    /** The 1-based column of the import on `line`. */
+    pub column: Option<u32>,
+    pub app_id: Option<String>,
+    /**
+     * What's left of the top-level load this import is part of, see `ResolutionDeadline`.
+     * Resolvers resolving further modules through the manager pass it on with the referer.
+     */
+    pub deadline: Option<ResolutionDeadline>,
+}
+
+/**
+ * How much of a referer's source is passed along with a resolution.
+ */
+pub const MAX_REFERER_SOURCE_BYTES: usize = 256 * 1024;
+
+/**
+ * The protocol of fallback resolvers, tried for every url after the resolvers for its own
+ * protocol, if any, fail. Say, forwarding unknown schemes to a proxy, or denying them with a
+ * friendlier error.
+ */
+pub const FALLBACK_PROTOCOL: &str = "*";
+
+/**
+ * `source`, cut at `MAX_REFERER_SOURCE_BYTES` on a line break where possible, with a
+ * trailing marker saying so. A source under the cap that's already an `Arc<str>` is shared,
+ * not copied.
+ */
+pub fn cap_referer_source<S: AsRef<str> + Into<Arc<str>>>(source: S) -> Arc<str> {
+    if source.as_ref().len() <= MAX_REFERER_SOURCE_BYTES {
+        return source.into();
+    }
+    let source = source.as_ref();
+    let mut end = MAX_REFERER_SOURCE_BYTES;
+    while !source.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = source[..end].rfind('\n').map(|i| i + 1).unwrap_or(end);
+    format!(
+        "{}\n/* referer source truncated: {} of {} bytes */",
+        &source[..end],
+        end,
+        source.len()
+    )
+    .into()
+}
+
+/**
+ * Where in `referer` the import of `specifier` is, to append to errors: the position the
+ * caller gave, or failing that the first line of the referer's source importing it.
+ */
+fn import_site(referer: &RefererInfo, specifier: &str) -> Option<String> {
+    let source = referer.source_code.as_ref();
+    match (referer.line, referer.column) {
+        (Some(line), column) => {
+            let text = source
+                .and_then(|s| s.lines().nth((line as usize).checked_sub(1)?))
+                .map(|text| format!(": {}", text.trim()))
+                .unwrap_or_default();
+            let column = column.map(|c| format!(":{}", c)).unwrap_or_default();
+            Some(format!(
+                "\n    imported from {}:{}{}{}",
+                referer.origin_url, line, column, text
+            ))
+        }
+        (None, _) => {
+            let (line, text) = importing_line(source?, specifier)?;
+            Some(format!(
+                "\n    at {}:{}: {}",
+                referer.origin_url, line, text
+            ))
+        }
+    }
+}
+
+/**
+ * The 1-based number and text of the first line in `source` that imports `specifier`.
+ */
+fn importing_line<'a>(source: &'a str, specifier: &str) -> Option<(usize, &'a str)> {
+    let quoted = ["\"", "'", "`"]
+        .iter()
+        .map(|q| format!("{}{}{}", q, specifier, q))
+        .collect::<Vec<String>>();
+    source
+        .lines()
+        .enumerate()
+        .find(|(_, line)| quoted.iter().any(|q| line.contains(q.as_str())))
+        .map(|(i, line)| (i + 1, line.trim()))
+}
+
+/**
+ * Loaded module code. `source` is reference counted so the shared cache and every runtime
+ * loading the module point at the same immutable bytes; replacing a module swaps the `Arc`
+ * and never touches bytes someone else still holds.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedSourceCode {
+    pub kind: ModuleKind,
+    pub source_map: Option<Arc<str>>,
+    pub source: Arc<str>,
+    /** The HTTP response the source came from, for modules fetched over HTTP. */
+    pub response: Option<ResponseMetadata>,
+    /**
+     * Which of several places the source came from, for loaders that pick one, e.g. the
+     * label of the `FallbackSourceLoader` step that succeeded.
+     */
+    pub provenance: Option<String>,
+    /**
+     * V8's code cache of exactly this source, from the first runtime that compiled it, so
+     * the next compiles it without parsing it again. Only a manager's `CodeCache` sets it.
+     */
+    pub code_cache: Option<Vec<u8>>,
+    /**
+     * What V8 serialized compiling this wasm module, for the runtime to instantiate it
+     * without compiling. Only a manager's `WasmCompileCache` sets it.
+     */
+    pub compiled_wasm: Option<Vec<u8>>,
+    /**
+     * The binary of a wasm module, shared like `source` so no runtime decodes or copies it
+     * again; `source` keeps its base64 for the caches and checks that go by text. Sources
+     * read back from a cache have none, see `wasm_bytes`.
+     */
+    pub wasm: Option<Bytes>,
+}
+
+impl LoadedSourceCode {
+    /** Whether `source` is a wasm binary, base64 encoded. */
+    pub fn is_wasm(&self) -> bool {
+        self.kind.is_wasm()
+    }
+}
+
+/**
+ * A resolved module. Every specifier that reaches the same module, however it's spelled,
+ * resolves to the same `origin_url`, and through a manager's resolution cache to an equal
+ * `LoadedModule`, so the module is instantiated once.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedModule {
+    pub loaded_source: LoadedSourceCode,
+    pub origin_url: String,
+    /** Run the source as-is: it came from a resolver whose upstream already transformed it. */
+    pub passthrough: bool,
+    /**
+     * The `name` of the resolver that resolved it; `None` for virtual modules and modules no
+     * resolver of this manager resolved, like replayed ones.
+     */
+    pub resolver: Option<String>,
+}
+
+pub struct ModuleSourceData {
+    pub origin_url: String,
+    pub source_loader: Box<SourceLoader>,
+}
+
+/**
+ * Where a specifier resolves to, found without loading any of its source.
+ */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResolvedModuleInfo {
+    pub origin_url: String,
+    /** The scheme of `origin_url`. */
+    pub protocol: String,
+    /** The kind of resolver that claimed it, as `describe` names it, or `virtual`. */
+    pub resolver: String,
+    /** Going by `origin_url`'s extension; `None` when it has none. */
+    pub is_wasm: Option<bool>,
+}
+
+impl ResolvedModuleInfo {
+    fn new(origin_url: &str, resolver: &str, protocol: &str) -> Self {
+        let parsed = url::Url::parse(origin_url).ok();
+        let is_wasm = parsed
+            .as_ref()
+            .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+            .and_then(|name| {
+                let dot = name.rfind('.')?;
+                Some(&name[dot + 1..] == "wasm")
+            });
+        Self {
+            origin_url: origin_url.to_string(),
+            protocol: parsed
+                .map(|url| url.scheme().to_string())
+                .unwrap_or_else(|| protocol.to_string()),
+            resolver: resolver.to_string(),
+            is_wasm,
+        }
+    }
+}
+
+/**
+ * Similar function to what is known as a "loader" in the javascript packaging world
+ */
+pub trait SourceLoader: Send + Sync {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode>;
+
+    /**
+     * The source's bytes to read at the caller's pace, for callers that pass it on rather
+     * than run it. Only loaders that can stream their source need to implement it; the rest
+     * hand out what `load_source` loads.
+     */
+    fn load_reader(&self) -> FlyResult<Box<std::io::Read + Send>> {
+        let source = self.load_source()?.source;
+        Ok(Box::new(std::io::Cursor::new(SourceBytes(source))))
+    }
+}
+
+/** A loaded source as bytes, to read without copying them. */
+struct SourceBytes(Arc<str>);
+
+impl AsRef<[u8]> for SourceBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+/**
+ * Resolves a module specifier and returns a "strategy" for loading the module to ES6 or WASM code.
+ * `Any` lets the manager tell the builtin resolver by its type, which no resolver can fake.
+ */
+pub trait ModuleResolver: Any + Send + Sync {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData>;
+    fn get_protocol(&self) -> String;
+    /**
+     * What this resolver is called in descriptions, traces and metrics, and on the modules
+     * it resolves. Its type's name unless it says otherwise, say to tell apart two disk
+     * resolvers with different roots.
+     */
+    fn name(&self) -> String {
+        short_type_name::<Self>()
+    }
+    /**
+     * Whether this resolver wants a bare specifier (e.g. "path") that would otherwise be
+     * joined to the referer and handed to the referer's protocol.
+     */
+    fn handles_bare_specifier(&self, _specifier: &str) -> bool {
+        false
+    }
+    /**
+     * What this resolver is and how it's configured, for diagnostics.
+     */
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("custom", &self.get_protocol()).named(&self.name())
+    }
+    /**
+     * Whether this resolver's modules are already in their final form, like a dev server's,
+     * so the manager mustn't cache them and the runtime mustn't transpile them.
+     */
+    fn is_passthrough(&self) -> bool {
+        false
+    }
+    /**
+     * Where this resolver goes among those for its protocol: higher priorities are tried
+     * first, and resolvers of equal priority in the order they were registered. Managers
+     * can override it per resolver with `add_resolver`.
+     */
+    fn priority(&self) -> i32 {
+        0
+    }
+    /**
+     * What this resolver's own cache holds for `origin_url`, one of the modules it resolved,
+     * found without loading or fetching anything. `None` if it keeps no cache or doesn't
+     * have it.
+     */
+    fn cache_state(&self, _origin_url: &str, _tenant: &str) -> Option<ModuleCacheState> {
+        None
+    }
+    /**
+     * Whether managers handed to child runtimes keep this resolver. Resolvers bound to
+     * their own runtime, like the ones guests register, don't carry over.
+     */
+    fn inheritable(&self) -> bool {
+        true
+    }
+    /**
+     * Whether `resolve_module` has to run on the thread of the runtime resolving, like the
+     * resolvers calling back into guest code. Every other resolver runs on the blocking pool,
+     * so checking the disk or the network never stalls a runtime's event loop.
+     */
+    fn needs_runtime_thread(&self) -> bool {
+        false
+    }
+    /**
+     * Whether this resolver goes over the network, so its failed attempts are worth
+     * retrying as the manager's `RetryPolicy` says.
+     */
+    fn is_remote(&self) -> bool {
+        false
+    }
+}
+
+pub type LoadedModuleFuture = Box<Future<Item = LoadedModule, Error = FlyError> + Send>;
+
+/**
+ * This trait is a used as the "front door" of the dynamic module resolution system.
+ */
+pub trait ModuleResolverManager: Send + Sync {
+    /**
+     * Resolves and loads `specifier`. Nothing blocks the calling thread: resolvers run when
+     * the future is polled, on the polling thread, and sources load on the blocking pool.
+     */
+    fn resolve_module(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> LoadedModuleFuture;
+    /**
+     * `resolve_module` for callers that can't wait on a future, like V8's import callback.
+     * Blocks until the module is loaded.
+     */
+    fn resolve_blocking(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<LoadedModule> {
+        self.resolve_module(specifier, referer_info).wait()
+    }
+    /**
+     * `resolve_module` for an import that asked more of its module than where it is, like
+     * `with { type: "json" }`. Fails with `ModuleTypeMismatch` if the module isn't of the
+     * type asserted, see `ImportOptions::apply`.
+     */
+    fn resolve_import(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+        options: ImportOptions,
+    ) -> LoadedModuleFuture {
+        Box::new(
+            self.resolve_module(specifier, referer_info)
+                .and_then(move |module| options.apply(module)),
+        )
+    }
+    /**
+     * Loads a module a runtime starts from, which no other module imports. `specifier` is a
+     * url, an absolute path on disk, or a path relative to this manager's working directory,
+     * `./` or not. The loaded module's origin url is then the referer of its own imports.
+     */
+    fn load_entry_point(&self, specifier: String) -> LoadedModuleFuture {
+        self.resolve_module(specifier, None)
+    }
+    /**
+     * `load_entry_point`, blocking until the module is loaded.
+     */
+    fn resolve_entry_point(&self, specifier: String) -> FlyResult<LoadedModule> {
+        self.load_entry_point(specifier).wait()
+    }
+    #[deprecated(note = "renamed to `resolve_module`; this blocks like `resolve_blocking`")]
+    fn resovle_module(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<LoadedModule> {
+        self.resolve_blocking(specifier, referer_info)
+    }
+    /**
+     * Resolves `specifier` as `resolve_module` would without loading it, for callers that
+     * only want to know where it is, or will stream it with `load_reader`. Blocks until a
+     * resolver found it.
+     */
+    fn resolve(
+        &self,
+        _specifier: String,
+        _referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        Err(FlyError::from(
+            "This module resolver manager cannot resolve modules without loading them".to_string(),
+        ))
+    }
+    /**
+     * A dry run of `resolve_module`: where `specifier` resolves to and what claimed it,
+     * without loading, reading or transpiling any source. Blocks until a resolver found it.
+     */
+    fn resolve_only(
+        &self,
+        _specifier: String,
+        _referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ResolvedModuleInfo> {
+        Err(FlyError::from(
+            "This module resolver manager cannot resolve modules without loading them".to_string(),
+        ))
+    }
+    /**
+     * Loads a module `resolve` found. Unlike one `resolve_module` loads, it's neither cached
+     * nor recorded in the module graph.
+     */
+    fn load(&self, module: &ModuleSourceData) -> FlyResult<LoadedSourceCode> {
+        module.source_loader.load_source()
+    }
+    /**
+     * `resolve`, recording every step it takes, if this manager can trace resolutions. See
+     * `debug_resolve`.
+     */
+    fn trace_resolution(
+        &self,
+        _specifier: String,
+        _referer_info: Option<RefererInfo>,
+    ) -> Option<(FlyResult<ModuleSourceData>, ResolutionTrace)> {
+        None
+    }
+    /**
+     * Timings of every module's first load, if this manager records them.
+     */
+    fn startup_report(&self) -> Option<StartupReport> {
+        None
+    }
+
+    /**
+     * The modules loaded so far and the imports between them, if this manager tracks them.
+     */
+    fn loaded_modules(&self) -> Option<ModuleGraphSnapshot> {
+        None
+    }
+
+    /**
+     * The resolvers, policy and caches this manager was set up with, if it can say.
+     */
+    fn describe(&self) -> Option<ManagerDescription> {
+        None
+    }
+
+    /**
+     * Hands out a manager for a child runtime that serves the same modules under the same
+     * policy, optionally narrowed further. Managers that can't be shared refuse.
+     */
+    fn inherit_for_child(
+        &self,
+        _policy: Option<ResolverPolicy>,
+    ) -> FlyResult<Box<ModuleResolverManager>> {
+        Err(FlyError::from(
+            "This module resolver manager cannot be inherited by child runtimes".to_string(),
+        ))
+    }
+
+    /**
+     * Resolves `specifier` as `resolve_module` would, but only reports whether the module is
+     * already cached. Never loads it, so never goes to the network.
+     */
+    fn cache_state(
+        &self,
+        _specifier: String,
+        _referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleCacheState> {
+        Err(FlyError::from(
+            "This module resolver manager cannot report what it has cached".to_string(),
+        ))
+    }
+
+    /**
+     * Loads `entry` and every module it imports statically, transitively, before the runtime
+     * asks for any of them, so its imports are all served from this manager's cache.
+     */
+    fn preload(&self, _entry: &str) -> FlyResult<PreloadSummary> {
+        Err(FlyError::from(
+            "This module resolver manager cannot preload modules".to_string(),
+        ))
+    }
+
+    /**
+     * How long each top-level load has for everything it resolves, see
+     * `ResolverOptions::total_deadline`. `None` if this manager doesn't bound them.
+     */
+    fn total_deadline(&self) -> Option<Duration> {
+        None
+    }
+
+    /**
+     * Adds a resolver once the runtime is running, by its priority and behind the ones
+     * already registered for its protocol with the same one. Managers inherited by child
+     * runtimes refuse, so a child can't reach further than its parent.
+     */
+    fn register_resolver(&mut self, _resolver: Box<ModuleResolver>) -> FlyResult<()> {
+        Err(FlyError::from(
+            "This module resolver manager cannot register resolvers".to_string(),
+        ))
+    }
+
+    /**
+     * Restricts what this manager resolves from now on to what `policy` allows, which must be
+     * no looser than what it allows already.
+     */
+    fn tighten_policy(&self, _policy: ResolverPolicy) -> FlyResult<()> {
+        Err(FlyError::from(
+            "This module resolver manager cannot change its policy".to_string(),
+        ))
+    }
+
+    /**
+     * Forgets the cached module at `origin_url`, or every cached module, so the next import
+     * loads it afresh. Returns how many entries were evicted.
+     */
+    fn clear_module_cache(&self, _origin_url: Option<&str>) -> FlyResult<usize> {
+        Err(FlyError::from(
+            "This module resolver manager cannot clear its module cache".to_string(),
+        ))
+    }
+
+    /**
+     * Hands back the code cache V8 made compiling `source`, the module at `origin_url`, for
+     * later resolutions of it to come with. Managers that keep no code caches drop it.
+     */
+    fn store_code_cache(&self, _origin_url: &str, _source: &str, _code_cache: Vec<u8>) {}
+
+    /**
+     * Hands back what V8 serialized compiling `wasm`, the binary of the module at
+     * `origin_url`, for later resolutions of any module with that binary to come with.
+     * Managers that keep no compiled wasm drop it.
+     */
+    fn store_compiled_wasm(&self, _origin_url: &str, _wasm: &[u8], _compiled: &[u8]) {}
+}
+
+/**
+ * Refuses `resolver` if it would serve `fly:` modules without being the builtin resolver, so
+ * nothing on disk or the network can pass for a builtin. Goes by its type rather than what
+ * it describes itself as, which wrappers forward and any resolver can claim.
+ */
+fn check_builtin_protocol(resolver: &ModuleResolver) -> FlyResult<()> {
+    if resolver.get_protocol() != BUILTIN_PROTOCOL {
+        return Ok(());
+    }
+    if resolver.type_id() == TypeId::of::<BuiltinModuleResolver>() {
+        return Ok(());
+    }
+    Err(FlyError::from(format!(
+        "Cannot register a {} resolver for protocol {}: only the builtin resolver serves {}: modules",
+        resolver.describe().kind,
+        BUILTIN_PROTOCOL,
+        BUILTIN_PROTOCOL
+    )))
+}
+
+/**
+ * A bare specifier is neither a URL nor a relative or absolute path.
+ */
+fn is_bare_specifier(specifier: &str) -> bool {
+    if specifier.starts_with("./") || specifier.starts_with("../") || specifier.starts_with('/') {
+        return false;
+    }
+    match url::Url::parse(specifier) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => true,
+        _ => false,
+    }
+}
+
+/**
+ * Extensions of modules V8 parses as scripts; extensionless urls count too.
+ */
+const SCRIPT_EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "jsx", "ts", "tsx"];
+
+/**
+ * `source` without a leading `#!` line, which V8 only accepts in its own shell. The line
+ * break is kept so every line, and so every source map mapping and error position, stays
+ * where it was.
+ */
+fn strip_shebang(origin_url: &str, source: LoadedSourceCode) -> LoadedSourceCode {
+    if source.is_wasm() || !source.source.starts_with("#!") {
+        return source;
+    }
+    let path = origin_url.split(|c| c == '?' || c == '#').next().unwrap();
+    let file_name = path.rsplit('/').next().unwrap();
+    let is_script = match file_name.rfind('.') {
+        Some(dot) => SCRIPT_EXTENSIONS.contains(&&file_name[dot + 1..]),
+        None => true,
+    };
+    if !is_script {
+        return source;
+    }
+    let line_end = source
+        .source
+        .find(|c| c == '\n' || c == '\r')
+        .unwrap_or_else(|| source.source.len());
+    debug!("stripping shebang from {}", origin_url);
+    LoadedSourceCode {
+        source: source.source[line_end..].into(),
+        ..source
+    }
+}
+
+/** `source` compiled to JavaScript, through `compile_cache` when there's one. */
+fn transpile_module(
+    compile_cache: &Option<SqliteModuleCache>,
+    origin_url: &str,
+    source: LoadedSourceCode,
+) -> FlyResult<LoadedSourceCode> {
+    match compile_cache {
+        Some(cache) => cache.transpile(origin_url, source),
+        None => transpile_source(origin_url, source),
+    }
+}
+
+/**
+ * Whether `s` is an absolute path on this OS rather than a url: `/srv/app` anywhere, or
+ * `C:\app` on Windows. `//host/path` is a url without its scheme.
+ */
+fn is_absolute_path(s: &str) -> bool {
+    if cfg!(windows) {
+        std::path::Path::new(s).is_absolute()
+    } else {
+        s.starts_with('/') && !s.starts_with("//")
+    }
+}
+
+/**
+ * The `file` url of `path`, relative to the current directory unless it's absolute. A
+ * trailing separator is kept, so directories stay directories.
+ */
+fn file_path_url(path: &str) -> FlyResult<url::Url> {
+    let invalid = || FlyError::from(format!("{} is not a valid file path", path));
+    if !is_absolute_path(path) {
+        let cwd = url::Url::from_directory_path(std::env::current_dir()?).map_err(|_| invalid())?;
+        return Ok(cwd.join(path)?);
+    }
+    let is_dir = path.ends_with('/') || (cfg!(windows) && path.ends_with('\\'));
+    let url = if is_dir {
+        url::Url::from_directory_path(path)
+    } else {
+        url::Url::from_file_path(path)
+    };
+    // reparsed so `..` segments are resolved the way they are in any other url
+    Ok(url::Url::parse(url.map_err(|_| invalid())?.as_str())?)
+}
+
+/** The longest path a local module can have, `PATH_MAX` on Linux. */
+pub const MAX_LOCAL_PATH_BYTES: usize = 4096;
+
+/**
+ * Refuses `path` if it isn't UTF-8, which origin urls and source maps can't name faithfully,
+ * or is longer than any filesystem allows. The error shows it lossily, cut short if huge.
+ */
+fn check_local_path(path: &std::path::Path) -> FlyResult<()> {
+    let len = path.as_os_str().len();
+    if len > MAX_LOCAL_PATH_BYTES {
+        let shown: String = path.to_string_lossy().chars().take(80).collect();
+        return Err(new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{}... is {} bytes long, more than the {} a local module path can be",
+                shown, len, MAX_LOCAL_PATH_BYTES
+            ),
+        ));
+    }
+    match path.to_str() {
+        Some(_) => Ok(()),
+        None => Err(new(
+            ErrorKind::InvalidInput,
+            format!("{} is not valid UTF-8", path.to_string_lossy()),
+        )),
+    }
+}
+
+/**
+ * The origin url of a module read from `path`, which must be absolute. Spaces and non-ASCII
+ * characters are percent-encoded and Windows paths become `file:///C:/...`, so the url
+ * parses back to the same path when the module is a referer.
+ */
+fn file_origin_url(path: &std::path::Path) -> FlyResult<String> {
+    url::Url::from_file_path(path)
+        .map(|url| url.into_string())
+        .map_err(|_| FlyError::from(format!("{} is not an absolute path", path.display())))
+}
+
+fn working_url(working_url_str: &str) -> FlyResult<url::Url> {
+    if is_absolute_path(working_url_str) {
+        return file_path_url(working_url_str);
+    }
+    match url::Url::parse(working_url_str) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => file_path_url(working_url_str),
+        result => Ok(result?),
+    }
+}
+
+/**
+ * Parse url or join it to the working url if it's relative. Either may also be a path on
+ * disk, like `/srv/app/main.ts` or `./main.ts`, which is taken as a `file` url; relative
+ * working paths are relative to the current directory. A protocol-relative `//host/path`
+ * keeps the working url's scheme. Fails with an `InvalidSpecifier` resolution error, also
+ * for a blank specifier, which would otherwise resolve to the working url itself.
+ */
+pub(crate) fn parse_url(url_str: &str, working_url_str: &str) -> FlyResult<url::Url> {
+    join_url(url_str, working_url_str).map_err(|e| {
+        ModuleResolutionError::InvalidSpecifier {
+            specifier: url_str.to_string(),
+            referer: working_url_str.to_string(),
+            reason: e.to_string(),
+        }
+        .into()
+    })
+}
+
+fn join_url(url_str: &str, working_url_str: &str) -> FlyResult<url::Url> {
+    debug!("parse_url {} from {}", &url_str, &working_url_str);
+    if url_str.trim().is_empty() {
+        return Err(FlyError::from(
+            "a module specifier can't be empty".to_string(),
+        ));
+    }
+    // `/lib.js` from an `https` module is a path on its host, not on disk
+    if is_absolute_path(url_str)
+        && working_url(working_url_str)
+            .map(|url| url.scheme() == "file")
+            .unwrap_or(true)
+    {
+        return file_path_url(url_str);
+    }
+    match url::Url::parse(url_str) {
+        Ok(url) => Ok(url),
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            // If the url is relative join it to the working path.
+            trace!("Url relative: {}", url_str);
+            let base = working_url(working_url_str)?;
+            if base.cannot_be_a_base() {
+                return Err(FlyError::from(format!(
+                    "relative imports need a base url, and {}: urls have no base; import by absolute url instead",
+                    base.scheme()
+                )));
+            }
+            Ok(base.join(url_str)?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/**
+ * Which non-script file extensions a disk resolver turns into modules. TOML, JSON (`.json`,
+ * parsed like `JSON.parse` unless `with_json` says otherwise) and GraphQL (`.graphql`, `.gql`)
+ * are on by default; YAML (`.yaml`, `.yml`) and JSON5 (`.json5`) are opt-in, and a disabled
+ * format's files are passed through as they are. `.js` and `.cjs` scripts written as CommonJS
+ * are wrapped into ES modules unless `without_commonjs` says otherwise. Assets, text files like
+ * `.css` and binary ones like `.png` by default, are imported as `assets` says.
+ */
+#[derive(Clone, Debug)]
+pub struct DataFormats {
+    pub toml: bool,
+    pub yaml: Option<YamlLimits>,
+    pub json: Option<JsonOptions>,
+    pub json5: bool,
+    pub graphql: bool,
+    pub commonjs: bool,
+    pub assets: AssetLoaders,
+}
+
+impl Default for DataFormats {
+    fn default() -> Self {
+        Self {
+            toml: true,
+            yaml: None,
+            json: Some(JsonOptions::default()),
+            json5: false,
+            graphql: true,
+            commonjs: true,
+            assets: AssetLoaders::default(),
+        }
+    }
+}
+
+impl DataFormats {
+    pub fn with_yaml(mut self, limits: YamlLimits) -> Self {
+        self.yaml = Some(limits);
+        self
+    }
+
+    pub fn with_json(mut self, options: JsonOptions) -> Self {
+        self.json = Some(options);
+        self
+    }
+
+    pub fn with_json5(mut self) -> Self {
+        self.json5 = true;
+        self
+    }
+
+    pub fn without_json(mut self) -> Self {
+        self.json = None;
+        self
+    }
+
+    pub fn without_toml(mut self) -> Self {
+        self.toml = false;
+        self
+    }
+
+    pub fn without_graphql(mut self) -> Self {
+        self.graphql = false;
+        self
+    }
+
+    pub fn without_commonjs(mut self) -> Self {
+        self.commonjs = false;
+        self
+    }
+
+    pub fn with_assets(mut self, assets: AssetLoaders) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /**
+     * Wraps `loader` in the loader that turns a non-script file into module code, picked by the
+     * extension of `origin_url`. Disabled formats, and scripts other than CommonJS ones, are
+     * passed through untouched.
+     */
+    pub fn loader_for(&self, origin_url: &str, loader: Box<SourceLoader>) -> Box<SourceLoader> {
+        let loader = match self.assets.loader_for(origin_url, loader) {
+            Ok(asset) => return asset,
+            Err(loader) => loader,
+        };
+        // by the extension of the path, whatever the query
+        let path = origin_url.split(|c| c == '?' || c == '#').next().unwrap();
+        if self.toml && path.ends_with(".toml") {
+            Box::new(TomlLoader::new(origin_url, loader))
+        } else if self.graphql && (path.ends_with(".graphql") || path.ends_with(".gql")) {
+            Box::new(GraphqlLoader::new(origin_url, loader))
+        } else if path.ends_with(".json") {
+            match self.json {
+                Some(ref options) => Box::new(JsonLoader::new(origin_url, loader, options.clone())),
+                None => loader,
+            }
+        } else if self.json5 && path.ends_with(".json5") {
+            Box::new(Json5Loader::new(origin_url, loader))
+        } else if path.ends_with(".yaml") || path.ends_with(".yml") {
+            match self.yaml {
+                Some(limits) => Box::new(YamlLoader::new(origin_url, loader, limits)),
+                None => loader,
+            }
+        } else if self.commonjs && (path.ends_with(".js") || path.ends_with(".cjs")) {
+            Box::new(CjsWrappingLoader::new(origin_url, loader))
+        } else {
+            loader
+        }
+    }
+}
+
+impl std::fmt::Display for DataFormats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut formats = vec![];
+        if self.toml {
+            formats.push("toml");
+        }
+        if self.yaml.is_some() {
+            formats.push("yaml");
+        }
+        if self.json.is_some() {
+            formats.push("json");
+        }
+        if self.json5 {
+            formats.push("json5");
+        }
+        if self.graphql {
+            formats.push("graphql");
+        }
+        if self.commonjs {
+            formats.push("commonjs");
+        }
+        formats.extend(self.assets.extensions());
+        if formats.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", formats.join(", "))
+        }
+    }
+}
+
+/**
+ * Identifies a resolver added to a `StandardModuleResolverManager`, to remove it again.
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ResolverId(usize);
+
+#[derive(Clone)]
+struct RegisteredResolver {
+    id: ResolverId,
+    priority: i32,
+    resolver: Arc<ModuleResolver>,
+}
+
+pub struct StandardModuleResolverManager {
+    /** Each protocol's resolvers in the order they're tried, copied on write by clones. */
+    protocol_resolver_map: Arc<HashMap<String, Vec<RegisteredResolver>>>,
+    next_resolver_id: usize,
+    /**
+     * What loads without a referer, like entry points, resolve against: absolute
+     * specifiers as they are, relative ones joined to it. `file://<cwd>/` unless given.
+     */
+    default_working_url: String,
+    virtual_resolver: Option<VirtualModuleResolver>,
+    /** Tightened in place by `tighten_policy`, so it's locked. */
+    policy: RwLock<ResolverPolicy>,
+    shared_cache: Option<(SharedSourceCache, usize)>,
+    profiler: Arc<StartupProfiler>,
+    load_scheduler: Option<LoadScheduler>,
+    base_urls: BaseUrlSubstitutions,
+    wasm_imports: Option<WasmImportMap>,
+    import_map: Option<ImportMap>,
+    recorder: Option<Arc<CassetteRecorder>>,
+    resolution_cache: Option<ResolutionCache>,
+    /** Resolutions running, for the same import made again meanwhile to wait for. */
+    flights: SingleFlight<FlyResult<LoadedModule>>,
+    graph: Arc<ModuleGraph>,
+    integrity: Option<ModuleIntegrity>,
+    lockfile: Option<Arc<Lockfile>>,
+    transpile: bool,
+    compile_cache: Option<SqliteModuleCache>,
+    inline_source_contents: bool,
+    code_cache: Option<CodeCache>,
+    wasm_cache: Option<WasmCompileCache>,
+    observers: Vec<Arc<ResolutionObserver>>,
+    traces: Option<TraceLog>,
+    options: Arc<ResolverOptions>,
+    /** Handed out by `inherit`, so it takes no resolvers beyond the parent's. */
+    inherited: bool,
+}
+
+impl StandardModuleResolverManager {
+    pub fn new(resolvers: Vec<Box<ModuleResolver>>, default_working_url: Option<String>) -> Self {
+        Self::with_options(resolvers, default_working_url, ResolverOptions::default())
+    }
+
+    /**
+     * A manager bounding its resolvers' attempts with `options` rather than the default
+     * timeout and retries.
+     */
+    pub fn with_options(
+        resolvers: Vec<Box<ModuleResolver>>,
+        default_working_url: Option<String>,
+        options: ResolverOptions,
+    ) -> Self {
+        let default_working_url = match default_working_url {
+            None => url::Url::from_directory_path(
+                std::env::current_dir().expect("invalid current directory"),
+            )
+            .unwrap()
+            .as_str()
+            .to_string(),
+            Some(default_working_url) => default_working_url,
+        };
+        let mut manager = Self {
+            protocol_resolver_map: Arc::new(HashMap::new()),
+            next_resolver_id: 0,
+            default_working_url,
+            virtual_resolver: None,
+            policy: RwLock::new(ResolverPolicy::allow_all()),
+            shared_cache: None,
+            profiler: Arc::new(StartupProfiler::new()),
+            load_scheduler: None,
+            base_urls: BaseUrlSubstitutions::new(),
+            wasm_imports: None,
+            import_map: None,
+            recorder: None,
+            resolution_cache: None,
+            flights: SingleFlight::new(),
+            graph: Arc::new(ModuleGraph::default()),
+            integrity: None,
+            lockfile: None,
+            transpile: false,
+            compile_cache: None,
+            inline_source_contents: false,
+            code_cache: None,
+            wasm_cache: None,
+            observers: vec![],
+            traces: None,
+            options: Arc::new(options),
+            inherited: false,
+        };
+        for resolver in resolvers {
+            if let Err(e) = check_builtin_protocol(&*resolver) {
+                error!("{}", e);
+                continue;
+            }
+            let priority = resolver.priority();
+            manager.insert_resolver(resolver, priority);
+        }
+        manager
+    }
+
+    fn insert_resolver(&mut self, resolver: Box<ModuleResolver>, priority: i32) -> ResolverId {
+        let id = ResolverId(self.next_resolver_id);
+        self.next_resolver_id += 1;
+        // clones resolving with other resolvers mustn't wait on each other
+        self.flights = SingleFlight::new();
+        let resolvers = Arc::make_mut(&mut self.protocol_resolver_map)
+            .entry(resolver.get_protocol())
+            .or_insert_with(Vec::new);
+        // behind every resolver of the same priority, so ties keep registration order
+        let position = resolvers
+            .iter()
+            .position(|r| r.priority < priority)
+            .unwrap_or_else(|| resolvers.len());
+        resolvers.insert(
+            position,
+            RegisteredResolver {
+                id,
+                priority,
+                resolver: Arc::from(resolver),
+            },
+        );
+        id
+    }
+
+    /**
+     * Serve modules registered in `registry` ahead of any protocol resolver.
+     */
+    pub fn with_virtual_modules(mut self, registry: VirtualModuleRegistry) -> Self {
+        self.virtual_resolver = Some(VirtualModuleResolver::new(registry));
+        self
+    }
+
+    /**
+     * Consult `cache` before loading any module source, and fill it with whatever gets
+     * loaded. Virtual modules are per-runtime and never go through it.
+     */
+    pub fn with_shared_cache(mut self, cache: SharedSourceCache) -> Self {
+        let consumer = cache.register_consumer();
+        self.shared_cache = Some((cache, consumer));
+        self
+    }
+
+    /**
+     * Reloads `origin_url` from its resolver and updates the shared cache only if the
+     * content changed. Returns whether it did.
+     */
+    pub fn invalidate_shared_source(&self, origin_url: &str) -> FlyResult<bool> {
+        let (cache, consumer) = match self.shared_cache {
+            Some((ref cache, consumer)) => (cache, consumer),
+            None => return Ok(false),
+        };
+        let url = url::Url::parse(origin_url)?;
+        let resolvers = match self.protocol_resolver_map.get(url.scheme()) {
+            Some(v) => v,
+            None => {
+                cache.remove(origin_url);
+                return Ok(true);
+            }
+        };
+        for registered in resolvers {
+            if let Ok(module_loader) = registered.resolver.resolve_module(origin_url, None) {
+                let mut fresh =
+                    strip_shebang(origin_url, module_loader.source_loader.load_source()?);
+                if self.transpile && !registered.resolver.is_passthrough() {
+                    fresh = transpile_module(&self.compile_cache, origin_url, fresh)?;
+                }
+                return Ok(cache.refresh(origin_url, fresh, consumer));
+            }
+        }
+        cache.remove(origin_url);
+        Ok(true)
+    }
+
+    /**
+     * Evicts `origin_url` from the resolution cache and refreshes it in the shared cache, or
+     * clears the whole resolution cache. The shared cache isn't cleared wholesale: other
+     * runtimes are using it, and refreshing an origin already serves them what changed.
+     * Returns how many entries were evicted or refreshed.
+     */
+    pub fn clear_module_cache(&self, origin_url: Option<&str>) -> FlyResult<usize> {
+        let origin_url = match origin_url {
+            Some(origin_url) => origin_url,
+            None => {
+                return Ok(self
+                    .resolution_cache
+                    .as_ref()
+                    .map_or(0, |cache| cache.clear()))
+            }
+        };
+        let mut evicted = self
+            .resolution_cache
+            .as_ref()
+            .map_or(0, |cache| cache.evict(origin_url));
+        let shared = match self.shared_cache {
+            Some((ref cache, _)) => cache.peek(origin_url).is_some(),
+            None => false,
+        };
+        if shared && self.invalidate_shared_source(origin_url)? {
+            evicted += 1;
+        }
+        Ok(evicted)
+    }
+
+    /**
+     * Queue source loads through `scheduler`, shared with other runtimes' managers, so
+     * tenants (the referer's app id) take turns for load slots.
+     */
+    pub fn with_load_scheduler(mut self, scheduler: LoadScheduler) -> Self {
+        self.load_scheduler = Some(scheduler);
+        self
+    }
+
+    /**
+     * Rewrite specifiers using a placeholder origin to this environment's base url before
+     * resolving them.
+     */
+    pub fn with_base_urls(mut self, base_urls: BaseUrlSubstitutions) -> Self {
+        self.base_urls = base_urls;
+        self
+    }
+
+    /**
+     * Map the import names of wasm modules through `imports` before resolving them; a wasm
+     * module importing an unmapped name fails to resolve.
+     */
+    pub fn with_wasm_imports(mut self, imports: WasmImportMap) -> Self {
+        self.wasm_imports = Some(imports);
+        self
+    }
+
+    /**
+     * Remap specifiers through `import_map` before anything else resolves them. Specifiers
+     * it doesn't cover resolve as usual.
+     */
+    pub fn with_import_map(mut self, import_map: ImportMap) -> Self {
+        self.import_map = Some(import_map);
+        self
+    }
+
+    /**
+     * Record every successful resolution, as asked for, into `recorder`'s cassette.
+     */
+    pub fn with_cassette_recorder(mut self, recorder: Arc<CassetteRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /**
+     * Refuse modules, and module graphs, bigger than `limits` rather than the defaults.
+     */
+    pub fn with_limits(mut self, limits: ModuleLimits) -> Self {
+        Arc::make_mut(&mut self.options).limits = limits;
+        self
+    }
+
+    /**
+     * Serve repeated imports of a module from `cache` instead of resolving and loading it
+     * again. Virtual modules, which can be replaced at any time, never go through it.
+     */
+    pub fn with_resolution_cache(mut self, cache: ResolutionCache) -> Self {
+        self.resolution_cache = Some(cache);
+        self
+    }
+
+    /**
+     * The cache set with `with_resolution_cache`, to invalidate what changed on disk.
+     */
+    pub fn resolution_cache(&self) -> Option<&ResolutionCache> {
+        self.resolution_cache.as_ref()
+    }
+
+    /**
+     * Check every module loaded against `integrity` before it's used. The shared source
+     * cache is bypassed meanwhile, since what it holds was loaded without the check. Virtual
+     * modules, which the host registers itself, aren't checked.
+     */
+    pub fn with_integrity(mut self, integrity: ModuleIntegrity) -> Self {
+        self.integrity = Some(integrity);
+        self
+    }
+
+    /**
+     * Record every resolution into `lockfile`, or fail those it doesn't have, depending on its
+     * mode. Dynamic imports are checked like the rest; virtual modules aren't. Modules are
+     * hashed as they're run, so after any transpiling: a lockfile holds for deploys
+     * configured alike.
+     */
+    pub fn with_lockfile(mut self, lockfile: Arc<Lockfile>) -> Self {
+        self.lockfile = Some(lockfile);
+        self
+    }
+
+    /**
+     * Compile `.ts` and `.tsx` modules to JavaScript as they're loaded, after any integrity
+     * check, so pins hash the TypeScript as written. Passthrough resolvers' modules are left
+     * alone. Off by default; deployments shipping prebuilt JavaScript keep it off. Builds
+     * without the `transpile` feature leave TypeScript as it is either way.
+     */
+    pub fn with_transpiling(mut self, transpile: bool) -> Self {
+        self.transpile = transpile;
+        self
+    }
+
+    /**
+     * Keep what `with_transpiling` compiles in `cache`, and compile only what it doesn't
+     * have, so restarts skip compiling modules that haven't changed.
+     */
+    pub fn with_compile_cache(mut self, cache: SqliteModuleCache) -> Self {
+        self.compile_cache = Some(cache);
+        self
+    }
+
+    /**
+     * Fill in what's missing from each module's source map `sourcesContent` with the
+     * sources it names, loaded through this manager's resolvers as its policy allows, so
+     * error overlays can show the original code. Its `sources` are made absolute whether or
+     * not. Off by default: it loads each original source again for every map naming it.
+     */
+    pub fn with_inlined_source_contents(mut self, inline: bool) -> Self {
+        self.inline_source_contents = inline;
+        self
+    }
+
+    /**
+     * Keeps the code caches runtimes hand back with `store_code_cache` in `cache`, and hands
+     * each out with the module it's for, so runtimes after the first skip compiling it.
+     */
+    pub fn with_code_cache(mut self, cache: CodeCache) -> Self {
+        self.code_cache = Some(cache);
+        self
+    }
+
+    pub fn code_cache(&self) -> Option<&CodeCache> {
+        self.code_cache.as_ref()
+    }
+
+    /**
+     * Keeps the compiled wasm runtimes hand back with `store_compiled_wasm` in `cache`, and
+     * hands each out with the wasm module it was compiled from, so runtimes booting after
+     * the first instantiate it without compiling.
+     */
+    pub fn with_wasm_cache(mut self, cache: WasmCompileCache) -> Self {
+        self.wasm_cache = Some(cache);
+        self
+    }
+
+    pub fn wasm_cache(&self) -> Option<&WasmCompileCache> {
+        self.wasm_cache.as_ref()
+    }
+
+    /**
+     * Tell `observer` about every resolver tried from now on, and every resolution cache
+     * hit. Observers are called in the order they were added; without any, nothing is
+     * timed or described.
+     */
+    pub fn with_observer(mut self, observer: Arc<ResolutionObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /**
+     * Record every step of each resolution from now on: rewrites, the protocol picked, each
+     * resolver tried and what it probed. `traces` has the latest `TRACE_CAPACITY` of them.
+     * Off by default, since recording allocates on every probe.
+     */
+    pub fn with_tracing(mut self, tracing: bool) -> Self {
+        self.traces = if tracing {
+            Some(TraceLog::default())
+        } else {
+            None
+        };
+        self
+    }
+
+    /** The latest resolutions traced, oldest first; empty unless `with_tracing` is on. */
+    pub fn traces(&self) -> Vec<ResolutionTrace> {
+        self.traces
+            .as_ref()
+            .map(|traces| traces.traces())
+            .unwrap_or_default()
+    }
+
+    fn observe_cache_hit(&self, specifier: &str, origin_url: &str, duration: Duration) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let protocol = origin_url.split(':').next().unwrap_or("");
+        let start = ResolveStart {
+            specifier,
+            protocol,
+            resolver: "resolution_cache",
+            name: "resolution_cache",
+        };
+        let end = ResolveEnd {
+            specifier,
+            protocol,
+            resolver: "resolution_cache",
+            name: "resolution_cache",
+            duration,
+            outcome: ResolutionOutcome::CacheHit,
+        };
+        for observer in &self.observers {
+            observer.on_resolve_start(&start);
+            observer.on_resolve_end(&end);
+        }
+    }
+
+    /**
+     * The modules checked so far and what the checks found, when `with_integrity` was set.
+     */
+    pub fn integrity_report(&self) -> Option<IntegrityReport> {
+        self.integrity.as_ref().map(|integrity| integrity.report())
+    }
+
+    /**
+     * Which module imported which, for every import resolved so far, by origin url.
+     */
+    pub fn get_graph(&self) -> &ModuleGraph {
+        &self.graph
+    }
+
+    pub fn importers_of(&self, origin_url: &str) -> Vec<String> {
+        self.graph.importers_of(origin_url)
+    }
+
+    pub fn base_urls(&self) -> &BaseUrlSubstitutions {
+        &self.base_urls
+    }
+
+    pub fn policy(&self) -> ResolverPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    /**
+     * Restricts this manager to `policy` from now on, say once an app has booted, without
+     * rebuilding it. Like `narrow`, `policy` must be at least as strict as the current one.
+     * Resolvers it no longer allows stay registered, but nothing reaches them.
+     */
+    pub fn tighten_policy(&self, policy: ResolverPolicy) -> FlyResult<()> {
+        let mut current = self.policy.write().unwrap();
+        *current = current.narrow(&policy)?;
+        Ok(())
+    }
+
+    /**
+     * Adds a resolver after construction at its own `priority()`. Fails if the manager's
+     * policy doesn't allow the resolver's protocol, or if the manager was inherited.
+     */
+    pub fn register_resolver(&mut self, resolver: Box<ModuleResolver>) -> FlyResult<()> {
+        let priority = resolver.priority();
+        self.add_resolver(resolver, priority).map(|_| ())
+    }
+
+    /**
+     * Adds a resolver at `priority`, overriding its own: it's tried before every resolver
+     * for its protocol with a lower priority, and after those with the same or a higher one.
+     * Say, a mock at priority 1 in front of the disk resolver's default 0.
+     */
+    pub fn add_resolver(
+        &mut self,
+        resolver: Box<ModuleResolver>,
+        priority: i32,
+    ) -> FlyResult<ResolverId> {
+        let protocol = resolver.get_protocol();
+        // even for a protocol the parent serves, a new resolver could reach further than the
+        // parent's, say a disk resolver allowed outside its root
+        if self.inherited {
+            return Err(FlyError::from(format!(
+                "Cannot register a resolver for protocol {}: the manager was inherited from a parent runtime",
+                protocol
+            )));
+        }
+        let policy = self.policy();
+        if !policy.permits(&protocol) {
+            return Err(FlyError::from(format!(
+                "Cannot register a resolver for protocol {}: not allowed by policy {}",
+                protocol, policy
+            )));
+        }
+        check_builtin_protocol(&*resolver)?;
+        Ok(self.insert_resolver(resolver, priority))
+    }
+
+    /**
+     * Removes a resolver added with `add_resolver`. Returns whether it was still there.
+     */
+    pub fn remove_resolver(&mut self, id: ResolverId) -> bool {
+        let mut removed = false;
+        self.flights = SingleFlight::new();
+        let protocol_resolver_map = Arc::make_mut(&mut self.protocol_resolver_map);
+        for resolvers in protocol_resolver_map.values_mut() {
+            let before = resolvers.len();
+            resolvers.retain(|r| r.id != id);
+            removed |= resolvers.len() != before;
+        }
+        protocol_resolver_map.retain(|_, resolvers| !resolvers.is_empty());
+        removed
+    }
+
+    /**
+     * A manager sharing this one's resolvers and virtual modules. An allow-all policy is
+     * pinned to the protocols served right now, so the child can never reach further than
+     * the parent did when it was spawned.
+     */
+    pub fn inherit(&self) -> Self {
+        let protocol_resolver_map: HashMap<String, Vec<RegisteredResolver>> = self
+            .protocol_resolver_map
+            .iter()
+            .map(|(protocol, resolvers)| {
+                let resolvers: Vec<_> = resolvers
+                    .iter()
+                    .filter(|r| r.resolver.inheritable())
+                    .cloned()
+                    .collect();
+                (protocol.clone(), resolvers)
+            })
+            .filter(|(_, resolvers)| !resolvers.is_empty())
+            .collect();
+        let policy = self.policy();
+        let policy = if policy == ResolverPolicy::allow_all() {
+            ResolverPolicy::allow_protocols(protocol_resolver_map.keys().cloned())
+        } else {
+            policy
+        };
+        Self {
+            protocol_resolver_map: Arc::new(protocol_resolver_map),
+            next_resolver_id: self.next_resolver_id,
+            default_working_url: self.default_working_url.clone(),
+            virtual_resolver: self.virtual_resolver.clone(),
+            policy: RwLock::new(policy),
+            shared_cache: self
+                .shared_cache
+                .as_ref()
+                .map(|(cache, _)| (cache.clone(), cache.register_consumer())),
+            profiler: Arc::new(StartupProfiler::new()),
+            load_scheduler: self.load_scheduler.clone(),
+            base_urls: self.base_urls.clone(),
+            wasm_imports: self.wasm_imports.clone(),
+            import_map: self.import_map.clone(),
+            recorder: self.recorder.clone(),
+            // what the parent resolved may be beyond a narrower child policy
+            resolution_cache: None,
+            flights: SingleFlight::new(),
+            graph: Arc::new(ModuleGraph::default()),
+            integrity: self.integrity.clone(),
+            lockfile: self.lockfile.clone(),
+            transpile: self.transpile,
+            compile_cache: self.compile_cache.clone(),
+            inline_source_contents: self.inline_source_contents,
+            code_cache: self.code_cache.clone(),
+            wasm_cache: self.wasm_cache.clone(),
+            observers: self.observers.clone(),
+            traces: self.traces.clone(),
+            options: self.options.clone(),
+            inherited: true,
+        }
+    }
+
+    /**
+     * Restricts this manager to `policy`, dropping resolvers it no longer allows. `policy`
+     * must be at least as strict as the current one.
+     */
+    pub fn narrow(mut self, policy: ResolverPolicy) -> FlyResult<Self> {
+        let policy = self.policy().narrow(&policy)?;
+        self.flights = SingleFlight::new();
+        Arc::make_mut(&mut self.protocol_resolver_map)
+            .retain(|protocol, _| policy.permits(protocol));
+        self.policy = RwLock::new(policy);
+        Ok(self)
+    }
+}
+
+/**
+ * Another handle on the same resolvers and caches, cheap enough to give every runtime its own
+ * so one resolver stack can back many isolates. Resolvers are shared until a clone adds or
+ * removes one, which only that clone sees; the resolution, shared source, compile and code
+ * caches stay shared. What each runtime records of its own loads starts empty: its module
+ * graph, startup profile, and policy tightening.
+ */
+impl Clone for StandardModuleResolverManager {
+    fn clone(&self) -> Self {
+        Self {
+            protocol_resolver_map: self.protocol_resolver_map.clone(),
+            next_resolver_id: self.next_resolver_id,
+            default_working_url: self.default_working_url.clone(),
+            virtual_resolver: self.virtual_resolver.clone(),
+            policy: RwLock::new(self.policy()),
+            shared_cache: self
+                .shared_cache
+                .as_ref()
+                .map(|(cache, _)| (cache.clone(), cache.register_consumer())),
+            profiler: Arc::new(StartupProfiler::new()),
+            load_scheduler: self.load_scheduler.clone(),
+            base_urls: self.base_urls.clone(),
+            wasm_imports: self.wasm_imports.clone(),
+            import_map: self.import_map.clone(),
+            recorder: self.recorder.clone(),
+            resolution_cache: self.resolution_cache.clone(),
+            flights: self.flights.clone(),
+            graph: Arc::new(ModuleGraph::default()),
+            integrity: self.integrity.clone(),
+            lockfile: self.lockfile.clone(),
+            transpile: self.transpile,
+            compile_cache: self.compile_cache.clone(),
+            inline_source_contents: self.inline_source_contents,
+            code_cache: self.code_cache.clone(),
+            wasm_cache: self.wasm_cache.clone(),
+            observers: self.observers.clone(),
+            traces: self.traces.clone(),
+            options: self.options.clone(),
+            inherited: self.inherited,
+        }
+    }
+}
+
+impl StandardModuleResolverManager {
+    fn prepare(
+        &self,
+        specifier: String,
+        referer_info: &Option<RefererInfo>,
+    ) -> FlyResult<Prepared> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url.clone(),
+            None => self.default_working_url.clone(),
+        };
+        let specifier = match (&self.wasm_imports, referer_info) {
+            (
+                Some(ref imports),
+                Some(RefererInfo {
+                    is_wasm: Some(true),
+                    ref source_code,
+                    ..
+                }),
+            ) => {
+                // a wasm referer's source is base64, like its `LoadedSourceCode`
+                let wasm = source_code
+                    .as_ref()
+                    .and_then(|s| base64::decode(s.as_bytes()).ok());
+                let mapped = imports.map_import(
+                    &specifier,
+                    &referer_origin_url,
+                    wasm.as_ref().map(|b| &b[..]),
+                )?;
+                if mapped != specifier {
+                    record_step(|| TraceStep::Rewritten {
+                        specifier: mapped.clone(),
+                    });
+                }
+                mapped
+            }
+            _ => specifier,
+        };
+        let specifier = match self.import_map {
+            // builtins are never remapped, see `check_builtin_protocol`
+            Some(_) if specifier.starts_with("fly:") => specifier,
+            Some(ref import_map) => match import_map.resolve(&specifier, &referer_origin_url)? {
+                Some(mapped) => {
+                    debug!("import map mapped {} to {}", specifier, mapped);
+                    record_step(|| TraceStep::Rewritten {
+                        specifier: mapped.clone(),
+                    });
+                    mapped
+                }
+                None => specifier,
+            },
+            None => specifier,
+        };
+        // Virtual modules are matched on the exact specifier, whatever its scheme.
+        if let Some(virtual_resolver) = &self.virtual_resolver {
+            if virtual_resolver.registry().contains(&specifier) {
+                let module_loader =
+                    virtual_resolver.resolve_module(specifier.as_str(), referer_info.clone())?;
+                record_step(|| TraceStep::Virtual);
+                return Ok(Prepared::Located(Located::Virtual(module_loader)));
+            }
+        }
+
+        let specifier = match self.base_urls.rewrite(&specifier) {
+            Some(rewritten) => {
+                debug!("rewrote {} to {}", specifier, rewritten);
+                record_step(|| TraceStep::Rewritten {
+                    specifier: rewritten.clone(),
+                });
+                rewritten
+            }
+            None => specifier,
+        };
+
+        let tenant = match referer_info {
+            Some(RefererInfo {
+                app_id: Some(ref app_id),
+                ..
+            }) => app_id.clone(),
+            _ => DEFAULT_TENANT.to_string(),
+        };
+        // resolvers load relative specifiers without a referer against the working url too,
+        // not against defaults of their own
+        let referer_info = match referer_info {
+            Some(info) => Some(info.clone()),
+            None => Some(RefererInfo {
+                origin_url: self.default_working_url.clone(),
+                is_wasm: Some(false),
+                source_code: None,
+                identifier_hash: None,
+                line: None,
+                column: None,
+                app_id: None,
+                deadline: None,
+            }),
+        };
+        let resolve_started = Instant::now();
+        let policy = self.policy.read().unwrap();
+        if is_bare_specifier(&specifier) {
+            // the highest priority claim wins, ties going to the first protocol by name
+            let mut protocols: Vec<_> = self.protocol_resolver_map.iter().collect();
+            protocols.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let claimed = protocols
+                .into_iter()
+                .flat_map(|(_, resolvers)| resolvers.iter())
+                .filter(|registered| {
+                    policy.permits(&registered.resolver.get_protocol())
+                        && registered.resolver.handles_bare_specifier(&specifier)
+                })
+                .min_by_key(|registered| std::cmp::Reverse(registered.priority));
+            if let Some(RegisteredResolver { resolver, .. }) = claimed {
+                return Ok(Prepared::Candidates(Candidates {
+                    specifier,
+                    referer_info,
+                    referer_origin_url,
+                    resolvers: vec![resolver.clone()],
+                    claimed: true,
+                    fallbacks: 0,
+                    tenant,
+                    resolve_started,
+                    options: self.options.clone(),
+                }));
+            }
+        }
+
+        // Parse the specifier with the referer origin_url as the working path/url.
+        info!("resolve_module {} from {}", &specifier, &referer_origin_url);
+
+        let specifier_url = parse_url(specifier.as_str(), referer_origin_url.as_str())?;
+        record_step(|| TraceStep::Parsed {
+            url: specifier_url.to_string(),
+        });
+
+        // before any resolver for it gets to run
+        let denied = if !policy.permits(specifier_url.scheme()) {
+            Some(format!("protocol {}", specifier_url.scheme()))
+        } else if !policy.permits_host(&specifier_url) {
+            Some(format!(
+                "host {} of protocol {}",
+                specifier_url.host_str().unwrap_or(""),
+                specifier_url.scheme()
+            ))
+        } else {
+            None
+        };
+        if let Some(denied) = denied {
+            warn!(
+                "policy denied {} from {}: {}",
+                specifier, &referer_origin_url, denied
+            );
+            return Err(ModuleResolutionError::PolicyDenied {
+                specifier,
+                referer: referer_origin_url,
+                denied,
+                policy: policy.to_string(),
+            }
+            .into());
+        }
+
+        // The resolvers for the protocol we are trying to resolve, then the fallbacks.
+        let fallbacks = self
+            .protocol_resolver_map
+            .get(FALLBACK_PROTOCOL)
+            .map(|v| &v[..])
+            .unwrap_or(&[]);
+        let resolvers: Vec<Arc<ModuleResolver>> = self
+            .protocol_resolver_map
+            .get(specifier_url.scheme())
+            .map(|v| &v[..])
+            .unwrap_or(&[])
+            .iter()
+            .chain(fallbacks)
+            .map(|r| r.resolver.clone())
+            .collect();
+        record_step(|| TraceStep::Protocol {
+            protocol: specifier_url.scheme().to_string(),
+            resolvers: resolvers.len(),
+        });
+        if resolvers.is_empty() {
+            return Err(ModuleResolutionError::NoResolverForProtocol {
+                specifier,
+                referer: referer_origin_url,
+                scheme: specifier_url.scheme().to_string(),
+            }
+            .into());
+        }
+
+        Ok(Prepared::Candidates(Candidates {
+            specifier,
+            referer_info,
+            referer_origin_url,
+            resolvers,
+            claimed: false,
+            fallbacks: fallbacks.len(),
+            tenant,
+            resolve_started,
+            options: self.options.clone(),
+        }))
+    }
+
+    /**
+     * `resolve_module` for an import asking `import_options` of its module. They're part of
+     * what a flight joins on, so imports asserting different types never share a load, nor
+     * how it failed.
+     */
+    fn resolve_with_options(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+        import_options: ImportOptions,
+    ) -> LoadedModuleFuture {
+        // Recorded as asked for, before import maps, wasm import maps and base urls rewrite it.
+        let recorder = self.recorder.clone();
+        // virtual modules are the host's own, like for integrity checks
+        let lockfile = self.lockfile.clone().map(|lockfile| {
+            let registry = self.virtual_resolver.as_ref().map(|r| r.registry().clone());
+            (lockfile, registry)
+        });
+        let graph = self.graph.clone();
+        let limits = self.options.limits;
+        let code_cache = self.code_cache.clone();
+        let wasm_cache = self.wasm_cache.clone();
+        let referer_origin_url = match referer_info {
+            Some(ref info) => info.origin_url.clone(),
+            None => self.default_working_url.clone(),
+        };
+        let lookup_started = Instant::now();
+        let cached = self
+            .resolution_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&specifier, &referer_origin_url));
+        let traces = self.traces.clone();
+        let trace: Option<TraceHandle> = traces.as_ref().map(|_| {
+            Arc::new(Mutex::new(ResolutionTrace::new(
+                &specifier,
+                &referer_origin_url,
+            )))
+        });
+        if let Some(ref module) = cached {
+            self.observe_cache_hit(&specifier, &module.origin_url, lookup_started.elapsed());
+            push_step(&trace, || TraceStep::CacheHit {
+                origin_url: module.origin_url.clone(),
+            });
+        }
+        let known_missing = match cached {
+            Some(_) => None,
+            None => self
+                .resolution_cache
+                .as_ref()
+                .and_then(|cache| cache.known_missing(&specifier, &referer_origin_url)),
+        };
+        if known_missing.is_some() {
+            push_step(&trace, || TraceStep::KnownMissing);
+        }
+        // the top-level load this import is part of, if that one is bounded
+        let deadline = referer_info.as_ref().and_then(|r| r.deadline.clone());
+        let import = format!("{} from {}", specifier, referer_origin_url);
+        let loaded: LoadedModuleFuture = match (cached, known_missing) {
+            (Some(module), _) => Box::new(future::ok(module)),
+            (None, Some(e)) => Box::new(future::err(e)),
+            (None, None) => {
+                let prepared = traced(&trace, || self.prepare(specifier.clone(), &referer_info));
+                let loading = self.loading();
+                let cache = self.resolution_cache.clone();
+                let missing = cache
+                    .clone()
+                    .map(|cache| (cache, specifier.clone(), referer_origin_url.clone()));
+                let specifier = specifier.clone();
+                let observers = self.observers.clone();
+                let load_trace = trace.clone();
+                let load = move |located: Box<Future<Item = Located, Error = FlyError> + Send>| {
+                    let trace = load_trace;
+                    located.and_then(move |located| match (cache, &located) {
+                        (Some(cache), Located::Resolved { .. }) => {
+                            let origin_url = located.origin_url().to_string();
+                            cache.load(specifier, referer_origin_url, &origin_url, move || {
+                                loading.load_falling_through(located, observers, trace)
+                            })
+                        }
+                        _ => loading.load_falling_through(located, observers, trace),
+                    })
+                };
+                let loaded: LoadedModuleFuture = match prepared {
+                    Ok(Prepared::Located(located)) => Box::new(load(Box::new(future::ok(located)))),
+                    Ok(Prepared::Candidates(candidates)) => {
+                        self.single_flight(candidates, &import_options, trace.clone(), load)
+                    }
+                    Err(e) => Box::new(future::err(e)),
+                };
+                match missing {
+                    Some((cache, specifier, referer_origin_url)) => {
+                        Box::new(loaded.map_err(move |e| {
+                            cache.record_missing(&specifier, &referer_origin_url, &e);
+                            e
+                        }))
+                    }
+                    None => loaded,
+                }
+            }
+        };
+        let resolved: LoadedModuleFuture = Box::new(loaded.then(move |result| {
+            if let (Some(traces), Some(trace)) = (traces, trace) {
+                let mut trace = trace.lock().unwrap().clone();
+                trace.steps.push(match result {
+                    Ok(ref module) => TraceStep::Resolved {
+                        origin_url: module.origin_url.clone(),
+                        resolver: module.resolver.clone(),
+                    },
+                    Err(ref e) => TraceStep::Failed {
+                        error: e.to_string(),
+                    },
+                });
+                debug!("{}", trace);
+                traces.push(trace);
+            }
+            let mut module = match result {
+                Ok(module) => module,
+                Err(e) => {
+                    // Point at the import when the referer's position or source came along.
+                    let context = referer_info
+                        .as_ref()
+                        .and_then(|r| import_site(r, &specifier));
+                    return Err(match context {
+                        Some(context) => new(e.kind(), format!("{}{}", e, context)),
+                        None => e,
+                    });
+                }
+            };
+            if let Some((lockfile, registry)) = lockfile {
+                if !registry.map_or(false, |r| r.contains(&module.origin_url)) {
+                    let referer = referer_info.as_ref().map(|r| r.origin_url.as_str());
+                    lockfile.check(&specifier, referer, &module)?;
+                }
+            }
+            limits.check_graph(
+                &graph,
+                &module.origin_url,
+                referer_info.as_ref().map(|r| r.origin_url.as_str()),
+            )?;
+            graph.record_module(
+                &module.origin_url,
+                module.loaded_source.source.len(),
+                module.loaded_source.is_wasm(),
+                referer_info.as_ref().map(|r| r.origin_url.as_str()),
+            );
+            if let Some(ref referer) = referer_info {
+                if let Some(cycle) = graph.record_import(&referer.origin_url, &module.origin_url) {
+                    warn!("import cycle: {}", cycle.join(" -> "));
+                }
+            }
+            if let Some(recorder) = recorder {
+                let referer = referer_info.as_ref().map(|r| r.origin_url.as_str());
+                if let Err(e) = recorder.record(&specifier, referer, &module) {
+                    error!("could not record {} in cassette: {}", specifier, e);
+                }
+            }
+            if let Some(cache) = code_cache {
+                if !module.loaded_source.is_wasm() {
+                    module.loaded_source.code_cache =
+                        cache.get(&module.origin_url, &module.loaded_source.source);
+                }
+            }
+            if let Some(cache) = wasm_cache {
+                if module.loaded_source.is_wasm() {
+                    module.loaded_source.compiled_wasm = wasm_bytes(&module.loaded_source)
+                        .ok()
+                        .and_then(|wasm| cache.get(&wasm));
+                }
+            }
+            Ok(module)
+        }));
+        // after the lockfile and caches, which go by the module as it loaded
+        let resolved: LoadedModuleFuture =
+            Box::new(resolved.and_then(move |module| import_options.apply(module)));
+        match deadline {
+            Some(deadline) => deadline.bound(import, resolved),
+            None => resolved,
+        }
+    }
+}
+
+impl ModuleResolverManager for StandardModuleResolverManager {
+    /**
+     * Entry points resolve against the working url, and are always paths on disk when
+     * absolute, even if the working url isn't a `file` url.
+     */
+    fn load_entry_point(&self, specifier: String) -> LoadedModuleFuture {
+        let working_url = if is_absolute_path(&specifier) {
+            "file:///"
+        } else {
+            self.default_working_url.as_str()
+        };
+        match parse_url(&specifier, working_url) {
+            Ok(url) => self.resolve_module(url.into_string(), None),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    fn resolve_module(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> LoadedModuleFuture {
+        self.resolve_with_options(specifier, referer_info, ImportOptions::default())
+    }
+
+    fn resolve_import(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+        options: ImportOptions,
+    ) -> LoadedModuleFuture {
+        self.resolve_with_options(specifier, referer_info, options)
+    }
+
+    /**
+     * The module's loader loads it as `resolve_module` would: within its resolver's deadline
+     * and retries, through the shared cache, checked and transpiled.
+     */
+    fn resolve(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        Ok(self.source_data(self.locate(specifier, &referer_info)?))
+    }
+
+    /**
+     * Traces into a trace of its own whether or not this manager keeps traces, ending it
+     * with where the specifier resolved to, as `resolve_module` does.
+     */
+    fn trace_resolution(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> Option<(FlyResult<ModuleSourceData>, ResolutionTrace)> {
+        let referer_origin_url = match referer_info {
+            Some(ref info) => info.origin_url.clone(),
+            None => self.default_working_url.clone(),
+        };
+        let trace: Option<TraceHandle> = Some(Arc::new(Mutex::new(ResolutionTrace::new(
+            &specifier,
+            &referer_origin_url,
+        ))));
+        let located = self.locate_traced(specifier, &referer_info, &trace);
+        push_step(&trace, || match located {
+            Ok(Located::Virtual(ref module_loader)) => TraceStep::Resolved {
+                origin_url: module_loader.origin_url.clone(),
+                resolver: None,
+            },
+            Ok(Located::Resolved {
+                ref module_loader,
+                ref resolver,
+                ..
+            }) => TraceStep::Resolved {
+                origin_url: module_loader.origin_url.clone(),
+                resolver: Some(resolver.name()),
+            },
+            Err(ref e) => TraceStep::Failed {
+                error: e.to_string(),
+            },
+        });
+        let trace = trace.unwrap().lock().unwrap().clone();
+        Some((located.map(|located| self.source_data(located)), trace))
+    }
+
+    /**
+     * Stops once a resolver resolved the specifier, dropping its loader unused.
+     */
+    fn resolve_only(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ResolvedModuleInfo> {
+        Ok(match self.locate(specifier, &referer_info)? {
+            Located::Virtual(module_loader) => {
+                ResolvedModuleInfo::new(&module_loader.origin_url, "virtual", "")
+            }
+            Located::Resolved {
+                module_loader,
+                resolver,
+                ..
+            } => ResolvedModuleInfo::new(
+                &module_loader.origin_url,
+                &resolver.describe().kind,
+                &resolver.get_protocol(),
+            ),
+        })
+    }
+
+    fn store_code_cache(&self, origin_url: &str, source: &str, code_cache: Vec<u8>) {
+        if let Some(ref cache) = self.code_cache {
+            cache.insert(origin_url, source, code_cache);
+        }
+    }
+
+    fn store_compiled_wasm(&self, origin_url: &str, wasm: &[u8], compiled: &[u8]) {
+        if let Some(ref cache) = self.wasm_cache {
+            debug!("keeping the compiled wasm of {}", origin_url);
+            cache.insert(wasm, compiled);
+        }
+    }
+
+    /**
+     * Only worth it with a resolution cache, which is what keeps the preloaded modules.
+     */
+    fn preload(&self, entry: &str) -> FlyResult<PreloadSummary> {
+        if self.resolution_cache.is_none() {
+            return Err(FlyError::from(
+                "preloading needs a resolution cache to keep the modules in, see `with_resolution_cache`"
+                    .to_string(),
+            ));
+        }
+        preload_graph(self, entry)
+    }
+
+    fn total_deadline(&self) -> Option<Duration> {
+        self.options.total_deadline
+    }
+
+    fn cache_state(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleCacheState> {
+        Ok(match self.locate(specifier, &referer_info)? {
+            Located::Virtual(module_loader) => {
+                let size = match self.virtual_resolver {
+                    Some(ref resolver) => resolver
+                        .registry()
+                        .get(&module_loader.origin_url)
+                        .map(|m| m.source.len())
+                        .unwrap_or(0),
+                    None => 0,
+                };
+                ModuleCacheState::cached(
+                    &module_loader.origin_url,
+                    CacheLocation::Memory,
+                    size,
+                    CacheFreshness::Fresh,
+                )
+            }
+            Located::Resolved {
+                module_loader,
+                resolver,
+                tenant,
+                ..
+            } => {
+                let origin_url = &module_loader.origin_url;
+                let in_memory = match self.shared_cache {
+                    Some((ref cache, _)) if !resolver.is_passthrough() => cache.peek(origin_url),
+                    _ => None,
+                };
+                match in_memory {
+                    Some(size) => ModuleCacheState::cached(
+                        origin_url,
+                        CacheLocation::Memory,
+                        size,
+                        CacheFreshness::Fresh,
+                    ),
+                    None => resolver
+                        .cache_state(origin_url, &tenant)
+                        .unwrap_or_else(|| ModuleCacheState::uncached(origin_url)),
+                }
+            }
+        })
+    }
+
+    fn startup_report(&self) -> Option<StartupReport> {
+        Some(self.profiler.report())
+    }
+
+    fn loaded_modules(&self) -> Option<ModuleGraphSnapshot> {
+        Some(self.graph.snapshot())
+    }
+
+    fn describe(&self) -> Option<ManagerDescription> {
+        let mut protocols: Vec<ProtocolDescription> = self
+            .protocol_resolver_map
+            .iter()
+            .map(|(protocol, resolvers)| ProtocolDescription {
+                protocol: protocol.clone(),
+                resolvers: resolvers
+                    .iter()
+                    .map(|r| {
+                        // named even by resolvers describing themselves without it
+                        let description = r.resolver.describe().named(&r.resolver.name());
+                        match r.priority {
+                            0 => description,
+                            priority => description.with("priority", &priority.to_string()),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        protocols.sort_by(|a, b| a.protocol.cmp(&b.protocol));
+        Some(ManagerDescription {
+            default_working_url: self.default_working_url.clone(),
+            policy: self.policy().to_string(),
+            protocols,
+            virtual_modules: match self.virtual_resolver {
+                Some(ref resolver) => resolver.registry().specifiers(),
+                None => vec![],
+            },
+            base_urls: self
+                .base_urls
+                .iter()
+                .map(|(placeholder, base_url)| (placeholder.clone(), base_url.clone()))
+                .collect(),
+            wasm_imports: match self.wasm_imports {
+                Some(ref imports) => imports
+                    .iter()
+                    .map(|(name, specifier)| (name.clone(), specifier.clone()))
+                    .collect(),
+                None => Default::default(),
+            },
+            import_map: match self.import_map {
+                Some(ref import_map) => import_map
+                    .iter()
+                    .map(|(specifier, url)| (specifier.clone(), url.to_string()))
+                    .collect(),
+                None => Default::default(),
+            },
+            load_scheduler: self.load_scheduler.as_ref().map(|s| s.config().clone()),
+            shared_cache: self.shared_cache.as_ref().map(|(cache, _)| cache.stats()),
+            resolution_cache: self.resolution_cache.as_ref().map(|cache| cache.stats()),
+        })
+    }
+
+    fn inherit_for_child(
+        &self,
+        policy: Option<ResolverPolicy>,
+    ) -> FlyResult<Box<ModuleResolverManager>> {
+        let child = self.inherit();
+        Ok(Box::new(match policy {
+            Some(policy) => child.narrow(policy)?,
+            None => child,
+        }))
+    }
+
+    fn register_resolver(&mut self, resolver: Box<ModuleResolver>) -> FlyResult<()> {
+        StandardModuleResolverManager::register_resolver(self, resolver)
+    }
+
+    fn tighten_policy(&self, policy: ResolverPolicy) -> FlyResult<()> {
+        StandardModuleResolverManager::tighten_policy(self, policy)
+    }
+
+    fn clear_module_cache(&self, origin_url: Option<&str>) -> FlyResult<usize> {
+        StandardModuleResolverManager::clear_module_cache(self, origin_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("util.ts"), "export default 1;").unwrap();
+        dir
+    }
+
+    fn resolver(dir: &tempfile::TempDir) -> LocalDiskModuleResolver {
+        LocalDiskModuleResolver::new(Some(
+            url::Url::from_directory_path(dir.path())
+                .unwrap()
+                .as_str()
+                .to_string(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_url_accepts_paths_and_urls() {
+        let cwd = url::Url::from_directory_path(std::env::current_dir().unwrap()).unwrap();
+        let cases = [
+            // bare paths
+            ("/srv/app/main.ts", "file:///", "file:///srv/app/main.ts"),
+            (
+                "/srv/app/my file.ts",
+                "/tmp/",
+                "file:///srv/app/my%20file.ts",
+            ),
+            ("/srv/app/../lib.ts", "/tmp/", "file:///srv/lib.ts"),
+            // file urls, whatever the working url
+            (
+                "file:///srv/app/main.ts",
+                "/tmp/",
+                "file:///srv/app/main.ts",
+            ),
+            (
+                "file:///srv/app/main.ts",
+                "https://cdn.test/",
+                "file:///srv/app/main.ts",
+            ),
+            // relative specifiers against each kind of working url
+            (
+                "./main.ts",
+                "file:///srv/app/index.ts",
+                "file:///srv/app/main.ts",
+            ),
+            ("../lib/a.ts", "file:///srv/app/", "file:///srv/lib/a.ts"),
+            ("./main.ts", "/srv/app/", "file:///srv/app/main.ts"),
+            ("./main.ts", "/srv/app/index.ts", "file:///srv/app/main.ts"),
+            (
+                "./main.ts",
+                "https://cdn.test/app/index.js",
+                "https://cdn.test/app/main.ts",
+            ),
+            (
+                "/lib.js",
+                "https://cdn.test/app/index.js",
+                "https://cdn.test/lib.js",
+            ),
+        ];
+        for &(specifier, working_url, expected) in cases.iter() {
+            assert_eq!(
+                expected,
+                parse_url(specifier, working_url).unwrap().as_str(),
+                "{} from {}",
+                specifier,
+                working_url
+            );
+        }
+        // relative working paths are relative to the current directory
+        assert_eq!(
+            cwd.join("app/main.ts").unwrap(),
+            parse_url("./main.ts", "app/").unwrap()
+        );
+        assert_eq!(
+            cwd.join("main.ts").unwrap(),
+            parse_url("./main.ts", "./").unwrap()
+        );
+        assert!(parse_url("http://[bad", "/srv/").is_err());
+    }
+
+    #[test]
+    fn test_parse_url_defaults_schemes() {
+        // protocol-relative specifiers keep the working url's scheme
+        let cases = [
+            (
+                "//cdn.test/lib.js",
+                "https://app.test/index.js",
+                "https://cdn.test/lib.js",
+            ),
+            (
+                "//cdn.test/lib.js",
+                "http://app.test/index.js",
+                "http://cdn.test/lib.js",
+            ),
+            ("//share/lib.js", "file:///srv/app/", "file://share/lib.js"),
+        ];
+        for &(specifier, working_url, expected) in cases.iter() {
+            assert_eq!(
+                expected,
+                parse_url(specifier, working_url).unwrap().as_str(),
+                "{} from {}",
+                specifier,
+                working_url
+            );
+        }
+
+        for &(specifier, working_url) in [
+            ("", "file:///srv/app/index.ts"),
+            ("  ", "https://cdn.test/index.js"),
+            ("http://[bad", "file:///srv/"),
+            ("http://cdn.test:99999/", "file:///srv/"),
+            ("./main.ts", "data:text/javascript,1"),
+        ]
+        .iter()
+        {
+            let err = parse_url(specifier, working_url).err().unwrap();
+            assert_eq!(
+                ErrorKind::InvalidSpecifier,
+                err.kind(),
+                "{:?} from {}: {}",
+                specifier,
+                working_url,
+                err
+            );
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_parse_url_accepts_windows_paths() {
+        let cases = [
+            ("C:\\app\\main.ts", "file:///", "file:///C:/app/main.ts"),
+            (".\\main.ts", "C:\\app\\", "file:///C:/app/main.ts"),
+            ("./lib/a.ts", "C:\\app\\index.ts", "file:///C:/app/lib/a.ts"),
+        ];
+        for &(specifier, working_url, expected) in cases.iter() {
+            assert_eq!(
+                expected,
+                parse_url(specifier, working_url).unwrap().as_str(),
+                "{} from {}",
+                specifier,
+                working_url
+            );
+        }
+    }
+
+    #[test]
+    fn test_failures_point_at_the_import_in_referer_source() {
+        let dir = app_dir();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+            Some(resolver(&dir).default_working_url),
+        );
+        let referer = RefererInfo {
+            origin_url: format!("{}snippet", resolver(&dir).default_working_url),
+            is_wasm: Some(false),
+            source_code: Some("// generated\nimport x from './missing.ts';\n".into()),
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let err = manager
+            .resolve_blocking("./missing.ts".to_string(), Some(referer))
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).ends_with("snippet:2: import x from './missing.ts';"));
+    }
+
+    #[test]
+    fn test_entry_points_resolve_without_a_referer() {
+        let dir = app_dir();
+        std::fs::write(dir.path().join("main.ts"), "import u from './util.ts';").unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(resolver(&dir)) as Box<ModuleResolver>,
+                Box::new(
+                    StaticModuleResolver::new("https")
+                        .insert("https://cdn.test/app/main.js", "export default 1;"),
+                ),
+            ],
+            Some(resolver(&dir).default_working_url),
+        );
+        let main = url::Url::from_file_path(dir.path().join("main.ts"))
+            .unwrap()
+            .into_string();
+        let abs_main = dir.path().join("main.ts").to_str().unwrap().to_string();
+        for specifier in &["./main.ts", "main.ts", abs_main.as_str(), main.as_str()] {
+            let module = manager.resolve_entry_point(specifier.to_string()).unwrap();
+            assert_eq!(main, module.origin_url, "{}", specifier);
+        }
+        let module = manager
+            .resolve_entry_point("https://cdn.test/app/main.js".to_string())
+            .unwrap();
+        assert_eq!("https://cdn.test/app/main.js", module.origin_url);
+
+        // the entry is the referer of its own imports
+        let referer = RefererInfo {
+            origin_url: main,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let util = manager
+            .resolve_blocking("./util.ts".to_string(), Some(referer))
+            .unwrap();
+        assert!(util.origin_url.ends_with("/util.ts"));
+
+        let err = manager
+            .resolve_entry_point("app://[::1".to_string())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidSpecifier, err.kind());
+    }
+
+    #[test]
+    #[cfg(feature = "transpile")]
+    fn test_source_maps_name_absolute_sources() {
+        let dir = app_dir();
+        std::fs::write(
+            dir.path().join("main.ts"),
+            "import b from './b.ts';\nconst a: number = b;",
+        )
+        .unwrap();
+        let map = |json: &str| LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: Some(json.into()),
+            source: "export default 1;".into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        };
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(resolver(&dir)) as Box<ModuleResolver>,
+                Box::new(
+                    StaticModuleResolver::new("https")
+                        .insert_source(
+                            "https://cdn.test/dist/app.js",
+                            map(r#"{"version":3,"sourceRoot":"src","sources":["a.ts","../b.ts"],"mappings":""}"#),
+                        )
+                        .insert_source("https://cdn.test/dist/broken.js", map("not a map"))
+                        .insert("https://cdn.test/dist/src/a.ts", "const a = 1;"),
+                ),
+            ],
+            Some(resolver(&dir).default_working_url),
+        )
+        .with_transpiling(true)
+        .with_inlined_source_contents(true);
+        let sources = |url: &str| {
+            let module = manager.resolve_entry_point(url.to_string()).unwrap();
+            let map: serde_json::Value =
+                serde_json::from_str(&module.loaded_source.source_map.unwrap()).unwrap();
+            assert!(map.get("sourceRoot").is_none(), "{}", map);
+            (map["sources"].clone(), map["sourcesContent"].clone())
+        };
+
+        let main = url::Url::from_file_path(dir.path().join("main.ts"))
+            .unwrap()
+            .into_string();
+        let (names, contents) = sources("./main.ts");
+        assert_eq!(serde_json::json!([main]), names);
+        assert_eq!(
+            serde_json::json!(["import b from './b.ts';\nconst a: number = b;"]),
+            contents
+        );
+
+        // b.ts can't be loaded, so its content stays unknown
+        let (names, contents) = sources("https://cdn.test/dist/app.js");
+        assert_eq!(
+            serde_json::json!([
+                "https://cdn.test/dist/src/a.ts",
+                "https://cdn.test/dist/b.ts"
+            ]),
+            names
+        );
+        assert_eq!(serde_json::json!(["const a = 1;", null]), contents);
+
+        let broken = manager
+            .resolve_entry_point("https://cdn.test/dist/broken.js".to_string())
+            .unwrap();
+        assert_eq!("export default 1;", &*broken.loaded_source.source);
+        assert!(broken.loaded_source.source_map.is_none());
+    }
+
+    #[test]
+    fn test_failures_report_the_import_position() {
+        let dir = app_dir();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+            Some(resolver(&dir).default_working_url),
+        );
+        let main = format!("{}main.ts", resolver(&dir).default_working_url);
+        let referer = |source_code: Option<&str>| RefererInfo {
+            origin_url: main.clone(),
+            is_wasm: Some(false),
+            source_code: source_code.map(Into::into),
+            identifier_hash: Some(42),
+            line: Some(2),
+            column: Some(8),
+            app_id: None,
+            deadline: None,
+        };
+        let error = |referer| {
+            let err = manager
+                .resolve_blocking("./foo".to_string(), Some(referer))
+                .err()
+                .unwrap();
+            format!("{}", err)
+        };
+
+        let err = error(referer(None));
+        assert!(err.starts_with(&format!("Could not resolve ./foo from {}", main)));
+        assert!(
+            err.ends_with(&format!("\n    imported from {}:2:8", main)),
+            "{}",
+            err
+        );
+        let err = error(referer(Some("// main\nimport foo from './foo';\n")));
+        assert!(err.ends_with(&format!(
+            "imported from {}:2:8: import foo from './foo';",
+            main
+        )));
+    }
+
+    #[test]
+    fn test_cache_state_reports_without_loading() {
+        let dir = app_dir();
+        let registry = VirtualModuleRegistry::new();
+        registry
+            .insert("app:config", "export default {};", MediaType::JavaScript)
+            .unwrap();
+        let cache = SharedSourceCache::new(DEFAULT_SHARED_CACHE_BYTES);
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+            Some(resolver(&dir).default_working_url),
+        )
+        .with_virtual_modules(registry)
+        .with_shared_cache(cache.clone());
+
+        let cold = manager.cache_state("./util".to_string(), None).unwrap();
+        assert!(cold.origin_url.ends_with("/util.ts"));
+        assert!(!cold.is_cached());
+        assert!(manager.startup_report().unwrap().modules.is_empty());
+        assert_eq!(0, cache.stats().misses);
+
+        manager
+            .resolve_blocking("./util".to_string(), None)
+            .unwrap();
+        let warm = manager.cache_state("./util".to_string(), None).unwrap();
+        assert_eq!(
+            ModuleCacheState::cached(
+                &cold.origin_url,
+                CacheLocation::Memory,
+                "export default 1;".len(),
+                CacheFreshness::Fresh
+            ),
+            warm
+        );
+        assert_eq!(0, cache.stats().hits);
+
+        let config = manager.cache_state("app:config".to_string(), None).unwrap();
+        assert_eq!(Some(CacheLocation::Memory), config.location);
+        assert_eq!("export default {};".len(), config.size);
+        assert!(manager.cache_state("./missing".to_string(), None).is_err());
+    }
+
+    struct Prioritized(i32, StaticModuleResolver);
+
+    impl ModuleResolver for Prioritized {
+        fn resolve_module(
+            &self,
+            module_specifier: &str,
+            referer_info: Option<RefererInfo>,
+        ) -> FlyResult<ModuleSourceData> {
+            self.1.resolve_module(module_specifier, referer_info)
+        }
+        fn get_protocol(&self) -> String {
+            self.1.get_protocol()
+        }
+        fn priority(&self) -> i32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_records_import_edges_by_origin_url() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                StaticModuleResolver::new("static")
+                    .insert("static://app/main.js", "")
+                    .insert("static://app/a.js", "")
+                    .insert("static://app/b.js", "")
+                    .insert("static://app/lib/c.js", ""),
+            ) as Box<ModuleResolver>],
+            Some("static://app/".to_string()),
+        );
+        let import = |specifier: &str, referer: &str| {
+            manager
+                .resolve_blocking(
+                    specifier.to_string(),
+                    Some(RefererInfo {
+                        origin_url: referer.to_string(),
+                        is_wasm: Some(false),
+                        source_code: None,
+                        identifier_hash: None,
+                        line: None,
+                        column: None,
+                        app_id: None,
+                        deadline: None,
+                    }),
+                )
+                .unwrap();
+        };
+        manager
+            .resolve_blocking("./main.js".to_string(), None)
+            .unwrap();
+        import("./a.js", "static://app/main.js");
+        import("./b.js", "static://app/main.js");
+        // different specifiers, one node
+        import("./lib/c.js", "static://app/a.js");
+        import("./lib/../lib/c.js", "static://app/b.js");
+        import("../main.js", "static://app/lib/c.js");
+
+        assert_eq!(
+            vec!["static://app/a.js", "static://app/b.js"],
+            manager.importers_of("static://app/lib/c.js")
+        );
+        assert_eq!(
+            vec!["static://app/lib/c.js"],
+            manager.importers_of("static://app/main.js")
+        );
+        assert_eq!(
+            vec!["static://app/main.js"],
+            manager.get_graph().imports_of("static://app/lib/c.js")
+        );
+    }
+
+    #[test]
+    fn test_higher_priority_resolvers_are_tried_first() {
+        let serving = |source: &str| {
+            StaticModuleResolver::new("static").insert("static://app/main.js", source)
+        };
+        let load = |manager: &StandardModuleResolverManager| {
+            manager
+                .resolve_blocking("static://app/main.js".to_string(), None)
+                .unwrap()
+                .loaded_source
+                .source
+                .to_string()
+        };
+        let mut manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(Prioritized(-1, serving("low"))) as Box<ModuleResolver>,
+                Box::new(serving("first")),
+                Box::new(serving("second")),
+            ],
+            Some("static://app/".to_string()),
+        );
+        // equal priorities keep registration order
+        assert_eq!("first", load(&manager));
+        let order: Vec<Option<String>> = manager.describe().unwrap().protocols[0]
+            .resolvers
+            .iter()
+            .map(|r| r.config.get("priority").cloned())
+            .collect();
+        assert_eq!(vec![None, None, Some("-1".to_string())], order);
+
+        let mock = manager.add_resolver(Box::new(serving("mock")), 1).unwrap();
+        manager
+            .add_resolver(Box::new(serving("later mock")), 1)
+            .unwrap();
+        assert_eq!("mock", load(&manager));
+        assert!(manager.remove_resolver(mock));
+        assert!(!manager.remove_resolver(mock));
+        assert_eq!("later mock", load(&manager));
+        assert!(manager
+            .narrow(ResolverPolicy::allow_protocols(vec!["file".to_string()]))
+            .unwrap()
+            .add_resolver(Box::new(serving("mock")), 1)
+            .is_err());
+    }
+
+    struct ThreadRecordingLoader(Arc<std::sync::Mutex<Vec<std::thread::ThreadId>>>);
+
+    impl SourceLoader for ThreadRecordingLoader {
+        fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+            self.0.lock().unwrap().push(std::thread::current().id());
+            Ok(LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: None,
+                source: "export default 1;".into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_resolves_when_polled_and_off_the_calling_thread() {
+        let resolved_on = Arc::new(std::sync::Mutex::new(vec![]));
+        let loaded_on = Arc::new(std::sync::Mutex::new(vec![]));
+        let (resolved, loaded) = (resolved_on.clone(), loaded_on.clone());
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "function",
+                Box::new(move |specifier: &str, _| {
+                    resolved.lock().unwrap().push(std::thread::current().id());
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(ThreadRecordingLoader(loaded.clone())),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            Some("function:///".to_string()),
+        );
+        let caller = std::thread::current().id();
+        let loading = manager.resolve_module("function:///main.js".to_string(), None);
+        assert!(resolved_on.lock().unwrap().is_empty());
+        let module = loading.wait().unwrap();
+        assert_eq!("export default 1;", &*module.loaded_source.source);
+        assert_ne!(caller, resolved_on.lock().unwrap()[0]);
+        assert_ne!(caller, loaded_on.lock().unwrap()[0]);
+    }
+
+    #[test]
+    fn test_shebangs_are_stripped_keeping_line_numbers() {
+        let dir = app_dir();
+        std::fs::write(
+            dir.path().join("cli.ts"),
+            "#!/usr/bin/env fly\r\nimport util from \"./dep\";\nthrow new Error(\"line 3\");\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("dep.js"), "#!/usr/bin/env node").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "#!not a script").unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+            Some(resolver(&dir).default_working_url),
+        );
+        let load = |specifier: &str| {
+            manager
+                .resolve_blocking(specifier.to_string(), None)
+                .unwrap()
+                .loaded_source
+                .source
+        };
+        let entry = load("./cli.ts");
+        assert_eq!(
+            "\r\nimport util from \"./dep\";\nthrow new Error(\"line 3\");\n",
+            &*entry
+        );
+        let throw_line = entry
+            .split('\n')
+            .position(|l| l.starts_with("throw"))
+            .unwrap()
+            + 1;
+        assert_eq!(3, throw_line);
+        // non-entry modules too
+        assert_eq!("", &*load("./dep.js"));
+        assert_eq!(
+            "export default \"#!not a script\";\n",
+            &*load("./notes.txt")
+        );
+    }
+
+    fn app_module(specifier: &str, source: &str) -> FlyResult<ModuleSourceData> {
+        Ok(ModuleSourceData {
+            origin_url: specifier.to_string(),
+            source_loader: Box::new(StaticSourceLoader {
+                source: LoadedSourceCode {
+                    kind: ModuleKind::JavaScript,
+                    source_map: None,
+                    source: source.into(),
+                    response: None,
+                    provenance: None,
+                    code_cache: None,
+                    compiled_wasm: None,
+                    wasm: None,
+                },
+            }),
+        })
+    }
+
+    /** Counts how many times its body is actually read. */
+    struct CountingLoader(Arc<std::sync::atomic::AtomicUsize>);
+
+    impl SourceLoader for CountingLoader {
+        fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: None,
+                source: "#!/usr/bin/env fly\nexport default 1;".into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_resolving_loads_nothing_until_asked() {
+        use std::io::Read;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let reads = Arc::new(AtomicUsize::new(0));
+        let counted = reads.clone();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, _| {
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(CountingLoader(counted.clone())),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        let module = manager.resolve("app://main.js".to_string(), None).unwrap();
+        assert_eq!("app://main.js", module.origin_url);
+        assert_eq!(0, reads.load(Ordering::SeqCst));
+
+        // loaded as an import would be, shebang stripped
+        let loaded = manager.load(&module).unwrap();
+        assert_eq!("\nexport default 1;", &*loaded.source);
+        assert_eq!(1, reads.load(Ordering::SeqCst));
+
+        let mut streamed = String::new();
+        module
+            .source_loader
+            .load_reader()
+            .unwrap()
+            .read_to_string(&mut streamed)
+            .unwrap();
+        assert_eq!(&*loaded.source, streamed.as_str());
+        assert_eq!(2, reads.load(Ordering::SeqCst));
+
+        assert!(manager
+            .resolve("gopher://main.js".to_string(), None)
+            .is_err());
+        assert_eq!(2, reads.load(Ordering::SeqCst));
+        manager
+            .resolve_blocking("app://main.js".to_string(), None)
+            .unwrap();
+        assert_eq!(3, reads.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_dry_runs_never_load() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let reads = Arc::new(AtomicUsize::new(0));
+        let counted = reads.clone();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, _| {
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(CountingLoader(counted.clone())),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            Some("app://site/".to_string()),
+        );
+        assert_eq!(
+            ResolvedModuleInfo {
+                origin_url: "app://site/lib/answer.wasm".to_string(),
+                protocol: "app".to_string(),
+                resolver: "function".to_string(),
+                is_wasm: Some(true),
+            },
+            manager
+                .resolve_only("app://site/lib/answer.wasm".to_string(), None)
+                .unwrap()
+        );
+        let main = manager
+            .resolve_only("app://site/main.js".to_string(), None)
+            .unwrap();
+        assert_eq!(Some(false), main.is_wasm);
+        // no extension, so no telling without reading it
+        let bin = manager
+            .resolve_only("app://site/bin".to_string(), None)
+            .unwrap();
+        assert_eq!(None, bin.is_wasm);
+        assert!(manager
+            .resolve_only("gopher://site/main.js".to_string(), None)
+            .is_err());
+        assert_eq!(0, reads.load(Ordering::SeqCst));
+        assert!(manager.loaded_modules().unwrap().modules.is_empty());
+    }
+
+    #[test]
+    fn test_code_caches_come_with_the_source_they_were_made_of() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.js");
+        std::fs::write(&path, "export default 1;").unwrap();
+        let url = url::Url::from_file_path(&path).unwrap().into_string();
+        let root = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(root.clone())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(root),
+        )
+        .with_code_cache(CodeCache::new(DEFAULT_CODE_CACHE_BYTES));
+        let resolve = || {
+            manager
+                .resolve_blocking(url.clone(), None)
+                .unwrap()
+                .loaded_source
+        };
+
+        let first = resolve();
+        assert_eq!(None, first.code_cache);
+        manager.store_code_cache(&url, &first.source, vec![0xC0, 0xDE]);
+        // what another runtime sharing the manager would be handed
+        assert_eq!(
+            Some(vec![0xC0, 0xDE]),
+            manager
+                .clone()
+                .resolve_blocking(url.clone(), None)
+                .unwrap()
+                .loaded_source
+                .code_cache
+        );
+
+        std::fs::write(&path, "export default 2;").unwrap();
+        assert_eq!(None, resolve().code_cache);
+        assert_eq!(0, manager.code_cache().unwrap().stats().modules);
+    }
+
+    #[test]
+    fn test_compiled_wasm_comes_with_any_module_of_its_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = b"\0asm\x01\0\0\0".to_vec();
+        std::fs::write(dir.path().join("a.wasm"), &wasm).unwrap();
+        std::fs::write(dir.path().join("copy.wasm"), &wasm).unwrap();
+        let root = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(root.clone())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(root),
+        )
+        .with_wasm_cache(WasmCompileCache::new(cache_dir.path(), "7.1.302.33").unwrap());
+        let resolve = |name: &str| {
+            manager
+                .resolve_blocking(format!("./{}", name), None)
+                .unwrap()
+        };
+
+        let first = resolve("a.wasm");
+        assert!(first.loaded_source.is_wasm());
+        assert_eq!(None, first.loaded_source.compiled_wasm);
+        manager.store_compiled_wasm(&first.origin_url, &wasm, b"compiled");
+        assert_eq!(
+            Some(b"compiled".to_vec()),
+            resolve("copy.wasm").loaded_source.compiled_wasm
+        );
+        assert_eq!(1, manager.wasm_cache().unwrap().stats().modules);
+
+        // what another process with the same engine would find
+        let reopened = WasmCompileCache::new(cache_dir.path(), "7.1.302.33").unwrap();
+        assert_eq!(Some(b"compiled".to_vec()), reopened.get(&wasm));
+    }
+
+    #[test]
+    fn test_function_resolvers_on_their_own_protocols() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(FunctionModuleResolver::new(
+                    "app",
+                    Box::new(move |specifier: &str, _| {
+                        let n = counted.fetch_add(1, Ordering::SeqCst);
+                        app_module(specifier, &format!("export default {};", n))
+                    }),
+                )) as Box<ModuleResolver>,
+                Box::new(FunctionModuleResolver::new(
+                    "cfg",
+                    Box::new(|specifier: &str, _| app_module(specifier, "export default {};")),
+                )),
+            ],
+            None,
+        );
+        let load = |specifier: &str| {
+            manager
+                .resolve_blocking(specifier.to_string(), None)
+                .unwrap()
+                .loaded_source
+                .source
+                .to_string()
+        };
+        assert_eq!("export default 0;", load("app://main.js"));
+        assert_eq!("export default {};", load("cfg://settings"));
+        assert_eq!("export default 1;", load("app://other.js"));
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        let protocols: Vec<String> = manager
+            .describe()
+            .unwrap()
+            .protocols
+            .into_iter()
+            .map(|p| p.protocol)
+            .collect();
+        assert!(protocols.contains(&"app".to_string()));
+        assert!(protocols.contains(&"cfg".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_protocols_go_to_the_fallback() {
+        let without = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(|specifier: &str, _| app_module(specifier, "export default 1;")),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        let msg = format!(
+            "{}",
+            without
+                .resolve_blocking("gopher://host/a.js".to_string(), None)
+                .err()
+                .unwrap()
+        );
+        assert!(msg.contains("no resolvers for protocol gopher"), "{}", msg);
+        assert!(
+            msg.contains("no fallback resolver is registered"),
+            "{}",
+            msg
+        );
+
+        let with = StandardModuleResolverManager::new(
+            vec![
+                Box::new(FunctionModuleResolver::new(
+                    "app",
+                    Box::new(|specifier: &str, _| {
+                        if specifier.ends_with("missing.js") {
+                            return Err(FlyError::from("not an app module".to_string()));
+                        }
+                        app_module(specifier, "export default 'app';")
+                    }),
+                )) as Box<ModuleResolver>,
+                Box::new(FunctionModuleResolver::new(
+                    FALLBACK_PROTOCOL,
+                    Box::new(|specifier: &str, _| {
+                        if specifier.starts_with("ftp:") {
+                            return Err(FlyError::from("ftp is not proxied".to_string()));
+                        }
+                        app_module(specifier, "export default 'proxied';")
+                    }),
+                )),
+            ],
+            None,
+        );
+        let load = |specifier: &str| {
+            with.resolve_blocking(specifier.to_string(), None)
+                .map(|m| m.loaded_source.source.to_string())
+        };
+        assert_eq!(
+            "export default 'proxied';",
+            load("gopher://host/a.js").unwrap()
+        );
+        // the protocol's own resolvers go first
+        assert_eq!("export default 'app';", load("app://main.js").unwrap());
+        assert_eq!(
+            "export default 'proxied';",
+            load("app://missing.js").unwrap()
+        );
+        let msg = format!("{}", load("ftp://host/a.js").err().unwrap());
+        assert!(msg.contains("including the fallback"), "{}", msg);
+        assert!(msg.contains("ftp is not proxied"), "{}", msg);
+    }
+
+    #[test]
+    fn test_not_found_lists_every_resolver_tried() {
+        let failing = |msg: &'static str| {
+            Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |_: &str, _| Err(FlyError::from(msg.to_string()))),
+            )) as Box<ModuleResolver>
+        };
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                failing("no copy in the vendor dir"),
+                failing("the mirror answered 503"),
+            ],
+            None,
+        );
+        let err = manager
+            .resolve_blocking("app://lib.js".to_string(), None)
+            .err()
+            .unwrap();
+        let msg = format!("{}", err);
+        assert!(msg.contains("exhausted all resolvers"), "{}", msg);
+        let vendor = msg.find("1. function for app: no copy in the vendor dir");
+        let mirror = msg.find("2. function for app: the mirror answered 503");
+        assert!(vendor.is_some() && vendor < mirror, "{}", msg);
+    }
+
+    #[test]
+    fn test_records_which_resolver_won() {
+        let dir = app_dir();
+        let metrics = Arc::new(ResolutionMetrics::new());
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(resolver(&dir)) as Box<ModuleResolver>,
+                Box::new(FunctionModuleResolver::new(
+                    FALLBACK_PROTOCOL,
+                    Box::new(|specifier: &str, _| app_module(specifier, "export default 2;")),
+                )),
+            ],
+            Some(
+                url::Url::from_directory_path(dir.path())
+                    .unwrap()
+                    .into_string(),
+            ),
+        )
+        .with_observer(metrics.clone())
+        .with_tracing(true);
+
+        let on_disk = manager
+            .resolve_blocking("./util".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            Some("LocalDiskModuleResolver".to_string()),
+            on_disk.resolver
+        );
+        let fallen_back = manager
+            .resolve_blocking("./missing.js".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            Some("FunctionModuleResolver".to_string()),
+            fallen_back.resolver
+        );
+
+        let traces = manager.traces();
+        assert_eq!(
+            Some(&TraceStep::Resolved {
+                origin_url: fallen_back.origin_url.clone(),
+                resolver: Some("FunctionModuleResolver".to_string()),
+            }),
+            traces[1].steps.last()
+        );
+        // the fallback's attempts count under its own protocol
+        let won: Vec<(String, String, usize)> = metrics
+            .snapshot()
+            .into_iter()
+            .flat_map(|stats| {
+                let protocol = stats.protocol;
+                stats
+                    .resolved_by
+                    .into_iter()
+                    .map(move |(name, n)| (protocol.clone(), name, n))
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("*".to_string(), "FunctionModuleResolver".to_string(), 1),
+                ("file".to_string(), "LocalDiskModuleResolver".to_string(), 1),
+            ],
+            won
+        );
+        let names: Vec<String> = manager
+            .describe()
+            .unwrap()
+            .protocols
+            .into_iter()
+            .flat_map(|protocol| protocol.resolvers)
+            .map(|resolver| resolver.name)
+            .collect();
+        assert_eq!(
+            vec!["FunctionModuleResolver", "LocalDiskModuleResolver"],
+            names
+        );
+    }
+
+    fn impatient(resolvers: Vec<Box<ModuleResolver>>) -> StandardModuleResolverManager {
+        StandardModuleResolverManager::with_options(
+            resolvers,
+            None,
+            ResolverOptions {
+                timeout: Some(Duration::from_millis(50)),
+                ..ResolverOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_hung_resolvers_time_out_to_the_next_one() {
+        let hung = || {
+            Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(|specifier: &str, _| {
+                    std::thread::sleep(Duration::from_secs(2));
+                    app_module(specifier, "export default 'hung';")
+                }),
+            )) as Box<ModuleResolver>
+        };
+        let manager = impatient(vec![
+            hung(),
+            Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(|specifier: &str, _| app_module(specifier, "export default 'mirror';")),
+            )),
+        ]);
+        let started = Instant::now();
+        let module = manager
+            .resolve_blocking("app://lib.js".to_string(), None)
+            .unwrap();
+        assert_eq!("export default 'mirror';", &*module.loaded_source.source);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let manager = impatient(vec![hung()]);
+        let err = manager
+            .resolve_blocking("app://lib.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::TimedOut, err.kind());
+        let msg = err.to_string();
+        assert!(
+            msg.contains("timed out") && msg.contains("resolving app://lib.js with function"),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn test_hung_loads_time_out_to_the_next_resolver() {
+        struct Hung;
+        impl SourceLoader for Hung {
+            fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+                std::thread::sleep(Duration::from_secs(2));
+                Err(FlyError::from("too late".to_string()))
+            }
+        }
+        let hung = || {
+            Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(|specifier: &str, _| {
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(Hung),
+                    })
+                }),
+            )) as Box<ModuleResolver>
+        };
+        let manager = impatient(vec![
+            hung(),
+            Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(|specifier: &str, _| app_module(specifier, "export default 'mirror';")),
+            )),
+        ])
+        .with_tracing(true);
+        let module = manager
+            .resolve_blocking("app://lib.js".to_string(), None)
+            .unwrap();
+        assert_eq!("export default 'mirror';", &*module.loaded_source.source);
+        assert!(manager.traces()[0].steps.iter().any(|step| match step {
+            TraceStep::ResolverFailed { error, .. } => error.contains("loading app://lib.js"),
+            _ => false,
+        }));
+
+        let err = impatient(vec![hung()])
+            .resolve_blocking("app://lib.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::TimedOut, err.kind());
+    }
+
+    #[test]
+    fn test_racing_resolvers_take_the_first_to_resolve() {
+        let mirror = |name: &'static str, delay: u64| {
+            Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, _| {
+                    std::thread::sleep(Duration::from_millis(delay));
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.replace("app://", &format!("app://{}/", name)),
+                        source_loader: app_module(specifier, "export default 1;")?.source_loader,
+                    })
+                }),
+            )) as Box<ModuleResolver>
+        };
+        let failing = Box::new(FunctionModuleResolver::new(
+            "app",
+            Box::new(|_: &str, _| Err(FlyError::from("the cache is cold".to_string()))),
+        )) as Box<ModuleResolver>;
+        let manager = |strategy, resolvers| {
+            StandardModuleResolverManager::with_options(
+                resolvers,
+                None,
+                ResolverOptions {
+                    strategy,
+                    ..ResolverOptions::default()
+                },
+            )
+            .with_tracing(true)
+        };
+
+        let racing = manager(
+            ResolutionStrategy::Race,
+            vec![mirror("slow", 500), failing, mirror("fast", 10)],
+        );
+        let started = Instant::now();
+        let module = racing
+            .resolve_blocking("app://lib.js".to_string(), None)
+            .unwrap();
+        assert_eq!("app://fast/lib.js", module.origin_url);
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert!(racing.traces()[0].steps.iter().any(|step| match step {
+            TraceStep::ResolverFailed { error, .. } => error == "the cache is cold",
+            _ => false,
+        }));
+
+        let module = manager(
+            ResolutionStrategy::Sequential,
+            vec![mirror("slow", 50), mirror("fast", 10)],
+        )
+        .resolve_blocking("app://lib.js".to_string(), None)
+        .unwrap();
+        assert_eq!("app://slow/lib.js", module.origin_url);
+    }
+
+    #[test]
+    fn test_resolution_errors_can_be_matched() {
+        struct Unreadable;
+        impl SourceLoader for Unreadable {
+            fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+                Err(FlyError::from("unreadable".to_string()))
+            }
+        }
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(|specifier: &str, _| {
+                    if specifier.ends_with("missing.js") {
+                        return Err(new(ErrorKind::NotFound, "no such module".to_string()));
+                    }
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(Unreadable),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        let err = |specifier: &str| {
+            manager
+                .resolve_blocking(specifier.to_string(), None)
+                .err()
+                .unwrap()
+        };
+
+        let missing = err("app://missing.js");
+        assert_eq!(ErrorKind::ModuleNotFound, missing.kind());
+        match missing.resolution_error() {
+            Some(ModuleResolutionError::NotFound {
+                specifier,
+                attempts,
+                ..
+            }) => {
+                assert_eq!("app://missing.js", specifier);
+                assert_eq!(1, attempts.len());
+                assert_eq!("function", attempts[0].resolver.kind);
+                assert_eq!(ErrorKind::NotFound, attempts[0].error.kind());
+            }
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(
+            ErrorKind::NoResolverForProtocol,
+            err("gopher://host/a.js").kind()
+        );
+        assert_eq!(ErrorKind::InvalidSpecifier, err("app://[::1").kind());
+        let unreadable = err("app://main.js");
+        assert_eq!(ErrorKind::ModuleLoadFailed, unreadable.kind());
+        match unreadable.resolution_error() {
+            Some(ModuleResolutionError::LoaderFailed { origin_url, .. }) => {
+                assert_eq!("app://main.js", origin_url)
+            }
+            other => panic!("{:?}", other),
+        }
+
+        manager
+            .tighten_policy(ResolverPolicy::allow_protocols(vec!["file"]))
+            .unwrap();
+        assert_eq!(ErrorKind::PolicyDenied, err("app://main.js").kind());
+    }
+
+    #[test]
+    fn test_cleared_modules_reload_from_disk() {
+        let dir = app_dir();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+            Some(resolver(&dir).default_working_url),
+        )
+        .with_resolution_cache(ResolutionCache::new())
+        .with_shared_cache(SharedSourceCache::new(DEFAULT_SHARED_CACHE_BYTES));
+        let load = || {
+            manager
+                .resolve_blocking("./util.ts".to_string(), None)
+                .unwrap()
+        };
+        let util = load();
+        assert_eq!("export default 1;", &*util.loaded_source.source);
+
+        std::fs::write(dir.path().join("util.ts"), "export default 2;").unwrap();
+        assert_eq!("export default 1;", &*load().loaded_source.source);
+        // the resolution cache entry and the shared source
+        assert_eq!(
+            2,
+            ModuleResolverManager::clear_module_cache(&manager, Some(&util.origin_url)).unwrap()
+        );
+        assert_eq!("export default 2;", &*load().loaded_source.source);
+
+        assert_eq!(
+            0,
+            manager
+                .clear_module_cache(Some("file:///elsewhere/a.ts"))
+                .unwrap()
+        );
+        assert_eq!(1, manager.clear_module_cache(None).unwrap());
+        assert_eq!(0, manager.clear_module_cache(None).unwrap());
+    }
+
+    #[test]
+    fn test_modules_and_graphs_over_the_limits_are_refused() {
+        let dir = app_dir();
+        for (name, source) in &[
+            ("main.ts", "import './a.ts';"),
+            ("a.ts", "import './b.ts';"),
+            ("b.ts", "import './c.ts';"),
+            ("c.ts", "export default 1;"),
+            ("big.ts", "// padding\nexport default 1;"),
+        ] {
+            std::fs::write(dir.path().join(name), source).unwrap();
+        }
+        let manager = |limits: ModuleLimits| {
+            StandardModuleResolverManager::new(
+                vec![Box::new(resolver(&dir)) as Box<ModuleResolver>],
+                Some(resolver(&dir).default_working_url),
+            )
+            .with_limits(limits)
+        };
+        let url = |name: &str| {
+            url::Url::from_file_path(dir.path().join(name))
+                .unwrap()
+                .into_string()
+        };
+        let import = |manager: &StandardModuleResolverManager, name: &str, referer: &str| {
+            manager.resolve_blocking(
+                format!("./{}", name),
+                Some(RefererInfo {
+                    origin_url: url(referer),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+        };
+        let limit_of = |err: FlyError| match err.resolution_error() {
+            Some(ModuleResolutionError::LimitExceeded {
+                origin_url,
+                limit,
+                max,
+                actual,
+            }) => (origin_url.clone(), *limit, *max, *actual),
+            other => panic!("{:?}", other),
+        };
+
+        // sources over max_module_bytes fail to load once they're read
+        let bytes = manager(ModuleLimits {
+            max_module_bytes: 20,
+            ..ModuleLimits::default()
+        });
+        assert!(bytes.resolve_entry_point("./c.ts".to_string()).is_ok());
+        let err = bytes
+            .resolve_entry_point("./big.ts".to_string())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ModuleLimitExceeded, err.kind());
+        assert_eq!(
+            (url("big.ts"), ModuleLimit::ModuleBytes, 20, 28),
+            limit_of(err)
+        );
+
+        // the entry point is 0 deep, c.ts 3
+        let deep = manager(ModuleLimits {
+            max_graph_depth: 2,
+            ..ModuleLimits::default()
+        });
+        deep.resolve_entry_point("./main.ts".to_string()).unwrap();
+        import(&deep, "a.ts", "main.ts").unwrap();
+        import(&deep, "b.ts", "a.ts").unwrap();
+        let err = import(&deep, "c.ts", "b.ts").err().unwrap();
+        assert!(
+            err.to_string()
+                .ends_with("c.ts: it is 3 imports deep, over the max_graph_depth limit of 2"),
+            "{}",
+            err
+        );
+        // an import of a module already loaded is no deeper than it was
+        import(&deep, "b.ts", "b.ts").unwrap();
+
+        let many = manager(ModuleLimits {
+            max_total_modules: 3,
+            ..ModuleLimits::default()
+        });
+        many.resolve_entry_point("./main.ts".to_string()).unwrap();
+        import(&many, "a.ts", "main.ts").unwrap();
+        import(&many, "b.ts", "a.ts").unwrap();
+        let err = import(&many, "c.ts", "b.ts").err().unwrap();
+        assert_eq!(
+            (url("c.ts"), ModuleLimit::TotalModules, 3, 4),
+            limit_of(err)
+        );
+        assert_eq!(3, many.get_graph().module_count());
+    }
+
+    #[test]
+    fn test_clones_share_one_resolver_stack_across_threads() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counting = calls.clone();
+        let cache = ResolutionCache::new();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, _| {
+                    counting.fetch_add(1, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(5));
+                    app_module(specifier, "export default 1;")
+                }),
+            )) as Box<ModuleResolver>],
+            Some("app://".to_string()),
+        )
+        .with_resolution_cache(cache.clone());
+
+        let runtimes: Vec<_> = (0..8)
+            .map(|runtime| {
+                let manager = manager.clone();
+                std::thread::spawn(move || {
+                    for i in 0..4 {
+                        let specifier = format!("app://lib{}.js", (runtime + i) % 4);
+                        let module = manager.resolve_blocking(specifier.clone(), None).unwrap();
+                        assert_eq!(specifier, module.origin_url);
+                    }
+                    manager.get_graph().module_count()
+                })
+            })
+            .collect();
+        for runtime in runtimes {
+            // each runtime's graph has only what it loaded
+            assert_eq!(4, runtime.join().unwrap());
+        }
+        // cached once between all of them
+        assert_eq!(4, cache.stats().modules);
+        let resolved = calls.load(Ordering::SeqCst);
+        manager
+            .resolve_blocking("app://lib0.js".to_string(), None)
+            .unwrap();
+        assert_eq!(resolved, calls.load(Ordering::SeqCst));
+        assert_eq!(1, manager.get_graph().module_count());
+
+        // resolvers added to a clone stay its own
+        let mut clone = manager.clone();
+        clone
+            .register_resolver(Box::new(
+                StaticModuleResolver::new("https").insert("https://cdn.test/a.js", "export {};"),
+            ))
+            .unwrap();
+        let kind = |manager: &StandardModuleResolverManager| {
+            manager
+                .resolve_blocking("https://cdn.test/b.js".to_string(), None)
+                .err()
+                .unwrap()
+                .kind()
+        };
+        assert_eq!(ErrorKind::ModuleNotFound, kind(&clone));
+        assert_eq!(ErrorKind::NoResolverForProtocol, kind(&manager));
+    }
+
+    #[test]
+    fn test_concurrent_imports_of_a_module_resolve_it_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counting = calls.clone();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, _| {
+                    // the first one fails
+                    if counting.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Err(FlyError::from("app server unavailable".to_string()));
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                    app_module(specifier, "export default 1;")
+                }),
+            )) as Box<ModuleResolver>],
+            Some("app:///".to_string()),
+        );
+        let imports = |manager: &StandardModuleResolverManager| {
+            let imports: Vec<_> = (0..10)
+                .map(|_| manager.resolve_module("app:///lib.js".to_string(), None))
+                .collect();
+            future::join_all(imports.into_iter().map(|import| import.then(Ok::<_, ()>)))
+                .wait()
+                .unwrap()
+        };
+
+        // everyone waiting gets the failure...
+        for result in imports(&manager) {
+            let err = result.err().unwrap();
+            assert_eq!(ErrorKind::ModuleNotFound, err.kind());
+            assert!(err.resolution_error().is_some());
+        }
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        // ...which isn't kept, so trying again resolves it, once again
+        for result in imports(&manager.clone()) {
+            assert_eq!("app:///lib.js", result.unwrap().origin_url);
+        }
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        assert_eq!(0, manager.flights.in_flight());
+    }
+
+    #[test]
+    fn test_imports_asserting_other_types_dont_join() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counting = calls.clone();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, _| {
+                    counting.fetch_add(1, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(5));
+                    app_module(specifier, "{\"a\": 1}")
+                }),
+            )) as Box<ModuleResolver>],
+            Some("app:///".to_string()),
+        );
+        let import = |options: ImportOptions| {
+            manager.resolve_import("app:///data".to_string(), None, options)
+        };
+        let json = || ImportOptions::with_type(ImportType::Json);
+
+        let (a, b) = import(json()).join(import(json())).wait().unwrap();
+        assert_eq!(ModuleKind::Json, a.loaded_source.kind);
+        assert_eq!(ModuleKind::Json, b.loaded_source.kind);
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+
+        let (typed, plain) = import(json())
+            .join(import(ImportOptions::default()))
+            .wait()
+            .unwrap();
+        assert_eq!(ModuleKind::Json, typed.loaded_source.kind);
+        assert_eq!(ModuleKind::JavaScript, plain.loaded_source.kind);
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_one_waiters_deadline_leaves_the_others_waiting() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, referer_info: Option<RefererInfo>| {
+                    std::thread::sleep(Duration::from_millis(100));
+                    // what resolving further modules under the deadline would run into
+                    match referer_info.and_then(|r| r.deadline) {
+                        Some(ref deadline) if deadline.is_expired() => Err(deadline.exceeded()),
+                        _ => app_module(specifier, "export default 1;"),
+                    }
+                }),
+            )) as Box<ModuleResolver>],
+            Some("app:///".to_string()),
+        );
+        let referer = |deadline: Option<ResolutionDeadline>| RefererInfo {
+            origin_url: "app:///main.js".to_string(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline,
+        };
+        let deadline = ResolutionDeadline::new(Duration::from_millis(20));
+        let hurried = manager.resolve_module("./lib.js".to_string(), Some(referer(Some(deadline))));
+        let patient = manager.resolve_module("./lib.js".to_string(), Some(referer(None)));
+        let (hurried, patient) = hurried
+            .then(Ok::<_, ()>)
+            .join(patient.then(Ok::<_, ()>))
+            .wait()
+            .unwrap();
+        assert_eq!(ErrorKind::DeadlineExceeded, hurried.err().unwrap().kind());
+        assert_eq!("app:///lib.js", patient.unwrap().origin_url);
+    }
+}
@@ -0,0 +1,108 @@
+use super::RemoteMediaType;
+
+/**
+ * What a module was before its loader made it loadable. Everything but `Wasm` arrives as a
+ * JavaScript module, `source` being base64 for wasm: a `Json` module's source exports the
+ * parsed document, a `TypeScript` one's is already compiled once the manager has it.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleKind {
+    JavaScript,
+    TypeScript,
+    /** Data: JSON, JSON5, TOML and YAML documents. */
+    Json,
+    Wasm,
+    /** A text asset, a GraphQL document or a value like an environment variable. */
+    Text,
+    /** A binary asset. */
+    Bytes,
+}
+
+impl ModuleKind {
+    /** The kind of a module served as `media_type`, `None` when that doesn't say. */
+    pub fn of_media_type(media_type: RemoteMediaType) -> Option<Self> {
+        match media_type {
+            RemoteMediaType::JavaScript => Some(ModuleKind::JavaScript),
+            RemoteMediaType::TypeScript(_) => Some(ModuleKind::TypeScript),
+            RemoteMediaType::Json => Some(ModuleKind::Json),
+            RemoteMediaType::Wasm => Some(ModuleKind::Wasm),
+            RemoteMediaType::Unknown => None,
+        }
+    }
+
+    /** The kind of a module only known to be wasm or not, as caches from before kinds have it. */
+    pub fn from_is_wasm(is_wasm: bool) -> Self {
+        if is_wasm {
+            ModuleKind::Wasm
+        } else {
+            ModuleKind::JavaScript
+        }
+    }
+
+    pub fn is_wasm(self) -> bool {
+        self == ModuleKind::Wasm
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModuleKind::JavaScript => "javascript",
+            ModuleKind::TypeScript => "typescript",
+            ModuleKind::Json => "json",
+            ModuleKind::Wasm => "wasm",
+            ModuleKind::Text => "text",
+            ModuleKind::Bytes => "bytes",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        DataFormats, EnvVarResolver, LocalDiskRawLoader, ModuleResolver, YamlLimits,
+    };
+
+    #[test]
+    fn test_loaders_report_their_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let formats = DataFormats::default()
+            .with_yaml(YamlLimits::default())
+            .with_json5();
+        let load = |name: &str, contents: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            let origin_url = format!("file://{}", path.display());
+            let raw = Box::new(LocalDiskRawLoader::new(path, None));
+            formats
+                .loader_for(&origin_url, raw)
+                .load_source()
+                .unwrap()
+                .kind
+        };
+        assert_eq!(
+            ModuleKind::JavaScript,
+            load("main.js", b"export default 1;")
+        );
+        assert_eq!(
+            ModuleKind::JavaScript,
+            load("legacy.cjs", b"module.exports = 1;")
+        );
+        assert_eq!(ModuleKind::Json, load("config.json", br#"{"a": 1}"#));
+        assert_eq!(ModuleKind::Json, load("config.json5", b"{a: 1}"));
+        assert_eq!(ModuleKind::Json, load("config.toml", b"a = 1"));
+        assert_eq!(ModuleKind::Json, load("config.yaml", b"a: 1"));
+        assert_eq!(ModuleKind::Text, load("queries.graphql", b"query A { a }"));
+        assert_eq!(ModuleKind::Text, load("styles.css", b"body {}"));
+        assert_eq!(ModuleKind::Bytes, load("logo.png", b"\x89PNG"));
+
+        std::env::set_var("FLY_TEST_KIND_PORT", "8080");
+        let env = EnvVarResolver::new(vec!["FLY_TEST_KIND_PORT".to_string()])
+            .resolve_module("env://FLY_TEST_KIND_PORT", None)
+            .unwrap();
+        assert_eq!(
+            ModuleKind::Text,
+            env.source_loader.load_source().unwrap().kind
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use crate::errors::*;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::{self, Future};
+
+use super::{
+    parse_url, LoadedModuleFuture, ModuleCacheState, ModuleResolutionError, ModuleResolverManager,
+    ModuleSourceData, RefererInfo, ResolvedModuleInfo,
+};
+
+/**
+ * One app's own resolvers, and the origins its modules are under.
+ */
+struct Tenant {
+    name: String,
+    roots: Vec<String>,
+    manager: Arc<ModuleResolverManager>,
+}
+
+impl Tenant {
+    fn owns(&self, origin_url: &str) -> bool {
+        self.roots.iter().any(|root| is_under(root, origin_url))
+    }
+}
+
+/**
+ * `root` without a trailing `**`, which only says that everything under it is meant.
+ */
+fn normalize_root(root: &str) -> String {
+    root.trim_end_matches("**").to_string()
+}
+
+/**
+ * Whether `origin_url` is `root` or under it: under `file:///apps/a`, `file:///apps/a/x.js`
+ * is, `file:///apps/ab/x.js` isn't.
+ */
+fn is_under(root: &str, origin_url: &str) -> bool {
+    if root.ends_with('/') || root.ends_with(':') {
+        return origin_url.starts_with(root);
+    }
+    origin_url == root
+        || (origin_url.starts_with(root) && origin_url[root.len()..].starts_with('/'))
+}
+
+/**
+ * Hosts several apps in one process, each with its own resolvers, so one app's `secrets:`
+ * (or any other) resolver is never reachable from another's code. Each tenant is a manager
+ * and the origins its modules are under, like `file:///apps/a/` or `app-a://`; an import
+ * goes to the manager of the tenant its referer is under, and to the shared manager when
+ * that tenant has no resolver for the protocol, or when the referer is under no tenant,
+ * like a module the shared manager served.
+ *
+ * An import from one tenant's module that resolves under another tenant fails with
+ * `PolicyDenied`, as does one from a shared module into any tenant. Entry points have no
+ * referer: they go to the tenant they're under, if any.
+ */
+pub struct MultiTenantResolverManager {
+    tenants: Vec<Arc<Tenant>>,
+    shared: Arc<ModuleResolverManager>,
+}
+
+impl MultiTenantResolverManager {
+    pub fn new(shared: Box<ModuleResolverManager>) -> Self {
+        Self {
+            tenants: vec![],
+            shared: Arc::from(shared),
+        }
+    }
+
+    /**
+     * Adds tenant `name`, whose modules are those under `roots` and resolve through
+     * `manager`. No two tenants may share a name, or have a root under the other's.
+     */
+    pub fn with_tenant(
+        mut self,
+        name: &str,
+        roots: &[&str],
+        manager: Box<ModuleResolverManager>,
+    ) -> FlyResult<Self> {
+        let roots: Vec<String> = roots.iter().map(|root| normalize_root(root)).collect();
+        if roots.is_empty() {
+            return Err(FlyError::from(format!(
+                "Tenant {} needs at least one root",
+                name
+            )));
+        }
+        for tenant in &self.tenants {
+            if tenant.name == name {
+                return Err(FlyError::from(format!("Tenant {} is added twice", name)));
+            }
+            let overlap = roots.iter().find(|root| {
+                tenant
+                    .roots
+                    .iter()
+                    .any(|other| is_under(other, root) || is_under(root, other))
+            });
+            if let Some(root) = overlap {
+                return Err(FlyError::from(format!(
+                    "Tenant {}'s root {} overlaps tenant {}'s",
+                    name, root, tenant.name
+                )));
+            }
+        }
+        self.tenants.push(Arc::new(Tenant {
+            name: name.to_string(),
+            roots,
+            manager: Arc::from(manager),
+        }));
+        Ok(self)
+    }
+
+    /** The name of the tenant `origin_url` is under, if any. */
+    pub fn tenant_of(&self, origin_url: &str) -> Option<&str> {
+        tenant_of(&self.tenants, origin_url).map(|tenant| tenant.name.as_str())
+    }
+
+    /**
+     * The tenant to resolve `specifier` with, `None` for the shared manager, once it's
+     * clear the import doesn't cross into another tenant.
+     */
+    fn route(
+        &self,
+        specifier: &str,
+        referer_info: Option<&RefererInfo>,
+    ) -> FlyResult<Option<Arc<Tenant>>> {
+        let referer = referer_info.map(|info| info.origin_url.as_str());
+        let own = referer.and_then(|referer| tenant_of(&self.tenants, referer).cloned());
+        // a specifier that doesn't parse is for the resolvers to reject
+        let target = parse_url(specifier, referer.unwrap_or("file:///"))
+            .ok()
+            .and_then(|url| tenant_of(&self.tenants, url.as_str()).cloned());
+        match (referer, own, target) {
+            (None, _, target) => Ok(target),
+            (Some(_), own, Some(target)) => {
+                check_tenant(&own, &target, specifier, referer)?;
+                Ok(own)
+            }
+            (Some(_), own, None) => Ok(own),
+        }
+    }
+
+    /**
+     * `resolve` with the manager `tenant` gets, falling back to the shared one for any
+     * protocol it doesn't serve.
+     */
+    fn with_fallback<T, F>(&self, tenant: &Option<Arc<Tenant>>, resolve: F) -> FlyResult<T>
+    where
+        F: Fn(&ModuleResolverManager) -> FlyResult<T>,
+    {
+        match tenant {
+            Some(tenant) => match resolve(&*tenant.manager) {
+                Err(ref e) if e.kind() == ErrorKind::NoResolverForProtocol => {
+                    resolve(&*self.shared)
+                }
+                result => result,
+            },
+            None => resolve(&*self.shared),
+        }
+    }
+
+    fn manager_of(&self, origin_url: &str) -> &ModuleResolverManager {
+        match tenant_of(&self.tenants, origin_url) {
+            Some(tenant) => &*tenant.manager,
+            None => &*self.shared,
+        }
+    }
+}
+
+fn tenant_of<'a>(tenants: &'a [Arc<Tenant>], origin_url: &str) -> Option<&'a Arc<Tenant>> {
+    tenants.iter().find(|tenant| tenant.owns(origin_url))
+}
+
+/**
+ * Fails unless a module under `target` (`None` for shared) may be imported from one under
+ * `own`.
+ */
+fn check_tenant(
+    own: &Option<Arc<Tenant>>,
+    target: &Arc<Tenant>,
+    specifier: &str,
+    referer: Option<&str>,
+) -> FlyResult<()> {
+    let referer = match (referer, own) {
+        (None, _) => return Ok(()),
+        (Some(_), Some(own)) if Arc::ptr_eq(own, target) => return Ok(()),
+        (Some(referer), _) => referer,
+    };
+    Err(ModuleResolutionError::PolicyDenied {
+        specifier: specifier.to_string(),
+        referer: referer.to_string(),
+        denied: format!("tenant {}'s modules", target.name),
+        policy: match own {
+            Some(own) => format!("tenant {}", own.name),
+            None => "shared".to_string(),
+        },
+    }
+    .into())
+}
+
+impl ModuleResolverManager for MultiTenantResolverManager {
+    fn resolve_module(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> LoadedModuleFuture {
+        let tenant = match self.route(&specifier, referer_info.as_ref()) {
+            Ok(tenant) => tenant,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let shared = self.shared.clone();
+        let tenants = self.tenants.clone();
+        let referer = referer_info.as_ref().map(|info| info.origin_url.clone());
+        let (fallback_specifier, fallback_referer) = (specifier.clone(), referer_info.clone());
+        let resolved = match tenant {
+            Some(ref tenant) => Box::new(
+                tenant
+                    .manager
+                    .resolve_module(specifier.clone(), referer_info)
+                    .or_else(move |e| -> LoadedModuleFuture {
+                        if e.kind() == ErrorKind::NoResolverForProtocol {
+                            shared.resolve_module(fallback_specifier, fallback_referer)
+                        } else {
+                            Box::new(future::err(e))
+                        }
+                    }),
+            ) as LoadedModuleFuture,
+            None => shared.resolve_module(specifier.clone(), referer_info),
+        };
+        // an import map or a redirect may still have led elsewhere
+        Box::new(resolved.and_then(move |module| {
+            if let Some(target) = tenant_of(&tenants, &module.origin_url) {
+                check_tenant(&tenant, target, &specifier, referer.as_deref())?;
+            }
+            Ok(module)
+        }))
+    }
+
+    fn resolve(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let tenant = self.route(&specifier, referer_info.as_ref())?;
+        let data = self.with_fallback(&tenant, |manager| {
+            manager.resolve(specifier.clone(), referer_info.clone())
+        })?;
+        if let Some(target) = tenant_of(&self.tenants, &data.origin_url) {
+            let referer = referer_info.as_ref().map(|info| info.origin_url.as_str());
+            check_tenant(&tenant, target, &specifier, referer)?;
+        }
+        Ok(data)
+    }
+
+    fn resolve_only(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ResolvedModuleInfo> {
+        let tenant = self.route(&specifier, referer_info.as_ref())?;
+        let info = self.with_fallback(&tenant, |manager| {
+            manager.resolve_only(specifier.clone(), referer_info.clone())
+        })?;
+        if let Some(target) = tenant_of(&self.tenants, &info.origin_url) {
+            let referer = referer_info.as_ref().map(|info| info.origin_url.as_str());
+            check_tenant(&tenant, target, &specifier, referer)?;
+        }
+        Ok(info)
+    }
+
+    fn cache_state(
+        &self,
+        specifier: String,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleCacheState> {
+        let tenant = self.route(&specifier, referer_info.as_ref())?;
+        self.with_fallback(&tenant, |manager| {
+            manager.cache_state(specifier.clone(), referer_info.clone())
+        })
+    }
+
+    /**
+     * Clears `origin_url` from the manager it came from, or every manager's whole cache.
+     */
+    fn clear_module_cache(&self, origin_url: Option<&str>) -> FlyResult<usize> {
+        match origin_url {
+            Some(origin_url) => self
+                .manager_of(origin_url)
+                .clear_module_cache(Some(origin_url)),
+            None => self
+                .tenants
+                .iter()
+                .map(|tenant| &*tenant.manager)
+                .chain(std::iter::once(&*self.shared))
+                .map(|manager| manager.clear_module_cache(None))
+                .sum(),
+        }
+    }
+
+    fn store_code_cache(&self, origin_url: &str, source: &str, code_cache: Vec<u8>) {
+        self.manager_of(origin_url)
+            .store_code_cache(origin_url, source, code_cache)
+    }
+
+    fn store_compiled_wasm(&self, origin_url: &str, wasm: &[u8], compiled: &[u8]) {
+        self.manager_of(origin_url)
+            .store_compiled_wasm(origin_url, wasm, compiled)
+    }
+
+    /**
+     * The shared manager's. Tenants' managers stay within whatever deadline their imports
+     * carry, whatever their own options say.
+     */
+    fn total_deadline(&self) -> Option<Duration> {
+        self.shared.total_deadline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        JsonSecretsResolver, ModuleResolver, StandardModuleResolverManager, StaticModuleResolver,
+    };
+
+    fn referer(origin_url: &str) -> Option<RefererInfo> {
+        Some(RefererInfo {
+            origin_url: origin_url.to_string(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        })
+    }
+
+    fn tenant(root: &str, password: &str) -> Box<ModuleResolverManager> {
+        Box::new(StandardModuleResolverManager::new(
+            vec![
+                Box::new(
+                    StaticModuleResolver::new("file")
+                        .insert(&format!("{}index.js", root), "export default 1;")
+                        .insert(&format!("{}util.js", root), "export default 2;"),
+                ) as Box<ModuleResolver>,
+                Box::new(JsonSecretsResolver::new(
+                    serde_json::json!({"db": {"password": password}}),
+                )),
+            ],
+            Some(root.to_string()),
+        ))
+    }
+
+    fn manager() -> MultiTenantResolverManager {
+        let shared = StandardModuleResolverManager::new(
+            vec![Box::new(
+                StaticModuleResolver::new("https")
+                    .insert("https://cdn.test/lib.js", "export default 3;"),
+            ) as Box<ModuleResolver>],
+            None,
+        );
+        MultiTenantResolverManager::new(Box::new(shared))
+            .with_tenant(
+                "a",
+                &["file:///apps/a/**"],
+                tenant("file:///apps/a/", "a-s3cret"),
+            )
+            .unwrap()
+            .with_tenant(
+                "b",
+                &["file:///apps/b/"],
+                tenant("file:///apps/b/", "b-s3cret"),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tenants_only_see_their_own_resolvers() {
+        let manager = manager();
+        for (index, password) in &[
+            ("file:///apps/a/index.js", "a-s3cret"),
+            ("file:///apps/b/index.js", "b-s3cret"),
+        ] {
+            let entry = manager.resolve_entry_point(index.to_string()).unwrap();
+            assert_eq!(*index, entry.origin_url);
+            let secret = manager
+                .resolve_blocking(
+                    "secrets:///db/password".to_string(),
+                    referer(&entry.origin_url),
+                )
+                .unwrap();
+            assert!(
+                secret.loaded_source.source.contains(password),
+                "{}",
+                secret.loaded_source.source
+            );
+            let util = manager
+                .resolve_blocking("./util.js".to_string(), referer(&entry.origin_url))
+                .unwrap();
+            assert_eq!(index.replace("index", "util"), util.origin_url);
+            // protocols the tenant doesn't serve go to the shared resolvers
+            let lib = manager
+                .resolve_blocking(
+                    "https://cdn.test/lib.js".to_string(),
+                    referer(&entry.origin_url),
+                )
+                .unwrap();
+            assert_eq!("https://cdn.test/lib.js", lib.origin_url);
+        }
+
+        // shared modules reach no tenant's secrets
+        let err = manager
+            .resolve_blocking(
+                "secrets:///db/password".to_string(),
+                referer("https://cdn.test/lib.js"),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::NoResolverForProtocol, err.kind());
+    }
+
+    #[test]
+    fn test_cross_tenant_imports_are_denied() {
+        let manager = manager();
+        let err = manager
+            .resolve_blocking(
+                "file:///apps/b/index.js".to_string(),
+                referer("file:///apps/a/index.js"),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PolicyDenied, err.kind());
+        assert_eq!(
+            "Could not resolve file:///apps/b/index.js from file:///apps/a/index.js: tenant b's modules is not allowed by policy tenant a",
+            err.to_string()
+        );
+        let err = manager
+            .resolve_blocking(
+                "../b/util.js".to_string(),
+                referer("file:///apps/a/index.js"),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PolicyDenied, err.kind());
+        let err = manager
+            .resolve(
+                "file:///apps/a/index.js".to_string(),
+                referer("https://cdn.test/lib.js"),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PolicyDenied, err.kind());
+
+        assert_eq!(Some("a"), manager.tenant_of("file:///apps/a/index.js"));
+        assert_eq!(None, manager.tenant_of("file:///apps/ab/index.js"));
+        let overlapping =
+            manager.with_tenant("c", &["file:///apps/"], tenant("file:///apps/", "c-s3cret"));
+        assert_eq!(
+            "Tenant c's root file:///apps/ overlaps tenant a's",
+            overlapping.err().unwrap().to_string()
+        );
+    }
+}
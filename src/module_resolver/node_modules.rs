@@ -0,0 +1,492 @@
+use crate::errors::*;
+
+use std::path::{Path, PathBuf};
+
+use super::yaml_loader::{load_yaml, YamlLimits};
+use super::{
+    file_origin_url, DataFormats, LocalDiskRawLoader, ModuleResolver, ModuleSourceData,
+    RefererInfo, ResolverDescription,
+};
+
+/**
+ * Tried, in order, after a subpath as given and before a package's own entry point.
+ */
+const FILE_SUFFIXES: &[&str] = &[".js", ".ts", ".json", "/index.js", "/index.ts"];
+
+/**
+ * Resolves bare package imports like `react` or `@scope/pkg/sub` from `node_modules`,
+ * walking up from the importing file the way Node does.
+ *
+ * Packages are followed through symlinks and identified by their realpath, which is what
+ * makes pnpm layouts work: `node_modules/<pkg>` links into the `.pnpm` virtual store, where
+ * each package's own dependencies (peers included) sit next to it, so resolving from the
+ * realpath finds the versions that package was installed with, and the same version is
+ * loaded once whichever dependent imports it. Realpaths must fall under one of the allowed
+ * roots: the project root, the virtual store named in `node_modules/.modules.yaml` and any
+ * added with `with_allowed_root`.
+ */
+pub struct NodeModulesResolver {
+    root: PathBuf,
+    allowed_roots: Vec<PathBuf>,
+    data_formats: DataFormats,
+}
+
+impl NodeModulesResolver {
+    pub fn new(root: &Path) -> FlyResult<Self> {
+        let root = root.canonicalize()?;
+        let mut allowed_roots = vec![root.clone()];
+        if let Some(store) = virtual_store_dir(&root)? {
+            if !store.starts_with(&root) {
+                allowed_roots.push(store);
+            }
+        }
+        Ok(Self {
+            root,
+            allowed_roots,
+            data_formats: DataFormats::default(),
+        })
+    }
+
+    /**
+     * Also allow packages whose realpath is under `root`, e.g. a store shared between
+     * projects.
+     */
+    pub fn with_allowed_root(mut self, root: &Path) -> FlyResult<Self> {
+        self.allowed_roots.push(root.canonicalize()?);
+        Ok(self)
+    }
+
+    pub fn with_data_formats(mut self, data_formats: DataFormats) -> Self {
+        self.data_formats = data_formats;
+        self
+    }
+
+    fn is_allowed(&self, path: &Path) -> bool {
+        self.allowed_roots.iter().any(|root| path.starts_with(root))
+    }
+
+    /**
+     * The realpath of the first `node_modules/<name>` found walking up from `dir`, never
+     * looking above an allowed root.
+     */
+    fn find_package(&self, name: &str, dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .take_while(|ancestor| self.is_allowed(ancestor))
+            .map(|ancestor| ancestor.join("node_modules").join(name))
+            .find(|candidate| {
+                trace!("trying package {}", candidate.display());
+                candidate.is_dir()
+            })
+            .and_then(|candidate| candidate.canonicalize().ok())
+    }
+}
+
+/**
+ * The file a package's `package.json` says to import it by, `module` before `main`, else its
+ * `index`.
+ */
+pub(super) fn entry_point(package_dir: &Path) -> FlyResult<Option<PathBuf>> {
+    let manifest_path = package_dir.join("package.json");
+    if manifest_path.is_file() {
+        let source = std::fs::read_to_string(&manifest_path)?;
+        let manifest: serde_json::Value = serde_json::from_str(&source)
+            .map_err(|e| FlyError::from(format!("invalid {}: {}", manifest_path.display(), e)))?;
+        for field in &["module", "main"] {
+            if let Some(entry) = manifest.get(*field).and_then(|v| v.as_str()) {
+                if let Some(path) = probe(&package_dir.join(entry)) {
+                    return Ok(Some(path));
+                }
+            }
+        }
+    }
+    Ok(probe(&package_dir.join("index")))
+}
+
+/**
+ * The nearest of `dir` and its ancestors with a `node_modules` directory, i.e. the root of
+ * the project `dir` is in, for when none was configured.
+ */
+pub fn discover_node_modules_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join("node_modules").is_dir())
+        .map(|ancestor| ancestor.to_path_buf())
+}
+
+/**
+ * The virtual store pnpm recorded for this project, if it's a pnpm install.
+ */
+fn virtual_store_dir(root: &Path) -> FlyResult<Option<PathBuf>> {
+    let modules_yaml = root.join("node_modules").join(".modules.yaml");
+    if !modules_yaml.is_file() {
+        return Ok(None);
+    }
+    let source = std::fs::read_to_string(&modules_yaml)?;
+    let label = modules_yaml.display().to_string();
+    let documents = load_yaml(&label, &source, YamlLimits::default())?;
+    let store = documents
+        .get(0)
+        .and_then(|d| d["virtualStoreDir"].as_str())
+        .unwrap_or(".pnpm");
+    // relative to node_modules
+    Ok(root.join("node_modules").join(store).canonicalize().ok())
+}
+
+pub(super) fn probe(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let path = path.to_str()?;
+    FILE_SUFFIXES
+        .iter()
+        .map(|suffix| PathBuf::from(format!("{}{}", path.trim_end_matches('/'), suffix)))
+        .find(|candidate| candidate.is_file())
+}
+
+/**
+ * `name` and subpath of a bare specifier, `None` if it's not a package import.
+ */
+fn split_package(specifier: &str) -> Option<(&str, &str)> {
+    let name_parts = if specifier.starts_with('@') { 2 } else { 1 };
+    let end = specifier
+        .match_indices('/')
+        .nth(name_parts - 1)
+        .map(|(i, _)| i)
+        .unwrap_or_else(|| specifier.len());
+    let name = &specifier[..end];
+    if name.is_empty() || name.starts_with('.') || (name_parts == 2 && !name.contains('/')) {
+        return None;
+    }
+    Some((name, specifier[end..].trim_start_matches('/')))
+}
+
+impl ModuleResolver for NodeModulesResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let (referer_origin_url, referer_dir) = match referer_info {
+            Some(v) => {
+                let dir = url::Url::parse(&v.origin_url)
+                    .ok()
+                    .and_then(|url| url.to_file_path().ok())
+                    .and_then(|path| path.canonicalize().ok())
+                    .and_then(|path| path.parent().map(|p| p.to_path_buf()));
+                (v.origin_url, dir.unwrap_or_else(|| self.root.clone()))
+            }
+            None => ("<entry point>".to_string(), self.root.clone()),
+        };
+        let (name, subpath) = match split_package(module_specifier) {
+            Some(parts) if !module_specifier.contains(':') => parts,
+            _ => {
+                return Err(FlyError::from(format!(
+                    "Cannot import {} from {}: not a package import",
+                    module_specifier, referer_origin_url
+                )))
+            }
+        };
+        let package_dir = self.find_package(name, &referer_dir).ok_or_else(|| {
+            FlyError::from(format!(
+                "Cannot import {} from {}: package {} is not installed in any node_modules above {}",
+                module_specifier,
+                referer_origin_url,
+                name,
+                referer_dir.display()
+            ))
+        })?;
+        let path = if subpath.is_empty() {
+            entry_point(&package_dir)?
+        } else {
+            probe(&package_dir.join(subpath))
+        };
+        // a package may link single files elsewhere too
+        let path = match path.and_then(|p| p.canonicalize().ok()) {
+            Some(path) => path,
+            None => {
+                return Err(FlyError::from(format!(
+                    "Cannot import {} from {}: no such file in {}",
+                    module_specifier,
+                    referer_origin_url,
+                    package_dir.display()
+                )))
+            }
+        };
+        if !self.is_allowed(&path) {
+            return Err(FlyError::from(format!(
+                "Cannot import {} from {}: it links to {}, outside the allowed roots",
+                module_specifier,
+                referer_origin_url,
+                path.display()
+            )));
+        }
+        let origin_url = file_origin_url(&path)?;
+        let source_loader = self
+            .data_formats
+            .loader_for(&origin_url, Box::new(LocalDiskRawLoader::new(path, None)));
+        Ok(ModuleSourceData {
+            origin_url,
+            source_loader,
+        })
+    }
+    fn get_protocol(&self) -> String {
+        "file".to_string()
+    }
+    fn describe(&self) -> ResolverDescription {
+        let allowed_roots: Vec<String> = self
+            .allowed_roots
+            .iter()
+            .map(|root| root.display().to_string())
+            .collect();
+        ResolverDescription::new("node_modules", "file")
+            .named(&self.name())
+            .with("root", &self.root.display().to_string())
+            .with("allowed_roots", &allowed_roots.join(", "))
+    }
+    fn handles_bare_specifier(&self, specifier: &str) -> bool {
+        // `pkg@1.2.3` is left to the CDN resolver
+        !specifier.trim_start_matches('@').contains('@') && split_package(specifier).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn link(root: &Path, link: &str, target: &str) {
+        let link = root.join(link);
+        std::fs::create_dir_all(link.parent().unwrap()).unwrap();
+        symlink(target, link).unwrap();
+    }
+
+    /**
+     * What `pnpm install` leaves for an app depending on `a` and `c`, which both depend on
+     * `b@2.0.0`, while the app itself has `b@1.0.0`.
+     */
+    fn pnpm_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            "node_modules/.modules.yaml",
+            "virtualStoreDir: .pnpm\n",
+        );
+        for (package, version) in &[
+            ("a", "1.0.0"),
+            ("b", "1.0.0"),
+            ("b", "2.0.0"),
+            ("c", "1.0.0"),
+        ] {
+            let package_dir = format!(
+                "node_modules/.pnpm/{}@{}/node_modules/{}",
+                package, version, package
+            );
+            write(
+                root,
+                &format!("{}/package.json", package_dir),
+                r#"{"main": "lib/index.js"}"#,
+            );
+            write(root, &format!("{}/lib/index.js", package_dir), "");
+        }
+        link(root, "node_modules/a", ".pnpm/a@1.0.0/node_modules/a");
+        link(root, "node_modules/b", ".pnpm/b@1.0.0/node_modules/b");
+        link(root, "node_modules/c", ".pnpm/c@1.0.0/node_modules/c");
+        link(
+            root,
+            "node_modules/.pnpm/a@1.0.0/node_modules/b",
+            "../../b@2.0.0/node_modules/b",
+        );
+        link(
+            root,
+            "node_modules/.pnpm/c@1.0.0/node_modules/b",
+            "../../b@2.0.0/node_modules/b",
+        );
+        write(root, "index.js", "");
+        dir
+    }
+
+    fn resolve(
+        resolver: &NodeModulesResolver,
+        specifier: &str,
+        referer: Option<&str>,
+    ) -> FlyResult<String> {
+        let referer = referer.map(|origin_url| RefererInfo {
+            origin_url: origin_url.to_string(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        });
+        Ok(resolver.resolve_module(specifier, referer)?.origin_url)
+    }
+
+    #[test]
+    fn test_resolves_through_pnpm_symlinks_by_realpath() {
+        let dir = pnpm_tree();
+        let root = dir.path().canonicalize().unwrap();
+        let store = |path: &str| {
+            url::Url::from_file_path(root.join("node_modules/.pnpm").join(path))
+                .unwrap()
+                .into_string()
+        };
+        let resolver = NodeModulesResolver::new(&root).unwrap();
+        let app = url::Url::from_file_path(root.join("index.js"))
+            .unwrap()
+            .into_string();
+        let a = resolve(&resolver, "a", Some(&app)).unwrap();
+        assert_eq!(store("a@1.0.0/node_modules/a/lib/index.js"), a);
+        assert_eq!(
+            store("b@1.0.0/node_modules/b/lib/index.js"),
+            resolve(&resolver, "b", Some(&app)).unwrap()
+        );
+        // a and c get their own b, and the same one
+        let c = resolve(&resolver, "c", Some(&app)).unwrap();
+        let b_from_a = resolve(&resolver, "b", Some(&a)).unwrap();
+        assert_eq!(store("b@2.0.0/node_modules/b/lib/index.js"), b_from_a);
+        assert_eq!(
+            b_from_a,
+            resolve(&resolver, "b/lib/index", Some(&c)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_links_outside_allowed_roots_are_rejected() {
+        let dir = pnpm_tree();
+        let elsewhere = tempfile::tempdir().unwrap();
+        write(elsewhere.path(), "d/index.js", "");
+        link(
+            dir.path(),
+            "node_modules/d",
+            elsewhere.path().join("d").to_str().unwrap(),
+        );
+        let resolver = NodeModulesResolver::new(dir.path()).unwrap();
+        let err = resolve(&resolver, "d", None).err().unwrap();
+        assert!(format!("{}", err).contains("outside the allowed roots"));
+        let resolver = resolver.with_allowed_root(elsewhere.path()).unwrap();
+        assert!(resolve(&resolver, "d", None).is_ok());
+    }
+
+    #[test]
+    fn test_modules_yaml_is_parsed_within_limits() {
+        let dir = pnpm_tree();
+        write(
+            dir.path(),
+            "node_modules/.modules.yaml",
+            "virtualStoreDir: .pnpm\n---\nvirtualStoreDir: /\n",
+        );
+        let err = NodeModulesResolver::new(dir.path()).err().unwrap();
+        assert!(
+            format!("{}", err).ends_with("multiple documents in one file are not supported"),
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_resolves_hoisted_packages_by_their_manifest() {
+        use crate::module_resolver::{
+            LocalDiskModuleResolver, ModuleResolverManager, StandardModuleResolverManager,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        write(
+            &root,
+            "node_modules/lodash/package.json",
+            r#"{"main": "lodash.js", "module": "esm/index"}"#,
+        );
+        write(
+            &root,
+            "node_modules/lodash/esm/index.js",
+            "import './chunk.js';",
+        );
+        write(&root, "node_modules/lodash/esm/chunk.js", "");
+        write(&root, "node_modules/lodash/lodash.js", "");
+        write(
+            &root,
+            "node_modules/@scope/pkg/package.json",
+            r#"{"main": "missing.js"}"#,
+        );
+        write(&root, "node_modules/@scope/pkg/index.js", "");
+        write(&root, "node_modules/@scope/pkg/dist/thing.js", "");
+        write(&root, "src/app/index.js", "");
+        let file = |path: &str| {
+            url::Url::from_file_path(root.join(path))
+                .unwrap()
+                .into_string()
+        };
+        let app = file("src/app/index.js");
+        let resolver = NodeModulesResolver::new(&root).unwrap();
+        // `module` over `main`, and an entry `main` can't find falls back to `index.js`
+        assert_eq!(
+            file("node_modules/@scope/pkg/index.js"),
+            resolve(&resolver, "@scope/pkg", Some(&app)).unwrap()
+        );
+        assert_eq!(
+            file("node_modules/@scope/pkg/dist/thing.js"),
+            resolve(&resolver, "@scope/pkg/dist/thing.js", Some(&app)).unwrap()
+        );
+
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(
+                    LocalDiskModuleResolver::new(Some(
+                        url::Url::from_directory_path(&root).unwrap().into_string(),
+                    ))
+                    .unwrap(),
+                ) as Box<ModuleResolver>,
+                Box::new(resolver),
+            ],
+            None,
+        );
+        let referer = |origin_url: String| RefererInfo {
+            origin_url,
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let lodash = manager
+            .resolve_blocking("lodash".to_string(), Some(referer(app)))
+            .unwrap();
+        assert_eq!(file("node_modules/lodash/esm/index.js"), lodash.origin_url);
+        // relative imports inside the package resolve against where it was found
+        let chunk = manager
+            .resolve_blocking("./chunk.js".to_string(), Some(referer(lodash.origin_url)))
+            .unwrap();
+        assert_eq!(file("node_modules/lodash/esm/chunk.js"), chunk.origin_url);
+
+        assert_eq!(
+            Some(root.clone()),
+            discover_node_modules_root(&root.join("src/app"))
+        );
+    }
+
+    #[test]
+    fn test_bare_specifiers() {
+        let dir = pnpm_tree();
+        let resolver = NodeModulesResolver::new(dir.path()).unwrap();
+        assert!(resolver.handles_bare_specifier("react"));
+        assert!(resolver.handles_bare_specifier("@scope/pkg/sub"));
+        // pinned versions are the CDN's
+        assert!(!resolver.handles_bare_specifier("react@18.2.0"));
+        assert_eq!(
+            Some(("@scope/pkg", "sub/file.js")),
+            split_package("@scope/pkg/sub/file.js")
+        );
+        assert_eq!(None, split_package("@scope"));
+        let err = resolve(&resolver, "missing", None).err().unwrap();
+        assert!(format!("{}", err).contains("package missing is not installed"));
+    }
+}
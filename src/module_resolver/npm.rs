@@ -0,0 +1,604 @@
+use crate::errors::*;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+
+use super::cdn::is_exact_version;
+use super::node_modules::{entry_point, probe};
+use super::{
+    fetch_with_options, parse_url, process_wide_fetcher, ArchiveBundle, ArchiveLimits, DataFormats,
+    FetchOptions, FetchRequest, HttpFetcher, LocalDiskRawLoader, ModuleResolver, ModuleSourceData,
+    PackageSpecifier, RefererInfo, ResolverDescription,
+};
+
+const NPM_PROTOCOL: &str = "npm";
+
+/**
+ * Which registry a `NpmResolver` installs packages from and how. With `offline`, only
+ * packages already extracted into its directory resolve, so a deploy can ship that directory
+ * and never reach the registry.
+ */
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct NpmResolverConfig {
+    pub registry: String,
+    /** Sent to the registry as a bearer token, for private packages. */
+    pub token: Option<String>,
+    pub offline: bool,
+    pub limits: ArchiveLimits,
+}
+
+impl Default for NpmResolverConfig {
+    fn default() -> Self {
+        Self {
+            registry: "https://registry.npmjs.org".to_string(),
+            token: None,
+            offline: false,
+            limits: ArchiveLimits::default(),
+        }
+    }
+}
+
+/** What the registry says a tarball hashes to, strongest first. */
+#[derive(Clone, Debug, PartialEq)]
+enum TarballDigest {
+    Sha512(Vec<u8>),
+    Sha1(Vec<u8>),
+}
+
+impl TarballDigest {
+    /**
+     * The digest a version's `dist` pins its tarball to: the `sha512` of its `integrity`,
+     * else the `sha1` there or in its older `shasum`.
+     */
+    fn of_dist(dist: &serde_json::Value) -> Option<Self> {
+        let integrity = dist.get("integrity").and_then(|v| v.as_str()).unwrap_or("");
+        let sri = |prefix: &str| {
+            integrity
+                .split_whitespace()
+                .find(|hash| hash.starts_with(prefix))
+                .and_then(|hash| base64::decode(&hash[prefix.len()..]).ok())
+        };
+        if let Some(digest) = sri("sha512-") {
+            return Some(TarballDigest::Sha512(digest));
+        }
+        if let Some(digest) = sri("sha1-") {
+            return Some(TarballDigest::Sha1(digest));
+        }
+        let shasum = dist.get("shasum").and_then(|v| v.as_str())?;
+        hex_decode(shasum).map(TarballDigest::Sha1)
+    }
+
+    /** `bytes` hashed the same way, to compare. */
+    fn of_bytes(&self, bytes: &[u8]) -> Self {
+        match self {
+            TarballDigest::Sha512(_) => TarballDigest::Sha512(Sha512::digest(bytes).to_vec()),
+            TarballDigest::Sha1(_) => TarballDigest::Sha1(Sha1::digest(bytes).to_vec()),
+        }
+    }
+}
+
+impl std::fmt::Display for TarballDigest {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TarballDigest::Sha512(digest) => write!(f, "sha512-{}", base64::encode(digest)),
+            TarballDigest::Sha1(digest) => write!(f, "sha1-{}", base64::encode(digest)),
+        }
+    }
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/**
+ * Imports packages from an npm registry: `npm:<package>@<version>[/<path>]` downloads the
+ * version's tarball, checks it against the registry's integrity, extracts it into the
+ * resolver's directory and imports the file `package.json` names (`module`, then `main`),
+ * or the path given. Versions must be exact; ranges and tags aren't resolved.
+ *
+ * Modules are identified as `npm:///<package>@<version>/<file>`, so relative imports from
+ * a package's files resolve inside its extracted tree. A version is extracted once, to a
+ * temporary directory renamed into place, and served from disk from then on; nothing is
+ * fetched for a package already there. Versions the registry doesn't have fail with
+ * `NotFound`, unpublished ones with `PackageYanked` and tarballs that don't match their
+ * integrity with `IntegrityMismatch`.
+ */
+pub struct NpmResolver {
+    config: NpmResolverConfig,
+    dir: PathBuf,
+    fetcher: Option<Arc<HttpFetcher>>,
+    options: FetchOptions,
+    data_formats: DataFormats,
+    // one install at a time in this process; across processes, the rename decides
+    installing: Mutex<()>,
+}
+
+impl NpmResolver {
+    /** Extracts packages under `dir`, e.g. a directory next to the module cache's. */
+    pub fn new(dir: &Path, config: NpmResolverConfig) -> Self {
+        Self {
+            config,
+            dir: dir.to_path_buf(),
+            fetcher: None,
+            options: FetchOptions::default(),
+            data_formats: DataFormats::default(),
+            installing: Mutex::new(()),
+        }
+    }
+
+    /**
+     * Fetch through `fetcher` instead of the process-wide one.
+     */
+    pub fn with_fetcher(mut self, fetcher: Arc<HttpFetcher>) -> Self {
+        self.fetcher = Some(fetcher);
+        self
+    }
+
+    pub fn with_options(mut self, options: FetchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_data_formats(mut self, data_formats: DataFormats) -> Self {
+        self.data_formats = data_formats;
+        self
+    }
+
+    /** Where `name@version` is, or would be, extracted. */
+    pub fn package_dir(&self, name: &str, version: &str) -> PathBuf {
+        self.dir.join(format!("{}@{}", name, version))
+    }
+
+    fn request(&self, url: &str) -> FetchRequest {
+        let request = FetchRequest::get(url);
+        match self.config.token {
+            // only the registry gets the token, not wherever it hosts tarballs
+            Some(ref token) if url.starts_with(self.config.registry.trim_end_matches('/')) => {
+                request.with_header("Authorization", &format!("Bearer {}", token))
+            }
+            _ => request,
+        }
+    }
+
+    fn fetch(&self, request: FetchRequest) -> FlyResult<Vec<u8>> {
+        let fetcher = match self.fetcher {
+            Some(ref fetcher) => fetcher.clone(),
+            None => process_wide_fetcher()?,
+        };
+        let (_, res) = fetch_with_options(&*fetcher, request, &self.options)?;
+        Ok(res.body)
+    }
+
+    /**
+     * The tarball url and digest of `name@version`, from the registry's document for the
+     * package.
+     */
+    fn dist(&self, name: &str, version: &str) -> FlyResult<(String, TarballDigest)> {
+        let url = format!(
+            "{}/{}",
+            self.config.registry.trim_end_matches('/'),
+            name.replace('/', "%2f")
+        );
+        let body = self
+            .fetch(self.request(&url).with_header("Accept", "application/json"))
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => new(
+                    ErrorKind::NotFound,
+                    format!("package {} is not in the registry {}", name, url),
+                ),
+                _ => e,
+            })?;
+        let document: serde_json::Value = serde_json::from_slice(&body)
+            .map_err(|e| FlyError::from(format!("invalid registry document {}: {}", url, e)))?;
+        let time = &document["time"];
+        if time.get("unpublished").is_some() {
+            return Err(new(
+                ErrorKind::PackageYanked,
+                format!("package {} was unpublished from the registry", name),
+            ));
+        }
+        let manifest = match document["versions"].get(version) {
+            Some(manifest) => manifest,
+            // the registry keeps the publish times of versions it took down
+            None if time.get(version).is_some() => {
+                return Err(new(
+                    ErrorKind::PackageYanked,
+                    format!("{}@{} was unpublished from the registry", name, version),
+                ))
+            }
+            None => {
+                let latest = match document["dist-tags"]["latest"].as_str() {
+                    Some(latest) => format!("; the latest is {}@{}", name, latest),
+                    None => String::new(),
+                };
+                return Err(new(
+                    ErrorKind::NotFound,
+                    format!("{}@{} is not published{}", name, version, latest),
+                ));
+            }
+        };
+        if let Some(deprecated) = manifest["deprecated"].as_str() {
+            warn!("{}@{} is deprecated: {}", name, version, deprecated);
+        }
+        let dist = &manifest["dist"];
+        let tarball = dist["tarball"].as_str().ok_or_else(|| {
+            FlyError::from(format!("{}@{} has no tarball in {}", name, version, url))
+        })?;
+        let digest = TarballDigest::of_dist(dist).ok_or_else(|| {
+            new(
+                ErrorKind::IntegrityMismatch,
+                format!(
+                    "{}@{} has no integrity to check its tarball against",
+                    name, version
+                ),
+            )
+        })?;
+        Ok((tarball.to_string(), digest))
+    }
+
+    /** The directory `name@version` is extracted to, installing it first if it isn't yet. */
+    pub fn install(&self, name: &str, version: &str) -> FlyResult<PathBuf> {
+        let package_dir = self.package_dir(name, version);
+        if package_dir.is_dir() {
+            return Ok(package_dir);
+        }
+        if self.config.offline {
+            return Err(new(
+                ErrorKind::NotFound,
+                format!(
+                    "{}@{} is not extracted in {} and fetching is disabled (offline)",
+                    name,
+                    version,
+                    self.dir.display()
+                ),
+            ));
+        }
+        let _installing = self.installing.lock().unwrap();
+        if package_dir.is_dir() {
+            return Ok(package_dir);
+        }
+        let (tarball, expected) = self.dist(name, version)?;
+        debug!("installing {}@{} from {}", name, version, tarball);
+        let bytes = self.fetch(self.request(&tarball))?;
+        let actual = expected.of_bytes(&bytes);
+        if actual != expected {
+            return Err(new(
+                ErrorKind::IntegrityMismatch,
+                format!(
+                    "{}@{}: tarball {} is {}, but the registry says {}",
+                    name, version, tarball, actual, expected
+                ),
+            ));
+        }
+        let bundle = ArchiveBundle::from_bytes(&tarball, bytes, &self.config.limits)?;
+        let tmp = self.dir.join(format!(
+            ".{}@{}.{}.tmp",
+            name.replace('/', "+"),
+            version,
+            std::process::id()
+        ));
+        let extracted = extract(&bundle, &tmp).and_then(|_| {
+            std::fs::create_dir_all(package_dir.parent().unwrap())?;
+            Ok(std::fs::rename(&tmp, &package_dir)?)
+        });
+        if let Err(e) = extracted {
+            let _ = std::fs::remove_dir_all(&tmp);
+            // another process got there first
+            if !package_dir.is_dir() {
+                return Err(e);
+            }
+        }
+        Ok(package_dir)
+    }
+}
+
+/**
+ * Writes `bundle`'s files under `dir`, without the directory npm tarballs put them all in
+ * (usually `package/`).
+ */
+fn extract(bundle: &ArchiveBundle, dir: &Path) -> FlyResult<()> {
+    std::fs::create_dir_all(dir)?;
+    for entry_name in bundle.names() {
+        let path = match entry_name.splitn(2, '/').nth(1) {
+            Some(path) => dir.join(path),
+            None => continue,
+        };
+        let contents = bundle.read(entry_name)?.unwrap_or_default();
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, contents)?;
+    }
+    Ok(())
+}
+
+impl ModuleResolver for NpmResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => format!("{}:///", NPM_PROTOCOL),
+        };
+        let invalid = |why: &str| {
+            new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Could not resolve {} from {}: {}",
+                    module_specifier, referer_origin_url, why
+                ),
+            )
+        };
+        let url = parse_url(module_specifier, &referer_origin_url)?;
+        if url.scheme() != NPM_PROTOCOL {
+            return Err(invalid("not an npm url"));
+        }
+        let path = url::percent_encoding::percent_decode(url.path().as_bytes())
+            .decode_utf8_lossy()
+            .into_owned();
+        let package = PackageSpecifier::parse(path.trim_start_matches('/'))
+            .ok_or_else(|| invalid("expected npm:<package>@<version>[/<path>]"))?;
+        if !is_exact_version(package.version) {
+            return Err(invalid(&format!(
+                "{}@{} must use an exact version like {}@1.2.3, not a tag or range",
+                package.name, package.version, package.name
+            )));
+        }
+        let package_dir = self.install(package.name, package.version).map_err(|e| {
+            new(
+                e.kind(),
+                format!(
+                    "Could not resolve {} from {}: {}",
+                    module_specifier, referer_origin_url, e
+                ),
+            )
+        })?;
+        let file = match package.path {
+            Some(path) => probe(&package_dir.join(path)),
+            None => entry_point(&package_dir)?,
+        };
+        let no_such_file = || {
+            new(
+                ErrorKind::NotFound,
+                format!(
+                    "Could not resolve {} from {}: no such file in {}@{}",
+                    module_specifier, referer_origin_url, package.name, package.version
+                ),
+            )
+        };
+        let file = file
+            .and_then(|file| file.canonicalize().ok())
+            .ok_or_else(no_such_file)?;
+        let relative = match package_dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| file.strip_prefix(dir).ok().map(|p| p.to_path_buf()))
+        {
+            Some(relative) => relative,
+            None => return Err(no_such_file()),
+        };
+        let relative: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        // via a url, so it's encoded the way referers joined to it will be
+        let origin_url = url::Url::parse(&format!(
+            "{}:///{}@{}/{}",
+            NPM_PROTOCOL,
+            package.name,
+            package.version,
+            relative.join("/")
+        ))?
+        .into_string();
+        debug!("resolved {} to {}", module_specifier, origin_url);
+        let source_loader = self
+            .data_formats
+            .loader_for(&origin_url, Box::new(LocalDiskRawLoader::new(file, None)));
+        Ok(ModuleSourceData {
+            origin_url,
+            source_loader,
+        })
+    }
+    fn get_protocol(&self) -> String {
+        NPM_PROTOCOL.to_string()
+    }
+    fn is_remote(&self) -> bool {
+        !self.config.offline
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("npm", NPM_PROTOCOL)
+            .named(&self.name())
+            .with("registry", &self.config.registry)
+            .with("dir", &self.dir.display().to_string())
+            .with("offline", &self.config.offline.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        FetchResponse, FunctionFetcher, ModuleResolverManager, StandardModuleResolverManager,
+    };
+    use std::io::Write;
+
+    fn tgz(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut tar = vec![];
+        for (name, contents) in entries {
+            let mut header = vec![0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[124..136].copy_from_slice(format!("{:011o}\0", contents.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            tar.extend(header);
+            tar.extend_from_slice(contents.as_bytes());
+            tar.resize((tar.len() + 511) / 512 * 512, 0);
+        }
+        tar.extend(vec![0u8; 1024]);
+        // stored blocks: libflate's lz77 matcher isn't sound under debug assertions
+        let options = libflate::gzip::EncodeOptions::new().no_compression();
+        let mut encoder = libflate::gzip::Encoder::with_options(vec![], options).unwrap();
+        encoder.write_all(&tar).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    /**
+     * A registry with `left-pad@1.3.0`, `1.2.0` unpublished and a `1.1.0` whose tarball
+     * isn't the one its integrity pins, plus an unpublished `gone`.
+     */
+    fn registry(requests: Arc<Mutex<Vec<String>>>) -> Arc<HttpFetcher> {
+        let tarball = tgz(&[
+            ("package/package.json", r#"{"main": "lib/index.js"}"#),
+            (
+                "package/lib/index.js",
+                "import { pad } from './pad.js'; export default pad;",
+            ),
+            ("package/lib/pad.js", "export const pad = (s) => ' ' + s;"),
+        ]);
+        let integrity = format!("sha512-{}", base64::encode(&Sha512::digest(&tarball)));
+        let document = serde_json::json!({
+            "name": "left-pad",
+            "dist-tags": {"latest": "1.3.0"},
+            "versions": {
+                "1.3.0": {"dist": {
+                    "tarball": "https://registry.test/left-pad/-/left-pad-1.3.0.tgz",
+                    "integrity": integrity,
+                }},
+                "1.1.0": {"dist": {
+                    "tarball": "https://registry.test/left-pad/-/left-pad-1.1.0.tgz",
+                    "shasum": "0000000000000000000000000000000000000000",
+                }},
+            },
+            "time": {"1.1.0": "2016-01-01T00:00:00Z", "1.2.0": "2016-02-01T00:00:00Z"},
+        })
+        .to_string();
+        Arc::new(FunctionFetcher::new(Box::new(move |req: FetchRequest| {
+            requests.lock().unwrap().push(req.url.clone());
+            let (status, body) = match req.url.as_str() {
+                "https://registry.test/left-pad" => (200, document.clone().into_bytes()),
+                "https://registry.test/gone" => (
+                    200,
+                    br#"{"name": "gone", "time": {"unpublished": {"time": "2020-01-01T00:00:00Z"}}}"#
+                        .to_vec(),
+                ),
+                url if url.ends_with(".tgz") => (200, tarball.clone()),
+                _ => (404, vec![]),
+            };
+            Ok(FetchResponse {
+                status,
+                headers: vec![],
+                body,
+            })
+        })))
+    }
+
+    fn config() -> NpmResolverConfig {
+        NpmResolverConfig {
+            registry: "https://registry.test".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_installs_once_and_resolves_inside_the_package() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let dir = tempfile::tempdir().unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                NpmResolver::new(dir.path(), config()).with_fetcher(registry(requests.clone())),
+            ) as Box<ModuleResolver>],
+            Some("file:///app/".to_string()),
+        );
+        let index = manager
+            .resolve_blocking("npm:left-pad@1.3.0".to_string(), None)
+            .unwrap();
+        assert_eq!("npm:///left-pad@1.3.0/lib/index.js", index.origin_url);
+        let referer = RefererInfo {
+            origin_url: index.origin_url.clone(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let pad = manager
+            .resolve_blocking("./pad.js".to_string(), Some(referer))
+            .unwrap();
+        assert_eq!("npm:///left-pad@1.3.0/lib/pad.js", pad.origin_url);
+        assert!(pad.loaded_source.source.contains("export const pad"));
+        let subpath = manager
+            .resolve_blocking("npm:left-pad@1.3.0/lib/pad".to_string(), None)
+            .unwrap();
+        assert_eq!("npm:///left-pad@1.3.0/lib/pad.js", subpath.origin_url);
+        assert!(dir.path().join("left-pad@1.3.0/package.json").is_file());
+        assert_eq!(
+            vec![
+                "https://registry.test/left-pad",
+                "https://registry.test/left-pad/-/left-pad-1.3.0.tgz",
+            ],
+            *requests.lock().unwrap()
+        );
+
+        // what's extracted is served offline, and nothing else is
+        let offline = NpmResolver::new(
+            dir.path(),
+            NpmResolverConfig {
+                offline: true,
+                ..config()
+            },
+        )
+        .with_fetcher(Arc::new(FunctionFetcher::new(Box::new(|_| {
+            panic!("fetched while offline")
+        }))));
+        let data = offline.resolve_module("npm:left-pad@1.3.0", None).unwrap();
+        assert_eq!("npm:///left-pad@1.3.0/lib/index.js", data.origin_url);
+        let err = offline
+            .resolve_module("npm:left-pad@1.1.0", None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+        assert!(err.to_string().contains("offline"), "{}", err);
+    }
+
+    #[test]
+    fn test_registry_failures_are_told_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let resolver = NpmResolver::new(dir.path(), config())
+            .with_fetcher(registry(Arc::new(Mutex::new(vec![]))));
+        let err = |specifier: &str| resolver.resolve_module(specifier, None).err().unwrap();
+
+        let missing = err("npm:left-pad@9.9.9");
+        assert_eq!(ErrorKind::NotFound, missing.kind());
+        assert!(
+            missing
+                .to_string()
+                .ends_with("left-pad@9.9.9 is not published; the latest is left-pad@1.3.0"),
+            "{}",
+            missing
+        );
+        assert_eq!(ErrorKind::NotFound, err("npm:no-such-pkg@1.0.0").kind());
+        assert_eq!(ErrorKind::PackageYanked, err("npm:left-pad@1.2.0").kind());
+        assert_eq!(ErrorKind::PackageYanked, err("npm:gone@1.0.0").kind());
+        let tampered = err("npm:left-pad@1.1.0");
+        assert_eq!(ErrorKind::IntegrityMismatch, tampered.kind());
+        assert!(!dir.path().join("left-pad@1.1.0").exists());
+        assert_eq!(ErrorKind::InvalidInput, err("npm:left-pad@^1.3.0").kind());
+        assert_eq!(ErrorKind::InvalidInput, err("npm:left-pad").kind());
+        assert_eq!(
+            ErrorKind::NotFound,
+            err("npm:left-pad@1.3.0/lib/missing.js").kind()
+        );
+    }
+}
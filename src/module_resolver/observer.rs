@@ -0,0 +1,240 @@
+use crate::errors::ErrorKind;
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::duration_ms;
+use crate::metrics::MODULE_RESOLUTION_TIME_HISTOGRAM;
+
+/** How one resolver's attempt at a specifier ended. */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResolutionOutcome {
+    Resolved,
+    Failed(ErrorKind),
+    /** Answered from the resolution cache, without asking any resolver. */
+    CacheHit,
+}
+
+/** A resolver about to try a specifier. */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolveStart<'a> {
+    pub specifier: &'a str,
+    pub protocol: &'a str,
+    /** The resolver's kind, as it describes itself, e.g. `local_disk`. */
+    pub resolver: &'a str,
+    /** The resolver's `name`, e.g. `LocalDiskModuleResolver`. */
+    pub name: &'a str,
+}
+
+/** A resolver done trying a specifier. */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolveEnd<'a> {
+    pub specifier: &'a str,
+    pub protocol: &'a str,
+    pub resolver: &'a str,
+    pub name: &'a str,
+    pub duration: Duration,
+    pub outcome: ResolutionOutcome,
+}
+
+/**
+ * Told about every resolver a manager tries, as it tries them, with a start and an end for
+ * each. Cache hits get a pair of their own, from the `resolution_cache` resolver. Observers
+ * are called on whichever thread resolves, so they must be quick and not block.
+ */
+pub trait ResolutionObserver: Send + Sync {
+    fn on_resolve_start(&self, _start: &ResolveStart) {}
+    fn on_resolve_end(&self, _end: &ResolveEnd) {}
+}
+
+/** Upper bounds, in milliseconds, of the latency histogram's buckets. */
+pub const RESOLUTION_LATENCY_BUCKETS_MS: &[f64] = &[
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 5000.0,
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ProtocolResolutionStats {
+    pub protocol: String,
+    pub resolved: usize,
+    pub failed: usize,
+    pub cache_hits: usize,
+    /** How many resolutions each resolver won, by its `name`. */
+    pub resolved_by: BTreeMap<String, usize>,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    /**
+     * How many attempts took at most each of `RESOLUTION_LATENCY_BUCKETS_MS`, cumulatively;
+     * one more entry counts every attempt.
+     */
+    pub latency_buckets: Vec<usize>,
+}
+
+/**
+ * A `ResolutionObserver` counting outcomes and latency per protocol, for embedders to dump
+ * with `snapshot`.
+ */
+#[derive(Default)]
+pub struct ResolutionMetrics {
+    protocols: Mutex<BTreeMap<String, ProtocolResolutionStats>>,
+}
+
+impl ResolutionMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /** Every protocol resolved so far, by name. */
+    pub fn snapshot(&self) -> Vec<ProtocolResolutionStats> {
+        self.protocols.lock().unwrap().values().cloned().collect()
+    }
+}
+
+impl ResolutionObserver for ResolutionMetrics {
+    fn on_resolve_end(&self, end: &ResolveEnd) {
+        let ms = duration_ms(end.duration);
+        let mut protocols = self.protocols.lock().unwrap();
+        let stats = protocols
+            .entry(end.protocol.to_string())
+            .or_insert_with(|| ProtocolResolutionStats {
+                protocol: end.protocol.to_string(),
+                latency_buckets: vec![0; RESOLUTION_LATENCY_BUCKETS_MS.len() + 1],
+                ..Default::default()
+            });
+        match end.outcome {
+            ResolutionOutcome::Resolved => {
+                stats.resolved += 1;
+                *stats.resolved_by.entry(end.name.to_string()).or_insert(0) += 1;
+            }
+            ResolutionOutcome::Failed(_) => stats.failed += 1,
+            ResolutionOutcome::CacheHit => stats.cache_hits += 1,
+        }
+        stats.total_ms += ms;
+        stats.max_ms = stats.max_ms.max(ms);
+        for (bucket, bound) in stats.latency_buckets.iter_mut().zip(
+            RESOLUTION_LATENCY_BUCKETS_MS
+                .iter()
+                .chain(&[std::f64::INFINITY]),
+        ) {
+            if ms <= *bound {
+                *bucket += 1;
+            }
+        }
+    }
+}
+
+/**
+ * A `ResolutionObserver` recording into the process's Prometheus metrics, timing each
+ * resolver tried by protocol, resolver and outcome.
+ */
+pub struct PrometheusResolutionObserver;
+
+impl ResolutionObserver for PrometheusResolutionObserver {
+    fn on_resolve_end(&self, end: &ResolveEnd) {
+        let outcome = match end.outcome {
+            ResolutionOutcome::Resolved => "resolved",
+            ResolutionOutcome::Failed(_) => "failed",
+            ResolutionOutcome::CacheHit => "cache_hit",
+        };
+        MODULE_RESOLUTION_TIME_HISTOGRAM
+            .with_label_values(&[end.protocol, end.resolver, outcome])
+            .observe(duration_ms(end.duration) / 1000.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::*;
+    use crate::module_resolver::{
+        FunctionModuleResolver, LoadedSourceCode, ModuleKind, ModuleResolver,
+        ModuleResolverManager, ModuleSourceData, ResolutionCache, StandardModuleResolverManager,
+        StaticSourceLoader,
+    };
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl ResolutionObserver for Recorder {
+        fn on_resolve_start(&self, start: &ResolveStart) {
+            self.events.lock().unwrap().push(format!(
+                "start {} {} {}",
+                start.resolver, start.protocol, start.specifier
+            ));
+        }
+        fn on_resolve_end(&self, end: &ResolveEnd) {
+            self.events.lock().unwrap().push(format!(
+                "end {} {} {:?}",
+                end.resolver, end.protocol, end.outcome
+            ));
+        }
+    }
+
+    #[test]
+    fn test_observes_each_resolver_tried_in_order() {
+        let resolver = |fails: bool| {
+            Box::new(FunctionModuleResolver::new(
+                "app",
+                Box::new(move |specifier: &str, _| {
+                    if fails {
+                        return Err(new(ErrorKind::NotFound, "not here".to_string()));
+                    }
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(StaticSourceLoader {
+                            source: LoadedSourceCode {
+                                kind: ModuleKind::JavaScript,
+                                source_map: None,
+                                source: "export default 1;".into(),
+                                response: None,
+                                provenance: None,
+                                code_cache: None,
+                                compiled_wasm: None,
+                                wasm: None,
+                            },
+                        }),
+                    })
+                }),
+            )) as Box<ModuleResolver>
+        };
+        let recorder = Arc::new(Recorder::default());
+        let metrics = Arc::new(ResolutionMetrics::new());
+        let manager =
+            StandardModuleResolverManager::new(vec![resolver(true), resolver(false)], None)
+                .with_resolution_cache(ResolutionCache::new())
+                .with_observer(recorder.clone())
+                .with_observer(metrics.clone());
+        for _ in 0..2 {
+            manager
+                .resolve_blocking("app://main.js".to_string(), None)
+                .unwrap();
+        }
+        assert_eq!(
+            vec![
+                "start function app app://main.js",
+                "end function app Failed(NotFound)",
+                "start function app app://main.js",
+                "end function app Resolved",
+                "start resolution_cache app app://main.js",
+                "end resolution_cache app CacheHit",
+            ],
+            *recorder.events.lock().unwrap()
+        );
+
+        let stats = metrics.snapshot();
+        assert_eq!(1, stats.len());
+        assert_eq!(
+            ("app", 1, 1, 1),
+            (
+                &stats[0].protocol[..],
+                stats[0].resolved,
+                stats[0].failed,
+                stats[0].cache_hits
+            )
+        );
+        assert_eq!(Some(&3), stats[0].latency_buckets.last(), "{:?}", stats[0]);
+    }
+}
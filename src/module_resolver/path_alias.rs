@@ -0,0 +1,216 @@
+use crate::errors::*;
+
+use std::path::Path;
+
+use super::data_module::DataValue;
+use super::json5_loader::parse_json5;
+use super::source_text::read_source_text;
+
+/**
+ * Path aliases as tsconfig's `compilerOptions.paths` has them: a pattern with at most one `*`,
+ * and the targets it maps to, tried in order.
+ */
+pub type PathAliases = Vec<(String, Vec<String>)>;
+
+/**
+ * The pattern `specifier` matches and its targets with the `*` substituted, as TypeScript
+ * picks them: an exact pattern first, otherwise the wildcard pattern with the longest prefix.
+ */
+pub fn match_path_alias<'a>(
+    aliases: &'a [(String, Vec<String>)],
+    specifier: &str,
+) -> Option<(&'a str, Vec<String>)> {
+    if let Some((pattern, targets)) = aliases.iter().find(|(pattern, _)| pattern == specifier) {
+        return Some((pattern, targets.clone()));
+    }
+    let (pattern, targets, matched) = aliases
+        .iter()
+        .filter_map(|(pattern, targets)| {
+            let star = pattern.find('*')?;
+            let (prefix, suffix) = (&pattern[..star], &pattern[star + 1..]);
+            if specifier.len() < prefix.len() + suffix.len()
+                || !specifier.starts_with(prefix)
+                || !specifier.ends_with(suffix)
+            {
+                return None;
+            }
+            let matched = &specifier[prefix.len()..specifier.len() - suffix.len()];
+            Some((pattern, targets, prefix.len(), matched))
+        })
+        .max_by_key(|(_, _, prefix_len, _)| *prefix_len)
+        .map(|(pattern, targets, _, matched)| (pattern, targets, matched))?;
+    let targets = targets
+        .iter()
+        .map(|target| target.replacen('*', matched, 1))
+        .collect();
+    Some((pattern, targets))
+}
+
+/**
+ * The `compilerOptions.paths` of the tsconfig.json at `path`, targets made absolute against
+ * its `baseUrl`, or the tsconfig's own directory without one, as TypeScript does. Comments and
+ * trailing commas are fine; `extends` isn't followed.
+ */
+pub fn read_tsconfig_paths(path: &Path) -> FlyResult<PathAliases> {
+    let source = read_source_text(path)?;
+    let invalid = |msg: &str| {
+        new(
+            ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), msg),
+        )
+    };
+    let config = parse_json5(&path.display().to_string(), &source)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("/"));
+    let options = match field(&config, "compilerOptions") {
+        Some(options) => options,
+        None => return Ok(vec![]),
+    };
+    let base = match field(options, "baseUrl") {
+        Some(DataValue::String(base_url)) => dir.join(base_url),
+        Some(_) => return Err(invalid("compilerOptions.baseUrl must be a string")),
+        None => dir.to_path_buf(),
+    };
+    let paths = match field(options, "paths") {
+        Some(DataValue::Object(paths)) => paths,
+        Some(_) => return Err(invalid("compilerOptions.paths must be an object")),
+        None => return Ok(vec![]),
+    };
+    paths
+        .iter()
+        .map(|(pattern, targets)| {
+            let targets = match targets {
+                DataValue::Array(targets) => targets,
+                _ => {
+                    return Err(invalid(&format!(
+                        "the targets of paths alias {} must be an array",
+                        pattern
+                    )))
+                }
+            };
+            let targets = targets
+                .iter()
+                .map(|target| match target {
+                    DataValue::String(target) => {
+                        Ok(base.join(target).to_string_lossy().into_owned())
+                    }
+                    _ => Err(invalid(&format!(
+                        "the targets of paths alias {} must be strings",
+                        pattern
+                    ))),
+                })
+                .collect::<FlyResult<Vec<String>>>()?;
+            if pattern.matches('*').count() > 1 {
+                return Err(invalid(&format!(
+                    "paths alias {} may have at most one '*'",
+                    pattern
+                )));
+            }
+            Ok((pattern.clone(), targets))
+        })
+        .collect()
+}
+
+fn field<'a>(value: &'a DataValue, name: &str) -> Option<&'a DataValue> {
+    match value {
+        DataValue::Object(fields) => fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases() -> PathAliases {
+        vec![
+            ("@app/*".to_string(), vec!["src/*".to_string()]),
+            (
+                "@lib/*".to_string(),
+                vec![
+                    "packages/lib/src/*".to_string(),
+                    "packages/lib/dist/*".to_string(),
+                ],
+            ),
+            ("@lib/legacy/*".to_string(), vec!["legacy/*".to_string()]),
+            ("config".to_string(), vec!["src/config.ts".to_string()]),
+        ]
+    }
+
+    #[test]
+    fn test_substitutes_the_wildcard() {
+        let aliases = aliases();
+        assert_eq!(
+            Some(("@app/*", vec!["src/widgets/button".to_string()])),
+            match_path_alias(&aliases, "@app/widgets/button")
+        );
+        assert_eq!(
+            Some((
+                "@lib/*",
+                vec![
+                    "packages/lib/src/format".to_string(),
+                    "packages/lib/dist/format".to_string()
+                ]
+            )),
+            match_path_alias(&aliases, "@lib/format")
+        );
+        // the longest prefix wins, and exact patterns match only themselves
+        assert_eq!(
+            Some(("@lib/legacy/*", vec!["legacy/date".to_string()])),
+            match_path_alias(&aliases, "@lib/legacy/date")
+        );
+        assert_eq!(
+            Some(("config", vec!["src/config.ts".to_string()])),
+            match_path_alias(&aliases, "config")
+        );
+        assert_eq!(None, match_path_alias(&aliases, "config/prod"));
+        assert_eq!(None, match_path_alias(&aliases, "@application/main"));
+        assert_eq!(None, match_path_alias(&aliases, "lodash"));
+    }
+
+    #[test]
+    fn test_reads_tsconfig_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let tsconfig = dir.path().join("tsconfig.json");
+        std::fs::write(
+            &tsconfig,
+            r#"{
+  // comments and trailing commas, as tsc allows
+  "compilerOptions": {
+    "baseUrl": "./web",
+    "paths": {
+      "@app/*": ["src/*", "generated/*"],
+    },
+  },
+}"#,
+        )
+        .unwrap();
+        let base = dir.path().join("./web");
+        assert_eq!(
+            vec![(
+                "@app/*".to_string(),
+                vec![
+                    base.join("src/*").to_string_lossy().into_owned(),
+                    base.join("generated/*").to_string_lossy().into_owned()
+                ]
+            )],
+            read_tsconfig_paths(&tsconfig).unwrap()
+        );
+
+        std::fs::write(
+            &tsconfig,
+            r#"{"compilerOptions": {"paths": {"@a/*/*": ["*"]}}}"#,
+        )
+        .unwrap();
+        let err = read_tsconfig_paths(&tsconfig).err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert!(err
+            .to_string()
+            .ends_with("paths alias @a/*/* may have at most one '*'"));
+
+        std::fs::write(&tsconfig, r#"{"compilerOptions": {"strict": true}}"#).unwrap();
+        assert!(read_tsconfig_paths(&tsconfig).unwrap().is_empty());
+    }
+}
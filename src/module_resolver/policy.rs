@@ -0,0 +1,312 @@
+use crate::errors::*;
+
+use std::collections::{BTreeMap, HashSet};
+
+/**
+ * Which protocols a resolver manager is allowed to serve, and for network protocols
+ * optionally which hosts. `allow_all` is the default for a manager built from scratch;
+ * managers inherited by child runtimes always carry an explicit allowlist that can only
+ * shrink from there.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResolverPolicy {
+    allowed_protocols: Option<HashSet<String>>,
+    /** protocol -> host patterns, like `cdn.example.com` or `*.example.com` */
+    allowed_hosts: BTreeMap<String, Vec<String>>,
+}
+
+/**
+ * Whether `host` matches `pattern`: the same host, or for `*.example.com` any host under
+ * `example.com`, but not `example.com` itself.
+ */
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    if pattern.starts_with("*.") {
+        host.len() > pattern.len() - 1 && host.ends_with(&pattern[1..])
+    } else {
+        pattern == host
+    }
+}
+
+impl ResolverPolicy {
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    pub fn allow_protocols<I, S>(protocols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed_protocols: Some(protocols.into_iter().map(|p| p.into()).collect()),
+            allowed_hosts: BTreeMap::new(),
+        }
+    }
+
+    /**
+     * Only allows urls of `protocol` whose host matches one of `patterns`.
+     */
+    pub fn with_hosts<I, S>(mut self, protocol: &str, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| p.into().to_lowercase())
+            .collect();
+        self.allowed_hosts.insert(protocol.to_string(), patterns);
+        self
+    }
+
+    pub fn permits(&self, protocol: &str) -> bool {
+        match self.allowed_protocols {
+            None => true,
+            Some(ref allowed) => allowed.contains(protocol),
+        }
+    }
+
+    /**
+     * Whether `url`'s host is allowed for its protocol. Protocols without host patterns
+     * allow any host.
+     */
+    pub fn permits_host(&self, url: &url::Url) -> bool {
+        match self.allowed_hosts.get(url.scheme()) {
+            None => true,
+            Some(patterns) => {
+                let host = url.host_str().unwrap_or("").to_lowercase();
+                patterns.iter().any(|pattern| host_matches(pattern, &host))
+            }
+        }
+    }
+
+    pub fn is_subset_of(&self, other: &ResolverPolicy) -> bool {
+        let protocols = match (&self.allowed_protocols, &other.allowed_protocols) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(mine), Some(theirs)) => mine.is_subset(theirs),
+        };
+        // each of our patterns has to be one of theirs, or under one of their wildcards
+        protocols
+            && other.allowed_hosts.iter().all(|(protocol, theirs)| {
+                !self.permits(protocol)
+                    || self.allowed_hosts.get(protocol).map_or(false, |mine| {
+                        mine.iter().all(|pattern| {
+                            theirs.iter().any(|their| {
+                                their == pattern
+                                    || (their.starts_with("*.")
+                                        && host_matches(their, pattern.trim_start_matches("*.")))
+                            })
+                        })
+                    })
+            })
+    }
+
+    /**
+     * Returns `stricter` if it allows nothing this policy doesn't. Anything looser is an
+     * escalation and is rejected rather than silently intersected.
+     */
+    pub fn narrow(&self, stricter: &ResolverPolicy) -> FlyResult<ResolverPolicy> {
+        if !stricter.is_subset_of(self) {
+            return Err(FlyError::from(format!(
+                "Resolver policy {} is looser than the inherited policy {}",
+                stricter, self
+            )));
+        }
+        Ok(stricter.clone())
+    }
+}
+
+impl std::fmt::Display for ResolverPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.allowed_protocols {
+            None => write!(f, "[*]")?,
+            Some(ref allowed) => {
+                let mut protocols: Vec<&String> = allowed.iter().collect();
+                protocols.sort();
+                write!(
+                    f,
+                    "[{}]",
+                    protocols
+                        .iter()
+                        .map(|p| p.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )?
+            }
+        }
+        for (protocol, patterns) in &self.allowed_hosts {
+            write!(f, " {} hosts [{}]", protocol, patterns.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        FunctionModuleResolver, JsonSecretsResolver, LoadedSourceCode, LocalDiskModuleResolver,
+        ModuleKind, ModuleResolver, ModuleResolverManager, ModuleSourceData,
+        StandardModuleResolverManager, StaticSourceLoader,
+    };
+
+    fn parent() -> StandardModuleResolverManager {
+        StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(None).unwrap()) as Box<ModuleResolver>,
+                Box::new(JsonSecretsResolver::new(
+                    serde_json::from_str(r#"{"db":{"password":"hunter2"}}"#).unwrap(),
+                )),
+            ],
+            Some("file:///app/".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_narrow_rejects_looser_policy() {
+        let inherited = ResolverPolicy::allow_protocols(vec!["file", "secrets"]);
+        assert!(inherited
+            .narrow(&ResolverPolicy::allow_protocols(vec!["file"]))
+            .is_ok());
+        assert!(inherited
+            .narrow(&ResolverPolicy::allow_protocols(vec!["file", "http"]))
+            .is_err());
+        assert!(inherited.narrow(&ResolverPolicy::allow_all()).is_err());
+    }
+
+    #[test]
+    fn test_host_patterns_only_narrow() {
+        let policy = ResolverPolicy::allow_protocols(vec!["file", "https"])
+            .with_hosts("https", vec!["deno.land", "*.example.com"]);
+        let permits = |url: &str| policy.permits_host(&url::Url::parse(url).unwrap());
+        assert!(permits("https://deno.land/std/mod.ts"));
+        assert!(permits("https://cdn.EXAMPLE.com/a.js"));
+        assert!(!permits("https://example.com/a.js"));
+        assert!(!permits("https://evil-example.com/a.js"));
+        assert!(permits("file:///app/main.ts"));
+
+        let https = ResolverPolicy::allow_protocols(vec!["file", "https"]);
+        assert!(https.narrow(&policy).is_ok());
+        assert!(policy.narrow(&https).is_err());
+        let narrower = ResolverPolicy::allow_protocols(vec!["https"])
+            .with_hosts("https", vec!["a.cdn.example.com", "*.cdn.example.com"]);
+        assert!(policy.narrow(&narrower).is_ok());
+        let elsewhere =
+            ResolverPolicy::allow_protocols(vec!["https"]).with_hosts("https", vec!["x.com"]);
+        assert!(policy.narrow(&elsewhere).is_err());
+        assert!(format!("{}", policy).ends_with("https hosts [deno.land, *.example.com]"));
+    }
+
+    #[test]
+    fn test_tightened_policy_denies_registered_resolvers() {
+        let mut manager = parent();
+        manager
+            .add_resolver(
+                Box::new(FunctionModuleResolver::new(
+                    "function",
+                    Box::new(|specifier, _| {
+                        Ok(ModuleSourceData {
+                            origin_url: specifier.to_string(),
+                            source_loader: Box::new(StaticSourceLoader {
+                                source: LoadedSourceCode {
+                                    kind: ModuleKind::JavaScript,
+                                    source_map: None,
+                                    source: "export default 1;".into(),
+                                    response: None,
+                                    provenance: None,
+                                    code_cache: None,
+                                    compiled_wasm: None,
+                                    wasm: None,
+                                },
+                            }),
+                        })
+                    }),
+                )),
+                0,
+            )
+            .unwrap();
+        manager
+            .resolve_blocking("secrets:///db".to_string(), None)
+            .unwrap();
+        manager
+            .tighten_policy(
+                ResolverPolicy::allow_protocols(vec!["file", "function"])
+                    .with_hosts("function", vec!["trusted"]),
+            )
+            .unwrap();
+
+        let err = manager
+            .resolve_blocking("secrets:///db".to_string(), None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PolicyDenied, err.kind());
+        let msg = format!("{}", err);
+        assert!(msg.contains("protocol secrets"), "{}", msg);
+        assert!(msg.contains("from file:///app/"), "{}", msg);
+        manager
+            .resolve_blocking("function://trusted/a.js".to_string(), None)
+            .unwrap();
+        let err = manager
+            .resolve_blocking("function://untrusted/a.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PolicyDenied, err.kind());
+        assert!(format!("{}", err).contains("host untrusted of protocol function"));
+
+        // once tightened, never loosened again
+        assert!(manager.tighten_policy(ResolverPolicy::allow_all()).is_err());
+        assert!(manager
+            .register_resolver(Box::new(JsonSecretsResolver::new(serde_json::json!({}))))
+            .is_err());
+    }
+
+    #[test]
+    fn test_child_shares_parent_resolvers() {
+        let child = parent().inherit();
+        let module = child
+            .resolve_blocking("secrets:///db".to_string(), None)
+            .unwrap();
+        assert!(module.loaded_source.source.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_narrowed_child_drops_disallowed_protocols() {
+        let child = parent()
+            .inherit()
+            .narrow(ResolverPolicy::allow_protocols(vec!["file"]))
+            .unwrap();
+        let err = child
+            .resolve_blocking("secrets:///db".to_string(), None)
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("not allowed"));
+    }
+
+    #[test]
+    fn test_inherited_manager_cannot_escalate() {
+        let mut child = parent().inherit();
+        // the parent serves no "function" protocol, so the child may not add one
+        let looser = Box::new(crate::module_resolver::FunctionModuleResolver::new(
+            "function",
+            Box::new(|specifier, _| Err(FlyError::from(specifier.to_string()))),
+        ));
+        assert!(child.register_resolver(looser).is_err());
+        assert!(child
+            .inherit()
+            .narrow(ResolverPolicy::allow_protocols(vec!["file", "http"]))
+            .is_err());
+
+        // nor replace one it does serve with a wider resolver, a disk resolver without a root
+        let err = child
+            .register_resolver(Box::new(LocalDiskModuleResolver::new(None).unwrap()))
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("inherited"), "{}", err);
+        // which holds for clones of the child too
+        assert!(child
+            .clone()
+            .register_resolver(Box::new(LocalDiskModuleResolver::new(None).unwrap()))
+            .is_err());
+    }
+}
@@ -0,0 +1,463 @@
+use crate::errors::*;
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use futures::{future, Future};
+
+use super::cjs_loader::{tokenize, Token};
+use super::{
+    declared_imports, wasm_bytes, LoadedModule, ModuleResolverManager, RefererInfo,
+    ResolutionDeadline,
+};
+
+/**
+ * What `ModuleResolverManager::preload` loaded.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PreloadSummary {
+    /** Distinct modules loaded, the entry included. */
+    pub modules: usize,
+    /** Their sources' bytes, altogether. */
+    pub bytes: usize,
+    pub duration: Duration,
+    /**
+     * Imports that failed, as `<specifier> from <referer>: <error>`. They aren't fatal: the
+     * runtime reports each one again when it gets to that import, if it ever does.
+     */
+    pub failures: Vec<String>,
+}
+
+/**
+ * The specifiers `source` imports with string literals: `import ... from`, `export ... from`,
+ * side-effect imports and `import()` of a literal, each once, in order. Other `import()`s are
+ * skipped, there's no telling what they load.
+ */
+pub fn static_import_specifiers(source: &str) -> Vec<&str> {
+    let tokens = tokenize(source);
+    let at = |i: usize| tokens.get(i);
+    let mut specifiers = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && at(i - 1) == Some(&Token::Punct(b'.')) {
+            continue;
+        }
+        let specifier = match token {
+            Token::Ident("import") => match at(i + 1) {
+                Some(Token::Str(Some(specifier))) => Some(*specifier),
+                Some(Token::Punct(b'(')) => match (at(i + 2), at(i + 3)) {
+                    (Some(Token::Str(Some(specifier))), Some(Token::Punct(b')'))) => {
+                        Some(*specifier)
+                    }
+                    _ => {
+                        debug!("not preloading an import() of a computed specifier");
+                        None
+                    }
+                },
+                Some(Token::Punct(b'.')) => None,
+                _ => from_clause(&tokens[i + 1..]),
+            },
+            Token::Ident("export") => match at(i + 1) {
+                Some(Token::Punct(b'{')) | Some(Token::Punct(b'*')) => {
+                    from_clause(&tokens[i + 1..])
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(specifier) = specifier {
+            if !specifiers.contains(&specifier) {
+                specifiers.push(specifier);
+            }
+        }
+    }
+    specifiers
+}
+
+/**
+ * The wasm binaries `source` fetches from next to itself, as wasm-bindgen's glue does with
+ * `new URL("thing_bg.wasm", import.meta.url)`: the string literals of `new URL`s relative to
+ * `import.meta.url` that end in `.wasm`, each once, in order.
+ */
+pub fn wasm_url_references(source: &str) -> Vec<&str> {
+    let relative_to_module = [
+        Token::Punct(b','),
+        Token::Ident("import"),
+        Token::Punct(b'.'),
+        Token::Ident("meta"),
+        Token::Punct(b'.'),
+        Token::Ident("url"),
+        Token::Punct(b')'),
+    ];
+    let tokens = tokenize(source);
+    let at = |i: usize| tokens.get(i);
+    let mut references = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        let reference = match (token, at(i + 1), at(i + 2), at(i + 3)) {
+            (
+                Token::Ident("new"),
+                Some(Token::Ident("URL")),
+                Some(Token::Punct(b'(')),
+                Some(Token::Str(Some(reference))),
+            ) => *reference,
+            _ => continue,
+        };
+        let relative = tokens
+            .get(i + 4..)
+            .map_or(false, |rest| rest.starts_with(&relative_to_module));
+        if relative && reference.ends_with(".wasm") && !references.contains(&reference) {
+            references.push(reference);
+        }
+    }
+    references
+}
+
+/** The specifier after the `from` ending the statement `tokens` start. */
+fn from_clause<'a>(tokens: &[Token<'a>]) -> Option<&'a str> {
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Ident("from") => match tokens.get(i + 1) {
+                Some(Token::Str(Some(specifier))) => return Some(specifier),
+                _ => continue,
+            },
+            Token::Punct(b';') | Token::Ident("import") | Token::Ident("export") => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/**
+ * What importing `module` loads in turn: a script's static imports and the wasm it fetches
+ * from next to itself, by url, or the modules a wasm module's imports name. Each comes with
+ * whether it's imported, rather than fetched, so has imports of its own to load.
+ */
+fn dependencies_of(module: &LoadedModule) -> Vec<(String, bool)> {
+    let source = &module.loaded_source;
+    if source.is_wasm() {
+        let imports = match wasm_bytes(source).and_then(|wasm| declared_imports(&wasm)) {
+            Ok(imports) => imports,
+            Err(e) => {
+                debug!("not preloading the imports of {}: {}", module.origin_url, e);
+                return vec![];
+            }
+        };
+        let mut modules: Vec<String> = vec![];
+        for import in imports {
+            if !modules.contains(&import.module) {
+                modules.push(import.module);
+            }
+        }
+        return modules.into_iter().map(|module| (module, true)).collect();
+    }
+    let mut dependencies: Vec<(String, bool)> = static_import_specifiers(&source.source)
+        .into_iter()
+        .map(|specifier| (specifier.to_string(), true))
+        .collect();
+    if let Ok(base) = url::Url::parse(&module.origin_url) {
+        for reference in wasm_url_references(&source.source) {
+            if let Ok(url) = base.join(reference) {
+                // instantiated by the script with imports of its own making
+                dependencies.push((url.into_string(), false));
+            }
+        }
+    }
+    dependencies
+}
+
+fn referer_of(module: &LoadedModule, deadline: &Option<ResolutionDeadline>) -> RefererInfo {
+    RefererInfo {
+        origin_url: module.origin_url.clone(),
+        is_wasm: Some(module.loaded_source.is_wasm()),
+        source_code: None,
+        identifier_hash: None,
+        line: None,
+        column: None,
+        app_id: None,
+        deadline: deadline.clone(),
+    }
+}
+
+/**
+ * Loads `entry` as `manager` would load an entry point, then everything it imports
+ * statically, transitively, along with the wasm scripts fetch from next to themselves. A
+ * WebAssembly module's imports are loaded with it as their wasm referer, as the runtime would
+ * when instantiating it. Each module's imports load together, a level of the graph at a time.
+ * The whole graph shares the manager's `total_deadline`, if it has one: past it, what's still
+ * loading is cancelled and the preload fails with `DeadlineExceeded`.
+ */
+pub fn preload_graph(manager: &ModuleResolverManager, entry: &str) -> FlyResult<PreloadSummary> {
+    let started = Instant::now();
+    let deadline = manager.total_deadline().map(ResolutionDeadline::new);
+    let loading = manager.load_entry_point(entry.to_string());
+    let entry = match deadline {
+        Some(ref deadline) => deadline.bound(entry.to_string(), loading).wait()?,
+        None => loading.wait()?,
+    };
+    let mut summary = PreloadSummary::default();
+    let mut seen = HashSet::new();
+    seen.insert(entry.origin_url.clone());
+    summary.modules += 1;
+    summary.bytes += entry.loaded_source.source.len();
+
+    let mut level = vec![entry];
+    while !level.is_empty() {
+        let mut loads = vec![];
+        for module in level.iter() {
+            for (specifier, imported) in dependencies_of(module) {
+                let referer = module.origin_url.clone();
+                let load = manager
+                    .resolve_module(specifier.clone(), Some(referer_of(module, &deadline)))
+                    .then(move |result| Ok::<_, ()>((specifier, referer, imported, result)));
+                loads.push(load);
+            }
+        }
+        level = vec![];
+        for (specifier, referer, imported, result) in future::join_all(loads).wait().unwrap() {
+            match result {
+                Ok(module) => {
+                    if seen.insert(module.origin_url.clone()) {
+                        summary.modules += 1;
+                        summary.bytes += module.loaded_source.source.len();
+                        if imported {
+                            level.push(module);
+                        }
+                    }
+                }
+                // the rest of the graph is out of time too
+                Err(e) if e.kind() == ErrorKind::DeadlineExceeded => return Err(e),
+                Err(e) => {
+                    debug!("could not preload {} from {}: {}", specifier, referer, e);
+                    summary
+                        .failures
+                        .push(format!("{} from {}: {}", specifier, referer, e));
+                }
+            }
+        }
+    }
+    summary.duration = started.elapsed();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolver, ResolutionCache, StandardModuleResolverManager,
+    };
+
+    #[test]
+    fn test_scans_literal_imports_only() {
+        let source = concat!(
+            "import a from './a.ts';\n",
+            "import { b, c as d } from \"./b.ts\";\n",
+            "import * as e from './e.ts'\n",
+            "import './side-effect.ts';\n",
+            "export { f } from './f.ts';\n",
+            "export * from './a.ts';\n",
+            "const lazy = () => import('./lazy.ts');\n",
+            "const computed = (name) => import(`./${name}.ts`);\n",
+            "export const g = 1;\n",
+            "// import h from './commented.ts';\n",
+            "const s = \"import i from './string.ts'\";\n",
+            "console.log(import.meta.url, x.import('./method.ts'));\n",
+        );
+        assert_eq!(
+            vec![
+                "./a.ts",
+                "./b.ts",
+                "./e.ts",
+                "./side-effect.ts",
+                "./f.ts",
+                "./lazy.ts"
+            ],
+            static_import_specifiers(source)
+        );
+    }
+
+    #[test]
+    fn test_preloads_the_whole_graph_into_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("lib")).unwrap();
+        let files = [
+            (
+                "main.ts",
+                "import a from './a.ts';\nimport b from './b.ts';\nexport * from './lib/c.ts';",
+            ),
+            ("a.ts", "import d from './d.ts';\nexport default d;"),
+            (
+                "b.ts",
+                "import './a.ts';\nimport config from './config.json';",
+            ),
+            ("d.ts", "export default () => import('./lazy.ts');"),
+            ("lazy.ts", "export default 1;"),
+            ("config.json", "{\"answer\": 42}"),
+            ("lib/c.ts", "export { e } from './e.ts';\nimport '../f.ts';"),
+            ("lib/e.ts", "export const e = (n) => import(`./${n}.ts`);"),
+            ("f.ts", "import { g } from './g.ts';"),
+            ("g.ts", "export const g = import('./missing.ts');"),
+        ];
+        for (name, source) in files.iter() {
+            std::fs::write(dir.path().join(name), source).unwrap();
+        }
+        let working_url = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let cache = ResolutionCache::new();
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(working_url.clone())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(working_url.clone()),
+        )
+        .with_resolution_cache(cache.clone());
+
+        let summary = manager.preload("./main.ts").unwrap();
+        assert_eq!(10, summary.modules);
+        assert_eq!(10, cache.stats().modules);
+        assert!(summary.bytes > 0);
+        assert_eq!(1, summary.failures.len());
+        assert!(summary.failures[0].starts_with("./missing.ts from "));
+
+        // everything the runtime asks for afterwards is a hit
+        let misses = cache.stats().misses;
+        let main = manager
+            .resolve_entry_point("./main.ts".to_string())
+            .unwrap();
+        let import = |specifier: &str, referer: &str| {
+            let referer = RefererInfo {
+                origin_url: format!("{}{}", working_url, referer),
+                is_wasm: Some(false),
+                source_code: None,
+                identifier_hash: None,
+                line: None,
+                column: None,
+                app_id: None,
+                deadline: None,
+            };
+            manager
+                .resolve_blocking(specifier.to_string(), Some(referer))
+                .unwrap()
+        };
+        assert_eq!(format!("{}main.ts", working_url), main.origin_url);
+        import("./a.ts", "main.ts");
+        import("./config.json", "b.ts");
+        import("./lazy.ts", "d.ts");
+        import("./e.ts", "lib/c.ts");
+        import("../f.ts", "lib/c.ts");
+        import("./g.ts", "f.ts");
+        assert_eq!(misses, cache.stats().misses);
+    }
+
+    #[test]
+    fn test_finds_wasm_fetched_relative_to_the_module() {
+        let source = concat!(
+            "const a = new URL(\"thing_bg.wasm\", import.meta.url);\n",
+            "const b = new URL('../lib/other.wasm', import.meta.url);\n",
+            "const c = new URL(\"thing_bg.wasm\", import.meta.url);\n",
+            "const d = new URL(\"thing.json\", import.meta.url);\n",
+            "const e = new URL(\"fixed.wasm\", \"https://example.com/\");\n",
+            "// new URL(\"commented.wasm\", import.meta.url)\n",
+        );
+        assert_eq!(
+            vec!["thing_bg.wasm", "../lib/other.wasm"],
+            wasm_url_references(source)
+        );
+    }
+
+    #[test]
+    fn test_preloads_wasm_bindgen_glue_with_its_binaries() {
+        use crate::module_resolver::{wasm_bytes, FunctionModuleResolver, ModuleKind};
+        use std::sync::{Arc, Mutex};
+
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/wasm_glue");
+        let root = url::Url::from_directory_path(dir).unwrap().into_string();
+        // what each import was resolved from, and whether that was a wasm module
+        let referers = Arc::new(Mutex::new(vec![]));
+        let recorded = referers.clone();
+        let disk = LocalDiskModuleResolver::new(Some(root.clone())).unwrap();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "file",
+                Box::new(move |specifier: &str, referer_info: Option<RefererInfo>| {
+                    if let Some(ref referer) = referer_info {
+                        recorded.lock().unwrap().push((
+                            specifier.to_string(),
+                            referer.origin_url.clone(),
+                            referer.is_wasm,
+                        ));
+                    }
+                    disk.resolve_module(specifier, referer_info)
+                }),
+            )) as Box<ModuleResolver>],
+            Some(root.clone()),
+        )
+        .with_resolution_cache(ResolutionCache::new());
+
+        let summary = manager.preload("./main.js").unwrap();
+        assert_eq!(Vec::<String>::new(), summary.failures);
+        // main, both glues, the bundler glue's bindings and both binaries
+        assert_eq!(6, summary.modules);
+
+        let referers = referers.lock().unwrap().clone();
+        let from = |specifier: &str| {
+            referers
+                .iter()
+                .filter(|(s, _, _)| s == specifier)
+                .map(|(_, referer, is_wasm)| (referer.as_str(), *is_wasm))
+                .collect::<Vec<_>>()
+        };
+        let (pkg, web) = (format!("{}pkg/", root), format!("{}web/", root));
+        assert_eq!(
+            vec![
+                (&*format!("{}answer.js", pkg), Some(false)),
+                (&*format!("{}answer_bg.wasm", pkg), Some(true))
+            ],
+            from("./answer_bg.js")
+        );
+        // fetched by url, and instantiated with the glue's own imports
+        let web_wasm = format!("{}answer_bg.wasm", web);
+        assert_eq!(
+            vec![(&*format!("{}answer.js", web), Some(false))],
+            from(&web_wasm)
+        );
+        assert!(from("wbg").is_empty());
+
+        for (specifier, referer) in &[
+            ("./answer_bg.wasm", format!("{}answer.js", pkg)),
+            (web_wasm.as_str(), format!("{}answer.js", web)),
+        ] {
+            let module = manager
+                .resolve_blocking(
+                    specifier.to_string(),
+                    Some(RefererInfo {
+                        origin_url: referer.clone(),
+                        is_wasm: Some(false),
+                        source_code: None,
+                        identifier_hash: None,
+                        line: None,
+                        column: None,
+                        app_id: None,
+                        deadline: None,
+                    }),
+                )
+                .unwrap();
+            assert_eq!(ModuleKind::Wasm, module.loaded_source.kind);
+            let path = url::Url::parse(&module.origin_url)
+                .unwrap()
+                .to_file_path()
+                .unwrap();
+            assert_eq!(
+                std::fs::read(path).unwrap(),
+                wasm_bytes(&module.loaded_source).unwrap().to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_needs_a_resolution_cache() {
+        let manager = StandardModuleResolverManager::new(vec![], Some("file:///app/".to_string()));
+        let err = manager.preload("./main.ts").err().unwrap();
+        assert!(err.to_string().contains("resolution cache"));
+    }
+}
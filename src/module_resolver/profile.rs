@@ -0,0 +1,245 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/**
+ * Timings for the first load of one module.
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct ModuleLoadProfile {
+    pub origin_url: String,
+    pub protocol: String,
+    /** The `name` of the resolver that resolved it. */
+    pub resolver: String,
+    pub resolve_ms: f64,
+    pub load_ms: f64,
+    pub transform_ms: f64,
+    pub transfer_bytes: usize,
+    pub cache_hit: bool,
+}
+
+impl ModuleLoadProfile {
+    pub fn total_ms(&self) -> f64 {
+        self.resolve_ms + self.load_ms + self.transform_ms
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ProtocolTotals {
+    pub modules: usize,
+    pub resolve_ms: f64,
+    pub load_ms: f64,
+    pub transform_ms: f64,
+    pub transfer_bytes: usize,
+}
+
+/**
+ * Where cold start time went: every module loaded so far, slowest first, with totals per
+ * protocol and shared cache hit ratio.
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct StartupReport {
+    pub modules: Vec<ModuleLoadProfile>,
+    pub protocols: BTreeMap<String, ProtocolTotals>,
+    pub total_ms: f64,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+    pub cache_hit_ratio: f64,
+}
+
+/**
+ * How many modules the text report lists.
+ */
+const SLOWEST_MODULES_SHOWN: usize = 10;
+
+impl StartupReport {
+    pub fn new(mut modules: Vec<ModuleLoadProfile>) -> Self {
+        modules.sort_by(|a, b| {
+            b.total_ms()
+                .partial_cmp(&a.total_ms())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut protocols: BTreeMap<String, ProtocolTotals> = BTreeMap::new();
+        for module in &modules {
+            let totals = protocols.entry(module.protocol.clone()).or_default();
+            totals.modules += 1;
+            totals.resolve_ms += module.resolve_ms;
+            totals.load_ms += module.load_ms;
+            totals.transform_ms += module.transform_ms;
+            totals.transfer_bytes += module.transfer_bytes;
+        }
+        let cache_hits = modules.iter().filter(|m| m.cache_hit).count();
+        let cache_misses = modules.len() - cache_hits;
+        Self {
+            total_ms: modules.iter().map(|m| m.total_ms()).sum(),
+            cache_hit_ratio: if modules.is_empty() {
+                0.0
+            } else {
+                cache_hits as f64 / modules.len() as f64
+            },
+            modules,
+            protocols,
+            cache_hits,
+            cache_misses,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Startup: {} modules in {:.1}ms",
+            self.modules.len(),
+            self.total_ms
+        )?;
+        writeln!(f, "Slowest modules:")?;
+        for module in self.modules.iter().take(SLOWEST_MODULES_SHOWN) {
+            writeln!(
+                f,
+                "  {:>8.1}ms  resolve {:.1}ms  load {:.1}ms  transform {:.1}ms  {} bytes  {}{}",
+                module.total_ms(),
+                module.resolve_ms,
+                module.load_ms,
+                module.transform_ms,
+                module.transfer_bytes,
+                module.origin_url,
+                if module.cache_hit { " (cached)" } else { "" }
+            )?;
+        }
+        writeln!(f, "Per protocol:")?;
+        for (protocol, totals) in &self.protocols {
+            writeln!(
+                f,
+                "  {}: {} modules, resolve {:.1}ms, load {:.1}ms, transform {:.1}ms, {} bytes",
+                protocol,
+                totals.modules,
+                totals.resolve_ms,
+                totals.load_ms,
+                totals.transform_ms,
+                totals.transfer_bytes
+            )?;
+        }
+        write!(
+            f,
+            "Cache: {} hits, {} misses ({:.0}% hit ratio)",
+            self.cache_hits,
+            self.cache_misses,
+            self.cache_hit_ratio * 100.0
+        )
+    }
+}
+
+pub fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0
+}
+
+#[derive(Default)]
+struct ProfilerState {
+    seen: HashSet<String>,
+    modules: Vec<ModuleLoadProfile>,
+}
+
+/**
+ * Records the first load of each module a manager serves. Later loads of the same origin
+ * url are ignored, since they aren't part of the cold start.
+ */
+#[derive(Default)]
+pub struct StartupProfiler {
+    state: Mutex<ProfilerState>,
+}
+
+impl StartupProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, profile: ModuleLoadProfile) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.seen.insert(profile.origin_url.clone()) {
+                state.modules.push(profile);
+            }
+        }
+    }
+
+    pub fn report(&self) -> StartupReport {
+        let modules = match self.state.lock() {
+            Ok(state) => state.modules.clone(),
+            Err(_) => vec![],
+        };
+        StartupReport::new(modules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        JsonSecretsResolver, ModuleResolver, ModuleResolverManager, StandardModuleResolverManager,
+    };
+
+    fn profile(
+        origin_url: &str,
+        protocol: &str,
+        load_ms: f64,
+        cache_hit: bool,
+    ) -> ModuleLoadProfile {
+        ModuleLoadProfile {
+            origin_url: origin_url.to_string(),
+            protocol: protocol.to_string(),
+            resolver: "LocalDiskModuleResolver".to_string(),
+            resolve_ms: 1.0,
+            load_ms,
+            transform_ms: 0.0,
+            transfer_bytes: 10,
+            cache_hit,
+        }
+    }
+
+    #[test]
+    fn test_report_orders_and_totals() {
+        let profiler = StartupProfiler::new();
+        profiler.record(profile("file:///app/a.js", "file", 5.0, false));
+        profiler.record(profile("file:///app/b.js", "file", 50.0, false));
+        profiler.record(profile("secrets:///db.js", "secrets", 1.0, true));
+        profiler.record(profile("file:///app/a.js", "file", 500.0, false));
+
+        let report = profiler.report();
+        assert_eq!(3, report.modules.len());
+        assert_eq!("file:///app/b.js", report.modules[0].origin_url);
+        assert_eq!(2, report.protocols["file"].modules);
+        assert_eq!(20, report.protocols["file"].transfer_bytes);
+        assert_eq!(1, report.cache_hits);
+        assert_eq!(2, report.cache_misses);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(3, json["modules"].as_array().unwrap().len());
+        assert!(format!("{}", report).contains("Slowest modules"));
+    }
+
+    #[test]
+    fn test_manager_records_first_loads() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(JsonSecretsResolver::new(
+                serde_json::from_str(r#"{"db":{"password":"hunter2"}}"#).unwrap(),
+            )) as Box<ModuleResolver>],
+            Some("secrets:///".to_string()),
+        );
+        manager
+            .resolve_blocking("secrets:///db".to_string(), None)
+            .unwrap();
+        manager
+            .resolve_blocking("secrets:///db".to_string(), None)
+            .unwrap();
+
+        let report = manager.startup_report().unwrap();
+        assert_eq!(1, report.modules.len());
+        assert_eq!("secrets", report.modules[0].protocol);
+        assert!(report.modules[0].transfer_bytes > 0);
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadProgress {
+    pub url: String,
+    pub received: u64,
+    /** From `Content-Length`, when the server sent one. */
+    pub total: Option<u64>,
+    pub done: bool,
+}
+
+/**
+ * Receives progress for module downloads. Called on the thread doing the download, so
+ * implementations should hand the event off rather than block.
+ */
+pub trait ProgressObserver: Send + Sync {
+    fn on_progress(&self, progress: &DownloadProgress);
+}
+
+#[derive(Clone, Debug)]
+pub struct ProgressOptions {
+    /** Downloads smaller than this report nothing. */
+    pub min_bytes: u64,
+    /** At most one event per interval, plus the final one. */
+    pub interval: Duration,
+}
+
+impl Default for ProgressOptions {
+    fn default() -> Self {
+        Self {
+            min_bytes: 1024 * 1024,
+            interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/**
+ * Turns the chunks of one download into throttled progress events. Nothing is emitted until
+ * the download is known to reach `min_bytes`, either from its length or from what has
+ * arrived, so small modules stay quiet.
+ */
+pub struct DownloadProgressReporter {
+    progress: DownloadProgress,
+    observer: Arc<ProgressObserver>,
+    options: ProgressOptions,
+    last_emit: Option<Instant>,
+}
+
+impl DownloadProgressReporter {
+    pub fn new(
+        url: &str,
+        total: Option<u64>,
+        observer: Arc<ProgressObserver>,
+        options: ProgressOptions,
+    ) -> Self {
+        Self {
+            progress: DownloadProgress {
+                url: url.to_string(),
+                received: 0,
+                total,
+                done: false,
+            },
+            observer,
+            options,
+            last_emit: None,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.progress.received += bytes;
+        if !self.reportable() {
+            return;
+        }
+        let due = match self.last_emit {
+            Some(at) => at.elapsed() >= self.options.interval,
+            None => true,
+        };
+        if due {
+            self.emit();
+        }
+    }
+
+    pub fn finish(mut self) {
+        self.progress.done = true;
+        if self.last_emit.is_some() || self.reportable() {
+            self.emit();
+        }
+    }
+
+    fn reportable(&self) -> bool {
+        self.progress.total.unwrap_or(0) >= self.options.min_bytes
+            || self.progress.received >= self.options.min_bytes
+    }
+
+    fn emit(&mut self) {
+        self.last_emit = Some(Instant::now());
+        self.observer.on_progress(&self.progress);
+    }
+}
+
+/**
+ * Fans progress out to subscribers, each watching one url or, with `None`, every download.
+ * Subscriptions end when `unsubscribe` is called or the receiver is dropped.
+ */
+#[derive(Default)]
+pub struct ProgressHub {
+    next_token: AtomicUsize,
+    subscribers: Mutex<HashMap<usize, (Option<String>, mpsc::Sender<DownloadProgress>)>>,
+}
+
+impl ProgressHub {
+    pub fn subscribe(&self, url: Option<&str>) -> (usize, mpsc::Receiver<DownloadProgress>) {
+        let token = self.next_token.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = mpsc::channel();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .insert(token, (url.map(|u| u.to_string()), tx));
+        (token, rx)
+    }
+
+    pub fn unsubscribe(&self, token: usize) {
+        self.subscribers.lock().unwrap().remove(&token);
+    }
+}
+
+impl ProgressObserver for ProgressHub {
+    fn on_progress(&self, progress: &DownloadProgress) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|_, (url, tx)| match url {
+                Some(url) if url != &progress.url => true,
+                _ => tx.send(progress.clone()).is_ok(),
+            });
+    }
+}
+
+lazy_static! {
+    static ref PROCESS_WIDE: Arc<ProgressHub> = Arc::new(ProgressHub::default());
+}
+
+/**
+ * The hub the default fetcher reports to.
+ */
+pub fn process_wide_progress() -> Arc<ProgressHub> {
+    PROCESS_WIDE.clone()
+}
+
+/**
+ * A one-line rendering for terminals, e.g. `https://x/app.wasm 12.0/30.5 MB (39%)`.
+ */
+pub fn format_progress(progress: &DownloadProgress) -> String {
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    match progress.total {
+        Some(total) if total > 0 => format!(
+            "{} {:.1}/{:.1} MB ({}%)",
+            progress.url,
+            mb(progress.received),
+            mb(total),
+            progress.received * 100 / total
+        ),
+        _ => format!("{} {:.1} MB", progress.url, mb(progress.received)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ProgressOptions {
+        ProgressOptions {
+            min_bytes: 100,
+            interval: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_small_downloads_are_quiet() {
+        let hub = Arc::new(ProgressHub::default());
+        let (_, rx) = hub.subscribe(None);
+        let mut reporter = DownloadProgressReporter::new(
+            "https://a.test/small.js",
+            Some(50),
+            hub.clone(),
+            options(),
+        );
+        reporter.advance(50);
+        reporter.finish();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_throttles_and_reports_completion() {
+        let hub = Arc::new(ProgressHub::default());
+        let (_, all) = hub.subscribe(None);
+        let (_, other) = hub.subscribe(Some("https://a.test/other.wasm"));
+        let mut reporter = DownloadProgressReporter::new(
+            "https://a.test/app.wasm",
+            Some(300),
+            hub.clone(),
+            options(),
+        );
+        reporter.advance(100);
+        reporter.advance(100);
+        reporter.advance(100);
+        reporter.finish();
+        let events: Vec<DownloadProgress> = all.try_iter().collect();
+        assert_eq!(2, events.len());
+        assert_eq!((100, false), (events[0].received, events[0].done));
+        assert_eq!((300, true), (events[1].received, events[1].done));
+        assert_eq!(
+            "https://a.test/app.wasm 0.0/0.0 MB (100%)",
+            format_progress(&events[1])
+        );
+        assert!(other.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_unknown_length_reports_after_threshold() {
+        let hub = Arc::new(ProgressHub::default());
+        let (token, rx) = hub.subscribe(Some("https://a.test/big.js"));
+        let mut reporter =
+            DownloadProgressReporter::new("https://a.test/big.js", None, hub.clone(), options());
+        reporter.advance(60);
+        assert!(rx.try_recv().is_err());
+        reporter.advance(60);
+        assert_eq!(120, rx.try_recv().unwrap().received);
+        hub.unsubscribe(token);
+        reporter.finish();
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,329 @@
+use crate::errors::*;
+use crate::settings::RedisStoreConfig;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use r2d2_redis::RedisConnectionManager;
+use r2d2_redis::{r2d2, redis};
+
+use super::{
+    parse_url, wasm_source, LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData,
+    RefererInfo, ResolverDescription, SourceLoader,
+};
+
+/** How long to wait for a connection before giving up on the server. */
+pub const DEFAULT_REDIS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/**
+ * Suffixes tried after the exact path, in order, like the release bundle resolver's.
+ */
+const PROBE_SUFFIXES: &[&str] = &["", ".ts", ".js", "/index.ts", "/index.js"];
+
+type Pool = r2d2::Pool<RedisConnectionManager>;
+
+fn connection_error(url: &str, e: impl std::fmt::Display) -> FlyError {
+    new(
+        ErrorKind::ConnectionRefused,
+        format!("could not connect to redis at {}: {}", url, e),
+    )
+}
+
+fn redis_error(url: &str, e: redis::RedisError) -> FlyError {
+    let kind = if e.is_connection_refusal() || e.is_connection_dropped() {
+        ErrorKind::ConnectionRefused
+    } else if e.is_timeout() {
+        ErrorKind::TimedOut
+    } else {
+        ErrorKind::Other
+    };
+    new(kind, format!("redis at {}: {}", url, e))
+}
+
+/**
+ * Serves modules published into Redis, `<protocol>://<app>/<path>` from the key
+ * `<namespace>:<app>/<path>`, or `<app>/<path>` without a namespace. A module's source map,
+ * if any, is at its key plus `.map`, and a hash at its key plus `.meta` can say what it is:
+ * `is_wasm` set to `true` or a `content_type` of `application/wasm` marks the value as wasm.
+ *
+ * Lookups block on the server, so this resolver must not be asked for the runtime's thread;
+ * managers run it on the blocking pool.
+ */
+pub struct RedisModuleResolver {
+    protocol: String,
+    url: String,
+    namespace: Option<String>,
+    pool: Pool,
+}
+
+impl RedisModuleResolver {
+    /**
+     * Resolves `protocol`'s modules from the server at `config.url`. Nothing is connected to
+     * until the first lookup, so a server that's down fails resolutions, not this.
+     */
+    pub fn new(protocol: &str, config: &RedisStoreConfig) -> FlyResult<Self> {
+        Ok(Self {
+            protocol: protocol.to_string(),
+            url: config.url.clone(),
+            namespace: config.namespace.clone(),
+            pool: build_pool(&config.url, DEFAULT_REDIS_CONNECT_TIMEOUT)?,
+        })
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> FlyResult<Self> {
+        self.pool = build_pool(&self.url, timeout)?;
+        Ok(self)
+    }
+
+    /** The key `path` of `app` is published at. */
+    pub fn key(&self, app: &str, path: &str) -> String {
+        match self.namespace {
+            Some(ref namespace) => format!("{}:{}/{}", namespace, app, path),
+            None => format!("{}/{}", app, path),
+        }
+    }
+
+    /** The path `path` of `app` resolves to, trying each probe suffix in order. */
+    fn probe(&self, app: &str, path: &str) -> FlyResult<Option<String>> {
+        let base = path.trim_end_matches('/');
+        let candidates: Vec<String> = PROBE_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{}{}", base, suffix))
+            .collect();
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| connection_error(&self.url, e))?;
+        let mut pipe = redis::pipe();
+        for candidate in &candidates {
+            pipe.cmd("EXISTS").arg(self.key(app, candidate));
+        }
+        let exists: Vec<bool> = pipe.query(&*conn).map_err(|e| redis_error(&self.url, e))?;
+        Ok(candidates
+            .into_iter()
+            .zip(exists)
+            .find(|(_, exists)| *exists)
+            .map(|(candidate, _)| candidate))
+    }
+}
+
+fn build_pool(url: &str, connect_timeout: Duration) -> FlyResult<Pool> {
+    let manager = RedisConnectionManager::new(url)
+        .map_err(|e| FlyError::from(format!("invalid redis url {}: {}", url, e)))?;
+    Ok(r2d2::Pool::builder()
+        .max_size(4)
+        .min_idle(Some(0))
+        .connection_timeout(connect_timeout)
+        .build_unchecked(manager))
+}
+
+impl ModuleResolver for RedisModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => format!("{}:///", self.protocol),
+        };
+        let url = parse_url(module_specifier, &referer_origin_url)?;
+        let app = match url.host_str() {
+            Some(app) if !app.is_empty() => app.to_string(),
+            _ => {
+                return Err(new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Could not resolve {} from {}: no app named, expected {}://<app>/<path>",
+                        module_specifier, referer_origin_url, self.protocol
+                    ),
+                ))
+            }
+        };
+        let path = url.path().trim_start_matches('/');
+        let found = match self.probe(&app, path)? {
+            Some(found) => found,
+            None => {
+                return Err(new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "Could not resolve {} from {}: no key {} in redis at {}",
+                        module_specifier,
+                        referer_origin_url,
+                        self.key(&app, path),
+                        self.url
+                    ),
+                ))
+            }
+        };
+        Ok(ModuleSourceData {
+            origin_url: format!("{}://{}/{}", self.protocol, app, found),
+            source_loader: Box::new(RedisSourceLoader {
+                url: self.url.clone(),
+                pool: self.pool.clone(),
+                key: self.key(&app, &found),
+            }),
+        })
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn is_remote(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("redis", &self.protocol)
+            .named(&self.name())
+            .with("url", &self.url)
+            .with(
+                "namespace",
+                self.namespace.as_ref().map_or("", |ns| ns.as_str()),
+            )
+    }
+}
+
+/** Loads a module's value, source map and metadata from Redis in one round trip. */
+pub struct RedisSourceLoader {
+    url: String,
+    pool: Pool,
+    key: String,
+}
+
+impl SourceLoader for RedisSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| connection_error(&self.url, e))?;
+        let (bytes, source_map, meta): (Option<Vec<u8>>, Option<String>, HashMap<String, String>) =
+            redis::pipe()
+                .cmd("GET")
+                .arg(&self.key)
+                .cmd("GET")
+                .arg(format!("{}.map", self.key))
+                .cmd("HGETALL")
+                .arg(format!("{}.meta", self.key))
+                .query(&*conn)
+                .map_err(|e| redis_error(&self.url, e))?;
+        let bytes = bytes.ok_or_else(|| {
+            new(
+                ErrorKind::NotFound,
+                format!("{} was removed from redis at {}", self.key, self.url),
+            )
+        })?;
+        let is_wasm = meta
+            .get("is_wasm")
+            .map_or(false, |v| v == "true" || v == "1")
+            || meta
+                .get("content_type")
+                .map_or(false, |v| v == "application/wasm");
+        if is_wasm {
+            return Ok(wasm_source(&bytes));
+        }
+        let source = String::from_utf8(bytes).map_err(|_| {
+            new(
+                ErrorKind::InvalidData,
+                format!("{} in redis at {} is not utf-8 text", self.key, self.url),
+            )
+        })?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: source_map.map(Into::into),
+            source: source.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(url: &str) -> RedisModuleResolver {
+        RedisModuleResolver::new(
+            "app",
+            &RedisStoreConfig {
+                url: url.to_string(),
+                namespace: Some("modtest".to_string()),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_redis_module_errors_are_distinct() {
+        let refused = resolver("redis://127.0.0.1:1")
+            .with_connect_timeout(Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(
+            "modtest:shop/lib/util.ts",
+            refused.key("shop", "lib/util.ts")
+        );
+        let err = refused
+            .resolve_module("app://shop/main.ts", None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ConnectionRefused, err.kind(), "{}", err);
+    }
+
+    #[test]
+    fn test_redis_module_resolve() {
+        let resolver = resolver("redis://localhost:6379");
+        let conn = resolver.pool.get().unwrap();
+        redis::pipe()
+            .cmd("SET")
+            .arg(resolver.key("shop", "main.ts"))
+            .arg("import './lib/util';")
+            .cmd("SET")
+            .arg(resolver.key("shop", "lib/util.ts"))
+            .arg("export default 1;")
+            .cmd("SET")
+            .arg(resolver.key("shop", "lib/util.ts.map"))
+            .arg("{\"version\":3}")
+            .cmd("SET")
+            .arg(resolver.key("shop", "add.wasm"))
+            .arg(&b"\0asm"[..])
+            .cmd("HSET")
+            .arg(resolver.key("shop", "add.wasm.meta"))
+            .arg("content_type")
+            .arg("application/wasm")
+            .query::<()>(&*conn)
+            .unwrap();
+
+        let main = resolver.resolve_module("app://shop/main.ts", None).unwrap();
+        let util = resolver
+            .resolve_module(
+                "./lib/util",
+                Some(RefererInfo {
+                    origin_url: main.origin_url,
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+            .unwrap();
+        assert_eq!("app://shop/lib/util.ts", util.origin_url);
+        let loaded = util.source_loader.load_source().unwrap();
+        assert_eq!("export default 1;", &*loaded.source);
+        assert_eq!(Some("{\"version\":3}".into()), loaded.source_map);
+
+        let wasm = resolver
+            .resolve_module("app://shop/add.wasm", None)
+            .unwrap();
+        assert!(wasm.source_loader.load_source().unwrap().is_wasm());
+
+        let err = resolver
+            .resolve_module("app://shop/missing.ts", None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::errors::*;
+use crate::runtime::EVENT_LOOP;
+
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+use hyper::Client;
+use hyper_tls::HttpsConnector;
+use native_tls::TlsConnector;
+
+use super::{
+    policy_connector, DenoDirCache, FetchNetworkPolicy, FetchNetworkPolicyConfig, FetchOptions,
+    HttpFetcher, HttpModuleResolver, HyperFetcher, ModuleResolver,
+};
+
+/**
+ * The url of an app entry given as an `http`/`https` url rather than a path.
+ */
+pub fn remote_entry_url(entry: &str) -> Option<url::Url> {
+    match url::Url::parse(entry) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Some(url),
+        _ => None,
+    }
+}
+
+/**
+ * The `module_fetch_network` policy, or the default one blocking internal addresses.
+ */
+pub fn network_policy(config: Option<&FetchNetworkPolicyConfig>) -> FlyResult<FetchNetworkPolicy> {
+    match config {
+        Some(config) => FetchNetworkPolicy::from_config(config),
+        None => Ok(FetchNetworkPolicy::default()),
+    }
+}
+
+/**
+ * Resolvers for `https` and `http` modules whose fetches only connect where `policy` allows,
+ * following redirects as `options` say. Modules already in the user's Deno cache are read
+ * from there instead.
+ */
+pub fn remote_module_resolvers(
+    policy: Arc<FetchNetworkPolicy>,
+    options: FetchOptions,
+) -> FlyResult<Vec<Box<ModuleResolver>>> {
+    let tls = TlsConnector::new()
+        .map_err(|e| FlyError::from(format!("could not create TLS connector: {}", e)))?;
+    let connector = HttpsConnector::from((policy_connector(policy.clone()), tls));
+    let fetcher: Arc<HttpFetcher> = Arc::new(
+        HyperFetcher::from_client(
+            Client::builder()
+                .executor(EVENT_LOOP.0.clone())
+                .build(connector),
+        )
+        .with_network_policy(Some(policy)),
+    );
+    let deno_dir = DenoDirCache::from_env();
+    Ok(vec!["https", "http"]
+        .into_iter()
+        .map(|protocol| {
+            let resolver = HttpModuleResolver::new(protocol)
+                .with_fetcher(fetcher.clone())
+                .with_options(options.clone());
+            Box::new(match deno_dir {
+                Some(ref deno_dir) => resolver.with_deno_dir(deno_dir.clone()),
+                None => resolver,
+            }) as Box<ModuleResolver>
+        })
+        .collect())
+}
+
+/**
+ * Applies `policy` to a remote entry before anything is fetched from it. A blocked entry
+ * fails with `PermissionDenied`; a host that doesn't resolve at all is `NotFound`, like any
+ * other entry that can't be fetched.
+ */
+pub fn check_remote_entry(url: &url::Url, policy: &FetchNetworkPolicy) -> FlyResult<()> {
+    policy.check_url(url)?;
+    let host = match url.host() {
+        Some(url::Host::Domain(host)) => host,
+        // ip addresses were checked above
+        _ => return Ok(()),
+    };
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs = (host, port).to_socket_addrs().map_err(|e| {
+        new(
+            ErrorKind::NotFound,
+            format!("could not fetch entry {}: {}", url, e),
+        )
+    })?;
+    policy
+        .filter_resolved(host, addrs.map(|addr| addr.ip()).collect())
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        ModuleResolverManager, RefererInfo, StandardModuleResolverManager,
+    };
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /**
+     * Serves a two-module app on a local port until the test process exits.
+     */
+    fn serve_app() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    if reader.read_line(&mut header).unwrap() == 0 || header == "\r\n" {
+                        break;
+                    }
+                }
+                let (status, body) = match request_line.split(' ').nth(1) {
+                    Some("/app/entry.ts") => ("200 OK", "import { greet } from './dep.ts';"),
+                    Some("/app/dep.ts") => ("200 OK", "export const greet = 'hello';"),
+                    _ => ("404 Not Found", ""),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        port
+    }
+
+    fn local_policy() -> FetchNetworkPolicy {
+        FetchNetworkPolicy::from_config(&FetchNetworkPolicyConfig {
+            blocked_ranges: None,
+            allowed_hosts: None,
+            allowed_ranges: Some(vec!["127.0.0.1".to_string()]),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_runs_an_app_from_a_local_server() {
+        let port = serve_app();
+        let entry = remote_entry_url(&format!("http://127.0.0.1:{}/app/entry.ts", port)).unwrap();
+        let policy = local_policy();
+        check_remote_entry(&entry, &policy).unwrap();
+
+        let manager = StandardModuleResolverManager::new(
+            remote_module_resolvers(Arc::new(policy), FetchOptions::default()).unwrap(),
+            None,
+        );
+        let module = manager.resolve_blocking(entry.to_string(), None).unwrap();
+        assert_eq!(entry.as_str(), module.origin_url);
+        assert!(module.loaded_source.source.contains("./dep.ts"));
+
+        let dep = manager
+            .resolve_blocking(
+                "./dep.ts".to_string(),
+                Some(RefererInfo {
+                    origin_url: module.origin_url.clone(),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+            .unwrap();
+        assert_eq!(
+            format!("http://127.0.0.1:{}/app/dep.ts", port),
+            dep.origin_url
+        );
+        assert_eq!("export const greet = 'hello';", &*dep.loaded_source.source);
+    }
+
+    #[test]
+    fn test_blocked_entries_fail_differently_from_unfetchable_ones() {
+        let port = serve_app();
+        let entry = remote_entry_url(&format!("http://127.0.0.1:{}/app/entry.ts", port)).unwrap();
+        let err = check_remote_entry(&entry, &FetchNetworkPolicy::default())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+
+        let manager = StandardModuleResolverManager::new(
+            remote_module_resolvers(Arc::new(local_policy()), FetchOptions::default()).unwrap(),
+            None,
+        );
+        let missing = format!("http://127.0.0.1:{}/app/missing.ts", port);
+        let err = manager.resolve_blocking(missing, None).err().unwrap();
+        assert_ne!(ErrorKind::PermissionDenied, err.kind());
+        assert!(format!("{}", err).contains("404"), format!("{}", err));
+
+        assert!(remote_entry_url("./index.ts").is_none());
+        assert!(remote_entry_url("file:///app/index.ts").is_none());
+    }
+}
@@ -0,0 +1,475 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::Future;
+
+use super::{CancellationToken, LoadedModule, LoadedModuleFuture, SingleFlight};
+
+/** A load's error, kept so every import waiting on the load gets it too. */
+type SharedLoadResult = FlyResult<LoadedModule>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct ResolutionCacheStats {
+    /** Imports served without asking a resolver or loading anything. */
+    pub hits: u64,
+    /** Imports that had to be resolved and loaded. */
+    pub misses: u64,
+    /** Distinct modules held. */
+    pub modules: usize,
+    /** Imports failed straight away because they failed within the negative TTL. */
+    pub negative_hits: u64,
+    /** Failed resolutions held, expired ones included until they're looked up. */
+    pub known_missing: usize,
+}
+
+#[derive(Default)]
+struct ResolutionCacheInner {
+    /** (specifier, referer origin url) -> origin url of the module it resolved to */
+    resolutions: HashMap<(String, String), String>,
+    modules: HashMap<String, LoadedModule>,
+    /** (specifier, referer origin url) -> how it failed, and until when to fail it again */
+    missing: HashMap<(String, String), (FlyError, Instant)>,
+    stats: ResolutionCacheStats,
+}
+
+/**
+ * Remembers what a manager's imports resolved to and the modules they loaded, so every
+ * later import of the same specifier from the same referer, or of any specifier ending up
+ * at the same origin url, reuses the module instead of probing and reading it again. Imports
+ * of a module that is still loading wait for that load. Clones share the same cache.
+ * Modules never expire on their own; dev workflows `invalidate` what changed. Failures are
+ * only remembered with `with_negative_ttl`, and only for that long.
+ */
+#[derive(Clone, Default)]
+pub struct ResolutionCache {
+    inner: Arc<Mutex<ResolutionCacheInner>>,
+    flights: SingleFlight<SharedLoadResult>,
+    negative_ttl: Option<Duration>,
+}
+
+impl ResolutionCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Fail imports of a module that wasn't found, from the same referer, with the same error
+     * for `ttl` after, without probing for it again. For apps probing optional modules on
+     * every request. Any `invalidate` forgets every failure, since the changed file may be the
+     * one missing. `None`, the default, remembers no failures, as development wants.
+     */
+    pub fn with_negative_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /**
+     * How importing `specifier` from `referer_origin_url` failed, if it did within the
+     * negative TTL. The error is the original, structured one.
+     */
+    pub fn known_missing(&self, specifier: &str, referer_origin_url: &str) -> Option<FlyError> {
+        self.negative_ttl?;
+        let mut inner = self.inner.lock().unwrap();
+        let key = (specifier.to_string(), referer_origin_url.to_string());
+        let error = match inner.missing.get(&key) {
+            Some((error, until)) if *until > Instant::now() => error.clone(),
+            Some(_) => {
+                inner.missing.remove(&key);
+                inner.stats.known_missing = inner.missing.len();
+                return None;
+            }
+            None => return None,
+        };
+        inner.stats.negative_hits += 1;
+        Some(error)
+    }
+
+    /**
+     * Remembers that importing `specifier` from `referer_origin_url` failed with `error`, if
+     * failures are remembered and it's one saying the module isn't there. Others, like
+     * timeouts, may well pass next time.
+     */
+    pub(crate) fn record_missing(
+        &self,
+        specifier: &str,
+        referer_origin_url: &str,
+        error: &FlyError,
+    ) {
+        let ttl = match self.negative_ttl {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        match error.kind() {
+            ErrorKind::ModuleNotFound | ErrorKind::NotFound => {}
+            _ => return,
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.missing.insert(
+            (specifier.to_string(), referer_origin_url.to_string()),
+            (error.clone(), Instant::now() + ttl),
+        );
+        inner.stats.known_missing = inner.missing.len();
+    }
+
+    /**
+     * The module `specifier`, imported from `referer_origin_url`, resolved to before.
+     */
+    pub fn get(&self, specifier: &str, referer_origin_url: &str) -> Option<LoadedModule> {
+        let mut inner = self.inner.lock().unwrap();
+        let module = {
+            let key = (specifier.to_string(), referer_origin_url.to_string());
+            let origin_url = inner.resolutions.get(&key)?;
+            inner.modules.get(origin_url)?.clone()
+        };
+        inner.stats.hits += 1;
+        Some(module)
+    }
+
+    /**
+     * The module at `origin_url`, which `specifier` was just resolved to, calling `load`
+     * only if it wasn't loaded before and isn't loading already.
+     */
+    pub(crate) fn load<F>(
+        &self,
+        specifier: String,
+        referer_origin_url: String,
+        origin_url: &str,
+        load: F,
+    ) -> LoadedModuleFuture
+    where
+        F: FnOnce() -> LoadedModuleFuture,
+    {
+        let key = (specifier, referer_origin_url);
+        {
+            let mut inner = self.inner.lock().unwrap();
+            let cached = inner.modules.get(origin_url).cloned();
+            if let Some(module) = cached {
+                inner.stats.hits += 1;
+                inner.resolutions.insert(key, origin_url.to_string());
+                return Box::new(futures::future::ok(module));
+            }
+        }
+        let inner = self.inner.clone();
+        let origin_url = origin_url.to_string();
+        let scope = CancellationToken::new();
+        let waiting = scope.clone();
+        // imports that join a load someone else started count as hits
+        let started = Arc::new(AtomicBool::new(false));
+        let starting = started.clone();
+        let shared_load = move || {
+            starting.store(true, Ordering::SeqCst);
+            load().then(Ok)
+        };
+        Box::new(
+            self.flights
+                .load(&origin_url, &waiting, |_| shared_load())
+                .and_then(|result| result)
+                .map(move |module| {
+                    // held until the load is done, or it'd count as abandoned
+                    drop(scope);
+                    let mut inner = inner.lock().unwrap();
+                    if started.load(Ordering::SeqCst) {
+                        inner.stats.misses += 1;
+                    } else {
+                        inner.stats.hits += 1;
+                    }
+                    inner.resolutions.insert(key, origin_url.clone());
+                    inner.modules.insert(origin_url, module.clone());
+                    inner.stats.modules = inner.modules.len();
+                    module
+                }),
+        )
+    }
+
+    /**
+     * Forgets the module at `origin_url`, and what resolved to it, so the next import of it
+     * loads it afresh. Returns whether it was cached.
+     */
+    pub fn invalidate(&self, origin_url: &str) -> bool {
+        self.evict(origin_url) > 0
+    }
+
+    /**
+     * `invalidate`, returning how many modules were dropped: more than one when several
+     * were redirected to `origin_url`.
+     */
+    pub fn evict(&self, origin_url: &str) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        // by where a redirected module ended up, too
+        let stale: Vec<String> = inner
+            .modules
+            .iter()
+            .filter(|(url, module)| *url == origin_url || module.origin_url == origin_url)
+            .map(|(url, _)| url.clone())
+            .collect();
+        for url in &stale {
+            inner.modules.remove(url);
+        }
+        inner.resolutions.retain(|_, url| !stale.contains(url));
+        inner.stats.modules = inner.modules.len();
+        inner.missing.clear();
+        inner.stats.known_missing = 0;
+        stale.len()
+    }
+
+    /**
+     * Forgets every module, returning how many there were.
+     */
+    pub fn clear(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let cleared = inner.modules.len();
+        inner.modules.clear();
+        inner.resolutions.clear();
+        inner.missing.clear();
+        inner.stats.modules = 0;
+        inner.stats.known_missing = 0;
+        cleared
+    }
+
+    pub fn stats(&self) -> ResolutionCacheStats {
+        self.inner.lock().unwrap().stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        FunctionModuleResolver, LoadedSourceCode, LocalDiskModuleResolver, ModuleKind,
+        ModuleResolutionError, ModuleResolver, ModuleResolverManager, ModuleSourceData,
+        RefererInfo, SourceLoader, StandardModuleResolverManager,
+    };
+    use std::sync::atomic::AtomicUsize;
+
+    struct CountingLoader {
+        loads: Arc<AtomicUsize>,
+        source: Option<&'static str>,
+    }
+
+    impl SourceLoader for CountingLoader {
+        fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            match self.source {
+                Some(source) => Ok(LoadedSourceCode {
+                    kind: ModuleKind::JavaScript,
+                    source_map: None,
+                    source: source.into(),
+                    response: None,
+                    provenance: None,
+                    code_cache: None,
+                    compiled_wasm: None,
+                    wasm: None,
+                }),
+                None => Err(new(ErrorKind::NotFound, "gone".to_string())),
+            }
+        }
+    }
+
+    /**
+     * Resolves `./util` and `./util.ts` to the same module, counting resolutions and loads.
+     */
+    fn counted_manager(
+        cache: &ResolutionCache,
+        source: Option<&'static str>,
+    ) -> (
+        StandardModuleResolverManager,
+        Arc<AtomicUsize>,
+        Arc<AtomicUsize>,
+    ) {
+        let resolves = Arc::new(AtomicUsize::new(0));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let (counted_resolves, counted_loads) = (resolves.clone(), loads.clone());
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "function",
+                Box::new(move |_, _| {
+                    counted_resolves.fetch_add(1, Ordering::SeqCst);
+                    Ok(ModuleSourceData {
+                        origin_url: "function:///app/util.ts".to_string(),
+                        source_loader: Box::new(CountingLoader {
+                            loads: counted_loads.clone(),
+                            source,
+                        }),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            Some("function:///app/".to_string()),
+        )
+        .with_resolution_cache(cache.clone());
+        (manager, resolves, loads)
+    }
+
+    fn referer(origin_url: &str) -> Option<RefererInfo> {
+        Some(RefererInfo {
+            origin_url: origin_url.to_string(),
+            is_wasm: Some(false),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        })
+    }
+
+    #[test]
+    fn test_loads_each_module_once_until_invalidated() {
+        let cache = ResolutionCache::new();
+        let (manager, resolves, loads) = counted_manager(&cache, Some("export default 1;"));
+        let main = || referer("function:///app/main.ts");
+        for _ in 0..3 {
+            let util = manager
+                .resolve_blocking("./util".to_string(), main())
+                .unwrap();
+            assert_eq!("export default 1;", &*util.loaded_source.source);
+        }
+        assert_eq!(1, resolves.load(Ordering::SeqCst));
+        // another specifier for the same module is resolved, but not loaded again
+        manager
+            .resolve_blocking("./util.ts".to_string(), referer("function:///app/lib.ts"))
+            .unwrap();
+        assert_eq!(2, resolves.load(Ordering::SeqCst));
+        assert_eq!(1, loads.load(Ordering::SeqCst));
+        assert_eq!(
+            ResolutionCacheStats {
+                hits: 3,
+                misses: 1,
+                modules: 1,
+                ..Default::default()
+            },
+            cache.stats()
+        );
+
+        assert!(cache.invalidate("function:///app/util.ts"));
+        assert!(!cache.invalidate("function:///app/util.ts"));
+        manager
+            .resolve_blocking("./util".to_string(), main())
+            .unwrap();
+        assert_eq!(2, loads.load(Ordering::SeqCst));
+        cache.clear();
+        manager
+            .resolve_blocking("./util".to_string(), main())
+            .unwrap();
+        assert_eq!(3, loads.load(Ordering::SeqCst));
+        assert_eq!(1, cache.stats().modules);
+    }
+
+    #[test]
+    fn test_concurrent_imports_share_one_load() {
+        let cache = ResolutionCache::new();
+        let (manager, _, loads) = counted_manager(&cache, Some("export default 1;"));
+        let (a, b) = manager
+            .resolve_module("./util".to_string(), referer("function:///app/a.ts"))
+            .join(manager.resolve_module("./util".to_string(), referer("function:///app/b.ts")))
+            .wait()
+            .unwrap();
+        assert_eq!(a.loaded_source.source, b.loaded_source.source);
+        assert_eq!(1, loads.load(Ordering::SeqCst));
+
+        // failures keep their kind and aren't cached
+        let cache = ResolutionCache::new();
+        let (manager, _, loads) = counted_manager(&cache, None);
+        for _ in 0..2 {
+            let err = manager
+                .resolve_blocking("./util".to_string(), None)
+                .err()
+                .unwrap();
+            assert_eq!(ErrorKind::NotFound, err.kind());
+        }
+        assert_eq!(2, loads.load(Ordering::SeqCst));
+    }
+
+    /** The disk resolver, counting how many times it probes. */
+    struct CountingDiskResolver {
+        disk: LocalDiskModuleResolver,
+        probes: Arc<AtomicUsize>,
+    }
+
+    impl ModuleResolver for CountingDiskResolver {
+        fn resolve_module(
+            &self,
+            module_specifier: &str,
+            referer_info: Option<RefererInfo>,
+        ) -> FlyResult<ModuleSourceData> {
+            self.probes.fetch_add(1, Ordering::SeqCst);
+            self.disk.resolve_module(module_specifier, referer_info)
+        }
+        fn get_protocol(&self) -> String {
+            self.disk.get_protocol()
+        }
+    }
+
+    #[test]
+    fn test_remembers_missing_modules_within_the_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("plugins")).unwrap();
+        let root = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let main = format!("{}main.ts", root);
+        let manager = |cache: &ResolutionCache| {
+            let probes = Arc::new(AtomicUsize::new(0));
+            let manager = StandardModuleResolverManager::new(
+                vec![Box::new(CountingDiskResolver {
+                    disk: LocalDiskModuleResolver::new(Some(root.clone())).unwrap(),
+                    probes: probes.clone(),
+                }) as Box<ModuleResolver>],
+                Some(root.clone()),
+            )
+            .with_resolution_cache(cache.clone());
+            (manager, probes)
+        };
+        let import = |manager: &StandardModuleResolverManager| {
+            manager
+                .resolve_blocking("./plugins/x".to_string(), referer(&main))
+                .err()
+                .unwrap()
+        };
+
+        let cache = ResolutionCache::new().with_negative_ttl(Some(Duration::from_millis(200)));
+        let (remembering, probes) = manager(&cache);
+        let first = import(&remembering);
+        for _ in 0..5 {
+            let again = import(&remembering);
+            assert_eq!(ErrorKind::ModuleNotFound, again.kind());
+            match again.resolution_error() {
+                Some(ModuleResolutionError::NotFound { specifier, .. }) => {
+                    assert_eq!("./plugins/x", specifier)
+                }
+                other => panic!("expected a structured not found, got {:?}", other),
+            }
+            assert_eq!(first.to_string(), again.to_string());
+        }
+        assert_eq!(1, probes.load(Ordering::SeqCst));
+        assert_eq!(5, cache.stats().negative_hits);
+
+        // found once it's there and something was invalidated
+        std::fs::write(dir.path().join("plugins/x.ts"), "export default 1;").unwrap();
+        cache.invalidate(&format!("{}plugins/x.ts", root));
+        assert_eq!(0, cache.stats().known_missing);
+        remembering
+            .resolve_blocking("./plugins/x".to_string(), referer(&main))
+            .unwrap();
+        std::fs::remove_file(dir.path().join("plugins/x.ts")).unwrap();
+        cache.clear();
+
+        // and probed again once the ttl is over
+        import(&remembering);
+        let probed = probes.load(Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(250));
+        import(&remembering);
+        assert_eq!(probed + 1, probes.load(Ordering::SeqCst));
+
+        // never remembered without a ttl
+        let (forgetting, probes) = manager(&ResolutionCache::new());
+        for _ in 0..3 {
+            import(&forgetting);
+        }
+        assert_eq!(3, probes.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::errors::*;
+
+use std::fmt;
+use std::time::Duration;
+
+use super::{abbreviate_data_url, ImportType, ModuleKind, ModuleLimit, ResolverDescription};
+
+/**
+ * Why a resolver manager couldn't resolve or load a module. It converts into a `FlyError`
+ * whose kind tells the variants apart, and embedders can get it back with
+ * `FlyError::resolution_error` to match on it.
+ */
+#[derive(Clone, Debug)]
+pub enum ModuleResolutionError {
+    /** Every resolver for the specifier was tried, and none had it. */
+    NotFound {
+        specifier: String,
+        referer: String,
+        /** Whether a fallback resolver was among those tried. */
+        fallback: bool,
+        /** Each resolver tried and why it didn't resolve the module, in the order tried. */
+        attempts: Vec<ResolverAttempt>,
+    },
+    /**
+     * The last resolver for the specifier ran out of time, resolving or loading the module,
+     * after any before it didn't have it or timed out too.
+     */
+    TimedOut {
+        specifier: String,
+        referer: String,
+        attempts: Vec<ResolverAttempt>,
+    },
+    /** No resolver is registered for the specifier's protocol, and there's no fallback. */
+    NoResolverForProtocol {
+        specifier: String,
+        referer: String,
+        scheme: String,
+    },
+    /**
+     * The module was found, but loading its source failed. The source's kind is kept when
+     * it says more than that, e.g. `IntegrityMismatch` or `PermissionDenied`.
+     */
+    LoaderFailed {
+        origin_url: String,
+        source: FlyError,
+    },
+    /** The manager's policy doesn't allow the protocol or host imported. */
+    PolicyDenied {
+        specifier: String,
+        referer: String,
+        /** What was denied, e.g. `protocol https`. */
+        denied: String,
+        policy: String,
+    },
+    /** The specifier isn't a url, and can't be joined to its referer's either. */
+    InvalidSpecifier {
+        specifier: String,
+        referer: String,
+        reason: String,
+    },
+    /** Loading the module would go over one of the manager's `ModuleLimits`. */
+    LimitExceeded {
+        origin_url: String,
+        limit: ModuleLimit,
+        max: u64,
+        /** The module's bytes, its depth or the count of modules with it, by `limit`. */
+        actual: u64,
+    },
+    /** The import asserted a type, as in `with { type: "json" }`, that the module isn't. */
+    TypeMismatch {
+        origin_url: String,
+        asserted: ImportType,
+        kind: ModuleKind,
+    },
+    /**
+     * The top-level load the module was imported under ran out of its
+     * `ResolverOptions::total_deadline`, and what it still had loading was cancelled.
+     */
+    DeadlineExceeded {
+        deadline: Duration,
+        /** Modules loaded under it before it ran out. */
+        completed: usize,
+        /** The import still loading, as `<specifier> from <referer>`; the innermost if several. */
+        in_flight: Option<String>,
+    },
+}
+
+/**
+ * One resolver a manager tried, as it describes itself, and why it didn't resolve the module.
+ */
+#[derive(Clone, Debug)]
+pub struct ResolverAttempt {
+    pub resolver: ResolverDescription,
+    pub error: FlyError,
+}
+
+impl fmt::Display for ResolverAttempt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} for {}:", self.resolver.kind, self.resolver.protocol)?;
+        // where it looked, e.g. a disk resolver's root
+        for (key, value) in &self.resolver.config {
+            write!(f, " {}={}", key, value)?;
+        }
+        write!(f, " {}", self.error)
+    }
+}
+
+impl ModuleResolutionError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ModuleResolutionError::NotFound { .. } => ErrorKind::ModuleNotFound,
+            ModuleResolutionError::TimedOut { .. } => ErrorKind::TimedOut,
+            ModuleResolutionError::NoResolverForProtocol { .. } => ErrorKind::NoResolverForProtocol,
+            ModuleResolutionError::LoaderFailed { source, .. } => match source.kind() {
+                ErrorKind::String | ErrorKind::Other => ErrorKind::ModuleLoadFailed,
+                kind => kind,
+            },
+            ModuleResolutionError::PolicyDenied { .. } => ErrorKind::PolicyDenied,
+            ModuleResolutionError::InvalidSpecifier { .. } => ErrorKind::InvalidSpecifier,
+            ModuleResolutionError::LimitExceeded { .. } => ErrorKind::ModuleLimitExceeded,
+            ModuleResolutionError::TypeMismatch { .. } => ErrorKind::ModuleTypeMismatch,
+            ModuleResolutionError::DeadlineExceeded { .. } => ErrorKind::DeadlineExceeded,
+        }
+    }
+
+    /**
+     * `source`, failing to load `origin_url`, unless it's already a resolution error, e.g.
+     * from a manager the loader resolved through.
+     */
+    pub fn loader_failed(origin_url: &str, source: FlyError) -> FlyError {
+        if source.resolution_error().is_some() {
+            return source;
+        }
+        ModuleResolutionError::LoaderFailed {
+            origin_url: origin_url.to_string(),
+            source,
+        }
+        .into()
+    }
+}
+
+impl fmt::Display for ModuleResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModuleResolutionError::NotFound {
+                specifier,
+                referer,
+                fallback,
+                attempts,
+            } => {
+                write!(
+                    f,
+                    "Could not resolve {} from {}: exhausted all resolvers, {}.",
+                    specifier,
+                    referer,
+                    if *fallback {
+                        "including the fallback"
+                    } else {
+                        "no fallback resolver is registered"
+                    }
+                )?;
+                if !attempts.is_empty() {
+                    write!(f, " Tried, in order:")?;
+                }
+                for (i, attempt) in attempts.iter().enumerate() {
+                    write!(f, "\n  {}. {}", i + 1, attempt)?;
+                }
+                Ok(())
+            }
+            ModuleResolutionError::TimedOut {
+                specifier,
+                referer,
+                attempts,
+            } => {
+                write!(
+                    f,
+                    "Could not resolve {} from {}: timed out. Tried, in order:",
+                    specifier, referer
+                )?;
+                for (i, attempt) in attempts.iter().enumerate() {
+                    write!(f, "\n  {}. {}", i + 1, attempt)?;
+                }
+                Ok(())
+            }
+            ModuleResolutionError::NoResolverForProtocol {
+                specifier,
+                referer,
+                scheme,
+            } => write!(
+                f,
+                "Could not resolve {} from {}: no resolvers for protocol {} setup, and no fallback resolver is registered.",
+                specifier, referer, scheme
+            ),
+            // loaders name the module in their own errors
+            ModuleResolutionError::LoaderFailed { source, .. } => source.fmt(f),
+            ModuleResolutionError::PolicyDenied {
+                specifier,
+                referer,
+                denied,
+                policy,
+            } => write!(
+                f,
+                "Could not resolve {} from {}: {} is not allowed by policy {}",
+                specifier, referer, denied, policy
+            ),
+            ModuleResolutionError::InvalidSpecifier {
+                specifier,
+                referer,
+                reason,
+            } => write!(
+                f,
+                "Could not resolve {} from {}: {}",
+                specifier,
+                abbreviate_data_url(referer),
+                reason
+            ),
+            ModuleResolutionError::LimitExceeded {
+                origin_url,
+                limit,
+                max,
+                actual,
+            } => {
+                write!(f, "Could not load {}: ", abbreviate_data_url(origin_url))?;
+                match limit {
+                    ModuleLimit::ModuleBytes => write!(f, "it is {} bytes", actual)?,
+                    ModuleLimit::GraphDepth => write!(f, "it is {} imports deep", actual)?,
+                    ModuleLimit::TotalModules => write!(f, "it would be module {}", actual)?,
+                }
+                write!(f, ", over the {} limit of {}", limit, max)
+            }
+            ModuleResolutionError::TypeMismatch {
+                origin_url,
+                asserted,
+                kind,
+            } => write!(
+                f,
+                "Could not import {} with type: \"{}\": it is a {} module",
+                abbreviate_data_url(origin_url),
+                asserted.as_str(),
+                kind.as_str()
+            ),
+            ModuleResolutionError::DeadlineExceeded {
+                deadline,
+                completed,
+                in_flight,
+            } => {
+                write!(
+                    f,
+                    "Could not load modules within the total deadline of {:?}: {} loaded",
+                    deadline, completed
+                )?;
+                match in_flight {
+                    Some(import) => write!(f, ", {} still loading", import),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModuleResolutionError {
+    fn description(&self) -> &str {
+        "could not resolve module"
+    }
+
+    fn cause(&self) -> Option<&std::error::Error> {
+        match self {
+            ModuleResolutionError::NotFound { attempts, .. }
+            | ModuleResolutionError::TimedOut { attempts, .. }
+                if !attempts.is_empty() =>
+            {
+                Some(&attempts[attempts.len() - 1].error)
+            }
+            ModuleResolutionError::LoaderFailed { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_converts_into_distinct_kinds() {
+        let err: FlyError = ModuleResolutionError::NoResolverForProtocol {
+            specifier: "gopher://host/a.js".to_string(),
+            referer: "file:///app/main.js".to_string(),
+            scheme: "gopher".to_string(),
+        }
+        .into();
+        assert_eq!(ErrorKind::NoResolverForProtocol, err.kind());
+        match err.resolution_error() {
+            Some(ModuleResolutionError::NoResolverForProtocol { scheme, .. }) => {
+                assert_eq!("gopher", scheme)
+            }
+            other => panic!("{:?}", other),
+        }
+
+        // loads failing for no particular reason are told apart from those that have one
+        let generic = ModuleResolutionError::loader_failed(
+            "file:///app/a.js",
+            FlyError::from("disk on fire".to_string()),
+        );
+        assert_eq!(ErrorKind::ModuleLoadFailed, generic.kind());
+        assert_eq!("disk on fire", format!("{}", generic));
+        let pinned = ModuleResolutionError::loader_failed(
+            "file:///app/a.js",
+            new(ErrorKind::IntegrityMismatch, "bad pin".to_string()),
+        );
+        assert_eq!(ErrorKind::IntegrityMismatch, pinned.kind());
+        // not wrapped twice
+        let again = ModuleResolutionError::loader_failed("file:///app/b.js", pinned);
+        match again.resolution_error() {
+            Some(ModuleResolutionError::LoaderFailed { origin_url, .. }) => {
+                assert_eq!("file:///app/a.js", origin_url)
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(FlyError::from("plain".to_string())
+            .resolution_error()
+            .is_none());
+    }
+}
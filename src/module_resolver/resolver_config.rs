@@ -0,0 +1,393 @@
+use crate::errors::*;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{
+    CodeCache, HttpModuleResolver, JsonSecretsResolver, LocalDiskModuleResolver, ModuleResolver,
+    ModuleResolverManagerBuilder, NodeBuiltinResolver, ResolutionCache, SharedSourceCache,
+    StandardModuleResolverManager,
+};
+
+/**
+ * A resolver stack as a deployment describes it, so which resolvers it runs can change
+ * without rebuilding the embedder. In TOML:
+ *
+ * ```toml
+ * default_root = "/srv/app"
+ * allow_protocols = ["file", "https", "secrets"]
+ *
+ * [cache]
+ * resolution = true
+ * shared_source_bytes = 67108864
+ * code_cache_bytes = 67108864
+ *
+ * [[resolvers]]
+ * type = "local_disk"
+ * root = "/srv/app"
+ *
+ * [[resolvers]]
+ * type = "https"
+ * allow_hosts = ["deno.land", "*.jsdelivr.net"]
+ * priority = 1
+ *
+ * [[resolvers]]
+ * type = "secrets"
+ * file = "secrets.json"
+ * allowed_origins = ["file:///srv/app/"]
+ * ```
+ *
+ * Relative paths are relative to the working directory. Resolvers that are code, like a
+ * `FunctionModuleResolver`, can't be configured; add them to `builder()` before building.
+ */
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ResolverConfig {
+    /** A path or a url; `file://<cwd>/` unless given. */
+    pub default_root: Option<String>,
+    pub allow_protocols: Option<Vec<String>>,
+    pub cache: ResolverCacheConfig,
+    pub resolvers: Vec<ResolverSpec>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ResolverCacheConfig {
+    /** Keep every module resolved, see `ResolutionCache`. */
+    pub resolution: bool,
+    /** Share loaded sources up to this many bytes, see `SharedSourceCache`. */
+    pub shared_source_bytes: Option<usize>,
+    /** Keep V8's code caches up to this many bytes, see `CodeCache`. */
+    pub code_cache_bytes: Option<usize>,
+    /**
+     * Fail imports of missing modules again for this many milliseconds without looking for
+     * them, see `ResolutionCache::with_negative_ttl`. Only with `resolution`.
+     */
+    pub negative_ttl_ms: Option<u64>,
+}
+
+/**
+ * One resolver, with the priority it's tried at among its protocol's, its own unless given.
+ */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResolverSpec {
+    #[serde(default)]
+    pub priority: Option<i32>,
+    #[serde(flatten)]
+    pub kind: ResolverKind,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResolverKind {
+    LocalDisk {
+        /** A path or a `file` url; the default root unless given. */
+        #[serde(default)]
+        root: Option<String>,
+        /** Paths searched in order, see `LocalDiskModuleResolver::with_roots`. */
+        #[serde(default)]
+        roots: Option<Vec<String>>,
+        /** A tsconfig.json whose `paths` aliases to resolve. */
+        #[serde(default)]
+        tsconfig: Option<String>,
+    },
+    Https {
+        /** Host patterns, like `*.deno.land`; any host unless given. */
+        #[serde(default)]
+        allow_hosts: Option<Vec<String>>,
+    },
+    Http {
+        #[serde(default)]
+        allow_hosts: Option<Vec<String>>,
+    },
+    Secrets {
+        /** A JSON file of the secrets. */
+        file: String,
+        /** See `JsonSecretsResolver::with_allowed_origins`; any origin unless given. */
+        #[serde(default)]
+        allowed_origins: Option<Vec<String>>,
+        #[serde(default)]
+        root_import: bool,
+    },
+    NodeBuiltin,
+}
+
+fn config_error(msg: impl std::fmt::Display) -> FlyError {
+    new(
+        ErrorKind::InvalidInput,
+        format!("invalid resolver config: {}", msg),
+    )
+}
+
+/** `root` as a directory url, whether it's a path or a url already. */
+fn directory_url(root: &str) -> FlyResult<String> {
+    if let Ok(url) = url::Url::parse(root) {
+        if url.scheme().len() > 1 {
+            return Ok(url.into_string());
+        }
+    }
+    let path = std::env::current_dir()?.join(root);
+    url::Url::from_directory_path(&path)
+        .map(|url| url.into_string())
+        .map_err(|_| config_error(format!("{} is not a directory path", path.display())))
+}
+
+impl ResolverConfig {
+    pub fn from_toml(toml: &str) -> FlyResult<Self> {
+        toml::from_str(toml).map_err(config_error)
+    }
+
+    pub fn from_json(json: &str) -> FlyResult<Self> {
+        serde_json::from_str(json).map_err(config_error)
+    }
+
+    /** Reads a `.json` file as JSON, and anything else as TOML. */
+    pub fn from_file(path: &Path) -> FlyResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            _ => Self::from_toml(&contents),
+        }
+    }
+
+    /**
+     * A builder with everything configured, for embedders to add resolvers of their own to.
+     * Fails if a secrets file can't be read.
+     */
+    pub fn builder(&self) -> FlyResult<ModuleResolverManagerBuilder> {
+        let default_root = match self.default_root {
+            Some(ref root) => Some(directory_url(root)?),
+            None => None,
+        };
+        let mut builder = ModuleResolverManagerBuilder::new();
+        if let Some(ref root) = default_root {
+            builder = builder.with_default_root(root);
+        }
+        if let Some(ref protocols) = self.allow_protocols {
+            builder = builder.with_protocol_allowlist(protocols);
+        }
+        if self.cache.resolution {
+            let negative_ttl = self.cache.negative_ttl_ms.map(Duration::from_millis);
+            builder = builder.with_cache(ResolutionCache::new().with_negative_ttl(negative_ttl));
+        }
+        if let Some(max_bytes) = self.cache.shared_source_bytes {
+            builder = builder.with_shared_cache(SharedSourceCache::new(max_bytes));
+        }
+        if let Some(max_bytes) = self.cache.code_cache_bytes {
+            builder = builder.with_code_cache(CodeCache::new(max_bytes));
+        }
+        for spec in &self.resolvers {
+            let resolver: Box<ModuleResolver> = match spec.kind {
+                ResolverKind::LocalDisk {
+                    ref root,
+                    ref roots,
+                    ref tsconfig,
+                } => {
+                    let root = match root {
+                        Some(root) => Some(directory_url(root)?),
+                        None => default_root.clone(),
+                    };
+                    let mut resolver = LocalDiskModuleResolver::new(root)?;
+                    if let Some(tsconfig) = tsconfig {
+                        resolver = resolver
+                            .with_tsconfig(Path::new(tsconfig))
+                            .map_err(|e| config_error(format!("tsconfig {}: {}", tsconfig, e)))?;
+                    }
+                    match roots {
+                        Some(roots) => {
+                            Box::new(resolver.with_roots(roots.iter().map(PathBuf::from).collect()))
+                        }
+                        None => Box::new(resolver),
+                    }
+                }
+                ResolverKind::Https { ref allow_hosts }
+                | ResolverKind::Http { ref allow_hosts } => {
+                    let protocol = match spec.kind {
+                        ResolverKind::Https { .. } => "https",
+                        _ => "http",
+                    };
+                    if let Some(ref hosts) = allow_hosts {
+                        builder = builder.with_allowed_hosts(protocol, hosts);
+                    }
+                    Box::new(HttpModuleResolver::new(protocol))
+                }
+                ResolverKind::Secrets {
+                    ref file,
+                    ref allowed_origins,
+                    root_import,
+                } => {
+                    let json = std::fs::read_to_string(file)
+                        .map_err(|e| config_error(format!("secrets file {}: {}", file, e)))?;
+                    let secrets = serde_json::from_str(&json)
+                        .map_err(|e| config_error(format!("secrets file {}: {}", file, e)))?;
+                    let resolver = JsonSecretsResolver::new(secrets).with_root_import(root_import);
+                    match allowed_origins {
+                        Some(origins) => Box::new(resolver.with_allowed_origins(origins.clone())),
+                        None => Box::new(resolver),
+                    }
+                }
+                ResolverKind::NodeBuiltin => Box::new(NodeBuiltinResolver::new()),
+            };
+            builder = builder.with_boxed_resolver(resolver, spec.priority);
+        }
+        Ok(builder)
+    }
+}
+
+impl StandardModuleResolverManager {
+    /**
+     * Every resolver `config` describes, set up as it says. To add resolvers that can't be
+     * configured, build from `config.builder()` instead.
+     */
+    pub fn from_config(config: ResolverConfig) -> FlyResult<Self> {
+        config.builder()?.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{FunctionModuleResolver, ModuleResolverManager, RefererInfo};
+
+    fn sample(dir: &Path) -> String {
+        std::fs::write(
+            dir.join("secrets.json"),
+            r#"{"db": {"password": "hunter2"}}"#,
+        )
+        .unwrap();
+        format!(
+            r#"
+default_root = "{root}"
+allow_protocols = ["file", "https", "secrets", "node"]
+
+[cache]
+resolution = true
+code_cache_bytes = 1048576
+
+[[resolvers]]
+type = "local_disk"
+
+[[resolvers]]
+type = "https"
+allow_hosts = ["deno.land"]
+
+[[resolvers]]
+type = "https"
+allow_hosts = ["*.jsdelivr.net"]
+priority = 1
+
+[[resolvers]]
+type = "secrets"
+file = "{root}/secrets.json"
+allowed_origins = ["file://{root}/config/"]
+
+[[resolvers]]
+type = "node_builtin"
+"#,
+            root = dir.display()
+        )
+    }
+
+    #[test]
+    fn test_builds_the_configured_stack() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ResolverConfig::from_toml(&sample(dir.path())).unwrap();
+        assert!(config.cache.resolution);
+        let manager = StandardModuleResolverManager::from_config(config).unwrap();
+        let description = manager.describe().unwrap();
+        let protocols: Vec<(&str, Vec<&str>)> = description
+            .protocols
+            .iter()
+            .map(|p| {
+                (
+                    p.protocol.as_str(),
+                    p.resolvers.iter().map(|r| r.kind.as_str()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("file", vec!["local_disk"]),
+                ("https", vec!["http", "http"]),
+                ("node", vec!["node_builtin"]),
+                ("secrets", vec!["json_secrets"]),
+            ],
+            protocols
+        );
+        assert_eq!(
+            "[file, https, node, secrets] https hosts [deno.land, *.jsdelivr.net]",
+            description.policy
+        );
+        assert!(manager.resolution_cache().is_some());
+        assert!(manager.code_cache().is_some());
+        assert_eq!(
+            url::Url::from_directory_path(dir.path()).unwrap().as_str(),
+            description.default_working_url
+        );
+
+        let import_secret = |referer: &str| {
+            manager.resolve_blocking(
+                "secrets:///db/password".to_string(),
+                Some(RefererInfo {
+                    origin_url: url::Url::from_file_path(dir.path().join(referer))
+                        .unwrap()
+                        .into_string(),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+        };
+        assert!(import_secret("config/db.ts").is_ok());
+        let err = import_secret("vendor/lib.js").err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("secrets may only be imported from"));
+    }
+
+    #[test]
+    fn test_json_configs_and_resolvers_of_the_embedders_own() {
+        let config = ResolverConfig::from_json(
+            r#"{"default_root": "file:///app/", "resolvers": [{"type": "local_disk"}]}"#,
+        )
+        .unwrap();
+        let manager = config
+            .builder()
+            .unwrap()
+            .with_resolver(FunctionModuleResolver::new(
+                "app",
+                Box::new(|_: &str, _| Err(FlyError::from("not here".to_string()))),
+            ))
+            .build()
+            .unwrap();
+        let protocols: Vec<_> = manager
+            .describe()
+            .unwrap()
+            .protocols
+            .into_iter()
+            .map(|p| p.protocol)
+            .collect();
+        assert_eq!(vec!["app", "file"], protocols);
+    }
+
+    #[test]
+    fn test_unknown_resolver_types_are_named() {
+        let err = ResolverConfig::from_toml("[[resolvers]]\ntype = \"ftp\"\nroot = \"/srv\"\n")
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+        let msg = err.to_string();
+        assert!(msg.starts_with("invalid resolver config: "), "{}", msg);
+        assert!(msg.contains("unknown variant `ftp`"), "{}", msg);
+        assert!(msg.contains("resolvers"), "{}", msg);
+
+        let err = ResolverConfig::from_json(r#"{"resolvers": [{"type": "gopher"}]}"#)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("`gopher`"), "{}", err);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use super::blocking::run_with_deadline;
+use super::{with_module_byte_limit, ModuleLimits, ModuleResolver};
+
+/** How long a resolver's attempt may take unless its manager's options say otherwise. */
+pub const DEFAULT_RESOLVER_TIMEOUT: Duration = Duration::from_secs(15);
+
+/**
+ * How the resolvers registered for one protocol are tried.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResolutionStrategy {
+    /** One after another, by priority, until one resolves the specifier. */
+    Sequential,
+    /**
+     * All at once, the first to resolve the specifier winning; the rest are cancelled. This
+     * gives up on priority: a lower priority resolver answering first wins even if a higher
+     * one would have resolved the specifier too, so only race resolvers that serve the same
+     * modules, like mirrors of one origin. Fallback resolvers are still only tried, in turn,
+     * once every racer failed.
+     */
+    Race,
+}
+
+impl Default for ResolutionStrategy {
+    fn default() -> Self {
+        ResolutionStrategy::Sequential
+    }
+}
+
+/**
+ * How a `StandardModuleResolverManager` bounds the resolvers it tries. Every
+ * `resolve_module` and `load_source` attempt gets `timeout`, or its resolver's entry in
+ * `timeouts`; an attempt that runs out fails with `ErrorKind::TimedOut` and the manager
+ * moves on to the protocol's next resolver. Remote resolvers' attempts are retried as
+ * `retry` says. Resolvers that must run on the runtime's thread, like guests', aren't bounded:
+ * there's no other thread to give up on them from.
+ */
+#[derive(Clone, Debug)]
+pub struct ResolverOptions {
+    /** `None` lets attempts take as long as they take. */
+    pub timeout: Option<Duration>,
+    /** Timeouts by the kind resolvers describe themselves as, e.g. `github`. */
+    pub timeouts: HashMap<String, Duration>,
+    pub retry: RetryPolicy,
+    /** Whether a protocol's resolvers are tried in turn or raced. */
+    pub strategy: ResolutionStrategy,
+    pub limits: ModuleLimits,
+    /**
+     * How long each top-level load, a `preload` or an import the runtime runs, however many
+     * `op_load_module`s its graph takes, has for everything it resolves, its imports' imports
+     * included. Past it, what's still loading is cancelled and the load fails with
+     * `ErrorKind::DeadlineExceeded`. `None` bounds attempts only.
+     */
+    pub total_deadline: Option<Duration>,
+}
+
+impl Default for ResolverOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Some(DEFAULT_RESOLVER_TIMEOUT),
+            timeouts: HashMap::new(),
+            retry: RetryPolicy::default(),
+            strategy: ResolutionStrategy::default(),
+            limits: ModuleLimits::default(),
+            total_deadline: None,
+        }
+    }
+}
+
+impl ResolverOptions {
+    pub fn timeout_for(&self, resolver: &ModuleResolver) -> Option<Duration> {
+        match self.timeouts.get(&resolver.describe().kind) {
+            Some(timeout) => Some(*timeout),
+            None => self.timeout,
+        }
+    }
+
+    /**
+     * Runs `work`, one of `resolver`'s attempts at `what`, within its deadline and the
+     * module byte limit, retrying it if the resolver is remote.
+     */
+    pub(super) fn attempt<T, F>(
+        &self,
+        resolver: &ModuleResolver,
+        what: &str,
+        work: F,
+    ) -> FlyResult<T>
+    where
+        T: Send + 'static,
+        F: Fn() -> FlyResult<T> + Send + Sync + 'static,
+    {
+        let work = Arc::new(work);
+        let timeout = self.timeout_for(resolver);
+        let max_module_bytes = self.limits.max_module_bytes;
+        let once = || {
+            let work = work.clone();
+            // the deadline runs `work` on the blocking pool
+            run_with_deadline(what, timeout, move || {
+                with_module_byte_limit(max_module_bytes, || work())
+            })
+        };
+        if resolver.is_remote() {
+            self.retry.run(what, once)
+        } else {
+            once()
+        }
+    }
+}
+
+/**
+ * How a remote resolver's failed attempts are retried: after `initial_backoff`, doubling for
+ * every retry up to `max_backoff`, until `max_attempts` were made. Only connection failures
+ * and server errors are retried. A missing module stays missing, and an attempt that timed
+ * out has had its chance; the next resolver gets one instead.
+ */
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /** Including the first; 1 never retries. */
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /** How long to wait before retry number `retry`, counting from 1. */
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+
+    pub fn is_retryable(err: &FlyError) -> bool {
+        match err.kind() {
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::HttpClosed
+            | ErrorKind::HttpCanceled
+            | ErrorKind::HttpOther
+            | ErrorKind::UpstreamError => true,
+            _ => false,
+        }
+    }
+
+    /**
+     * Calls `attempt` until it succeeds, fails in a way not worth retrying, or every attempt
+     * was made, sleeping the backoff in between.
+     */
+    pub fn run<T>(&self, what: &str, mut attempt: impl FnMut() -> FlyResult<T>) -> FlyResult<T> {
+        let mut made = 1;
+        loop {
+            match attempt() {
+                Err(ref e) if made < self.max_attempts && Self::is_retryable(e) => {
+                    let backoff = self.backoff(made);
+                    warn!(
+                        "{} failed, retrying in {:?} ({}/{}): {}",
+                        what, backoff, made, self.max_attempts, e
+                    );
+                    thread::sleep(backoff);
+                    made += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retries_only_connection_and_server_failures() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(3),
+        };
+        assert_eq!(
+            vec![1, 2, 3, 3],
+            (1..5)
+                .map(|retry| policy.backoff(retry).as_millis())
+                .collect::<Vec<_>>()
+        );
+
+        let mut calls = 0;
+        let flaky = policy.run("fetching", || {
+            calls += 1;
+            match calls {
+                1 => Err(new(ErrorKind::ConnectionRefused, "refused".to_string())),
+                2 => Err(new(ErrorKind::UpstreamError, "status 503".to_string())),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(3, flaky.unwrap());
+
+        let mut calls = 0;
+        let missing: FlyResult<()> = policy.run("fetching", || {
+            calls += 1;
+            Err(new(ErrorKind::NotFound, "status 404".to_string()))
+        });
+        assert_eq!(ErrorKind::NotFound, missing.err().unwrap().kind());
+        assert_eq!(1, calls);
+
+        let mut calls = 0;
+        let down: FlyResult<()> = policy.run("fetching", || {
+            calls += 1;
+            Err(new(ErrorKind::ConnectionReset, "reset".to_string()))
+        });
+        assert!(down.is_err());
+        assert_eq!(4, calls);
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use futures::task::{self, Task};
+use futures::{Async, Future, Poll};
+
+use crate::errors::*;
+use crate::metrics::*;
+
+use super::duration_ms;
+
+/**
+ * Tenant used for loads that carry no app id.
+ */
+pub const DEFAULT_TENANT: &str = "default";
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LoadSchedulerConfig {
+    pub max_in_flight: usize,
+    pub per_tenant_in_flight: usize,
+}
+
+impl Default for LoadSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 16,
+            per_tenant_in_flight: 4,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    next_ticket: u64,
+    in_flight: usize,
+    tenant_in_flight: HashMap<String, usize>,
+    queues: HashMap<String, VecDeque<u64>>,
+    // tenants with queued loads, in the order they get their next turn
+    ring: VecDeque<String>,
+    granted: HashSet<u64>,
+    // what's polling each ticket not granted yet, to wake once it is
+    waiting: HashMap<u64, Task>,
+}
+
+impl SchedulerState {
+    fn enqueue(&mut self, tenant: &str) -> u64 {
+        self.next_ticket += 1;
+        let ticket = self.next_ticket;
+        self.queues
+            .entry(tenant.to_string())
+            .or_insert_with(VecDeque::new)
+            .push_back(ticket);
+        if !self.ring.iter().any(|t| t == tenant) {
+            self.ring.push_back(tenant.to_string());
+        }
+        ticket
+    }
+
+    /**
+     * Hands free slots out round-robin, skipping tenants already at their in-flight cap.
+     * A tenant that gets a slot goes to the back of the ring.
+     */
+    fn dispatch(&mut self, config: &LoadSchedulerConfig) {
+        while self.in_flight < config.max_in_flight {
+            let position = {
+                let tenant_in_flight = &self.tenant_in_flight;
+                self.ring.iter().position(|tenant| {
+                    tenant_in_flight.get(tenant).cloned().unwrap_or(0) < config.per_tenant_in_flight
+                })
+            };
+            let tenant = match position.and_then(|i| self.ring.remove(i)) {
+                Some(tenant) => tenant,
+                None => break,
+            };
+            let (ticket, drained) = match self.queues.get_mut(&tenant) {
+                Some(queue) => (queue.pop_front(), queue.is_empty()),
+                None => (None, true),
+            };
+            if drained {
+                self.queues.remove(&tenant);
+            } else {
+                self.ring.push_back(tenant.clone());
+            }
+            if let Some(ticket) = ticket {
+                self.granted.insert(ticket);
+                if let Some(task) = self.waiting.remove(&ticket) {
+                    task.notify();
+                }
+                self.in_flight += 1;
+                *self.tenant_in_flight.entry(tenant).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /** Takes back `ticket`, given up on while it was still queued. */
+    fn withdraw(&mut self, tenant: &str, ticket: u64) {
+        self.waiting.remove(&ticket);
+        let drained = match self.queues.get_mut(tenant) {
+            Some(queue) => {
+                queue.retain(|queued| *queued != ticket);
+                queue.is_empty()
+            }
+            None => false,
+        };
+        if drained {
+            self.queues.remove(tenant);
+            self.ring.retain(|t| t != tenant);
+        }
+    }
+
+    fn release(&mut self, tenant: &str) {
+        self.in_flight -= 1;
+        let drained = match self.tenant_in_flight.get_mut(tenant) {
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => false,
+        };
+        if drained {
+            self.tenant_in_flight.remove(tenant);
+        }
+    }
+}
+
+/**
+ * Caps concurrent module loads across every runtime sharing it, handing slots out fairly
+ * between tenants so one app loading a large graph can't starve another's single import.
+ */
+#[derive(Clone)]
+pub struct LoadScheduler {
+    config: LoadSchedulerConfig,
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+/**
+ * A load slot. The slot goes back to the scheduler when this is dropped.
+ */
+pub struct LoadPermit {
+    scheduler: LoadScheduler,
+    tenant: String,
+}
+
+impl Drop for LoadPermit {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.scheduler.state.lock() {
+            state.release(&self.tenant);
+            state.dispatch(&self.scheduler.config);
+        }
+    }
+}
+
+/**
+ * A tenant's turn for a load slot, resolving to its permit. Nothing waits on it but the task
+ * polling it, which is woken once the turn comes. Dropped before that, it leaves the queue,
+ * and a slot it was granted but never took goes to the next in line.
+ */
+pub struct Acquiring {
+    scheduler: LoadScheduler,
+    tenant: String,
+    ticket: u64,
+    started: Instant,
+    acquired: bool,
+}
+
+impl Future for Acquiring {
+    type Item = LoadPermit;
+    type Error = FlyError;
+
+    fn poll(&mut self) -> Poll<LoadPermit, FlyError> {
+        let mut state = self.scheduler.state.lock().unwrap();
+        if !state.granted.remove(&self.ticket) {
+            state.waiting.insert(self.ticket, task::current());
+            return Ok(Async::NotReady);
+        }
+        self.acquired = true;
+        MODULE_LOAD_QUEUE_WAIT_HISTOGRAM
+            .with_label_values(&[&self.tenant])
+            .observe(duration_ms(self.started.elapsed()) / 1000.0);
+        Ok(Async::Ready(LoadPermit {
+            scheduler: self.scheduler.clone(),
+            tenant: self.tenant.clone(),
+        }))
+    }
+}
+
+impl Drop for Acquiring {
+    fn drop(&mut self) {
+        if self.acquired {
+            return;
+        }
+        if let Ok(mut state) = self.scheduler.state.lock() {
+            if state.granted.remove(&self.ticket) {
+                state.release(&self.tenant);
+                state.dispatch(&self.scheduler.config);
+            } else {
+                state.withdraw(&self.tenant, self.ticket);
+            }
+        }
+    }
+}
+
+impl LoadScheduler {
+    pub fn new(config: LoadSchedulerConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(SchedulerState::default())),
+        }
+    }
+
+    pub fn config(&self) -> &LoadSchedulerConfig {
+        &self.config
+    }
+
+    /**
+     * Queues `tenant` for a load slot; the future resolves once it gets its turn. It never
+     * fails.
+     */
+    pub fn acquire(&self, tenant: &str) -> Acquiring {
+        let ticket = {
+            let mut state = self.state.lock().unwrap();
+            let ticket = state.enqueue(tenant);
+            state.dispatch(&self.config);
+            ticket
+        };
+        Acquiring {
+            scheduler: self.clone(),
+            tenant: tenant.to_string(),
+            ticket,
+            started: Instant::now(),
+            acquired: false,
+        }
+    }
+}
+
+lazy_static! {
+    static ref PROCESS_WIDE: Mutex<Option<LoadScheduler>> = Mutex::new(None);
+}
+
+/**
+ * The scheduler shared by every runtime in this process. The first caller decides its limits.
+ */
+pub fn process_wide_scheduler(config: &LoadSchedulerConfig) -> LoadScheduler {
+    let mut scheduler = PROCESS_WIDE.lock().unwrap();
+    scheduler
+        .get_or_insert_with(|| LoadScheduler::new(config.clone()))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    fn config(max_in_flight: usize, per_tenant_in_flight: usize) -> LoadSchedulerConfig {
+        LoadSchedulerConfig {
+            max_in_flight,
+            per_tenant_in_flight,
+        }
+    }
+
+    #[test]
+    fn test_small_tenant_is_not_starved() {
+        let config = config(1, 1);
+        let mut state = SchedulerState::default();
+        let big: Vec<u64> = (0..5).map(|_| state.enqueue("big")).collect();
+        let small = state.enqueue("small");
+
+        state.dispatch(&config);
+        assert!(state.granted.remove(&big[0]));
+        state.release("big");
+        state.dispatch(&config);
+        // the small tenant's single import goes next, not after the whole graph
+        assert!(state.granted.remove(&small));
+        state.release("small");
+        state.dispatch(&config);
+        assert!(state.granted.remove(&big[1]));
+    }
+
+    #[test]
+    fn test_per_tenant_cap() {
+        let config = config(4, 1);
+        let mut state = SchedulerState::default();
+        let big: Vec<u64> = (0..3).map(|_| state.enqueue("big")).collect();
+        let small = state.enqueue("small");
+
+        state.dispatch(&config);
+        assert_eq!(2, state.in_flight);
+        assert!(state.granted.contains(&big[0]));
+        assert!(state.granted.contains(&small));
+        assert!(!state.granted.contains(&big[1]));
+    }
+
+    #[test]
+    fn test_permits_limit_concurrency() {
+        let scheduler = LoadScheduler::new(config(2, 2));
+        let running = Arc::new(Mutex::new((0, 0)));
+        let handles: Vec<_> = (0..6)
+            .map(|i| {
+                let (scheduler, running) = (scheduler.clone(), running.clone());
+                thread::spawn(move || {
+                    let _permit = scheduler
+                        .acquire(if i % 2 == 0 { "a" } else { "b" })
+                        .wait()
+                        .unwrap();
+                    {
+                        let mut running = running.lock().unwrap();
+                        running.0 += 1;
+                        running.1 = std::cmp::max(running.0, running.1);
+                    }
+                    thread::sleep(Duration::from_millis(5));
+                    running.lock().unwrap().0 -= 1;
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(running.lock().unwrap().1 <= 2);
+    }
+
+    #[test]
+    fn test_turns_given_up_on_go_to_the_next_in_line() {
+        let scheduler = LoadScheduler::new(config(1, 1));
+        let granted = |acquiring: &Acquiring| {
+            let state = scheduler.state.lock().unwrap();
+            state.granted.contains(&acquiring.ticket)
+        };
+        let first = scheduler.acquire("a").wait().unwrap();
+        // queued behind `first` without anything waiting on it
+        let queued = scheduler.acquire("b");
+        let next = scheduler.acquire("c");
+        drop(queued);
+        assert!(scheduler.state.lock().unwrap().queues.get("b").is_none());
+        drop(first);
+        // `c` was granted the slot, and gives it back without ever taking it
+        assert!(granted(&next));
+        drop(next);
+        assert_eq!(0, scheduler.state.lock().unwrap().in_flight);
+        let last = scheduler.acquire("d");
+        assert!(granted(&last));
+        last.wait().unwrap();
+    }
+}
@@ -0,0 +1,512 @@
+use crate::errors::*;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use super::{
+    file_path_url, is_absolute_path, js_string_literal, parse_url, LoadedSourceCode, ModuleKind,
+    ModuleResolver, ModuleSourceData, RefererInfo, ResolutionCache, ResolverDescription,
+    SourceLoader,
+};
+
+pub struct JsonSecretsLoader {
+    pub json_value: serde_json::Value,
+}
+
+impl JsonSecretsLoader {
+    pub fn new(json_value: &serde_json::Value) -> Self {
+        Self {
+            json_value: (*json_value).clone(),
+        }
+    }
+}
+
+impl SourceLoader for JsonSecretsLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let source_code = format!(
+            "export default JSON.parse({});",
+            js_string_literal(&self.json_value.to_string())
+        );
+
+        return Ok(LoadedSourceCode {
+            kind: ModuleKind::Json,
+            source_map: None,
+            source: source_code.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        });
+    }
+}
+
+/**
+ * Serves values from a JSON secrets object, each url pointing at one of them: both
+ * `secrets://db/password` and `secrets:///db/password` export `secrets["db"]["password"]`,
+ * and numeric segments index arrays, as in `secrets:///keys/0`. Only the value pointed at
+ * is exported. The whole object, at `secrets:///`, only with `with_root_import`. The secrets
+ * can be rotated while runtimes are using them, see `handle`.
+ */
+pub struct JsonSecretsResolver {
+    secrets: SecretsHandle,
+    allow_root_import: bool,
+    allowed_origins: Option<Vec<String>>,
+}
+
+/**
+ * Rotates the secrets of the `JsonSecretsResolver` it came from, wherever it's shared. Later
+ * resolutions see the new values; the allowed origins still apply to them. Modules already
+ * evaluated keep the value they were resolved with, and so do resolutions cached by the
+ * manager, so to have running code pick up a rotated secret:
+ *
+ * 1. `update` the secrets, which returns the urls of the secret modules served so far whose
+ *    value changed;
+ * 2. evict those from the manager's caches with `clear_module_cache`, or have the resolver do
+ *    it for its resolution cache by building it `with_invalidation`;
+ * 3. import them afresh, say with a dynamic `import()`, or in a new runtime.
+ */
+#[derive(Clone)]
+pub struct SecretsHandle {
+    json_value: Arc<RwLock<serde_json::Value>>,
+    // origin url -> the path of each secret module served
+    served: Arc<Mutex<BTreeMap<String, Vec<String>>>>,
+    invalidate: Option<ResolutionCache>,
+}
+
+impl SecretsHandle {
+    /**
+     * Replaces every secret with those in `json_value`. Returns the origin urls of the secret
+     * modules served so far whose value is now different, or gone.
+     */
+    pub fn update(&self, json_value: serde_json::Value) -> Vec<String> {
+        let old = std::mem::replace(&mut *self.json_value.write().unwrap(), json_value);
+        let new = self.json_value.read().unwrap();
+        let changed: Vec<String> = self
+            .served
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, path)| lookup_secret(&old, path) != lookup_secret(&new, path))
+            .map(|(origin_url, _)| origin_url.clone())
+            .collect();
+        if let Some(ref cache) = self.invalidate {
+            for origin_url in &changed {
+                cache.evict(origin_url);
+            }
+        }
+        changed
+    }
+}
+
+/** The secret at `path`, numeric segments indexing arrays. */
+fn lookup_secret<'a>(
+    json_value: &'a serde_json::Value,
+    path: &[String],
+) -> Option<&'a serde_json::Value> {
+    path.iter().try_fold(json_value, |value, key| match value {
+        serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => value.get(key),
+    })
+}
+
+impl JsonSecretsResolver {
+    pub fn new(json_value: serde_json::Value) -> Self {
+        Self {
+            secrets: SecretsHandle {
+                json_value: Arc::new(RwLock::new(json_value)),
+                served: Arc::new(Mutex::new(BTreeMap::new())),
+                invalidate: None,
+            },
+            allow_root_import: false,
+            allowed_origins: None,
+        }
+    }
+
+    /** A handle to rotate the secrets with, for the embedder to keep. */
+    pub fn handle(&self) -> SecretsHandle {
+        self.secrets.clone()
+    }
+
+    /**
+     * Has `update` evict the secret modules it changed from `cache`, the manager's resolution
+     * cache, so the next import of one resolves it again.
+     */
+    pub fn with_invalidation(mut self, cache: ResolutionCache) -> Self {
+        self.secrets.invalidate = Some(cache);
+        self
+    }
+
+    pub fn with_root_import(mut self, allow_root_import: bool) -> Self {
+        self.allow_root_import = allow_root_import;
+        self
+    }
+
+    /**
+     * Only modules under one of `origins`, like `file:///srv/app/`, may import secrets;
+     * everything else is denied, loads without a referer included. `file:///srv/app` allows
+     * the same, and neither allows `file:///srv/application/`. Origins are percent-encoded as
+     * module urls are, so `file:///srv/my app/` matches a module in `/srv/my app`, and
+     * absolute paths are taken as `file` urls.
+     */
+    pub fn with_allowed_origins(mut self, origins: Vec<String>) -> Self {
+        let normalized = origins
+            .into_iter()
+            .map(|origin| {
+                let url = if is_absolute_path(&origin) {
+                    file_path_url(&origin)
+                } else {
+                    url::Url::parse(&origin).map_err(FlyError::from)
+                };
+                url.map(|url| url.into_string()).unwrap_or(origin)
+            })
+            .collect();
+        self.allowed_origins = Some(normalized);
+        self
+    }
+}
+
+/**
+ * Whether `url` is `origin` or under it: the same scheme, host and port, and a path within
+ * `origin`'s, taken as a directory whether or not it ends in `/`. Prefixes of a host or of a
+ * path segment don't count, so `https://cdn.test` doesn't cover `https://cdn.test.evil/`.
+ */
+fn within_origin(url: &str, origin: &str) -> bool {
+    let (url, origin) = match (url::Url::parse(url), url::Url::parse(origin)) {
+        (Ok(url), Ok(origin)) => (url, origin),
+        _ => return false,
+    };
+    if url.scheme() != origin.scheme()
+        || url.host() != origin.host()
+        || url.port_or_known_default() != origin.port_or_known_default()
+    {
+        return false;
+    }
+    let dir = origin.path().trim_end_matches('/');
+    url.path() == dir || url.path().starts_with(&format!("{}/", dir))
+}
+
+/**
+ * The keys `url` points at: its host, if any, then its path segments, percent-decoded.
+ */
+fn secret_path(url: &url::Url) -> Vec<String> {
+    let segments = url
+        .path_segments()
+        .map(|s| s.collect())
+        .unwrap_or_else(Vec::new);
+    url.host_str()
+        .into_iter()
+        .chain(segments)
+        .filter(|seg| !seg.is_empty())
+        .map(|seg| {
+            url::percent_encoding::percent_decode(seg.as_bytes())
+                .decode_utf8_lossy()
+                .into_owned()
+        })
+        .collect()
+}
+
+impl ModuleResolver for JsonSecretsResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let allowed = match (&self.allowed_origins, &referer_info) {
+            (None, _) => true,
+            (Some(origins), Some(referer)) => origins
+                .iter()
+                .any(|origin| within_origin(&referer.origin_url, origin)),
+            (Some(_), None) => false,
+        };
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => "secrets:///".to_string(),
+        };
+        let module_specifier_url = parse_url(module_specifier, referer_origin_url.as_str())?;
+        if !allowed {
+            return Err(new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "Could not resolve {} from {}: secrets may only be imported from {}",
+                    module_specifier_url,
+                    referer_origin_url,
+                    self.allowed_origins.as_ref().unwrap().join(", ")
+                ),
+            ));
+        }
+        let path = secret_path(&module_specifier_url);
+        if path.is_empty() && !self.allow_root_import {
+            return Err(new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "Could not resolve {} from {}: importing every secret at once isn't allowed, import the one needed, like secrets:///name",
+                    module_specifier_url, referer_origin_url
+                ),
+            ));
+        }
+        let json_value = self.secrets.json_value.read().unwrap();
+        let value = match lookup_secret(&json_value, &path) {
+            Some(value) => value,
+            // the path asked for, never what's there instead, so logs don't list secret names
+            None => {
+                let missing = (1..=path.len())
+                    .find(|i| lookup_secret(&json_value, &path[..*i]).is_none())
+                    .unwrap_or(path.len());
+                return Err(new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "Could not resolve {} from {}: no secret at {}",
+                        module_specifier_url,
+                        referer_origin_url,
+                        path[..missing].join("/")
+                    ),
+                ));
+            }
+        };
+        // the url resolved against the referer, not the specifier as written, so relative
+        // imports between secrets get absolute urls like every other module
+        let origin_url = format!("{}.js", module_specifier_url);
+        self.secrets
+            .served
+            .lock()
+            .unwrap()
+            .insert(origin_url.clone(), path.clone());
+        return Ok(ModuleSourceData {
+            origin_url,
+            source_loader: Box::new(JsonSecretsLoader::new(value)),
+        });
+    }
+    fn get_protocol(&self) -> String {
+        return "secrets".to_string();
+    }
+    fn describe(&self) -> ResolverDescription {
+        let entries = self
+            .secrets
+            .json_value
+            .read()
+            .unwrap()
+            .as_object()
+            .map(|o| o.len())
+            .unwrap_or(0);
+        let description = ResolverDescription::new("json_secrets", "secrets")
+            .named(&self.name())
+            .with("entries", &entries.to_string());
+        let description = match self.allowed_origins {
+            Some(ref origins) => description.with("allowed_origins", &origins.join(", ")),
+            None => description,
+        };
+        if self.allow_root_import {
+            description.with("root_import", "true")
+        } else {
+            description
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{ModuleResolverManager, StandardModuleResolverManager};
+
+    #[test]
+    fn test_secrets_export_only_the_value_pointed_at() {
+        let secrets = || {
+            JsonSecretsResolver::new(serde_json::json!({
+                "db": {"password": "hunter2", "Replicas": ["a`${b}", "c\u{2028}"]},
+                "api key": "k",
+            }))
+        };
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(secrets()) as Box<ModuleResolver>],
+            Some("secrets:///".to_string()),
+        );
+        let load = |specifier: &str| {
+            manager
+                .resolve_blocking(specifier.to_string(), None)
+                .map(|m| m.loaded_source.source.to_string())
+        };
+        assert_eq!(
+            r#"export default JSON.parse("\"hunter2\"");"#,
+            load("secrets://db/password").unwrap()
+        );
+        assert_eq!(
+            load("secrets://db/password").unwrap(),
+            load("secrets:///db/password").unwrap()
+        );
+        assert_eq!(
+            r#"export default JSON.parse("\"a`${b}\"");"#,
+            load("secrets:///db/Replicas/0").unwrap()
+        );
+        assert!(load("secrets:///db/Replicas/1")
+            .unwrap()
+            .contains("c\\u2028"));
+        assert!(load("secrets:///api%20key").is_ok());
+
+        let msg = format!("{}", load("secrets:///db/passwd").err().unwrap());
+        assert!(msg.contains("no secret at db/passwd"), "{}", msg);
+        assert!(!msg.contains("password"), "{}", msg);
+        assert!(load("secrets:///db/Replicas/2").is_err());
+        assert!(load("secrets:///db/password/0").is_err());
+
+        let relative = secrets()
+            .resolve_module(
+                "./password",
+                Some(RefererInfo {
+                    origin_url: "secrets:///db/Replicas".to_string(),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+            .unwrap();
+        assert_eq!("secrets:///db/password.js", relative.origin_url);
+
+        let err = secrets().resolve_module("secrets:///", None).err().unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+        let root = secrets()
+            .with_root_import(true)
+            .resolve_module("secrets:///", None)
+            .unwrap();
+        assert!(root
+            .source_loader
+            .load_source()
+            .unwrap()
+            .source
+            .contains("hunter2"));
+    }
+
+    #[test]
+    fn test_secrets_round_trip_every_character() {
+        let secrets = serde_json::json!({
+            "backtick": "a`b`${c}",
+            "quotes": "'single' \"double\"",
+            "newlines": "line\nnext\r\n\ttab \\ backslash",
+            "non_ascii": "pässwörd 密码 🔑 \u{2028}\u{2029}",
+            "nested": {"list": [1, null, true]},
+        });
+        let source = JsonSecretsLoader::new(&secrets).load_source().unwrap();
+        let literal = source
+            .source
+            .trim_start_matches("export default JSON.parse(")
+            .trim_end_matches(");");
+        assert!(!literal.contains('\u{2028}') && !literal.contains('\u{2029}'));
+        // the literal is a JSON string too, holding the secrets' JSON
+        let json: String = serde_json::from_str(literal).unwrap();
+        assert_eq!(
+            secrets,
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_secrets_allowed_origins_match_encoded_module_urls() {
+        let secrets = JsonSecretsResolver::new(serde_json::json!({"key": "k"}))
+            .with_allowed_origins(vec![
+                "file:///srv/my app/config/".to_string(),
+                "/srv/módulos/".to_string(),
+            ]);
+        let import_from = |path: &str| {
+            let origin_url = url::Url::from_file_path(path).unwrap().into_string();
+            secrets.resolve_module(
+                "secrets:///key",
+                Some(RefererInfo {
+                    origin_url,
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+        };
+        assert!(import_from("/srv/my app/config/db.ts").is_ok());
+        assert!(import_from("/srv/módulos/db.ts").is_ok());
+        assert!(import_from("/srv/my app/vendor/lib.ts").is_err());
+    }
+
+    #[test]
+    fn test_secrets_allowed_origins_match_whole_segments() {
+        for (url, origin, within) in &[
+            ("file:///srv/app/db.ts", "file:///srv/app", true),
+            ("file:///srv/app/lib/db.ts", "file:///srv/app/", true),
+            ("file:///srv/application/db.ts", "file:///srv/app", false),
+            ("file:///srv/app/../etc/db.ts", "file:///srv/app/", false),
+            ("https://cdn.test:443/a.js", "https://cdn.test/", true),
+            ("https://cdn.test.evil/app/a.js", "https://cdn.test", false),
+            ("http://cdn.test:8080/a.js", "http://cdn.test/", false),
+            ("http://cdn.test/app/a.js", "https://cdn.test/app/", false),
+            ("not a url", "file:///srv/app/", false),
+        ] {
+            assert_eq!(*within, within_origin(url, origin), "{} in {}", url, origin);
+        }
+    }
+
+    #[test]
+    fn test_rotated_secrets_reach_fresh_resolutions() {
+        let cache = ResolutionCache::new();
+        let secrets = JsonSecretsResolver::new(
+            serde_json::json!({"db": {"password": "hunter2"}, "region": "ord"}),
+        )
+        .with_allowed_origins(vec!["file:///app/config/".to_string()])
+        .with_invalidation(cache.clone());
+        let handle = secrets.handle();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(secrets) as Box<ModuleResolver>],
+            Some("file:///app/".to_string()),
+        )
+        .with_resolution_cache(cache);
+        let import_from = |referer: &str, specifier: &str| {
+            manager.resolve_blocking(
+                specifier.to_string(),
+                Some(RefererInfo {
+                    origin_url: referer.to_string(),
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+        };
+        let password = || {
+            import_from("file:///app/config/db.ts", "secrets:///db/password")
+                .unwrap()
+                .loaded_source
+                .source
+                .to_string()
+        };
+        assert!(password().contains("hunter2"));
+        import_from("file:///app/config/db.ts", "secrets:///region").unwrap();
+
+        // only what changed is reported, and evicted, so it resolves afresh
+        assert_eq!(
+            vec!["secrets:///db/password.js".to_string()],
+            handle
+                .update(serde_json::json!({"db": {"password": "correct horse"}, "region": "ord"}))
+        );
+        assert!(password().contains("correct horse"));
+
+        let err = import_from("file:///app/vendor/lib.js", "secrets:///db/password")
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("secrets may only be imported from file:///app/config/"));
+
+        // a secret removed by the rotation is gone too
+        assert_eq!(
+            vec!["secrets:///region.js".to_string()],
+            handle.update(serde_json::json!({"db": {"password": "correct horse"}}))
+        );
+        assert!(import_from("file:///app/config/db.ts", "secrets:///region").is_err());
+    }
+}
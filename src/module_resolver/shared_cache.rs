@@ -0,0 +1,865 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+
+use crate::metrics::*;
+
+use super::{LoadedSourceCode, ModuleKind, ResponseMetadata};
+
+/**
+ * Default ceiling for a shared cache: 64MB of module source.
+ */
+pub const DEFAULT_SHARED_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    Lz4,
+    Zstd,
+}
+
+/**
+ * Keeps sources of at least `min_bytes` compressed while cached. Smaller sources (most hot
+ * modules) stay as they are so reading them costs nothing extra.
+ */
+#[derive(Clone, Debug, Deserialize)]
+pub struct SourceCompression {
+    pub algorithm: CompressionAlgorithm,
+    #[serde(default = "default_compression_min_bytes")]
+    pub min_bytes: usize,
+}
+
+fn default_compression_min_bytes() -> usize {
+    64 * 1024
+}
+
+impl SourceCompression {
+    fn compress(&self, source: &str) -> Option<Vec<u8>> {
+        let compressed = match self.algorithm {
+            CompressionAlgorithm::Lz4 => lz4_flex::compress_prepend_size(source.as_bytes()),
+            CompressionAlgorithm::Zstd => zstd::encode_all(source.as_bytes(), 3).ok()?,
+        };
+        // not worth a decompression on every read if it barely shrank
+        if compressed.len() >= source.len() * 9 / 10 {
+            return None;
+        }
+        Some(compressed)
+    }
+
+    fn decompress(algorithm: CompressionAlgorithm, bytes: &[u8]) -> Option<String> {
+        let raw = match algorithm {
+            CompressionAlgorithm::Lz4 => lz4_flex::decompress_size_prepended(bytes).ok()?,
+            CompressionAlgorithm::Zstd => zstd::decode_all(bytes).ok()?,
+        };
+        String::from_utf8(raw).ok()
+    }
+}
+
+/**
+ * A source's content, without what tells apart the urls it was loaded from: their response
+ * and provenance stay on their entries.
+ */
+enum CachedBody {
+    Plain(LoadedSourceCode),
+    Compressed {
+        algorithm: CompressionAlgorithm,
+        bytes: Vec<u8>,
+        original_len: usize,
+        kind: ModuleKind,
+        source_map: Option<Arc<str>>,
+    },
+}
+
+impl CachedBody {
+    /**
+     * The source `entry` holds. A compressed body is inflated into a new buffer owned by the
+     * returned source, so it lives exactly as long as whoever is reading (or transferring)
+     * it holds on to it.
+     */
+    fn load(&self, entry: &CacheEntry) -> Option<LoadedSourceCode> {
+        let source = match self {
+            CachedBody::Plain(source) => source.clone(),
+            CachedBody::Compressed {
+                algorithm,
+                bytes,
+                kind,
+                source_map,
+                ..
+            } => LoadedSourceCode {
+                kind: *kind,
+                source_map: source_map.clone(),
+                source: SourceCompression::decompress(*algorithm, bytes)?.into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            },
+        };
+        Some(LoadedSourceCode {
+            response: entry.response.clone(),
+            provenance: entry.provenance.clone(),
+            ..source
+        })
+    }
+
+    fn original_len(&self) -> usize {
+        match self {
+            CachedBody::Plain(source) => source.source.len(),
+            CachedBody::Compressed { original_len, .. } => *original_len,
+        }
+    }
+
+    fn saved_bytes(&self) -> usize {
+        match self {
+            CachedBody::Plain(_) => 0,
+            CachedBody::Compressed {
+                bytes,
+                original_len,
+                ..
+            } => original_len - bytes.len(),
+        }
+    }
+}
+
+/**
+ * When the file a source was loaded from was last modified, and how big it was, to tell it
+ * was edited since.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+/**
+ * The stamp of the file at `origin_url`, for `file:` urls of files that are there.
+ */
+fn file_stamp(origin_url: &str) -> Option<FileStamp> {
+    if !origin_url.starts_with("file:") {
+        return None;
+    }
+    let path = url::Url::parse(origin_url).ok()?.to_file_path().ok()?;
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(FileStamp {
+        modified: metadata.modified().ok()?,
+        len: metadata.len(),
+    })
+}
+
+/** A SHA-256 of a source's kind, code and source map, which its body is kept under. */
+type ContentHash = [u8; 32];
+
+struct CacheEntry {
+    content_hash: ContentHash,
+    response: Option<ResponseMetadata>,
+    provenance: Option<String>,
+    last_used: u64,
+    inserted_by: usize,
+    /** Of the file it was loaded from, when inserted, for sources from the local disk. */
+    stamp: Option<FileStamp>,
+}
+
+/** A body and how many entries hold it. */
+struct SharedBody {
+    body: CachedBody,
+    size: usize,
+    entries: usize,
+}
+
+/** An app's namespace and a canonical origin url. */
+type CacheKey = (Arc<str>, String);
+
+struct CacheInner {
+    entries: HashMap<CacheKey, CacheEntry>,
+    bodies: HashMap<ContentHash, SharedBody>,
+    total_bytes: usize,
+    tick: u64,
+    stats: SharedSourceCacheStats,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SharedSourceCacheStats {
+    pub hits: u64,
+    pub cross_runtime_hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /** Entries dropped on lookup because the file they were loaded from changed since. */
+    pub stale: u64,
+    /** Bytes held, counting compressed sources at their compressed size. */
+    pub bytes: usize,
+    pub compressed_entries: usize,
+    /** How much smaller `bytes` is than it would be without compression. */
+    pub compression_saved_bytes: usize,
+    /** How much smaller `bytes` is for keeping content cached under several urls once. */
+    pub deduplicated_bytes: usize,
+}
+
+/**
+ * Process-wide cache of loaded module source keyed by app and canonical origin url, each
+ * entry holding its content by hash, so content cached under several urls or apps, like the
+ * same vendored library, is kept once. Clones share the same cache, so every runtime hosting
+ * the same app can hand it to its manager and only the first one pays for the loads. Apps
+ * only see what was loaded under their own namespace, see `scoped`: two apps can each serve
+ * a different `file:///app/index.js`. Sources loaded from the local disk are checked against
+ * their file on every hit, so an edit is loaded afresh instead of served stale.
+ */
+#[derive(Clone)]
+pub struct SharedSourceCache {
+    inner: Arc<Mutex<CacheInner>>,
+    max_bytes: usize,
+    compression: Option<SourceCompression>,
+    next_consumer: Arc<AtomicUsize>,
+    namespace: Arc<str>,
+}
+
+fn content_hash(source: &LoadedSourceCode) -> ContentHash {
+    let mut hasher = Sha256::new();
+    hasher.input(source.kind.as_str().as_bytes());
+    hasher.input(&(source.source.len() as u64).to_le_bytes());
+    hasher.input(source.source.as_bytes());
+    if let Some(ref source_map) = source.source_map {
+        hasher.input(source_map.as_bytes());
+    }
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.result());
+    hash
+}
+
+fn body_size(body: &CachedBody) -> usize {
+    match body {
+        CachedBody::Plain(source) => {
+            source.source.len() + source.source_map.as_ref().map(|m| m.len()).unwrap_or(0)
+        }
+        CachedBody::Compressed {
+            bytes, source_map, ..
+        } => bytes.len() + source_map.as_ref().map(|m| m.len()).unwrap_or(0),
+    }
+}
+
+impl CacheInner {
+    /**
+     * Puts `entry` under `key`, holding the body already cached for its content, or the one
+     * `body` makes when there's none.
+     */
+    fn add<B: FnOnce() -> CachedBody>(&mut self, key: CacheKey, entry: CacheEntry, body: B) {
+        if let Some(previous) = self.entries.remove(&key) {
+            self.remove(&previous);
+        }
+        match self.bodies.get_mut(&entry.content_hash) {
+            Some(shared) => {
+                shared.entries += 1;
+                self.stats.deduplicated_bytes += shared.size;
+            }
+            None => {
+                let body = body();
+                let size = body_size(&body);
+                self.total_bytes += size;
+                MODULE_SOURCE_CACHE_BYTES.add(size as i64);
+                let saved = body.saved_bytes();
+                if saved > 0 {
+                    self.stats.compressed_entries += 1;
+                    self.stats.compression_saved_bytes += saved;
+                    MODULE_SOURCE_CACHE_COMPRESSION_SAVED_BYTES.add(saved as i64);
+                }
+                self.bodies.insert(
+                    entry.content_hash,
+                    SharedBody {
+                        body,
+                        size,
+                        entries: 1,
+                    },
+                );
+            }
+        }
+        self.entries.insert(key, entry);
+    }
+
+    /** Lets go of `entry`'s body, which goes with the last entry holding it. */
+    fn remove(&mut self, entry: &CacheEntry) {
+        match self.bodies.get_mut(&entry.content_hash) {
+            Some(shared) if shared.entries > 1 => {
+                shared.entries -= 1;
+                self.stats.deduplicated_bytes -= shared.size;
+                return;
+            }
+            Some(_) => {}
+            None => return,
+        }
+        let shared = self.bodies.remove(&entry.content_hash).unwrap();
+        self.total_bytes -= shared.size;
+        MODULE_SOURCE_CACHE_BYTES.sub(shared.size as i64);
+        let saved = shared.body.saved_bytes();
+        if saved > 0 {
+            self.stats.compressed_entries -= 1;
+            self.stats.compression_saved_bytes -= saved;
+            MODULE_SOURCE_CACHE_COMPRESSION_SAVED_BYTES.sub(saved as i64);
+        }
+    }
+}
+
+impl SharedSourceCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(CacheInner {
+                entries: HashMap::new(),
+                bodies: HashMap::new(),
+                total_bytes: 0,
+                tick: 0,
+                stats: SharedSourceCacheStats::default(),
+            })),
+            max_bytes,
+            compression: None,
+            next_consumer: Arc::new(AtomicUsize::new(1)),
+            namespace: "".into(),
+        }
+    }
+
+    /**
+     * A handle on the same cache that keeps `namespace`'s sources apart from those of every
+     * other namespace, typically an app's name. Sizes, stats and eviction stay shared.
+     */
+    pub fn scoped(&self, namespace: &str) -> Self {
+        Self {
+            namespace: namespace.into(),
+            ..self.clone()
+        }
+    }
+
+    fn key(&self, origin_url: &str) -> CacheKey {
+        (self.namespace.clone(), origin_url.to_string())
+    }
+
+    pub fn with_compression(mut self, compression: Option<SourceCompression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /**
+     * Hands out an id for a manager using this cache, so hits on entries another manager
+     * inserted can be counted as cross-runtime hits.
+     */
+    pub fn register_consumer(&self) -> usize {
+        self.next_consumer.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub fn get(&self, origin_url: &str, consumer: usize) -> Option<LoadedSourceCode> {
+        let key = self.key(origin_url);
+        // outside the lock, it's a syscall
+        let stamp = file_stamp(origin_url);
+        let mut guard = match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(_) => return None,
+        };
+        let inner = &mut *guard;
+        inner.tick += 1;
+        let tick = inner.tick;
+        let stale = match inner.entries.get(&key) {
+            Some(entry) => entry.stamp != stamp,
+            None => false,
+        };
+        if stale {
+            if let Some(entry) = inner.entries.remove(&key) {
+                trace!("{} changed on disk since it was cached", origin_url);
+                inner.remove(&entry);
+                inner.stats.stale += 1;
+            }
+        }
+        let found = match inner.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.last_used = tick;
+                inner
+                    .bodies
+                    .get(&entry.content_hash)
+                    .and_then(|shared| shared.body.load(entry))
+                    .map(|source| (source, entry.inserted_by != consumer))
+            }
+            None => None,
+        };
+        match found {
+            Some((source, cross_runtime)) => {
+                if cross_runtime {
+                    inner.stats.cross_runtime_hits += 1;
+                    MODULE_SOURCE_CACHE_LOOKUPS
+                        .with_label_values(&["cross_runtime_hit"])
+                        .inc();
+                } else {
+                    inner.stats.hits += 1;
+                    MODULE_SOURCE_CACHE_LOOKUPS
+                        .with_label_values(&["hit"])
+                        .inc();
+                }
+                Some(source)
+            }
+            None => {
+                inner.stats.misses += 1;
+                MODULE_SOURCE_CACHE_LOOKUPS
+                    .with_label_values(&["miss"])
+                    .inc();
+                None
+            }
+        }
+    }
+
+    /**
+     * The length of the source cached for `origin_url`, if any. Unlike `get` this isn't a
+     * lookup: it's not counted in the stats and doesn't make the entry recently used.
+     */
+    pub fn peek(&self, origin_url: &str) -> Option<usize> {
+        let stamp = file_stamp(origin_url);
+        let inner = self.inner.lock().ok()?;
+        match inner.entries.get(&self.key(origin_url)) {
+            Some(entry) if entry.stamp == stamp => inner
+                .bodies
+                .get(&entry.content_hash)
+                .map(|shared| shared.body.original_len()),
+            _ => None,
+        }
+    }
+
+    /**
+     * Stores `source` for `origin_url`, evicting least recently used entries to stay under
+     * the size limit. Content already cached under another url is held once for both.
+     * Sources larger than the whole cache are not kept.
+     */
+    pub fn insert(&self, origin_url: &str, source: LoadedSourceCode, consumer: usize) {
+        let hash = content_hash(&source);
+        let stamp = file_stamp(origin_url);
+        let entry = CacheEntry {
+            content_hash: hash,
+            response: source.response.clone(),
+            provenance: source.provenance.clone(),
+            last_used: 0,
+            inserted_by: consumer,
+            stamp,
+        };
+        let known = match self.inner.lock() {
+            Ok(inner) => inner.bodies.contains_key(&hash),
+            Err(_) => return,
+        };
+        // compressed outside the lock, unless another url's entry already holds it
+        let body = if known {
+            None
+        } else {
+            Some(self.body(source.clone()))
+        };
+        if body
+            .as_ref()
+            .map_or(false, |body| body_size(body) > self.max_bytes)
+        {
+            return;
+        }
+        let mut inner = match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(_) => return,
+        };
+        inner.tick += 1;
+        let entry = CacheEntry {
+            last_used: inner.tick,
+            ..entry
+        };
+        // unless the entry holding it went in the meantime
+        inner.add(self.key(origin_url), entry, || {
+            body.unwrap_or_else(|| self.body(source))
+        });
+        self.evict(&mut inner);
+    }
+
+    /** `source` as its entry's body, compressed if it's worth it. */
+    fn body(&self, source: LoadedSourceCode) -> CachedBody {
+        let source = LoadedSourceCode {
+            response: None,
+            provenance: None,
+            ..source
+        };
+        match self.compression {
+            Some(ref compression) if source.source.len() >= compression.min_bytes => {
+                match compression.compress(&source.source) {
+                    Some(bytes) => CachedBody::Compressed {
+                        algorithm: compression.algorithm,
+                        bytes,
+                        original_len: source.source.len(),
+                        kind: source.kind,
+                        source_map: source.source_map,
+                    },
+                    None => CachedBody::Plain(source),
+                }
+            }
+            _ => CachedBody::Plain(source),
+        }
+    }
+
+    /**
+     * Replaces the entry for `origin_url` only if `fresh` differs from what's cached, so one
+     * runtime invalidating a module doesn't throw away source other runtimes still share.
+     * Returns whether the cached content changed.
+     */
+    pub fn refresh(&self, origin_url: &str, fresh: LoadedSourceCode, consumer: usize) -> bool {
+        let unchanged = match self.inner.lock() {
+            Ok(inner) => match inner.entries.get(&self.key(origin_url)) {
+                Some(entry) => entry.content_hash == content_hash(&fresh),
+                None => false,
+            },
+            Err(_) => return false,
+        };
+        if unchanged {
+            return false;
+        }
+        self.insert(origin_url, fresh, consumer);
+        true
+    }
+
+    pub fn remove(&self, origin_url: &str) -> bool {
+        let mut inner = match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(_) => return false,
+        };
+        match inner.entries.remove(&self.key(origin_url)) {
+            Some(entry) => {
+                inner.remove(&entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stats(&self) -> SharedSourceCacheStats {
+        match self.inner.lock() {
+            Ok(inner) => SharedSourceCacheStats {
+                bytes: inner.total_bytes,
+                ..inner.stats.clone()
+            },
+            Err(_) => SharedSourceCacheStats::default(),
+        }
+    }
+
+    fn evict(&self, inner: &mut CacheInner) {
+        while inner.total_bytes > self.max_bytes {
+            let oldest = match inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+            {
+                Some((key, _)) => key.clone(),
+                None => break,
+            };
+            if let Some(entry) = inner.entries.remove(&oldest) {
+                trace!("evicting {} from shared module source cache", oldest.1);
+                inner.remove(&entry);
+                inner.stats.evictions += 1;
+                MODULE_SOURCE_CACHE_EVICTIONS.inc();
+            }
+        }
+    }
+}
+
+lazy_static! {
+    static ref PROCESS_WIDE: Mutex<Option<SharedSourceCache>> = Mutex::new(None);
+}
+
+/**
+ * The cache shared by every runtime in this process. The first caller decides its size and
+ * compression.
+ */
+pub fn process_wide_cache(
+    max_bytes: usize,
+    compression: Option<SourceCompression>,
+) -> SharedSourceCache {
+    let mut cache = PROCESS_WIDE.lock().unwrap();
+    cache
+        .get_or_insert_with(|| SharedSourceCache::new(max_bytes).with_compression(compression))
+        .clone()
+}
+
+impl Default for SharedSourceCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARED_CACHE_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::*;
+    use crate::module_resolver::{
+        FunctionModuleResolver, LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager,
+        ModuleSourceData, SourceLoader, StandardModuleResolverManager,
+    };
+    use std::sync::RwLock;
+
+    fn source(code: &str) -> LoadedSourceCode {
+        LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: None,
+            source: code.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        }
+    }
+
+    struct CountingLoader {
+        loads: Arc<AtomicUsize>,
+        code: Arc<RwLock<String>>,
+    }
+
+    impl SourceLoader for CountingLoader {
+        fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            Ok(source(&self.code.read().unwrap()))
+        }
+    }
+
+    fn manager(
+        cache: &SharedSourceCache,
+        loads: &Arc<AtomicUsize>,
+        code: &Arc<RwLock<String>>,
+    ) -> StandardModuleResolverManager {
+        let (loads, code) = (loads.clone(), code.clone());
+        let resolver = FunctionModuleResolver::new(
+            "function",
+            Box::new(move |specifier, _| {
+                Ok(ModuleSourceData {
+                    origin_url: specifier.to_string(),
+                    source_loader: Box::new(CountingLoader {
+                        loads: loads.clone(),
+                        code: code.clone(),
+                    }),
+                })
+            }),
+        );
+        StandardModuleResolverManager::new(
+            vec![Box::new(resolver) as Box<ModuleResolver>],
+            Some("function:///app/".to_string()),
+        )
+        .with_shared_cache(cache.clone())
+    }
+
+    #[test]
+    fn test_second_runtime_loads_from_memory() {
+        let cache = SharedSourceCache::default();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let code = Arc::new(RwLock::new("export default 1;".to_string()));
+        let first = manager(&cache, &loads, &code);
+        let second = manager(&cache, &loads, &code);
+
+        first
+            .resolve_blocking("function:///app/index.js".to_string(), None)
+            .unwrap();
+        let module = second
+            .resolve_blocking("function:///app/index.js".to_string(), None)
+            .unwrap();
+        assert_eq!("export default 1;", &*module.loaded_source.source);
+        assert_eq!(1, loads.load(Ordering::SeqCst));
+        let stats = cache.stats();
+        assert_eq!(1, stats.misses);
+        assert_eq!(1, stats.cross_runtime_hits);
+    }
+
+    #[test]
+    fn test_apps_keep_their_own_sources() {
+        let cache = SharedSourceCache::default();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let shop = Arc::new(RwLock::new("export default 'shop';".to_string()));
+        let blog = Arc::new(RwLock::new("export default 'blog';".to_string()));
+        let load = |app: &str, code: &Arc<RwLock<String>>| {
+            manager(&cache.scoped(app), &loads, code)
+                .resolve_blocking("function:///app/index.js".to_string(), None)
+                .unwrap()
+                .loaded_source
+                .source
+        };
+
+        assert_eq!("export default 'shop';", &*load("shop", &shop));
+        assert_eq!("export default 'blog';", &*load("blog", &blog));
+        assert_eq!(2, loads.load(Ordering::SeqCst));
+        // another runtime of the same app still shares what it loaded
+        assert_eq!("export default 'shop';", &*load("shop", &blog));
+        assert_eq!(2, loads.load(Ordering::SeqCst));
+        assert_eq!(1, cache.stats().cross_runtime_hits);
+
+        assert!(cache.scoped("blog").remove("function:///app/index.js"));
+        assert!(cache
+            .scoped("shop")
+            .peek("function:///app/index.js")
+            .is_some());
+    }
+
+    #[test]
+    fn test_disk_edits_are_loaded_afresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.js");
+        std::fs::write(&path, "export default 1;").unwrap();
+        let root = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let cache = SharedSourceCache::default();
+        let manager = || {
+            StandardModuleResolverManager::new(
+                vec![
+                    Box::new(LocalDiskModuleResolver::new(Some(root.clone())).unwrap())
+                        as Box<ModuleResolver>,
+                ],
+                Some(root.clone()),
+            )
+            .with_shared_cache(cache.clone())
+        };
+        let load = || {
+            manager()
+                .resolve_blocking("./index.js".to_string(), None)
+                .unwrap()
+                .loaded_source
+                .source
+        };
+
+        assert_eq!("export default 1;", &*load());
+        assert_eq!("export default 1;", &*load());
+        assert_eq!(1, cache.stats().cross_runtime_hits);
+
+        std::fs::write(&path, "export default 'edited';").unwrap();
+        let url = url::Url::from_file_path(&path).unwrap().into_string();
+        assert_eq!(None, cache.peek(&url));
+        assert_eq!("export default 'edited';", &*load());
+        let stats = cache.stats();
+        assert_eq!(1, stats.stale);
+        assert_eq!(2, stats.misses);
+        assert_eq!(24, stats.bytes);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = SharedSourceCache::new(10);
+        cache.insert("a", source("aaaa"), 1);
+        cache.insert("b", source("bbbb"), 1);
+        assert!(cache.get("a", 1).is_some());
+        cache.insert("c", source("cccc"), 1);
+
+        assert!(cache.get("b", 1).is_none());
+        assert!(cache.get("a", 1).is_some());
+        assert!(cache.get("c", 1).is_some());
+        assert_eq!(8, cache.stats().bytes);
+        assert_eq!(1, cache.stats().evictions);
+
+        cache.insert("huge", source("0123456789abc"), 1);
+        assert!(cache.get("huge", 1).is_none());
+    }
+
+    #[test]
+    fn test_invalidation_keeps_unchanged_source() {
+        let cache = SharedSourceCache::default();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let code = Arc::new(RwLock::new("export default 1;".to_string()));
+        let first = manager(&cache, &loads, &code);
+        let second = manager(&cache, &loads, &code);
+        first
+            .resolve_blocking("function:///app/index.js".to_string(), None)
+            .unwrap();
+
+        assert!(!first
+            .invalidate_shared_source("function:///app/index.js")
+            .unwrap());
+        assert_eq!(
+            "export default 1;",
+            &*cache.get("function:///app/index.js", 0).unwrap().source
+        );
+
+        *code.write().unwrap() = "export default 2;".to_string();
+        assert!(first
+            .invalidate_shared_source("function:///app/index.js")
+            .unwrap());
+        let module = second
+            .resolve_blocking("function:///app/index.js".to_string(), None)
+            .unwrap();
+        assert_eq!("export default 2;", &*module.loaded_source.source);
+    }
+
+    #[test]
+    fn test_runtimes_share_source_bytes() {
+        let cache = SharedSourceCache::default();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let code = Arc::new(RwLock::new("export default 1;".to_string()));
+        let first = manager(&cache, &loads, &code)
+            .resolve_blocking("function:///app/index.js".to_string(), None)
+            .unwrap();
+        let second = manager(&cache, &loads, &code)
+            .resolve_blocking("function:///app/index.js".to_string(), None)
+            .unwrap();
+        assert!(Arc::ptr_eq(
+            &first.loaded_source.source,
+            &second.loaded_source.source
+        ));
+
+        cache.insert("function:///app/index.js", source("export default 2;"), 0);
+        // bytes already handed out are never rewritten by a replacement
+        assert_eq!("export default 1;", &*first.loaded_source.source);
+    }
+
+    #[test]
+    fn test_keeps_identical_content_once() {
+        let cache = SharedSourceCache::default();
+        let lib = "export const version = '1.0.0';";
+        cache.insert("https://cdn.test/lib@1.0.0.js", source(lib), 1);
+        cache.scoped("blog").insert(
+            "file:///app/vendor/lib.js",
+            LoadedSourceCode {
+                provenance: Some("vendored".to_string()),
+                ..source(lib)
+            },
+            2,
+        );
+        let stats = cache.stats();
+        assert_eq!(lib.len(), stats.bytes);
+        assert_eq!(lib.len(), stats.deduplicated_bytes);
+
+        let cdn = cache.get("https://cdn.test/lib@1.0.0.js", 1).unwrap();
+        let vendored = cache
+            .scoped("blog")
+            .get("file:///app/vendor/lib.js", 2)
+            .unwrap();
+        assert!(Arc::ptr_eq(&cdn.source, &vendored.source));
+        // what's particular to each url stays its own
+        assert_eq!(None, cdn.provenance);
+        assert_eq!(Some("vendored".to_string()), vendored.provenance);
+
+        // the content stays for as long as one of its urls is cached
+        assert!(cache.remove("https://cdn.test/lib@1.0.0.js"));
+        assert_eq!(lib.len(), cache.stats().bytes);
+        assert_eq!(0, cache.stats().deduplicated_bytes);
+        assert!(cache.get("https://cdn.test/lib@1.0.0.js", 1).is_none());
+        assert!(cache.scoped("blog").remove("file:///app/vendor/lib.js"));
+        assert_eq!(0, cache.stats().bytes);
+    }
+
+    #[test]
+    fn test_compresses_large_sources_only() {
+        for algorithm in &[CompressionAlgorithm::Lz4, CompressionAlgorithm::Zstd] {
+            let cache = SharedSourceCache::default().with_compression(Some(SourceCompression {
+                algorithm: *algorithm,
+                min_bytes: 1024,
+            }));
+            let big = "export const x = 'aaaaaaaaaaaaaaaa';\n".repeat(200);
+            cache.insert("big", source(&big), 1);
+            cache.insert("small", source("export default 1;"), 1);
+
+            let stats = cache.stats();
+            assert_eq!(1, stats.compressed_entries);
+            assert!(stats.compression_saved_bytes > big.len() / 2);
+            assert_eq!(big.len() + 17 - stats.compression_saved_bytes, stats.bytes);
+            assert_eq!(big, &*cache.get("big", 1).unwrap().source);
+            let small = cache.get("small", 1).unwrap();
+            assert!(Arc::ptr_eq(
+                &small.source,
+                &cache.get("small", 1).unwrap().source
+            ));
+
+            cache.remove("big");
+            assert_eq!(0, cache.stats().compressed_entries);
+            assert_eq!(17, cache.stats().bytes);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::errors::*;
+
+use std::sync::Arc;
+
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+
+use super::{
+    wasm_bytes, LoadedSourceCode, ModuleCacheState, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+/** The first line of a module carrying its own signature, followed by the base64. */
+pub const SIGNATURE_PREFIX: &str = "// ed25519-signature: ";
+
+/**
+ * Where a module's detached ed25519 signature, in base64, is found.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignatureLocation {
+    /** In `<module>.sig`, resolved through the same resolver as the module. */
+    Sidecar,
+    /**
+     * On the module's first line, after `SIGNATURE_PREFIX`, signing everything after that
+     * line. The line is kept, so line numbers don't move.
+     */
+    Prefix,
+}
+
+fn signature_error(origin_url: &str, msg: impl std::fmt::Display) -> FlyError {
+    new(
+        ErrorKind::PermissionDenied,
+        format!("Could not verify the signature of {}: {}", origin_url, msg),
+    )
+}
+
+fn parse_public_key(key: &str) -> FlyResult<PublicKey> {
+    let bytes = base64::decode(key.trim())
+        .map_err(|e| FlyError::from(format!("invalid ed25519 public key {}: {}", key, e)))?;
+    PublicKey::from_bytes(&bytes)
+        .map_err(|e| FlyError::from(format!("invalid ed25519 public key {}: {}", key, e)))
+}
+
+/**
+ * Loads modules through another resolver, e.g. one fetching through a mirror, and only
+ * returns those signed by one of its public keys. Several keys are accepted at once so they
+ * can be rotated: sign with the new key, add it, then drop the old one.
+ */
+pub struct SignedModuleResolver {
+    inner: Box<ModuleResolver>,
+    public_keys: Arc<Vec<PublicKey>>,
+    location: SignatureLocation,
+}
+
+impl SignedModuleResolver {
+    /**
+     * `public_keys` are base64, as 32 raw bytes each.
+     */
+    pub fn new(
+        inner: Box<ModuleResolver>,
+        public_keys: &[String],
+        location: SignatureLocation,
+    ) -> FlyResult<Self> {
+        if public_keys.is_empty() {
+            return Err(FlyError::from(
+                "a signed module resolver needs at least one public key".to_string(),
+            ));
+        }
+        let public_keys = public_keys
+            .iter()
+            .map(|key| parse_public_key(key))
+            .collect::<FlyResult<Vec<_>>>()?;
+        Ok(Self {
+            inner,
+            public_keys: Arc::new(public_keys),
+            location,
+        })
+    }
+}
+
+impl ModuleResolver for SignedModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let data = self.inner.resolve_module(module_specifier, referer_info)?;
+        let signature = match self.location {
+            SignatureLocation::Sidecar => {
+                let sidecar_url = format!("{}.sig", data.origin_url);
+                let resolved = self.inner.resolve_module(
+                    &sidecar_url,
+                    Some(RefererInfo {
+                        origin_url: data.origin_url.clone(),
+                        is_wasm: Some(false),
+                        source_code: None,
+                        identifier_hash: None,
+                        line: None,
+                        column: None,
+                        app_id: None,
+                        deadline: None,
+                    }),
+                );
+                // reported once the module loads, naming the module rather than its sidecar
+                Some(
+                    resolved
+                        .map(|sidecar| sidecar.source_loader)
+                        .map_err(|e| format!("no signature at {}: {}", sidecar_url, e)),
+                )
+            }
+            SignatureLocation::Prefix => None,
+        };
+        Ok(ModuleSourceData {
+            source_loader: Box::new(SignedSourceLoader {
+                origin_url: data.origin_url.clone(),
+                loader: data.source_loader,
+                signature,
+                public_keys: self.public_keys.clone(),
+            }),
+            origin_url: data.origin_url,
+        })
+    }
+    fn get_protocol(&self) -> String {
+        self.inner.get_protocol()
+    }
+    fn is_remote(&self) -> bool {
+        self.inner.is_remote()
+    }
+    fn handles_bare_specifier(&self, specifier: &str) -> bool {
+        self.inner.handles_bare_specifier(specifier)
+    }
+    fn name(&self) -> String {
+        format!("SignedModuleResolver({})", self.inner.name())
+    }
+    fn describe(&self) -> ResolverDescription {
+        self.inner
+            .describe()
+            .named(&self.name())
+            .with(
+                "signature",
+                match self.location {
+                    SignatureLocation::Sidecar => "sidecar",
+                    SignatureLocation::Prefix => "prefix",
+                },
+            )
+            .with("public_keys", &self.public_keys.len().to_string())
+    }
+    fn is_passthrough(&self) -> bool {
+        self.inner.is_passthrough()
+    }
+    fn priority(&self) -> i32 {
+        self.inner.priority()
+    }
+    fn cache_state(&self, origin_url: &str, tenant: &str) -> Option<ModuleCacheState> {
+        self.inner.cache_state(origin_url, tenant)
+    }
+    fn inheritable(&self) -> bool {
+        self.inner.inheritable()
+    }
+    fn needs_runtime_thread(&self) -> bool {
+        self.inner.needs_runtime_thread()
+    }
+}
+
+/**
+ * Loads through the wrapped resolver's loader and checks the signature over the bytes it
+ * loaded: the source text, or a wasm module's binary.
+ */
+struct SignedSourceLoader {
+    origin_url: String,
+    loader: Box<SourceLoader>,
+    /** The sidecar's loader, or why there's none; `None` for prefixed signatures. */
+    signature: Option<Result<Box<SourceLoader>, String>>,
+    public_keys: Arc<Vec<PublicKey>>,
+}
+
+impl SignedSourceLoader {
+    fn verify(&self, signed: &[u8], signature: &str) -> FlyResult<()> {
+        let origin_url = &self.origin_url[..];
+        let bytes = base64::decode(signature.trim())
+            .map_err(|e| signature_error(origin_url, format!("invalid signature: {}", e)))?;
+        let signature = Signature::from_bytes(&bytes)
+            .map_err(|e| signature_error(origin_url, format!("invalid signature: {}", e)))?;
+        if self
+            .public_keys
+            .iter()
+            .any(|key| key.verify(signed, &signature).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(signature_error(
+                origin_url,
+                "it isn't signed by any of the trusted keys",
+            ))
+        }
+    }
+}
+
+impl SourceLoader for SignedSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let source = self.loader.load_source()?;
+        match self.signature {
+            Some(Ok(ref sidecar)) => {
+                let signature = sidecar.load_source().map_err(|e| {
+                    signature_error(&self.origin_url, format!("no signature: {}", e))
+                })?;
+                if source.is_wasm() {
+                    self.verify(&wasm_bytes(&source)?, &signature.source)?;
+                } else {
+                    self.verify(source.source.as_bytes(), &signature.source)?;
+                }
+            }
+            Some(Err(ref missing)) => return Err(signature_error(&self.origin_url, missing)),
+            None => {
+                let text = &source.source[..];
+                if source.is_wasm() || !text.starts_with(SIGNATURE_PREFIX) {
+                    return Err(signature_error(
+                        &self.origin_url,
+                        format!("it doesn't start with {:?}", SIGNATURE_PREFIX.trim()),
+                    ));
+                }
+                let line_end = text.find('\n').unwrap_or_else(|| text.len());
+                let signed = text.get(line_end + 1..).unwrap_or("");
+                self.verify(signed.as_bytes(), &text[SIGNATURE_PREFIX.len()..line_end])?;
+            }
+        }
+        Ok(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolverManager, StandardModuleResolverManager,
+    };
+
+    fn fixtures() -> std::path::PathBuf {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/signed")
+    }
+
+    fn key(name: &str) -> String {
+        std::fs::read_to_string(fixtures().join(name)).unwrap()
+    }
+
+    fn manager(keys: &[String], location: SignatureLocation) -> StandardModuleResolverManager {
+        let root = url::Url::from_directory_path(fixtures())
+            .unwrap()
+            .into_string();
+        let signed = SignedModuleResolver::new(
+            Box::new(LocalDiskModuleResolver::new(Some(root.clone())).unwrap()),
+            keys,
+            location,
+        )
+        .unwrap();
+        StandardModuleResolverManager::new(
+            vec![Box::new(signed) as Box<ModuleResolver>],
+            Some(root),
+        )
+    }
+
+    fn load(manager: &StandardModuleResolverManager, specifier: &str) -> FlyResult<String> {
+        manager
+            .resolve_blocking(specifier.to_string(), None)
+            .map(|m| m.loaded_source.source.to_string())
+    }
+
+    #[test]
+    fn test_loads_modules_with_a_valid_sidecar_signature() {
+        // the signing key is accepted among retired ones
+        let manager = manager(
+            &[key("retired.pub"), key("key.pub")],
+            SignatureLocation::Sidecar,
+        );
+        assert!(load(&manager, "./greet.js").unwrap().contains("hello"));
+
+        let err = load(&manager, "./tampered.js").err().unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+        let msg = format!("{}", err);
+        assert!(msg.contains("tampered.js"), msg);
+        assert!(msg.contains("isn't signed by any of the trusted keys"), msg);
+
+        let msg = format!("{}", load(&manager, "./unsigned.js").err().unwrap());
+        assert!(msg.contains("signature of file://"), msg);
+        assert!(msg.contains("unsigned.js.sig"), msg);
+
+        let retired = self::manager(&[key("retired.pub")], SignatureLocation::Sidecar);
+        assert!(load(&retired, "./greet.js").is_err());
+    }
+
+    #[test]
+    fn test_loads_modules_with_a_valid_prefix_signature() {
+        let manager = manager(&[key("key.pub")], SignatureLocation::Prefix);
+        let source = load(&manager, "./prefixed.js").unwrap();
+        assert!(source.starts_with(SIGNATURE_PREFIX));
+        assert!(load(&manager, "./greet.js").is_err());
+
+        assert!(SignedModuleResolver::new(
+            Box::new(LocalDiskModuleResolver::new(None).unwrap()),
+            &[],
+            SignatureLocation::Prefix,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::future::Shared;
+use futures::sync::oneshot;
+use futures::{Async, Future, Poll};
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: bool,
+    waiters: Vec<oneshot::Sender<()>>,
+    children: Vec<CancellationToken>,
+}
+
+/**
+ * Cancelled when the scope that owns it ends: a runtime when it's disposed, or a request
+ * when its client goes away. Clones share the same state; `child` makes a token that is also
+ * cancelled with this one.
+ */
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<Mutex<TokenState>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn child(&self) -> Self {
+        let child = Self::new();
+        let mut state = self.state.lock().unwrap();
+        if state.cancelled {
+            child.cancel();
+        } else {
+            state.children.push(child.clone());
+        }
+        child
+    }
+
+    pub fn cancel(&self) {
+        let (waiters, children) = {
+            let mut state = self.state.lock().unwrap();
+            if state.cancelled {
+                return;
+            }
+            state.cancelled = true;
+            (
+                std::mem::replace(&mut state.waiters, vec![]),
+                std::mem::replace(&mut state.children, vec![]),
+            )
+        };
+        for tx in waiters {
+            let _ = tx.send(());
+        }
+        for child in children {
+            child.cancel();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+
+    /**
+     * Resolves once the token is cancelled. Also resolves if every clone of the token is
+     * dropped, since nothing can be waiting on that scope any more.
+     */
+    pub fn cancelled(&self) -> Box<Future<Item = (), Error = ()> + Send> {
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.state.lock().unwrap();
+        if state.cancelled {
+            let _ = tx.send(());
+        } else {
+            state.waiters.push(tx);
+        }
+        Box::new(rx.then(|_| Ok(())))
+    }
+}
+
+type SharedLoad<T> = Shared<Box<Future<Item = T, Error = String> + Send>>;
+
+struct Flight<T> {
+    id: u64,
+    load: SharedLoad<T>,
+    waiters: usize,
+    abort: CancellationToken,
+}
+
+struct Flights<T> {
+    next_id: u64,
+    by_key: HashMap<String, Flight<T>>,
+}
+
+/**
+ * Runs one load per key no matter how many callers ask for it at once. Each caller waits
+ * under its own scope token; when a caller's scope is cancelled only that caller stops
+ * waiting, and once no caller is left the load is dropped (which aborts its download) and
+ * its abort token is cancelled so it can skip committing anything to a cache.
+ */
+pub struct SingleFlight<T> {
+    flights: Arc<Mutex<Flights<T>>>,
+}
+
+impl<T> Clone for SingleFlight<T> {
+    fn clone(&self) -> Self {
+        Self {
+            flights: self.flights.clone(),
+        }
+    }
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            flights: Arc::new(Mutex::new(Flights {
+                next_id: 0,
+                by_key: HashMap::new(),
+            })),
+        }
+    }
+}
+
+impl<T> SingleFlight<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Waits for the load of `key`, calling `start` with the load's abort token if no load is
+     * running yet.
+     */
+    pub fn load<L, F>(
+        &self,
+        key: &str,
+        scope: &CancellationToken,
+        start: L,
+    ) -> Box<Future<Item = T, Error = FlyError> + Send>
+    where
+        L: FnOnce(CancellationToken) -> F,
+        F: Future<Item = T, Error = FlyError> + Send + 'static,
+    {
+        let (id, load) = {
+            let mut flights = self.flights.lock().unwrap();
+            if !flights.by_key.contains_key(key) {
+                flights.next_id += 1;
+                let abort = CancellationToken::new();
+                let load: Box<Future<Item = T, Error = String> + Send> =
+                    Box::new(start(abort.clone()).map_err(|e| format!("{}", e)));
+                let flight = Flight {
+                    id: flights.next_id,
+                    load: load.shared(),
+                    waiters: 0,
+                    abort,
+                };
+                flights.by_key.insert(key.to_string(), flight);
+            }
+            let flight = flights.by_key.get_mut(key).unwrap();
+            flight.waiters += 1;
+            (flight.id, flight.load.clone())
+        };
+        Box::new(Waiter {
+            key: key.to_string(),
+            id,
+            load,
+            cancelled: scope.cancelled(),
+            flights: self.flights.clone(),
+            done: false,
+        })
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.flights.lock().unwrap().by_key.len()
+    }
+}
+
+struct Waiter<T> {
+    key: String,
+    id: u64,
+    load: SharedLoad<T>,
+    cancelled: Box<Future<Item = (), Error = ()> + Send>,
+    flights: Arc<Mutex<Flights<T>>>,
+    done: bool,
+}
+
+impl<T> Waiter<T> {
+    fn finish(&mut self, cancelled: bool) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        let mut flights = match self.flights.lock() {
+            Ok(flights) => flights,
+            Err(_) => return,
+        };
+        let abandoned = match flights.by_key.get_mut(&self.key) {
+            Some(ref mut flight) if flight.id == self.id => {
+                if cancelled {
+                    flight.waiters -= 1;
+                    flight.waiters == 0
+                } else {
+                    true
+                }
+            }
+            _ => false,
+        };
+        if abandoned {
+            let flight = flights.by_key.remove(&self.key).unwrap();
+            if cancelled {
+                debug!("aborting load of {}, no one is waiting for it", self.key);
+                flight.abort.cancel();
+            }
+        }
+    }
+}
+
+impl<T: Clone> Future for Waiter<T> {
+    type Item = T;
+    type Error = FlyError;
+
+    fn poll(&mut self) -> Poll<T, FlyError> {
+        match self.load.poll() {
+            Ok(Async::Ready(value)) => {
+                self.finish(false);
+                return Ok(Async::Ready((*value).clone()));
+            }
+            Err(e) => {
+                self.finish(false);
+                return Err(FlyError::from((*e).clone()));
+            }
+            Ok(Async::NotReady) => {}
+        }
+        match self.cancelled.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            _ => {
+                self.finish(true);
+                Err(FlyError::from(format!(
+                    "loading {} was cancelled",
+                    self.key
+                )))
+            }
+        }
+    }
+}
+
+impl<T> Drop for Waiter<T> {
+    fn drop(&mut self) {
+        self.finish(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    fn gated_load(
+        started: Arc<AtomicUsize>,
+        gate: oneshot::Receiver<&'static str>,
+    ) -> impl FnOnce(CancellationToken) -> Box<Future<Item = String, Error = FlyError> + Send> {
+        move |_abort| {
+            started.fetch_add(1, Ordering::SeqCst);
+            Box::new(
+                gate.map(|s| s.to_string())
+                    .map_err(|_| FlyError::from("gate dropped".to_string())),
+            )
+        }
+    }
+
+    #[test]
+    fn test_survivor_gets_module_after_other_importer_cancels() {
+        let flights = SingleFlight::<String>::new();
+        let started = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = oneshot::channel();
+        let first_scope = CancellationToken::new();
+        let second_scope = CancellationToken::new();
+
+        let first = flights.load(
+            "https://a.test/mod.js",
+            &first_scope,
+            gated_load(started.clone(), rx),
+        );
+        let (_unused_tx, unused_rx) = oneshot::channel();
+        let second = flights.load(
+            "https://a.test/mod.js",
+            &second_scope,
+            gated_load(started.clone(), unused_rx),
+        );
+        assert_eq!(1, started.load(Ordering::SeqCst));
+
+        first_scope.cancel();
+        let err = first.wait().err().unwrap();
+        assert!(format!("{}", err).contains("cancelled"));
+        assert_eq!(1, flights.in_flight());
+
+        tx.send("export default 1").unwrap();
+        assert_eq!("export default 1", second.wait().unwrap());
+        assert_eq!(0, flights.in_flight());
+    }
+
+    #[test]
+    fn test_last_waiter_cancelling_aborts_the_load() {
+        let flights = SingleFlight::<String>::new();
+        let runtime = CancellationToken::new();
+        let request = runtime.child();
+        let load_abort = Arc::new(Mutex::new(None));
+        let wrote_cache = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = oneshot::channel::<&'static str>();
+
+        let captured = load_abort.clone();
+        let wrote = wrote_cache.clone();
+        let waiter = flights.load("https://a.test/big.wasm", &request, move |abort| {
+            *captured.lock().unwrap() = Some(abort.clone());
+            Box::new(rx.then(move |res| {
+                if !abort.is_cancelled() {
+                    wrote.store(true, Ordering::SeqCst);
+                }
+                res.map(|s| s.to_string())
+                    .map_err(|_| FlyError::from("aborted".to_string()))
+            })) as Box<Future<Item = String, Error = FlyError> + Send>
+        });
+
+        runtime.cancel();
+        assert!(request.is_cancelled());
+        assert!(waiter.wait().is_err());
+        assert_eq!(0, flights.in_flight());
+        let abort = load_abort.lock().unwrap().take().unwrap();
+        assert!(abort.is_cancelled());
+        // the load was dropped with its last waiter, so nothing is left to receive the body
+        assert!(tx.send("late").is_err());
+        assert!(!wrote_cache.load(Ordering::SeqCst));
+    }
+}
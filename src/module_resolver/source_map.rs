@@ -0,0 +1,391 @@
+use std::path::Path;
+
+/**
+ * Returns `map_json` with the contents of every `sources` entry inlined into
+ * `sourcesContent`, so the map still symbolicates once the module is cached or deployed
+ * away from its original tree. Entries are resolved relative to the map's location (and
+ * `sourceRoot`), and rewritten as the `file` urls they resolve to; only local files inside
+ * `sandbox`, when given, are read. A source that can't be read is left as `null` with a
+ * warning. Maps that aren't JSON objects are returned unchanged.
+ */
+pub fn inline_source_map_sources(
+    map_json: &str,
+    map_path: &Path,
+    sandbox: Option<&Path>,
+) -> String {
+    let mut map: serde_json::Value = match serde_json::from_str(map_json) {
+        Ok(map @ serde_json::Value::Object(_)) => map,
+        _ => {
+            warn!(
+                "{} is not a JSON source map, leaving it as is",
+                map_path.display()
+            );
+            return map_json.to_string();
+        }
+    };
+    let sources: Vec<Option<String>> = match map.get("sources").and_then(|s| s.as_array()) {
+        Some(sources) => sources
+            .iter()
+            .map(|s| s.as_str().map(|s| s.to_string()))
+            .collect(),
+        None => return map_json.to_string(),
+    };
+    let existing: Vec<serde_json::Value> = map
+        .get("sourcesContent")
+        .and_then(|c| c.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let map_url = match url::Url::from_file_path(map_path) {
+        Ok(url) => url,
+        Err(_) => return map_json.to_string(),
+    };
+    let base_url = source_base_url(&map, &map_url);
+    absolutize_sources(&mut map, &base_url);
+    if sources.len() == existing.len() && existing.iter().all(|c| c.is_string()) {
+        return map.to_string();
+    }
+
+    let contents: Vec<serde_json::Value> = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            if let Some(content @ serde_json::Value::String(_)) = existing.get(i) {
+                return content.clone();
+            }
+            let source = match source {
+                Some(source) => source,
+                None => return serde_json::Value::Null,
+            };
+            match read_source(&base_url, source, sandbox) {
+                Ok(content) => serde_json::Value::String(content),
+                Err(reason) => {
+                    warn!(
+                        "source map {}: not inlining {}: {}",
+                        map_path.display(),
+                        source,
+                        reason
+                    );
+                    serde_json::Value::Null
+                }
+            }
+        })
+        .collect();
+    map["sourcesContent"] = serde_json::Value::Array(contents);
+    map.to_string()
+}
+
+/**
+ * What `map`'s `sources` are relative to: `url`, where the map is, joined with the map's
+ * `sourceRoot`.
+ */
+fn source_base_url(map: &serde_json::Value, url: &url::Url) -> url::Url {
+    let source_root = map
+        .get("sourceRoot")
+        .and_then(|r| r.as_str())
+        .filter(|r| !r.is_empty())
+        .map(|r| format!("{}/", r.trim_end_matches('/')));
+    match source_root {
+        Some(ref root) => url.join(root).unwrap_or_else(|_| url.clone()),
+        None => url.clone(),
+    }
+}
+
+/**
+ * Rewrites `map`'s `sources` as the urls they resolve to against `base_url`; its
+ * `sourceRoot` goes, since `base_url` includes it.
+ */
+fn absolutize_sources(map: &mut serde_json::Value, base_url: &url::Url) {
+    if let Some(sources) = map.get_mut("sources").and_then(|s| s.as_array_mut()) {
+        for source in sources.iter_mut() {
+            let url = source.as_str().and_then(|s| base_url.join(s).ok());
+            if let Some(url) = url {
+                *source = serde_json::Value::String(url.into_string());
+            }
+        }
+    }
+    if let Some(map) = map.as_object_mut() {
+        map.remove("sourceRoot");
+    }
+}
+
+/**
+ * `map_json`, the source map of the module at `origin_url`, with its `sources` resolved
+ * against `origin_url` (and `sourceRoot`) as absolute urls, so the map still makes sense
+ * once the runtime has it as a string, wherever it came from. Sources missing from
+ * `sourcesContent` are filled in with what `load` returns for their url, when given, or left
+ * `null` with a warning when it fails. `None`, with a warning, if the map isn't a JSON
+ * object: a map that can't be used is dropped rather than failing the module.
+ */
+pub fn absolutize_source_map(
+    map_json: &str,
+    origin_url: &str,
+    load: Option<&Fn(&str) -> Result<String, String>>,
+) -> Option<String> {
+    let mut map: serde_json::Value = match serde_json::from_str(map_json) {
+        Ok(map @ serde_json::Value::Object(_)) => map,
+        _ => {
+            warn!("{}: dropping its source map, which isn't JSON", origin_url);
+            return None;
+        }
+    };
+    if map.get("sources").is_none() && map.get("sourceRoot").is_none() {
+        return Some(map_json.to_string());
+    }
+    // a data: url, say, has nothing to resolve against
+    match url::Url::parse(origin_url) {
+        Ok(ref url) if !url.cannot_be_a_base() => {
+            let base_url = source_base_url(&map, url);
+            absolutize_sources(&mut map, &base_url);
+        }
+        _ => {}
+    }
+    let load = match load {
+        Some(load) => load,
+        None => return Some(map.to_string()),
+    };
+    let sources: Vec<Option<String>> = match map.get("sources").and_then(|s| s.as_array()) {
+        Some(sources) => sources
+            .iter()
+            .map(|s| s.as_str().map(|s| s.to_string()))
+            .collect(),
+        None => return Some(map.to_string()),
+    };
+    let existing: Vec<serde_json::Value> = map
+        .get("sourcesContent")
+        .and_then(|c| c.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let contents = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| match (existing.get(i), source) {
+            (Some(content @ serde_json::Value::String(_)), _) => content.clone(),
+            (_, None) => serde_json::Value::Null,
+            (_, Some(source)) => match load(source) {
+                Ok(content) => serde_json::Value::String(content),
+                Err(reason) => {
+                    warn!(
+                        "source map of {}: not inlining {}: {}",
+                        origin_url, source, reason
+                    );
+                    serde_json::Value::Null
+                }
+            },
+        })
+        .collect();
+    map["sourcesContent"] = serde_json::Value::Array(contents);
+    Some(map.to_string())
+}
+
+/**
+ * The source map for the file at `source_path`, when none was named: the one its trailing
+ * `//# sourceMappingURL=` comment points to, a relative file or an inline
+ * `data:application/json;base64,` map, or else `<file>.map` next to it. Sources are inlined
+ * as with `inline_source_map_sources`. A referenced map that can't be read is logged and
+ * skipped, like a source that can't be.
+ */
+pub fn discover_source_map(
+    source: &str,
+    source_path: &Path,
+    sandbox: Option<&Path>,
+) -> Option<String> {
+    let (map_json, map_path) = match source_mapping_url(source) {
+        Some(reference) => match load_referenced_map(reference, source_path, sandbox) {
+            Ok(found) => found,
+            Err(reason) => {
+                warn!(
+                    "{}: ignoring source map {}: {}",
+                    source_path.display(),
+                    reference,
+                    reason
+                );
+                return None;
+            }
+        },
+        None => {
+            let mut adjacent = source_path.as_os_str().to_owned();
+            adjacent.push(".map");
+            let adjacent = std::path::PathBuf::from(adjacent);
+            (std::fs::read_to_string(&adjacent).ok()?, adjacent)
+        }
+    };
+    Some(inline_source_map_sources(&map_json, &map_path, sandbox))
+}
+
+/**
+ * What the last `//# sourceMappingURL=` comment points to, if the source ends with one
+ * (trailing blank lines aside). The older `//@` form counts too.
+ */
+fn source_mapping_url(source: &str) -> Option<&str> {
+    let last = source
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())?
+        .trim();
+    let reference = last
+        .trim_start_matches("//#")
+        .trim_start_matches("//@")
+        .trim_start();
+    if reference.len() == last.len() || !reference.starts_with("sourceMappingURL=") {
+        return None;
+    }
+    Some(reference["sourceMappingURL=".len()..].trim()).filter(|r| !r.is_empty())
+}
+
+fn load_referenced_map(
+    reference: &str,
+    source_path: &Path,
+    sandbox: Option<&Path>,
+) -> Result<(String, std::path::PathBuf), String> {
+    if reference.starts_with("data:") {
+        let mut parts = reference[5..].splitn(2, ',');
+        let meta = parts.next().unwrap_or("");
+        let data = parts.next().unwrap_or("");
+        if !meta.starts_with("application/json") || !meta.ends_with(";base64") {
+            return Err(format!("unsupported data url type {}", meta));
+        }
+        let bytes = base64::decode(data).map_err(|e| e.to_string())?;
+        let json = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+        // its sources are relative to the file it's in
+        return Ok((json, source_path.to_path_buf()));
+    }
+    let source_url = url::Url::from_file_path(source_path)
+        .map_err(|_| format!("{} is not a local file", source_path.display()))?;
+    let map_path = source_url
+        .join(reference)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| format!("{} is not a local file", reference))?;
+    let map_json = read_source(&source_url, reference, sandbox)?;
+    // whatever the comment names, only a source map is worth keeping
+    match serde_json::from_str(&map_json) {
+        Ok(serde_json::Value::Object(_)) => Ok((map_json, map_path)),
+        _ => Err(format!("{} is not a JSON source map", map_path.display())),
+    }
+}
+
+fn read_source(
+    base_url: &url::Url,
+    source: &str,
+    sandbox: Option<&Path>,
+) -> Result<String, String> {
+    let url = base_url.join(source).map_err(|e| e.to_string())?;
+    if url.scheme() != "file" {
+        return Err(format!("{} is not a local file", url));
+    }
+    let path = url
+        .to_file_path()
+        .map_err(|_| format!("{} is not a local file", url))?;
+    let path = path.canonicalize().map_err(|e| e.to_string())?;
+    if let Some(sandbox) = sandbox {
+        let inside = sandbox
+            .canonicalize()
+            .map(|sandbox| path.starts_with(sandbox))
+            .unwrap_or(false);
+        if !inside {
+            return Err(format!(
+                "{} is outside {}",
+                path.display(),
+                sandbox.display()
+            ));
+        }
+    }
+    std::fs::read_to_string(&path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inlines_relative_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("dist")).unwrap();
+        std::fs::write(root.join("src/app.ts"), "let a: number = 1;").unwrap();
+        let map_path = root.join("dist/app.js.map");
+        let map = r#"{"version":3,"sources":["../src/app.ts","../src/missing.ts"],"names":[],"mappings":"AAAA"}"#;
+
+        let inlined: serde_json::Value =
+            serde_json::from_str(&inline_source_map_sources(map, &map_path, Some(root))).unwrap();
+        assert_eq!(
+            serde_json::json!(["let a: number = 1;", null]),
+            inlined["sourcesContent"]
+        );
+        assert_eq!("AAAA", inlined["mappings"]);
+    }
+
+    #[test]
+    fn test_discovers_adjacent_inline_and_referenced_maps() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("dist/maps")).unwrap();
+        std::fs::write(root.join("app.ts"), "let a = 1;").unwrap();
+        let map = r#"{"version":3,"sources":["../app.ts"],"names":[],"mappings":"AAAA"}"#;
+        let sources = |map: Option<String>| {
+            let map: serde_json::Value = serde_json::from_str(&map.unwrap()).unwrap();
+            map["sourcesContent"].clone()
+        };
+
+        let adjacent = root.join("dist/adjacent.js");
+        std::fs::write(root.join("dist/adjacent.js.map"), map).unwrap();
+        assert_eq!(
+            serde_json::json!(["let a = 1;"]),
+            sources(discover_source_map("var a = 1;", &adjacent, Some(root)))
+        );
+
+        let inline = format!(
+            "var a = 1;\n//# sourceMappingURL=data:application/json;charset=utf-8;base64,{}\n",
+            base64::encode(map)
+        );
+        assert_eq!(
+            serde_json::json!(["let a = 1;"]),
+            sources(discover_source_map(
+                &inline,
+                &root.join("dist/inline.js"),
+                Some(root)
+            ))
+        );
+
+        // relative to the file, and preferred to an adjacent map
+        std::fs::write(
+            root.join("dist/maps/referenced.map"),
+            r#"{"version":3,"sources":["../../app.ts"],"names":[],"mappings":"AAAA"}"#,
+        )
+        .unwrap();
+        let referenced = "var a = 1;\n//# sourceMappingURL=maps/referenced.map";
+        assert_eq!(
+            serde_json::json!(["let a = 1;"]),
+            sources(discover_source_map(referenced, &adjacent, Some(root)))
+        );
+
+        let missing = "var a = 1;\n//# sourceMappingURL=maps/missing.map";
+        assert_eq!(None, discover_source_map(missing, &adjacent, Some(root)));
+        let not_a_map = "var a = 1;\n//# sourceMappingURL=../app.ts";
+        assert_eq!(None, discover_source_map(not_a_map, &adjacent, Some(root)));
+        assert_eq!(
+            None,
+            discover_source_map("var a = 1;", &root.join("dist/none.js"), Some(root))
+        );
+    }
+
+    #[test]
+    fn test_respects_sandbox_and_source_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("app/lib")).unwrap();
+        std::fs::write(root.join("app/lib/a.ts"), "a").unwrap();
+        std::fs::write(root.join("secret.ts"), "secret").unwrap();
+        let map_path = root.join("app/a.js.map");
+        let map = r#"{"version":3,"sourceRoot":"lib","sources":["a.ts","../../secret.ts"],"sourcesContent":[null],"mappings":""}"#;
+
+        let inlined: serde_json::Value = serde_json::from_str(&inline_source_map_sources(
+            map,
+            &map_path,
+            Some(&root.join("app")),
+        ))
+        .unwrap();
+        assert_eq!(serde_json::json!(["a", null]), inlined["sourcesContent"]);
+    }
+}
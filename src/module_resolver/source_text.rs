@@ -0,0 +1,146 @@
+use crate::errors::*;
+
+use std::io;
+use std::path::Path;
+
+use encoding_rs::{DecoderResult, Encoding};
+
+/**
+ * The text of the source file at `path`. A UTF-8 byte order mark is stripped and UTF-16
+ * files, little or big endian, are transcoded by theirs; anything else must be UTF-8. Failing
+ * to read keeps the IO error's kind, so a missing file is still `NotFound` and a directory
+ * `IsADirectory`, with the path in the message.
+ */
+pub fn read_source_text(path: &Path) -> FlyResult<String> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        FlyError::from(io::Error::new(
+            e.kind(),
+            format!("Could not read {}: {}", path.display(), e),
+        ))
+    })?;
+    decode_source_text(path, &bytes)
+}
+
+/**
+ * `bytes` of the file at `path` as text, by their byte order mark as `read_source_text`
+ * decodes them. Malformed bytes fail with the offset of the first bad sequence rather than
+ * turning into replacement characters.
+ */
+pub fn decode_source_text(path: &Path, bytes: &[u8]) -> FlyResult<String> {
+    let (encoding, bom_len) = Encoding::for_bom(bytes).unwrap_or((encoding_rs::UTF_8, 0));
+    let body = &bytes[bom_len..];
+    if encoding == encoding_rs::UTF_8 {
+        return match std::str::from_utf8(body) {
+            Ok(text) => Ok(text.to_string()),
+            Err(e) => Err(malformed(path, encoding, bom_len + e.valid_up_to())),
+        };
+    }
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(
+        decoder
+            .max_utf8_buffer_length_without_replacement(body.len())
+            .unwrap_or(0),
+    );
+    match decoder.decode_to_string_without_replacement(body, &mut text, true) {
+        (DecoderResult::InputEmpty, _) => Ok(text),
+        (DecoderResult::Malformed(bad, after), read) => Err(malformed(
+            path,
+            encoding,
+            bom_len + read - after as usize - bad as usize,
+        )),
+        (DecoderResult::OutputFull, read) => Err(malformed(path, encoding, bom_len + read)),
+    }
+}
+
+fn malformed(path: &Path, encoding: &'static Encoding, offset: usize) -> FlyError {
+    new(
+        ErrorKind::InvalidData,
+        format!(
+            "{} is not {} text: invalid byte sequence at byte {}",
+            path.display(),
+            encoding.name(),
+            offset
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        let mut bytes = if big_endian {
+            vec![0xFE, 0xFF]
+        } else {
+            vec![0xFF, 0xFE]
+        };
+        for unit in text.encode_utf16() {
+            let pair = if big_endian {
+                unit.to_be_bytes()
+            } else {
+                unit.to_le_bytes()
+            };
+            bytes.extend_from_slice(&pair);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_decodes_by_byte_order_mark() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "export const greeting = 'héllo, 世界';\n";
+        let fixtures: Vec<(&str, Vec<u8>)> = vec![
+            ("plain.ts", source.as_bytes().to_vec()),
+            ("bom.ts", [&b"\xEF\xBB\xBF"[..], source.as_bytes()].concat()),
+            ("utf16le.ts", utf16(source, false)),
+            ("utf16be.ts", utf16(source, true)),
+        ];
+        for (name, bytes) in fixtures {
+            let path = dir.path().join(name);
+            std::fs::write(&path, bytes).unwrap();
+            assert_eq!(source, read_source_text(&path).unwrap(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_malformed_text_names_the_offending_byte() {
+        let path = Path::new("/app/latin1.ts");
+        let err = decode_source_text(path, b"const caf\xE9 = 1;")
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert_eq!(
+            "/app/latin1.ts is not UTF-8 text: invalid byte sequence at byte 9",
+            err.to_string()
+        );
+
+        // counted from the start of the file, byte order mark included
+        let err = decode_source_text(path, b"\xEF\xBB\xBFok\xFF")
+            .err()
+            .unwrap();
+        assert!(err.to_string().ends_with("at byte 5"), "{}", err);
+
+        // a lone surrogate
+        let mut bytes = utf16("ab", false);
+        bytes.extend_from_slice(&[0x00, 0xD8, b'c', 0x00]);
+        let err = decode_source_text(path, &bytes).err().unwrap();
+        assert_eq!(
+            "/app/latin1.ts is not UTF-16LE text: invalid byte sequence at byte 6",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_io_errors_keep_their_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.ts");
+        let err = read_source_text(&missing).err().unwrap();
+        assert_eq!(ErrorKind::NotFound, err.kind());
+        assert!(err
+            .to_string()
+            .starts_with(&format!("Could not read {}: ", missing.display())));
+
+        let err = read_source_text(dir.path()).err().unwrap();
+        assert_eq!(ErrorKind::IsADirectory, err.kind());
+    }
+}
@@ -0,0 +1,354 @@
+use crate::errors::*;
+
+use std::path::Path;
+
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::ToSql;
+use rusqlite::{OpenFlags, NO_PARAMS};
+use sha2::{Digest, Sha256};
+
+use super::{
+    parse_url, LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+/**
+ * Columns a bundle's `files` table has to have.
+ */
+const FILE_COLUMNS: &[&str] = &["path", "bytes", "content_type", "hash"];
+
+/**
+ * Suffixes tried after the exact path, in order, like the disk resolver's probing plus
+ * directory index files.
+ */
+const PROBE_SUFFIXES: &[&str] = &["", ".ts", ".js", "/index.ts", "/index.js"];
+
+type Pool = r2d2::Pool<SqliteConnectionManager>;
+
+fn sqlite_error(db_path: &str, e: impl std::fmt::Display) -> FlyError {
+    FlyError::from(format!("release bundle {}: {}", db_path, e))
+}
+
+/**
+ * Serves an app from a release bundle: a SQLite database with a `files(path, bytes,
+ * content_type, hash)` table, where `hash` is the hex sha256 of `bytes` and a file's source
+ * map, if any, is stored at its path plus `.map`. Paths are relative, without a leading
+ * slash; `prefix` selects the part of the bundle served at the root of `protocol`.
+ *
+ * The database is opened read-only once, with connections (and their prepared statements)
+ * reused across lookups, and every extension and index candidate is checked in one query.
+ */
+pub struct SqliteBundleResolver {
+    protocol: String,
+    prefix: String,
+    db_path: String,
+    pool: Pool,
+}
+
+impl SqliteBundleResolver {
+    /**
+     * Opens the bundle at `db_path`, failing if it doesn't exist or has no usable `files`
+     * table.
+     */
+    pub fn open(db_path: &Path, protocol: &str) -> FlyResult<Self> {
+        let db_path_str = db_path.display().to_string();
+        if !db_path.is_file() {
+            return Err(sqlite_error(&db_path_str, "no such file"));
+        }
+        let manager = SqliteConnectionManager::file(db_path)
+            .with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX);
+        let pool = r2d2::Pool::builder()
+            .max_size(4)
+            .build(manager)
+            .map_err(|e| sqlite_error(&db_path_str, e))?;
+        let columns: Vec<String> = {
+            let conn = pool.get().map_err(|e| sqlite_error(&db_path_str, e))?;
+            let mut stmt = conn
+                .prepare("PRAGMA table_info(files)")
+                .map_err(|e| sqlite_error(&db_path_str, e))?;
+            let rows = stmt
+                .query_map(NO_PARAMS, |row| row.get_checked::<_, String>(1))
+                .map_err(|e| sqlite_error(&db_path_str, e))?;
+            rows.filter_map(|r| r.ok().and_then(|r| r.ok())).collect()
+        };
+        let missing: Vec<&str> = FILE_COLUMNS
+            .iter()
+            .cloned()
+            .filter(|c| !columns.iter().any(|column| column == c))
+            .collect();
+        if columns.is_empty() {
+            return Err(sqlite_error(
+                &db_path_str,
+                "not a release bundle, it has no files table",
+            ));
+        }
+        if !missing.is_empty() {
+            return Err(sqlite_error(
+                &db_path_str,
+                format!("files table is missing columns {}", missing.join(", ")),
+            ));
+        }
+        Ok(Self {
+            protocol: protocol.to_string(),
+            prefix: String::new(),
+            db_path: db_path_str,
+            pool,
+        })
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_start_matches('/').to_string();
+        self
+    }
+
+    /**
+     * The stored path `path` resolves to, trying each probe suffix in order.
+     */
+    fn probe(&self, path: &str) -> FlyResult<Option<String>> {
+        let base = format!("{}{}", self.prefix, path.trim_end_matches('/'));
+        let candidates: Vec<String> = PROBE_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{}{}", base, suffix))
+            .collect();
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| sqlite_error(&self.db_path, e))?;
+        let mut stmt = conn
+            .prepare_cached("SELECT path FROM files WHERE path IN (?1, ?2, ?3, ?4, ?5)")
+            .map_err(|e| sqlite_error(&self.db_path, e))?;
+        let params: Vec<&ToSql> = candidates.iter().map(|c| c as &ToSql).collect();
+        let found: Vec<String> = stmt
+            .query_map(params, |row| row.get_checked::<_, String>(0))
+            .map_err(|e| sqlite_error(&self.db_path, e))?
+            .filter_map(|r| r.ok().and_then(|r| r.ok()))
+            .collect();
+        Ok(candidates.into_iter().find(|c| found.contains(c)))
+    }
+}
+
+impl ModuleResolver for SqliteBundleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => format!("{}:///", self.protocol),
+        };
+        let url = parse_url(module_specifier, &referer_origin_url)?;
+        let path = url.path().trim_start_matches('/');
+        let stored_path = match self.probe(path)? {
+            Some(stored_path) => stored_path,
+            None => {
+                return Err(FlyError::from(format!(
+                    "Could not resolve {} from {}: not in release bundle {}",
+                    module_specifier, referer_origin_url, self.db_path
+                )))
+            }
+        };
+        Ok(ModuleSourceData {
+            origin_url: format!("{}:///{}", self.protocol, &stored_path[self.prefix.len()..]),
+            source_loader: Box::new(SqliteBundleLoader {
+                db_path: self.db_path.clone(),
+                pool: self.pool.clone(),
+                path: stored_path,
+            }),
+        })
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("sqlite_bundle", &self.protocol)
+            .named(&self.name())
+            .with("database", &self.db_path)
+            .with("prefix", &self.prefix)
+    }
+}
+
+pub struct SqliteBundleLoader {
+    db_path: String,
+    pool: Pool,
+    path: String,
+}
+
+impl SqliteBundleLoader {
+    fn read(&self, path: &str) -> FlyResult<Option<Vec<u8>>> {
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| sqlite_error(&self.db_path, e))?;
+        let mut stmt = conn
+            .prepare_cached("SELECT bytes, hash FROM files WHERE path = ?1")
+            .map_err(|e| sqlite_error(&self.db_path, e))?;
+        let mut rows = stmt
+            .query(&[&path as &ToSql])
+            .map_err(|e| sqlite_error(&self.db_path, e))?;
+        let row = match rows.next() {
+            Some(row) => row.map_err(|e| sqlite_error(&self.db_path, e))?,
+            None => return Ok(None),
+        };
+        let bytes: Vec<u8> = row
+            .get_checked(0)
+            .map_err(|e| sqlite_error(&self.db_path, e))?;
+        let hash: String = row
+            .get_checked(1)
+            .map_err(|e| sqlite_error(&self.db_path, e))?;
+        let actual: String = Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        if !actual.eq_ignore_ascii_case(&hash) {
+            return Err(sqlite_error(
+                &self.db_path,
+                format!(
+                    "{} is corrupt: stored hash {} but its bytes hash to {}",
+                    path, hash, actual
+                ),
+            ));
+        }
+        Ok(Some(bytes))
+    }
+}
+
+impl SourceLoader for SqliteBundleLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let bytes = self.read(&self.path)?.ok_or_else(|| {
+            sqlite_error(
+                &self.db_path,
+                format!("{} was removed from the bundle", self.path),
+            )
+        })?;
+        let source = String::from_utf8(bytes)
+            .map_err(|_| sqlite_error(&self.db_path, format!("{} is not utf-8 text", self.path)))?;
+        let source_map = match self.read(&format!("{}.map", self.path))? {
+            Some(map) => Some(String::from_utf8(map).map_err(|_| {
+                sqlite_error(
+                    &self.db_path,
+                    format!("source map of {} is not utf-8", self.path),
+                )
+            })?),
+            None => None,
+        };
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: source_map.map(Into::into),
+            source: source.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn sha256(bytes: &[u8]) -> String {
+        Sha256::digest(bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn bundle(dir: &tempfile::TempDir, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let path = dir.path().join("release.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute(
+            "CREATE TABLE files (path TEXT PRIMARY KEY, bytes BLOB, content_type TEXT, hash TEXT)",
+            NO_PARAMS,
+        )
+        .unwrap();
+        for (file, contents) in files {
+            conn.execute(
+                "INSERT INTO files VALUES (?1, ?2, 'application/javascript', ?3)",
+                &[
+                    file as &ToSql,
+                    &contents.as_bytes().to_vec(),
+                    &sha256(contents.as_bytes()),
+                ],
+            )
+            .unwrap();
+        }
+        path
+    }
+
+    fn load(
+        resolver: &SqliteBundleResolver,
+        specifier: &str,
+    ) -> FlyResult<(String, LoadedSourceCode)> {
+        let data = resolver.resolve_module(specifier, None)?;
+        Ok((data.origin_url, data.source_loader.load_source()?))
+    }
+
+    #[test]
+    fn test_probes_extensions_and_index_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = bundle(
+            &dir,
+            &[
+                ("app/index.ts", "export default 1;"),
+                ("app/index.ts.map", "{\"version\":3}"),
+                ("app/lib/index.js", "export default 2;"),
+                ("other/index.ts", "export default 3;"),
+            ],
+        );
+        let resolver = SqliteBundleResolver::open(&db, "bundle")
+            .unwrap()
+            .with_prefix("app/");
+        let (origin_url, source) = load(&resolver, "bundle:///index").unwrap();
+        assert_eq!("bundle:///index.ts", origin_url);
+        assert_eq!("export default 1;", &*source.source);
+        assert_eq!(Some("{\"version\":3}".into()), source.source_map);
+        let (origin_url, _) = load(&resolver, "bundle:///lib").unwrap();
+        assert_eq!("bundle:///lib/index.js", origin_url);
+        assert!(load(&resolver, "bundle:///other/index.ts").is_err());
+    }
+
+    #[test]
+    fn test_verifies_stored_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = bundle(&dir, &[("index.js", "export default 1;")]);
+        Connection::open(&db)
+            .unwrap()
+            .execute(
+                "UPDATE files SET bytes = X'00' WHERE path = 'index.js'",
+                NO_PARAMS,
+            )
+            .unwrap();
+        let resolver = SqliteBundleResolver::open(&db, "bundle").unwrap();
+        let err = load(&resolver, "bundle:///index.js").err().unwrap();
+        assert!(format!("{}", err).contains("index.js is corrupt"));
+    }
+
+    #[test]
+    fn test_rejects_missing_and_malformed_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = SqliteBundleResolver::open(&dir.path().join("missing.db"), "bundle")
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("no such file"));
+
+        let path = dir.path().join("partial.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute("CREATE TABLE files (path TEXT, bytes BLOB)", NO_PARAMS)
+            .unwrap();
+        let err = SqliteBundleResolver::open(&path, "bundle").err().unwrap();
+        assert!(format!("{}", err).contains("missing columns content_type, hash"));
+
+        let garbage = dir.path().join("garbage.db");
+        std::fs::write(
+            &garbage,
+            "not a database, just some text that is long enough",
+        )
+        .unwrap();
+        assert!(SqliteBundleResolver::open(&garbage, "bundle").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+
+use super::{
+    parse_url, LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+/**
+ * Modules fixed at startup, e.g. compiled into the executable with `include_str!`, served
+ * under their own protocol like `static://app/main.js`. Unlike virtual modules they have
+ * urls, so they can import one another relatively.
+ */
+pub struct StaticModuleResolver {
+    protocol: String,
+    modules: HashMap<String, LoadedSourceCode>,
+}
+
+impl StaticModuleResolver {
+    pub fn new(protocol: &str) -> Self {
+        Self {
+            protocol: protocol.to_string(),
+            modules: HashMap::new(),
+        }
+    }
+
+    /**
+     * Adds a script module at `url`, an absolute url with this resolver's protocol.
+     */
+    pub fn insert(self, url: &str, source: &str) -> Self {
+        self.insert_source(
+            url,
+            LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: None,
+                source: source.into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            },
+        )
+    }
+
+    /**
+     * Adds a module that's already loaded, say with its source map or as wasm.
+     */
+    pub fn insert_source(mut self, url: &str, source: LoadedSourceCode) -> Self {
+        // stored the way lookups spell them, e.g. with `./` segments resolved
+        let url = url::Url::parse(url)
+            .map(|u| u.into_string())
+            .unwrap_or_else(|_| url.to_string());
+        self.modules.insert(url, source);
+        self
+    }
+}
+
+impl ModuleResolver for StaticModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => format!("{}://", self.protocol),
+        };
+        let url = parse_url(module_specifier, &referer_origin_url)?;
+        match self.modules.get(url.as_str()) {
+            Some(source) => Ok(ModuleSourceData {
+                origin_url: url.into_string(),
+                source_loader: Box::new(StaticSourceLoader {
+                    source: source.clone(),
+                }),
+            }),
+            None => Err(new(
+                ErrorKind::NotFound,
+                format!(
+                    "Could not resolve {} from {}: no static module {}",
+                    module_specifier, referer_origin_url, url
+                ),
+            )),
+        }
+    }
+    fn get_protocol(&self) -> String {
+        self.protocol.clone()
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("static", &self.protocol)
+            .named(&self.name())
+            .with("modules", &self.modules.len().to_string())
+    }
+}
+
+pub struct StaticSourceLoader {
+    pub source: LoadedSourceCode,
+}
+
+impl SourceLoader for StaticSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        Ok(self.source.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{ModuleResolverManager, StandardModuleResolverManager};
+
+    #[test]
+    fn test_static_modules_import_each_other() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                StaticModuleResolver::new("static")
+                    .insert("static://app/main.js", "import { a } from './lib/a.js';")
+                    .insert("static://app/lib/./a.js", "export const a = 1;"),
+            ) as Box<ModuleResolver>],
+            Some("static://app/".to_string()),
+        );
+        let main = manager
+            .resolve_blocking("static://app/main.js".to_string(), None)
+            .unwrap();
+        assert_eq!("static://app/main.js", main.origin_url);
+        let a = manager
+            .resolve_blocking(
+                "./lib/a.js".to_string(),
+                Some(RefererInfo {
+                    origin_url: main.origin_url,
+                    is_wasm: Some(false),
+                    source_code: None,
+                    identifier_hash: None,
+                    line: None,
+                    column: None,
+                    app_id: None,
+                    deadline: None,
+                }),
+            )
+            .unwrap();
+        assert_eq!("static://app/lib/a.js", a.origin_url);
+        assert_eq!("export const a = 1;", &*a.loaded_source.source);
+
+        let err = manager
+            .resolve_blocking("static://app/missing.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("no static module static://app/missing.js"));
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::Path;
+
+/**
+ * Directories with more entries than this aren't scanned for suggestions.
+ */
+pub const MAX_SCANNED_ENTRIES: usize = 1000;
+
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/**
+ * Levenshtein distance, by chars.
+ */
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+fn stem(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(0) | None => name,
+        Some(i) => &name[..i],
+    }
+}
+
+/**
+ * How far `candidate` is from the `wanted` file name, or `None` if it's too far off to
+ * suggest. A file with the same stem is always a candidate, whatever its extension.
+ */
+fn distance(wanted: &str, candidate: &str) -> Option<usize> {
+    if stem(wanted) == stem(candidate) {
+        return Some(1);
+    }
+    let distance =
+        edit_distance(wanted, candidate).min(edit_distance(stem(wanted), stem(candidate)));
+    if distance <= (wanted.chars().count() / 4).max(1).min(3) {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/**
+ * File names near the one `path` asked for, from the directory it was looked for in, closest
+ * first. Only used once resolution has failed. Nothing is suggested for directories outside
+ * `root` or too large to scan.
+ */
+pub fn nearby_files(path: &Path, root: &Path) -> Vec<String> {
+    let (dir, wanted) = match (path.parent(), path.file_name().and_then(|n| n.to_str())) {
+        (Some(dir), Some(wanted)) => (dir, wanted),
+        _ => return vec![],
+    };
+    match (dir.canonicalize(), root.canonicalize()) {
+        (Ok(dir), Ok(root)) if dir.starts_with(&root) => {}
+        _ => return vec![],
+    }
+    let entries: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.take(MAX_SCANNED_ENTRIES + 1).collect(),
+        Err(_) => return vec![],
+    };
+    if entries.len() > MAX_SCANNED_ENTRIES {
+        debug!(
+            "not suggesting files from {}, it has over {} entries",
+            dir.display(),
+            MAX_SCANNED_ENTRIES
+        );
+        return vec![];
+    }
+    let mut candidates: Vec<(usize, String)> = entries
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name != wanted)
+        .filter_map(|name| distance(wanted, &name).map(|d| (d, name)))
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/**
+ * `specifier` with its last segment replaced by `file_name`, so suggestions read the way
+ * the import was written.
+ */
+pub fn respecify(specifier: &str, file_name: &str) -> String {
+    match specifier.rfind('/') {
+        Some(i) => format!("{}{}", &specifier[..=i], file_name),
+        None => file_name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggests_typos_and_wrong_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in &["utils.ts", "main.js", "unrelated.ts", "README.md"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        std::fs::create_dir(dir.path().join("utl")).unwrap();
+        assert_eq!(
+            vec!["utils.ts"],
+            nearby_files(&dir.path().join("utls.ts"), dir.path())
+        );
+        assert_eq!(
+            vec!["main.js"],
+            nearby_files(&dir.path().join("main.ts"), dir.path())
+        );
+        assert!(nearby_files(&dir.path().join("server.ts"), dir.path()).is_empty());
+        assert_eq!("./lib/utils.ts", respecify("./lib/utls.ts", "utils.ts"));
+    }
+
+    #[test]
+    fn test_stays_inside_root_and_skips_huge_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let app = root.path().join("app");
+        std::fs::create_dir(&app).unwrap();
+        std::fs::write(root.path().join("secret.ts"), "").unwrap();
+        assert!(nearby_files(&app.join("../secrt.ts"), &app).is_empty());
+        assert_eq!(
+            vec!["secret.ts"],
+            nearby_files(&app.join("../secrt.ts"), root.path())
+        );
+
+        for i in 0..=MAX_SCANNED_ENTRIES {
+            std::fs::write(app.join(format!("{}.ts", i)), "").unwrap();
+        }
+        assert!(nearby_files(&app.join("1.js"), &app).is_empty());
+    }
+}
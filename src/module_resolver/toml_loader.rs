@@ -0,0 +1,140 @@
+use crate::errors::*;
+
+use super::data_module::{data_to_module, parse_error, DataValue};
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+/**
+ * Turns a TOML file into a module: the parsed document is the default export and every
+ * top-level table whose key is a valid identifier is also a named export. Datetimes become
+ * their RFC 3339 strings.
+ */
+pub struct TomlLoader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+}
+
+impl TomlLoader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+        }
+    }
+}
+
+impl SourceLoader for TomlLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let raw = self.inner.load_source()?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::Json,
+            source_map: None,
+            source: toml_to_module(&self.origin_url, &raw.source)?.into(),
+            response: raw.response,
+            provenance: raw.provenance,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+pub fn toml_to_module(origin_url: &str, toml_source: &str) -> FlyResult<String> {
+    let document: toml::Value = match toml::from_str(toml_source) {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(match e.line_col() {
+                Some((line, col)) => {
+                    parse_error(origin_url, "TOML", line + 1, col + 1, &format!("{}", e))
+                }
+                None => FlyError::from(format!("{}: invalid TOML: {}", origin_url, e)),
+            })
+        }
+    };
+    data_to_module(&to_data(document))
+}
+
+fn to_data(value: toml::Value) -> DataValue {
+    match value {
+        toml::Value::String(s) => DataValue::String(s),
+        toml::Value::Integer(i) => DataValue::Integer(i),
+        toml::Value::Float(f) => DataValue::Float(f),
+        toml::Value::Boolean(b) => DataValue::Bool(b),
+        toml::Value::Datetime(d) => DataValue::String(d.to_string()),
+        toml::Value::Array(items) => DataValue::Array(items.into_iter().map(to_data).collect()),
+        toml::Value::Table(table) => DataValue::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, to_data(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_and_named_exports() {
+        let module = toml_to_module(
+            "file:///app/config.toml",
+            "title = \"app\"\n[server]\nport = 8080\n[\"not-an-ident\"]\na = 1\n[default]\nb = 2\n",
+        )
+        .unwrap();
+        assert!(module.contains("export default __data;"));
+        assert!(module.contains("\"title\":\"app\""));
+        assert!(module.contains("export const server = __data.server;"));
+        assert!(!module.contains("export const not-an-ident"));
+        assert!(!module.contains("export const default"));
+        assert!(!module.contains("export const title"));
+    }
+
+    #[test]
+    fn test_datetimes_become_strings() {
+        let module = toml_to_module(
+            "file:///app/config.toml",
+            "released = 1979-05-27T07:32:00Z\nday = 1979-05-27\n",
+        )
+        .unwrap();
+        assert!(module.contains("\"released\":\"1979-05-27T07:32:00Z\""));
+        assert!(module.contains("\"day\":\"1979-05-27\""));
+    }
+
+    #[test]
+    fn test_unsafe_integers_are_rejected() {
+        assert!(toml_to_module("file:///app/a.toml", "n = 9007199254740991").is_ok());
+        let err = toml_to_module("file:///app/a.toml", "[limits]\nn = 9007199254740992")
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("limits.n"));
+    }
+
+    #[test]
+    fn test_deep_nesting_is_not_converted() {
+        let deep = format!("a = {}1{}", "[".repeat(128), "]".repeat(128));
+        let err = toml_to_module("file:///app/a.toml", &deep).err().unwrap();
+        assert_eq!(
+            format!("a{} nests deeper than 128 levels", "[0]".repeat(127)),
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_special_floats() {
+        let module = toml_to_module("file:///app/a.toml", "a = nan\nb = -inf\nc = 1.5").unwrap();
+        assert!(module.contains("\"a\":NaN"));
+        assert!(module.contains("\"b\":-Infinity"));
+        assert!(module.contains("\"c\":1.5"));
+    }
+
+    #[test]
+    fn test_parse_error_has_position() {
+        let err = toml_to_module("file:///app/a.toml", "a = 1\nb = \n")
+            .err()
+            .unwrap();
+        assert!(
+            format!("{}", err).starts_with("file:///app/a.toml:2:"),
+            format!("{}", err)
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/** How many of the latest traces a tracing manager keeps. */
+pub const TRACE_CAPACITY: usize = 64;
+
+/** One thing a manager did while resolving a specifier. */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum TraceStep {
+    /** Answered from the resolution cache. */
+    CacheHit {
+        origin_url: String,
+    },
+    /** Failed within the resolution cache's negative TTL, so failed again straight away. */
+    KnownMissing,
+    /** An import map, wasm import map or base url rewrote the specifier. */
+    Rewritten {
+        specifier: String,
+    },
+    /** The specifier is a virtual module. */
+    Virtual,
+    /** The same import was resolving already, so this waited for it. */
+    Joined,
+    /** The specifier joined to its referer's url. */
+    Parsed {
+        url: String,
+    },
+    /** The protocol chosen, and how many resolvers it has, fallbacks included. */
+    Protocol {
+        protocol: String,
+        resolvers: usize,
+    },
+    ResolverTried {
+        resolver: String,
+        protocol: String,
+    },
+    /** A file or url a resolver looked for. */
+    Probed {
+        candidate: String,
+        found: bool,
+    },
+    ResolverFailed {
+        resolver: String,
+        error: String,
+    },
+    /** A fetch was redirected, with the status that redirected it. */
+    Redirected {
+        from: String,
+        to: String,
+        status: u16,
+    },
+    /** Where the specifier resolved to, and the `name` of the resolver that resolved it. */
+    Resolved {
+        origin_url: String,
+        resolver: Option<String>,
+    },
+    /** Why the resolution failed, once every step was taken. */
+    Failed {
+        error: String,
+    },
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceStep::CacheHit { origin_url } => write!(f, "cache hit: {}", origin_url),
+            TraceStep::KnownMissing => write!(f, "known missing: failed before"),
+            TraceStep::Rewritten { specifier } => write!(f, "rewritten to {}", specifier),
+            TraceStep::Virtual => write!(f, "virtual module"),
+            TraceStep::Joined => write!(f, "joined the same resolution running"),
+            TraceStep::Parsed { url } => write!(f, "parsed as {}", url),
+            TraceStep::Protocol {
+                protocol,
+                resolvers,
+            } => write!(f, "protocol {} with {} resolvers", protocol, resolvers),
+            TraceStep::ResolverTried { resolver, protocol } => {
+                write!(f, "trying {} for {}", resolver, protocol)
+            }
+            TraceStep::Probed { candidate, found } => write!(
+                f,
+                "probed {}: {}",
+                candidate,
+                if *found { "found" } else { "missing" }
+            ),
+            TraceStep::ResolverFailed { resolver, error } => {
+                write!(f, "{} failed: {}", resolver, error)
+            }
+            TraceStep::Redirected { from, to, status } => {
+                write!(f, "redirected from {} to {} ({})", from, to, status)
+            }
+            TraceStep::Resolved {
+                origin_url,
+                resolver: Some(resolver),
+            } => write!(f, "resolved to {} by {}", origin_url, resolver),
+            TraceStep::Resolved { origin_url, .. } => write!(f, "resolved to {}", origin_url),
+            TraceStep::Failed { error } => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/** Every step of resolving one specifier, in order. */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResolutionTrace {
+    pub specifier: String,
+    pub referer: String,
+    pub steps: Vec<TraceStep>,
+}
+
+impl ResolutionTrace {
+    pub fn new(specifier: &str, referer: &str) -> Self {
+        Self {
+            specifier: specifier.to_string(),
+            referer: referer.to_string(),
+            steps: vec![],
+        }
+    }
+}
+
+impl fmt::Display for ResolutionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "resolving {} from {}", self.specifier, self.referer)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            write!(f, "\n  {}. {}", i + 1, step)?;
+        }
+        Ok(())
+    }
+}
+
+/** A trace being recorded, shared by the threads a resolution runs on. */
+pub type TraceHandle = Arc<Mutex<ResolutionTrace>>;
+
+/** The latest traces a manager recorded, oldest first. */
+#[derive(Clone, Default)]
+pub struct TraceLog {
+    traces: Arc<Mutex<VecDeque<ResolutionTrace>>>,
+}
+
+impl TraceLog {
+    pub fn push(&self, trace: ResolutionTrace) {
+        let mut traces = self.traces.lock().unwrap();
+        if traces.len() == TRACE_CAPACITY {
+            traces.pop_front();
+        }
+        traces.push_back(trace);
+    }
+
+    pub fn traces(&self) -> Vec<ResolutionTrace> {
+        self.traces.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceHandle>> = RefCell::new(None);
+}
+
+/** Puts back the trace recorded before `traced`, even if it panics. */
+struct Restore(Option<TraceHandle>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/**
+ * Runs `f` recording the steps it takes, e.g. a resolver's probes, into `trace`. Without a
+ * trace, `f` records into whatever trace it already would.
+ */
+pub fn traced<T>(trace: &Option<TraceHandle>, f: impl FnOnce() -> T) -> T {
+    let trace = match trace {
+        Some(trace) => trace.clone(),
+        None => return f(),
+    };
+    let _restore = Restore(CURRENT.with(|current| current.replace(Some(trace))));
+    f()
+}
+
+/** The trace recorded on this thread, to carry over to threads doing its work. */
+pub(super) fn current_trace() -> Option<TraceHandle> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/**
+ * Records a step into the trace recorded on this thread. `step` is only called when there's
+ * one, so resolvers can call this on every probe.
+ */
+pub fn record_step(step: impl FnOnce() -> TraceStep) {
+    CURRENT.with(|current| {
+        if let Some(ref trace) = *current.borrow() {
+            trace.lock().unwrap().steps.push(step());
+        }
+    })
+}
+
+/** Records a step into `trace`, if there's one. */
+pub fn push_step(trace: &Option<TraceHandle>, step: impl FnOnce() -> TraceStep) {
+    if let Some(trace) = trace {
+        trace.lock().unwrap().steps.push(step());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager,
+        StandardModuleResolverManager,
+    };
+
+    #[test]
+    fn test_traces_every_candidate_probed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("util.js"), "export default 1;").unwrap();
+        let root = url::Url::from_directory_path(dir.path())
+            .unwrap()
+            .into_string();
+        let manager = StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(root.clone())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(root.clone()),
+        )
+        .with_tracing(true);
+        manager
+            .resolve_blocking("./util".to_string(), None)
+            .unwrap();
+
+        let traces = manager.traces();
+        assert_eq!(1, traces.len());
+        let trace = &traces[0];
+        assert_eq!(
+            ("./util", &root[..]),
+            (&trace.specifier[..], &trace.referer[..])
+        );
+        let probed = |file: &str, found: bool| TraceStep::Probed {
+            candidate: dir.path().join(file).display().to_string(),
+            found,
+        };
+        let ts = trace
+            .steps
+            .iter()
+            .position(|s| *s == probed("util.ts", false));
+        let js = trace
+            .steps
+            .iter()
+            .position(|s| *s == probed("util.js", true));
+        assert!(ts.is_some() && ts < js, "{}", trace);
+        assert_eq!(
+            Some(&TraceStep::Resolved {
+                origin_url: format!("{}util.js", root),
+                resolver: Some("LocalDiskModuleResolver".to_string()),
+            }),
+            trace.steps.last()
+        );
+
+        // nothing is kept without tracing
+        let untraced = StandardModuleResolverManager::new(
+            vec![
+                Box::new(LocalDiskModuleResolver::new(Some(root.clone())).unwrap())
+                    as Box<ModuleResolver>,
+            ],
+            Some(root),
+        );
+        untraced
+            .resolve_blocking("./util".to_string(), None)
+            .unwrap();
+        assert!(untraced.traces().is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::errors::*;
+
+#[cfg(feature = "transpile")]
+use swc_core::common::comments::SingleThreadedComments;
+#[cfg(feature = "transpile")]
+use swc_core::common::source_map::DefaultSourceMapGenConfig;
+#[cfg(feature = "transpile")]
+use swc_core::common::sync::Lrc;
+#[cfg(feature = "transpile")]
+use swc_core::common::{FileName, Globals, Mark, SourceMap, Spanned, GLOBALS};
+#[cfg(feature = "transpile")]
+use swc_core::ecma::ast::EsVersion;
+#[cfg(feature = "transpile")]
+use swc_core::ecma::codegen::text_writer::JsWriter;
+#[cfg(feature = "transpile")]
+use swc_core::ecma::codegen::{Config, Emitter};
+#[cfg(feature = "transpile")]
+use swc_core::ecma::parser::{parse_file_as_program, Syntax, TsSyntax};
+#[cfg(feature = "transpile")]
+use swc_core::ecma::transforms::base::resolver;
+#[cfg(feature = "transpile")]
+use swc_core::ecma::transforms::react::react;
+#[cfg(feature = "transpile")]
+use swc_core::ecma::transforms::typescript::strip;
+
+#[cfg(feature = "transpile")]
+use super::data_module::parse_error;
+use super::{LoadedSourceCode, ModuleKind, RemoteMediaType, SourceLoader};
+
+/**
+ * Whether `origin_url` names TypeScript, by the extension of its path: `Some(true)` for
+ * `.tsx`, `Some(false)` for `.ts` and `None` for anything else, declaration files included.
+ */
+pub fn typescript_flavor(origin_url: &str) -> Option<bool> {
+    let path = match url::Url::parse(origin_url) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => origin_url.to_string(),
+    };
+    if path.ends_with(".d.ts") {
+        None
+    } else if path.ends_with(".tsx") {
+        Some(true)
+    } else if path.ends_with(".ts") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/**
+ * Like `typescript_flavor`, but a source served with a media type goes by that instead of
+ * its url, so `/module?id=42` served as `application/typescript` is compiled and a `.ts`
+ * url served as JavaScript isn't.
+ */
+pub fn source_flavor(origin_url: &str, source: &LoadedSourceCode) -> Option<bool> {
+    if source.is_wasm() {
+        return None;
+    }
+    match RemoteMediaType::of_response(&source.response) {
+        Some(RemoteMediaType::TypeScript(tsx)) => {
+            Some(tsx || typescript_flavor(origin_url) == Some(true))
+        }
+        None | Some(RemoteMediaType::Unknown) => typescript_flavor(origin_url),
+        Some(_) => None,
+    }
+}
+
+/**
+ * Compiles TypeScript to JavaScript, JSX in `.tsx` files to `React.createElement` calls,
+ * returning the code and a source map pointing back at `source`. Types are stripped and
+ * enums compiled, nothing is checked.
+ */
+#[cfg(feature = "transpile")]
+pub fn transpile_typescript(
+    origin_url: &str,
+    source: &str,
+    tsx: bool,
+) -> FlyResult<(String, String)> {
+    let cm: Lrc<SourceMap> = Default::default();
+    let file = cm.new_source_file(
+        Lrc::new(FileName::Custom(origin_url.to_string())),
+        source.to_string(),
+    );
+    let mut recovered = vec![];
+    let program = parse_file_as_program(
+        &file,
+        Syntax::Typescript(TsSyntax {
+            tsx,
+            ..Default::default()
+        }),
+        EsVersion::latest(),
+        None,
+        &mut recovered,
+    );
+    // errors the parser recovered from still make for code that wouldn't run as written
+    let program = match program {
+        Ok(_) if !recovered.is_empty() => Err(recovered.remove(0)),
+        program => program,
+    };
+    let program = program.map_err(|e| {
+        let at = cm.lookup_char_pos(e.span().lo);
+        parse_error(
+            origin_url,
+            "TypeScript",
+            at.line,
+            at.col.0 + 1,
+            &e.kind().msg(),
+        )
+    })?;
+
+    GLOBALS.set(&Globals::new(), || {
+        let unresolved_mark = Mark::new();
+        let top_level_mark = Mark::new();
+        let program = program
+            .apply(resolver(unresolved_mark, top_level_mark, true))
+            .apply(strip(unresolved_mark, top_level_mark));
+        let program = if tsx {
+            program.apply(react::<SingleThreadedComments>(
+                cm.clone(),
+                None,
+                Default::default(),
+                top_level_mark,
+                unresolved_mark,
+            ))
+        } else {
+            program
+        };
+
+        let mut code = vec![];
+        let mut mappings = vec![];
+        {
+            let mut emitter = Emitter {
+                cfg: Config::default(),
+                cm: cm.clone(),
+                comments: None,
+                wr: JsWriter::new(cm.clone(), "\n", &mut code, Some(&mut mappings)),
+            };
+            emitter.emit_program(&program).map_err(|e| {
+                FlyError::from(format!(
+                    "Could not emit {} as JavaScript: {}",
+                    origin_url, e
+                ))
+            })?;
+        }
+        let mut source_map = vec![];
+        cm.build_source_map(&mappings, None, DefaultSourceMapGenConfig)
+            .to_writer(&mut source_map)
+            .map_err(|e| {
+                FlyError::from(format!(
+                    "Could not write the source map of {}: {}",
+                    origin_url, e
+                ))
+            })?;
+        Ok((
+            String::from_utf8(code).unwrap(),
+            String::from_utf8(source_map).unwrap(),
+        ))
+    })
+}
+
+/**
+ * Without the `transpile` feature there's no compiler to hand TypeScript to; callers check
+ * `TRANSPILES` and leave it to the dev tools compiler in v8env instead.
+ */
+#[cfg(not(feature = "transpile"))]
+pub fn transpile_typescript(
+    origin_url: &str,
+    _source: &str,
+    _tsx: bool,
+) -> FlyResult<(String, String)> {
+    Err(FlyError::from(format!(
+        "Could not compile {}: built without the transpile feature",
+        origin_url
+    )))
+}
+
+/** Whether this build compiles TypeScript itself, with the `transpile` feature. */
+pub const TRANSPILES: bool = cfg!(feature = "transpile");
+
+/**
+ * `source`, loaded for `origin_url`, compiled to JavaScript if it's TypeScript and handed
+ * back untouched otherwise. Any source map it came with is replaced by the compiler's.
+ * Builds without the `transpile` feature hand TypeScript back untouched too.
+ */
+pub fn transpile_source(origin_url: &str, source: LoadedSourceCode) -> FlyResult<LoadedSourceCode> {
+    let tsx = match source_flavor(origin_url, &source) {
+        Some(tsx) => tsx,
+        None => return Ok(source),
+    };
+    if !TRANSPILES {
+        debug!("leaving {} to the dev tools compiler", origin_url);
+        return Ok(source);
+    }
+    let (code, source_map) = transpile_typescript(origin_url, &source.source, tsx)?;
+    Ok(LoadedSourceCode {
+        kind: ModuleKind::TypeScript,
+        source: code.into(),
+        source_map: Some(source_map.into()),
+        ..source
+    })
+}
+
+/**
+ * Loads through another loader and compiles what it loads to JavaScript when `origin_url`
+ * is a `.ts` or `.tsx` file, so the runtime can run it as it is. Every other source passes
+ * through untouched.
+ */
+pub struct TranspilingLoader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+}
+
+impl TranspilingLoader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+        }
+    }
+}
+
+impl SourceLoader for TranspilingLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        transpile_source(&self.origin_url, self.inner.load_source()?)
+    }
+}
+
+#[cfg(all(test, feature = "transpile"))]
+mod tests {
+    use super::*;
+    use crate::module_resolver::StaticSourceLoader;
+
+    fn load(origin_url: &str, source: &str) -> FlyResult<LoadedSourceCode> {
+        TranspilingLoader::new(
+            origin_url,
+            Box::new(StaticSourceLoader {
+                source: LoadedSourceCode {
+                    kind: ModuleKind::JavaScript,
+                    source_map: None,
+                    source: source.into(),
+                    response: None,
+                    provenance: None,
+                    code_cache: None,
+                    compiled_wasm: None,
+                    wasm: None,
+                },
+            }),
+        )
+        .load_source()
+    }
+
+    #[test]
+    fn test_compiles_typescript_with_a_source_map() {
+        let loaded = load(
+            "file:///app/util.ts",
+            "interface Point { x: number }\nexport const origin: Point = { x: 0 } as Point;\nexport enum Dir { Up, Down }\n",
+        )
+        .unwrap();
+        assert_eq!(ModuleKind::TypeScript, loaded.kind);
+        assert!(!loaded.source.contains("interface"));
+        assert!(!loaded.source.contains("as Point"));
+        assert!(loaded.source.contains("export const origin"));
+        assert!(loaded.source.contains("Dir[\"Up\"] = 0"));
+        let source_map: serde_json::Value =
+            serde_json::from_str(loaded.source_map.as_ref().unwrap()).unwrap();
+        assert_eq!(3, source_map["version"]);
+        assert_eq!("file:///app/util.ts", source_map["sources"][0]);
+
+        let loaded = load(
+            "file:///app/view.tsx?v=2",
+            "export const view = (n: number) => <b>{n}</b>;",
+        )
+        .unwrap();
+        assert!(loaded.source.contains("React.createElement(\"b\""));
+
+        // anything else stays as it is, declarations included
+        for origin_url in &["file:///app/main.js", "file:///app/types.d.ts"] {
+            let loaded = load(origin_url, "let x: number = 1;").unwrap();
+            assert_eq!("let x: number = 1;", &*loaded.source);
+            assert_eq!(None, loaded.source_map);
+        }
+    }
+
+    #[test]
+    fn test_compile_errors_point_at_the_source() {
+        let err = load(
+            "file:///app/broken.ts",
+            "export const a = 1;\nlet b: = 2;\n",
+        )
+        .err()
+        .unwrap();
+        let msg = format!("{}", err);
+        assert!(
+            msg.starts_with("file:///app/broken.ts:2:8: invalid TypeScript: "),
+            "{}",
+            msg
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use crate::errors::*;
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::{
+    CacheFreshness, CacheLocation, LoadedSourceCode, ModuleCacheState, ModuleKind, ModuleResolver,
+    ModuleSourceData, RefererInfo, ResolverDescription, ResponseMetadata, SourceLoader,
+    StaticSourceLoader,
+};
+
+pub const DEFAULT_VENDOR_CACHE_DIR: &str = ".fly/cache";
+
+/**
+ * One cached module. The source's hash is kept alongside it so an entry that was cut short
+ * or edited is noticed instead of served.
+ */
+#[derive(Debug, Deserialize, Serialize)]
+struct VendorCacheEntry {
+    origin_url: String,
+    sha256: String,
+    is_wasm: bool,
+    /** Missing from entries written before modules had kinds. */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<ModuleKind>,
+    source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_map: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<ResponseMetadata>,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/**
+ * The cache directory of a `CachingResolverWrapper`: `<dir>/<sha256 of origin url>`, one
+ * json file per module, replaced by rename. Any entry that can't be read back whole is a
+ * miss.
+ */
+#[derive(Clone, Debug)]
+struct VendorCacheDir {
+    dir: PathBuf,
+}
+
+impl VendorCacheDir {
+    fn entry_path(&self, origin_url: &str) -> PathBuf {
+        self.dir.join(sha256_hex(origin_url.as_bytes()))
+    }
+
+    fn read(&self, origin_url: &str) -> Option<LoadedSourceCode> {
+        let path = self.entry_path(origin_url);
+        let body = std::fs::read_to_string(&path).ok()?;
+        let entry: VendorCacheEntry = match serde_json::from_str(&body) {
+            Ok(entry) => entry,
+            Err(e) => return self.discard(&path, origin_url, e),
+        };
+        if entry.origin_url != origin_url {
+            return self.discard(
+                &path,
+                origin_url,
+                format!("entry is for {}", entry.origin_url),
+            );
+        }
+        if sha256_hex(entry.source.as_bytes()) != entry.sha256 {
+            return self.discard(&path, origin_url, "source doesn't match its hash");
+        }
+        Some(LoadedSourceCode {
+            kind: entry
+                .kind
+                .unwrap_or_else(|| ModuleKind::from_is_wasm(entry.is_wasm)),
+            source_map: entry.source_map.map(Into::into),
+            source: entry.source.into(),
+            response: entry.response,
+            provenance: Some("vendor_cache".to_string()),
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+
+    fn discard(
+        &self,
+        path: &Path,
+        origin_url: &str,
+        reason: impl std::fmt::Display,
+    ) -> Option<LoadedSourceCode> {
+        warn!(
+            "discarding cached {} at {}: {}",
+            origin_url,
+            path.display(),
+            reason
+        );
+        let _ = std::fs::remove_file(path);
+        None
+    }
+
+    fn write(&self, origin_url: &str, source: &LoadedSourceCode) -> FlyResult<()> {
+        let entry = VendorCacheEntry {
+            origin_url: origin_url.to_string(),
+            sha256: sha256_hex(source.source.as_bytes()),
+            is_wasm: source.is_wasm(),
+            kind: Some(source.kind),
+            source: source.source.to_string(),
+            source_map: source.source_map.as_ref().map(|m| m.to_string()),
+            response: source.response.clone(),
+        };
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(origin_url);
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_vec(&entry).unwrap())?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+/**
+ * Keeps whatever another resolver loads in a directory on disk, e.g. `.fly/cache`, and serves
+ * it from there on later runs without loading it again. Entries record the module's origin
+ * url and the response it came from, so relative imports resolve against the same url they
+ * did when it was first loaded. With `bypass_cache` every module is loaded afresh and its
+ * entry rewritten.
+ */
+pub struct CachingResolverWrapper {
+    inner: Box<ModuleResolver>,
+    cache: VendorCacheDir,
+    bypass_cache: bool,
+}
+
+impl CachingResolverWrapper {
+    pub fn new<P: Into<PathBuf>>(inner: Box<ModuleResolver>, dir: P) -> Self {
+        Self {
+            inner,
+            cache: VendorCacheDir { dir: dir.into() },
+            bypass_cache: false,
+        }
+    }
+
+    pub fn bypass_cache(mut self, bypass_cache: bool) -> Self {
+        self.bypass_cache = bypass_cache;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.cache.dir
+    }
+
+    /**
+     * Removes every cached module, returning how many there were.
+     */
+    pub fn purge(&self) -> FlyResult<usize> {
+        let entries = match std::fs::read_dir(&self.cache.dir) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_file() {
+                std::fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+impl ModuleResolver for CachingResolverWrapper {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let data = self.inner.resolve_module(module_specifier, referer_info)?;
+        if !self.bypass_cache {
+            if let Some(source) = self.cache.read(&data.origin_url) {
+                return Ok(ModuleSourceData {
+                    origin_url: data.origin_url,
+                    source_loader: Box::new(StaticSourceLoader { source }),
+                });
+            }
+        }
+        Ok(ModuleSourceData {
+            source_loader: Box::new(CachingSourceLoader {
+                origin_url: data.origin_url.clone(),
+                loader: data.source_loader,
+                cache: self.cache.clone(),
+            }),
+            origin_url: data.origin_url,
+        })
+    }
+    fn get_protocol(&self) -> String {
+        self.inner.get_protocol()
+    }
+    fn is_remote(&self) -> bool {
+        self.inner.is_remote()
+    }
+    fn handles_bare_specifier(&self, specifier: &str) -> bool {
+        self.inner.handles_bare_specifier(specifier)
+    }
+    fn name(&self) -> String {
+        format!("CachingResolverWrapper({})", self.inner.name())
+    }
+    fn describe(&self) -> ResolverDescription {
+        self.inner
+            .describe()
+            .named(&self.name())
+            .with("vendor_cache", &self.cache.dir.display().to_string())
+            .with("bypass_cache", &self.bypass_cache.to_string())
+    }
+    fn is_passthrough(&self) -> bool {
+        self.inner.is_passthrough()
+    }
+    fn priority(&self) -> i32 {
+        self.inner.priority()
+    }
+    fn cache_state(&self, origin_url: &str, tenant: &str) -> Option<ModuleCacheState> {
+        if !self.bypass_cache {
+            if let Some(source) = self.cache.read(origin_url) {
+                return Some(ModuleCacheState::cached(
+                    origin_url,
+                    CacheLocation::Disk,
+                    source.source.len(),
+                    CacheFreshness::Fresh,
+                ));
+            }
+        }
+        self.inner.cache_state(origin_url, tenant)
+    }
+    fn inheritable(&self) -> bool {
+        self.inner.inheritable()
+    }
+    fn needs_runtime_thread(&self) -> bool {
+        self.inner.needs_runtime_thread()
+    }
+}
+
+/**
+ * Loads through the wrapped resolver's loader and stores the result. Failing to store it
+ * only costs the next run a reload, so it doesn't fail the load.
+ */
+struct CachingSourceLoader {
+    origin_url: String,
+    loader: Box<SourceLoader>,
+    cache: VendorCacheDir,
+}
+
+impl SourceLoader for CachingSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let source = self.loader.load_source()?;
+        if let Err(e) = self.cache.write(&self.origin_url, &source) {
+            warn!("could not cache {}: {}", self.origin_url, e);
+        }
+        Ok(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::StaticModuleResolver;
+
+    fn wrapped(dir: &Path, source: &str) -> CachingResolverWrapper {
+        CachingResolverWrapper::new(
+            Box::new(StaticModuleResolver::new("static").insert("static://app/main.js", source)),
+            dir,
+        )
+    }
+
+    fn load(resolver: &CachingResolverWrapper) -> LoadedSourceCode {
+        resolver
+            .resolve_module("static://app/main.js", None)
+            .unwrap()
+            .source_loader
+            .load_source()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_serves_cached_modules_until_purged() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            "export default 1;",
+            &*load(&wrapped(dir.path(), "export default 1;")).source
+        );
+
+        // a later run whose upstream changed still sees what was vendored
+        let later = wrapped(dir.path(), "export default 2;");
+        let cached = load(&later);
+        assert_eq!("export default 1;", &*cached.source);
+        assert_eq!(
+            Some("vendor_cache"),
+            cached.provenance.as_ref().map(|p| p.as_str())
+        );
+        assert!(later
+            .cache_state("static://app/main.js", "app")
+            .unwrap()
+            .is_cached());
+
+        let bypassed = wrapped(dir.path(), "export default 2;").bypass_cache(true);
+        assert_eq!("export default 2;", &*load(&bypassed).source);
+
+        assert_eq!(1, later.purge().unwrap());
+        assert_eq!(0, later.purge().unwrap());
+        assert_eq!(
+            "export default 3;",
+            &*load(&wrapped(dir.path(), "export default 3;")).source
+        );
+    }
+
+    #[test]
+    fn test_keeps_the_origin_url_of_redirected_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        let redirected = LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: None,
+            source: "export * from './dep.js';".into(),
+            response: Some(ResponseMetadata {
+                final_url: "static://cdn/v2/main.js".to_string(),
+                status: 200,
+                ..Default::default()
+            }),
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        };
+        CachingResolverWrapper::new(
+            Box::new(
+                StaticModuleResolver::new("static")
+                    .insert_source("static://app/main.js", redirected),
+            ),
+            dir.path(),
+        )
+        .resolve_module("static://app/main.js", None)
+        .unwrap()
+        .source_loader
+        .load_source()
+        .unwrap();
+
+        let cached = load(&wrapped(dir.path(), ""));
+        assert_eq!(
+            "static://cdn/v2/main.js",
+            cached.response.unwrap().final_url
+        );
+    }
+
+    #[test]
+    fn test_refetches_corrupted_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let resolver = wrapped(dir.path(), "export default 1;");
+        load(&resolver);
+        let path = resolver.cache.entry_path("static://app/main.js");
+        let body = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::write(&path, &body[..body.len() / 2]).unwrap();
+        assert_eq!(
+            "export default 2;",
+            &*load(&wrapped(dir.path(), "export default 2;")).source
+        );
+
+        let tampered = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("export default 2;", "export default 666;");
+        std::fs::write(&path, tampered).unwrap();
+        assert_eq!(
+            "export default 3;",
+            &*load(&wrapped(dir.path(), "export default 3;")).source
+        );
+        assert_eq!("export default 3;", &*load(&wrapped(dir.path(), "")).source);
+    }
+}
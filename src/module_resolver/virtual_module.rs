@@ -0,0 +1,281 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::{
+    LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+/**
+ * The kind of source registered as a virtual module. Decides how the source is turned into
+ * module code when it gets loaded.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaType {
+    JavaScript,
+    TypeScript,
+    Json,
+}
+
+#[derive(Clone, Debug)]
+pub struct VirtualModule {
+    pub media_type: MediaType,
+    /** Shared with every load of the module rather than copied into it. */
+    pub source: Arc<str>,
+}
+
+/**
+ * Shared, in-memory table of modules keyed by their exact specifier (e.g. "app:config").
+ * Cloning the registry hands out another handle to the same table so modules can be
+ * registered or replaced after the resolver using it has been handed off.
+ */
+#[derive(Clone, Default)]
+pub struct VirtualModuleRegistry {
+    modules: Arc<RwLock<HashMap<String, VirtualModule>>>,
+}
+
+impl VirtualModuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Registers (or replaces) a module. Returns the module previously registered under
+     * the same specifier, if any.
+     */
+    pub fn insert(
+        &self,
+        specifier: &str,
+        source: &str,
+        media_type: MediaType,
+    ) -> FlyResult<Option<VirtualModule>> {
+        if media_type == MediaType::Json {
+            if let Err(e) = serde_json::from_str::<serde_json::Value>(source) {
+                return Err(FlyError::from(format!(
+                    "Invalid JSON for virtual module {}: {}",
+                    specifier, e
+                )));
+            }
+        }
+        let mut modules = match self.modules.write() {
+            Ok(m) => m,
+            Err(_) => return Err(FlyError::from("virtual module lock poisoned".to_string())),
+        };
+        Ok(modules.insert(
+            specifier.to_string(),
+            VirtualModule {
+                media_type,
+                source: source.into(),
+            },
+        ))
+    }
+
+    pub fn remove(&self, specifier: &str) -> Option<VirtualModule> {
+        match self.modules.write() {
+            Ok(mut modules) => modules.remove(specifier),
+            Err(_) => None,
+        }
+    }
+
+    pub fn get(&self, specifier: &str) -> Option<VirtualModule> {
+        match self.modules.read() {
+            Ok(modules) => modules.get(specifier).cloned(),
+            Err(_) => None,
+        }
+    }
+
+    /**
+     * Every registered specifier, sorted.
+     */
+    pub fn specifiers(&self) -> Vec<String> {
+        let mut specifiers: Vec<String> = self.modules.read().unwrap().keys().cloned().collect();
+        specifiers.sort();
+        specifiers
+    }
+
+    pub fn contains(&self, specifier: &str) -> bool {
+        match self.modules.read() {
+            Ok(modules) => modules.contains_key(specifier),
+            Err(_) => false,
+        }
+    }
+}
+
+pub struct VirtualSourceLoader {
+    pub module: VirtualModule,
+}
+
+impl SourceLoader for VirtualSourceLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let (kind, source) = match self.module.media_type {
+            MediaType::JavaScript => (ModuleKind::JavaScript, self.module.source.clone()),
+            MediaType::TypeScript => (ModuleKind::TypeScript, self.module.source.clone()),
+            MediaType::Json => (
+                ModuleKind::Json,
+                format!("export default {};", self.module.source).into(),
+            ),
+        };
+        Ok(LoadedSourceCode {
+            kind,
+            source_map: None,
+            source,
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+/**
+ * Resolves specifiers registered in a `VirtualModuleRegistry`. The manager consults it
+ * before protocol dispatch, so virtual specifiers can use any scheme.
+ */
+#[derive(Clone)]
+pub struct VirtualModuleResolver {
+    registry: VirtualModuleRegistry,
+}
+
+impl VirtualModuleResolver {
+    pub fn new(registry: VirtualModuleRegistry) -> Self {
+        Self { registry }
+    }
+
+    pub fn registry(&self) -> &VirtualModuleRegistry {
+        &self.registry
+    }
+}
+
+impl ModuleResolver for VirtualModuleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        _referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        match self.registry.get(module_specifier) {
+            Some(module) => Ok(ModuleSourceData {
+                origin_url: module_specifier.to_string(),
+                source_loader: Box::new(VirtualSourceLoader { module }),
+            }),
+            None => Err(FlyError::from(format!(
+                "No virtual module registered for {}",
+                module_specifier
+            ))),
+        }
+    }
+    fn get_protocol(&self) -> String {
+        return "virtual".to_string();
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("virtual", "virtual")
+            .named(&self.name())
+            .with("modules", &self.registry.specifiers().join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_counter::copies_of;
+    use crate::module_resolver::{ModuleResolverManager, StandardModuleResolverManager};
+
+    fn manager(registry: &VirtualModuleRegistry) -> StandardModuleResolverManager {
+        StandardModuleResolverManager::new(vec![], Some("file:///app/".to_string()))
+            .with_virtual_modules(registry.clone())
+    }
+
+    #[test]
+    fn test_register_before_manager_is_built() {
+        let registry = VirtualModuleRegistry::new();
+        registry
+            .insert("app:config", "export default 42;", MediaType::JavaScript)
+            .unwrap();
+        let manager = manager(&registry);
+
+        let module = manager
+            .resolve_blocking("app:config".to_string(), None)
+            .unwrap();
+        assert_eq!("app:config", module.origin_url);
+        assert_eq!("export default 42;", &*module.loaded_source.source);
+    }
+
+    #[test]
+    fn test_register_after_manager_is_built() {
+        let registry = VirtualModuleRegistry::new();
+        let manager = manager(&registry);
+        assert!(manager
+            .resolve_blocking("app:config".to_string(), None)
+            .is_err());
+
+        registry
+            .insert("app:config", "{\"debug\":true}", MediaType::Json)
+            .unwrap();
+        let module = manager
+            .resolve_blocking("app:config".to_string(), None)
+            .unwrap();
+        assert_eq!(
+            "export default {\"debug\":true};",
+            &*module.loaded_source.source
+        );
+    }
+
+    #[test]
+    fn test_replace_and_remove() {
+        let registry = VirtualModuleRegistry::new();
+        let manager = manager(&registry);
+        assert!(registry
+            .insert("app:gen", "export const a = 1;", MediaType::TypeScript)
+            .unwrap()
+            .is_none());
+        let previous = registry
+            .insert("app:gen", "export const a = 2;", MediaType::TypeScript)
+            .unwrap();
+        assert_eq!("export const a = 1;", &*previous.unwrap().source);
+        assert_eq!(
+            "export const a = 2;",
+            &*manager
+                .resolve_blocking("app:gen".to_string(), None)
+                .unwrap()
+                .loaded_source
+                .source
+        );
+
+        assert!(registry.remove("app:gen").is_some());
+        assert!(manager
+            .resolve_blocking("app:gen".to_string(), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_json_is_rejected() {
+        let registry = VirtualModuleRegistry::new();
+        assert!(registry
+            .insert("app:config", "{not json", MediaType::Json)
+            .is_err());
+        assert!(!registry.contains("app:config"));
+    }
+
+    #[test]
+    fn test_resolving_shares_the_registered_source() {
+        let registry = VirtualModuleRegistry::new();
+        let source = format!("export default {:?};", "x".repeat(5 * 1024 * 1024));
+        registry
+            .insert("app:big", &source, MediaType::JavaScript)
+            .unwrap();
+        let registered = registry.get("app:big").unwrap().source;
+        let manager = manager(&registry);
+
+        let (copies, ()) = copies_of(source.len(), || {
+            for _ in 0..100 {
+                let module = manager
+                    .resolve_blocking("app:big".to_string(), None)
+                    .unwrap();
+                assert!(Arc::ptr_eq(&registered, &module.loaded_source.source));
+            }
+        });
+        assert_eq!(0, copies);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::errors::*;
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn remove_file(path: &Path) -> bool {
+    std::fs::remove_file(path).is_ok()
+}
+
+/** How big a `WasmCompileCache` is on disk. */
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct WasmCompileCacheStats {
+    pub modules: usize,
+    pub bytes: u64,
+}
+
+/**
+ * WebAssembly modules as V8 compiled them, serialized to a directory so a runtime booting
+ * after the first instantiates them without compiling again. Artifacts are keyed by the
+ * sha256 of the wasm binary they were compiled from, wherever it was loaded from, and kept
+ * under a subdirectory named for the engine that compiled them: V8 can't read another
+ * version's, so a new engine starts afresh, and older engines' directories are removed when
+ * the cache is opened.
+ *
+ * Each file starts with the sha256 of the artifact after it. One that doesn't match, say
+ * because it was cut short, is removed and treated as missing; one V8 refuses anyway only
+ * costs the compile, whose artifact then replaces it. Other processes may share the
+ * directory: files are replaced by rename.
+ */
+#[derive(Clone, Debug)]
+pub struct WasmCompileCache {
+    dir: PathBuf,
+}
+
+impl WasmCompileCache {
+    /** The artifacts `engine_version`, e.g. `libfly::version()`, compiled, in `root`. */
+    pub fn new<P: AsRef<Path>>(root: P, engine_version: &str) -> FlyResult<Self> {
+        let root = root.as_ref();
+        let name: String = engine_version
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if name.is_empty() {
+            return Err(FlyError::from(
+                "a wasm compile cache needs the engine's version".to_string(),
+            ));
+        }
+        let dir = root.join(format!("v8-{}", name));
+        std::fs::create_dir_all(&dir)?;
+        for entry in std::fs::read_dir(root)? {
+            let entry = entry?;
+            let is_other_engine = entry.file_name().to_string_lossy().starts_with("v8-")
+                && entry.path() != dir
+                && entry.file_type()?.is_dir();
+            if is_other_engine {
+                debug!("removing stale wasm artifacts {}", entry.path().display());
+                if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                    warn!("could not remove {}: {}", entry.path().display(), e);
+                }
+            }
+        }
+        Ok(WasmCompileCache { dir })
+    }
+
+    /** Where this engine's artifacts are. */
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, wasm: &[u8]) -> PathBuf {
+        self.dir.join(sha256_hex(wasm))
+    }
+
+    /** The compiled artifact of `wasm`, the module's binary, if there's one intact. */
+    pub fn get(&self, wasm: &[u8]) -> Option<Vec<u8>> {
+        let path = self.path(wasm);
+        let mut contents = std::fs::read(&path).ok()?;
+        if contents.len() < 32 || Sha256::digest(&contents[32..]).as_slice() != &contents[..32] {
+            warn!("removing the corrupt wasm artifact {}", path.display());
+            remove_file(&path);
+            return None;
+        }
+        Some(contents.split_off(32))
+    }
+
+    /**
+     * Keeps `artifact`, what V8 serialized compiling `wasm`. Failing to only costs the next
+     * runtime the compile, so it's logged rather than returned.
+     */
+    pub fn insert(&self, wasm: &[u8], artifact: &[u8]) {
+        let path = self.path(wasm);
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut contents = Sha256::digest(artifact).to_vec();
+        contents.extend_from_slice(artifact);
+        let written = std::fs::write(&tmp, &contents).and_then(|_| std::fs::rename(&tmp, &path));
+        if let Err(e) = written {
+            warn!("could not keep the wasm artifact {}: {}", path.display(), e);
+            remove_file(&tmp);
+        }
+    }
+
+    /** Forgets the artifact of `wasm`, returning whether there was one. */
+    pub fn remove(&self, wasm: &[u8]) -> bool {
+        remove_file(&self.path(wasm))
+    }
+
+    pub fn stats(&self) -> WasmCompileCacheStats {
+        let mut stats = WasmCompileCacheStats::default();
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return stats,
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            if entry.path().extension().is_some() {
+                continue;
+            }
+            if let Ok(meta) = entry.metadata() {
+                stats.modules += 1;
+                stats.bytes += meta.len();
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+    #[test]
+    fn test_artifacts_are_for_the_exact_binary_and_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = WasmCompileCache::new(dir.path(), "7.1.302.33").unwrap();
+        assert_eq!(None, cache.get(WASM));
+        cache.insert(WASM, b"compiled");
+        assert_eq!(Some(b"compiled".to_vec()), cache.get(WASM));
+        assert_eq!(None, cache.get(b"\0asm\x01\0\0\0\x01"));
+        assert_eq!(1, cache.stats().modules);
+
+        // the same engine finds them again...
+        let reopened = WasmCompileCache::new(dir.path(), "7.1.302.33").unwrap();
+        assert_eq!(Some(b"compiled".to_vec()), reopened.get(WASM));
+        // ...another starts afresh, and the old artifacts go
+        let upgraded = WasmCompileCache::new(dir.path(), "7.2.1").unwrap();
+        assert_eq!(None, upgraded.get(WASM));
+        assert!(!cache.dir().exists());
+        assert!(dir.path().join("v8-7.2.1").is_dir());
+    }
+
+    #[test]
+    fn test_corrupt_artifacts_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = WasmCompileCache::new(dir.path(), "7.1.302.33").unwrap();
+        cache.insert(WASM, b"compiled");
+        let path = cache.path(WASM);
+        let mut contents = std::fs::read(&path).unwrap();
+        contents.truncate(contents.len() - 1);
+        std::fs::write(&path, contents).unwrap();
+
+        assert_eq!(None, cache.get(WASM));
+        assert!(!path.exists());
+        assert_eq!(WasmCompileCacheStats::default(), cache.stats());
+        assert!(!cache.remove(WASM));
+    }
+}
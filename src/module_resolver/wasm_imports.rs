@@ -0,0 +1,291 @@
+use crate::errors::*;
+
+use std::collections::{BTreeMap, HashMap};
+
+/**
+ * One entry of a wasm module's import section.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct WasmImport {
+    pub module: String,
+    pub field: String,
+}
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+const IMPORT_SECTION: u8 = 2;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> FlyResult<u8> {
+        let b = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| FlyError::from("unexpected end of wasm module".to_string()))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn leb_u32(&mut self) -> FlyResult<u32> {
+        let mut result = 0u32;
+        for shift in (0..35).step_by(7) {
+            let b = self.byte()?;
+            result |= ((b & 0x7f) as u32) << shift;
+            if b & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(FlyError::from(
+            "invalid LEB128 integer in wasm module".to_string(),
+        ))
+    }
+
+    fn take(&mut self, len: usize) -> FlyResult<&'a [u8]> {
+        if self.bytes.len() - self.pos < len {
+            return Err(FlyError::from("unexpected end of wasm module".to_string()));
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn name(&mut self) -> FlyResult<String> {
+        let len = self.leb_u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| FlyError::from("wasm import name is not utf-8".to_string()))
+    }
+
+    fn limits(&mut self) -> FlyResult<()> {
+        let has_max = self.byte()? & 1 == 1;
+        self.leb_u32()?;
+        if has_max {
+            self.leb_u32()?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * The imports `wasm` declares, in section order.
+ */
+pub fn declared_imports(wasm: &[u8]) -> FlyResult<Vec<WasmImport>> {
+    if wasm.len() < 8 || &wasm[..4] != WASM_MAGIC {
+        return Err(FlyError::from("not a wasm module".to_string()));
+    }
+    let mut reader = Reader {
+        bytes: wasm,
+        pos: 8,
+    };
+    while reader.pos < wasm.len() {
+        let id = reader.byte()?;
+        let len = reader.leb_u32()? as usize;
+        if id != IMPORT_SECTION {
+            reader.take(len)?;
+            continue;
+        }
+        let mut imports = vec![];
+        for _ in 0..reader.leb_u32()? {
+            let module = reader.name()?;
+            let field = reader.name()?;
+            match reader.byte()? {
+                // function: type index
+                0 => {
+                    reader.leb_u32()?;
+                }
+                // table: element type, limits
+                1 => {
+                    reader.byte()?;
+                    reader.limits()?;
+                }
+                2 => reader.limits()?,
+                // global: value type, mutability
+                3 => {
+                    reader.take(2)?;
+                }
+                kind => return Err(FlyError::from(format!("unknown wasm import kind {}", kind))),
+            }
+            imports.push(WasmImport { module, field });
+        }
+        return Ok(imports);
+    }
+    Ok(vec![])
+}
+
+/**
+ * Maps the module names in a wasm module's imports, like `env` or `./glue`, to the specifiers
+ * that satisfy them, e.g. `fly:wasm-env` or a local JS file. Once configured, every import of
+ * a wasm module must be mapped.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct WasmImportMap {
+    mappings: BTreeMap<String, String>,
+}
+
+impl WasmImportMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mapping(mut self, import_module: &str, specifier: &str) -> Self {
+        self.mappings
+            .insert(import_module.to_string(), specifier.to_string());
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.mappings.iter()
+    }
+
+    /**
+     * The specifier for `import_module`, imported by `referer_url`. An unmapped name fails with
+     * the module names `referer_wasm` declares, when its bytes are at hand, so it's clear what
+     * still needs a mapping.
+     */
+    pub fn map_import(
+        &self,
+        import_module: &str,
+        referer_url: &str,
+        referer_wasm: Option<&[u8]>,
+    ) -> FlyResult<String> {
+        if let Some(specifier) = self.mappings.get(import_module) {
+            debug!("mapped wasm import {} to {}", import_module, specifier);
+            return Ok(specifier.clone());
+        }
+        let declared = match referer_wasm.map(declared_imports) {
+            Some(Ok(imports)) => {
+                let mut modules: Vec<String> = vec![];
+                for import in imports {
+                    if !modules.contains(&import.module) {
+                        modules.push(import.module);
+                    }
+                }
+                format!("; it declares imports from {}", modules.join(", "))
+            }
+            _ => String::new(),
+        };
+        Err(FlyError::from(format!(
+            "wasm module {} imports {}, which has no wasm import mapping{}",
+            referer_url, import_module, declared
+        )))
+    }
+}
+
+impl<'a> From<&'a HashMap<String, String>> for WasmImportMap {
+    fn from(mappings: &'a HashMap<String, String>) -> Self {
+        Self {
+            mappings: mappings
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(bytes: &mut Vec<u8>, name: &str) {
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+    }
+
+    // imports env.memory (memory), env.log (func) and ./glue.now (func)
+    fn wasm_with_imports() -> Vec<u8> {
+        let mut section = vec![3];
+        name(&mut section, "env");
+        name(&mut section, "memory");
+        section.extend_from_slice(&[2, 1, 1, 2]);
+        name(&mut section, "env");
+        name(&mut section, "log");
+        section.extend_from_slice(&[0, 0]);
+        name(&mut section, "./glue");
+        name(&mut section, "now");
+        section.extend_from_slice(&[0, 1]);
+
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // a type section to skip over
+        wasm.extend_from_slice(&[1, 4, 1, 0x60, 0, 0]);
+        wasm.push(IMPORT_SECTION);
+        wasm.push(section.len() as u8);
+        wasm.extend_from_slice(&section);
+        wasm
+    }
+
+    #[test]
+    fn test_reads_declared_imports() {
+        let imports = declared_imports(&wasm_with_imports()).unwrap();
+        let names: Vec<(&str, &str)> = imports
+            .iter()
+            .map(|i| (i.module.as_str(), i.field.as_str()))
+            .collect();
+        assert_eq!(
+            vec![("env", "memory"), ("env", "log"), ("./glue", "now")],
+            names
+        );
+        assert!(declared_imports(b"export default 1;").is_err());
+    }
+
+    #[test]
+    fn test_unmapped_import_lists_declared_modules() {
+        let map = WasmImportMap::new().with_mapping("env", "fly:wasm-env");
+        assert_eq!(
+            "fly:wasm-env",
+            map.map_import("env", "file:///app/lib.wasm", None).unwrap()
+        );
+        let wasm = wasm_with_imports();
+        let err = map
+            .map_import("./glue", "file:///app/lib.wasm", Some(&wasm))
+            .err()
+            .unwrap();
+        assert_eq!(
+            "wasm module file:///app/lib.wasm imports ./glue, which has no wasm import mapping; it declares imports from env, ./glue",
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_manager_maps_imports_of_wasm_referers_only() {
+        use crate::module_resolver::{
+            MediaType, ModuleResolverManager, RefererInfo, StandardModuleResolverManager,
+            VirtualModuleRegistry,
+        };
+        let registry = VirtualModuleRegistry::new();
+        registry
+            .insert(
+                "fly:wasm-env",
+                "export const log = () => {};",
+                MediaType::JavaScript,
+            )
+            .unwrap();
+        let manager = StandardModuleResolverManager::new(vec![], Some("file:///app/".to_string()))
+            .with_virtual_modules(registry)
+            .with_wasm_imports(WasmImportMap::new().with_mapping("env", "fly:wasm-env"));
+        let referer = |is_wasm| RefererInfo {
+            origin_url: "file:///app/lib.wasm".to_string(),
+            is_wasm: Some(is_wasm),
+            source_code: None,
+            identifier_hash: Some(7),
+            line: None,
+            column: None,
+            app_id: None,
+            deadline: None,
+        };
+        let module = manager
+            .resolve_blocking("env".to_string(), Some(referer(true)))
+            .unwrap();
+        assert_eq!("fly:wasm-env", module.origin_url);
+        let err = manager
+            .resolve_blocking("./glue".to_string(), Some(referer(true)))
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("no wasm import mapping"));
+        // a JS referer's "env" is just a bare specifier
+        assert!(manager
+            .resolve_blocking("env".to_string(), Some(referer(false)))
+            .is_err());
+    }
+}
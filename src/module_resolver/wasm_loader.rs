@@ -0,0 +1,129 @@
+use crate::errors::*;
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+pub(super) const WASM_MAGIC: &[u8] = b"\0asm";
+
+/**
+ * Whether the file at `path` is a wasm binary, going by its magic number rather than its
+ * extension.
+ */
+pub fn is_wasm_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| magic == WASM_MAGIC)
+        .unwrap_or(false)
+}
+
+/**
+ * Wasm modules travel as base64 in `LoadedSourceCode::source`, which has to stay text so the
+ * caches can hold any module the same way. The binary comes along in `wasm`, for runtimes to
+ * share rather than decode.
+ */
+pub fn wasm_source(bytes: &[u8]) -> LoadedSourceCode {
+    LoadedSourceCode {
+        kind: ModuleKind::Wasm,
+        source_map: None,
+        source: base64::encode(bytes).into(),
+        response: None,
+        provenance: None,
+        code_cache: None,
+        compiled_wasm: None,
+        wasm: Some(Bytes::from(bytes)),
+    }
+}
+
+/**
+ * The binary of a source with `is_wasm` set: the one it came with, shared, or else decoded
+ * from its base64, e.g. for sources read back from a cache.
+ */
+pub fn wasm_bytes(source: &LoadedSourceCode) -> FlyResult<Bytes> {
+    if !source.is_wasm() {
+        return Err(FlyError::from("not a wasm module".to_string()));
+    }
+    if let Some(ref wasm) = source.wasm {
+        return Ok(wasm.clone());
+    }
+    base64::decode(source.source.as_bytes())
+        .map(Bytes::from)
+        .map_err(|e| {
+            new(
+                ErrorKind::InvalidData,
+                format!("invalid wasm module: {}", e),
+            )
+        })
+}
+
+/**
+ * Reads a wasm binary from disk, checking it's one.
+ */
+pub struct LocalDiskWasmLoader {
+    pub path: PathBuf,
+}
+
+impl LocalDiskWasmLoader {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl SourceLoader for LocalDiskWasmLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let bytes = std::fs::read(&self.path)?;
+        if !bytes.starts_with(WASM_MAGIC) {
+            return Err(new(
+                ErrorKind::InvalidData,
+                format!("{} is not a wasm module", self.path.display()),
+            ));
+        }
+        Ok(wasm_source(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (module (func (export "answer") (result i32) i32.const 42))
+    const ANSWER: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f,
+        0x03, 0x02, 0x01, 0x00, 0x07, 0x0a, 0x01, 0x06, 0x61, 0x6e, 0x73, 0x77, 0x65, 0x72, 0x00,
+        0x00, 0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x2a, 0x0b,
+    ];
+
+    #[test]
+    fn test_loads_wasm_binaries_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("answer.wasm");
+        std::fs::write(&path, ANSWER).unwrap();
+        assert!(is_wasm_file(&path));
+
+        let source = LocalDiskWasmLoader::new(path).load_source().unwrap();
+        assert_eq!(ModuleKind::Wasm, source.kind);
+        assert!(source.is_wasm());
+        assert_eq!(ANSWER, &wasm_bytes(&source).unwrap()[..]);
+        // shared, not decoded for each runtime
+        assert_eq!(
+            wasm_bytes(&source).unwrap().as_ptr(),
+            wasm_bytes(&source.clone()).unwrap().as_ptr()
+        );
+        // the same binary comes back from its base64, as from a cache holding its text
+        let cached = LoadedSourceCode {
+            wasm: None,
+            ..source.clone()
+        };
+        assert_eq!(wasm_bytes(&source).unwrap(), wasm_bytes(&cached).unwrap());
+
+        let text = dir.path().join("fake.wasm");
+        std::fs::write(&text, "export default 1;").unwrap();
+        assert!(!is_wasm_file(&text));
+        let err = LocalDiskWasmLoader::new(text).load_source().err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+    }
+}
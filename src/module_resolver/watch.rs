@@ -0,0 +1,232 @@
+use crate::errors::*;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+/**
+ * How long a file has to stay quiet after a change before it's reported, so an editor's save
+ * (often a write, a rename and a chmod) is one invalidation.
+ */
+pub const DEFAULT_WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+pub type InvalidationCallback = Box<Fn(String) + Send>;
+
+#[derive(Default)]
+struct Watched {
+    /** canonical file path -> origin urls it was resolved as */
+    files: HashMap<PathBuf, BTreeSet<String>>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl Watched {
+    fn origins_of(&self, path: &Path) -> Vec<String> {
+        self.files
+            .get(path)
+            .map(|origins| origins.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/**
+ * Watches the files local modules were resolved from and calls back with a module's origin
+ * url when its file is written, replaced, renamed or deleted, e.g. to invalidate a
+ * `ResolutionCache` and reload the module. Files are watched through their directories, so
+ * editors that save by renaming a new file over the old one are noticed too. Callbacks run on
+ * the watcher's own thread; registering a file never waits for them. Clones share the same
+ * watcher.
+ */
+#[derive(Clone)]
+pub struct ModuleWatcher {
+    watched: Arc<Mutex<Watched>>,
+    watcher: Arc<Mutex<RecommendedWatcher>>,
+}
+
+impl ModuleWatcher {
+    pub fn new(on_change: InvalidationCallback) -> FlyResult<Self> {
+        Self::with_debounce(on_change, DEFAULT_WATCH_DEBOUNCE)
+    }
+
+    pub fn with_debounce(on_change: InvalidationCallback, debounce: Duration) -> FlyResult<Self> {
+        let (tx, rx) = mpsc::channel();
+        let watcher = notify::watcher(tx, debounce)
+            .map_err(|e| FlyError::from(format!("could not start file watcher: {}", e)))?;
+        let watched = Arc::new(Mutex::new(Watched::default()));
+        let events_for = watched.clone();
+        std::thread::Builder::new()
+            .name("module-watcher".to_string())
+            .spawn(move || {
+                // ends once the watcher, and with it the sender, is dropped
+                for event in rx {
+                    let changed = match event {
+                        DebouncedEvent::Write(path)
+                        | DebouncedEvent::Create(path)
+                        | DebouncedEvent::Remove(path) => vec![path],
+                        DebouncedEvent::Rename(from, to) => vec![from, to],
+                        DebouncedEvent::Rescan => {
+                            let watched = events_for.lock().unwrap();
+                            watched.files.keys().cloned().collect()
+                        }
+                        DebouncedEvent::Error(e, path) => {
+                            warn!("file watcher error at {:?}: {}", path, e);
+                            continue;
+                        }
+                        DebouncedEvent::NoticeWrite(_)
+                        | DebouncedEvent::NoticeRemove(_)
+                        | DebouncedEvent::Chmod(_) => continue,
+                    };
+                    let origins: BTreeSet<String> = {
+                        let watched = events_for.lock().unwrap();
+                        changed
+                            .iter()
+                            .flat_map(|path| watched.origins_of(path))
+                            .collect()
+                    };
+                    for origin_url in origins {
+                        debug!("{} changed on disk", origin_url);
+                        on_change(origin_url);
+                    }
+                }
+            })?;
+        Ok(Self {
+            watched,
+            watcher: Arc::new(Mutex::new(watcher)),
+        })
+    }
+
+    /**
+     * Reports changes to the file at `path` as changes to `origin_url`.
+     */
+    pub fn watch(&self, path: &Path, origin_url: &str) -> FlyResult<()> {
+        let path = path.canonicalize()?;
+        let dir = match path.parent() {
+            Some(dir) => dir.to_path_buf(),
+            None => return Ok(()),
+        };
+        let new_dir = {
+            let mut watched = self.watched.lock().unwrap();
+            watched
+                .files
+                .entry(path.clone())
+                .or_insert_with(BTreeSet::new)
+                .insert(origin_url.to_string());
+            watched.dirs.insert(dir.clone())
+        };
+        if new_dir {
+            if let Err(e) = self
+                .watcher
+                .lock()
+                .unwrap()
+                .watch(&dir, RecursiveMode::NonRecursive)
+            {
+                self.watched.lock().unwrap().dirs.remove(&dir);
+                return Err(FlyError::from(format!(
+                    "could not watch {}: {}",
+                    dir.display(),
+                    e
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn watched_files(&self) -> usize {
+        self.watched.lock().unwrap().files.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{
+        LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager, RefererInfo,
+        ResolutionCache, StandardModuleResolverManager,
+    };
+
+    #[test]
+    fn test_changed_renamed_and_deleted_files_invalidate() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("main.ts"), "import './util.ts';").unwrap();
+        std::fs::write(root.join("util.ts"), "export default 1;").unwrap();
+
+        let cache = ResolutionCache::new();
+        let (tx, rx) = mpsc::channel();
+        let invalidated = cache.clone();
+        let tx = Mutex::new(tx);
+        let watcher = ModuleWatcher::with_debounce(
+            Box::new(move |origin_url| {
+                invalidated.invalidate(&origin_url);
+                tx.lock().unwrap().send(origin_url).unwrap();
+            }),
+            Duration::from_millis(10),
+        )
+        .unwrap();
+        let working_url = url::Url::from_directory_path(&root).unwrap().into_string();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                LocalDiskModuleResolver::new(Some(working_url.clone()))
+                    .unwrap()
+                    .with_watcher(watcher),
+            ) as Box<ModuleResolver>],
+            Some(working_url),
+        )
+        .with_resolution_cache(cache.clone());
+        let main = manager
+            .resolve_blocking("./main.ts".to_string(), None)
+            .unwrap();
+        let referer = || {
+            Some(RefererInfo {
+                origin_url: main.origin_url.clone(),
+                is_wasm: Some(false),
+                source_code: None,
+                identifier_hash: None,
+                line: None,
+                column: None,
+                app_id: None,
+                deadline: None,
+            })
+        };
+        let resolve_util = || {
+            manager
+                .resolve_blocking("./util.ts".to_string(), referer())
+                .map(|m| m.loaded_source.source.to_string())
+        };
+        assert_eq!("export default 1;", resolve_util().unwrap());
+        let util_url = url::Url::from_file_path(root.join("util.ts"))
+            .unwrap()
+            .into_string();
+        // one save can be reported more than once; any repeats are drained too
+        let changed = || {
+            let origin_url = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            while rx.recv_timeout(Duration::from_millis(50)).is_ok() {}
+            origin_url
+        };
+
+        std::fs::write(root.join("util.ts"), "export default 2;").unwrap();
+        assert_eq!(util_url, changed());
+        assert_eq!("export default 2;", resolve_util().unwrap());
+
+        // saved by renaming a new file over it, as many editors do
+        std::fs::write(root.join(".util.ts.swp"), "export default 3;").unwrap();
+        std::fs::rename(root.join(".util.ts.swp"), root.join("util.ts")).unwrap();
+        assert_eq!(util_url, changed());
+        assert_eq!("export default 3;", resolve_util().unwrap());
+
+        std::fs::rename(root.join("util.ts"), root.join("moved.ts")).unwrap();
+        assert_eq!(util_url, changed());
+        assert!(resolve_util().is_err());
+
+        std::fs::write(root.join("util.ts"), "export default 4;").unwrap();
+        assert_eq!(util_url, changed());
+        assert_eq!("export default 4;", resolve_util().unwrap());
+        std::fs::remove_file(root.join("util.ts")).unwrap();
+        assert_eq!(util_url, changed());
+        assert!(resolve_util().is_err());
+        assert_eq!(1, cache.stats().modules);
+    }
+}
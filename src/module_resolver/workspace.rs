@@ -0,0 +1,491 @@
+use crate::errors::*;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::yaml_loader::{load_yaml, YamlLimits};
+use super::{
+    file_origin_url, DataFormats, LocalDiskRawLoader, ModuleResolver, ModuleSourceData,
+    RefererInfo, ResolverDescription,
+};
+
+const WORKSPACE_PROTOCOL: &str = "workspace";
+
+/**
+ * Files tried, in order, as a member's entry point before falling back to the `module` and
+ * `main` fields of its package.json. Sources come first so importers never see stale builds.
+ */
+pub const DEFAULT_ENTRY_CANDIDATES: &[&str] = &["src/index.ts", "src/index.js", "index.ts"];
+
+#[derive(Clone, Debug)]
+pub struct WorkspaceMember {
+    pub name: String,
+    pub root: PathBuf,
+    pub manifest: serde_json::Value,
+}
+
+/**
+ * Maps `workspace:<package>[/<path>]` specifiers to the source of a monorepo member package.
+ * Members are the directories matched by the `workspaces` field of the root package.json, or
+ * the `packages` list of a pnpm-workspace.yaml; without either every package.json under the
+ * root outside `node_modules` is a member.
+ */
+pub struct WorkspaceResolver {
+    root: PathBuf,
+    members: BTreeMap<String, WorkspaceMember>,
+    entry_candidates: Vec<String>,
+    data_formats: DataFormats,
+    // member name -> origin urls of the modules that imported it
+    importers: Mutex<HashMap<String, BTreeSet<String>>>,
+}
+
+impl WorkspaceResolver {
+    pub fn discover(root: &Path) -> FlyResult<Self> {
+        let root = root.canonicalize()?;
+        let mut members: BTreeMap<String, WorkspaceMember> = BTreeMap::new();
+        for manifest_path in member_manifests(&root)? {
+            let manifest = read_manifest(&manifest_path)?;
+            let name = match manifest.get("name").and_then(|n| n.as_str()) {
+                Some(name) => name.to_string(),
+                None => {
+                    warn!(
+                        "skipping unnamed workspace package {}",
+                        manifest_path.display()
+                    );
+                    continue;
+                }
+            };
+            let member_root = manifest_path.parent().unwrap().to_path_buf();
+            if let Some(existing) = members.get(&name) {
+                return Err(FlyError::from(format!(
+                    "workspace package {} is defined twice: {} and {}",
+                    name,
+                    existing.root.display(),
+                    member_root.display()
+                )));
+            }
+            members.insert(
+                name.clone(),
+                WorkspaceMember {
+                    name,
+                    root: member_root,
+                    manifest,
+                },
+            );
+        }
+        Ok(Self {
+            root,
+            members,
+            entry_candidates: DEFAULT_ENTRY_CANDIDATES
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            data_formats: DataFormats::default(),
+            importers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn with_entry_candidates(mut self, candidates: Vec<String>) -> Self {
+        self.entry_candidates = candidates;
+        self
+    }
+
+    pub fn with_data_formats(mut self, data_formats: DataFormats) -> Self {
+        self.data_formats = data_formats;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = &WorkspaceMember> {
+        self.members.values()
+    }
+
+    /**
+     * The file a bare `workspace:<name>` import loads.
+     */
+    pub fn entry_point(&self, member: &WorkspaceMember) -> FlyResult<PathBuf> {
+        let manifest_entries = ["module", "main"]
+            .iter()
+            .filter_map(|field| member.manifest.get(*field).and_then(|v| v.as_str()))
+            .map(|s| s.to_string());
+        for candidate in self
+            .entry_candidates
+            .iter()
+            .cloned()
+            .chain(manifest_entries)
+        {
+            let path = member.root.join(&candidate);
+            if path.is_file() {
+                return Ok(path);
+            }
+        }
+        Err(FlyError::from(format!(
+            "workspace package {} has no entry point (tried {} and package.json module/main)",
+            member.name,
+            self.entry_candidates.join(", ")
+        )))
+    }
+
+    /**
+     * For a file watcher: the member package containing `path`, if any, and every module that
+     * imported it. Those importers are forgotten; they're recorded again when re-imported.
+     */
+    pub fn invalidate_path(&self, path: &Path) -> Option<(String, Vec<String>)> {
+        let member = self
+            .members
+            .values()
+            .filter(|m| path.starts_with(&m.root))
+            .max_by_key(|m| m.root.as_os_str().len())?;
+        let importers = self
+            .importers
+            .lock()
+            .unwrap()
+            .remove(&member.name)
+            .unwrap_or_default();
+        Some((member.name.clone(), importers.into_iter().collect()))
+    }
+
+    /**
+     * Splits `@scope/name/sub/path` or `name/sub/path` into the member and the rest.
+     */
+    fn split_specifier<'a>(&self, specifier: &'a str) -> (&'a str, &'a str) {
+        let segments = if specifier.starts_with('@') { 2 } else { 1 };
+        let mut end = specifier.len();
+        let mut seen = 0;
+        for (i, c) in specifier.char_indices() {
+            if c == '/' {
+                seen += 1;
+                if seen == segments {
+                    end = i;
+                    break;
+                }
+            }
+        }
+        let rest = if end < specifier.len() {
+            &specifier[end + 1..]
+        } else {
+            ""
+        };
+        (&specifier[..end], rest)
+    }
+}
+
+impl ModuleResolver for WorkspaceResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        let referer_origin_url = match referer_info {
+            Some(v) => v.origin_url,
+            None => "<entry point>".to_string(),
+        };
+        let prefix = format!("{}:", WORKSPACE_PROTOCOL);
+        let specifier = module_specifier.trim_start_matches(prefix.as_str());
+        let (name, rest) = self.split_specifier(specifier);
+        let member = match self.members.get(name) {
+            Some(member) => member,
+            None => {
+                let known: Vec<&str> = self.members.keys().map(|k| k.as_str()).collect();
+                return Err(FlyError::from(format!(
+                    "Cannot import {} from {}: unknown workspace package {} (workspace members: {})",
+                    module_specifier,
+                    referer_origin_url,
+                    name,
+                    if known.is_empty() {
+                        "none".to_string()
+                    } else {
+                        known.join(", ")
+                    }
+                )));
+            }
+        };
+        let path = if rest.is_empty() {
+            self.entry_point(member)?
+        } else {
+            let path = member.root.join(rest);
+            if path
+                .components()
+                .any(|c| c == std::path::Component::ParentDir)
+            {
+                return Err(FlyError::from(format!(
+                    "Cannot import {} from {}: paths may not leave workspace package {}",
+                    module_specifier, referer_origin_url, member.name
+                )));
+            }
+            if !path.is_file() {
+                return Err(FlyError::from(format!(
+                    "Cannot import {} from {}: {} does not exist in workspace package {}",
+                    module_specifier, referer_origin_url, rest, member.name
+                )));
+            }
+            path
+        };
+
+        self.importers
+            .lock()
+            .unwrap()
+            .entry(member.name.clone())
+            .or_insert_with(BTreeSet::new)
+            .insert(referer_origin_url);
+
+        // a file url, so the member's relative imports go through the disk resolver
+        let origin_url = file_origin_url(&path)?;
+        let source_loader = self
+            .data_formats
+            .loader_for(&origin_url, Box::new(LocalDiskRawLoader::new(path, None)));
+        Ok(ModuleSourceData {
+            origin_url,
+            source_loader,
+        })
+    }
+    fn get_protocol(&self) -> String {
+        return WORKSPACE_PROTOCOL.to_string();
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("workspace", WORKSPACE_PROTOCOL)
+            .named(&self.name())
+            .with("root", &self.root.display().to_string())
+            .with(
+                "members",
+                &self.members.keys().cloned().collect::<Vec<_>>().join(", "),
+            )
+    }
+}
+
+fn read_manifest(path: &Path) -> FlyResult<serde_json::Value> {
+    let source = std::fs::read_to_string(path)?;
+    serde_json::from_str(&source)
+        .map_err(|e| FlyError::from(format!("invalid {}: {}", path.display(), e)))
+}
+
+/**
+ * Member glob patterns from the root package.json (`workspaces: [..]` or
+ * `workspaces: { packages: [..] }`) or pnpm-workspace.yaml.
+ */
+fn member_patterns(root: &Path) -> FlyResult<Option<Vec<String>>> {
+    let package_json = root.join("package.json");
+    if package_json.is_file() {
+        let manifest = read_manifest(&package_json)?;
+        let workspaces = manifest.get("workspaces").map(|w| match w.get("packages") {
+            Some(packages) => packages,
+            None => w,
+        });
+        if let Some(serde_json::Value::Array(patterns)) = workspaces {
+            return Ok(Some(
+                patterns
+                    .iter()
+                    .filter_map(|p| p.as_str())
+                    .map(|p| p.to_string())
+                    .collect(),
+            ));
+        }
+    }
+    let pnpm_workspace = root.join("pnpm-workspace.yaml");
+    if pnpm_workspace.is_file() {
+        let source = std::fs::read_to_string(&pnpm_workspace)?;
+        let label = pnpm_workspace.display().to_string();
+        let documents = load_yaml(&label, &source, YamlLimits::default())?;
+        if let Some(packages) = documents.get(0).and_then(|d| d["packages"].as_vec()) {
+            return Ok(Some(
+                packages
+                    .iter()
+                    .filter_map(|p| p.as_str())
+                    .map(|p| p.to_string())
+                    .collect(),
+            ));
+        }
+    }
+    Ok(None)
+}
+
+fn member_manifests(root: &Path) -> FlyResult<Vec<PathBuf>> {
+    let patterns: Vec<String> = match member_patterns(root)? {
+        Some(patterns) => patterns
+            .iter()
+            .map(|p| {
+                let p = p.trim_start_matches("./").trim_end_matches('/');
+                if p.starts_with('!') {
+                    p.to_string()
+                } else {
+                    format!("{}/package.json", p)
+                }
+            })
+            .chain(std::iter::once("!**/node_modules/**".to_string()))
+            .collect(),
+        None => vec![
+            "**/package.json".to_string(),
+            "!package.json".to_string(),
+            "!**/node_modules/**".to_string(),
+        ],
+    };
+    let walker = globwalk::GlobWalkerBuilder::from_patterns(root, &patterns)
+        .build()
+        .map_err(|e| FlyError::from(format!("invalid workspace pattern: {}", e)))?;
+    let mut manifests: Vec<PathBuf> = walker
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().to_path_buf())
+        .filter(|path| path.is_file())
+        .collect();
+    manifests.sort();
+    Ok(manifests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn monorepo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            "package.json",
+            r#"{"private": true, "workspaces": ["packages/*"]}"#,
+        );
+        write(
+            root,
+            "packages/logging/package.json",
+            r#"{"name": "@ourco/logging", "main": "dist/index.js"}"#,
+        );
+        write(root, "packages/logging/dist/index.js", "");
+        write(root, "packages/logging/src/index.ts", "");
+        write(root, "packages/logging/src/format.ts", "");
+        write(
+            root,
+            "packages/http/package.json",
+            r#"{"name": "http-utils", "main": "lib/main.js"}"#,
+        );
+        write(root, "packages/http/lib/main.js", "");
+        write(root, "tools/package.json", r#"{"name": "not-a-member"}"#);
+        dir
+    }
+
+    #[test]
+    fn test_discovers_members_from_manifest() {
+        let dir = monorepo();
+        let resolver = WorkspaceResolver::discover(dir.path()).unwrap();
+        let names: Vec<&str> = resolver.members().map(|m| m.name.as_str()).collect();
+        assert_eq!(vec!["@ourco/logging", "http-utils"], names);
+    }
+
+    #[test]
+    fn test_resolves_source_entry_point() {
+        let dir = monorepo();
+        let resolver = WorkspaceResolver::discover(dir.path()).unwrap();
+        let module = resolver
+            .resolve_module("workspace:@ourco/logging", None)
+            .unwrap();
+        assert!(module
+            .origin_url
+            .ends_with("/packages/logging/src/index.ts"));
+        let module = resolver
+            .resolve_module("workspace:@ourco/logging/src/format.ts", None)
+            .unwrap();
+        assert!(module
+            .origin_url
+            .ends_with("/packages/logging/src/format.ts"));
+        let module = resolver
+            .resolve_module("workspace:http-utils", None)
+            .unwrap();
+        assert!(module.origin_url.ends_with("/packages/http/lib/main.js"));
+
+        let built = WorkspaceResolver::discover(dir.path())
+            .unwrap()
+            .with_entry_candidates(vec![]);
+        let module = built
+            .resolve_module("workspace:@ourco/logging", None)
+            .unwrap();
+        assert!(module
+            .origin_url
+            .ends_with("/packages/logging/dist/index.js"));
+    }
+
+    #[test]
+    fn test_unknown_package_lists_members() {
+        let dir = monorepo();
+        let resolver = WorkspaceResolver::discover(dir.path()).unwrap();
+        let err = resolver
+            .resolve_module("workspace:@ourco/metrics", None)
+            .err()
+            .unwrap();
+        assert!(
+            format!("{}", err).contains("workspace members: @ourco/logging, http-utils"),
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_invalidating_a_member_returns_its_importers() {
+        let dir = monorepo();
+        let resolver = WorkspaceResolver::discover(dir.path()).unwrap();
+        let referer = RefererInfo {
+            origin_url: "file:///app/index.ts".to_string(),
+            app_id: None,
+            deadline: None,
+            is_wasm: None,
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+        };
+        resolver
+            .resolve_module("workspace:@ourco/logging", Some(referer))
+            .unwrap();
+        let edited = resolver.root().join("packages/logging/src/format.ts");
+        assert_eq!(
+            Some((
+                "@ourco/logging".to_string(),
+                vec!["file:///app/index.ts".to_string()]
+            )),
+            resolver.invalidate_path(&edited)
+        );
+        assert_eq!(
+            Some(("@ourco/logging".to_string(), vec![])),
+            resolver.invalidate_path(&edited)
+        );
+        assert_eq!(None, resolver.invalidate_path(Path::new("/elsewhere/a.ts")));
+    }
+
+    #[test]
+    fn test_pnpm_workspace_is_parsed_within_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "package.json", r#"{"private": true}"#);
+        write(
+            root,
+            "pnpm-workspace.yaml",
+            "a: &a [x, x, x, x, x, x, x, x, x, x]\n\
+             b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a, *a]\n\
+             c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b, *b]\n\
+             d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c, *c]\n\
+             e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d, *d]\n\
+             packages: [packages/*]\n",
+        );
+        let err = WorkspaceResolver::discover(root).err().unwrap();
+        assert!(
+            format!("{}", err).contains("expands to more than"),
+            format!("{}", err)
+        );
+
+        write(root, "pnpm-workspace.yaml", "packages: [packages/*]\n");
+        write(
+            root,
+            "packages/logging/package.json",
+            r#"{"name": "logging"}"#,
+        );
+        let resolver = WorkspaceResolver::discover(root).unwrap();
+        let names: Vec<&str> = resolver.members().map(|m| m.name.as_str()).collect();
+        assert_eq!(vec!["logging"], names);
+    }
+}
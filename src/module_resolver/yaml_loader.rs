@@ -0,0 +1,358 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+
+use yaml_rust::parser::{MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+use yaml_rust::{Event, Yaml, YamlLoader as YamlDocumentLoader};
+
+use super::data_module::{data_to_module, parse_error, DataValue, MAX_DATA_DEPTH};
+use super::{LoadedSourceCode, ModuleKind, SourceLoader};
+
+/**
+ * Caps applied before a YAML file is materialized. Aliases are expanded by copying the
+ * anchored node, so `max_nodes` counts nodes after expansion; that's what stops a
+ * billion-laughs file from exhausting memory. `max_depth` bounds how deeply sequences and
+ * mappings nest, and so how deep converting them recurses; past `MAX_DATA_DEPTH` nothing is
+ * converted anyway.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct YamlLimits {
+    pub max_documents: usize,
+    pub max_aliases: usize,
+    pub max_nodes: usize,
+    pub max_depth: usize,
+}
+
+impl Default for YamlLimits {
+    fn default() -> Self {
+        Self {
+            max_documents: 1,
+            max_aliases: 1_000,
+            max_nodes: 100_000,
+            max_depth: MAX_DATA_DEPTH,
+        }
+    }
+}
+
+/**
+ * Turns a YAML file into a module the same way `TomlLoader` does for TOML. Files holding
+ * more than one document are rejected; there's no single obvious default export for them.
+ */
+pub struct YamlLoader {
+    pub origin_url: String,
+    pub inner: Box<SourceLoader>,
+    pub limits: YamlLimits,
+}
+
+impl YamlLoader {
+    pub fn new(origin_url: &str, inner: Box<SourceLoader>, limits: YamlLimits) -> Self {
+        Self {
+            origin_url: origin_url.to_string(),
+            inner,
+            limits,
+        }
+    }
+}
+
+impl SourceLoader for YamlLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let raw = self.inner.load_source()?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::Json,
+            source_map: None,
+            source: yaml_to_module(&self.origin_url, &raw.source, self.limits)?.into(),
+            response: raw.response,
+            provenance: raw.provenance,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+/**
+ * Walks the event stream tracking how big every node would be once aliases are expanded.
+ */
+struct LimitChecker {
+    limits: YamlLimits,
+    documents: usize,
+    aliases: usize,
+    // expanded size of every anchored node, by anchor id
+    anchor_sizes: HashMap<usize, usize>,
+    // open collections: (anchor id, expanded size so far)
+    open: Vec<(usize, usize)>,
+    total: usize,
+    violation: Option<(Marker, String)>,
+}
+
+impl LimitChecker {
+    fn new(limits: YamlLimits) -> Self {
+        Self {
+            limits,
+            documents: 0,
+            aliases: 0,
+            anchor_sizes: HashMap::new(),
+            open: vec![],
+            total: 0,
+            violation: None,
+        }
+    }
+
+    fn add(&mut self, size: usize, mark: Marker) {
+        let size_so_far = match self.open.last_mut() {
+            Some(parent) => {
+                parent.1 = parent.1.saturating_add(size);
+                parent.1
+            }
+            None => {
+                self.total = self.total.saturating_add(size);
+                self.total
+            }
+        };
+        if size_so_far > self.limits.max_nodes {
+            self.violate(
+                mark,
+                format!(
+                    "expands to more than {} nodes (check anchors and aliases)",
+                    self.limits.max_nodes
+                ),
+            );
+        }
+    }
+
+    fn violate(&mut self, mark: Marker, msg: String) {
+        if self.violation.is_none() {
+            self.violation = Some((mark, msg));
+        }
+    }
+}
+
+impl MarkedEventReceiver for LimitChecker {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        if self.violation.is_some() {
+            return;
+        }
+        match ev {
+            Event::DocumentStart => {
+                self.documents += 1;
+                if self.documents > self.limits.max_documents {
+                    let msg = if self.limits.max_documents == 1 {
+                        "multiple documents in one file are not supported".to_string()
+                    } else {
+                        format!("more than {} documents", self.limits.max_documents)
+                    };
+                    self.violate(mark, msg);
+                }
+            }
+            Event::Alias(id) => {
+                self.aliases += 1;
+                if self.aliases > self.limits.max_aliases {
+                    let msg = format!("more than {} aliases", self.limits.max_aliases);
+                    self.violate(mark, msg);
+                    return;
+                }
+                let size = self.anchor_sizes.get(&id).cloned().unwrap_or(1);
+                self.add(size, mark);
+            }
+            Event::Scalar(_, _, anchor, _) => {
+                if anchor > 0 {
+                    self.anchor_sizes.insert(anchor, 1);
+                }
+                self.add(1, mark);
+            }
+            Event::SequenceStart(anchor) | Event::MappingStart(anchor) => {
+                if self.open.len() == self.limits.max_depth {
+                    let msg = format!("nested deeper than {} levels", self.limits.max_depth);
+                    self.violate(mark, msg);
+                    return;
+                }
+                self.open.push((anchor, 1));
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some((anchor, size)) = self.open.pop() {
+                    if anchor > 0 {
+                        self.anchor_sizes.insert(anchor, size);
+                    }
+                    self.add(size, mark);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn yaml_to_module(
+    origin_url: &str,
+    yaml_source: &str,
+    limits: YamlLimits,
+) -> FlyResult<String> {
+    let document = match load_yaml(origin_url, yaml_source, limits)?.pop() {
+        Some(document) => to_data(document, origin_url)?,
+        None => DataValue::Null,
+    };
+    data_to_module(&document)
+}
+
+/**
+ * Parses a YAML file into its documents, refusing it if it's past `limits`. Everything that
+ * reads YAML goes through here, not just modules: manifests are as untrusted as imports.
+ */
+pub(super) fn load_yaml(
+    origin_url: &str,
+    yaml_source: &str,
+    limits: YamlLimits,
+) -> FlyResult<Vec<Yaml>> {
+    let mut checker = LimitChecker::new(limits);
+    if let Err(e) = Parser::new(yaml_source.chars()).load(&mut checker, true) {
+        let mark = e.marker();
+        return Err(parse_error(
+            origin_url,
+            "YAML",
+            mark.line(),
+            mark.col() + 1,
+            &format!("{}", e),
+        ));
+    }
+    if let Some((mark, msg)) = checker.violation {
+        return Err(parse_error(
+            origin_url,
+            "YAML",
+            mark.line(),
+            mark.col() + 1,
+            &msg,
+        ));
+    }
+
+    YamlDocumentLoader::load_from_str(yaml_source).map_err(|e| {
+        let mark = e.marker();
+        parse_error(
+            origin_url,
+            "YAML",
+            mark.line(),
+            mark.col() + 1,
+            &format!("{}", e),
+        )
+    })
+}
+
+fn to_data(value: Yaml, origin_url: &str) -> FlyResult<DataValue> {
+    Ok(match value {
+        Yaml::Real(ref r) => match value.as_f64() {
+            Some(f) => DataValue::Float(f),
+            None => DataValue::String(r.clone()),
+        },
+        Yaml::Integer(i) => DataValue::Integer(i),
+        Yaml::String(s) => DataValue::String(s),
+        Yaml::Boolean(b) => DataValue::Bool(b),
+        Yaml::Null => DataValue::Null,
+        Yaml::Array(items) => {
+            let mut elements = Vec::with_capacity(items.len());
+            for item in items {
+                elements.push(to_data(item, origin_url)?);
+            }
+            DataValue::Array(elements)
+        }
+        Yaml::Hash(hash) => {
+            let mut members = Vec::with_capacity(hash.len());
+            for (key, member) in hash {
+                let key = match key {
+                    Yaml::String(s) | Yaml::Real(s) => s,
+                    Yaml::Integer(i) => i.to_string(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    Yaml::Null => "null".to_string(),
+                    _ => {
+                        return Err(FlyError::from(format!(
+                            "{}: invalid YAML: only scalar mapping keys are supported",
+                            origin_url
+                        )))
+                    }
+                };
+                members.push((key, to_data(member, origin_url)?));
+            }
+            DataValue::Object(members)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => {
+            return Err(FlyError::from(format!(
+                "{}: invalid YAML: unresolved alias or bad value",
+                origin_url
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(source: &str) -> FlyResult<String> {
+        yaml_to_module("file:///app/config.yaml", source, YamlLimits::default())
+    }
+
+    #[test]
+    fn test_default_and_named_exports() {
+        let module = module("name: app\nserver:\n  port: 8080\nratio: .5\n").unwrap();
+        assert!(module.contains("\"server\":{\"port\":8080}"));
+        assert!(module.contains("\"ratio\":0.5"));
+        assert!(module.contains("export const server = __data.server;"));
+    }
+
+    #[test]
+    fn test_aliases_are_expanded() {
+        let module = module("base: &base\n  a: 1\ncopy: *base\n").unwrap();
+        assert!(module.contains("\"copy\":{\"a\":1}"));
+    }
+
+    #[test]
+    fn test_billion_laughs_is_rejected() {
+        let source = "a: &a [x, x, x, x, x, x, x, x, x, x]\n\
+                      b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a, *a]\n\
+                      c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b, *b]\n\
+                      d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c, *c]\n\
+                      e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d, *d]\n";
+        let err = module(source).err().unwrap();
+        assert!(format!("{}", err).contains("expands to more than"));
+
+        let limits = YamlLimits {
+            max_aliases: 5,
+            ..YamlLimits::default()
+        };
+        let err = yaml_to_module("file:///app/a.yaml", source, limits)
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("more than 5 aliases"));
+    }
+
+    #[test]
+    fn test_deep_nesting_is_rejected() {
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        let err = module(&deep).err().unwrap();
+        let msg = format!("{}", err);
+        assert!(msg.starts_with("file:///app/config.yaml:1:129:"), msg);
+        assert!(msg.ends_with("nested deeper than 128 levels"), msg);
+
+        let limits = YamlLimits {
+            max_depth: 2,
+            ..YamlLimits::default()
+        };
+        assert!(yaml_to_module("file:///app/a.yaml", "a: {b: 1}\n", limits).is_ok());
+        let err = yaml_to_module("file:///app/a.yaml", "a:\n  b:\n    c: 1\n", limits)
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("nested deeper than 2 levels"));
+    }
+
+    #[test]
+    fn test_multiple_documents_are_rejected() {
+        let err = module("a: 1\n---\nb: 2\n").err().unwrap();
+        let msg = format!("{}", err);
+        assert!(msg.contains("multiple documents"), msg);
+        assert!(msg.starts_with("file:///app/config.yaml:2:"), msg);
+    }
+
+    #[test]
+    fn test_parse_error_has_position() {
+        let err = module("a: 1\nb: [1, 2\n").err().unwrap();
+        assert!(format!("{}", err).starts_with("file:///app/config.yaml:"));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::errors::*;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use url::percent_encoding::percent_decode;
+
+use super::archive::{normalize_entry_name, PROBE_SUFFIXES};
+use super::integrity::{parse_digest, sri};
+use super::{
+    is_bare_specifier, parse_url, wasm_source, ArchiveBundle, ArchiveLimits, DataFormats,
+    ImportMap, LoadedSourceCode, ModuleKind, ModuleResolver, ModuleSourceData, RefererInfo,
+    ResolverDescription, SourceLoader,
+};
+
+pub const ZIP_BUNDLE_PROTOCOL: &str = "bundle";
+pub const ZIP_BUNDLE_MANIFEST: &str = "manifest.json";
+
+/**
+ * The `manifest.json` at the root of a deployment bundle:
+ *
+ * ```json
+ * {
+ *   "entry": "src/main.ts",
+ *   "importMap": { "imports": { "utils": "./src/utils/index.ts" } },
+ *   "integrity": { "src/main.ts": "sha256-...", "src/utils/index.ts": "sha256-..." }
+ * }
+ * ```
+ *
+ * Paths are relative to the bundle's root, as are the import map's addresses. Hashes are
+ * sha256, in hex or as SRI strings, of each member as it's stored.
+ */
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub entry: String,
+    #[serde(default)]
+    pub import_map: Option<serde_json::Value>,
+    #[serde(default)]
+    pub integrity: HashMap<String, String>,
+}
+
+/**
+ * Serves an app packaged as a zip with a `BundleManifest`, without unpacking it: its members
+ * are `bundle://<app>/<path>`, its import map applies to bare specifiers imported from
+ * inside the bundle, and every member is checked against its manifest hash when it's
+ * loaded. A member the manifest has no hash for doesn't load.
+ *
+ * Errors tell the failures apart: a missing or unreadable manifest is `InvalidData`, a
+ * member that isn't in the bundle is `NotFound`, and one that doesn't match its hash is
+ * `IntegrityMismatch`.
+ */
+pub struct ZipBundleResolver {
+    app: String,
+    root_url: String,
+    entry: String,
+    bundle: Arc<ArchiveBundle>,
+    import_map: ImportMap,
+    digests: Arc<HashMap<String, Vec<u8>>>,
+    data_formats: DataFormats,
+}
+
+fn bundle_error(kind: ErrorKind, bundle: &ArchiveBundle, msg: impl std::fmt::Display) -> FlyError {
+    new(kind, format!("bundle {}: {}", bundle.name(), msg))
+}
+
+impl ZipBundleResolver {
+    /** The bundle at `path` as `app`, within the default `ArchiveLimits`. */
+    pub fn open(path: &Path, app: &str) -> FlyResult<Self> {
+        Self::new(ArchiveBundle::open(path, &ArchiveLimits::default())?, app)
+    }
+
+    pub fn new(bundle: ArchiveBundle, app: &str) -> FlyResult<Self> {
+        let root_url = format!("{}://{}/", ZIP_BUNDLE_PROTOCOL, app);
+        match url::Url::parse(&root_url) {
+            Ok(ref url) if url.host_str() == Some(app) && !app.is_empty() => {}
+            _ => {
+                return Err(new(
+                    ErrorKind::InvalidInput,
+                    format!("{} can't name a bundle: it must be a valid host name", app),
+                ))
+            }
+        }
+        let manifest = match bundle.read(ZIP_BUNDLE_MANIFEST)? {
+            Some(manifest) => manifest,
+            None => {
+                return Err(bundle_error(
+                    ErrorKind::InvalidData,
+                    &bundle,
+                    format!("has no {}", ZIP_BUNDLE_MANIFEST),
+                ))
+            }
+        };
+        let manifest: BundleManifest = serde_json::from_slice(&manifest).map_err(|e| {
+            bundle_error(
+                ErrorKind::InvalidData,
+                &bundle,
+                format!("invalid {}: {}", ZIP_BUNDLE_MANIFEST, e),
+            )
+        })?;
+        let member = |path: &str| {
+            let name = normalize_entry_name(path).map_err(|why| {
+                bundle_error(
+                    ErrorKind::InvalidData,
+                    &bundle,
+                    format!("manifest path {} {}", path, why),
+                )
+            })?;
+            if !bundle.contains(&name) {
+                return Err(bundle_error(
+                    ErrorKind::NotFound,
+                    &bundle,
+                    format!("the manifest lists {}, which isn't in the bundle", name),
+                ));
+            }
+            Ok(name)
+        };
+        let entry = member(&manifest.entry)?;
+        let mut digests = HashMap::new();
+        for (path, hash) in &manifest.integrity {
+            let digest = parse_digest(hash).map_err(|e| {
+                bundle_error(
+                    ErrorKind::InvalidData,
+                    &bundle,
+                    format!("{} for {}", e, path),
+                )
+            })?;
+            digests.insert(member(path)?, digest);
+        }
+        let import_map = match manifest.import_map {
+            Some(ref import_map) => ImportMap::from_json(&import_map.to_string(), &root_url)?,
+            None => ImportMap::new(),
+        };
+        Ok(Self {
+            app: app.to_string(),
+            root_url,
+            entry,
+            bundle: Arc::new(bundle),
+            import_map,
+            digests: Arc::new(digests),
+            data_formats: DataFormats::default(),
+        })
+    }
+
+    pub fn with_data_formats(mut self, data_formats: DataFormats) -> Self {
+        self.data_formats = data_formats;
+        self
+    }
+
+    /** The url of the manifest's entry module, to boot the app with. */
+    pub fn entry_point_url(&self) -> String {
+        self.member_url(&self.entry)
+    }
+
+    fn member_url(&self, name: &str) -> String {
+        // joined, so it's encoded the way referers joined to it will be
+        match url::Url::parse(&self.root_url).and_then(|root| root.join(name)) {
+            Ok(url) => url.into_string(),
+            Err(_) => format!("{}{}", self.root_url, name),
+        }
+    }
+
+    /** Whether `url` is inside this bundle. */
+    fn contains_url(&self, url: &url::Url) -> bool {
+        url.scheme() == ZIP_BUNDLE_PROTOCOL && url.host_str() == Some(&self.app)
+    }
+}
+
+impl ModuleResolver for ZipBundleResolver {
+    fn resolve_module(
+        &self,
+        module_specifier: &str,
+        referer_info: Option<RefererInfo>,
+    ) -> FlyResult<ModuleSourceData> {
+        // the import map is the bundle's own, for its own modules
+        let referer_origin_url = match referer_info {
+            Some(ref info) if info.origin_url.starts_with(&self.root_url) => {
+                info.origin_url.clone()
+            }
+            _ => self.root_url.clone(),
+        };
+        let url = match self
+            .import_map
+            .resolve(module_specifier, &referer_origin_url)?
+        {
+            Some(mapped) => url::Url::parse(&mapped)?,
+            None if is_bare_specifier(module_specifier) => {
+                return Err(bundle_error(
+                    ErrorKind::NotFound,
+                    &self.bundle,
+                    format!("its import map doesn't map {}", module_specifier),
+                ))
+            }
+            None => parse_url(module_specifier, &referer_origin_url)?,
+        };
+        if !self.contains_url(&url) {
+            return Err(FlyError::from(format!(
+                "{} is outside bundle {}",
+                url, self.root_url
+            )));
+        }
+        let path = percent_decode(url.path().trim_start_matches('/').as_bytes())
+            .decode_utf8_lossy()
+            .into_owned();
+        let name = PROBE_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{}{}", path.trim_end_matches('/'), suffix))
+            .find(|candidate| self.bundle.contains(candidate) && candidate != ZIP_BUNDLE_MANIFEST)
+            .ok_or_else(|| {
+                bundle_error(
+                    ErrorKind::NotFound,
+                    &self.bundle,
+                    format!("no member {}, imported from {}", path, referer_origin_url),
+                )
+            })?;
+        let origin_url = self.member_url(&name);
+        let loader = ZipMemberLoader {
+            bundle: self.bundle.clone(),
+            digests: self.digests.clone(),
+            name,
+        };
+        Ok(ModuleSourceData {
+            source_loader: self.data_formats.loader_for(&origin_url, Box::new(loader)),
+            origin_url,
+        })
+    }
+    fn get_protocol(&self) -> String {
+        ZIP_BUNDLE_PROTOCOL.to_string()
+    }
+    /** The bare specifiers the bundle's import map maps into the bundle. */
+    fn handles_bare_specifier(&self, specifier: &str) -> bool {
+        self.import_map.mappings().any(|(key, url)| {
+            let covers = key == specifier || (key.ends_with('/') && specifier.starts_with(key));
+            covers && self.contains_url(url)
+        })
+    }
+    fn describe(&self) -> ResolverDescription {
+        ResolverDescription::new("zip_bundle", ZIP_BUNDLE_PROTOCOL)
+            .named(&self.name())
+            .with("bundle", self.bundle.name())
+            .with("app", &self.app)
+            .with("entry", &self.entry)
+    }
+}
+
+/** A member of a `ZipBundleResolver`'s bundle, checked against its manifest hash. */
+pub struct ZipMemberLoader {
+    bundle: Arc<ArchiveBundle>,
+    digests: Arc<HashMap<String, Vec<u8>>>,
+    name: String,
+}
+
+impl SourceLoader for ZipMemberLoader {
+    fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+        let bytes = self.bundle.read(&self.name)?.ok_or_else(|| {
+            bundle_error(
+                ErrorKind::NotFound,
+                &self.bundle,
+                format!("no member {}", self.name),
+            )
+        })?;
+        let digest = Sha256::digest(&bytes);
+        match self.digests.get(&self.name) {
+            Some(expected) if expected[..] == digest[..] => {}
+            Some(expected) => {
+                return Err(bundle_error(
+                    ErrorKind::IntegrityMismatch,
+                    &self.bundle,
+                    format!(
+                        "{} is {}, but the manifest says {}",
+                        self.name,
+                        sri(&digest),
+                        sri(expected)
+                    ),
+                ))
+            }
+            None => {
+                return Err(bundle_error(
+                    ErrorKind::IntegrityMismatch,
+                    &self.bundle,
+                    format!("the manifest has no hash for {}", self.name),
+                ))
+            }
+        }
+        if self.name.ends_with(".wasm") {
+            return Ok(wasm_source(&bytes));
+        }
+        let source = String::from_utf8(bytes).map_err(|_| {
+            bundle_error(
+                ErrorKind::InvalidData,
+                &self.bundle,
+                format!("{} is not utf-8 text", self.name),
+            )
+        })?;
+        Ok(LoadedSourceCode {
+            kind: ModuleKind::JavaScript,
+            source_map: None,
+            source: source.into(),
+            response: None,
+            provenance: None,
+            code_cache: None,
+            compiled_wasm: None,
+            wasm: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_resolver::{ModuleResolverManager, StandardModuleResolverManager};
+
+    fn push_u16(out: &mut Vec<u8>, v: u16) {
+        out.extend_from_slice(&[v as u8, (v >> 8) as u8]);
+    }
+
+    fn push_u32(out: &mut Vec<u8>, v: u32) {
+        push_u16(out, v as u16);
+        push_u16(out, (v >> 16) as u16);
+    }
+
+    // stored, not deflated: the archive tests cover inflating
+    fn zip(members: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = vec![];
+        let mut central = vec![];
+        for (name, contents) in members {
+            let offset = zip.len() as u32;
+            push_u32(&mut zip, 0x0403_4b50);
+            zip.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            push_u32(&mut zip, contents.len() as u32);
+            push_u32(&mut zip, contents.len() as u32);
+            push_u16(&mut zip, name.len() as u16);
+            push_u16(&mut zip, 0);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(contents.as_bytes());
+
+            push_u32(&mut central, 0x0201_4b50);
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            push_u32(&mut central, contents.len() as u32);
+            push_u32(&mut central, contents.len() as u32);
+            push_u16(&mut central, name.len() as u16);
+            central.extend_from_slice(&[0; 12]);
+            push_u32(&mut central, offset);
+            central.extend_from_slice(name.as_bytes());
+        }
+        let central_offset = zip.len() as u32;
+        zip.extend_from_slice(&central);
+        push_u32(&mut zip, 0x0605_4b50);
+        zip.extend_from_slice(&[0, 0, 0, 0]);
+        push_u16(&mut zip, members.len() as u16);
+        push_u16(&mut zip, members.len() as u16);
+        push_u32(&mut zip, central.len() as u32);
+        push_u32(&mut zip, central_offset);
+        push_u16(&mut zip, 0);
+        zip
+    }
+
+    const MAIN: &str = "import { greet } from 'greeting';\nexport default greet();\n";
+    const GREETING: &str = "import name from './name.js';\nexport const greet = () => name;\n";
+    const NAME: &str = "export default 'bundle';\n";
+
+    fn hash(contents: &str) -> String {
+        sri(&Sha256::digest(contents.as_bytes()))
+    }
+
+    /** A three module app, and `lib/broken.js`, which isn't what the manifest hashed. */
+    fn app_bundle() -> ArchiveBundle {
+        let manifest = serde_json::json!({
+            "entry": "src/main.js",
+            "importMap": { "imports": { "greeting": "./lib/greeting.js" } },
+            "integrity": {
+                "src/main.js": hash(MAIN),
+                "lib/greeting.js": hash(GREETING),
+                "lib/name.js": hash(NAME),
+                "lib/broken.js": hash("export default 1;"),
+            },
+        });
+        let bundle = zip(&[
+            ("manifest.json", &manifest.to_string()),
+            ("src/main.js", MAIN),
+            ("lib/greeting.js", GREETING),
+            ("lib/name.js", NAME),
+            ("lib/broken.js", "export default 2;"),
+            ("lib/unlisted.js", "export default 3;"),
+        ]);
+        ArchiveBundle::from_bytes("app.zip", bundle, &ArchiveLimits::default()).unwrap()
+    }
+
+    #[test]
+    fn test_boots_an_app_from_its_manifest() {
+        let resolver = ZipBundleResolver::new(app_bundle(), "shop").unwrap();
+        assert_eq!("bundle://shop/src/main.js", resolver.entry_point_url());
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver) as Box<ModuleResolver>],
+            None,
+        );
+
+        let main = manager
+            .resolve_blocking("bundle://shop/src/main.js".to_string(), None)
+            .unwrap();
+        assert_eq!(MAIN, &*main.loaded_source.source);
+        let referer = |module: &str| {
+            Some(RefererInfo {
+                origin_url: module.to_string(),
+                is_wasm: Some(false),
+                source_code: None,
+                identifier_hash: None,
+                line: None,
+                column: None,
+                app_id: None,
+                deadline: None,
+            })
+        };
+        // a bare specifier through the bundle's import map
+        let greeting = manager
+            .resolve_blocking("greeting".to_string(), referer(&main.origin_url))
+            .unwrap();
+        assert_eq!("bundle://shop/lib/greeting.js", greeting.origin_url);
+        let name = manager
+            .resolve_blocking("./name".to_string(), referer(&greeting.origin_url))
+            .unwrap();
+        assert_eq!("bundle://shop/lib/name.js", name.origin_url);
+        assert_eq!(NAME, &*name.loaded_source.source);
+    }
+
+    #[test]
+    fn test_failures_are_told_apart() {
+        let resolver = ZipBundleResolver::new(app_bundle(), "shop").unwrap();
+        let load_err = |specifier: &str| {
+            resolver
+                .resolve_module(specifier, None)
+                .and_then(|data| data.source_loader.load_source())
+                .err()
+                .unwrap()
+        };
+        let broken = load_err("bundle://shop/lib/broken.js");
+        assert_eq!(ErrorKind::IntegrityMismatch, broken.kind());
+        assert!(format!("{}", broken).contains("lib/broken.js is sha256-"));
+        assert_eq!(
+            ErrorKind::IntegrityMismatch,
+            load_err("bundle://shop/lib/unlisted.js").kind()
+        );
+        assert_eq!(
+            ErrorKind::NotFound,
+            load_err("bundle://shop/lib/missing.js").kind()
+        );
+        assert_eq!(ErrorKind::NotFound, load_err("lodash").kind());
+        // another app's modules aren't this bundle's
+        assert!(load_err("bundle://admin/src/main.js").kind() != ErrorKind::NotFound);
+
+        let no_manifest = zip(&[("src/main.js", MAIN)]);
+        let no_manifest =
+            ArchiveBundle::from_bytes("app.zip", no_manifest, &ArchiveLimits::default()).unwrap();
+        assert_eq!(
+            ErrorKind::InvalidData,
+            ZipBundleResolver::new(no_manifest, "shop")
+                .err()
+                .unwrap()
+                .kind()
+        );
+        let missing_entry = zip(&[("manifest.json", r#"{"entry": "src/main.js"}"#)]);
+        let missing_entry =
+            ArchiveBundle::from_bytes("app.zip", missing_entry, &ArchiveLimits::default()).unwrap();
+        assert_eq!(
+            ErrorKind::NotFound,
+            ZipBundleResolver::new(missing_entry, "shop")
+                .err()
+                .unwrap()
+                .kind()
+        );
+    }
+}
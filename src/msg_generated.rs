@@ -741,11 +741,28 @@ pub enum Any {
   AcmeGetChallenge = 40,
   AcmeGetChallengeReady = 41,
   OsExit = 42,
+  ModuleCached = 43,
+  ModuleCachedResp = 44,
+  RuntimeMetrics = 45,
+  RuntimeMetricsReady = 46,
+  RegisterModuleResolver = 47,
+  GuestResolve = 48,
+  GuestResolveResult = 49,
+  LoadModuleStartResp = 50,
+  LoadModuleChunk = 51,
+  LoadModuleChunkResp = 52,
+  ClearModuleCache = 53,
+  ClearModuleCacheResp = 54,
+  ListLoadedModules = 55,
+  ListLoadedModulesResp = 56,
+  ResolveModule = 57,
+  ResolveModuleResp = 58,
+  StoreCompiledWasm = 59,
 
 }
 
 const ENUM_MIN_ANY: u8 = 0;
-const ENUM_MAX_ANY: u8 = 42;
+const ENUM_MAX_ANY: u8 = 59;
 
 impl<'a> flatbuffers::Follow<'a> for Any {
   type Inner = Self;
@@ -779,7 +796,7 @@ impl flatbuffers::Push for Any {
 }
 
 #[allow(non_camel_case_types)]
-const ENUM_VALUES_ANY:[Any; 43] = [
+const ENUM_VALUES_ANY:[Any; 60] = [
   Any::NONE,
   Any::TimerStart,
   Any::TimerReady,
@@ -822,11 +839,28 @@ const ENUM_VALUES_ANY:[Any; 43] = [
   Any::ImageReady,
   Any::AcmeGetChallenge,
   Any::AcmeGetChallengeReady,
-  Any::OsExit
+  Any::OsExit,
+  Any::ModuleCached,
+  Any::ModuleCachedResp,
+  Any::RuntimeMetrics,
+  Any::RuntimeMetricsReady,
+  Any::RegisterModuleResolver,
+  Any::GuestResolve,
+  Any::GuestResolveResult,
+  Any::LoadModuleStartResp,
+  Any::LoadModuleChunk,
+  Any::LoadModuleChunkResp,
+  Any::ClearModuleCache,
+  Any::ClearModuleCacheResp,
+  Any::ListLoadedModules,
+  Any::ListLoadedModulesResp,
+  Any::ResolveModule,
+  Any::ResolveModuleResp,
+  Any::StoreCompiledWasm
 ];
 
 #[allow(non_camel_case_types)]
-const ENUM_NAMES_ANY:[&'static str; 43] = [
+const ENUM_NAMES_ANY:[&'static str; 60] = [
     "NONE",
     "TimerStart",
     "TimerReady",
@@ -869,7 +903,24 @@ const ENUM_NAMES_ANY:[&'static str; 43] = [
     "ImageReady",
     "AcmeGetChallenge",
     "AcmeGetChallengeReady",
-    "OsExit"
+    "OsExit",
+    "ModuleCached",
+    "ModuleCachedResp",
+    "RuntimeMetrics",
+    "RuntimeMetricsReady",
+    "RegisterModuleResolver",
+    "GuestResolve",
+    "GuestResolveResult",
+    "LoadModuleStartResp",
+    "LoadModuleChunk",
+    "LoadModuleChunkResp",
+    "ClearModuleCache",
+    "ClearModuleCacheResp",
+    "ListLoadedModules",
+    "ListLoadedModulesResp",
+    "ResolveModule",
+    "ResolveModuleResp",
+    "StoreCompiledWasm"
 ];
 
 pub fn enum_name_any(e: Any) -> &'static str {
@@ -917,11 +968,27 @@ pub enum ErrorKind {
   HttpCanceled = 32,
   HttpParse = 33,
   HttpOther = 34,
+  Backpressure = 35,
+  IntegrityMismatch = 36,
+  PolicyDenied = 37,
+  ModuleNotFound = 38,
+  NoResolverForProtocol = 39,
+  ModuleLoadFailed = 40,
+  InvalidSpecifier = 41,
+  RateLimited = 42,
+  RedirectLoop = 43,
+  TooManyRedirects = 44,
+  UpstreamError = 45,
+  ModuleLimitExceeded = 46,
+  IsADirectory = 47,
+  PackageYanked = 48,
+  ModuleTypeMismatch = 49,
+  DeadlineExceeded = 50,
 
 }
 
 const ENUM_MIN_ERROR_KIND: i8 = 0;
-const ENUM_MAX_ERROR_KIND: i8 = 34;
+const ENUM_MAX_ERROR_KIND: i8 = 50;
 
 impl<'a> flatbuffers::Follow<'a> for ErrorKind {
   type Inner = Self;
@@ -955,7 +1022,7 @@ impl flatbuffers::Push for ErrorKind {
 }
 
 #[allow(non_camel_case_types)]
-const ENUM_VALUES_ERROR_KIND:[ErrorKind; 35] = [
+const ENUM_VALUES_ERROR_KIND:[ErrorKind; 51] = [
   ErrorKind::NoError,
   ErrorKind::String,
   ErrorKind::NotFound,
@@ -990,11 +1057,27 @@ const ENUM_VALUES_ERROR_KIND:[ErrorKind; 35] = [
   ErrorKind::HttpClosed,
   ErrorKind::HttpCanceled,
   ErrorKind::HttpParse,
-  ErrorKind::HttpOther
+  ErrorKind::HttpOther,
+  ErrorKind::Backpressure,
+  ErrorKind::IntegrityMismatch,
+  ErrorKind::PolicyDenied,
+  ErrorKind::ModuleNotFound,
+  ErrorKind::NoResolverForProtocol,
+  ErrorKind::ModuleLoadFailed,
+  ErrorKind::InvalidSpecifier,
+  ErrorKind::RateLimited,
+  ErrorKind::RedirectLoop,
+  ErrorKind::TooManyRedirects,
+  ErrorKind::UpstreamError,
+  ErrorKind::ModuleLimitExceeded,
+  ErrorKind::IsADirectory,
+  ErrorKind::PackageYanked,
+  ErrorKind::ModuleTypeMismatch,
+  ErrorKind::DeadlineExceeded
 ];
 
 #[allow(non_camel_case_types)]
-const ENUM_NAMES_ERROR_KIND:[&'static str; 35] = [
+const ENUM_NAMES_ERROR_KIND:[&'static str; 51] = [
     "NoError",
     "String",
     "NotFound",
@@ -1029,7 +1112,23 @@ const ENUM_NAMES_ERROR_KIND:[&'static str; 35] = [
     "HttpClosed",
     "HttpCanceled",
     "HttpParse",
-    "HttpOther"
+    "HttpOther",
+    "Backpressure",
+    "IntegrityMismatch",
+    "PolicyDenied",
+    "ModuleNotFound",
+    "NoResolverForProtocol",
+    "ModuleLoadFailed",
+    "InvalidSpecifier",
+    "RateLimited",
+    "RedirectLoop",
+    "TooManyRedirects",
+    "UpstreamError",
+    "ModuleLimitExceeded",
+    "IsADirectory",
+    "PackageYanked",
+    "ModuleTypeMismatch",
+    "DeadlineExceeded"
 ];
 
 pub fn enum_name_error_kind(e: ErrorKind) -> &'static str {
@@ -1178,6 +1277,78 @@ pub fn enum_name_event_type(e: EventType) -> &'static str {
   ENUM_NAMES_EVENT_TYPE[index]
 }
 
+#[allow(non_camel_case_types)]
+#[repr(i8)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ModuleKind {
+  JavaScript = 0,
+  TypeScript = 1,
+  Json = 2,
+  Wasm = 3,
+  Text = 4,
+  Bytes = 5,
+
+}
+
+const ENUM_MIN_MODULE_KIND: i8 = 0;
+const ENUM_MAX_MODULE_KIND: i8 = 5;
+
+impl<'a> flatbuffers::Follow<'a> for ModuleKind {
+  type Inner = Self;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    flatbuffers::read_scalar_at::<Self>(buf, loc)
+  }
+}
+
+impl flatbuffers::EndianScalar for ModuleKind {
+  #[inline]
+  fn to_little_endian(self) -> Self {
+    let n = i8::to_le(self as i8);
+    let p = &n as *const i8 as *const ModuleKind;
+    unsafe { *p }
+  }
+  #[inline]
+  fn from_little_endian(self) -> Self {
+    let n = i8::from_le(self as i8);
+    let p = &n as *const i8 as *const ModuleKind;
+    unsafe { *p }
+  }
+}
+
+impl flatbuffers::Push for ModuleKind {
+    type Output = ModuleKind;
+    #[inline]
+    fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+        flatbuffers::emplace_scalar::<ModuleKind>(dst, *self);
+    }
+}
+
+#[allow(non_camel_case_types)]
+const ENUM_VALUES_MODULE_KIND:[ModuleKind; 6] = [
+  ModuleKind::JavaScript,
+  ModuleKind::TypeScript,
+  ModuleKind::Json,
+  ModuleKind::Wasm,
+  ModuleKind::Text,
+  ModuleKind::Bytes
+];
+
+#[allow(non_camel_case_types)]
+const ENUM_NAMES_MODULE_KIND:[&'static str; 6] = [
+    "JavaScript",
+    "TypeScript",
+    "Json",
+    "Wasm",
+    "Text",
+    "Bytes"
+];
+
+pub fn enum_name_module_kind(e: ModuleKind) -> &'static str {
+  let index: usize = e as usize;
+  ENUM_NAMES_MODULE_KIND[index]
+}
+
 pub enum DnsAOffset {}
 #[derive(Copy, Clone, Debug, PartialEq)]
 
@@ -5453,6 +5624,176 @@ impl<'a> Base<'a> {
     }
   }
 
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_module_cached(&'a self) -> Option<ModuleCached> {
+    if self.msg_type() == Any::ModuleCached {
+      self.msg().map(|u| ModuleCached::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_module_cached_resp(&'a self) -> Option<ModuleCachedResp> {
+    if self.msg_type() == Any::ModuleCachedResp {
+      self.msg().map(|u| ModuleCachedResp::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_runtime_metrics(&'a self) -> Option<RuntimeMetrics> {
+    if self.msg_type() == Any::RuntimeMetrics {
+      self.msg().map(|u| RuntimeMetrics::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_runtime_metrics_ready(&'a self) -> Option<RuntimeMetricsReady> {
+    if self.msg_type() == Any::RuntimeMetricsReady {
+      self.msg().map(|u| RuntimeMetricsReady::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_register_module_resolver(&'a self) -> Option<RegisterModuleResolver> {
+    if self.msg_type() == Any::RegisterModuleResolver {
+      self.msg().map(|u| RegisterModuleResolver::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_guest_resolve(&'a self) -> Option<GuestResolve> {
+    if self.msg_type() == Any::GuestResolve {
+      self.msg().map(|u| GuestResolve::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_guest_resolve_result(&'a self) -> Option<GuestResolveResult> {
+    if self.msg_type() == Any::GuestResolveResult {
+      self.msg().map(|u| GuestResolveResult::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_load_module_start_resp(&'a self) -> Option<LoadModuleStartResp> {
+    if self.msg_type() == Any::LoadModuleStartResp {
+      self.msg().map(|u| LoadModuleStartResp::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_load_module_chunk(&'a self) -> Option<LoadModuleChunk> {
+    if self.msg_type() == Any::LoadModuleChunk {
+      self.msg().map(|u| LoadModuleChunk::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_load_module_chunk_resp(&'a self) -> Option<LoadModuleChunkResp> {
+    if self.msg_type() == Any::LoadModuleChunkResp {
+      self.msg().map(|u| LoadModuleChunkResp::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_clear_module_cache(&'a self) -> Option<ClearModuleCache> {
+    if self.msg_type() == Any::ClearModuleCache {
+      self.msg().map(|u| ClearModuleCache::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_clear_module_cache_resp(&'a self) -> Option<ClearModuleCacheResp> {
+    if self.msg_type() == Any::ClearModuleCacheResp {
+      self.msg().map(|u| ClearModuleCacheResp::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_list_loaded_modules(&'a self) -> Option<ListLoadedModules> {
+    if self.msg_type() == Any::ListLoadedModules {
+      self.msg().map(|u| ListLoadedModules::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_list_loaded_modules_resp(&'a self) -> Option<ListLoadedModulesResp> {
+    if self.msg_type() == Any::ListLoadedModulesResp {
+      self.msg().map(|u| ListLoadedModulesResp::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_resolve_module(&'a self) -> Option<ResolveModule> {
+    if self.msg_type() == Any::ResolveModule {
+      self.msg().map(|u| ResolveModule::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_resolve_module_resp(&'a self) -> Option<ResolveModuleResp> {
+    if self.msg_type() == Any::ResolveModuleResp {
+      self.msg().map(|u| ResolveModuleResp::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn msg_as_store_compiled_wasm(&'a self) -> Option<StoreCompiledWasm> {
+    if self.msg_type() == Any::StoreCompiledWasm {
+      self.msg().map(|u| StoreCompiledWasm::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
 }
 
 pub struct BaseArgs<'a> {
@@ -7057,13 +7398,29 @@ impl<'a> LoadModule<'a> {
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
         args: &'args LoadModuleArgs<'args>) -> flatbuffers::WIPOffset<LoadModule<'bldr>> {
       let mut builder = LoadModuleBuilder::new(_fbb);
+      builder.add_deadline(args.deadline);
+      if let Some(x) = args.asserted_type { builder.add_asserted_type(x); }
+      builder.add_referer_column(args.referer_column);
+      builder.add_referer_line(args.referer_line);
+      if let Some(x) = args.referer_source { builder.add_referer_source(x); }
+      builder.add_referer_identifier_hash(args.referer_identifier_hash);
       if let Some(x) = args.referer_origin_url { builder.add_referer_origin_url(x); }
       if let Some(x) = args.specifier_url { builder.add_specifier_url(x); }
+      builder.add_entry(args.entry);
+      builder.add_referer_is_wasm(args.referer_is_wasm);
       builder.finish()
     }
 
     pub const VT_SPECIFIER_URL: flatbuffers::VOffsetT = 4;
     pub const VT_REFERER_ORIGIN_URL: flatbuffers::VOffsetT = 6;
+    pub const VT_REFERER_IS_WASM: flatbuffers::VOffsetT = 8;
+    pub const VT_REFERER_IDENTIFIER_HASH: flatbuffers::VOffsetT = 10;
+    pub const VT_REFERER_SOURCE: flatbuffers::VOffsetT = 12;
+    pub const VT_ENTRY: flatbuffers::VOffsetT = 14;
+    pub const VT_REFERER_LINE: flatbuffers::VOffsetT = 16;
+    pub const VT_REFERER_COLUMN: flatbuffers::VOffsetT = 18;
+    pub const VT_ASSERTED_TYPE: flatbuffers::VOffsetT = 20;
+    pub const VT_DEADLINE: flatbuffers::VOffsetT = 22;
 
   #[inline]
   pub fn specifier_url(&self) -> Option<&'a str> {
@@ -7073,11 +7430,51 @@ impl<'a> LoadModule<'a> {
   pub fn referer_origin_url(&self) -> Option<&'a str> {
     self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModule::VT_REFERER_ORIGIN_URL, None)
   }
+  #[inline]
+  pub fn referer_is_wasm(&self) -> bool {
+    self._tab.get::<bool>(LoadModule::VT_REFERER_IS_WASM, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn referer_identifier_hash(&self) -> i32 {
+    self._tab.get::<i32>(LoadModule::VT_REFERER_IDENTIFIER_HASH, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn referer_source(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModule::VT_REFERER_SOURCE, None)
+  }
+  #[inline]
+  pub fn entry(&self) -> bool {
+    self._tab.get::<bool>(LoadModule::VT_ENTRY, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn referer_line(&self) -> u32 {
+    self._tab.get::<u32>(LoadModule::VT_REFERER_LINE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn referer_column(&self) -> u32 {
+    self._tab.get::<u32>(LoadModule::VT_REFERER_COLUMN, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn asserted_type(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModule::VT_ASSERTED_TYPE, None)
+  }
+  #[inline]
+  pub fn deadline(&self) -> u32 {
+    self._tab.get::<u32>(LoadModule::VT_DEADLINE, Some(0)).unwrap()
+  }
 }
 
 pub struct LoadModuleArgs<'a> {
     pub specifier_url: Option<flatbuffers::WIPOffset<&'a  str>>,
     pub referer_origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub referer_is_wasm: bool,
+    pub referer_identifier_hash: i32,
+    pub referer_source: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub entry: bool,
+    pub referer_line: u32,
+    pub referer_column: u32,
+    pub asserted_type: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub deadline: u32,
 }
 impl<'a> Default for LoadModuleArgs<'a> {
     #[inline]
@@ -7085,6 +7482,14 @@ impl<'a> Default for LoadModuleArgs<'a> {
         LoadModuleArgs {
             specifier_url: None,
             referer_origin_url: None,
+            referer_is_wasm: false,
+            referer_identifier_hash: 0,
+            referer_source: None,
+            entry: false,
+            referer_line: 0,
+            referer_column: 0,
+            asserted_type: None,
+            deadline: 0,
         }
     }
 }
@@ -7102,6 +7507,38 @@ impl<'a: 'b, 'b> LoadModuleBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModule::VT_REFERER_ORIGIN_URL, referer_origin_url);
   }
   #[inline]
+  pub fn add_referer_is_wasm(&mut self, referer_is_wasm: bool) {
+    self.fbb_.push_slot::<bool>(LoadModule::VT_REFERER_IS_WASM, referer_is_wasm, false);
+  }
+  #[inline]
+  pub fn add_referer_identifier_hash(&mut self, referer_identifier_hash: i32) {
+    self.fbb_.push_slot::<i32>(LoadModule::VT_REFERER_IDENTIFIER_HASH, referer_identifier_hash, 0);
+  }
+  #[inline]
+  pub fn add_referer_source(&mut self, referer_source: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModule::VT_REFERER_SOURCE, referer_source);
+  }
+  #[inline]
+  pub fn add_entry(&mut self, entry: bool) {
+    self.fbb_.push_slot::<bool>(LoadModule::VT_ENTRY, entry, false);
+  }
+  #[inline]
+  pub fn add_referer_line(&mut self, referer_line: u32) {
+    self.fbb_.push_slot::<u32>(LoadModule::VT_REFERER_LINE, referer_line, 0);
+  }
+  #[inline]
+  pub fn add_referer_column(&mut self, referer_column: u32) {
+    self.fbb_.push_slot::<u32>(LoadModule::VT_REFERER_COLUMN, referer_column, 0);
+  }
+  #[inline]
+  pub fn add_asserted_type(&mut self, asserted_type: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModule::VT_ASSERTED_TYPE, asserted_type);
+  }
+  #[inline]
+  pub fn add_deadline(&mut self, deadline: u32) {
+    self.fbb_.push_slot::<u32>(LoadModule::VT_DEADLINE, deadline, 0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> LoadModuleBuilder<'a, 'b> {
     let start = _fbb.start_table();
     LoadModuleBuilder {
@@ -7145,13 +7582,29 @@ impl<'a> LoadModuleResp<'a> {
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
         args: &'args LoadModuleRespArgs<'args>) -> flatbuffers::WIPOffset<LoadModuleResp<'bldr>> {
       let mut builder = LoadModuleRespBuilder::new(_fbb);
+      builder.add_deadline(args.deadline);
+      if let Some(x) = args.compiled_wasm { builder.add_compiled_wasm(x); }
+      if let Some(x) = args.source_map { builder.add_source_map(x); }
+      if let Some(x) = args.wasm { builder.add_wasm(x); }
       if let Some(x) = args.source_code { builder.add_source_code(x); }
       if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      builder.add_kind(args.kind);
+      builder.add_is_wasm(args.is_wasm);
+      builder.add_is_main(args.is_main);
+      builder.add_passthrough(args.passthrough);
       builder.finish()
     }
 
     pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 4;
     pub const VT_SOURCE_CODE: flatbuffers::VOffsetT = 6;
+    pub const VT_PASSTHROUGH: flatbuffers::VOffsetT = 8;
+    pub const VT_IS_MAIN: flatbuffers::VOffsetT = 10;
+    pub const VT_IS_WASM: flatbuffers::VOffsetT = 12;
+    pub const VT_WASM: flatbuffers::VOffsetT = 14;
+    pub const VT_SOURCE_MAP: flatbuffers::VOffsetT = 16;
+    pub const VT_KIND: flatbuffers::VOffsetT = 18;
+    pub const VT_COMPILED_WASM: flatbuffers::VOffsetT = 20;
+    pub const VT_DEADLINE: flatbuffers::VOffsetT = 22;
 
   #[inline]
   pub fn origin_url(&self) -> Option<&'a str> {
@@ -7161,11 +7614,51 @@ impl<'a> LoadModuleResp<'a> {
   pub fn source_code(&self) -> Option<&'a str> {
     self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModuleResp::VT_SOURCE_CODE, None)
   }
+  #[inline]
+  pub fn passthrough(&self) -> bool {
+    self._tab.get::<bool>(LoadModuleResp::VT_PASSTHROUGH, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn is_main(&self) -> bool {
+    self._tab.get::<bool>(LoadModuleResp::VT_IS_MAIN, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn is_wasm(&self) -> bool {
+    self._tab.get::<bool>(LoadModuleResp::VT_IS_WASM, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn wasm(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(LoadModuleResp::VT_WASM, None).map(|v| v.safe_slice())
+  }
+  #[inline]
+  pub fn source_map(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModuleResp::VT_SOURCE_MAP, None)
+  }
+  #[inline]
+  pub fn kind(&self) -> ModuleKind {
+    self._tab.get::<ModuleKind>(LoadModuleResp::VT_KIND, Some(ModuleKind::JavaScript)).unwrap()
+  }
+  #[inline]
+  pub fn compiled_wasm(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(LoadModuleResp::VT_COMPILED_WASM, None).map(|v| v.safe_slice())
+  }
+  #[inline]
+  pub fn deadline(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleResp::VT_DEADLINE, Some(0)).unwrap()
+  }
 }
 
 pub struct LoadModuleRespArgs<'a> {
     pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
     pub source_code: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub passthrough: bool,
+    pub is_main: bool,
+    pub is_wasm: bool,
+    pub wasm: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a ,  u8>>>,
+    pub source_map: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub kind: ModuleKind,
+    pub compiled_wasm: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a ,  u8>>>,
+    pub deadline: u32,
 }
 impl<'a> Default for LoadModuleRespArgs<'a> {
     #[inline]
@@ -7173,6 +7666,14 @@ impl<'a> Default for LoadModuleRespArgs<'a> {
         LoadModuleRespArgs {
             origin_url: None,
             source_code: None,
+            passthrough: false,
+            is_main: false,
+            is_wasm: false,
+            wasm: None,
+            source_map: None,
+            kind: ModuleKind::JavaScript,
+            compiled_wasm: None,
+            deadline: 0,
         }
     }
 }
@@ -7190,6 +7691,38 @@ impl<'a: 'b, 'b> LoadModuleRespBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleResp::VT_SOURCE_CODE, source_code);
   }
   #[inline]
+  pub fn add_passthrough(&mut self, passthrough: bool) {
+    self.fbb_.push_slot::<bool>(LoadModuleResp::VT_PASSTHROUGH, passthrough, false);
+  }
+  #[inline]
+  pub fn add_is_main(&mut self, is_main: bool) {
+    self.fbb_.push_slot::<bool>(LoadModuleResp::VT_IS_MAIN, is_main, false);
+  }
+  #[inline]
+  pub fn add_is_wasm(&mut self, is_wasm: bool) {
+    self.fbb_.push_slot::<bool>(LoadModuleResp::VT_IS_WASM, is_wasm, false);
+  }
+  #[inline]
+  pub fn add_wasm(&mut self, wasm: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleResp::VT_WASM, wasm);
+  }
+  #[inline]
+  pub fn add_source_map(&mut self, source_map: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleResp::VT_SOURCE_MAP, source_map);
+  }
+  #[inline]
+  pub fn add_kind(&mut self, kind: ModuleKind) {
+    self.fbb_.push_slot::<ModuleKind>(LoadModuleResp::VT_KIND, kind, ModuleKind::JavaScript);
+  }
+  #[inline]
+  pub fn add_compiled_wasm(&mut self, compiled_wasm: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleResp::VT_COMPILED_WASM, compiled_wasm);
+  }
+  #[inline]
+  pub fn add_deadline(&mut self, deadline: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleResp::VT_DEADLINE, deadline, 0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> LoadModuleRespBuilder<'a, 'b> {
     let start = _fbb.start_table();
     LoadModuleRespBuilder {
@@ -7204,6 +7737,1952 @@ impl<'a: 'b, 'b> LoadModuleRespBuilder<'a, 'b> {
   }
 }
 
+pub enum ModuleCachedOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ModuleCached<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for ModuleCached<'a> {
+    type Inner = ModuleCached<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> ModuleCached<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ModuleCached {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ModuleCachedArgs<'args>) -> flatbuffers::WIPOffset<ModuleCached<'bldr>> {
+      let mut builder = ModuleCachedBuilder::new(_fbb);
+      if let Some(x) = args.referer_origin_url { builder.add_referer_origin_url(x); }
+      if let Some(x) = args.specifier_url { builder.add_specifier_url(x); }
+      builder.finish()
+    }
+
+    pub const VT_SPECIFIER_URL: flatbuffers::VOffsetT = 4;
+    pub const VT_REFERER_ORIGIN_URL: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn specifier_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ModuleCached::VT_SPECIFIER_URL, None)
+  }
+  #[inline]
+  pub fn referer_origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ModuleCached::VT_REFERER_ORIGIN_URL, None)
+  }
+}
+
+pub struct ModuleCachedArgs<'a> {
+    pub specifier_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub referer_origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for ModuleCachedArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        ModuleCachedArgs {
+            specifier_url: None,
+            referer_origin_url: None,
+        }
+    }
+}
+pub struct ModuleCachedBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ModuleCachedBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_specifier_url(&mut self, specifier_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ModuleCached::VT_SPECIFIER_URL, specifier_url);
+  }
+  #[inline]
+  pub fn add_referer_origin_url(&mut self, referer_origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ModuleCached::VT_REFERER_ORIGIN_URL, referer_origin_url);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ModuleCachedBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ModuleCachedBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ModuleCached<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ModuleCachedRespOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ModuleCachedResp<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for ModuleCachedResp<'a> {
+    type Inner = ModuleCachedResp<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> ModuleCachedResp<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ModuleCachedResp {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ModuleCachedRespArgs<'args>) -> flatbuffers::WIPOffset<ModuleCachedResp<'bldr>> {
+      let mut builder = ModuleCachedRespBuilder::new(_fbb);
+      if let Some(x) = args.freshness { builder.add_freshness(x); }
+      builder.add_size(args.size);
+      if let Some(x) = args.location { builder.add_location(x); }
+      if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      builder.finish()
+    }
+
+    pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 4;
+    pub const VT_LOCATION: flatbuffers::VOffsetT = 6;
+    pub const VT_SIZE: flatbuffers::VOffsetT = 8;
+    pub const VT_FRESHNESS: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub fn origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ModuleCachedResp::VT_ORIGIN_URL, None)
+  }
+  #[inline]
+  pub fn location(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ModuleCachedResp::VT_LOCATION, None)
+  }
+  #[inline]
+  pub fn size(&self) -> u32 {
+    self._tab.get::<u32>(ModuleCachedResp::VT_SIZE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn freshness(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ModuleCachedResp::VT_FRESHNESS, None)
+  }
+}
+
+pub struct ModuleCachedRespArgs<'a> {
+    pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub location: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub size: u32,
+    pub freshness: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for ModuleCachedRespArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        ModuleCachedRespArgs {
+            origin_url: None,
+            location: None,
+            size: 0,
+            freshness: None,
+        }
+    }
+}
+pub struct ModuleCachedRespBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ModuleCachedRespBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_origin_url(&mut self, origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ModuleCachedResp::VT_ORIGIN_URL, origin_url);
+  }
+  #[inline]
+  pub fn add_location(&mut self, location: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ModuleCachedResp::VT_LOCATION, location);
+  }
+  #[inline]
+  pub fn add_size(&mut self, size: u32) {
+    self.fbb_.push_slot::<u32>(ModuleCachedResp::VT_SIZE, size, 0);
+  }
+  #[inline]
+  pub fn add_freshness(&mut self, freshness: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ModuleCachedResp::VT_FRESHNESS, freshness);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ModuleCachedRespBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ModuleCachedRespBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ModuleCachedResp<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum RuntimeMetricsOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct RuntimeMetrics<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for RuntimeMetrics<'a> {
+    type Inner = RuntimeMetrics<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> RuntimeMetrics<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        RuntimeMetrics {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args RuntimeMetricsArgs) -> flatbuffers::WIPOffset<RuntimeMetrics<'bldr>> {
+      let mut builder = RuntimeMetricsBuilder::new(_fbb);
+      builder.add_reset(args.reset);
+      builder.finish()
+    }
+
+    pub const VT_RESET: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn reset(&self) -> bool {
+    self._tab.get::<bool>(RuntimeMetrics::VT_RESET, Some(false)).unwrap()
+  }
+}
+
+pub struct RuntimeMetricsArgs {
+    pub reset: bool,
+}
+impl<'a> Default for RuntimeMetricsArgs {
+    #[inline]
+    fn default() -> Self {
+        RuntimeMetricsArgs {
+            reset: false,
+        }
+    }
+}
+pub struct RuntimeMetricsBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> RuntimeMetricsBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_reset(&mut self, reset: bool) {
+    self.fbb_.push_slot::<bool>(RuntimeMetrics::VT_RESET, reset, false);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> RuntimeMetricsBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    RuntimeMetricsBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<RuntimeMetrics<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum OpMetricOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct OpMetric<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for OpMetric<'a> {
+    type Inner = OpMetric<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> OpMetric<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        OpMetric {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args OpMetricArgs<'args>) -> flatbuffers::WIPOffset<OpMetric<'bldr>> {
+      let mut builder = OpMetricBuilder::new(_fbb);
+      builder.add_max_ms(args.max_ms);
+      builder.add_total_ms(args.total_ms);
+      builder.add_errors(args.errors);
+      builder.add_calls(args.calls);
+      if let Some(x) = args.op { builder.add_op(x); }
+      builder.finish()
+    }
+
+    pub const VT_OP: flatbuffers::VOffsetT = 4;
+    pub const VT_CALLS: flatbuffers::VOffsetT = 6;
+    pub const VT_ERRORS: flatbuffers::VOffsetT = 8;
+    pub const VT_TOTAL_MS: flatbuffers::VOffsetT = 10;
+    pub const VT_MAX_MS: flatbuffers::VOffsetT = 12;
+
+  #[inline]
+  pub fn op(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(OpMetric::VT_OP, None)
+  }
+  #[inline]
+  pub fn calls(&self) -> u32 {
+    self._tab.get::<u32>(OpMetric::VT_CALLS, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn errors(&self) -> u32 {
+    self._tab.get::<u32>(OpMetric::VT_ERRORS, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn total_ms(&self) -> f64 {
+    self._tab.get::<f64>(OpMetric::VT_TOTAL_MS, Some(0.0)).unwrap()
+  }
+  #[inline]
+  pub fn max_ms(&self) -> f64 {
+    self._tab.get::<f64>(OpMetric::VT_MAX_MS, Some(0.0)).unwrap()
+  }
+}
+
+pub struct OpMetricArgs<'a> {
+    pub op: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub calls: u32,
+    pub errors: u32,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+impl<'a> Default for OpMetricArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        OpMetricArgs {
+            op: None,
+            calls: 0,
+            errors: 0,
+            total_ms: 0.0,
+            max_ms: 0.0,
+        }
+    }
+}
+pub struct OpMetricBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> OpMetricBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_op(&mut self, op: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(OpMetric::VT_OP, op);
+  }
+  #[inline]
+  pub fn add_calls(&mut self, calls: u32) {
+    self.fbb_.push_slot::<u32>(OpMetric::VT_CALLS, calls, 0);
+  }
+  #[inline]
+  pub fn add_errors(&mut self, errors: u32) {
+    self.fbb_.push_slot::<u32>(OpMetric::VT_ERRORS, errors, 0);
+  }
+  #[inline]
+  pub fn add_total_ms(&mut self, total_ms: f64) {
+    self.fbb_.push_slot::<f64>(OpMetric::VT_TOTAL_MS, total_ms, 0.0);
+  }
+  #[inline]
+  pub fn add_max_ms(&mut self, max_ms: f64) {
+    self.fbb_.push_slot::<f64>(OpMetric::VT_MAX_MS, max_ms, 0.0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> OpMetricBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    OpMetricBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<OpMetric<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum RuntimeMetricsReadyOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct RuntimeMetricsReady<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for RuntimeMetricsReady<'a> {
+    type Inner = RuntimeMetricsReady<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> RuntimeMetricsReady<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        RuntimeMetricsReady {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args RuntimeMetricsReadyArgs<'args>) -> flatbuffers::WIPOffset<RuntimeMetricsReady<'bldr>> {
+      let mut builder = RuntimeMetricsReadyBuilder::new(_fbb);
+      builder.add_queued(args.queued);
+      builder.add_pending_expensive(args.pending_expensive);
+      builder.add_pending(args.pending);
+      if let Some(x) = args.ops { builder.add_ops(x); }
+      builder.finish()
+    }
+
+    pub const VT_OPS: flatbuffers::VOffsetT = 4;
+    pub const VT_PENDING: flatbuffers::VOffsetT = 6;
+    pub const VT_PENDING_EXPENSIVE: flatbuffers::VOffsetT = 8;
+    pub const VT_QUEUED: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub fn ops(&self) -> Option<flatbuffers::Vector<flatbuffers::ForwardsUOffset<OpMetric<'a>>>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<flatbuffers::ForwardsUOffset<OpMetric<'a>>>>>(RuntimeMetricsReady::VT_OPS, None)
+  }
+  #[inline]
+  pub fn pending(&self) -> u32 {
+    self._tab.get::<u32>(RuntimeMetricsReady::VT_PENDING, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn pending_expensive(&self) -> u32 {
+    self._tab.get::<u32>(RuntimeMetricsReady::VT_PENDING_EXPENSIVE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn queued(&self) -> u32 {
+    self._tab.get::<u32>(RuntimeMetricsReady::VT_QUEUED, Some(0)).unwrap()
+  }
+}
+
+pub struct RuntimeMetricsReadyArgs<'a> {
+    pub ops: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<OpMetric<'a >>>>>,
+    pub pending: u32,
+    pub pending_expensive: u32,
+    pub queued: u32,
+}
+impl<'a> Default for RuntimeMetricsReadyArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        RuntimeMetricsReadyArgs {
+            ops: None,
+            pending: 0,
+            pending_expensive: 0,
+            queued: 0,
+        }
+    }
+}
+pub struct RuntimeMetricsReadyBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> RuntimeMetricsReadyBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_ops(&mut self, ops: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<OpMetric<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(RuntimeMetricsReady::VT_OPS, ops);
+  }
+  #[inline]
+  pub fn add_pending(&mut self, pending: u32) {
+    self.fbb_.push_slot::<u32>(RuntimeMetricsReady::VT_PENDING, pending, 0);
+  }
+  #[inline]
+  pub fn add_pending_expensive(&mut self, pending_expensive: u32) {
+    self.fbb_.push_slot::<u32>(RuntimeMetricsReady::VT_PENDING_EXPENSIVE, pending_expensive, 0);
+  }
+  #[inline]
+  pub fn add_queued(&mut self, queued: u32) {
+    self.fbb_.push_slot::<u32>(RuntimeMetricsReady::VT_QUEUED, queued, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> RuntimeMetricsReadyBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    RuntimeMetricsReadyBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<RuntimeMetricsReady<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum RegisterModuleResolverOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct RegisterModuleResolver<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for RegisterModuleResolver<'a> {
+    type Inner = RegisterModuleResolver<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> RegisterModuleResolver<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        RegisterModuleResolver {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args RegisterModuleResolverArgs<'args>) -> flatbuffers::WIPOffset<RegisterModuleResolver<'bldr>> {
+      let mut builder = RegisterModuleResolverBuilder::new(_fbb);
+      if let Some(x) = args.protocol { builder.add_protocol(x); }
+      builder.finish()
+    }
+
+    pub const VT_PROTOCOL: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn protocol(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(RegisterModuleResolver::VT_PROTOCOL, None)
+  }
+}
+
+pub struct RegisterModuleResolverArgs<'a> {
+    pub protocol: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for RegisterModuleResolverArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        RegisterModuleResolverArgs {
+            protocol: None,
+        }
+    }
+}
+pub struct RegisterModuleResolverBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> RegisterModuleResolverBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_protocol(&mut self, protocol: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(RegisterModuleResolver::VT_PROTOCOL, protocol);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> RegisterModuleResolverBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    RegisterModuleResolverBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<RegisterModuleResolver<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum GuestResolveOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct GuestResolve<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for GuestResolve<'a> {
+    type Inner = GuestResolve<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> GuestResolve<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        GuestResolve {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args GuestResolveArgs<'args>) -> flatbuffers::WIPOffset<GuestResolve<'bldr>> {
+      let mut builder = GuestResolveBuilder::new(_fbb);
+      if let Some(x) = args.referer { builder.add_referer(x); }
+      if let Some(x) = args.specifier { builder.add_specifier(x); }
+      if let Some(x) = args.protocol { builder.add_protocol(x); }
+      builder.add_id(args.id);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_PROTOCOL: flatbuffers::VOffsetT = 6;
+    pub const VT_SPECIFIER: flatbuffers::VOffsetT = 8;
+    pub const VT_REFERER: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(GuestResolve::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn protocol(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(GuestResolve::VT_PROTOCOL, None)
+  }
+  #[inline]
+  pub fn specifier(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(GuestResolve::VT_SPECIFIER, None)
+  }
+  #[inline]
+  pub fn referer(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(GuestResolve::VT_REFERER, None)
+  }
+}
+
+pub struct GuestResolveArgs<'a> {
+    pub id: u32,
+    pub protocol: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub specifier: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub referer: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for GuestResolveArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        GuestResolveArgs {
+            id: 0,
+            protocol: None,
+            specifier: None,
+            referer: None,
+        }
+    }
+}
+pub struct GuestResolveBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> GuestResolveBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(GuestResolve::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_protocol(&mut self, protocol: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GuestResolve::VT_PROTOCOL, protocol);
+  }
+  #[inline]
+  pub fn add_specifier(&mut self, specifier: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GuestResolve::VT_SPECIFIER, specifier);
+  }
+  #[inline]
+  pub fn add_referer(&mut self, referer: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GuestResolve::VT_REFERER, referer);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> GuestResolveBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    GuestResolveBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<GuestResolve<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum GuestResolveResultOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct GuestResolveResult<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for GuestResolveResult<'a> {
+    type Inner = GuestResolveResult<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> GuestResolveResult<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        GuestResolveResult {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args GuestResolveResultArgs<'args>) -> flatbuffers::WIPOffset<GuestResolveResult<'bldr>> {
+      let mut builder = GuestResolveResultBuilder::new(_fbb);
+      if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      if let Some(x) = args.error { builder.add_error(x); }
+      if let Some(x) = args.redirect { builder.add_redirect(x); }
+      if let Some(x) = args.source { builder.add_source(x); }
+      builder.add_id(args.id);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_SOURCE: flatbuffers::VOffsetT = 6;
+    pub const VT_REDIRECT: flatbuffers::VOffsetT = 8;
+    pub const VT_ERROR: flatbuffers::VOffsetT = 10;
+    pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 12;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(GuestResolveResult::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn source(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(GuestResolveResult::VT_SOURCE, None)
+  }
+  #[inline]
+  pub fn redirect(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(GuestResolveResult::VT_REDIRECT, None)
+  }
+  #[inline]
+  pub fn error(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(GuestResolveResult::VT_ERROR, None)
+  }
+  #[inline]
+  pub fn origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(GuestResolveResult::VT_ORIGIN_URL, None)
+  }
+}
+
+pub struct GuestResolveResultArgs<'a> {
+    pub id: u32,
+    pub source: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub redirect: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub error: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for GuestResolveResultArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        GuestResolveResultArgs {
+            id: 0,
+            source: None,
+            redirect: None,
+            error: None,
+            origin_url: None,
+        }
+    }
+}
+pub struct GuestResolveResultBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> GuestResolveResultBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(GuestResolveResult::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_source(&mut self, source: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GuestResolveResult::VT_SOURCE, source);
+  }
+  #[inline]
+  pub fn add_redirect(&mut self, redirect: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GuestResolveResult::VT_REDIRECT, redirect);
+  }
+  #[inline]
+  pub fn add_error(&mut self, error: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GuestResolveResult::VT_ERROR, error);
+  }
+  #[inline]
+  pub fn add_origin_url(&mut self, origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GuestResolveResult::VT_ORIGIN_URL, origin_url);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> GuestResolveResultBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    GuestResolveResultBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<GuestResolveResult<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum LoadModuleStartRespOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct LoadModuleStartResp<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for LoadModuleStartResp<'a> {
+    type Inner = LoadModuleStartResp<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> LoadModuleStartResp<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        LoadModuleStartResp {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args LoadModuleStartRespArgs<'args>) -> flatbuffers::WIPOffset<LoadModuleStartResp<'bldr>> {
+      let mut builder = LoadModuleStartRespBuilder::new(_fbb);
+      builder.add_deadline(args.deadline);
+      builder.add_compiled_wasm_size(args.compiled_wasm_size);
+      if let Some(x) = args.source_map { builder.add_source_map(x); }
+      builder.add_chunks(args.chunks);
+      builder.add_size(args.size);
+      if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      builder.add_id(args.id);
+      builder.add_kind(args.kind);
+      builder.add_is_wasm(args.is_wasm);
+      builder.add_is_main(args.is_main);
+      builder.add_passthrough(args.passthrough);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 6;
+    pub const VT_SIZE: flatbuffers::VOffsetT = 8;
+    pub const VT_CHUNKS: flatbuffers::VOffsetT = 10;
+    pub const VT_PASSTHROUGH: flatbuffers::VOffsetT = 12;
+    pub const VT_IS_MAIN: flatbuffers::VOffsetT = 14;
+    pub const VT_IS_WASM: flatbuffers::VOffsetT = 16;
+    pub const VT_SOURCE_MAP: flatbuffers::VOffsetT = 18;
+    pub const VT_KIND: flatbuffers::VOffsetT = 20;
+    pub const VT_COMPILED_WASM_SIZE: flatbuffers::VOffsetT = 22;
+    pub const VT_DEADLINE: flatbuffers::VOffsetT = 24;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleStartResp::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModuleStartResp::VT_ORIGIN_URL, None)
+  }
+  #[inline]
+  pub fn size(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleStartResp::VT_SIZE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn chunks(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleStartResp::VT_CHUNKS, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn passthrough(&self) -> bool {
+    self._tab.get::<bool>(LoadModuleStartResp::VT_PASSTHROUGH, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn is_main(&self) -> bool {
+    self._tab.get::<bool>(LoadModuleStartResp::VT_IS_MAIN, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn is_wasm(&self) -> bool {
+    self._tab.get::<bool>(LoadModuleStartResp::VT_IS_WASM, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn source_map(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadModuleStartResp::VT_SOURCE_MAP, None)
+  }
+  #[inline]
+  pub fn kind(&self) -> ModuleKind {
+    self._tab.get::<ModuleKind>(LoadModuleStartResp::VT_KIND, Some(ModuleKind::JavaScript)).unwrap()
+  }
+  #[inline]
+  pub fn compiled_wasm_size(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleStartResp::VT_COMPILED_WASM_SIZE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn deadline(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleStartResp::VT_DEADLINE, Some(0)).unwrap()
+  }
+}
+
+pub struct LoadModuleStartRespArgs<'a> {
+    pub id: u32,
+    pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub size: u32,
+    pub chunks: u32,
+    pub passthrough: bool,
+    pub is_main: bool,
+    pub is_wasm: bool,
+    pub source_map: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub kind: ModuleKind,
+    pub compiled_wasm_size: u32,
+    pub deadline: u32,
+}
+impl<'a> Default for LoadModuleStartRespArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        LoadModuleStartRespArgs {
+            id: 0,
+            origin_url: None,
+            size: 0,
+            chunks: 0,
+            passthrough: false,
+            is_main: false,
+            is_wasm: false,
+            source_map: None,
+            kind: ModuleKind::JavaScript,
+            compiled_wasm_size: 0,
+            deadline: 0,
+        }
+    }
+}
+pub struct LoadModuleStartRespBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> LoadModuleStartRespBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleStartResp::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_origin_url(&mut self, origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleStartResp::VT_ORIGIN_URL, origin_url);
+  }
+  #[inline]
+  pub fn add_size(&mut self, size: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleStartResp::VT_SIZE, size, 0);
+  }
+  #[inline]
+  pub fn add_chunks(&mut self, chunks: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleStartResp::VT_CHUNKS, chunks, 0);
+  }
+  #[inline]
+  pub fn add_passthrough(&mut self, passthrough: bool) {
+    self.fbb_.push_slot::<bool>(LoadModuleStartResp::VT_PASSTHROUGH, passthrough, false);
+  }
+  #[inline]
+  pub fn add_is_main(&mut self, is_main: bool) {
+    self.fbb_.push_slot::<bool>(LoadModuleStartResp::VT_IS_MAIN, is_main, false);
+  }
+  #[inline]
+  pub fn add_is_wasm(&mut self, is_wasm: bool) {
+    self.fbb_.push_slot::<bool>(LoadModuleStartResp::VT_IS_WASM, is_wasm, false);
+  }
+  #[inline]
+  pub fn add_source_map(&mut self, source_map: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleStartResp::VT_SOURCE_MAP, source_map);
+  }
+  #[inline]
+  pub fn add_kind(&mut self, kind: ModuleKind) {
+    self.fbb_.push_slot::<ModuleKind>(LoadModuleStartResp::VT_KIND, kind, ModuleKind::JavaScript);
+  }
+  #[inline]
+  pub fn add_compiled_wasm_size(&mut self, compiled_wasm_size: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleStartResp::VT_COMPILED_WASM_SIZE, compiled_wasm_size, 0);
+  }
+  #[inline]
+  pub fn add_deadline(&mut self, deadline: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleStartResp::VT_DEADLINE, deadline, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> LoadModuleStartRespBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    LoadModuleStartRespBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<LoadModuleStartResp<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum LoadModuleChunkOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct LoadModuleChunk<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for LoadModuleChunk<'a> {
+    type Inner = LoadModuleChunk<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> LoadModuleChunk<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        LoadModuleChunk {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args LoadModuleChunkArgs) -> flatbuffers::WIPOffset<LoadModuleChunk<'bldr>> {
+      let mut builder = LoadModuleChunkBuilder::new(_fbb);
+      builder.add_index(args.index);
+      builder.add_id(args.id);
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_INDEX: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleChunk::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn index(&self) -> u32 {
+    self._tab.get::<u32>(LoadModuleChunk::VT_INDEX, Some(0)).unwrap()
+  }
+}
+
+pub struct LoadModuleChunkArgs {
+    pub id: u32,
+    pub index: u32,
+}
+impl<'a> Default for LoadModuleChunkArgs {
+    #[inline]
+    fn default() -> Self {
+        LoadModuleChunkArgs {
+            id: 0,
+            index: 0,
+        }
+    }
+}
+pub struct LoadModuleChunkBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> LoadModuleChunkBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleChunk::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_index(&mut self, index: u32) {
+    self.fbb_.push_slot::<u32>(LoadModuleChunk::VT_INDEX, index, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> LoadModuleChunkBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    LoadModuleChunkBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<LoadModuleChunk<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum LoadModuleChunkRespOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct LoadModuleChunkResp<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for LoadModuleChunkResp<'a> {
+    type Inner = LoadModuleChunkResp<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> LoadModuleChunkResp<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        LoadModuleChunkResp {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args LoadModuleChunkRespArgs<'args>) -> flatbuffers::WIPOffset<LoadModuleChunkResp<'bldr>> {
+      let mut builder = LoadModuleChunkRespBuilder::new(_fbb);
+      if let Some(x) = args.data { builder.add_data(x); }
+      builder.finish()
+    }
+
+    pub const VT_DATA: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn data(&self) -> Option<&'a [u8]> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(LoadModuleChunkResp::VT_DATA, None).map(|v| v.safe_slice())
+  }
+}
+
+pub struct LoadModuleChunkRespArgs<'a> {
+    pub data: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a ,  u8>>>,
+}
+impl<'a> Default for LoadModuleChunkRespArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        LoadModuleChunkRespArgs {
+            data: None,
+        }
+    }
+}
+pub struct LoadModuleChunkRespBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> LoadModuleChunkRespBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_data(&mut self, data: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadModuleChunkResp::VT_DATA, data);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> LoadModuleChunkRespBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    LoadModuleChunkRespBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<LoadModuleChunkResp<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ClearModuleCacheOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ClearModuleCache<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for ClearModuleCache<'a> {
+    type Inner = ClearModuleCache<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> ClearModuleCache<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ClearModuleCache {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ClearModuleCacheArgs<'args>) -> flatbuffers::WIPOffset<ClearModuleCache<'bldr>> {
+      let mut builder = ClearModuleCacheBuilder::new(_fbb);
+      if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      builder.finish()
+    }
+
+    pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ClearModuleCache::VT_ORIGIN_URL, None)
+  }
+}
+
+pub struct ClearModuleCacheArgs<'a> {
+    pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for ClearModuleCacheArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        ClearModuleCacheArgs {
+            origin_url: None,
+        }
+    }
+}
+pub struct ClearModuleCacheBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ClearModuleCacheBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_origin_url(&mut self, origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ClearModuleCache::VT_ORIGIN_URL, origin_url);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ClearModuleCacheBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ClearModuleCacheBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ClearModuleCache<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ClearModuleCacheRespOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ClearModuleCacheResp<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for ClearModuleCacheResp<'a> {
+    type Inner = ClearModuleCacheResp<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> ClearModuleCacheResp<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ClearModuleCacheResp {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ClearModuleCacheRespArgs) -> flatbuffers::WIPOffset<ClearModuleCacheResp<'bldr>> {
+      let mut builder = ClearModuleCacheRespBuilder::new(_fbb);
+      builder.add_evicted(args.evicted);
+      builder.finish()
+    }
+
+    pub const VT_EVICTED: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn evicted(&self) -> u32 {
+    self._tab.get::<u32>(ClearModuleCacheResp::VT_EVICTED, Some(0)).unwrap()
+  }
+}
+
+pub struct ClearModuleCacheRespArgs {
+    pub evicted: u32,
+}
+impl<'a> Default for ClearModuleCacheRespArgs {
+    #[inline]
+    fn default() -> Self {
+        ClearModuleCacheRespArgs {
+            evicted: 0,
+        }
+    }
+}
+pub struct ClearModuleCacheRespBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ClearModuleCacheRespBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_evicted(&mut self, evicted: u32) {
+    self.fbb_.push_slot::<u32>(ClearModuleCacheResp::VT_EVICTED, evicted, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ClearModuleCacheRespBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ClearModuleCacheRespBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ClearModuleCacheResp<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum LoadedModuleEntryOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct LoadedModuleEntry<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for LoadedModuleEntry<'a> {
+    type Inner = LoadedModuleEntry<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> LoadedModuleEntry<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        LoadedModuleEntry {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args LoadedModuleEntryArgs<'args>) -> flatbuffers::WIPOffset<LoadedModuleEntry<'bldr>> {
+      let mut builder = LoadedModuleEntryBuilder::new(_fbb);
+      if let Some(x) = args.referer { builder.add_referer(x); }
+      builder.add_size(args.size);
+      if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      builder.add_is_wasm(args.is_wasm);
+      builder.finish()
+    }
+
+    pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 4;
+    pub const VT_SIZE: flatbuffers::VOffsetT = 6;
+    pub const VT_IS_WASM: flatbuffers::VOffsetT = 8;
+    pub const VT_REFERER: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub fn origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadedModuleEntry::VT_ORIGIN_URL, None)
+  }
+  #[inline]
+  pub fn size(&self) -> u32 {
+    self._tab.get::<u32>(LoadedModuleEntry::VT_SIZE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn is_wasm(&self) -> bool {
+    self._tab.get::<bool>(LoadedModuleEntry::VT_IS_WASM, Some(false)).unwrap()
+  }
+  #[inline]
+  pub fn referer(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(LoadedModuleEntry::VT_REFERER, None)
+  }
+}
+
+pub struct LoadedModuleEntryArgs<'a> {
+    pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub size: u32,
+    pub is_wasm: bool,
+    pub referer: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for LoadedModuleEntryArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        LoadedModuleEntryArgs {
+            origin_url: None,
+            size: 0,
+            is_wasm: false,
+            referer: None,
+        }
+    }
+}
+pub struct LoadedModuleEntryBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> LoadedModuleEntryBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_origin_url(&mut self, origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadedModuleEntry::VT_ORIGIN_URL, origin_url);
+  }
+  #[inline]
+  pub fn add_size(&mut self, size: u32) {
+    self.fbb_.push_slot::<u32>(LoadedModuleEntry::VT_SIZE, size, 0);
+  }
+  #[inline]
+  pub fn add_is_wasm(&mut self, is_wasm: bool) {
+    self.fbb_.push_slot::<bool>(LoadedModuleEntry::VT_IS_WASM, is_wasm, false);
+  }
+  #[inline]
+  pub fn add_referer(&mut self, referer: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LoadedModuleEntry::VT_REFERER, referer);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> LoadedModuleEntryBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    LoadedModuleEntryBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<LoadedModuleEntry<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ModuleImportOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ModuleImport<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for ModuleImport<'a> {
+    type Inner = ModuleImport<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> ModuleImport<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ModuleImport {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ModuleImportArgs<'args>) -> flatbuffers::WIPOffset<ModuleImport<'bldr>> {
+      let mut builder = ModuleImportBuilder::new(_fbb);
+      if let Some(x) = args.dependency { builder.add_dependency(x); }
+      if let Some(x) = args.importer { builder.add_importer(x); }
+      builder.finish()
+    }
+
+    pub const VT_IMPORTER: flatbuffers::VOffsetT = 4;
+    pub const VT_DEPENDENCY: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn importer(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ModuleImport::VT_IMPORTER, None)
+  }
+  #[inline]
+  pub fn dependency(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ModuleImport::VT_DEPENDENCY, None)
+  }
+}
+
+pub struct ModuleImportArgs<'a> {
+    pub importer: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub dependency: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for ModuleImportArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        ModuleImportArgs {
+            importer: None,
+            dependency: None,
+        }
+    }
+}
+pub struct ModuleImportBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ModuleImportBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_importer(&mut self, importer: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ModuleImport::VT_IMPORTER, importer);
+  }
+  #[inline]
+  pub fn add_dependency(&mut self, dependency: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ModuleImport::VT_DEPENDENCY, dependency);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ModuleImportBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ModuleImportBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ModuleImport<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ListLoadedModulesOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ListLoadedModules<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for ListLoadedModules<'a> {
+    type Inner = ListLoadedModules<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> ListLoadedModules<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ListLoadedModules {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        _args: &'args ListLoadedModulesArgs) -> flatbuffers::WIPOffset<ListLoadedModules<'bldr>> {
+      let mut builder = ListLoadedModulesBuilder::new(_fbb);
+      builder.finish()
+    }
+
+
+}
+
+pub struct ListLoadedModulesArgs {
+}
+impl<'a> Default for ListLoadedModulesArgs {
+    #[inline]
+    fn default() -> Self {
+        ListLoadedModulesArgs {
+        }
+    }
+}
+pub struct ListLoadedModulesBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ListLoadedModulesBuilder<'a, 'b> {
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ListLoadedModulesBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ListLoadedModulesBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ListLoadedModules<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ListLoadedModulesRespOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ListLoadedModulesResp<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+impl<'a> flatbuffers::Follow<'a> for ListLoadedModulesResp<'a> {
+    type Inner = ListLoadedModulesResp<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+impl<'a> ListLoadedModulesResp<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ListLoadedModulesResp {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ListLoadedModulesRespArgs<'args>) -> flatbuffers::WIPOffset<ListLoadedModulesResp<'bldr>> {
+      let mut builder = ListLoadedModulesRespBuilder::new(_fbb);
+      if let Some(x) = args.imports { builder.add_imports(x); }
+      if let Some(x) = args.modules { builder.add_modules(x); }
+      builder.finish()
+    }
+
+    pub const VT_MODULES: flatbuffers::VOffsetT = 4;
+    pub const VT_IMPORTS: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn modules(&self) -> Option<flatbuffers::Vector<flatbuffers::ForwardsUOffset<LoadedModuleEntry<'a>>>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<flatbuffers::ForwardsUOffset<LoadedModuleEntry<'a>>>>>(ListLoadedModulesResp::VT_MODULES, None)
+  }
+  #[inline]
+  pub fn imports(&self) -> Option<flatbuffers::Vector<flatbuffers::ForwardsUOffset<ModuleImport<'a>>>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<flatbuffers::ForwardsUOffset<ModuleImport<'a>>>>>(ListLoadedModulesResp::VT_IMPORTS, None)
+  }
+}
+
+pub struct ListLoadedModulesRespArgs<'a> {
+    pub modules: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<LoadedModuleEntry<'a >>>>>,
+    pub imports: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<ModuleImport<'a >>>>>,
+}
+impl<'a> Default for ListLoadedModulesRespArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        ListLoadedModulesRespArgs {
+            modules: None,
+            imports: None,
+        }
+    }
+}
+pub struct ListLoadedModulesRespBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ListLoadedModulesRespBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_modules(&mut self, modules: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<LoadedModuleEntry<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ListLoadedModulesResp::VT_MODULES, modules);
+  }
+  #[inline]
+  pub fn add_imports(&mut self, imports: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<ModuleImport<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ListLoadedModulesResp::VT_IMPORTS, imports);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ListLoadedModulesRespBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ListLoadedModulesRespBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ListLoadedModulesResp<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ResolveModuleOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ResolveModule<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for ResolveModule<'a> {
+    type Inner = ResolveModule<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> ResolveModule<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ResolveModule {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ResolveModuleArgs<'args>) -> flatbuffers::WIPOffset<ResolveModule<'bldr>> {
+      let mut builder = ResolveModuleBuilder::new(_fbb);
+      if let Some(x) = args.referer_origin_url { builder.add_referer_origin_url(x); }
+      if let Some(x) = args.specifier { builder.add_specifier(x); }
+      builder.finish()
+    }
+
+    pub const VT_SPECIFIER: flatbuffers::VOffsetT = 4;
+    pub const VT_REFERER_ORIGIN_URL: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub fn specifier(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ResolveModule::VT_SPECIFIER, None)
+  }
+  #[inline]
+  pub fn referer_origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ResolveModule::VT_REFERER_ORIGIN_URL, None)
+  }
+}
+
+pub struct ResolveModuleArgs<'a> {
+    pub specifier: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub referer_origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for ResolveModuleArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        ResolveModuleArgs {
+            specifier: None,
+            referer_origin_url: None,
+        }
+    }
+}
+pub struct ResolveModuleBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ResolveModuleBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_specifier(&mut self, specifier: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ResolveModule::VT_SPECIFIER, specifier);
+  }
+  #[inline]
+  pub fn add_referer_origin_url(&mut self, referer_origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ResolveModule::VT_REFERER_ORIGIN_URL, referer_origin_url);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ResolveModuleBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ResolveModuleBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ResolveModule<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum ResolveModuleRespOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct ResolveModuleResp<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for ResolveModuleResp<'a> {
+    type Inner = ResolveModuleResp<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> ResolveModuleResp<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        ResolveModuleResp {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args ResolveModuleRespArgs<'args>) -> flatbuffers::WIPOffset<ResolveModuleResp<'bldr>> {
+      let mut builder = ResolveModuleRespBuilder::new(_fbb);
+      if let Some(x) = args.format { builder.add_format(x); }
+      if let Some(x) = args.resolver { builder.add_resolver(x); }
+      if let Some(x) = args.protocol { builder.add_protocol(x); }
+      if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      builder.finish()
+    }
+
+    pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 4;
+    pub const VT_PROTOCOL: flatbuffers::VOffsetT = 6;
+    pub const VT_RESOLVER: flatbuffers::VOffsetT = 8;
+    pub const VT_FORMAT: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub fn origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ResolveModuleResp::VT_ORIGIN_URL, None)
+  }
+  #[inline]
+  pub fn protocol(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ResolveModuleResp::VT_PROTOCOL, None)
+  }
+  #[inline]
+  pub fn resolver(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ResolveModuleResp::VT_RESOLVER, None)
+  }
+  #[inline]
+  pub fn format(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(ResolveModuleResp::VT_FORMAT, None)
+  }
+}
+
+pub struct ResolveModuleRespArgs<'a> {
+    pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub protocol: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub resolver: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub format: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for ResolveModuleRespArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        ResolveModuleRespArgs {
+            origin_url: None,
+            protocol: None,
+            resolver: None,
+            format: None,
+        }
+    }
+}
+pub struct ResolveModuleRespBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> ResolveModuleRespBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_origin_url(&mut self, origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ResolveModuleResp::VT_ORIGIN_URL, origin_url);
+  }
+  #[inline]
+  pub fn add_protocol(&mut self, protocol: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ResolveModuleResp::VT_PROTOCOL, protocol);
+  }
+  #[inline]
+  pub fn add_resolver(&mut self, resolver: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ResolveModuleResp::VT_RESOLVER, resolver);
+  }
+  #[inline]
+  pub fn add_format(&mut self, format: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(ResolveModuleResp::VT_FORMAT, format);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> ResolveModuleRespBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    ResolveModuleRespBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<ResolveModuleResp<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum StoreCompiledWasmOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct StoreCompiledWasm<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for StoreCompiledWasm<'a> {
+    type Inner = StoreCompiledWasm<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> StoreCompiledWasm<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        StoreCompiledWasm {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args StoreCompiledWasmArgs<'args>) -> flatbuffers::WIPOffset<StoreCompiledWasm<'bldr>> {
+      let mut builder = StoreCompiledWasmBuilder::new(_fbb);
+      if let Some(x) = args.origin_url { builder.add_origin_url(x); }
+      builder.finish()
+    }
+
+    pub const VT_ORIGIN_URL: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub fn origin_url(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(StoreCompiledWasm::VT_ORIGIN_URL, None)
+  }
+}
+
+pub struct StoreCompiledWasmArgs<'a> {
+    pub origin_url: Option<flatbuffers::WIPOffset<&'a  str>>,
+}
+impl<'a> Default for StoreCompiledWasmArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        StoreCompiledWasmArgs {
+            origin_url: None,
+        }
+    }
+}
+pub struct StoreCompiledWasmBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> StoreCompiledWasmBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_origin_url(&mut self, origin_url: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(StoreCompiledWasm::VT_ORIGIN_URL, origin_url);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> StoreCompiledWasmBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    StoreCompiledWasmBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<StoreCompiledWasm<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
 #[inline]
 pub fn get_root_as_base<'a>(buf: &'a [u8]) -> Base<'a> {
   flatbuffers::get_root::<Base<'a>>(buf)
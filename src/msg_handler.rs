@@ -41,6 +41,15 @@ impl MessageHandler for DefaultMessageHandler {
             msg::Any::DnsResponse => ops::dns::op_dns_response,
             msg::Any::AddEventListener => ops::events::op_add_event_ln,
             msg::Any::LoadModule => ops::modules::op_load_module,
+            msg::Any::LoadModuleChunk => ops::modules::op_load_module_chunk,
+            msg::Any::ClearModuleCache => ops::modules::op_clear_module_cache,
+            msg::Any::StoreCompiledWasm => ops::modules::op_store_compiled_wasm,
+            msg::Any::ListLoadedModules => ops::modules::op_list_loaded_modules,
+            msg::Any::ModuleCached => ops::modules::op_module_cached,
+            msg::Any::ResolveModule => ops::modules::op_resolve_module,
+            msg::Any::RegisterModuleResolver => ops::modules::op_register_module_resolver,
+            msg::Any::GuestResolveResult => ops::modules::op_guest_resolve_result,
+            msg::Any::RuntimeMetrics => ops::metrics::op_runtime_metrics,
             msg::Any::ImageApplyTransforms => ops::image::op_image_transform,
             msg::Any::AcmeGetChallenge => ops::acme::op_get_challenge,
             msg::Any::OsExit => ops::os::op_exit,
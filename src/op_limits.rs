@@ -0,0 +1,292 @@
+use crate::errors::*;
+use crate::msg;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use futures::sync::oneshot;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    /** Wait in the queue for a slot, failing only once the queue is full too. */
+    Queue,
+    /** Fail right away with a `Backpressure` error. */
+    Fail,
+}
+
+/**
+ * Caps on how many async ops a runtime may have pending at once. Expensive ops count towards
+ * both `max_pending` and their own, lower, `max_pending_expensive`.
+ */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct OpLimitsConfig {
+    pub max_pending: usize,
+    pub max_pending_expensive: usize,
+    pub overflow: OverflowPolicy,
+    /** How many ops may wait for a slot with the `queue` policy. */
+    pub max_queued: usize,
+}
+
+impl Default for OpLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_pending: 1024,
+            max_pending_expensive: 64,
+            overflow: OverflowPolicy::Fail,
+            max_queued: 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpClass {
+    Standard,
+    Expensive,
+}
+
+/**
+ * Which cap an op counts against, or `None` for ops that are never limited: the ones other
+ * pending ops wait on to finish (responses, stream chunks, timers), so capping them could
+ * leave every slot held by ops that can't complete.
+ */
+pub fn op_class(msg_type: msg::Any) -> Option<OpClass> {
+    use crate::msg::Any::*;
+    match msg_type {
+        HttpResponse | StreamChunk | DnsResponse | GuestResolveResult | TimerStart | TimerClear
+        | AddEventListener | RuntimeMetrics | OsExit | LoadModuleChunk => None,
+        LoadModule | ImageApplyTransforms => Some(OpClass::Expensive),
+        _ => Some(OpClass::Standard),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct PendingOps {
+    pub pending: usize,
+    pub pending_expensive: usize,
+    pub queued: usize,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    pending: usize,
+    pending_expensive: usize,
+    queue: VecDeque<(OpClass, oneshot::Sender<OpPermit>)>,
+}
+
+impl LimiterState {
+    fn fits(&self, config: &OpLimitsConfig, class: OpClass) -> bool {
+        self.pending < config.max_pending
+            && (class == OpClass::Standard
+                || self.pending_expensive < config.max_pending_expensive)
+    }
+
+    fn take(&mut self, class: OpClass) {
+        self.pending += 1;
+        if class == OpClass::Expensive {
+            self.pending_expensive += 1;
+        }
+    }
+}
+
+/**
+ * Held by an op for as long as it's pending; dropping it frees the slot for the next
+ * queued op.
+ */
+pub struct OpPermit {
+    limiter: OpLimiter,
+    class: OpClass,
+}
+
+impl Drop for OpPermit {
+    fn drop(&mut self) {
+        self.limiter.release(self.class);
+    }
+}
+
+pub enum Admission {
+    Now(OpPermit),
+    Queued(oneshot::Receiver<OpPermit>),
+    Rejected(FlyError),
+}
+
+/**
+ * Admits a runtime's async ops under its `OpLimitsConfig`. Clones share the same counts.
+ */
+#[derive(Clone)]
+pub struct OpLimiter {
+    config: Arc<OpLimitsConfig>,
+    state: Arc<Mutex<LimiterState>>,
+}
+
+impl OpLimiter {
+    pub fn new(config: OpLimitsConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(LimiterState::default())),
+        }
+    }
+
+    pub fn admit(&self, msg_type: msg::Any, class: OpClass) -> Admission {
+        let mut state = self.state.lock().unwrap();
+        // Anything still queued doesn't fit either, so this can't jump ahead of its own class.
+        if state.fits(&self.config, class) {
+            state.take(class);
+            return Admission::Now(OpPermit {
+                limiter: self.clone(),
+                class,
+            });
+        }
+        if self.config.overflow == OverflowPolicy::Queue
+            && state.queue.len() < self.config.max_queued
+        {
+            let (tx, rx) = oneshot::channel();
+            state.queue.push_back((class, tx));
+            return Admission::Queued(rx);
+        }
+        Admission::Rejected(new(
+            ErrorKind::Backpressure,
+            format!(
+                "{:?} rejected: {} ops pending ({} expensive), limits are {} ({} expensive)",
+                msg_type,
+                state.pending,
+                state.pending_expensive,
+                self.config.max_pending,
+                self.config.max_pending_expensive
+            ),
+        ))
+    }
+
+    pub fn pending(&self) -> PendingOps {
+        let state = self.state.lock().unwrap();
+        PendingOps {
+            pending: state.pending,
+            pending_expensive: state.pending_expensive,
+            queued: state.queue.len(),
+        }
+    }
+
+    /**
+     * Frees a slot and grants it, and any others now free, to queued ops in order. A queued
+     * expensive op that still doesn't fit doesn't hold up standard ops behind it.
+     */
+    fn release(&self, class: OpClass) {
+        let mut granted = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            state.pending -= 1;
+            if class == OpClass::Expensive {
+                state.pending_expensive -= 1;
+            }
+            let mut i = 0;
+            while i < state.queue.len() {
+                let class = state.queue[i].0;
+                if !state.fits(&self.config, class) {
+                    i += 1;
+                    continue;
+                }
+                let (class, tx) = state.queue.remove(i).unwrap();
+                state.take(class);
+                granted.push((class, tx));
+            }
+        }
+        // Outside the lock: a permit for an op that was dropped while queued comes straight
+        // back here through its own drop.
+        for (class, tx) in granted {
+            let _ = tx.send(OpPermit {
+                limiter: self.clone(),
+                class,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::Future;
+
+    fn limiter(overflow: OverflowPolicy) -> OpLimiter {
+        OpLimiter::new(OpLimitsConfig {
+            max_pending: 2,
+            max_pending_expensive: 1,
+            overflow,
+            max_queued: 1,
+        })
+    }
+
+    fn admitted(admission: Admission) -> OpPermit {
+        match admission {
+            Admission::Now(permit) => permit,
+            _ => panic!("expected the op to be admitted"),
+        }
+    }
+
+    #[test]
+    fn test_rejects_past_the_caps() {
+        let limiter = limiter(OverflowPolicy::Fail);
+        let module = admitted(limiter.admit(msg::Any::LoadModule, OpClass::Expensive));
+        match limiter.admit(msg::Any::LoadModule, OpClass::Expensive) {
+            Admission::Rejected(e) => assert_eq!(ErrorKind::Backpressure, e.kind()),
+            _ => panic!("expected a second module load to be rejected"),
+        }
+        let _fetch = admitted(limiter.admit(msg::Any::HttpRequest, OpClass::Standard));
+        assert!(match limiter.admit(msg::Any::DataGet, OpClass::Standard) {
+            Admission::Rejected(_) => true,
+            _ => false,
+        });
+        drop(module);
+        assert_eq!(
+            PendingOps {
+                pending: 1,
+                pending_expensive: 0,
+                queued: 0
+            },
+            limiter.pending()
+        );
+        admitted(limiter.admit(msg::Any::LoadModule, OpClass::Expensive));
+    }
+
+    #[test]
+    fn test_queued_ops_get_freed_slots_in_order() {
+        let limiter = limiter(OverflowPolicy::Queue);
+        let first = admitted(limiter.admit(msg::Any::LoadModule, OpClass::Expensive));
+        let _second = admitted(limiter.admit(msg::Any::DataGet, OpClass::Standard));
+        let queued = match limiter.admit(msg::Any::DataGet, OpClass::Standard) {
+            Admission::Queued(rx) => rx,
+            _ => panic!("expected the op to be queued"),
+        };
+        assert!(match limiter.admit(msg::Any::DataGet, OpClass::Standard) {
+            Admission::Rejected(_) => true,
+            _ => false,
+        });
+        assert_eq!(1, limiter.pending().queued);
+        drop(first);
+        let permit = queued.wait().unwrap();
+        assert_eq!(
+            PendingOps {
+                pending: 2,
+                pending_expensive: 0,
+                queued: 0
+            },
+            limiter.pending()
+        );
+        drop(permit);
+        assert_eq!(1, limiter.pending().pending);
+    }
+
+    #[test]
+    fn test_abandoned_queued_ops_give_their_slot_back() {
+        let limiter = limiter(OverflowPolicy::Queue);
+        let first = admitted(limiter.admit(msg::Any::DataGet, OpClass::Standard));
+        let _second = admitted(limiter.admit(msg::Any::DataGet, OpClass::Standard));
+        match limiter.admit(msg::Any::DataGet, OpClass::Standard) {
+            Admission::Queued(rx) => drop(rx),
+            _ => panic!("expected the op to be queued"),
+        }
+        drop(first);
+        assert_eq!(1, limiter.pending().pending);
+        admitted(limiter.admit(msg::Any::DataGet, OpClass::Standard));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::msg;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/**
+ * `msg::Any` is a `u8`, so every message type has a slot.
+ */
+const OP_SLOTS: usize = 256;
+
+#[derive(Default)]
+struct OpCounters {
+    calls: AtomicUsize,
+    errors: AtomicUsize,
+    total_us: AtomicUsize,
+    max_us: AtomicUsize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OpStats {
+    pub op: String,
+    pub calls: usize,
+    pub errors: usize,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+/**
+ * Per-runtime invocation counts, error counts and latency of every op, by message type.
+ * Recording is a handful of relaxed atomic adds, so the dispatcher does it for every message.
+ * Latency runs from dispatch until the op's future resolves, so async ops include the time
+ * they spent waiting.
+ */
+pub struct OpMetrics {
+    ops: Vec<OpCounters>,
+}
+
+impl Default for OpMetrics {
+    fn default() -> Self {
+        Self {
+            ops: (0..OP_SLOTS).map(|_| OpCounters::default()).collect(),
+        }
+    }
+}
+
+fn micros(elapsed: Duration) -> usize {
+    elapsed.as_secs() as usize * 1_000_000 + elapsed.subsec_micros() as usize
+}
+
+impl OpMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, op: msg::Any, elapsed: Duration, failed: bool) {
+        let counters = &self.ops[op as usize];
+        let us = micros(elapsed);
+        counters.calls.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        counters.total_us.fetch_add(us, Ordering::Relaxed);
+        counters.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    /**
+     * Every op called so far, the one that took the most time in total first. With `reset`,
+     * each op's counters are zeroed as they're read, so the next snapshot covers only what
+     * happened since.
+     */
+    pub fn snapshot(&self, reset: bool) -> Vec<OpStats> {
+        let take = |counter: &AtomicUsize| {
+            if reset {
+                counter.swap(0, Ordering::Relaxed)
+            } else {
+                counter.load(Ordering::Relaxed)
+            }
+        };
+        let mut stats: Vec<OpStats> = self
+            .ops
+            .iter()
+            .enumerate()
+            .filter(|(_, counters)| counters.calls.load(Ordering::Relaxed) > 0)
+            .map(|(i, counters)| OpStats {
+                op: msg::enum_name_any(flatbuffers::read_scalar_at::<msg::Any>(&[i as u8], 0))
+                    .to_string(),
+                calls: take(&counters.calls),
+                errors: take(&counters.errors),
+                total_ms: take(&counters.total_us) as f64 / 1000.0,
+                max_ms: take(&counters.max_us) as f64 / 1000.0,
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.total_ms
+                .partial_cmp(&a.total_ms)
+                .unwrap()
+                .then_with(|| a.op.cmp(&b.op))
+        });
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_calls_errors_and_latency_per_op() {
+        let metrics = OpMetrics::new();
+        metrics.record(msg::Any::CacheGet, Duration::from_millis(2), false);
+        metrics.record(msg::Any::CacheGet, Duration::from_millis(4), true);
+        metrics.record(msg::Any::LoadModule, Duration::from_millis(10), false);
+        assert_eq!(
+            vec![
+                OpStats {
+                    op: "LoadModule".to_string(),
+                    calls: 1,
+                    errors: 0,
+                    total_ms: 10.0,
+                    max_ms: 10.0,
+                },
+                OpStats {
+                    op: "CacheGet".to_string(),
+                    calls: 2,
+                    errors: 1,
+                    total_ms: 6.0,
+                    max_ms: 4.0,
+                },
+            ],
+            metrics.snapshot(false)
+        );
+        assert_eq!(2, metrics.snapshot(true).len());
+        assert!(metrics.snapshot(false).is_empty());
+    }
+}
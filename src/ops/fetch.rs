@@ -313,7 +313,10 @@ pub fn op_http_response(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box
 
 fn file_request(rt: &mut Runtime, cmd_id: u32, url: &str) -> Box<Op> {
     let req_id = get_next_stream_id();
-    let path: String = url.chars().skip(7).collect();
+    // percent-decoded, so a path with spaces or non-ASCII characters survives the url
+    let path = url::percent_encoding::percent_decode(&url.as_bytes()["file://".len()..])
+        .decode_utf8_lossy()
+        .into_owned();
 
     let ptr = rt.ptr;
 
@@ -0,0 +1,106 @@
+use crate::msg;
+use flatbuffers::FlatBufferBuilder;
+
+use crate::op_limits::PendingOps;
+use crate::op_metrics::OpStats;
+use crate::runtime::Runtime;
+use crate::utils::*;
+use libfly::*;
+
+use futures::future;
+
+/**
+ * Hands JS the runtime's per-op table and its pending op counts. The snapshot is taken before this op's own call is
+ * recorded, so it never shows up in its own result.
+ */
+pub fn op_runtime_metrics(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let cmd_id = base.cmd_id();
+    let msg = base.msg_as_runtime_metrics().unwrap();
+    let stats = rt.op_metrics.snapshot(msg.reset());
+    let pending = rt.op_limiter.pending();
+
+    Box::new(future::lazy(move || {
+        let builder = &mut FlatBufferBuilder::new();
+        let msg = runtime_metrics_ready(builder, &stats, pending);
+        Ok(serialize_response(
+            cmd_id,
+            builder,
+            msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::RuntimeMetricsReady,
+                ..Default::default()
+            },
+        ))
+    }))
+}
+
+fn saturating_u32(n: usize) -> u32 {
+    n.min(u32::max_value() as usize) as u32
+}
+
+fn runtime_metrics_ready<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    stats: &[OpStats],
+    pending: PendingOps,
+) -> flatbuffers::WIPOffset<msg::RuntimeMetricsReady<'a>> {
+    let ops: Vec<_> = stats
+        .iter()
+        .map(|op| {
+            let name = builder.create_string(&op.op);
+            msg::OpMetric::create(
+                builder,
+                &msg::OpMetricArgs {
+                    op: Some(name),
+                    calls: saturating_u32(op.calls),
+                    errors: saturating_u32(op.errors),
+                    total_ms: op.total_ms,
+                    max_ms: op.max_ms,
+                },
+            )
+        })
+        .collect();
+    let ops = builder.create_vector(&ops);
+    msg::RuntimeMetricsReady::create(
+        builder,
+        &msg::RuntimeMetricsReadyArgs {
+            ops: Some(ops),
+            pending: saturating_u32(pending.pending),
+            pending_expensive: saturating_u32(pending.pending_expensive),
+            queued: saturating_u32(pending.queued),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op_metrics::OpMetrics;
+    use std::time::Duration;
+
+    #[test]
+    fn test_metrics_table_is_encoded_in_snapshot_order() {
+        let metrics = OpMetrics::new();
+        metrics.record(msg::Any::DataGet, Duration::from_millis(1), true);
+        metrics.record(msg::Any::HttpRequest, Duration::from_millis(30), false);
+
+        let builder = &mut FlatBufferBuilder::new();
+        let pending = PendingOps {
+            pending: 3,
+            pending_expensive: 1,
+            queued: 0,
+        };
+        let msg = runtime_metrics_ready(builder, &metrics.snapshot(false), pending);
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let msg = flatbuffers::get_root::<msg::RuntimeMetricsReady>(&data);
+        let ops = msg.ops().unwrap();
+        assert_eq!(2, ops.len());
+        assert_eq!(Some("HttpRequest"), ops.get(0).op());
+        assert_eq!(30.0, ops.get(0).total_ms());
+        assert_eq!(Some("DataGet"), ops.get(1).op());
+        assert_eq!(1, ops.get(1).calls());
+        assert_eq!(1, ops.get(1).errors());
+        assert_eq!(3, msg.pending());
+        assert_eq!(1, msg.pending_expensive());
+    }
+}
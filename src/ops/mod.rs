@@ -6,6 +6,7 @@ pub mod dns;
 pub mod events;
 pub mod fetch;
 pub mod image;
+pub mod metrics;
 pub mod modules;
 pub mod os;
 pub mod source_map;
@@ -2,15 +2,76 @@ use crate::msg;
 use flatbuffers::FlatBufferBuilder;
 
 use crate::runtime::Runtime;
-use crate::errors::permission_denied;
+use crate::errors::{permission_denied, FlyError, FlyResult};
 use libfly::*;
 
 use crate::utils::*;
 
-use crate::module_resolver::RefererInfo;
+use crate::guest_resolver::{check_guest_protocol, GuestModuleResolver, GuestResolution};
+use crate::module_chunks::{ChunkedSource, ModuleChunks};
+use crate::module_resolver::{
+    cap_referer_source, wasm_bytes, ImportOptions, LoadedModule, LoadedModuleFuture,
+    ModuleCacheState, ModuleGraphSnapshot, ModuleKind, RefererInfo, ResolvedModuleInfo,
+};
 
-use futures::future;
+use futures::{future, Future};
 
+/**
+ * Stands in for the referer of loads that didn't name one, when the runtime was built with a
+ * default referer.
+ */
+fn default_referer_info(rt: &Runtime) -> Option<RefererInfo> {
+    rt.default_referer.as_ref().map(|origin_url| RefererInfo {
+        origin_url: origin_url.clone(),
+        is_wasm: Some(false),
+        source_code: None,
+        identifier_hash: None,
+        line: None,
+        column: None,
+        app_id: Some(rt.name.clone()),
+        deadline: None,
+    })
+}
+
+/**
+ * Whether the module the runtime compiled from `origin_url` is wasm; `None` if it hasn't
+ * compiled one.
+ */
+fn compiled_is_wasm(rt: &Runtime, origin_url: &str) -> Option<bool> {
+    let hash = rt.module_identifier_hash(origin_url)?;
+    rt.get_module_info(&hash).map(|info| info.kind.is_wasm())
+}
+
+/**
+ * The referer described by a `LoadModule` message. A zero identifier hash means the JS side
+ * didn't know it; V8 never hands out zero as an identity hash. Positions are 1-based, so a
+ * zero line or column is unknown too.
+ */
+fn referer_info(msg: &msg::LoadModule, app_id: &str) -> Option<RefererInfo> {
+    let origin_url = msg.referer_origin_url()?;
+    Some(RefererInfo {
+        origin_url: origin_url.to_string(),
+        is_wasm: Some(msg.referer_is_wasm()),
+        source_code: msg.referer_source().map(cap_referer_source),
+        identifier_hash: match msg.referer_identifier_hash() {
+            0 => None,
+            hash => Some(hash),
+        },
+        line: Some(msg.referer_line()).filter(|&line| line > 0),
+        column: Some(msg.referer_column()).filter(|&column| column > 0),
+        app_id: Some(app_id.to_string()),
+        deadline: None,
+    })
+}
+
+/**
+ * Resolves and loads a module for the JavaScript module loader. Failures answer with the
+ * kind of their `ModuleResolutionError`, e.g. `ModuleNotFound` or `InvalidSpecifier`, so
+ * the loader can throw the matching error, and `ModuleTypeMismatch` for a module that isn't
+ * the type its import asserted. A top-level load gets the manager's `total_deadline`, if it
+ * has one, for everything it resolves: its first load starts it and answers with its id, which
+ * the loads of the imports under it name. Past it, they fail with `DeadlineExceeded`.
+ */
 pub fn op_load_module(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
     let cmd_id = base.cmd_id();
     let msg = base.msg_as_load_module().unwrap();
@@ -20,44 +81,1511 @@ pub fn op_load_module(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<
         return odd_future(permission_denied());
     }
 
-    let referer_info = match msg.referer_origin_url() {
-        Some(v) => Some(RefererInfo {
-            origin_url: v.to_string(),
-            is_wasm: Some(false),
-            source_code: None,
-            indentifier_hash: None,
-        }),
-        None => None,
+    let import_options = match ImportOptions::from_asserted(msg.asserted_type()) {
+        Ok(options) => options,
+        Err(e) => return odd_future(e),
+    };
+
+    let deadline = match (msg.deadline(), rt.module_resolver_manager.total_deadline()) {
+        (_, None) => None,
+        (0, Some(budget)) => Some(rt.module_deadlines.start(budget)),
+        (id, Some(_)) => match rt.module_deadlines.get(id) {
+            Ok(deadline) => Some((id, deadline)),
+            Err(e) => return odd_future(e),
+        },
+    };
+    let deadline_id = deadline.as_ref().map_or(0, |(id, _)| *id);
+    let deadline = deadline.map(|(_, deadline)| deadline);
+    let referer_info = referer_info(&msg, &rt.name)
+        .or_else(|| default_referer_info(rt))
+        .map(|mut info| {
+            // loaders that don't know what their referer compiled to say it's not wasm
+            if let Some(is_wasm) = compiled_is_wasm(rt, &info.origin_url) {
+                info.is_wasm = Some(is_wasm);
+            }
+            if info.identifier_hash.is_none() {
+                info.identifier_hash = rt.module_identifier_hash(&info.origin_url);
+            }
+            if info.source_code.is_none() {
+                info.source_code = rt
+                    .virtual_modules
+                    .get(&info.origin_url)
+                    .map(|m| cap_referer_source(m.source));
+            }
+            info.deadline = deadline.clone();
+            info
+        });
+
+    let ptr = rt.ptr;
+    let entry = msg.entry();
+    let manager = &rt.module_resolver_manager;
+    let loading: LoadedModuleFuture = match referer_info {
+        Some(referer_info) => {
+            manager.resolve_import(specifier_url, Some(referer_info), import_options)
+        }
+        None => {
+            let loaded = match deadline {
+                Some(deadline) => deadline.bound(
+                    specifier_url.clone(),
+                    manager.load_entry_point(specifier_url),
+                ),
+                None => manager.load_entry_point(specifier_url),
+            };
+            Box::new(loaded.and_then(move |module| import_options.apply(module)))
+        }
+    };
+
+    Box::new(loading.and_then(move |module| {
+        let is_main = ptr
+            .to_runtime()
+            .entry_modules
+            .loaded(&module.origin_url, entry);
+
+        let builder = &mut FlatBufferBuilder::new();
+        let (msg_type, msg) = load_module_resp(
+            builder,
+            &module,
+            is_main,
+            deadline_id,
+            &ptr.to_runtime().module_chunks,
+        )?;
+        Ok(serialize_response(
+            cmd_id,
+            builder,
+            msg::BaseArgs {
+                msg: Some(msg),
+                msg_type,
+                ..Default::default()
+            },
+        ))
+    }))
+}
+
+/**
+ * Answers with a chunk of a module source that `op_load_module` split up.
+ */
+pub fn op_load_module_chunk(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let cmd_id = base.cmd_id();
+    let msg = base.msg_as_load_module_chunk().unwrap();
+    let chunk = match rt.module_chunks.chunk(msg.id(), msg.index()) {
+        Ok(chunk) => chunk,
+        Err(e) => return odd_future(e),
     };
+    let builder = &mut FlatBufferBuilder::new();
+    let msg = load_module_chunk_resp(builder, &chunk);
+    ok_future(serialize_response(
+        cmd_id,
+        builder,
+        msg::BaseArgs {
+            msg: Some(msg.as_union_value()),
+            msg_type: msg::Any::LoadModuleChunkResp,
+            ..Default::default()
+        },
+    ))
+}
+
+/**
+ * Reports whether the module a specifier resolves to is already cached, without loading it.
+ * Unlike `op_load_module` this is open to guest code: it deliberately never fetches, so
+ * guests can pick a fast path before paying for a cold import.
+ */
+pub fn op_module_cached(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let cmd_id = base.cmd_id();
+    let msg = base.msg_as_module_cached().unwrap();
+    let specifier_url = msg.specifier_url().unwrap().to_string();
+    let referer_info = msg
+        .referer_origin_url()
+        .map(|origin_url| RefererInfo {
+            origin_url: origin_url.to_string(),
+            is_wasm: Some(compiled_is_wasm(rt, origin_url).unwrap_or(false)),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: Some(rt.name.clone()),
+            deadline: None,
+        })
+        .or_else(|| default_referer_info(rt));
 
-    let module = match rt
+    let state = match rt
         .module_resolver_manager
-        .resolve_module(specifier_url, referer_info)
+        .cache_state(specifier_url, referer_info)
     {
-        Ok(m) => m,
+        Ok(state) => state,
         Err(e) => return odd_future(e.into()),
     };
 
     Box::new(future::lazy(move || {
         let builder = &mut FlatBufferBuilder::new();
-        let origin_url = builder.create_string(&module.origin_url);
-        let source_code = builder.create_string(&module.loaded_source.source);
+        let msg = module_cached_resp(builder, &state);
+        Ok(serialize_response(
+            cmd_id,
+            builder,
+            msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::ModuleCachedResp,
+                ..Default::default()
+            },
+        ))
+    }))
+}
+
+/**
+ * Answers with where a specifier resolves to and which resolver claimed it, for tooling that
+ * checks imports without running them. Like `op_module_cached` it never loads the module.
+ * Resolving probes resolvers, the disk included, so it needs dev tools.
+ */
+pub fn op_resolve_module(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let cmd_id = base.cmd_id();
+    let msg = base.msg_as_resolve_module().unwrap();
+    let specifier = msg.specifier().unwrap().to_string();
+
+    if !rt.dev_tools {
+        return odd_future(permission_denied());
+    }
+    let referer_info = msg
+        .referer_origin_url()
+        .map(|origin_url| RefererInfo {
+            origin_url: origin_url.to_string(),
+            is_wasm: Some(compiled_is_wasm(rt, origin_url).unwrap_or(false)),
+            source_code: None,
+            identifier_hash: None,
+            line: None,
+            column: None,
+            app_id: Some(rt.name.clone()),
+            deadline: None,
+        })
+        .or_else(|| default_referer_info(rt));
+
+    let info = match rt
+        .module_resolver_manager
+        .resolve_only(specifier, referer_info)
+    {
+        Ok(info) => info,
+        Err(e) => return odd_future(e),
+    };
 
-        let msg = msg::LoadModuleResp::create(
+    Box::new(future::lazy(move || {
+        let builder = &mut FlatBufferBuilder::new();
+        let msg = resolve_module_resp(builder, &info);
+        Ok(serialize_response(
+            cmd_id,
             builder,
-            &msg::LoadModuleRespArgs {
-                origin_url: Some(origin_url),
-                source_code: Some(source_code),
+            msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::ResolveModuleResp,
+                ..Default::default()
             },
-        );
+        ))
+    }))
+}
+
+/**
+ * Evicts a module, or every module, this runtime compiled from the module caches, for dev
+ * tooling that reloads what changed on disk. Answers with how many entries were evicted.
+ * Other runtimes may share the caches, so modules only they compiled are left alone.
+ */
+pub fn op_clear_module_cache(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let cmd_id = base.cmd_id();
+    let msg = base.msg_as_clear_module_cache().unwrap();
+
+    if !rt.dev_tools {
+        return odd_future(permission_denied());
+    }
+
+    let mut evicted = 0;
+    for origin_url in rt.compiled_origin_urls() {
+        if msg.origin_url().map_or(false, |url| url != origin_url) {
+            continue;
+        }
+        match rt
+            .module_resolver_manager
+            .clear_module_cache(Some(&origin_url))
+        {
+            Ok(n) => evicted += n,
+            Err(e) => return odd_future(e),
+        }
+    }
+    let builder = &mut FlatBufferBuilder::new();
+    let msg = msg::ClearModuleCacheResp::create(
+        builder,
+        &msg::ClearModuleCacheRespArgs {
+            evicted: evicted as u32,
+        },
+    );
+    ok_future(serialize_response(
+        cmd_id,
+        builder,
+        msg::BaseArgs {
+            msg: Some(msg.as_union_value()),
+            msg_type: msg::Any::ClearModuleCacheResp,
+            ..Default::default()
+        },
+    ))
+}
+
+/**
+ * Keeps what V8 serialized compiling the wasm module at `origin_url`, the raw data, in the
+ * manager's wasm cache. The module is resolved again to key it by its binary; with a
+ * resolution cache that's a lookup, and it only happens the first time a binary is compiled.
+ */
+pub fn op_store_compiled_wasm(rt: &mut Runtime, base: &msg::Base, raw: fly_buf) -> Box<Op> {
+    let msg = base.msg_as_store_compiled_wasm().unwrap();
+    let origin_url = msg.origin_url().unwrap().to_string();
+
+    if !rt.dev_tools {
+        return odd_future(permission_denied());
+    }
+
+    let compiled = unsafe { std::slice::from_raw_parts(raw.data_ptr, raw.data_len) }.to_vec();
+    let ptr = rt.ptr;
+    Box::new(
+        rt.module_resolver_manager
+            .resolve_module(origin_url.clone(), None)
+            .and_then(move |module| {
+                if module.origin_url != origin_url || !module.loaded_source.is_wasm() {
+                    return Err(FlyError::from(format!(
+                        "{} isn't the wasm module it was compiled from",
+                        origin_url
+                    )));
+                }
+                let wasm = wasm_bytes(&module.loaded_source)?;
+                ptr.to_runtime()
+                    .module_resolver_manager
+                    .store_compiled_wasm(&origin_url, &wasm, &compiled);
+                Ok(None)
+            }),
+    )
+}
+
+/**
+ * Lists every module loaded so far and the imports between them, for dev tools. The listing
+ * is copied out of the manager first, so nothing is held while it's serialized.
+ */
+pub fn op_list_loaded_modules(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let cmd_id = base.cmd_id();
+
+    if !rt.dev_tools {
+        return odd_future(permission_denied());
+    }
+    let snapshot = rt
+        .module_resolver_manager
+        .loaded_modules()
+        .unwrap_or_default();
+
+    Box::new(future::lazy(move || {
+        let builder = &mut FlatBufferBuilder::new();
+        let msg = list_loaded_modules_resp(builder, &snapshot);
         Ok(serialize_response(
             cmd_id,
             builder,
             msg::BaseArgs {
                 msg: Some(msg.as_union_value()),
-                msg_type: msg::Any::LoadModuleResp,
+                msg_type: msg::Any::ListLoadedModulesResp,
                 ..Default::default()
             },
         ))
     }))
 }
+
+/**
+ * Lets the guest serve a protocol of its own, behind every resolver the host set up. Its
+ * callback is asked through `GuestResolve` events and answers with `op_guest_resolve_result`.
+ */
+pub fn op_register_module_resolver(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let msg = base.msg_as_register_module_resolver().unwrap();
+    let protocol = msg.protocol().unwrap();
+
+    if !rt.guest_resolvers.enabled() {
+        return odd_future(permission_denied());
+    }
+    if let Err(e) = check_guest_protocol(protocol) {
+        return odd_future(e);
+    }
+    let resolver = GuestModuleResolver::new(protocol, rt.ptr, rt.guest_resolvers.clone());
+    match rt
+        .module_resolver_manager
+        .register_resolver(Box::new(resolver))
+    {
+        Ok(()) => ok_future(None),
+        Err(e) => odd_future(e),
+    }
+}
+
+pub fn op_guest_resolve_result(rt: &mut Runtime, base: &msg::Base, _raw: fly_buf) -> Box<Op> {
+    let msg = base.msg_as_guest_resolve_result().unwrap();
+    rt.guest_resolvers
+        .complete(msg.id(), GuestResolution::from_result(&msg));
+    ok_future(None)
+}
+
+/**
+ * A loaded module as a `LoadModuleResp`, or as a `LoadModuleStartResp` when its source is
+ * over the chunk threshold, leaving the source in `chunks` to be fetched. `source_map` was
+ * added to `LoadModuleResp` before `kind`, so runtimes built before it skip it, and its absence
+ * reads as no source map; a missing `kind` reads as JavaScript. `deadline` is the id of the
+ * top-level load it was loaded under, 0 for none.
+ */
+fn load_module_resp(
+    builder: &mut FlatBufferBuilder,
+    module: &LoadedModule,
+    is_main: bool,
+    deadline: u32,
+    chunks: &ModuleChunks,
+) -> FlyResult<(
+    msg::Any,
+    flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>,
+)> {
+    let loaded_source = &module.loaded_source;
+    let source = if loaded_source.is_wasm() {
+        ChunkedSource::Binary(wasm_bytes(loaded_source)?)
+    } else {
+        ChunkedSource::Text(loaded_source.source.clone())
+    };
+    let compiled_wasm = loaded_source
+        .compiled_wasm
+        .as_ref()
+        .filter(|_| loaded_source.is_wasm());
+    let origin_url = builder.create_string(&module.origin_url);
+    let source_map = loaded_source
+        .source_map
+        .as_ref()
+        .map(|source_map| builder.create_string(source_map));
+    let size = source.as_bytes().len();
+    if chunks.chunking().chunks(size) {
+        // the compiled wasm is chunked along, after the binary
+        let compiled_wasm_size = compiled_wasm.map_or(0, |compiled| compiled.len());
+        let source = match (source, compiled_wasm) {
+            (ChunkedSource::Binary(bytes), Some(compiled)) => {
+                let mut joined = bytes.to_vec();
+                joined.extend_from_slice(compiled);
+                ChunkedSource::Binary(joined.into())
+            }
+            (source, _) => source,
+        };
+        let size = size + compiled_wasm_size;
+        let (id, chunks) = chunks.start(source);
+        let msg = msg::LoadModuleStartResp::create(
+            builder,
+            &msg::LoadModuleStartRespArgs {
+                id,
+                origin_url: Some(origin_url),
+                size: size as u32,
+                chunks,
+                passthrough: module.passthrough,
+                is_main,
+                is_wasm: loaded_source.is_wasm(),
+                source_map,
+                kind: msg_module_kind(loaded_source.kind),
+                compiled_wasm_size: compiled_wasm_size as u32,
+                deadline,
+            },
+        );
+        return Ok((msg::Any::LoadModuleStartResp, msg.as_union_value()));
+    }
+    let (source_code, wasm) = match source {
+        ChunkedSource::Binary(ref bytes) => (None, Some(builder.create_vector(&bytes[..]))),
+        ChunkedSource::Text(ref text) => (Some(builder.create_string(text)), None),
+    };
+    let compiled_wasm = compiled_wasm.map(|compiled| builder.create_vector(compiled));
+    let msg = msg::LoadModuleResp::create(
+        builder,
+        &msg::LoadModuleRespArgs {
+            origin_url: Some(origin_url),
+            source_code,
+            passthrough: module.passthrough,
+            is_main,
+            is_wasm: loaded_source.is_wasm(),
+            wasm,
+            source_map,
+            kind: msg_module_kind(loaded_source.kind),
+            compiled_wasm,
+            deadline,
+        },
+    );
+    Ok((msg::Any::LoadModuleResp, msg.as_union_value()))
+}
+
+fn msg_module_kind(kind: ModuleKind) -> msg::ModuleKind {
+    match kind {
+        ModuleKind::JavaScript => msg::ModuleKind::JavaScript,
+        ModuleKind::TypeScript => msg::ModuleKind::TypeScript,
+        ModuleKind::Json => msg::ModuleKind::Json,
+        ModuleKind::Wasm => msg::ModuleKind::Wasm,
+        ModuleKind::Text => msg::ModuleKind::Text,
+        ModuleKind::Bytes => msg::ModuleKind::Bytes,
+    }
+}
+
+fn load_module_chunk_resp<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    chunk: &[u8],
+) -> flatbuffers::WIPOffset<msg::LoadModuleChunkResp<'a>> {
+    let data = builder.create_vector(chunk);
+    msg::LoadModuleChunkResp::create(builder, &msg::LoadModuleChunkRespArgs { data: Some(data) })
+}
+
+fn module_cached_resp<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    state: &ModuleCacheState,
+) -> flatbuffers::WIPOffset<msg::ModuleCachedResp<'a>> {
+    let origin_url = builder.create_string(&state.origin_url);
+    let location = state.location.map(|l| builder.create_string(l.as_str()));
+    let freshness = state.freshness.map(|f| builder.create_string(f.as_str()));
+    msg::ModuleCachedResp::create(
+        builder,
+        &msg::ModuleCachedRespArgs {
+            origin_url: Some(origin_url),
+            location,
+            size: state.size.min(u32::max_value() as usize) as u32,
+            freshness,
+        },
+    )
+}
+
+fn resolve_module_resp<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    info: &ResolvedModuleInfo,
+) -> flatbuffers::WIPOffset<msg::ResolveModuleResp<'a>> {
+    let origin_url = builder.create_string(&info.origin_url);
+    let protocol = builder.create_string(&info.protocol);
+    let resolver = builder.create_string(&info.resolver);
+    let format = info
+        .is_wasm
+        .map(|is_wasm| builder.create_string(if is_wasm { "wasm" } else { "source" }));
+    msg::ResolveModuleResp::create(
+        builder,
+        &msg::ResolveModuleRespArgs {
+            origin_url: Some(origin_url),
+            protocol: Some(protocol),
+            resolver: Some(resolver),
+            format,
+        },
+    )
+}
+
+fn list_loaded_modules_resp<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    snapshot: &ModuleGraphSnapshot,
+) -> flatbuffers::WIPOffset<msg::ListLoadedModulesResp<'a>> {
+    let modules: Vec<_> = snapshot
+        .modules
+        .iter()
+        .map(|module| {
+            let origin_url = builder.create_string(&module.origin_url);
+            let referer = module
+                .referer
+                .as_ref()
+                .map(|referer| builder.create_string(referer));
+            msg::LoadedModuleEntry::create(
+                builder,
+                &msg::LoadedModuleEntryArgs {
+                    origin_url: Some(origin_url),
+                    size: module.size.min(u32::max_value() as usize) as u32,
+                    is_wasm: module.is_wasm,
+                    referer,
+                },
+            )
+        })
+        .collect();
+    let modules = builder.create_vector(&modules);
+    let imports: Vec<_> = snapshot
+        .imports
+        .iter()
+        .map(|(importer, dependency)| {
+            let importer = builder.create_string(importer);
+            let dependency = builder.create_string(dependency);
+            msg::ModuleImport::create(
+                builder,
+                &msg::ModuleImportArgs {
+                    importer: Some(importer),
+                    dependency: Some(dependency),
+                },
+            )
+        })
+        .collect();
+    let imports = builder.create_vector(&imports);
+    msg::ListLoadedModulesResp::create(
+        builder,
+        &msg::ListLoadedModulesRespArgs {
+            modules: Some(modules),
+            imports: Some(imports),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_counter::copies_of;
+    use crate::errors::{ErrorKind, FlyError};
+    use crate::module_chunks::ModuleChunking;
+    use crate::module_resolver::{
+        parse_url, wasm_source, FunctionModuleResolver, LoadedSourceCode, ModuleResolver,
+        ModuleResolverManager, ModuleSourceData, ResolutionCache, ResolverOptions,
+        SharedSourceCache, SourceLoader, StandardModuleResolverManager, StaticModuleResolver,
+        StaticSourceLoader,
+    };
+    use crate::settings::Settings;
+    use slog::{slog_o, Logger};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    struct StaticLoader;
+
+    impl SourceLoader for StaticLoader {
+        fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+            Ok(LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source_map: None,
+                source: "export default 1;".into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            })
+        }
+    }
+
+    fn load_module_msg(
+        builder: &mut FlatBufferBuilder,
+        is_wasm: bool,
+        hash: i32,
+        referer_source: Option<&str>,
+    ) -> Vec<u8> {
+        let specifier_url = builder.create_string("function:///glue.js");
+        let referer_origin_url = builder.create_string("file:///app/main.wasm");
+        let referer_source = referer_source.map(|source| builder.create_string(source));
+        let msg = msg::LoadModule::create(
+            builder,
+            &msg::LoadModuleArgs {
+                specifier_url: Some(specifier_url),
+                referer_origin_url: Some(referer_origin_url),
+                referer_is_wasm: is_wasm,
+                referer_identifier_hash: hash,
+                referer_source,
+                ..Default::default()
+            },
+        );
+        builder.finish(msg, None);
+        builder.finished_data().to_vec()
+    }
+
+    #[test]
+    fn test_referer_fields_reach_resolvers() {
+        let data = load_module_msg(&mut FlatBufferBuilder::new(), true, 1234, None);
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        let info = referer_info(&msg, "app").unwrap();
+
+        let seen = Arc::new(Mutex::new(None));
+        let recorder = seen.clone();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "function",
+                Box::new(move |specifier, referer| {
+                    *recorder.lock().unwrap() = referer;
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(StaticLoader),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        manager
+            .resolve_blocking("function:///glue.js".to_string(), Some(info))
+            .unwrap();
+        let referer = seen.lock().unwrap().take().unwrap();
+        assert_eq!("file:///app/main.wasm", referer.origin_url);
+        assert_eq!(Some(true), referer.is_wasm);
+        assert_eq!(Some(1234), referer.identifier_hash);
+        assert_eq!(Some("app".to_string()), referer.app_id);
+    }
+
+    #[test]
+    fn test_import_position_reaches_errors() {
+        let builder = &mut FlatBufferBuilder::new();
+        let specifier_url = builder.create_string("./missing.ts");
+        let referer_origin_url = builder.create_string("file:///app/main.ts");
+        let msg = msg::LoadModule::create(
+            builder,
+            &msg::LoadModuleArgs {
+                specifier_url: Some(specifier_url),
+                referer_origin_url: Some(referer_origin_url),
+                referer_line: 12,
+                referer_column: 8,
+                ..Default::default()
+            },
+        );
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        let info = referer_info(&msg, "app").unwrap();
+        assert_eq!((Some(12), Some(8)), (info.line, info.column));
+
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "file",
+                Box::new(|specifier, _| {
+                    Err(crate::errors::new(
+                        ErrorKind::ModuleNotFound,
+                        format!("Cannot resolve \"{}\"", specifier),
+                    ))
+                }),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        let err = manager
+            .resolve_blocking("./missing.ts".to_string(), Some(info))
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .ends_with("\n    imported from file:///app/main.ts:12:8"));
+
+        let data = load_module_msg(&mut FlatBufferBuilder::new(), false, 0, None);
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        let info = referer_info(&msg, "app").unwrap();
+        assert_eq!((None, None), (info.line, info.column));
+    }
+
+    #[test]
+    fn test_asserted_types_reach_the_manager() {
+        let builder = &mut FlatBufferBuilder::new();
+        let specifier_url = builder.create_string("function:///glue.js");
+        let referer_origin_url = builder.create_string("file:///app/main.js");
+        let asserted_type = builder.create_string("json");
+        let msg = msg::LoadModule::create(
+            builder,
+            &msg::LoadModuleArgs {
+                specifier_url: Some(specifier_url),
+                referer_origin_url: Some(referer_origin_url),
+                asserted_type: Some(asserted_type),
+                ..Default::default()
+            },
+        );
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        let options = ImportOptions::from_asserted(msg.asserted_type()).unwrap();
+
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "function",
+                Box::new(|specifier, _| {
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: Box::new(StaticLoader),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        let info = referer_info(&msg, "app");
+        let err = manager
+            .resolve_import("function:///glue.js".to_string(), info.clone(), options)
+            .wait()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ModuleTypeMismatch, err.kind());
+        assert!(
+            err.to_string().ends_with("it is a javascript module"),
+            "{}",
+            err
+        );
+
+        // the same module, imported without asserting a type, loads as it always did
+        let data = load_module_msg(&mut FlatBufferBuilder::new(), false, 0, None);
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        let options = ImportOptions::from_asserted(msg.asserted_type()).unwrap();
+        let module = manager
+            .resolve_import("function:///glue.js".to_string(), info, options)
+            .wait()
+            .unwrap();
+        assert_eq!("export default 1;", &*module.loaded_source.source);
+    }
+
+    #[test]
+    fn test_loads_without_a_referer_resolve_against_the_working_url() {
+        let builder = &mut FlatBufferBuilder::new();
+        let specifier_url = builder.create_string("./glue.js");
+        let msg = msg::LoadModule::create(
+            builder,
+            &msg::LoadModuleArgs {
+                specifier_url: Some(specifier_url),
+                ..Default::default()
+            },
+        );
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        assert!(referer_info(&msg, "app").is_none());
+
+        let seen = Arc::new(Mutex::new(None));
+        let recorder = seen.clone();
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "function",
+                Box::new(move |specifier, referer| {
+                    let referer: RefererInfo = referer.unwrap();
+                    *recorder.lock().unwrap() = Some(referer.origin_url.clone());
+                    Ok(ModuleSourceData {
+                        origin_url: url::Url::parse(&referer.origin_url)?
+                            .join(specifier)?
+                            .into_string(),
+                        source_loader: Box::new(StaticLoader),
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            Some("function:///app/".to_string()),
+        );
+        let module = manager
+            .resolve_module(msg.specifier_url().unwrap().to_string(), None)
+            .wait()
+            .unwrap();
+        assert_eq!("function:///app/glue.js", module.origin_url);
+        assert_eq!(
+            Some("function:///app/".to_string()),
+            seen.lock().unwrap().take()
+        );
+    }
+
+    #[test]
+    fn test_loads_without_a_referer_need_a_base_for_relative_specifiers() {
+        let manager = |root: &str| {
+            StandardModuleResolverManager::new(
+                vec![Box::new(FunctionModuleResolver::new(
+                    "function",
+                    Box::new(|specifier, _| {
+                        Ok(ModuleSourceData {
+                            origin_url: specifier.to_string(),
+                            source_loader: Box::new(StaticLoader),
+                        })
+                    }),
+                )) as Box<ModuleResolver>],
+                Some(root.to_string()),
+            )
+        };
+        // an app root that can't be a base, e.g. an entry module run from a data url
+        let rootless = manager("function:entry");
+        let module = rootless
+            .resolve_blocking("function:///lib/glue.js".to_string(), None)
+            .unwrap();
+        assert_eq!("function:///lib/glue.js", module.origin_url);
+        let err = rootless
+            .resolve_blocking("./glue.js".to_string(), None)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidSpecifier, err.kind());
+        assert!(format!("{}", err).contains("relative imports need a base url"));
+        assert_eq!(
+            "Could not resolve ./glue.js from function:entry: relative imports need a base url, and function: urls have no base; import by absolute url instead",
+            format!("{}", err)
+        );
+    }
+
+    #[test]
+    fn test_unknown_identifier_hash_is_none() {
+        let data = load_module_msg(&mut FlatBufferBuilder::new(), false, 0, None);
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        let info = referer_info(&msg, "app").unwrap();
+        assert_eq!(Some(false), info.is_wasm);
+        assert_eq!(None, info.identifier_hash);
+        assert_eq!(None, info.source_code);
+    }
+
+    #[test]
+    fn test_cache_state_response() {
+        use crate::module_resolver::{CacheFreshness, CacheLocation};
+        let encode = |state: &ModuleCacheState| {
+            let builder = &mut FlatBufferBuilder::new();
+            let msg = module_cached_resp(builder, state);
+            builder.finish(msg, None);
+            builder.finished_data().to_vec()
+        };
+        let data = encode(&ModuleCacheState::cached(
+            "https://deno.land/std/mod.ts",
+            CacheLocation::Disk,
+            42,
+            CacheFreshness::Stale,
+        ));
+        let msg = flatbuffers::get_root::<msg::ModuleCachedResp>(&data);
+        assert_eq!(Some("https://deno.land/std/mod.ts"), msg.origin_url());
+        assert_eq!(Some("disk"), msg.location());
+        assert_eq!(42, msg.size());
+        assert_eq!(Some("stale"), msg.freshness());
+
+        let data = encode(&ModuleCacheState::uncached("file:///app/heavy.ts"));
+        let msg = flatbuffers::get_root::<msg::ModuleCachedResp>(&data);
+        assert_eq!(None, msg.location());
+        assert_eq!(0, msg.size());
+        assert_eq!(None, msg.freshness());
+    }
+
+    #[test]
+    fn test_resolve_module_response() {
+        let encode = |is_wasm: Option<bool>| {
+            let builder = &mut FlatBufferBuilder::new();
+            let msg = resolve_module_resp(
+                builder,
+                &ResolvedModuleInfo {
+                    origin_url: "https://deno.land/std/mod.ts".to_string(),
+                    protocol: "https".to_string(),
+                    resolver: "http".to_string(),
+                    is_wasm,
+                },
+            );
+            builder.finish(msg, None);
+            builder.finished_data().to_vec()
+        };
+        let data = encode(Some(false));
+        let msg = flatbuffers::get_root::<msg::ResolveModuleResp>(&data);
+        assert_eq!(Some("https://deno.land/std/mod.ts"), msg.origin_url());
+        assert_eq!(Some("https"), msg.protocol());
+        assert_eq!(Some("http"), msg.resolver());
+        assert_eq!(Some("source"), msg.format());
+
+        let data = encode(Some(true));
+        let msg = flatbuffers::get_root::<msg::ResolveModuleResp>(&data);
+        assert_eq!(Some("wasm"), msg.format());
+        let data = encode(None);
+        let msg = flatbuffers::get_root::<msg::ResolveModuleResp>(&data);
+        assert_eq!(None, msg.format());
+    }
+
+    #[test]
+    fn test_referer_source_is_capped() {
+        use crate::module_resolver::MAX_REFERER_SOURCE_BYTES;
+        let source = "import \"./dep\";\n".repeat(MAX_REFERER_SOURCE_BYTES / 8);
+        let data = load_module_msg(&mut FlatBufferBuilder::new(), false, 0, Some(&source));
+        let msg = flatbuffers::get_root::<msg::LoadModule>(&data);
+        let capped = referer_info(&msg, "app").unwrap().source_code.unwrap();
+        assert!(capped.len() < source.len());
+        assert!(capped.starts_with("import \"./dep\";\n"));
+        assert!(capped.ends_with(&format!(
+            "\n/* referer source truncated: {} of {} bytes */",
+            MAX_REFERER_SOURCE_BYTES - MAX_REFERER_SOURCE_BYTES % 16,
+            source.len()
+        )));
+    }
+
+    #[test]
+    fn test_source_maps_arrive_in_the_response() {
+        struct MappedLoader;
+        impl SourceLoader for MappedLoader {
+            fn load_source(&self) -> FlyResult<LoadedSourceCode> {
+                Ok(LoadedSourceCode {
+                    kind: ModuleKind::JavaScript,
+                    source_map: Some(r#"{"version":3,"mappings":"AAAA"}"#.into()),
+                    source: "export default 1;".into(),
+                    response: None,
+                    provenance: None,
+                    code_cache: None,
+                    compiled_wasm: None,
+                    wasm: None,
+                })
+            }
+        }
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(FunctionModuleResolver::new(
+                "function",
+                Box::new(|specifier, _| {
+                    Ok(ModuleSourceData {
+                        origin_url: specifier.to_string(),
+                        source_loader: if specifier.ends_with("mapped.js") {
+                            Box::new(MappedLoader)
+                        } else {
+                            Box::new(StaticLoader)
+                        },
+                    })
+                }),
+            )) as Box<ModuleResolver>],
+            None,
+        );
+        let encode = |specifier: &str| {
+            let module = manager
+                .resolve_blocking(specifier.to_string(), None)
+                .unwrap();
+            let builder = &mut FlatBufferBuilder::new();
+            let chunks = ModuleChunks::new(ModuleChunking::default());
+            let (msg_type, msg) = load_module_resp(builder, &module, true, 0, &chunks).unwrap();
+            assert_eq!(msg::Any::LoadModuleResp, msg_type);
+            builder.finish(msg, None);
+            builder.finished_data().to_vec()
+        };
+        let data = encode("function:///mapped.js");
+        let msg = flatbuffers::get_root::<msg::LoadModuleResp>(&data);
+        assert_eq!(Some("function:///mapped.js"), msg.origin_url());
+        assert_eq!(Some("export default 1;"), msg.source_code());
+        assert_eq!(Some(r#"{"version":3,"mappings":"AAAA"}"#), msg.source_map());
+        assert!(!msg.is_wasm());
+        assert_eq!(msg::ModuleKind::JavaScript, msg.kind());
+        assert!(msg.is_main());
+
+        let data = encode("function:///plain.js");
+        let msg = flatbuffers::get_root::<msg::LoadModuleResp>(&data);
+        assert_eq!(None, msg.source_map());
+    }
+
+    #[test]
+    fn test_slow_resolvers_dont_stall_other_ops() {
+        use std::sync::mpsc;
+        use std::time::Duration;
+        let (release, released) = mpsc::channel::<()>();
+        let released = Mutex::new(released);
+        let manager = Arc::new(StandardModuleResolverManager::new(
+            vec![
+                Box::new(FunctionModuleResolver::new(
+                    "slow",
+                    Box::new(move |specifier, _| {
+                        // fails, rather than hangs, if nothing else got to run meanwhile
+                        released
+                            .lock()
+                            .unwrap()
+                            .recv_timeout(Duration::from_secs(5))
+                            .map_err(|_| {
+                                FlyError::from("nothing ran while resolving".to_string())
+                            })?;
+                        Ok(ModuleSourceData {
+                            origin_url: specifier.to_string(),
+                            source_loader: Box::new(StaticLoader),
+                        })
+                    }),
+                )) as Box<ModuleResolver>,
+                Box::new(FunctionModuleResolver::new(
+                    "function",
+                    Box::new(|specifier, _| {
+                        Ok(ModuleSourceData {
+                            origin_url: specifier.to_string(),
+                            source_loader: Box::new(StaticLoader),
+                        })
+                    }),
+                )),
+            ],
+            None,
+        ));
+        // a runtime's event loop, one thread running every op
+        let mut event_loop = tokio::runtime::current_thread::Runtime::new().unwrap();
+        let slow = manager.resolve_module("slow:///main.js".to_string(), None);
+        let (tx, slow_loaded) = futures::sync::oneshot::channel();
+        event_loop.spawn(slow.then(move |result| {
+            let _ = tx.send(result.map(|m| m.origin_url));
+            Ok(())
+        }));
+        let fast = manager
+            .resolve_module("function:///other.js".to_string(), None)
+            .map(move |module| {
+                release.send(()).unwrap();
+                module.origin_url
+            });
+        assert_eq!("function:///other.js", event_loop.block_on(fast).unwrap());
+        assert_eq!(
+            "slow:///main.js",
+            event_loop.block_on(slow_loaded).unwrap().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_big_modules_round_trip_in_chunks() {
+        // not a multiple of the chunk size, with multi-byte characters across chunk ends
+        let source: String = (0..400_000).map(|i| format!("/*é{}*/\n", i)).collect();
+        assert!(source.len() > 4 * 1024 * 1024);
+        let module = LoadedModule {
+            loaded_source: LoadedSourceCode {
+                kind: ModuleKind::TypeScript,
+                source_map: None,
+                source: source.clone().into(),
+                response: None,
+                provenance: None,
+                code_cache: None,
+                compiled_wasm: None,
+                wasm: None,
+            },
+            origin_url: "file:///app/bundle.js".to_string(),
+            passthrough: false,
+            resolver: None,
+        };
+        let chunks = ModuleChunks::new(ModuleChunking {
+            threshold_bytes: 1024 * 1024,
+            chunk_bytes: 256 * 1024,
+        });
+        let builder = &mut FlatBufferBuilder::new();
+        let (msg_type, msg) = load_module_resp(builder, &module, false, 0, &chunks).unwrap();
+        assert_eq!(msg::Any::LoadModuleStartResp, msg_type);
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let start = flatbuffers::get_root::<msg::LoadModuleStartResp>(&data);
+        assert_eq!(Some("file:///app/bundle.js"), start.origin_url());
+        assert_eq!(source.len(), start.size() as usize);
+        assert!(!start.is_wasm());
+        assert_eq!(msg::ModuleKind::TypeScript, start.kind());
+
+        let mut bytes = Vec::with_capacity(start.size() as usize);
+        for index in 0..start.chunks() {
+            let chunk = chunks.chunk(start.id(), index).unwrap();
+            let builder = &mut FlatBufferBuilder::new();
+            let msg = load_module_chunk_resp(builder, &chunk);
+            builder.finish(msg, None);
+            let data = builder.finished_data().to_vec();
+            let msg = flatbuffers::get_root::<msg::LoadModuleChunkResp>(&data);
+            assert!(msg.data().unwrap().len() <= 256 * 1024);
+            bytes.extend_from_slice(msg.data().unwrap());
+        }
+        assert_eq!(source, String::from_utf8(bytes).unwrap());
+        assert_eq!(0, chunks.pending());
+
+        // small modules still go in one message
+        let builder = &mut FlatBufferBuilder::new();
+        let small = LoadedModule {
+            loaded_source: LoadedSourceCode {
+                source: "export default 1;".into(),
+                ..module.loaded_source.clone()
+            },
+            ..module
+        };
+        let (msg_type, _) = load_module_resp(builder, &small, false, 0, &chunks).unwrap();
+        assert_eq!(msg::Any::LoadModuleResp, msg_type);
+        assert_eq!(0, chunks.pending());
+    }
+
+    #[test]
+    fn test_compiled_wasm_rides_along_with_its_binary() {
+        let wasm = b"\0asm\x01\0\0\0".to_vec();
+        let module = LoadedModule {
+            loaded_source: LoadedSourceCode {
+                compiled_wasm: Some(b"compiled".to_vec()),
+                ..wasm_source(&wasm)
+            },
+            origin_url: "file:///app/answer.wasm".to_string(),
+            passthrough: false,
+            resolver: None,
+        };
+        let chunks = ModuleChunks::new(ModuleChunking::default());
+        let builder = &mut FlatBufferBuilder::new();
+        let (msg_type, msg) = load_module_resp(builder, &module, false, 0, &chunks).unwrap();
+        assert_eq!(msg::Any::LoadModuleResp, msg_type);
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let msg = flatbuffers::get_root::<msg::LoadModuleResp>(&data);
+        assert_eq!(Some(&wasm[..]), msg.wasm());
+        assert_eq!(Some(&b"compiled"[..]), msg.compiled_wasm());
+
+        // chunked, it comes after the binary
+        let chunks = ModuleChunks::new(ModuleChunking {
+            threshold_bytes: 4,
+            chunk_bytes: 5,
+        });
+        let builder = &mut FlatBufferBuilder::new();
+        let (msg_type, msg) = load_module_resp(builder, &module, false, 0, &chunks).unwrap();
+        assert_eq!(msg::Any::LoadModuleStartResp, msg_type);
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let start = flatbuffers::get_root::<msg::LoadModuleStartResp>(&data);
+        assert_eq!(16, start.size());
+        assert_eq!(8, start.compiled_wasm_size());
+        let bytes: Vec<u8> = (0..start.chunks())
+            .flat_map(|index| chunks.chunk(start.id(), index).unwrap())
+            .collect();
+        assert_eq!(b"\0asm\x01\0\0\0compiled".to_vec(), bytes);
+
+        // JavaScript never has any
+        let js = LoadedModule {
+            loaded_source: LoadedSourceCode {
+                kind: ModuleKind::JavaScript,
+                source: "export default 1;".into(),
+                ..module.loaded_source.clone()
+            },
+            ..module
+        };
+        let builder = &mut FlatBufferBuilder::new();
+        let (_, msg) = load_module_resp(builder, &js, false, 0, &chunks).unwrap();
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let start = flatbuffers::get_root::<msg::LoadModuleStartResp>(&data);
+        assert_eq!(0, start.compiled_wasm_size());
+        assert_eq!(17, start.size());
+    }
+
+    #[test]
+    fn test_lists_a_loaded_module_graph() {
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(
+                StaticModuleResolver::new("static")
+                    .insert("static://app/main.js", "import './lib.js';")
+                    .insert("static://app/lib.js", "import './answer.wasm';")
+                    .insert_source("static://app/answer.wasm", wasm_source(b"\0asm\x01\0\0\0")),
+            ) as Box<ModuleResolver>],
+            Some("static://app/".to_string()),
+        );
+        let from = |origin_url: &str| {
+            Some(RefererInfo {
+                origin_url: origin_url.to_string(),
+                is_wasm: Some(false),
+                source_code: None,
+                identifier_hash: None,
+                line: None,
+                column: None,
+                app_id: None,
+                deadline: None,
+            })
+        };
+        let main = manager
+            .resolve_blocking("./main.js".to_string(), None)
+            .unwrap();
+        let lib = manager
+            .resolve_blocking("./lib.js".to_string(), from(&main.origin_url))
+            .unwrap();
+        let wasm = manager
+            .resolve_blocking("./answer.wasm".to_string(), from(&lib.origin_url))
+            .unwrap();
+        // imported again from elsewhere, it keeps its first referer
+        manager
+            .resolve_blocking("./lib.js".to_string(), from(&wasm.origin_url))
+            .unwrap();
+
+        let builder = &mut FlatBufferBuilder::new();
+        let msg = list_loaded_modules_resp(builder, &manager.loaded_modules().unwrap());
+        builder.finish(msg, None);
+        let data = builder.finished_data().to_vec();
+        let msg = flatbuffers::get_root::<msg::ListLoadedModulesResp>(&data);
+        let modules = msg.modules().unwrap();
+        let listed: Vec<_> = (0..modules.len())
+            .map(|i| {
+                let module = modules.get(i);
+                (
+                    module.origin_url().unwrap(),
+                    module.size() as usize,
+                    module.is_wasm(),
+                    module.referer(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "static://app/answer.wasm",
+                    wasm.loaded_source.source.len(),
+                    true,
+                    Some("static://app/lib.js")
+                ),
+                (
+                    "static://app/lib.js",
+                    "import './answer.wasm';".len(),
+                    false,
+                    Some("static://app/main.js")
+                ),
+                (
+                    "static://app/main.js",
+                    "import './lib.js';".len(),
+                    false,
+                    None
+                ),
+            ],
+            listed
+        );
+        let imports = msg.imports().unwrap();
+        let imports: Vec<_> = (0..imports.len())
+            .map(|i| {
+                (
+                    imports.get(i).importer().unwrap(),
+                    imports.get(i).dependency().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("static://app/answer.wasm", "static://app/lib.js"),
+                ("static://app/lib.js", "static://app/answer.wasm"),
+                ("static://app/main.js", "static://app/lib.js"),
+            ],
+            imports
+        );
+    }
+
+    /**
+     * Sends a `LoadModule` through the op, as the JS side does; answers with the deadline the
+     * module was loaded under.
+     */
+    fn send_load_module(
+        rt: &mut Runtime,
+        specifier: &str,
+        referer: Option<&str>,
+        deadline: u32,
+    ) -> FlyResult<u32> {
+        let builder = &mut FlatBufferBuilder::new();
+        let specifier_url = builder.create_string(specifier);
+        let referer_origin_url = referer.map(|referer| builder.create_string(referer));
+        let msg = msg::LoadModule::create(
+            builder,
+            &msg::LoadModuleArgs {
+                specifier_url: Some(specifier_url),
+                referer_origin_url,
+                deadline,
+                ..Default::default()
+            },
+        );
+        let base = msg::Base::create(
+            builder,
+            &msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::LoadModule,
+                ..Default::default()
+            },
+        );
+        builder.finish(base, None);
+        let data = builder.finished_data().to_vec();
+        let resp = op_load_module(rt, &msg::get_root_as_base(&data), null_buf())
+            .wait()?
+            .unwrap();
+        let resp = msg::get_root_as_base(&resp);
+        Ok(match resp.msg_as_load_module_start_resp() {
+            Some(start) => start.deadline(),
+            None => resp.msg_as_load_module_resp().unwrap().deadline(),
+        })
+    }
+
+    #[test]
+    fn test_a_top_level_load_shares_one_deadline_across_ops() {
+        // every module takes 150ms to find, well within the deadline on its own
+        let resolver = FunctionModuleResolver::new(
+            "function",
+            Box::new(|specifier, referer_info| {
+                let referer = referer_info.map_or("function:///app/".to_string(), |r| r.origin_url);
+                let url = parse_url(specifier, &referer)?.into_string();
+                thread::sleep(Duration::from_millis(150));
+                Ok(ModuleSourceData {
+                    origin_url: url,
+                    source_loader: Box::new(StaticSourceLoader {
+                        source: LoadedSourceCode {
+                            kind: ModuleKind::JavaScript,
+                            source_map: None,
+                            source: "export default 1;".into(),
+                            response: None,
+                            provenance: None,
+                            code_cache: None,
+                            compiled_wasm: None,
+                            wasm: None,
+                        },
+                    }),
+                })
+            }),
+        );
+        let manager = StandardModuleResolverManager::with_options(
+            vec![Box::new(resolver) as Box<ModuleResolver>],
+            Some("function:///app/".to_string()),
+            ResolverOptions {
+                total_deadline: Some(Duration::from_millis(375)),
+                ..ResolverOptions::default()
+            },
+        );
+        let settings = Settings::default();
+        let logger = Logger::root(slog::Discard, slog_o!());
+        let mut rt = Runtime::builder(&settings, &logger)
+            .dev_tools(true)
+            .resolver_manager(Box::new(manager))
+            .build()
+            .unwrap();
+
+        let deadline = send_load_module(&mut rt, "./main.js", None, 0).unwrap();
+        assert_ne!(0, deadline);
+        let main = Some("function:///app/main.js");
+        let a = Some("function:///app/a.js");
+        assert_eq!(
+            deadline,
+            send_load_module(&mut rt, "./a.js", main, deadline).unwrap()
+        );
+        let err = send_load_module(&mut rt, "./b.js", a, deadline)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::DeadlineExceeded, err.kind());
+        // as do the imports still naming it once it's forgotten
+        let other = send_load_module(&mut rt, "./c.js", main, 0).unwrap();
+        assert_ne!(deadline, other);
+        let err = send_load_module(&mut rt, "./d.js", main, deadline)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::DeadlineExceeded, err.kind());
+    }
+
+    #[test]
+    fn test_big_sources_are_shared_all_the_way_to_the_op() {
+        // an odd size, which nothing else allocates
+        let source: Arc<str> = format!("export default {:?};", "x".repeat(5 * 1024 * 1024)).into();
+        let loaded = source.clone();
+        let resolver = FunctionModuleResolver::new(
+            "function",
+            Box::new(move |specifier, _| {
+                Ok(ModuleSourceData {
+                    origin_url: specifier.to_string(),
+                    source_loader: Box::new(StaticSourceLoader {
+                        source: LoadedSourceCode {
+                            kind: ModuleKind::JavaScript,
+                            source_map: None,
+                            source: loaded.clone(),
+                            response: None,
+                            provenance: None,
+                            code_cache: None,
+                            compiled_wasm: None,
+                            wasm: None,
+                        },
+                    }),
+                })
+            }),
+        );
+        let manager = StandardModuleResolverManager::new(
+            vec![Box::new(resolver) as Box<ModuleResolver>],
+            None,
+        )
+        .with_shared_cache(SharedSourceCache::new(64 * 1024 * 1024));
+
+        // through load_resolved and the shared cache
+        let (copies, ()) = copies_of(source.len(), || {
+            for _ in 0..100 {
+                let module = manager
+                    .resolve_blocking("function:///big.js".to_string(), None)
+                    .unwrap();
+                assert!(Arc::ptr_eq(&source, &module.loaded_source.source));
+            }
+        });
+        assert_eq!(0, copies);
+
+        // and on through op_load_module, which sends it in chunks read from the same buffer
+        let settings = Settings::default();
+        let logger = Logger::root(slog::Discard, slog_o!());
+        let mut rt = Runtime::builder(&settings, &logger)
+            .dev_tools(true)
+            .resolver_manager(Box::new(manager))
+            .build()
+            .unwrap();
+        let (copies, ()) = copies_of(source.len(), || {
+            for _ in 0..100 {
+                send_load_module(&mut rt, "function:///big.js", None, 0).unwrap();
+            }
+        });
+        assert_eq!(0, copies);
+    }
+
+    fn send_clear_module_cache(rt: &mut Runtime, origin_url: Option<&str>) -> FlyResult<u32> {
+        let builder = &mut FlatBufferBuilder::new();
+        let origin_url = origin_url.map(|url| builder.create_string(url));
+        let msg = msg::ClearModuleCache::create(builder, &msg::ClearModuleCacheArgs { origin_url });
+        let base = msg::Base::create(
+            builder,
+            &msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::ClearModuleCache,
+                ..Default::default()
+            },
+        );
+        builder.finish(base, None);
+        let data = builder.finished_data().to_vec();
+        let resp = op_clear_module_cache(rt, &msg::get_root_as_base(&data), null_buf())
+            .wait()?
+            .unwrap();
+        let resp = msg::get_root_as_base(&resp);
+        Ok(resp.msg_as_clear_module_cache_resp().unwrap().evicted())
+    }
+
+    #[test]
+    fn test_clearing_the_cache_leaves_other_runtimes_modules() {
+        let cache = ResolutionCache::new();
+        let manager = || {
+            StandardModuleResolverManager::new(
+                vec![Box::new(
+                    StaticModuleResolver::new("static")
+                        .insert("static://app/mine.js", "export default 1;")
+                        .insert("static://app/theirs.js", "export default 2;"),
+                ) as Box<ModuleResolver>],
+                Some("static://app/".to_string()),
+            )
+            .with_resolution_cache(cache.clone())
+        };
+        let settings = Settings::default();
+        let logger = Logger::root(slog::Discard, slog_o!());
+        let build = |dev_tools: bool| {
+            Runtime::builder(&settings, &logger)
+                .dev_tools(dev_tools)
+                .resolver_manager(Box::new(manager()))
+                .build()
+                .unwrap()
+        };
+        let mut rt = build(true);
+        rt.eval_module_with_dev_tools("static://app/mine.js")
+            .unwrap();
+        // another runtime's, in the same cache
+        manager()
+            .resolve_blocking("./theirs.js".to_string(), None)
+            .unwrap();
+        assert_eq!(2, cache.stats().modules);
+
+        assert_eq!(
+            0,
+            send_clear_module_cache(&mut rt, Some("static://app/theirs.js")).unwrap()
+        );
+        assert_eq!(1, send_clear_module_cache(&mut rt, None).unwrap());
+        assert_eq!(1, cache.stats().modules);
+        assert!(cache.get("./theirs.js", "static://app/").is_some());
+
+        let mut rt = build(false);
+        let err = send_clear_module_cache(&mut rt, None).err().unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+        assert_eq!(1, cache.stats().modules);
+    }
+
+    #[test]
+    fn test_resolving_needs_dev_tools() {
+        let settings = Settings::default();
+        let logger = Logger::root(slog::Discard, slog_o!());
+        let mut rt = Runtime::builder(&settings, &logger).build().unwrap();
+        let builder = &mut FlatBufferBuilder::new();
+        let specifier = builder.create_string("file:///etc/passwd");
+        let msg = msg::ResolveModule::create(
+            builder,
+            &msg::ResolveModuleArgs {
+                specifier: Some(specifier),
+                ..Default::default()
+            },
+        );
+        let base = msg::Base::create(
+            builder,
+            &msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::ResolveModule,
+                ..Default::default()
+            },
+        );
+        builder.finish(base, None);
+        let data = builder.finished_data().to_vec();
+        let err = op_resolve_module(&mut rt, &msg::get_root_as_base(&data), null_buf())
+            .wait()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+    }
+
+    #[test]
+    fn test_listing_needs_dev_tools() {
+        let settings = Settings::default();
+        let logger = Logger::root(slog::Discard, slog_o!());
+        let mut rt = Runtime::builder(&settings, &logger).build().unwrap();
+        let builder = &mut FlatBufferBuilder::new();
+        let msg = msg::ListLoadedModules::create(builder, &msg::ListLoadedModulesArgs {});
+        let base = msg::Base::create(
+            builder,
+            &msg::BaseArgs {
+                msg: Some(msg.as_union_value()),
+                msg_type: msg::Any::ListLoadedModules,
+                ..Default::default()
+            },
+        );
+        builder.finish(base, None);
+        let data = builder.finished_data().to_vec();
+        let err = op_list_loaded_modules(&mut rt, &msg::get_root_as_base(&data), null_buf())
+            .wait()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::PermissionDenied, err.kind());
+    }
+}
@@ -3,7 +3,7 @@ use tokio;
 use tokio::runtime::current_thread;
 
 use std::ffi::{CStr, CString};
-use std::sync::{Mutex, Once};
+use std::sync::{Arc, Mutex, Once};
 
 use self::fs::File;
 use std::fs;
@@ -19,8 +19,6 @@ use std::collections::HashMap;
 
 use std::thread;
 
-use std::sync::RwLock;
-
 use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 
 use std::ptr;
@@ -31,7 +29,7 @@ use crate::js::*;
 use crate::msg;
 use flatbuffers::FlatBufferBuilder;
 
-use crate::errors::FlyError;
+use crate::errors::{FlyError, FlyResult};
 
 use slog::{slog_debug, slog_error, slog_info, slog_o, slog_trace, slog_warn, Logger};
 
@@ -57,8 +55,10 @@ use crate::settings::{
 };
 
 use crate::module_resolver::{
-  LoadedModule, LocalDiskModuleResolver, ModuleResolver, ModuleResolverManager, RefererInfo,
-  StandardModuleResolverManager,
+  allowlisted_env, cap_referer_source, BuiltinModuleResolver, BUILTIN_PROTOCOL, check_remote_entry, network_policy, process_wide_cache, remote_module_resolvers, register_fly_builtins, AssetLoaders, BuildInfo, process_wide_scheduler, CancellationToken, CassetteMode, CassetteRecorder, CassetteResolver, CompiledModuleInfo, CompiledModuleRegistry, DataFormats, DataUrlResolver, FetchOptions, DEFAULT_MAX_REDIRECTS, DevServerResolver, EnvVarResolver, EntryModules, HotUpdate, ImportMap, LoadedModule, LoadedSourceCode, LocalDiskModuleResolver, Lockfile, LockfileMode, MediaType, ModuleResolver,
+  ModuleGraph, ModuleResolverManager, NodeModulesResolver, preload_graph, PreloadSummary, PrometheusResolutionObserver, RefererInfo, ResolutionDeadlines, ResolverPolicy, SqliteModuleCache, StandardModuleResolverManager,
+  VirtualModuleRegistry, WasmCompileCache, WorkspaceResolver, DEFAULT_BINARY_ASSETS, DEFAULT_BINARY_ASSET_MAX_BYTES, DEFAULT_COMPILE_CACHE_BYTES,
+  DEFAULT_SHARED_CACHE_BYTES, DEFAULT_TEXT_ASSETS,
 };
 
 use super::NEXT_FUTURE_ID;
@@ -66,7 +66,11 @@ use std::str;
 
 use std::time;
 
+use crate::guest_resolver::GuestResolvers;
+use crate::module_chunks::{ModuleChunking, ModuleChunks};
 use crate::msg_handler::{DefaultMessageHandler, MessageHandler};
+use crate::op_limits::{op_class, Admission, OpLimiter};
+use crate::op_metrics::OpMetrics;
 
 #[derive(Debug, Copy, Clone)]
 pub struct JsRuntime(pub *const js_runtime);
@@ -115,16 +119,45 @@ pub struct Runtime {
   pub resolv_events: Option<mpsc::UnboundedSender<JsDnsRequest>>,
   pub last_event_at: AtomicUsize,
   pub module_resolver_manager: Box<ModuleResolverManager>,
+  pub virtual_modules: VirtualModuleRegistry,
+  /// Scope for module loads started by this runtime; cancelled on dispose so downloads no
+  /// one else is waiting on stop. Request scopes should use `module_loads.child()`.
+  pub module_loads: CancellationToken,
   pub msg_handler: Box<MessageHandler>,
+  /// Calls, errors and latency of every op this runtime dispatched.
+  pub op_metrics: Arc<OpMetrics>,
+  /// Caps this runtime's pending async ops.
+  pub op_limiter: OpLimiter,
+  /// Referer for module loads that don't name one, e.g. the app's root url.
+  pub default_referer: Option<String>,
+  /// Resolutions waiting on the app's own module resolvers.
+  pub guest_resolvers: Arc<GuestResolvers>,
+  /// Module sources too big for one message, waiting for the JS side to fetch their chunks.
+  pub module_chunks: ModuleChunks,
+  /// Deadlines of the top-level module loads going on, shared by every import under each.
+  pub module_deadlines: ResolutionDeadlines,
   pub permissions: RuntimePermissions,
   pub dev_tools: bool,
-  metadata_cache: RwLock<HashMap<i32, Box<LoadedModule>>>,
+  entry_points: Vec<EntryPoint>,
+  module_registry: CompiledModuleRegistry,
+  pub module_graph: ModuleGraph,
+  /// Modules this runtime was started from, for `import.meta.main`.
+  pub entry_modules: EntryModules,
   ready_ch: Option<oneshot::Sender<()>>,
   quit_ch: Option<oneshot::Receiver<()>>,
 }
 
 static JSINIT: Once = Once::new();
 
+/// How module fetches follow redirects, as `settings` say.
+fn module_fetch_options(settings: &Settings) -> FetchOptions {
+  FetchOptions {
+    max_redirects: settings.module_fetch_max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS),
+    allow_insecure_redirects: settings.module_fetch_allow_insecure_redirects.unwrap_or(false),
+    ..FetchOptions::default()
+  }
+}
+
 fn init_event_loop(
   name: String,
 ) -> (
@@ -167,6 +200,14 @@ fn init_event_loop(
   p.wait().unwrap()
 }
 
+/// A named module specifier the runtime can be started from, e.g. an http handler
+/// and a scheduled job sharing most of their dependencies.
+#[derive(Debug, Clone)]
+pub struct EntryPoint {
+  pub name: String,
+  pub specifier: String,
+}
+
 pub struct RuntimeConfig<'a> {
   pub name: Option<String>,
   pub version: Option<String>,
@@ -176,22 +217,424 @@ pub struct RuntimeConfig<'a> {
   pub msg_handler: Option<Box<MessageHandler>>,
   pub permissions: Option<RuntimePermissions>,
   pub dev_tools: bool,
+  pub entry_points: Option<Vec<EntryPoint>>,
+  /// Exported to the app as `fly:build`. Defaults to just the runtime version.
+  pub build_info: Option<BuildInfo>,
+}
+
+/// V8 heap limits of a runtime, in megabytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapLimits {
+  pub soft_mb: usize,
+  pub hard_mb: usize,
+}
+
+impl Default for HeapLimits {
+  fn default() -> Self {
+    HeapLimits {
+      soft_mb: 128,
+      hard_mb: 256,
+    }
+  }
+}
+
+/// Stands up a runtime. Anything not set falls back to what `settings` configures, and
+/// without any of that to a standard module resolver manager over a local disk resolver
+/// rooted at the working directory.
+///
+/// ```ignore
+/// let rt = Runtime::builder(&settings, &logger)
+///   .resolver_manager(Box::new(manager))
+///   .default_referer("file:///app/index.js")
+///   .heap_limits(HeapLimits { soft_mb: 64, hard_mb: 128 })
+///   .build()?;
+/// ```
+pub struct RuntimeBuilder<'a> {
+  config: RuntimeConfig<'a>,
+  resolver_manager: Option<Box<ModuleResolverManager>>,
+  default_referer: Option<String>,
+  heap_limits: HeapLimits,
+  module_chunking: ModuleChunking,
+  remote_entry: Option<url::Url>,
+  builtin_modules: Vec<(String, String)>,
+}
+
+impl<'a> RuntimeBuilder<'a> {
+  pub fn name(mut self, name: &str) -> Self {
+    self.config.name = Some(name.to_string());
+    self
+  }
+
+  pub fn version(mut self, version: &str) -> Self {
+    self.config.version = Some(version.to_string());
+    self
+  }
+
+  /// Resolvers for the standard manager built from `settings`. Can't be combined with
+  /// `resolver_manager`.
+  pub fn module_resolvers(mut self, resolvers: Vec<Box<ModuleResolver>>) -> Self {
+    self.config.module_resolvers = Some(resolvers);
+    self
+  }
+
+  /// Uses `manager` for every module load as is; the `module_*` settings that shape the
+  /// standard manager don't apply to it.
+  pub fn resolver_manager(mut self, manager: Box<ModuleResolverManager>) -> Self {
+    self.resolver_manager = Some(manager);
+    self
+  }
+
+  pub fn default_referer(mut self, url: &str) -> Self {
+    self.default_referer = Some(url.to_string());
+    self
+  }
+
+  pub fn heap_limits(mut self, limits: HeapLimits) -> Self {
+    self.heap_limits = limits;
+    self
+  }
+
+  /// Sources bigger than `chunking.threshold_bytes` reach the JS side in chunks of
+  /// `chunking.chunk_bytes`, rather than in one message.
+  pub fn module_chunking(mut self, chunking: ModuleChunking) -> Self {
+    self.module_chunking = chunking;
+    self
+  }
+
+  /// Runs the app from a remote entry: adds `https`/`http` resolvers that fetch under the
+  /// `module_fetch_network` policy and makes `url` the default referer, so relative imports
+  /// go back to its origin. `build` checks `url` against that policy before anything is
+  /// fetched from it.
+  pub fn remote_entry(mut self, url: &url::Url) -> Self {
+    self.remote_entry = Some(url.clone());
+    self
+  }
+
+  pub fn msg_handler(mut self, handler: Box<MessageHandler>) -> Self {
+    self.config.msg_handler = Some(handler);
+    self
+  }
+
+  pub fn permissions(mut self, permissions: RuntimePermissions) -> Self {
+    self.config.permissions = Some(permissions);
+    self
+  }
+
+  pub fn dev_tools(mut self, enabled: bool) -> Self {
+    self.config.dev_tools = enabled;
+    self
+  }
+
+  pub fn entry_points(mut self, entry_points: Vec<EntryPoint>) -> Self {
+    self.config.entry_points = Some(entry_points);
+    self
+  }
+
+  pub fn build_info(mut self, build_info: BuildInfo) -> Self {
+    self.config.build_info = Some(build_info);
+    self
+  }
+
+  /// Serves `source` to the app as `fly:<name>`, next to the bundled builtins.
+  pub fn builtin_module(mut self, name: &str, source: &str) -> Self {
+    self.builtin_modules.push((name.to_string(), source.to_string()));
+    self
+  }
+
+  /// Checks the configuration before anything is started, so a runtime never exists
+  /// without a usable module resolver manager.
+  fn validate(&self) -> FlyResult<()> {
+    if self.resolver_manager.is_some()
+      && (self.config.module_resolvers.is_some()
+        || self.remote_entry.is_some()
+        || !self.builtin_modules.is_empty())
+    {
+      return Err(FlyError::from(
+        "module resolvers only apply to the standard manager, not a supplied one".to_string(),
+      ));
+    }
+    if let Some(ref url) = self.default_referer {
+      if let Err(e) = url::Url::parse(url) {
+        return Err(FlyError::from(format!("invalid default referer '{}': {}", url, e)));
+      }
+    }
+    let limits = self.heap_limits;
+    if limits.soft_mb == 0 || limits.soft_mb > limits.hard_mb {
+      return Err(FlyError::from(format!(
+        "soft heap limit ({}MB) must be above zero and at most the hard limit ({}MB)",
+        limits.soft_mb, limits.hard_mb
+      )));
+    }
+    self.module_chunking.validate()?;
+    Ok(())
+  }
+
+  pub fn build(self) -> FlyResult<Box<Runtime>> {
+    self.validate()?;
+    let mut default_referer = self.default_referer;
+    let mut extra_resolvers = vec![];
+    if let Some(ref url) = self.remote_entry {
+      let policy = network_policy(self.config.settings.module_fetch_network.as_ref())?;
+      check_remote_entry(url, &policy)?;
+      extra_resolvers = remote_module_resolvers(Arc::new(policy), module_fetch_options(&self.config.settings))?;
+      default_referer = default_referer.or_else(|| Some(url.to_string()));
+    }
+    if !self.builtin_modules.is_empty() {
+      let builtins = self
+        .builtin_modules
+        .iter()
+        .fold(BuiltinModuleResolver::new(), |builtins, (name, source)| builtins.with_builtin(name, source));
+      extra_resolvers.push(Box::new(builtins) as Box<ModuleResolver>);
+    }
+    // a supplied manager was set up with whatever lockfile it has
+    let lockfile = match self.resolver_manager {
+      Some(_) => None,
+      None => open_lockfile(self.config.settings)?,
+    };
+    let mut rt = Runtime::create(
+      self.config,
+      self.resolver_manager,
+      extra_resolvers,
+      default_referer,
+      self.heap_limits,
+      lockfile,
+    );
+    rt.module_chunks = ModuleChunks::new(self.module_chunking);
+    Ok(rt)
+  }
+}
+
+/// The lockfile `settings` ask for, if any. One to verify against that can't be read
+/// refuses the runtime: running unverified is what a locked deploy is meant to rule out.
+fn open_lockfile(settings: &Settings) -> FlyResult<Option<Lockfile>> {
+  match settings.module_lockfile {
+    Some(ref lockfile) if lockfile.mode != LockfileMode::Off => {
+      match Lockfile::open(std::path::Path::new(&lockfile.path), lockfile.mode) {
+        Ok(opened) => Ok(Some(opened)),
+        Err(e) if lockfile.mode == LockfileMode::Verify => Err(FlyError::from(format!(
+          "could not verify against lockfile {}: {}",
+          lockfile.path, e
+        ))),
+        Err(e) => {
+          error!("could not write lockfile {}: {}", lockfile.path, e);
+          Ok(None)
+        }
+      }
+    }
+    _ => Ok(None),
+  }
+}
+
+impl<'a> From<RuntimeConfig<'a>> for RuntimeBuilder<'a> {
+  fn from(config: RuntimeConfig<'a>) -> Self {
+    RuntimeBuilder {
+      config,
+      resolver_manager: None,
+      default_referer: None,
+      heap_limits: HeapLimits::default(),
+      module_chunking: ModuleChunking::default(),
+      remote_entry: None,
+      builtin_modules: vec![],
+    }
+  }
 }
 
 impl Runtime {
+  pub fn builder<'a>(settings: &'a Settings, app_logger: &'a Logger) -> RuntimeBuilder<'a> {
+    RuntimeBuilder::from(RuntimeConfig {
+      name: None,
+      version: None,
+      settings,
+      module_resolvers: None,
+      app_logger,
+      msg_handler: None,
+      permissions: None,
+      dev_tools: false,
+      entry_points: None,
+      build_info: None,
+    })
+  }
+
+  /// Panics if the runtime can't be built, say when its lockfile can't be verified; see
+  /// `try_new` to handle that instead.
   pub fn new(config: RuntimeConfig) -> Box<Runtime> {
+    Runtime::try_new(config).unwrap_or_else(|e| panic!("could not build runtime: {}", e))
+  }
+
+  pub fn try_new(config: RuntimeConfig) -> FlyResult<Box<Runtime>> {
+    RuntimeBuilder::from(config).build()
+  }
+
+  fn create(
+    config: RuntimeConfig,
+    resolver_manager: Option<Box<ModuleResolverManager>>,
+    extra_resolvers: Vec<Box<ModuleResolver>>,
+    default_referer: Option<String>,
+    heap_limits: HeapLimits,
+    lockfile: Option<Lockfile>,
+  ) -> Box<Runtime> {
     JSINIT.call_once(|| unsafe { js_init() });
 
+    let version = config.version.clone();
+    let build_info = config.build_info.unwrap_or_else(|| BuildInfo {
+      version,
+      ..BuildInfo::default()
+    });
     let rt_name = config.name.unwrap_or("v8".to_string());
     let rt_version = config.version.unwrap_or("0".to_string());
     let app_logger = config
       .app_logger
       .new(slog_o!("app_name" => rt_name.to_owned(), "app_version" => rt_version.to_owned()));
     let (rthandle, txready, rxquit) = init_event_loop(format!("{}-{}", rt_name, rt_version));
-    let rt_module_resolvers =
-      config.module_resolvers.unwrap_or(vec![
-        Box::new(LocalDiskModuleResolver::new(None)) as Box<ModuleResolver>
-      ]);
+    let virtual_modules = VirtualModuleRegistry::new();
+    let app_env = match config.settings.module_env_allowlist {
+      Some(ref allow) => allowlisted_env(allow),
+      None => Default::default(),
+    };
+    if let Err(e) = register_fly_builtins(&virtual_modules, &build_info, &app_env) {
+      error!("could not register fly builtin modules: {}", e);
+    }
+    let module_resolver_manager: Box<ModuleResolverManager> = match resolver_manager {
+      Some(manager) => manager,
+      None => {
+        let mut rt_module_resolvers = match config.module_resolvers {
+          Some(resolvers) => resolvers,
+          None => {
+            let assets = match config.settings.module_text_assets {
+              Some(ref text_assets) => AssetLoaders::text(text_assets),
+              None => AssetLoaders::text(DEFAULT_TEXT_ASSETS),
+            };
+            let max_binary_bytes =
+              config.settings.module_binary_asset_max_bytes.unwrap_or(DEFAULT_BINARY_ASSET_MAX_BYTES);
+            let assets = match config.settings.module_binary_assets {
+              Some(ref binary_assets) => assets.with_binary_assets(binary_assets, max_binary_bytes),
+              None => assets.with_binary_assets(DEFAULT_BINARY_ASSETS, max_binary_bytes),
+            };
+            let mut data_formats = DataFormats::default().with_assets(assets);
+            if !config.settings.module_commonjs.unwrap_or(true) {
+              data_formats = data_formats.without_commonjs();
+            }
+            let local_disk = LocalDiskModuleResolver::new(None).map(|local_disk| {
+              let mut local_disk = local_disk
+                .with_data_formats(data_formats.clone())
+                .with_strict(config.settings.module_strict_resolution.unwrap_or(false))
+                .with_allow_outside_root(config.settings.module_allow_outside_root.unwrap_or(false))
+                .with_canonicalize(config.settings.module_canonicalize_paths.unwrap_or(false))
+                .with_case_sensitivity(config.settings.module_case_sensitivity.unwrap_or_default());
+              if let Some(ref index_files) = config.settings.module_index_files {
+                local_disk = local_disk.with_index_files(index_files.clone());
+              }
+              if let Some(ref extensions) = config.settings.module_extensions {
+                local_disk = local_disk.with_extensions(extensions.clone());
+              }
+              local_disk
+            });
+            let mut resolvers = vec![];
+            if let Some(ref dev_server) = config.settings.module_dev_server {
+              match DevServerResolver::from_config(dev_server) {
+                Ok(dev_server) => resolvers.push(Box::new(dev_server) as Box<ModuleResolver>),
+                Err(e) => error!("could not set up dev server resolver: {}", e),
+              }
+            }
+            match local_disk {
+              Ok(local_disk) => resolvers.push(Box::new(local_disk)),
+              Err(e) => error!("could not set up the local disk resolver: {}", e),
+            }
+            resolvers.push(Box::new(DataUrlResolver::new()));
+            if let Some(ref allow) = config.settings.module_env_allowlist {
+              resolvers.push(Box::new(EnvVarResolver::new(allow.clone())));
+            }
+            if let Some(ref root) = config.settings.module_workspace_root {
+              match WorkspaceResolver::discover(std::path::Path::new(root)) {
+                Ok(workspace) => resolvers.push(Box::new(workspace)),
+                Err(e) => error!("could not load workspace at {}: {}", root, e),
+              }
+            }
+            if let Some(ref root) = config.settings.module_node_modules_root {
+              match NodeModulesResolver::new(std::path::Path::new(root)) {
+                Ok(node_modules) => resolvers.push(Box::new(node_modules.with_data_formats(data_formats))),
+                Err(e) => error!("could not use node_modules under {}: {}", root, e),
+              }
+            }
+            // a remote entry already brought its own
+            let has_remote = extra_resolvers.iter().any(|r| r.get_protocol() == "https");
+            if config.settings.module_remote_imports.unwrap_or(true) && !has_remote {
+              let options = module_fetch_options(&config.settings);
+              match network_policy(config.settings.module_fetch_network.as_ref())
+                .and_then(|policy| remote_module_resolvers(Arc::new(policy), options))
+              {
+                Ok(remote) => resolvers.extend(remote),
+                Err(e) => error!("could not set up remote module resolvers: {}", e),
+              }
+            }
+            resolvers
+          }
+        };
+        rt_module_resolvers.extend(extra_resolvers);
+        if !rt_module_resolvers.iter().any(|r| r.get_protocol() == BUILTIN_PROTOCOL) {
+          rt_module_resolvers.push(Box::new(BuiltinModuleResolver::new()));
+        }
+        let mut module_resolver_manager =
+          StandardModuleResolverManager::new(rt_module_resolvers, None)
+            .with_virtual_modules(virtual_modules.clone())
+            .with_transpiling(config.settings.module_transpile.unwrap_or(true))
+            .with_observer(Arc::new(PrometheusResolutionObserver));
+        if let Some(ref compile_cache) = config.settings.module_compile_cache {
+          let max_bytes = compile_cache.max_bytes.unwrap_or(DEFAULT_COMPILE_CACHE_BYTES);
+          match SqliteModuleCache::open(std::path::Path::new(&compile_cache.path), max_bytes) {
+            Ok(cache) => module_resolver_manager = module_resolver_manager.with_compile_cache(cache),
+            Err(e) => error!("could not open compile cache {}: {}", compile_cache.path, e),
+          }
+        }
+        if let Some(ref dir) = config.settings.module_wasm_cache {
+          match WasmCompileCache::new(std::path::Path::new(dir), &libfly::version()) {
+            Ok(cache) => module_resolver_manager = module_resolver_manager.with_wasm_cache(cache),
+            Err(e) => error!("could not open wasm cache {}: {}", dir, e),
+          }
+        }
+        if let Some(lockfile) = lockfile {
+          module_resolver_manager = module_resolver_manager.with_lockfile(Arc::new(lockfile));
+        }
+        if let Some(ref base_urls) = config.settings.module_base_urls {
+          module_resolver_manager = module_resolver_manager.with_base_urls(base_urls.into());
+        }
+        if let Some(ref wasm_imports) = config.settings.module_wasm_imports {
+          module_resolver_manager = module_resolver_manager.with_wasm_imports(wasm_imports.into());
+        }
+        if let Some(ref path) = config.settings.module_import_map {
+          match ImportMap::from_file(std::path::Path::new(path)) {
+            Ok(import_map) => module_resolver_manager = module_resolver_manager.with_import_map(import_map),
+            Err(e) => error!("could not load import map {}: {}", path, e),
+          }
+        }
+        if let Some(ref scheduler_config) = config.settings.module_load_scheduler {
+          module_resolver_manager =
+            module_resolver_manager.with_load_scheduler(process_wide_scheduler(scheduler_config));
+        }
+        if let Some(ref cache_config) = config.settings.module_source_cache {
+          // runtimes of other apps may serve different sources at the same urls
+          let cache = process_wide_cache(
+            cache_config.max_bytes.unwrap_or(DEFAULT_SHARED_CACHE_BYTES),
+            cache_config.compression.clone(),
+          );
+          module_resolver_manager = module_resolver_manager.with_shared_cache(cache.scoped(&rt_name));
+        }
+        match config.settings.module_cassette {
+          Some(ref cassette) if cassette.mode == CassetteMode::Replay => {
+            let dir = std::path::Path::new(&cassette.dir);
+            Box::new(CassetteResolver::open(dir).unwrap_or_else(|e| panic!("could not replay cassette: {}", e)))
+          }
+          Some(ref cassette) => match CassetteRecorder::create(std::path::Path::new(&cassette.dir)) {
+            Ok(recorder) => Box::new(module_resolver_manager.with_cassette_recorder(Arc::new(recorder))),
+            Err(e) => {
+              error!("could not record cassette: {}", e);
+              Box::new(module_resolver_manager)
+            }
+          },
+          None => Box::new(module_resolver_manager),
+        }
+      }
+    };
 
     let mut rt = Box::new(Runtime {
       ptr: JsRuntime(ptr::null() as *const js_runtime),
@@ -248,16 +691,41 @@ impl Runtime {
         None => None,
       },
       last_event_at: ATOMIC_USIZE_INIT,
-      module_resolver_manager: Box::new(StandardModuleResolverManager::new(
-        rt_module_resolvers,
-        None,
-      )),
-      metadata_cache: RwLock::new(HashMap::new()),
+      module_resolver_manager,
+      virtual_modules,
+      module_loads: CancellationToken::new(),
+      module_registry: CompiledModuleRegistry::new(
+        config
+          .settings
+          .module_release_compiled_sources
+          .unwrap_or(false),
+      ),
+      module_graph: ModuleGraph::new(
+        config
+          .settings
+          .module_hot_reload
+          .clone()
+          .unwrap_or_default(),
+      ),
+      entry_modules: EntryModules::new(),
       msg_handler: config
         .msg_handler
         .unwrap_or(Box::new(DefaultMessageHandler {})),
+      op_metrics: Arc::new(OpMetrics::new()),
+      op_limiter: OpLimiter::new(config.settings.op_limits.clone().unwrap_or_default()),
+      default_referer,
+      guest_resolvers: Arc::new(GuestResolvers::new(
+        config
+          .settings
+          .module_guest_resolvers
+          .clone()
+          .unwrap_or_default(),
+      )),
+      module_chunks: ModuleChunks::new(ModuleChunking::default()),
+      module_deadlines: ResolutionDeadlines::new(),
       permissions: config.permissions.unwrap_or_default(),
       dev_tools: config.dev_tools,
+      entry_points: config.entry_points.unwrap_or_default(),
     });
 
     (*rt).ptr.0 = unsafe {
@@ -267,8 +735,8 @@ impl Runtime {
         recv_cb: msg_from_js,
         print_cb: print_from_js,
         resolve_cb: resolve_callback,
-        soft_memory_limit: 128,
-        hard_memory_limit: 256,
+        soft_memory_limit: heap_limits.soft_mb,
+        hard_memory_limit: heap_limits.hard_mb,
       });
       let cfilename = CString::new("fly_main.js").unwrap();
       let cscript = CString::new("flyMain()").unwrap();
@@ -286,6 +754,34 @@ impl Runtime {
     rt
   }
 
+  /// Starts a worker-style runtime that shares this runtime's module resolvers and
+  /// virtual modules. `policy` can only narrow what the parent allows; resolvers
+  /// passed in `config` are refused since they could widen it.
+  pub fn new_child(
+    &self,
+    config: RuntimeConfig,
+    policy: Option<ResolverPolicy>,
+  ) -> FlyResult<Box<Runtime>> {
+    if config.module_resolvers.is_some() {
+      return Err(FlyError::from(
+        "child runtimes inherit their module resolvers from the parent".to_string(),
+      ));
+    }
+    let module_resolver_manager = self.module_resolver_manager.inherit_for_child(policy)?;
+    let mut rt = Runtime::create(
+      config,
+      Some(module_resolver_manager),
+      vec![],
+      self.default_referer.clone(),
+      HeapLimits::default(),
+      None,
+    );
+    rt.virtual_modules = self.virtual_modules.clone();
+    rt.module_loads = self.module_loads.child();
+    rt.module_chunks = ModuleChunks::new(self.module_chunks.chunking());
+    Ok(rt)
+  }
+
   pub fn eval(&self, filename: &str, code: &str) {
     debug!("evaluating '{}'", filename);
     let cfilename = CString::new(filename).unwrap();
@@ -297,6 +793,98 @@ impl Runtime {
     debug!("finished evaluating '{}'", cfilename.to_string_lossy());
   }
 
+  fn eval_checked(&self, filename: &str, code: &str) -> bool {
+    let cfilename = CString::new(filename).unwrap();
+    let ccode = CString::new(code).unwrap();
+    unsafe { js_eval(self.ptr.0, cfilename.as_ptr(), ccode.as_ptr()) }
+  }
+
+  pub fn entry_points(&self) -> &[EntryPoint] {
+    &self.entry_points
+  }
+
+  /// Evaluates a named entry point with the dev tools module loader. All entry points
+  /// share the loader's module registry, so common dependencies are only loaded once.
+  pub fn eval_entry_point(&self, name: &str) -> FlyResult<()> {
+    let entry = match self.entry_points.iter().find(|e| e.name == name) {
+      Some(e) => e,
+      None => return Err(FlyError::from(format!("unknown entry point '{}'", name))),
+    };
+    if !self.dev_tools {
+      return Err(FlyError::from(format!(
+        "entry point '{}' requires dev tools to be enabled",
+        name
+      )));
+    }
+    debug!("evaluating entry point '{}' ({})", entry.name, entry.specifier);
+    let specifier = serde_json::to_string(&entry.specifier).unwrap();
+    if self.eval_checked(
+      &format!("<entry:{}>", entry.name),
+      &format!("dev.run({})", specifier),
+    ) {
+      Ok(())
+    } else {
+      Err(FlyError::from(format!(
+        "module graph of entry point '{}' ({}) failed to load",
+        entry.name, entry.specifier
+      )))
+    }
+  }
+
+  /// Loads the module graph of a named entry point, without running it, so the runtime's
+  /// imports of it are served from the module resolver manager's caches. Imports that
+  /// fail to load fail the whole entry point.
+  pub fn preload_entry_point(&self, name: &str) -> FlyResult<PreloadSummary> {
+    let entry = match self.entry_points.iter().find(|e| e.name == name) {
+      Some(e) => e,
+      None => return Err(FlyError::from(format!("unknown entry point '{}'", name))),
+    };
+    debug!("preloading entry point '{}' ({})", entry.name, entry.specifier);
+    let failed = |why: String| {
+      FlyError::from(format!(
+        "module graph of entry point '{}' ({}) failed to load: {}",
+        entry.name, entry.specifier, why
+      ))
+    };
+    let summary =
+      preload_graph(&*self.module_resolver_manager, &entry.specifier).map_err(|e| failed(format!("{}", e)))?;
+    if summary.failures.is_empty() {
+      Ok(summary)
+    } else {
+      Err(failed(summary.failures.join(", ")))
+    }
+  }
+
+  /// Warms up every configured entry point: evaluates them with dev tools, preloads their
+  /// module graphs otherwise. A failing entry doesn't prevent the others from loading;
+  /// the returned error names each entry that failed.
+  pub fn warmup_entry_points(&self) -> FlyResult<()> {
+    let failures: Vec<String> = self
+      .entry_points
+      .iter()
+      .filter_map(|entry| match self.warmup_entry_point(&entry.name) {
+        Ok(_) => None,
+        Err(e) => {
+          error!("{}", e);
+          Some(format!("{}", e))
+        }
+      })
+      .collect();
+    if failures.is_empty() {
+      Ok(())
+    } else {
+      Err(FlyError::from(failures.join("; ")))
+    }
+  }
+
+  fn warmup_entry_point(&self, name: &str) -> FlyResult<()> {
+    if self.dev_tools {
+      self.eval_entry_point(name)
+    } else {
+      self.preload_entry_point(name).map(|_| ())
+    }
+  }
+
   pub fn eval_file(&self, filename: &str) {
     let mut file = File::open(filename).unwrap();
     let mut contents = String::new();
@@ -309,6 +897,18 @@ impl Runtime {
     self.eval(filename, &format!("dev.run('{}')", filename));
   }
 
+  /// Loads `specifier` as the app's entry module with the dev tools module loader, from
+  /// whichever resolver handles it, e.g. a remote entry's url. Why the load failed is
+  /// logged by the loader; this only reports that it did.
+  pub fn eval_module_with_dev_tools(&self, specifier: &str) -> FlyResult<()> {
+    let quoted = serde_json::to_string(specifier).unwrap();
+    if self.eval_checked(specifier, &format!("dev.run({})", quoted)) {
+      Ok(())
+    } else {
+      Err(FlyError::from(format!("could not load entry module {}", specifier)))
+    }
+  }
+
   pub fn heap_statistics(&self) -> js_heap_stats {
     unsafe { js_runtime_heap_statistics(self.ptr.0) }
   }
@@ -325,6 +925,8 @@ impl Runtime {
       self.resolv_events.take();
     };
 
+    self.module_loads.cancel();
+
     match self.timers.lock() {
       Ok(mut timers) => timers.clear(),
       Err(_) => error!("error acquiring lock to clear timers"),
@@ -404,20 +1006,122 @@ impl Runtime {
     Some(Ok(res))
   }
 
+  /// The module compiled with identity `hash`. If its source was released after
+  /// compiling, it's reloaded through the module resolvers, which is slow; callers that
+  /// only need the origin should use `get_module_info`.
   pub fn get_module_metadata(&self, hash: &i32) -> Option<Box<LoadedModule>> {
-    return match self.metadata_cache.read().unwrap().get(hash) {
-      Some(v) => Some((*v).clone()),
-      None => None,
+    let info = self.module_registry.info(*hash)?;
+    let source = match self.module_registry.source(*hash, |origin_url| {
+      self
+        .module_resolver_manager
+        .resolve_blocking(origin_url.to_string(), None)
+        .map(|m| m.loaded_source)
+    }) {
+      Ok(source) => source,
+      Err(e) => {
+        error!("could not reload source of {}: {}", info.origin_url, e);
+        return None;
+      }
     };
+    Some(Box::new(LoadedModule {
+      origin_url: info.origin_url,
+      loaded_source: LoadedSourceCode {
+        kind: info.kind,
+        source_map: info.source_map,
+        source,
+        response: None,
+        provenance: None,
+        code_cache: None,
+        compiled_wasm: None,
+        wasm: None,
+      },
+      passthrough: info.passthrough,
+      resolver: None,
+    }))
   }
 
-  pub fn insert_module_metadata(&mut self, hash: i32, module_metadata: LoadedModule) {
-    let locked_cache = self.metadata_cache.get_mut().unwrap();
-    if locked_cache.contains_key(&hash) {
-      error!("Attempted to overwrite entry in module metadata cache.");
-    } else {
-      locked_cache.insert(hash, Box::new(module_metadata));
+  pub fn module_identifier_hash(&self, origin_url: &str) -> Option<i32> {
+    self.module_registry.hash_of(origin_url)
+  }
+
+  pub fn get_module_info(&self, hash: &i32) -> Option<CompiledModuleInfo> {
+    self.module_registry.info(*hash)
+  }
+
+  /// The origin urls of the modules this runtime compiled.
+  pub fn compiled_origin_urls(&self) -> Vec<String> {
+    self.module_registry.origin_urls()
+  }
+
+  /// Makes `specifier` importable from memory. Registering over an existing
+  /// virtual module replaces it and drops any compiled metadata kept for it, here and
+  /// in the dev tools module loader, so later imports get the new source.
+  pub fn register_virtual_module(
+    &self,
+    specifier: &str,
+    source: &str,
+    media_type: MediaType,
+  ) -> FlyResult<()> {
+    if self
+      .virtual_modules
+      .insert(specifier, source, media_type)?
+      .is_some()
+    {
+      self.invalidate_module_metadata(specifier);
+      self.evict_from_dev_tools(specifier);
     }
+    Ok(())
+  }
+
+  pub fn remove_virtual_module(&self, specifier: &str) -> bool {
+    match self.virtual_modules.remove(specifier) {
+      Some(_) => {
+        self.invalidate_module_metadata(specifier);
+        self.evict_from_dev_tools(specifier);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// The dev tools module loader keeps every module it loaded by origin url; this makes
+  /// it load `origin_url` again the next time it's imported.
+  fn evict_from_dev_tools(&self, origin_url: &str) {
+    if !self.dev_tools {
+      return;
+    }
+    let quoted = serde_json::to_string(origin_url).unwrap();
+    self.eval("<evict-module>", &format!("dev.evict({})", quoted));
+  }
+
+  fn invalidate_module_metadata(&self, origin_url: &str) {
+    self.module_registry.invalidate(origin_url);
+  }
+
+  /// Works out what a change to `origin_url` affects. For a contained update the modules
+  /// to re-run are dropped from the compiled metadata (their state is discarded) and JS
+  /// `hotupdate` listeners are told; a `FullRestart` is left to the caller.
+  pub fn hot_update(&self, origin_url: &str) -> HotUpdate {
+    let update = self.module_graph.plan_update(origin_url);
+    if let HotUpdate::Update { ref reload, .. } = update {
+      for url in reload {
+        self.invalidate_module_metadata(url);
+        self.module_graph.forget_imports_of(url);
+      }
+      let json = serde_json::to_string(&update).unwrap();
+      self.eval(
+        "<hot-update>",
+        &format!(
+          "typeof flyHotUpdate === 'function' && flyHotUpdate({})",
+          json
+        ),
+      );
+    }
+    update
+  }
+
+  pub fn insert_module_metadata(&mut self, hash: i32, module_metadata: LoadedModule) {
+    self.module_registry.insert(hash, module_metadata);
   }
 }
 
@@ -448,10 +1152,46 @@ pub extern "C" fn msg_from_js(raw: *const js_runtime, buf: fly_buf, raw_buf: fly
 
   let msg_type = base.msg_type();
   let cmd_id = base.cmd_id();
+  let op_metrics = rt.op_metrics.clone();
+  let dispatched_at = time::Instant::now();
+
+  // Sync ops hold the JS thread until they're done, so only async ones can pile up.
+  let admission = match op_class(msg_type) {
+    Some(class) if !base.sync() => Some(rt.op_limiter.admit(msg_type, class)),
+    _ => None,
+  };
+  let op: Box<Op> = match admission {
+    None => rt.msg_handler.handle_msg(ptr.to_runtime(), &base, raw_buf),
+    Some(Admission::Rejected(err)) => odd_future(err),
+    Some(Admission::Now(permit)) => Box::new(
+      rt.msg_handler
+        .handle_msg(ptr.to_runtime(), &base, raw_buf)
+        .then(move |res| {
+          drop(permit);
+          res
+        }),
+    ),
+    // The handler has to run now, while the message is readable; only its future waits.
+    Some(Admission::Queued(permit)) => {
+      let op = rt.msg_handler.handle_msg(ptr.to_runtime(), &base, raw_buf);
+      Box::new(
+        permit
+          .map_err(|_| FlyError::from("op limiter went away".to_string()))
+          .and_then(move |permit| {
+            op.then(move |res| {
+              drop(permit);
+              res
+            })
+          }),
+      )
+    }
+  };
 
-  let fut = rt
-    .msg_handler
-    .handle_msg(ptr.to_runtime(), &base, raw_buf)
+  let fut = op
+    .then(move |res| {
+      op_metrics.record(msg_type, dispatched_at.elapsed(), res.is_err());
+      res
+    })
     .or_else(move |err| {
       error!("error in {:?}: {:?}", msg_type, err);
       Ok(build_error(cmd_id, err))
@@ -531,21 +1271,35 @@ pub unsafe extern "C" fn resolve_callback(
   let rt = Runtime::from_raw(raw);
   let specifier_str = CStr::from_ptr(specifier).to_string_lossy().into_owned();
 
-  let referer_loaded_module = match rt.get_module_metadata(&referer_identity_hash) {
+  let referer_info = match rt.get_module_info(&referer_identity_hash) {
     Some(v) => v,
     None => {
       error!("Failed to find module hash in metadata cache! Exiting.");
       std::process::exit(1);
     }
   };
+  // released sources aren't reloaded just to describe the referer, but virtual modules
+  // have nothing to reload from and are still in memory
+  let referer_source = if referer_info.resident {
+    rt.get_module_metadata(&referer_identity_hash)
+      .map(|m| cap_referer_source(m.loaded_source.source))
+  } else {
+    rt.virtual_modules
+      .get(&referer_info.origin_url)
+      .map(|m| cap_referer_source(m.source))
+  };
 
-  let loaded_module = match rt.module_resolver_manager.resolve_module(
+  let loaded_module = match rt.module_resolver_manager.resolve_blocking(
     specifier_str,
     Some(RefererInfo {
-      origin_url: referer_loaded_module.origin_url,
-      is_wasm: Some(referer_loaded_module.loaded_source.is_wasm),
-      source_code: Some(referer_loaded_module.loaded_source.source),
-      indentifier_hash: Some(referer_identity_hash),
+      origin_url: referer_info.origin_url.clone(),
+      is_wasm: Some(referer_info.kind.is_wasm()),
+      source_code: referer_source,
+      identifier_hash: Some(referer_identity_hash),
+      line: None,
+      column: None,
+      app_id: Some(rt.name.clone()),
+      deadline: None,
     }),
   ) {
     Ok(v) => v,
@@ -555,24 +1309,438 @@ pub unsafe extern "C" fn resolve_callback(
     }
   };
 
+  // The shared source stays untouched; libfly gets its own NUL terminated copy, which
+  // has to outlive the compile call.
+  rt.module_graph
+    .record_import(&referer_info.origin_url, &loaded_module.origin_url);
+
+  let origin_url = CString::new(loaded_module.origin_url.as_str()).unwrap();
+  let source_map_url = CString::new("").unwrap();
+  let source_code = CString::new(&*loaded_module.loaded_source.source).unwrap();
+  let code_cache = match loaded_module.loaded_source.code_cache {
+    Some(ref bytes) => fly_simple_buf {
+      ptr: bytes.as_ptr() as *const libc::c_char,
+      len: bytes.len() as i32,
+    },
+    None => fly_simple_buf {
+      ptr: std::ptr::null(),
+      len: 0,
+    },
+  };
   let module_data = js_module_data {
-    origin_url: CString::new(loaded_module.origin_url).unwrap().as_ptr(),
-    source_map_url: CString::new("").unwrap().as_ptr(),
-    is_wasm: loaded_module.loaded_source.is_wasm,
+    origin_url: origin_url.as_ptr(),
+    source_map_url: source_map_url.as_ptr(),
+    is_wasm: loaded_module.loaded_source.is_wasm(),
     source_code: fly_simple_buf {
-      ptr: CString::new(loaded_module.loaded_source.source.as_str())
-        .unwrap()
-        .as_ptr(),
+      ptr: source_code.as_ptr(),
       len: loaded_module.loaded_source.source.len() as i32,
     },
+    code_cache,
   };
 
-  let compile_result = js_compile_module(raw, module_data);
+  let mut compile_result = js_compile_module(raw, module_data);
 
   if compile_result.success {
+    // what V8 made compiling it, for the next runtime to skip parsing with
+    let made = compile_result.compiled_module.code_cache;
+    if !made.ptr.is_null() {
+      let bytes = std::slice::from_raw_parts(made.ptr as *const u8, made.len as usize).to_vec();
+      js_free_code_cache(made);
+      compile_result.compiled_module.code_cache.ptr = std::ptr::null();
+      rt.module_resolver_manager.store_code_cache(
+        &loaded_module.origin_url,
+        &loaded_module.loaded_source.source,
+        bytes,
+      );
+    }
+    let hash = compile_result.compiled_module.hash;
+    rt.insert_module_metadata(hash, loaded_module);
+    rt.module_registry.compiled(hash);
     return compile_result.compiled_module;
   } else {
     error!("Module compile failed! Exiting.");
     std::process::exit(1);
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::module_resolver::LockfileConfig;
+  use crate::settings::ModuleSourceCacheConfig;
+  use std::time::{Duration, Instant};
+
+  fn invalid(builder: RuntimeBuilder) -> String {
+    match builder.validate() {
+      Ok(()) => panic!("expected the builder to refuse its configuration"),
+      Err(e) => format!("{}", e),
+    }
+  }
+
+  #[test]
+  fn test_builder_validates_before_starting_anything() {
+    let settings = Settings::default();
+    let logger = Logger::root(slog::Discard, slog_o!());
+
+    let err = invalid(
+      Runtime::builder(&settings, &logger)
+        .module_resolvers(vec![])
+        .resolver_manager(Box::new(StandardModuleResolverManager::new(vec![], None))),
+    );
+    assert!(err.contains("supplied"), err);
+
+    let err = invalid(
+      Runtime::builder(&settings, &logger)
+        .builtin_module("kv", "export default fly.kv;")
+        .resolver_manager(Box::new(StandardModuleResolverManager::new(vec![], None))),
+    );
+    assert!(err.contains("supplied"), err);
+
+    let err = invalid(Runtime::builder(&settings, &logger).default_referer("not a url"));
+    assert!(err.contains("not a url"), err);
+
+    let err = invalid(Runtime::builder(&settings, &logger).heap_limits(HeapLimits {
+      soft_mb: 512,
+      hard_mb: 256,
+    }));
+    assert!(err.contains("512MB"), err);
+  }
+
+  #[test]
+  fn test_refuses_to_start_unverified() {
+    let mut settings = Settings::default();
+    settings.module_lockfile = Some(LockfileConfig {
+      path: "/nonexistent/fly.lock".to_string(),
+      mode: LockfileMode::Verify,
+    });
+    let logger = Logger::root(slog::Discard, slog_o!());
+    let err = Runtime::builder(&settings, &logger).build().err().unwrap();
+    assert!(
+      format!("{}", err).starts_with("could not verify against lockfile /nonexistent/fly.lock: "),
+      "{}",
+      err
+    );
+
+    // only verifying needs one to be there
+    settings.module_lockfile = Some(LockfileConfig {
+      path: "/nonexistent/fly.lock".to_string(),
+      mode: LockfileMode::Write,
+    });
+    assert!(Runtime::builder(&settings, &logger).build().is_ok());
+  }
+
+  fn dev_runtime() -> Box<Runtime> {
+    let settings = Settings::default();
+    let logger = Logger::root(slog::Discard, slog_o!());
+    Runtime::builder(&settings, &logger)
+      .dev_tools(true)
+      .build()
+      .unwrap()
+  }
+
+  /// A module that throws unless `app:config`'s default export is `expected`.
+  fn expects_config(rt: &Runtime, specifier: &str, expected: u32) {
+    let source = format!(
+      "import config from 'app:config';\n\
+       if (config !== {0}) throw new Error('expected {0}, got ' + config);",
+      expected
+    );
+    rt.register_virtual_module(specifier, &source, MediaType::JavaScript)
+      .unwrap();
+  }
+
+  #[test]
+  fn test_virtual_modules_replace_what_running_runtimes_import() {
+    let rt = dev_runtime();
+    // registered before the runtime ran anything
+    rt.register_virtual_module("app:config", "export default 1;", MediaType::JavaScript)
+      .unwrap();
+    expects_config(&rt, "app:first", 1);
+    rt.eval_module_with_dev_tools("app:first").unwrap();
+
+    // replaced once a module it ran imported it
+    rt.register_virtual_module("app:config", "export default 2;", MediaType::JavaScript)
+      .unwrap();
+    expects_config(&rt, "app:second", 2);
+    rt.eval_module_with_dev_tools("app:second").unwrap();
+
+    // and removed
+    assert!(rt.remove_virtual_module("app:config"));
+    expects_config(&rt, "app:third", 2);
+    assert!(rt.eval_module_with_dev_tools("app:third").is_err());
+  }
+
+  #[test]
+  fn test_virtual_modules_registered_after_running() {
+    let rt = dev_runtime();
+    rt.register_virtual_module(
+      "app:main",
+      "export const started = true;",
+      MediaType::JavaScript,
+    )
+    .unwrap();
+    rt.eval_module_with_dev_tools("app:main").unwrap();
+
+    rt.register_virtual_module("app:config", "3", MediaType::Json)
+      .unwrap();
+    expects_config(&rt, "app:late", 3);
+    rt.eval_module_with_dev_tools("app:late").unwrap();
+    assert!(!rt.remove_virtual_module("app:missing"));
+  }
+
+  fn leb128(mut n: usize, out: &mut Vec<u8>) {
+    loop {
+      let byte = (n & 0x7f) as u8;
+      n >>= 7;
+      if n == 0 {
+        out.push(byte);
+        return;
+      }
+      out.push(byte | 0x80);
+    }
+  }
+
+  fn wasm_section(id: u8, content: Vec<u8>, out: &mut Vec<u8>) {
+    out.push(id);
+    leb128(content.len(), out);
+    out.extend(content);
+  }
+
+  /// A wasm binary of `functions` functions each adding up a few dozen ones, exporting the
+  /// first as `answer`, returning 42: big enough for V8 to take a while compiling it.
+  fn big_wasm(functions: usize) -> Vec<u8> {
+    let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+    // one type, () -> i32
+    wasm_section(1, vec![1, 0x60, 0, 1, 0x7f], &mut wasm);
+    let mut types = vec![];
+    leb128(functions, &mut types);
+    types.extend(std::iter::repeat(0).take(functions));
+    wasm_section(3, types, &mut wasm);
+    let mut exports = vec![1, 6];
+    exports.extend(b"answer");
+    exports.extend(&[0, 0]);
+    wasm_section(7, exports, &mut wasm);
+
+    let mut code = vec![];
+    leb128(functions, &mut code);
+    // no locals, i32.const 42, end
+    code.extend(&[4, 0, 0x41, 42, 0x0b]);
+    // no locals, i32.const 1, (i32.const 1, i32.add) * 20, end
+    let mut body = vec![0, 0x41, 1];
+    for _ in 0..20 {
+      body.extend(&[0x41, 1, 0x6a]);
+    }
+    body.push(0x0b);
+    for _ in 1..functions {
+      leb128(body.len(), &mut code);
+      code.extend(&body);
+    }
+    wasm_section(10, code, &mut wasm);
+    wasm
+  }
+
+  /// How long runtimes take to boot an app importing an 8MB wasm module, compiling it and then
+  /// instantiating it from `module_wasm_cache`:
+  /// `cargo test --release bench_boot_with_wasm_cache -- --ignored --nocapture`
+  #[test]
+  #[ignore]
+  fn bench_boot_with_wasm_cache() {
+    let app = tempfile::tempdir().unwrap();
+    let wasm = big_wasm(120_000);
+    std::fs::write(app.path().join("big.wasm"), &wasm).unwrap();
+    std::fs::write(
+      app.path().join("main.js"),
+      "import { answer } from './big.wasm';\n\
+       if (answer() !== 42) throw new Error('expected 42, got ' + answer());",
+    )
+    .unwrap();
+    let app_url = url::Url::from_directory_path(app.path()).unwrap();
+    let main = app_url.join("main.js").unwrap().into_string();
+    let cache = tempfile::tempdir().unwrap();
+    let logger = Logger::root(slog::Discard, slog_o!());
+
+    let boot = |settings: &Settings| {
+      let started = Instant::now();
+      let rt = Runtime::builder(settings, &logger)
+        .dev_tools(true)
+        .module_resolvers(vec![Box::new(
+          LocalDiskModuleResolver::new(Some(app_url.to_string())).unwrap(),
+        ) as Box<ModuleResolver>])
+        .build()
+        .unwrap();
+      rt.eval_module_with_dev_tools(&main).unwrap();
+      (started.elapsed(), rt)
+    };
+    let stored = || {
+      WasmCompileCache::new(cache.path(), &libfly::version())
+        .unwrap()
+        .stats()
+        .modules
+    };
+
+    let (without_cache, _) = boot(&Settings::default());
+    let mut settings = Settings::default();
+    settings.module_wasm_cache = Some(cache.path().to_string_lossy().into_owned());
+    let (compiling, _rt) = boot(&settings);
+    // the artifact is stored once the first runtime compiled it
+    let waiting = Instant::now();
+    while stored() == 0 {
+      assert!(
+        waiting.elapsed() < Duration::from_secs(30),
+        "nothing was stored"
+      );
+      std::thread::sleep(Duration::from_millis(10));
+    }
+    let runs = 5;
+    let mut cached = Duration::from_secs(0);
+    for _ in 0..runs {
+      cached += boot(&settings).0;
+    }
+    let cached = cached / runs;
+
+    println!(
+      "booting with a {} byte wasm module: {:?} without a cache, {:?} compiling into it, {:?} from it",
+      wasm.len(),
+      without_cache,
+      compiling,
+      cached
+    );
+    assert!(cached < without_cache);
+  }
+
+  /// The resident set size of this process, from `/proc/self/statm`.
+  fn resident_bytes() -> usize {
+    let statm = std::fs::read_to_string("/proc/self/statm").unwrap();
+    let pages: usize = statm.split_whitespace().nth(1).unwrap().parse().unwrap();
+    pages * unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize
+  }
+
+  /// What 50 runtimes running a 5MB app add to the resident set, each loading its own copy of
+  /// the sources and then all sharing them through `module_source_cache`:
+  /// `cargo test --release bench_resident_memory_of_runtimes -- --ignored --nocapture`
+  #[test]
+  #[ignore]
+  fn bench_resident_memory_of_runtimes() {
+    let app = tempfile::tempdir().unwrap();
+    let modules = 20;
+    let mut main = String::new();
+    for i in 0..modules {
+      let source = format!(
+        "export default '{}';\n",
+        std::iter::repeat('x').take(256 * 1024).collect::<String>()
+      );
+      std::fs::write(app.path().join(format!("part{}.js", i)), source).unwrap();
+      main.push_str(&format!("import part{0} from './part{0}.js';\n", i));
+    }
+    std::fs::write(app.path().join("main.js"), main).unwrap();
+    let app_url = url::Url::from_directory_path(app.path()).unwrap();
+    let main = app_url.join("main.js").unwrap().into_string();
+    let logger = Logger::root(slog::Discard, slog_o!());
+
+    let runtimes = 50;
+    // each set stays up while the next one boots, so it can't reuse what the first freed
+    let mut running = vec![];
+    let mut boot_all = |settings: &Settings| {
+      let before = resident_bytes();
+      for _ in 0..runtimes {
+        let rt = Runtime::builder(settings, &logger)
+          .name("app")
+          .dev_tools(true)
+          .module_resolvers(vec![Box::new(
+            LocalDiskModuleResolver::new(Some(app_url.to_string())).unwrap(),
+          ) as Box<ModuleResolver>])
+          .build()
+          .unwrap();
+        rt.eval_module_with_dev_tools(&main).unwrap();
+        running.push(rt);
+      }
+      resident_bytes().saturating_sub(before)
+    };
+
+    let unshared = boot_all(&Settings::default());
+    let mut settings = Settings::default();
+    settings.module_source_cache = Some(ModuleSourceCacheConfig {
+      max_bytes: Some(64 * 1024 * 1024),
+      compression: None,
+    });
+    let shared = boot_all(&settings);
+
+    let mb = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    println!(
+      "{} runtimes of a {}KB app: {:.1}MB resident ({:.2}MB each) loading their own sources, {:.1}MB ({:.2}MB each) sharing them",
+      runtimes,
+      modules * 256,
+      mb(unshared),
+      mb(unshared) / runtimes as f64,
+      mb(shared),
+      mb(shared) / runtimes as f64
+    );
+    assert!(shared < unshared);
+  }
+
+  fn entry(name: &str, specifier: &str) -> EntryPoint {
+    EntryPoint {
+      name: name.to_string(),
+      specifier: specifier.to_string(),
+    }
+  }
+
+  /// `app:http` loads; `app:cron` imports a module nothing serves.
+  fn with_entry_points(dev_tools: bool) -> Box<Runtime> {
+    let settings = Settings::default();
+    let logger = Logger::root(slog::Discard, slog_o!());
+    let rt = Runtime::builder(&settings, &logger)
+      .dev_tools(dev_tools)
+      .entry_points(vec![entry("cron", "app:cron"), entry("http", "app:http")])
+      .build()
+      .unwrap();
+    rt.register_virtual_module("app:shared", "export default 1;", MediaType::JavaScript)
+      .unwrap();
+    rt.register_virtual_module(
+      "app:http",
+      "import shared from 'app:shared';\nwindow.httpLoaded = shared;",
+      MediaType::JavaScript,
+    )
+    .unwrap();
+    rt.register_virtual_module(
+      "app:cron",
+      "import shared from 'app:shared';\nimport missing from 'app:missing';",
+      MediaType::JavaScript,
+    )
+    .unwrap();
+    rt
+  }
+
+  #[test]
+  fn test_entry_points_warm_up_independently() {
+    let rt = with_entry_points(true);
+    let err = rt.warmup_entry_points().err().unwrap().to_string();
+    assert!(err.contains("entry point 'cron' (app:cron)"), err);
+    assert!(!err.contains("'http'"), err);
+    // the failing entry didn't keep the other from running
+    assert!(rt.eval_checked("<check>", "if (window.httpLoaded !== 1) throw new Error()"));
+    assert!(rt.eval_entry_point("http").is_ok());
+  }
+
+  #[test]
+  fn test_entry_points_preload_without_dev_tools() {
+    let rt = with_entry_points(false);
+    let summary = rt.preload_entry_point("http").unwrap();
+    assert_eq!(2, summary.modules);
+    let err = rt.warmup_entry_points().err().unwrap().to_string();
+    assert!(err.contains("entry point 'cron' (app:cron)"), err);
+    assert!(err.contains("app:missing from app:cron"), err);
+    assert!(!err.contains("'http'"), err);
+
+    let err = rt.eval_entry_point("http").err().unwrap().to_string();
+    assert_eq!("entry point 'http' requires dev tools to be enabled", err);
+  }
+
+  #[test]
+  fn test_unknown_entry_points() {
+    let rt = with_entry_points(true);
+    let err = rt.eval_entry_point("worker").err().unwrap().to_string();
+    assert_eq!("unknown entry point 'worker'", err);
+    assert!(rt.preload_entry_point("worker").is_err());
+  }
+}
@@ -1,5 +1,11 @@
 extern crate config;
 use self::config::{Config, ConfigError, Environment, File};
+use crate::module_resolver::{
+  CaseSensitivityMode, CassetteConfig, DevServerConfig, LockfileConfig, FetchNetworkPolicyConfig, HotReloadConfig, LoadSchedulerConfig, RemoteModuleCacheConfig, SourceCompression,
+};
+use crate::guest_resolver::GuestResolversConfig;
+use crate::op_limits::OpLimitsConfig;
+use std::collections::HashMap;
 use std::sync::RwLock;
 
 lazy_static! {
@@ -65,6 +71,26 @@ pub enum CacheStoreNotifier {
   Redis(RedisCacheNotifierConfig),
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModuleSourceCacheConfig {
+  pub max_bytes: Option<usize>,
+  pub compression: Option<SourceCompression>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModuleDiskCacheConfig {
+  pub dir: String,
+  #[serde(flatten)]
+  pub cache: RemoteModuleCacheConfig,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModuleCompileCacheConfig {
+  /// The SQLite database compiled modules are kept in, created if missing.
+  pub path: String,
+  pub max_bytes: Option<usize>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
   pub data_store: Option<DataStore>,
@@ -72,6 +98,82 @@ pub struct Settings {
   pub cache_store_notifier: Option<CacheStoreNotifier>,
   pub fs_store: Option<FsStore>,
   pub acme_store: Option<AcmeStoreConfig>,
+  pub module_source_cache: Option<ModuleSourceCacheConfig>,
+  /// Drop module sources once they're compiled, reloading them if ever needed again.
+  /// Off by default so debugging keeps sources at hand.
+  pub module_release_compiled_sources: Option<bool>,
+  pub module_disk_cache: Option<ModuleDiskCacheConfig>,
+  pub module_fetch_network: Option<FetchNetworkPolicyConfig>,
+  /// How many redirects a module fetch follows before giving up. 10 unless set.
+  pub module_fetch_max_redirects: Option<u32>,
+  /// Let module fetches follow redirects from `https` to `http`. Off unless turned on.
+  pub module_fetch_allow_insecure_redirects: Option<bool>,
+  /// Whether apps may import `https`/`http` modules, fetched under `module_fetch_network`.
+  /// On unless turned off.
+  pub module_remote_imports: Option<bool>,
+  pub module_load_scheduler: Option<LoadSchedulerConfig>,
+  pub module_base_urls: Option<HashMap<String, String>>,
+  /// Specifiers for the module names wasm modules import, e.g. `env` -> `fly:wasm-env`.
+  pub module_wasm_imports: Option<HashMap<String, String>>,
+  /// A browser style import map file remapping specifiers, e.g. `lodash` to a CDN url.
+  pub module_import_map: Option<String>,
+  pub module_workspace_root: Option<String>,
+  /// Resolve bare package imports from the node_modules under this directory. The `fly` CLI
+  /// defaults it to the nearest one above where it runs.
+  pub module_node_modules_root: Option<String>,
+  /// Files an import naming a directory resolves to, tried in order. Defaults to
+  /// `index.ts`, `index.js`, `index.wasm`.
+  pub module_index_files: Option<Vec<String>>,
+  /// Extensions appended, in order, to a local import naming no file exactly. Defaults to
+  /// `ts`, `tsx`, `js`, `jsx`, `mjs`, `json`, `wasm`.
+  pub module_extensions: Option<Vec<String>>,
+  /// Load local `.js` and `.cjs` files written as CommonJS as ES modules, their `module.exports`
+  /// the default export. On unless turned off.
+  pub module_commonjs: Option<bool>,
+  /// Extensions of local files imported as text, their contents the default export. Defaults
+  /// to `css`, `txt`, `html`, `md`.
+  pub module_text_assets: Option<Vec<String>>,
+  /// Extensions of local files imported as a `Uint8Array` of their bytes. Defaults to common
+  /// image and font formats.
+  pub module_binary_assets: Option<Vec<String>>,
+  /// The largest binary asset that can be imported, 1 MB unless configured.
+  pub module_binary_asset_max_bytes: Option<u64>,
+  /// Resolve local modules only by their exact file name, never probing extensions.
+  pub module_strict_resolution: Option<bool>,
+  /// Let apps import local files outside the directory they run from. The `fly` CLI turns it
+  /// on unless configured.
+  pub module_allow_outside_root: Option<bool>,
+  /// Identify local modules by their real path, symlinks resolved, so a file reached through
+  /// several symlinked paths loads once.
+  pub module_canonicalize_paths: Option<bool>,
+  /// Whether a local import whose file name differs in case from the file's, which only
+  /// case-insensitive filesystems resolve, is ignored, warned about (the default) or refused.
+  pub module_case_sensitivity: Option<CaseSensitivityMode>,
+  /// Compile `.ts` and `.tsx` modules to JavaScript as they load. On unless turned off, for
+  /// apps deployed as prebuilt JavaScript. Needs a build with the `transpile` feature; others
+  /// leave TypeScript to the dev tools compiler.
+  pub module_transpile: Option<bool>,
+  /// Keep compiled TypeScript across restarts, recompiling only modules whose source changed.
+  pub module_compile_cache: Option<ModuleCompileCacheConfig>,
+  /// Directory WebAssembly modules are kept in as V8 compiled them, so runtimes booting after
+  /// the first instantiate them without compiling. Artifacts of other V8 versions are removed.
+  pub module_wasm_cache: Option<String>,
+  pub module_hot_reload: Option<HotReloadConfig>,
+  /// Environment variables apps may read through `fly:env` or `env://NAME`; nothing else is
+  /// exposed.
+  pub module_env_allowlist: Option<Vec<String>>,
+  /// Development only: serve modules from a local dev server, ahead of the disk.
+  pub module_dev_server: Option<DevServerConfig>,
+  /// Record every module resolution into a cassette directory, or replay one instead of resolving.
+  pub module_cassette: Option<CassetteConfig>,
+  /// Record the origin and hash of every module resolved into a lockfile, or run only what one
+  /// locked.
+  pub module_lockfile: Option<LockfileConfig>,
+  /// Whether apps may serve a protocol of their own through `fly.registerResolver`. Off
+  /// unless enabled here.
+  pub module_guest_resolvers: Option<GuestResolversConfig>,
+  /// Caps on each runtime's pending async ops; defaults apply when unset.
+  pub op_limits: Option<OpLimitsConfig>,
 }
 
 impl Settings {
@@ -92,6 +194,39 @@ impl Default for Settings {
       cache_store_notifier: None,
       fs_store: None,
       acme_store: None,
+      module_source_cache: None,
+      module_release_compiled_sources: None,
+      module_disk_cache: None,
+      module_fetch_network: None,
+      module_fetch_max_redirects: None,
+      module_fetch_allow_insecure_redirects: None,
+      module_remote_imports: None,
+      module_load_scheduler: None,
+      module_base_urls: None,
+      module_wasm_imports: None,
+      module_import_map: None,
+      module_workspace_root: None,
+      module_node_modules_root: None,
+      module_index_files: None,
+      module_extensions: None,
+      module_commonjs: None,
+      module_text_assets: None,
+      module_binary_assets: None,
+      module_binary_asset_max_bytes: None,
+      module_strict_resolution: None,
+      module_allow_outside_root: None,
+      module_canonicalize_paths: None,
+      module_case_sensitivity: None,
+      module_transpile: None,
+      module_compile_cache: None,
+      module_wasm_cache: None,
+      module_hot_reload: None,
+      module_env_allowlist: None,
+      module_dev_server: None,
+      module_cassette: None,
+      module_lockfile: None,
+      module_guest_resolvers: None,
+      op_limits: None,
     }
   }
 }
@@ -0,0 +1,4 @@
+// not to be confused with module.exports = require("./main")
+import greeting from "./main.js";
+
+export default greeting;
@@ -0,0 +1,5 @@
+const { punctuate } = require("../strings");
+
+module.exports = function greet(name) {
+  return punctuate("hello " + name);
+};
@@ -0,0 +1,3 @@
+const greet = require("./lib/greet");
+
+module.exports = greet("fly");
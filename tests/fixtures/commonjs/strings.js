@@ -0,0 +1 @@
+exports.punctuate = (s) => s + "!";
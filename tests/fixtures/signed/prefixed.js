@@ -0,0 +1,2 @@
+// ed25519-signature: wiDsgOJusMEBayQdfIAoIf5V6xJ3RftVA1y7iz81kqg43lRqIF3e2opm4Cy7d4jfXgx0lHsDTAMeQHEbRpLoAQ==
+export const answer = 42;
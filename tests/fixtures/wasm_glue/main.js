@@ -0,0 +1,7 @@
+import { answer } from "./pkg/answer.js";
+import init, { answer as webAnswer } from "./web/answer.js";
+
+export default async function both() {
+  await init();
+  return [answer(), webAnswer()];
+}
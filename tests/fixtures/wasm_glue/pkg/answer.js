@@ -0,0 +1,5 @@
+// what wasm-bindgen --target bundler generates, trimmed
+import * as wasm from "./answer_bg.wasm";
+import { __wbg_set_wasm } from "./answer_bg.js";
+__wbg_set_wasm(wasm);
+export * from "./answer_bg.js";
@@ -0,0 +1,12 @@
+let wasm;
+export function __wbg_set_wasm(val) {
+  wasm = val;
+}
+
+export function answer() {
+  return wasm.answer();
+}
+
+export function __wbindgen_throw(ptr, len) {
+  throw new Error(`wasm threw ${len} bytes at ${ptr}`);
+}
@@ -0,0 +1,22 @@
+// what wasm-bindgen --target web generates, trimmed
+let wasm;
+
+export function answer() {
+  return wasm.answer();
+}
+
+export default async function init(input) {
+  if (input === undefined) {
+    input = new URL("answer_bg.wasm", import.meta.url);
+  }
+  const imports = {
+    wbg: {
+      __wbindgen_throw(ptr, len) {
+        throw new Error(`wasm threw ${len} bytes at ${ptr}`);
+      },
+    },
+  };
+  const { instance } = await WebAssembly.instantiate(await (await fetch(input)).arrayBuffer(), imports);
+  wasm = instance.exports;
+  return wasm;
+}
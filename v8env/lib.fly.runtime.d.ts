@@ -4,3 +4,10 @@
 /// <reference lib="es6" />
 /// <reference lib="dom.iterable" />
 /// <reference lib="scripthost" />
+
+interface ImportMeta {
+  /** The module's own url. */
+  url: string;
+  /** Whether the runtime was started from this module, rather than it being imported. */
+  main: boolean;
+}
@@ -60,6 +60,13 @@ function handleEvent(base: fbs.Base, raw: Uint8Array) {
   }
 }
 
+/**
+ * Handles every event of one type the runtime sends on its own, replacing any earlier handler.
+ */
+export function onEvent(type: fbs.Any, fn: (base: fbs.Base) => void) {
+  listenerTable.set(type, fn);
+}
+
 function handleBody(base: fbs.Base, raw: Uint8Array) {
   let msg = new fbs.StreamChunk();
   base.msg(msg);
@@ -86,11 +93,38 @@ export interface HttpRequestEvent extends RequestEvent<Response, FlyRequest> {
 
 export type EventListenerFunction<ET> = (event: ET) => void;
 
+/**
+ * Sent by the runtime when a module changed and the update stayed inside accepting modules.
+ * `reload` lists every module re-run, changed module first; their old state is gone.
+ */
+export interface HotUpdateEvent {
+  changed: string,
+  reload: string[],
+  boundaries: string[],
+}
+
+const hotUpdateListeners: Array<EventListenerFunction<HotUpdateEvent>> = [];
+
+export function dispatchHotUpdate(update: HotUpdateEvent) {
+  for (const fn of hotUpdateListeners) {
+    try {
+      fn.call(window, update);
+    } catch (e) {
+      console.log("error in hotupdate event handler function", e);
+    }
+  }
+}
+
 export function addEventListener(name: "fetch", fn: EventListenerFunction<HttpRequestEvent>);
 export function addEventListener(name: "resolve", fn: EventListenerFunction<DnsRequestEvent>);
-export function addEventListener(name: string, fn: EventListenerFunction<RequestEvent<any, any>>) {
+export function addEventListener(name: "hotupdate", fn: EventListenerFunction<HotUpdateEvent>);
+export function addEventListener(name: string, fn: EventListenerFunction<any>) {
   let event_type: fbs.EventType;
   switch (name) {
+    case "hotupdate":
+      // delivered by the runtime evaluating flyHotUpdate, so there's nothing to register
+      hotUpdateListeners.push(fn);
+      return;
     case "fetch":
       listenerTable.set(fbs.Any.HttpRequest, function (base: fbs.Base) {
         let msg = new fbs.HttpRequest();
@@ -8,6 +8,7 @@ export interface ConfigOptions {
 export interface DevTools {
   run(path: string): void;
   runTests(paths: string[]): void;
+  evict(originUrl: string): boolean;
 }
 
 export type initFn = (target: object, config: ConfigOptions) => DevTools;
\ No newline at end of file
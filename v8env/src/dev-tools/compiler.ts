@@ -2,6 +2,7 @@ import * as ts from "typescript"
 
 import { assert, assertNotNull, assertNotNullOrUndef, assertNotUndef } from "./util"
 import { fetchModule } from "./resolver";
+import { compileWasm, withOneDeadline } from "../module_loader";
 import { extname } from "./path";
 import { ContainerName } from "./assets";
 
@@ -66,6 +67,12 @@ export function fileNameToOriginUrl(fileName: string): string {
   return fileNameParts[1].replace(protocolPathPrefix, "") + "//" + fileNameParts[2].replace(hostPathPrefix, "") + "/" + fileNameParts.slice(3).join("/");
 }
 
+// Referers from anywhere else (eval'd snippets, virtual and generated modules) have no
+// source the runtime could read again, so theirs is sent along with their imports.
+function isReloadable(originUrl: string): boolean {
+  return /^(file|https?):/.test(originUrl);
+}
+
 class ModuleInfo implements ts.IScriptSnapshot {
   public inputCode: SourceCode = "";
   public outputCode?: OutputCode;
@@ -74,6 +81,18 @@ class ModuleInfo implements ts.IScriptSnapshot {
   public factory?: AmdFactory;
   public gatheringDeps = false;
   public deps?: ModuleId[];
+  public isWasm = false;
+  // a WebAssembly module's binary; it has no inputCode
+  public wasm?: Uint8Array;
+  // what V8 serialized compiling wasm before, when the runtime kept it
+  public compiledWasm?: Uint8Array;
+  // the source map its loader sent along, mapping inputCode back to where it came from
+  public sourceMap?: string;
+  // V8's identity hash for the module, when it was compiled natively
+  public identifierHash?: number;
+  // one of the runtime's entry modules; decided when it's first loaded and never changes
+  public isMain = false;
+  private importMeta?: { url: string, main: boolean };
 
   public constructor(
     public readonly originUrl: string,
@@ -90,6 +109,14 @@ class ModuleInfo implements ts.IScriptSnapshot {
     this.deps = undefined
     this.version += 1
     this.outputCode = undefined
+    this.importMeta = undefined
+  }
+
+  get meta(): { url: string, main: boolean } {
+    if (!this.importMeta) {
+      this.importMeta = { url: this.originUrl, main: this.isMain };
+    }
+    return this.importMeta;
   }
 
   getText(start: number, end: number): string {
@@ -128,6 +155,10 @@ class ModuleCache {
   public set(moduleInfo: ModuleInfo) {
     this.moduleIndex.set(moduleInfo.originUrl, moduleInfo);
   }
+
+  public delete(originUrl: string): boolean {
+    return this.moduleIndex.delete(originUrl);
+  }
   
   public has(originUrl: ModuleFileName): boolean {
     return this.moduleIndex.has(originUrl);
@@ -157,21 +188,28 @@ export class Compiler {
     this.globalEval = options.globalEval;
   }
 
-  public run(specifierUrl: ModuleSpecifier, containingFile?: ContainingFile) {
-    trace("run()", { specifierUrl, containingFile });
-    // Load entry point module and put it's file name in the scriptFileNames field as a new array
-    const moduleMetaData = this.resolveModule(specifierUrl, containingFile);
-    this.scriptFileNames = [moduleMetaData.fileName];
-    // If the module doesn't have any dependencies(hasn't been loaded before) instantiate it
-    if (!moduleMetaData.deps) {
-      this.instantiateModule(moduleMetaData);
-    }
-    // 
-    this.drainRunQueue();
-    return moduleMetaData;
+  /**
+   * Runs a module and everything it imports. `entry` is set for the modules the runtime is
+   * started from, never for dynamic imports, so only those see `import.meta.main`. Everything
+   * it loads shares one deadline; a dynamic import it runs into gets one of its own.
+   */
+  public run(specifierUrl: ModuleSpecifier, containingFile?: ContainingFile, entry = false) {
+    trace("run()", { specifierUrl, containingFile, entry });
+    return withOneDeadline(() => {
+      // Load entry point module and put it's file name in the scriptFileNames field as a new array
+      const moduleMetaData = this.resolveModule(specifierUrl, containingFile, entry);
+      this.scriptFileNames = [moduleMetaData.fileName];
+      // If the module doesn't have any dependencies(hasn't been loaded before) instantiate it
+      if (!moduleMetaData.deps) {
+        this.instantiateModule(moduleMetaData);
+      }
+      // 
+      this.drainRunQueue();
+      return moduleMetaData;
+    });
   }
 
-  public resolveModule(specifierUrl: string, refererOriginUrl?: string): ModuleInfo {
+  public resolveModule(specifierUrl: string, refererOriginUrl?: string, entry = false): ModuleInfo {
     trace("resolveModule()", { specifierUrl, refererOriginUrl })
     // attempt to load module from cache
     let fn = this.fileNameCache.get([specifierUrl, refererOriginUrl]);
@@ -179,7 +217,19 @@ export class Compiler {
       // return if found
       return this.moduleCache.get(fn);
     }
-    let { originUrl, loadedSource } = fetchModule(specifierUrl, refererOriginUrl);
+    const referer = refererOriginUrl && this.moduleCache.has(refererOriginUrl)
+      ? this.moduleCache.get(refererOriginUrl)
+      : undefined;
+    let { originUrl, loadedSource, passthrough, isMain } = fetchModule(
+      specifierUrl,
+      refererOriginUrl,
+      referer && {
+        isWasm: referer.isWasm,
+        identifierHash: referer.identifierHash,
+        source: isReloadable(referer.originUrl) ? undefined : referer.inputCode,
+      },
+      entry,
+    );
 
     // If module id is null or undef resolve failed.
     if (!originUrl) {
@@ -192,14 +242,35 @@ export class Compiler {
     }
 
     // Create new ModuleInfo object and fill it with info 
-    const moduleInfo = new ModuleInfo(originUrl, 0, mediaType(originUrl));
+    // Passthrough modules are already JavaScript, whatever their extension says
+    const moduleInfo = new ModuleInfo(originUrl, 0, passthrough ? MediaType.JavaScript : mediaType(originUrl));
     moduleInfo.inputCode = loadedSource.source;
+    moduleInfo.isWasm = loadedSource.isWasm;
+    moduleInfo.wasm = loadedSource.wasm;
+    moduleInfo.compiledWasm = loadedSource.compiledWasm;
+    moduleInfo.sourceMap = loadedSource.sourceMap;
+    moduleInfo.isMain = !!isMain;
     // Put module into cache for the next guy to pick it up
     this.moduleCache.set(moduleInfo);
     this.fileNameCache.set([specifierUrl, refererOriginUrl], originUrl);
     return moduleInfo;
   }
 
+  /**
+   * Forgets the module at `originUrl`, so the next import of it loads whatever the runtime
+   * serves now, e.g. once a virtual module was replaced or removed. Modules that imported
+   * it already keep what they were given.
+   */
+  public evict(originUrl: string): boolean {
+    trace("evict()", { originUrl });
+    this.fileNameCache.forEach((fn, specifier) => {
+      if (fn === originUrl) {
+        this.fileNameCache.delete(specifier);
+      }
+    });
+    return this.moduleCache.delete(originUrl);
+  }
+
   getModuleInfo(originUrl: string): ModuleInfo {
     if (this.moduleCache.has(originUrl)) {
       return this.moduleCache.get(originUrl);
@@ -232,7 +303,7 @@ export class Compiler {
       ...this.languageService.getCompilerOptionsDiagnostics(),
       ...this.languageService.getSyntacticDiagnostics(fileName),
       ...this.languageService.getSemanticDiagnostics(fileName)
-    ];
+    ].filter(d => d.code !== importMetaNeedsEsModules);
     // If the language service reports log error and throw.
     if (diagnostics.length > 0) {
       const errMsg = ts.formatDiagnosticsWithColorAndContext(diagnostics, diagnosticHost);
@@ -310,6 +381,11 @@ export class Compiler {
       return;
     }
 
+    if (moduleInfo.isWasm) {
+      this.instantiateWasm(moduleInfo);
+      return;
+    }
+
     /**
      * I assume the global part has some use but it may be not longer be needed
      */
@@ -318,6 +394,26 @@ export class Compiler {
     this.global.define = undefined;
   }
 
+  /**
+   * Queues a WebAssembly module the way `define` queues a compiled one: the modules it
+   * imports from are its dependencies, and its factory instantiates it with their exports.
+   */
+  instantiateWasm(moduleInfo: ModuleInfo): void {
+    const { WebAssembly } = this.global;
+    const wasmModule = compileWasm(moduleInfo.originUrl, moduleInfo.wasm!, moduleInfo.compiledWasm);
+    // only needed the once
+    moduleInfo.compiledWasm = undefined;
+    const importModules: string[] = Array.from(new Set(
+      WebAssembly.Module.imports(wasmModule).map(i => i.module)
+    ));
+    this.makeDefine(moduleInfo)(importModules, (...importExports: any[]) => {
+      const importObject = {};
+      importModules.forEach((name, i) => importObject[name] = importExports[i]);
+      const instance = new WebAssembly.Instance(wasmModule, importObject);
+      Object.assign(moduleInfo.exports, instance.exports);
+    });
+  }
+
   /**
    * Retrieve the arguments to pass a module's factory function.
    */
@@ -384,7 +480,7 @@ export class Compiler {
    * emit of a dynamic ES `import()` from TypeScript.
    */
   makeLocalRequire(moduleInfo: ModuleInfo): AMDRequire {
-    return (
+    const localRequire = (
       deps: ModuleSpecifier[],
       callback: AmdCallback,
       errback: AmdErrback
@@ -402,9 +498,29 @@ export class Compiler {
         errback(e);
       }
     };
+    // what the module's `import.meta` was compiled to, see importMetaTransformer
+    return Object.assign(localRequire, { meta: moduleInfo.meta });
   }
 }
 
+// "The 'import.meta' meta-property is only allowed when the '--module' option is 'esnext' or
+// 'system'", which importMetaTransformer takes care of
+const importMetaNeedsEsModules = 1343;
+
+/**
+ * AMD modules have no `import.meta`, so it's rewritten to the `meta` of the module's local
+ * `require`, which every AMD factory gets as its first argument.
+ */
+function importMetaTransformer(context: ts.TransformationContext) {
+  const visit = (node: ts.Node): ts.Node => {
+    if (ts.isMetaProperty(node) && node.keywordToken === ts.SyntaxKind.ImportKeyword) {
+      return ts.createPropertyAccess(ts.createIdentifier("require"), "meta");
+    }
+    return ts.visitEachChild(node, visit, context);
+  };
+  return (sourceFile: ts.SourceFile) => ts.visitNode(sourceFile, visit);
+}
+
 const settings: ts.CompilerOptions = {
   allowJs: true,
   module: ts.ModuleKind.AMD,
@@ -422,6 +538,9 @@ function createLanguageService(compiler: Compiler): ts.LanguageService {
     getCompilationSettings(): ts.CompilerOptions {
       return settings;
     },
+    getCustomTransformers(): ts.CustomTransformers {
+      return { before: [importMetaTransformer] };
+    },
     getScriptFileNames(): string[] {
       return compiler.scriptFileNames;
     },
@@ -465,6 +584,10 @@ function createLanguageService(compiler: Compiler): ts.LanguageService {
         const moduleInfo = compiler.resolveModule(moduleName, fileNameToOriginUrl(containingFile))
         // an empty string will cause typescript to bomb, maybe fail here instead?
         const resolvedFileName = moduleInfo && moduleInfo.fileName || ""
+        if (moduleInfo && moduleInfo.isWasm) {
+          // nothing for typescript to read; its exports are untyped
+          return { resolvedFileName, extension: ts.Extension.Js, isExternalLibraryImport: true }
+        }
         const isExternalLibraryImport = false; // need cwd/cjs logic for this maybe?
         return { resolvedFileName, isExternalLibraryImport }
       })
@@ -511,7 +634,8 @@ function mediaType(originUrl): MediaType {
   switch (extname(originUrl)) {
     case ".ts": return MediaType.TypeScript;
     case ".js": return MediaType.JavaScript;
-    case ".json": return MediaType.Json;
+    // the host turns JSON files into modules exporting their data
+    case ".json": return MediaType.JavaScript;
   }
   return MediaType.Unknown;
 }
@@ -14,14 +14,14 @@ class FlyDevTools implements DevTools {
   }
 
   run(path: string) {
-    this.compiler.run(path);
+    this.compiler.run(path, undefined, true);
   }
 
   runTests(paths: string[]) {
     for (const suitePath of paths) {
       loadSuite(suitePath);
       try {
-        this.compiler.run(suitePath);
+        this.compiler.run(suitePath, undefined, true);
       } catch (err) {
         printSuiteError(suitePath, err);
         exit(1);
@@ -29,6 +29,10 @@ class FlyDevTools implements DevTools {
     }
     run()
   }
+
+  evict(originUrl: string): boolean {
+    return this.compiler.evict(originUrl);
+  }
 }
 
 /**
@@ -1,9 +1,10 @@
 import { assetSourceCode, ContainerName, AssetsProtocol } from "./assets";
 import { assert } from "./util";
-import { loadModule, LoadedModule } from "../module_loader";
+import { loadModule, LoadedModule, RefererDetails } from "../module_loader";
 import { URL } from "../url";
+import { ModuleKind } from "../msg_generated";
 
-export function fetchModule(specifierUrl: string, refererOriginUrl?: string): LoadedModule {  
+export function fetchModule(specifierUrl: string, refererOriginUrl?: string, referer?: RefererDetails, entry = false, assertedType?: string): LoadedModule {  
   console.trace("[resolver] fetchModule()", { specifierUrl, refererOriginUrl });
   // If module is a "asset" I.E. lib.dom.d.ts
   if (isAsset(specifierUrl, refererOriginUrl)) {
@@ -25,6 +26,7 @@ export function fetchModule(specifierUrl: string, refererOriginUrl?: string): Lo
       originUrl: parsedUrl.toString(),
       loadedSource: {
         isWasm: false,
+        kind: ModuleKind.TypeScript,
         source: assetSourceCode[assetName],
       },
     };
@@ -32,7 +34,7 @@ export function fetchModule(specifierUrl: string, refererOriginUrl?: string): Lo
 
   console.trace(`Finished module fetch ${specifierUrl} from ${refererOriginUrl}`);
   // Use std loadModule function to load module
-  return loadModule(specifierUrl, refererOriginUrl);
+  return loadModule(specifierUrl, refererOriginUrl, referer, entry, assertedType);
 }
 
 function isAsset(specifierUrl: string, refererOriginUrl: string): boolean {
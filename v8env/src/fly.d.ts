@@ -68,4 +68,63 @@ export declare module fly {
    * An image manipulation library. Useful for resizing and optimizing images.
    */
   //export const Image: img.Image
+
+  /**
+   * Checks whether a module is already cached, without importing it. Use it to skip a heavy
+   * optional import when it would have to be fetched first.
+   * @param specifier The specifier you would import
+   * @param referer The url to resolve it from, the app root by default
+   * @returns where it's cached ("memory" or "disk"), its size and whether it's "fresh" or "stale"
+   */
+  export function moduleCached(specifier: string, referer?: string): {
+    originUrl: string, cached: boolean, location?: "memory" | "disk", size: number,
+    freshness?: "fresh" | "stale"
+  }
+
+  /**
+   * Finds where a module would be imported from, without importing or even loading it, e.g.
+   * to check every import of a bundle resolves before deploying it. Needs dev tools.
+   * @param specifier The specifier you would import
+   * @param referer The url to resolve it from, the app root by default
+   * @returns its origin url and protocol, the kind of resolver that claimed it, and whether
+   *   it's wasm when its extension tells
+   */
+  export function resolveModule(specifier: string, referer?: string): {
+    originUrl: string, protocol: string, resolver: string, isWasm?: boolean
+  }
+
+  /**
+   * Evicts a module the runtime compiled from the module caches, so its next import reads
+   * it afresh. For dev tooling reloading files as they change; needs dev tools.
+   * @param originUrl The module's origin url; every module the runtime compiled when left out
+   * @returns how many cached entries were evicted
+   */
+  export function clearModuleCache(originUrl?: string): number
+
+  /**
+   * Lists every module loaded so far and the imports between them, e.g. to find out what
+   * makes a bundle big. Needs dev tools.
+   * @returns each module's origin url, size in bytes, whether it's wasm and the module that
+   *   first imported it, and every import as an importer and dependency pair
+   */
+  export function loadedModules(): {
+    modules: { originUrl: string, size: number, isWasm: boolean, referer?: string }[],
+    imports: { importer: string, dependency: string }[]
+  }
+
+  /**
+   * Serves imports of `protocol:` urls from your own code, e.g. modules kept in `fly.data`
+   * or a manifest mapping names onto urls. Tried after the runtime's own resolvers, and
+   * `file`, `http`, `https`, `fly` and `data` can't be claimed. Only where the host enabled
+   * guest resolvers.
+   * @param protocol The url scheme to serve, without the colon
+   * @param fn Gets the full url and the importing module's url. Returns the module's source,
+   *   `{ source, originUrl }` to have it known by another url of the same protocol,
+   *   `{ redirect }` with a specifier to import instead, or nothing if it can't resolve it.
+   *   It must settle without waiting on I/O: the import holds the runtime until it does.
+   *   It may import modules itself, through this resolver too.
+   */
+  export function registerResolver(protocol: string, fn: (specifier: string, referer?: string) =>
+    string | { source: string, originUrl?: string } | { redirect: string } | null | undefined |
+    Promise<string | { source: string, originUrl?: string } | { redirect: string } | null | undefined>): void
 }
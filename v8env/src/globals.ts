@@ -20,11 +20,12 @@ import * as flyData from './fly/data';
 import * as flyCache from './fly/cache';
 import * as flyResponseCache from './fly/response';
 import flyHttp from './fly/http'
-import { loadModule } from "./module_loader";
+import { clearModuleCache, loadedModules, loadModule, moduleCached, registerResolver, resolveModule } from "./module_loader";
 import { installDevTools } from "./dev-tools";
 import * as streams from "./streams";
 import { AppRelease } from "./app";
 import { runtime, Runtime } from "./runtime";
+import * as runtimeMetrics from "./runtime_metrics";
 import { Logger } from "./logging";
 
 import * as domTypes from './dom_types';
@@ -48,6 +49,7 @@ declare global {
   const window: Window;
 
   const addEventListener: typeof bridge.addEventListener;
+  const flyHotUpdate: typeof bridge.dispatchHotUpdate;
 
   const Response: typeof FlyResponse;
   const Request: typeof FlyRequest;
@@ -72,7 +74,15 @@ declare global {
     http: typeof flyHttp
     Image: typeof Image
     app: AppRelease;
-    runtime: Runtime;
+    runtime: Runtime & {
+      metrics: typeof runtimeMetrics.metrics;
+      pendingOps: typeof runtimeMetrics.pendingOps;
+    };
+    moduleCached: typeof moduleCached;
+    resolveModule: typeof resolveModule;
+    clearModuleCache: typeof clearModuleCache;
+    loadedModules: typeof loadedModules;
+    registerResolver: typeof registerResolver;
   }
   // TODO: remove
   const fly: Fly
@@ -104,6 +114,7 @@ window.Response = FlyResponse;
 window.Request = FlyRequest;
 
 window.addEventListener = bridge.addEventListener;
+window.flyHotUpdate = bridge.dispatchHotUpdate;
 
 const logger = new Logger(libfly.print);
 window.logger = logger;
@@ -131,7 +142,12 @@ window.fly = {
   data: flyData,
   http: flyHttp,
   Image: Image,
-  runtime,
+  runtime: { ...runtime, metrics: runtimeMetrics.metrics, pendingOps: runtimeMetrics.pendingOps },
+  moduleCached,
+  resolveModule,
+  clearModuleCache,
+  loadedModules,
+  registerResolver,
 }
 
 window.DNSRequest = dns.DNSRequest;
@@ -15,6 +15,9 @@ interface Libfly {
     ) => void
   ) => void;
   getNextStreamId(): number;
+  // compiles wasm, or deserializes what compiling it serialized before; hands back what it
+  // serializes when it compiled
+  compileWasm(wasm: Uint8Array, compiled?: Uint8Array): [object, Uint8Array | undefined];
 }
 
 const window = globalEval("this");
@@ -1,70 +1,460 @@
-import { sendSync } from './bridge';
+import { sendAsync, sendSync, onEvent } from './bridge';
+import { FlyError } from "./errors";
 import * as fbs from "./msg_generated";
 import * as flatbuffers from "./flatbuffers"
+import { libfly } from "./libfly";
 
 export interface LoadedSourceCode {
   isWasm: boolean;
+  // what the module was before its loader made it loadable; everything but Wasm arrives as JavaScript
+  kind: fbs.ModuleKind;
   sourceMap?: string;
   source: string;
+  // the binary of a WebAssembly module, whose source is empty
+  wasm?: Uint8Array;
+  // what V8 serialized compiling wasm before, when the runtime keeps compiled wasm
+  compiledWasm?: Uint8Array;
 }
 
 export interface LoadedModule {
   originUrl: string;
   loadedSource: LoadedSourceCode;
+  // Already transformed upstream (e.g. by a dev server), so it runs as plain JavaScript.
+  passthrough?: boolean;
+  // One of the runtime's entry modules, see `import.meta.main`.
+  isMain?: boolean;
 }
 
-export function loadModule(specifierUrl: string, refererOriginUrl?: string): LoadedModule {
+/**
+ * What the module registry knows about the importing module.
+ */
+export interface RefererDetails {
+  isWasm: boolean;
+  identifierHash?: number;
+  // The referer's source, when it has no file to re-read (eval'd or generated code).
+  source?: string;
+  // 1-based position of the import in the referer, for error messages.
+  line?: number;
+  column?: number;
+}
+
+// Keep in sync with MAX_REFERER_SOURCE_BYTES; the runtime caps it again either way.
+const maxRefererSourceLength = 256 * 1024;
+
+// The top-level load the imports being loaded are part of, by the id the runtime gave it; 0
+// until the runtime answered the first of them, undefined outside of one.
+let deadline: number | undefined;
+
+/**
+ * Runs `load`, a top-level load such as `dev.run` or a dynamic import, with every module it
+ * loads along the way, however deep, under one deadline: the manager's `total_deadline`.
+ */
+export function withOneDeadline<T>(load: () => T): T {
+  const outer = deadline;
+  deadline = 0;
+  try {
+    return load();
+  } finally {
+    deadline = outer;
+  }
+}
+
+/**
+ * Loads `specifierUrl` as imported from `refererOriginUrl`, or as one of the runtime's
+ * entry modules when `entry` is set. `assertedType` is the type the import asserted, as in
+ * `with { type: "json" }`; a module of another type throws a `ModuleTypeMismatch` FlyError.
+ */
+export function loadModule(specifierUrl: string, refererOriginUrl?: string, referer?: RefererDetails, entry = false, assertedType?: string): LoadedModule {
   if (refererOriginUrl) {
-    return loadModuleStandard(specifierUrl, refererOriginUrl);
+    return loadModuleStandard(specifierUrl, refererOriginUrl, referer, entry, assertedType);
   } else {
-    return loadModuleWithoutReferer(specifierUrl);
+    return loadModuleWithoutReferer(specifierUrl, entry, assertedType);
   }
 }
 
-function loadModuleWithoutReferer(specifierUrl: string): LoadedModule {
+function loadModuleWithoutReferer(specifierUrl: string, entry: boolean, assertedType?: string): LoadedModule {
   // Allocate new message and fill it with data
   const fbb = flatbuffers.createBuilder();
   const fbSpcecifierUrl = fbb.createString(specifierUrl);
+  const fbAssertedType = assertedType !== undefined ? fbb.createString(assertedType) : undefined;
   // Fill message handle with data
   fbs.LoadModule.startLoadModule(fbb);
   fbs.LoadModule.addSpecifierUrl(fbb, fbSpcecifierUrl);
+  fbs.LoadModule.addEntry(fbb, entry);
+  if (fbAssertedType !== undefined) {
+    fbs.LoadModule.addAssertedType(fbb, fbAssertedType);
+  }
+  fbs.LoadModule.addDeadline(fbb, deadline || 0);
   // Send flatbuffer messaage and collect response
-  const resp = sendSync(fbb, fbs.Any.LoadModule, fbs.LoadModule.endLoadModule(fbb));
+  const resp = sendLoadModule(fbb, fbs.LoadModule.endLoadModule(fbb));
+  // Transform data into local format and return.
+  return loadedModule(resp);
+}
+
+/**
+ * Sends a `LoadModule` message. An invalid specifier throws a TypeError, as it does for any
+ * import; other failures throw the FlyError their kind names, e.g. `ModuleNotFound`.
+ */
+function sendLoadModule(fbb: flatbuffers.Builder, msg: flatbuffers.Offset): fbs.Base {
+  try {
+    const resp = sendSync(fbb, fbs.Any.LoadModule, msg)!;
+    if (deadline === 0) {
+      // the first load of a top-level load, whose imports go on under its deadline
+      deadline = respDeadline(resp);
+    }
+    return resp;
+  } catch (e) {
+    if (e instanceof FlyError && e.kind === fbs.ErrorKind.InvalidSpecifier) {
+      throw new TypeError(e.message);
+    }
+    throw e;
+  }
+}
+
+function respDeadline(resp: fbs.Base): number {
+  if (resp.msgType() === fbs.Any.LoadModuleStartResp) {
+    const start = new fbs.LoadModuleStartResp();
+    resp.msg(start);
+    return start.deadline();
+  }
+  const msg = new fbs.LoadModuleResp();
+  resp.msg(msg);
+  return msg.deadline();
+}
+
+function loadedModule(resp: fbs.Base): LoadedModule {
+  if (resp.msgType() === fbs.Any.LoadModuleStartResp) {
+    const start = new fbs.LoadModuleStartResp();
+    resp.msg(start);
+    return {
+      originUrl: start.originUrl(),
+      loadedSource: chunkedSource(start),
+      passthrough: start.passthrough(),
+      isMain: start.isMain(),
+    };
+  }
   // Allocate new LoadModuleResp handle
   const msg = new fbs.LoadModuleResp();
   // Write message data to handle
   resp.msg(msg);
-  // Transform data into local format and return.
   return {
     originUrl: msg.originUrl(),
-    loadedSource: {
-      isWasm: false,
-      source: msg.sourceCode(),
-    },
+    loadedSource: loadedSource(msg),
+    passthrough: msg.passthrough(),
+    isMain: msg.isMain(),
   };
 }
 
-function loadModuleStandard(specifierUrl: string, refererOriginUrl: string): LoadedModule {
+/**
+ * Fetches the chunks of a source too big for one message and puts them back together, so
+ * it's evaluated as if it came whole.
+ */
+function chunkedSource(start: fbs.LoadModuleStartResp): LoadedSourceCode {
+  const bytes = new Uint8Array(start.size());
+  let offset = 0;
+  for (let index = 0; index < start.chunks(); index++) {
+    const fbb = flatbuffers.createBuilder();
+    fbs.LoadModuleChunk.startLoadModuleChunk(fbb);
+    fbs.LoadModuleChunk.addId(fbb, start.id());
+    fbs.LoadModuleChunk.addIndex(fbb, index);
+    const resp = sendSync(fbb, fbs.Any.LoadModuleChunk, fbs.LoadModuleChunk.endLoadModuleChunk(fbb));
+    const chunk = new fbs.LoadModuleChunkResp();
+    resp.msg(chunk);
+    const data = chunk.dataArray();
+    bytes.set(data, offset);
+    offset += data.length;
+  }
+  const sourceMap = start.sourceMap() || undefined;
+  const kind = start.kind();
+  if (start.isWasm()) {
+    // the compiled wasm, if any, comes after the binary
+    const wasmSize = bytes.length - start.compiledWasmSize();
+    const compiledWasm = start.compiledWasmSize() > 0 ? bytes.subarray(wasmSize) : undefined;
+    return { isWasm: true, kind, source: "", wasm: bytes.subarray(0, wasmSize), compiledWasm, sourceMap };
+  }
+  return { isWasm: false, kind, source: new TextDecoder("utf-8").decode(bytes), sourceMap };
+}
+
+function loadedSource(msg: fbs.LoadModuleResp): LoadedSourceCode {
+  // null from runtimes that don't send source maps
+  const sourceMap = msg.sourceMap() || undefined;
+  // JavaScript from runtimes that don't send kinds
+  const kind = msg.kind();
+  if (msg.isWasm()) {
+    // copied out, the response buffer gets reused
+    const compiled = msg.compiledWasmArray();
+    const compiledWasm = compiled ? compiled.slice() : undefined;
+    return { isWasm: true, kind, source: "", wasm: msg.wasmArray().slice(), compiledWasm, sourceMap };
+  }
+  return { isWasm: false, kind, source: msg.sourceCode(), sourceMap };
+}
+
+function loadModuleStandard(specifierUrl: string, refererOriginUrl: string, referer: RefererDetails | undefined, entry: boolean, assertedType?: string): LoadedModule {
   // Allocate new message handle
   const fbb = flatbuffers.createBuilder();
   const fbSpcecifierUrl = fbb.createString(specifierUrl);
   const fbRefererOriginUrl = fbb.createString(refererOriginUrl);
+  const fbRefererSource = referer && referer.source !== undefined
+    ? fbb.createString(referer.source.slice(0, maxRefererSourceLength + 1))
+    : undefined;
+  const fbAssertedType = assertedType !== undefined ? fbb.createString(assertedType) : undefined;
   // Fill message handle with data
   fbs.LoadModule.startLoadModule(fbb);
   fbs.LoadModule.addSpecifierUrl(fbb, fbSpcecifierUrl);
   fbs.LoadModule.addRefererOriginUrl(fbb, fbRefererOriginUrl);
+  fbs.LoadModule.addEntry(fbb, entry);
+  if (fbAssertedType !== undefined) {
+    fbs.LoadModule.addAssertedType(fbb, fbAssertedType);
+  }
+  fbs.LoadModule.addDeadline(fbb, deadline || 0);
+  if (referer) {
+    fbs.LoadModule.addRefererIsWasm(fbb, referer.isWasm);
+    if (referer.identifierHash) {
+      fbs.LoadModule.addRefererIdentifierHash(fbb, referer.identifierHash);
+    }
+    if (fbRefererSource !== undefined) {
+      fbs.LoadModule.addRefererSource(fbb, fbRefererSource);
+    }
+    if (referer.line) {
+      fbs.LoadModule.addRefererLine(fbb, referer.line);
+      fbs.LoadModule.addRefererColumn(fbb, referer.column || 0);
+    }
+  }
   // Send flatbuffer messaage and collect response
-  const resp = sendSync(fbb, fbs.Any.LoadModule, fbs.LoadModule.endLoadModule(fbb));
-  // Allocate new LoadModuleResp handle
-  const msg = new fbs.LoadModuleResp();
-  // Write message data to handle
-  resp.msg(msg);
+  const resp = sendLoadModule(fbb, fbs.LoadModule.endLoadModule(fbb));
   // Return data from handle
+  return loadedModule(resp);
+}
+
+/**
+ * Whether the module a specifier resolves to is already cached, so it can be imported
+ * without going to its source.
+ */
+export interface ModuleCacheState {
+  originUrl: string;
+  cached: boolean;
+  location?: "memory" | "disk";
+  // bytes of source held, 0 when not cached
+  size: number;
+  // a stale module is cached but would be refetched before importing it
+  freshness?: "fresh" | "stale";
+}
+
+/**
+ * Checks whether importing `specifierUrl` (from `refererOriginUrl`, or the app root) would be
+ * served from a cache. Only resolves the specifier; never loads or fetches it.
+ */
+export function moduleCached(specifierUrl: string, refererOriginUrl?: string): ModuleCacheState {
+  const fbb = flatbuffers.createBuilder();
+  const fbSpecifierUrl = fbb.createString(specifierUrl);
+  const fbRefererOriginUrl = refererOriginUrl ? fbb.createString(refererOriginUrl) : undefined;
+  fbs.ModuleCached.startModuleCached(fbb);
+  fbs.ModuleCached.addSpecifierUrl(fbb, fbSpecifierUrl);
+  if (fbRefererOriginUrl !== undefined) {
+    fbs.ModuleCached.addRefererOriginUrl(fbb, fbRefererOriginUrl);
+  }
+  const resp = sendSync(fbb, fbs.Any.ModuleCached, fbs.ModuleCached.endModuleCached(fbb));
+  const msg = new fbs.ModuleCachedResp();
+  resp.msg(msg);
+  const location = msg.location() as "memory" | "disk" | null;
+  const freshness = msg.freshness() as "fresh" | "stale" | null;
+  return {
+    originUrl: msg.originUrl(),
+    cached: location !== null,
+    location: location || undefined,
+    size: msg.size(),
+    freshness: freshness || undefined,
+  };
+}
+
+/**
+ * Where importing a specifier would load a module from, found without loading it.
+ */
+export interface ResolvedModule {
+  originUrl: string;
+  protocol: string;
+  // the kind of resolver that claimed it, e.g. "local_disk" or "http"
+  resolver: string;
+  // absent when the origin url's extension doesn't tell
+  isWasm?: boolean;
+}
+
+/**
+ * Resolves `specifier` (from `refererOriginUrl`, or the app root) as importing it would,
+ * without loading, fetching or transpiling its source. Throws if it wouldn't resolve.
+ */
+export function resolveModule(specifier: string, refererOriginUrl?: string): ResolvedModule {
+  const fbb = flatbuffers.createBuilder();
+  const fbSpecifier = fbb.createString(specifier);
+  const fbRefererOriginUrl = refererOriginUrl ? fbb.createString(refererOriginUrl) : undefined;
+  fbs.ResolveModule.startResolveModule(fbb);
+  fbs.ResolveModule.addSpecifier(fbb, fbSpecifier);
+  if (fbRefererOriginUrl !== undefined) {
+    fbs.ResolveModule.addRefererOriginUrl(fbb, fbRefererOriginUrl);
+  }
+  const resp = sendSync(fbb, fbs.Any.ResolveModule, fbs.ResolveModule.endResolveModule(fbb));
+  const msg = new fbs.ResolveModuleResp();
+  resp.msg(msg);
+  const format = msg.format();
   return {
     originUrl: msg.originUrl(),
-    loadedSource: {
-      isWasm: false,
-      source: msg.sourceCode(),
-    },
+    protocol: msg.protocol(),
+    resolver: msg.resolver(),
+    isWasm: format === null ? undefined : format === "wasm",
   };
-}
\ No newline at end of file
+}
+
+/**
+ * Evicts the module at `originUrl`, or every module, the runtime compiled from the module
+ * caches, so the next load of it reads it afresh, e.g. once a watcher saw its file change.
+ * Returns how many cached entries were evicted.
+ */
+export function clearModuleCache(originUrl?: string): number {
+  const fbb = flatbuffers.createBuilder();
+  const fbOriginUrl = originUrl !== undefined ? fbb.createString(originUrl) : undefined;
+  fbs.ClearModuleCache.startClearModuleCache(fbb);
+  if (fbOriginUrl !== undefined) {
+    fbs.ClearModuleCache.addOriginUrl(fbb, fbOriginUrl);
+  }
+  const resp = sendSync(fbb, fbs.Any.ClearModuleCache, fbs.ClearModuleCache.endClearModuleCache(fbb));
+  const msg = new fbs.ClearModuleCacheResp();
+  resp.msg(msg);
+  return msg.evicted();
+}
+
+/**
+ * Compiles the wasm module at `originUrl` from `compiledWasm`, what V8 serialized compiling
+ * it before, when the runtime has that; otherwise compiles it and hands the runtime what V8
+ * serializes, for the next boot.
+ */
+export function compileWasm(originUrl: string, wasm: Uint8Array, compiledWasm?: Uint8Array): object {
+  const [wasmModule, compiled] = libfly.compileWasm(wasm, compiledWasm);
+  if (compiled !== undefined) {
+    storeCompiledWasm(originUrl, compiled);
+  }
+  return wasmModule;
+}
+
+/**
+ * Hands back what V8 serialized compiling the wasm module at `originUrl`, for runtimes
+ * booting later to instantiate it without compiling. Failing to keep it only costs them the
+ * compile, so it's logged rather than thrown.
+ */
+export function storeCompiledWasm(originUrl: string, compiled: Uint8Array): Promise<void> {
+  const fbb = flatbuffers.createBuilder();
+  const fbOriginUrl = fbb.createString(originUrl);
+  fbs.StoreCompiledWasm.startStoreCompiledWasm(fbb);
+  fbs.StoreCompiledWasm.addOriginUrl(fbb, fbOriginUrl);
+  return sendAsync(fbb, fbs.Any.StoreCompiledWasm, fbs.StoreCompiledWasm.endStoreCompiledWasm(fbb), compiled)
+    .then(() => undefined, e => console.warn(`not keeping the compiled wasm of ${originUrl}: ${e}`));
+}
+
+export interface LoadedModuleEntry {
+  originUrl: string;
+  // bytes of source, a wasm module's base64
+  size: number;
+  isWasm: boolean;
+  // the module that first imported it, absent for entry modules
+  referer?: string;
+}
+
+/**
+ * Every module the runtime has loaded so far, and which imports which.
+ */
+export function loadedModules(): { modules: LoadedModuleEntry[], imports: { importer: string, dependency: string }[] } {
+  const fbb = flatbuffers.createBuilder();
+  fbs.ListLoadedModules.startListLoadedModules(fbb);
+  const resp = sendSync(fbb, fbs.Any.ListLoadedModules, fbs.ListLoadedModules.endListLoadedModules(fbb));
+  const msg = new fbs.ListLoadedModulesResp();
+  resp.msg(msg);
+  const modules: LoadedModuleEntry[] = [];
+  for (let i = 0; i < msg.modulesLength(); i++) {
+    const module = msg.modules(i)!;
+    modules.push({
+      originUrl: module.originUrl(),
+      size: module.size(),
+      isWasm: module.isWasm(),
+      referer: module.referer() || undefined,
+    });
+  }
+  const imports: { importer: string, dependency: string }[] = [];
+  for (let i = 0; i < msg.importsLength(); i++) {
+    const edge = msg.imports(i)!;
+    imports.push({ importer: edge.importer(), dependency: edge.dependency() });
+  }
+  return { modules, imports };
+}
+
+/**
+ * What a guest resolver answers with: module source, optionally with the url it's known by,
+ * a specifier to resolve instead, or nothing if it doesn't know the module.
+ */
+export type GuestResolution = string | { source: string, originUrl?: string } | { redirect: string } |
+  null | undefined;
+
+export type GuestResolverFn = (specifier: string, referer?: string) =>
+  GuestResolution | Promise<GuestResolution>;
+
+const guestResolvers = new Map<string, GuestResolverFn>();
+
+/**
+ * Serves imports of `protocol:` urls from `fn`. The runtime's own resolvers are tried first,
+ * and it has to be answered without waiting on I/O: the import holds the runtime until `fn`
+ * settles, or times out. `fn` may import modules itself; those resolve, and answer, before
+ * its own import does.
+ */
+export function registerResolver(protocol: string, fn: GuestResolverFn) {
+  const fbb = flatbuffers.createBuilder();
+  const fbProtocol = fbb.createString(protocol);
+  fbs.RegisterModuleResolver.startRegisterModuleResolver(fbb);
+  fbs.RegisterModuleResolver.addProtocol(fbb, fbProtocol);
+  sendSync(fbb, fbs.Any.RegisterModuleResolver, fbs.RegisterModuleResolver.endRegisterModuleResolver(fbb));
+  if (guestResolvers.size === 0) {
+    onEvent(fbs.Any.GuestResolve, handleGuestResolve);
+  }
+  guestResolvers.set(protocol, fn);
+}
+
+function handleGuestResolve(base: fbs.Base) {
+  const msg = new fbs.GuestResolve();
+  base.msg(msg);
+  const id = msg.id();
+  const fn = guestResolvers.get(msg.protocol());
+  if (!fn) {
+    return sendGuestResolveResult(id, { error: "no resolver for " + msg.protocol() });
+  }
+  try {
+    Promise.resolve(fn(msg.specifier(), msg.referer() || undefined)).then(
+      res => sendGuestResolveResult(id, typeof res === "string" ? { source: res } : res || {}),
+      err => sendGuestResolveResult(id, { error: String(err) }),
+    );
+  } catch (err) {
+    sendGuestResolveResult(id, { error: String(err) });
+  }
+}
+
+function sendGuestResolveResult(id: number,
+                                res: { source?: string, originUrl?: string, redirect?: string, error?: string }) {
+  const fbb = flatbuffers.createBuilder();
+  const fbSource = res.source !== undefined ? fbb.createString(res.source) : undefined;
+  const fbOriginUrl = res.source !== undefined && res.originUrl !== undefined ?
+    fbb.createString(res.originUrl) : undefined;
+  const fbRedirect = res.redirect !== undefined ? fbb.createString(res.redirect) : undefined;
+  const fbError = res.source === undefined && res.redirect === undefined ?
+    fbb.createString(res.error || "not found") : undefined;
+  fbs.GuestResolveResult.startGuestResolveResult(fbb);
+  fbs.GuestResolveResult.addId(fbb, id);
+  if (fbSource !== undefined) {
+    fbs.GuestResolveResult.addSource(fbb, fbSource);
+  }
+  if (fbOriginUrl !== undefined) {
+    fbs.GuestResolveResult.addOriginUrl(fbb, fbOriginUrl);
+  }
+  if (fbRedirect !== undefined) {
+    fbs.GuestResolveResult.addRedirect(fbb, fbRedirect);
+  }
+  if (fbError !== undefined) {
+    fbs.GuestResolveResult.addError(fbb, fbError);
+  }
+  sendSync(fbb, fbs.Any.GuestResolveResult, fbs.GuestResolveResult.endGuestResolveResult(fbb));
+}
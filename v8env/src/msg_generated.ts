@@ -167,7 +167,24 @@ export enum Any{
   ImageReady= 39,
   AcmeGetChallenge= 40,
   AcmeGetChallengeReady= 41,
-  OsExit= 42
+  OsExit= 42,
+  ModuleCached= 43,
+  ModuleCachedResp= 44,
+  RuntimeMetrics= 45,
+  RuntimeMetricsReady= 46,
+  RegisterModuleResolver= 47,
+  GuestResolve= 48,
+  GuestResolveResult= 49,
+  LoadModuleStartResp= 50,
+  LoadModuleChunk= 51,
+  LoadModuleChunkResp= 52,
+  ClearModuleCache= 53,
+  ClearModuleCacheResp= 54,
+  ListLoadedModules= 55,
+  ListLoadedModulesResp= 56,
+  ResolveModule= 57,
+  ResolveModuleResp= 58,
+  StoreCompiledWasm= 59
 };
 
 /**
@@ -208,7 +225,23 @@ export enum ErrorKind{
   HttpClosed= 31,
   HttpCanceled= 32,
   HttpParse= 33,
-  HttpOther= 34
+  HttpOther= 34,
+  Backpressure= 35,
+  IntegrityMismatch= 36,
+  PolicyDenied= 37,
+  ModuleNotFound= 38,
+  NoResolverForProtocol= 39,
+  ModuleLoadFailed= 40,
+  InvalidSpecifier= 41,
+  RateLimited= 42,
+  RedirectLoop= 43,
+  TooManyRedirects= 44,
+  UpstreamError= 45,
+  ModuleLimitExceeded= 46,
+  IsADirectory= 47,
+  PackageYanked= 48,
+  ModuleTypeMismatch= 49,
+  DeadlineExceeded= 50
 };
 
 /**
@@ -234,6 +267,15 @@ export enum EventType{
   Resolv= 1
 };
 
+export enum ModuleKind{
+  JavaScript= 0,
+  TypeScript= 1,
+  Json= 2,
+  Wasm= 3,
+  Text= 4,
+  Bytes= 5
+};
+
 /**
  * @constructor
  */
@@ -6662,11 +6704,171 @@ refererOriginUrl(optionalEncoding?:any):string|Uint8Array|null {
   return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
 };
 
+/**
+ * @returns boolean
+ */
+refererIsWasm():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_referer_is_wasm(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+refererIdentifierHash():number {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.readInt32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_referer_identifier_hash(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+refererSource():string|null
+refererSource(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+refererSource(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns boolean
+ */
+entry():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_entry(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+refererLine():number {
+  var offset = this.bb!.__offset(this.bb_pos, 16);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_referer_line(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 16);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+refererColumn():number {
+  var offset = this.bb!.__offset(this.bb_pos, 18);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_referer_column(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 18);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+assertedType():string|null
+assertedType(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+assertedType(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns number
+ */
+deadline():number {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_deadline(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
 /**
  * @param flatbuffers.Builder builder
  */
 static startLoadModule(builder:flatbuffers.Builder) {
-  builder.startObject(2);
+  builder.startObject(10);
 };
 
 /**
@@ -6685,6 +6887,70 @@ static addRefererOriginUrl(builder:flatbuffers.Builder, refererOriginUrlOffset:f
   builder.addFieldOffset(1, refererOriginUrlOffset, 0);
 };
 
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean refererIsWasm
+ */
+static addRefererIsWasm(builder:flatbuffers.Builder, refererIsWasm:boolean) {
+  builder.addFieldInt8(2, +refererIsWasm, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number refererIdentifierHash
+ */
+static addRefererIdentifierHash(builder:flatbuffers.Builder, refererIdentifierHash:number) {
+  builder.addFieldInt32(3, refererIdentifierHash, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset refererSourceOffset
+ */
+static addRefererSource(builder:flatbuffers.Builder, refererSourceOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(4, refererSourceOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean entry
+ */
+static addEntry(builder:flatbuffers.Builder, entry:boolean) {
+  builder.addFieldInt8(5, +entry, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number refererLine
+ */
+static addRefererLine(builder:flatbuffers.Builder, refererLine:number) {
+  builder.addFieldInt32(6, refererLine, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number refererColumn
+ */
+static addRefererColumn(builder:flatbuffers.Builder, refererColumn:number) {
+  builder.addFieldInt32(7, refererColumn, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset assertedTypeOffset
+ */
+static addAssertedType(builder:flatbuffers.Builder, assertedTypeOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(8, assertedTypeOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number deadline
+ */
+static addDeadline(builder:flatbuffers.Builder, deadline:number) {
+  builder.addFieldInt32(9, deadline, 0);
+};
+
 /**
  * @param flatbuffers.Builder builder
  * @returns flatbuffers.Offset
@@ -6745,33 +7011,2697 @@ sourceCode(optionalEncoding?:any):string|Uint8Array|null {
 };
 
 /**
- * @param flatbuffers.Builder builder
+ * @returns boolean
  */
-static startLoadModuleResp(builder:flatbuffers.Builder) {
-  builder.startObject(2);
+passthrough():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
 };
 
 /**
- * @param flatbuffers.Builder builder
- * @param flatbuffers.Offset originUrlOffset
+ * @param boolean value
+ * @returns boolean
  */
-static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
-  builder.addFieldOffset(0, originUrlOffset, 0);
+mutate_passthrough(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
 };
 
 /**
- * @param flatbuffers.Builder builder
- * @param flatbuffers.Offset sourceCodeOffset
+ * @returns boolean
  */
-static addSourceCode(builder:flatbuffers.Builder, sourceCodeOffset:flatbuffers.Offset) {
-  builder.addFieldOffset(1, sourceCodeOffset, 0);
+isMain():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
 };
 
 /**
- * @param flatbuffers.Builder builder
- * @returns flatbuffers.Offset
+ * @param boolean value
+ * @returns boolean
  */
-static endLoadModuleResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+mutate_is_main(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @returns boolean
+ */
+isWasm():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_is_wasm(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @param number index
+ * @returns number
+ */
+wasm(index: number):number|null {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+  return offset ? this.bb!.readUint8(this.bb!.__vector(this.bb_pos + offset) + index) : 0;
+};
+
+/**
+ * @returns number
+ */
+wasmLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @returns Uint8Array
+ */
+wasmArray():Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+  return offset ? new Uint8Array(this.bb!.bytes().buffer, this.bb!.bytes().byteOffset + this.bb!.__vector(this.bb_pos + offset), this.bb!.__vector_len(this.bb_pos + offset)) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+sourceMap():string|null
+sourceMap(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+sourceMap(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 16);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns ModuleKind
+ */
+kind():ModuleKind {
+  var offset = this.bb!.__offset(this.bb_pos, 18);
+  return offset ? /**  */ (this.bb!.readInt8(this.bb_pos + offset)) : ModuleKind.JavaScript;
+};
+
+/**
+ * @param ModuleKind value
+ * @returns boolean
+ */
+mutate_kind(value:ModuleKind):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 18);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param number index
+ * @returns number
+ */
+compiledWasm(index: number):number|null {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+  return offset ? this.bb!.readUint8(this.bb!.__vector(this.bb_pos + offset) + index) : 0;
+};
+
+/**
+ * @returns number
+ */
+compiledWasmLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @returns Uint8Array
+ */
+compiledWasmArray():Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+  return offset ? new Uint8Array(this.bb!.bytes().buffer, this.bb!.bytes().byteOffset + this.bb!.__vector(this.bb_pos + offset), this.bb!.__vector_len(this.bb_pos + offset)) : null;
+};
+
+/**
+ * @returns number
+ */
+deadline():number {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_deadline(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startLoadModuleResp(builder:flatbuffers.Builder) {
+  builder.startObject(10);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset sourceCodeOffset
+ */
+static addSourceCode(builder:flatbuffers.Builder, sourceCodeOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, sourceCodeOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean passthrough
+ */
+static addPassthrough(builder:flatbuffers.Builder, passthrough:boolean) {
+  builder.addFieldInt8(2, +passthrough, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean isMain
+ */
+static addIsMain(builder:flatbuffers.Builder, isMain:boolean) {
+  builder.addFieldInt8(3, +isMain, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean isWasm
+ */
+static addIsWasm(builder:flatbuffers.Builder, isWasm:boolean) {
+  builder.addFieldInt8(4, +isWasm, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset wasmOffset
+ */
+static addWasm(builder:flatbuffers.Builder, wasmOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(5, wasmOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<number> data
+ * @returns flatbuffers.Offset
+ */
+static createWasmVector(builder:flatbuffers.Builder, data:number[] | Uint8Array):flatbuffers.Offset {
+  builder.startVector(1, data.length, 1);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addInt8(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startWasmVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(1, numElems, 1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset sourceMapOffset
+ */
+static addSourceMap(builder:flatbuffers.Builder, sourceMapOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(6, sourceMapOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param ModuleKind kind
+ */
+static addKind(builder:flatbuffers.Builder, kind:ModuleKind) {
+  builder.addFieldInt8(7, kind, ModuleKind.JavaScript);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset compiledWasmOffset
+ */
+static addCompiledWasm(builder:flatbuffers.Builder, compiledWasmOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(8, compiledWasmOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<number> data
+ * @returns flatbuffers.Offset
+ */
+static createCompiledWasmVector(builder:flatbuffers.Builder, data:number[] | Uint8Array):flatbuffers.Offset {
+  builder.startVector(1, data.length, 1);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addInt8(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startCompiledWasmVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(1, numElems, 1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number deadline
+ */
+static addDeadline(builder:flatbuffers.Builder, deadline:number) {
+  builder.addFieldInt32(9, deadline, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endLoadModuleResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ModuleCached {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ModuleCached
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ModuleCached {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ModuleCached= obj
+ * @returns ModuleCached
+ */
+static getRootAsModuleCached(bb:flatbuffers.ByteBuffer, obj?:ModuleCached):ModuleCached {
+  return (obj || new ModuleCached).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+specifierUrl():string|null
+specifierUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+specifierUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+refererOriginUrl():string|null
+refererOriginUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+refererOriginUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startModuleCached(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset specifierUrlOffset
+ */
+static addSpecifierUrl(builder:flatbuffers.Builder, specifierUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, specifierUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset refererOriginUrlOffset
+ */
+static addRefererOriginUrl(builder:flatbuffers.Builder, refererOriginUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, refererOriginUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endModuleCached(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ModuleCachedResp {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ModuleCachedResp
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ModuleCachedResp {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ModuleCachedResp= obj
+ * @returns ModuleCachedResp
+ */
+static getRootAsModuleCachedResp(bb:flatbuffers.ByteBuffer, obj?:ModuleCachedResp):ModuleCachedResp {
+  return (obj || new ModuleCachedResp).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+originUrl():string|null
+originUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+originUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+location():string|null
+location(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+location(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns number
+ */
+size():number {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_size(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+freshness():string|null
+freshness(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+freshness(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startModuleCachedResp(builder:flatbuffers.Builder) {
+  builder.startObject(4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset locationOffset
+ */
+static addLocation(builder:flatbuffers.Builder, locationOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, locationOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number size
+ */
+static addSize(builder:flatbuffers.Builder, size:number) {
+  builder.addFieldInt32(2, size, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset freshnessOffset
+ */
+static addFreshness(builder:flatbuffers.Builder, freshnessOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(3, freshnessOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endModuleCachedResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class RuntimeMetrics {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns RuntimeMetrics
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):RuntimeMetrics {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param RuntimeMetrics= obj
+ * @returns RuntimeMetrics
+ */
+static getRootAsRuntimeMetrics(bb:flatbuffers.ByteBuffer, obj?:RuntimeMetrics):RuntimeMetrics {
+  return (obj || new RuntimeMetrics).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns boolean
+ */
+reset():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_reset(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startRuntimeMetrics(builder:flatbuffers.Builder) {
+  builder.startObject(1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean reset
+ */
+static addReset(builder:flatbuffers.Builder, reset:boolean) {
+  builder.addFieldInt8(0, +reset, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endRuntimeMetrics(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class OpMetric {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns OpMetric
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):OpMetric {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param OpMetric= obj
+ * @returns OpMetric
+ */
+static getRootAsOpMetric(bb:flatbuffers.ByteBuffer, obj?:OpMetric):OpMetric {
+  return (obj || new OpMetric).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+op():string|null
+op(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+op(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns number
+ */
+calls():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_calls(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+errors():number {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_errors(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+totalMs():number {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.readFloat64(this.bb_pos + offset) : 0.0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_total_ms(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeFloat64(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+maxMs():number {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? this.bb!.readFloat64(this.bb_pos + offset) : 0.0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_max_ms(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeFloat64(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startOpMetric(builder:flatbuffers.Builder) {
+  builder.startObject(5);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset opOffset
+ */
+static addOp(builder:flatbuffers.Builder, opOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, opOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number calls
+ */
+static addCalls(builder:flatbuffers.Builder, calls:number) {
+  builder.addFieldInt32(1, calls, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number errors
+ */
+static addErrors(builder:flatbuffers.Builder, errors:number) {
+  builder.addFieldInt32(2, errors, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number totalMs
+ */
+static addTotalMs(builder:flatbuffers.Builder, totalMs:number) {
+  builder.addFieldFloat64(3, totalMs, 0.0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number maxMs
+ */
+static addMaxMs(builder:flatbuffers.Builder, maxMs:number) {
+  builder.addFieldFloat64(4, maxMs, 0.0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endOpMetric(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class RuntimeMetricsReady {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns RuntimeMetricsReady
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):RuntimeMetricsReady {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param RuntimeMetricsReady= obj
+ * @returns RuntimeMetricsReady
+ */
+static getRootAsRuntimeMetricsReady(bb:flatbuffers.ByteBuffer, obj?:RuntimeMetricsReady):RuntimeMetricsReady {
+  return (obj || new RuntimeMetricsReady).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param number index
+ * @param OpMetric= obj
+ * @returns OpMetric
+ */
+ops(index: number, obj?:OpMetric):OpMetric|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? (obj || new OpMetric).__init(this.bb!.__indirect(this.bb!.__vector(this.bb_pos + offset) + index * 4), this.bb!) : null;
+};
+
+/**
+ * @returns number
+ */
+opsLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @returns number
+ */
+pending():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_pending(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+pendingExpensive():number {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_pending_expensive(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+queued():number {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_queued(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startRuntimeMetricsReady(builder:flatbuffers.Builder) {
+  builder.startObject(4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset opsOffset
+ */
+static addOps(builder:flatbuffers.Builder, opsOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, opsOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<flatbuffers.Offset> data
+ * @returns flatbuffers.Offset
+ */
+static createOpsVector(builder:flatbuffers.Builder, data:flatbuffers.Offset[]):flatbuffers.Offset {
+  builder.startVector(4, data.length, 4);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addOffset(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startOpsVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(4, numElems, 4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number pending
+ */
+static addPending(builder:flatbuffers.Builder, pending:number) {
+  builder.addFieldInt32(1, pending, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number pendingExpensive
+ */
+static addPendingExpensive(builder:flatbuffers.Builder, pendingExpensive:number) {
+  builder.addFieldInt32(2, pendingExpensive, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number queued
+ */
+static addQueued(builder:flatbuffers.Builder, queued:number) {
+  builder.addFieldInt32(3, queued, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endRuntimeMetricsReady(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class RegisterModuleResolver {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns RegisterModuleResolver
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):RegisterModuleResolver {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param RegisterModuleResolver= obj
+ * @returns RegisterModuleResolver
+ */
+static getRootAsRegisterModuleResolver(bb:flatbuffers.ByteBuffer, obj?:RegisterModuleResolver):RegisterModuleResolver {
+  return (obj || new RegisterModuleResolver).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+protocol():string|null
+protocol(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+protocol(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startRegisterModuleResolver(builder:flatbuffers.Builder) {
+  builder.startObject(1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset protocolOffset
+ */
+static addProtocol(builder:flatbuffers.Builder, protocolOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, protocolOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endRegisterModuleResolver(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class GuestResolve {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns GuestResolve
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):GuestResolve {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param GuestResolve= obj
+ * @returns GuestResolve
+ */
+static getRootAsGuestResolve(bb:flatbuffers.ByteBuffer, obj?:GuestResolve):GuestResolve {
+  return (obj || new GuestResolve).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+protocol():string|null
+protocol(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+protocol(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+specifier():string|null
+specifier(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+specifier(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+referer():string|null
+referer(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+referer(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startGuestResolve(builder:flatbuffers.Builder) {
+  builder.startObject(4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
+ */
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset protocolOffset
+ */
+static addProtocol(builder:flatbuffers.Builder, protocolOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, protocolOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset specifierOffset
+ */
+static addSpecifier(builder:flatbuffers.Builder, specifierOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(2, specifierOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset refererOffset
+ */
+static addReferer(builder:flatbuffers.Builder, refererOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(3, refererOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endGuestResolve(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class GuestResolveResult {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns GuestResolveResult
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):GuestResolveResult {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param GuestResolveResult= obj
+ * @returns GuestResolveResult
+ */
+static getRootAsGuestResolveResult(bb:flatbuffers.ByteBuffer, obj?:GuestResolveResult):GuestResolveResult {
+  return (obj || new GuestResolveResult).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+source():string|null
+source(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+source(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+redirect():string|null
+redirect(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+redirect(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+error():string|null
+error(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+error(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+originUrl():string|null
+originUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+originUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startGuestResolveResult(builder:flatbuffers.Builder) {
+  builder.startObject(5);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
+ */
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset sourceOffset
+ */
+static addSource(builder:flatbuffers.Builder, sourceOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, sourceOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset redirectOffset
+ */
+static addRedirect(builder:flatbuffers.Builder, redirectOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(2, redirectOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset errorOffset
+ */
+static addError(builder:flatbuffers.Builder, errorOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(3, errorOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(4, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endGuestResolveResult(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class LoadModuleStartResp {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns LoadModuleStartResp
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):LoadModuleStartResp {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param LoadModuleStartResp= obj
+ * @returns LoadModuleStartResp
+ */
+static getRootAsLoadModuleStartResp(bb:flatbuffers.ByteBuffer, obj?:LoadModuleStartResp):LoadModuleStartResp {
+  return (obj || new LoadModuleStartResp).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+originUrl():string|null
+originUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+originUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns number
+ */
+size():number {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_size(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+chunks():number {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_chunks(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns boolean
+ */
+passthrough():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_passthrough(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 12);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @returns boolean
+ */
+isMain():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_is_main(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 14);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @returns boolean
+ */
+isWasm():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 16);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_is_wasm(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 16);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+sourceMap():string|null
+sourceMap(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+sourceMap(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 18);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns ModuleKind
+ */
+kind():ModuleKind {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+  return offset ? /**  */ (this.bb!.readInt8(this.bb_pos + offset)) : ModuleKind.JavaScript;
+};
+
+/**
+ * @param ModuleKind value
+ * @returns boolean
+ */
+mutate_kind(value:ModuleKind):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 20);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+compiledWasmSize():number {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_compiled_wasm_size(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 22);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+deadline():number {
+  var offset = this.bb!.__offset(this.bb_pos, 24);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_deadline(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 24);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startLoadModuleStartResp(builder:flatbuffers.Builder) {
+  builder.startObject(11);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
+ */
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number size
+ */
+static addSize(builder:flatbuffers.Builder, size:number) {
+  builder.addFieldInt32(2, size, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number chunks
+ */
+static addChunks(builder:flatbuffers.Builder, chunks:number) {
+  builder.addFieldInt32(3, chunks, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean passthrough
+ */
+static addPassthrough(builder:flatbuffers.Builder, passthrough:boolean) {
+  builder.addFieldInt8(4, +passthrough, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean isMain
+ */
+static addIsMain(builder:flatbuffers.Builder, isMain:boolean) {
+  builder.addFieldInt8(5, +isMain, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean isWasm
+ */
+static addIsWasm(builder:flatbuffers.Builder, isWasm:boolean) {
+  builder.addFieldInt8(6, +isWasm, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset sourceMapOffset
+ */
+static addSourceMap(builder:flatbuffers.Builder, sourceMapOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(7, sourceMapOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param ModuleKind kind
+ */
+static addKind(builder:flatbuffers.Builder, kind:ModuleKind) {
+  builder.addFieldInt8(8, kind, ModuleKind.JavaScript);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number compiledWasmSize
+ */
+static addCompiledWasmSize(builder:flatbuffers.Builder, compiledWasmSize:number) {
+  builder.addFieldInt32(9, compiledWasmSize, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number deadline
+ */
+static addDeadline(builder:flatbuffers.Builder, deadline:number) {
+  builder.addFieldInt32(10, deadline, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endLoadModuleStartResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class LoadModuleChunk {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns LoadModuleChunk
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):LoadModuleChunk {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param LoadModuleChunk= obj
+ * @returns LoadModuleChunk
+ */
+static getRootAsLoadModuleChunk(bb:flatbuffers.ByteBuffer, obj?:LoadModuleChunk):LoadModuleChunk {
+  return (obj || new LoadModuleChunk).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+id():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_id(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns number
+ */
+index():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_index(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startLoadModuleChunk(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number id
+ */
+static addId(builder:flatbuffers.Builder, id:number) {
+  builder.addFieldInt32(0, id, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number index
+ */
+static addIndex(builder:flatbuffers.Builder, index:number) {
+  builder.addFieldInt32(1, index, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endLoadModuleChunk(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class LoadModuleChunkResp {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns LoadModuleChunkResp
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):LoadModuleChunkResp {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param LoadModuleChunkResp= obj
+ * @returns LoadModuleChunkResp
+ */
+static getRootAsLoadModuleChunkResp(bb:flatbuffers.ByteBuffer, obj?:LoadModuleChunkResp):LoadModuleChunkResp {
+  return (obj || new LoadModuleChunkResp).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param number index
+ * @returns number
+ */
+data(index: number):number|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint8(this.bb!.__vector(this.bb_pos + offset) + index) : 0;
+};
+
+/**
+ * @returns number
+ */
+dataLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @returns Uint8Array
+ */
+dataArray():Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? new Uint8Array(this.bb!.bytes().buffer, this.bb!.bytes().byteOffset + this.bb!.__vector(this.bb_pos + offset), this.bb!.__vector_len(this.bb_pos + offset)) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startLoadModuleChunkResp(builder:flatbuffers.Builder) {
+  builder.startObject(1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset dataOffset
+ */
+static addData(builder:flatbuffers.Builder, dataOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, dataOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<number> data
+ * @returns flatbuffers.Offset
+ */
+static createDataVector(builder:flatbuffers.Builder, data:number[] | Uint8Array):flatbuffers.Offset {
+  builder.startVector(1, data.length, 1);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addInt8(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startDataVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(1, numElems, 1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endLoadModuleChunkResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ClearModuleCache {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ClearModuleCache
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ClearModuleCache {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ClearModuleCache= obj
+ * @returns ClearModuleCache
+ */
+static getRootAsClearModuleCache(bb:flatbuffers.ByteBuffer, obj?:ClearModuleCache):ClearModuleCache {
+  return (obj || new ClearModuleCache).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+originUrl():string|null
+originUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+originUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startClearModuleCache(builder:flatbuffers.Builder) {
+  builder.startObject(1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endClearModuleCache(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ClearModuleCacheResp {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ClearModuleCacheResp
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ClearModuleCacheResp {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ClearModuleCacheResp= obj
+ * @returns ClearModuleCacheResp
+ */
+static getRootAsClearModuleCacheResp(bb:flatbuffers.ByteBuffer, obj?:ClearModuleCacheResp):ClearModuleCacheResp {
+  return (obj || new ClearModuleCacheResp).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @returns number
+ */
+evicted():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_evicted(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startClearModuleCacheResp(builder:flatbuffers.Builder) {
+  builder.startObject(1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number evicted
+ */
+static addEvicted(builder:flatbuffers.Builder, evicted:number) {
+  builder.addFieldInt32(0, evicted, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endClearModuleCacheResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class LoadedModuleEntry {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns LoadedModuleEntry
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):LoadedModuleEntry {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param LoadedModuleEntry= obj
+ * @returns LoadedModuleEntry
+ */
+static getRootAsLoadedModuleEntry(bb:flatbuffers.ByteBuffer, obj?:LoadedModuleEntry):LoadedModuleEntry {
+  return (obj || new LoadedModuleEntry).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+originUrl():string|null
+originUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+originUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @returns number
+ */
+size():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.readUint32(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number value
+ * @returns boolean
+ */
+mutate_size(value:number):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeUint32(this.bb_pos + offset, value);
+  return true;
+};
+
+/**
+ * @returns boolean
+ */
+isWasm():boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? !!this.bb!.readInt8(this.bb_pos + offset) : false;
+};
+
+/**
+ * @param boolean value
+ * @returns boolean
+ */
+mutate_is_wasm(value:boolean):boolean {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+
+  if (offset === 0) {
+    return false;
+  }
+
+  this.bb!.writeInt8(this.bb_pos + offset, +value);
+  return true;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+referer():string|null
+referer(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+referer(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startLoadedModuleEntry(builder:flatbuffers.Builder) {
+  builder.startObject(4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number size
+ */
+static addSize(builder:flatbuffers.Builder, size:number) {
+  builder.addFieldInt32(1, size, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param boolean isWasm
+ */
+static addIsWasm(builder:flatbuffers.Builder, isWasm:boolean) {
+  builder.addFieldInt8(2, +isWasm, +false);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset refererOffset
+ */
+static addReferer(builder:flatbuffers.Builder, refererOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(3, refererOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endLoadedModuleEntry(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ModuleImport {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ModuleImport
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ModuleImport {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ModuleImport= obj
+ * @returns ModuleImport
+ */
+static getRootAsModuleImport(bb:flatbuffers.ByteBuffer, obj?:ModuleImport):ModuleImport {
+  return (obj || new ModuleImport).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+importer():string|null
+importer(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+importer(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+dependency():string|null
+dependency(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+dependency(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startModuleImport(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset importerOffset
+ */
+static addImporter(builder:flatbuffers.Builder, importerOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, importerOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset dependencyOffset
+ */
+static addDependency(builder:flatbuffers.Builder, dependencyOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, dependencyOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endModuleImport(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ListLoadedModules {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ListLoadedModules
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ListLoadedModules {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ListLoadedModules= obj
+ * @returns ListLoadedModules
+ */
+static getRootAsListLoadedModules(bb:flatbuffers.ByteBuffer, obj?:ListLoadedModules):ListLoadedModules {
+  return (obj || new ListLoadedModules).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startListLoadedModules(builder:flatbuffers.Builder) {
+  builder.startObject(0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endListLoadedModules(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ListLoadedModulesResp {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ListLoadedModulesResp
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ListLoadedModulesResp {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ListLoadedModulesResp= obj
+ * @returns ListLoadedModulesResp
+ */
+static getRootAsListLoadedModulesResp(bb:flatbuffers.ByteBuffer, obj?:ListLoadedModulesResp):ListLoadedModulesResp {
+  return (obj || new ListLoadedModulesResp).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param number index
+ * @param LoadedModuleEntry= obj
+ * @returns LoadedModuleEntry
+ */
+modules(index: number, obj?:LoadedModuleEntry):LoadedModuleEntry|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? (obj || new LoadedModuleEntry).__init(this.bb!.__indirect(this.bb!.__vector(this.bb_pos + offset) + index * 4), this.bb!) : null;
+};
+
+/**
+ * @returns number
+ */
+modulesLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param number index
+ * @param ModuleImport= obj
+ * @returns ModuleImport
+ */
+imports(index: number, obj?:ModuleImport):ModuleImport|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? (obj || new ModuleImport).__init(this.bb!.__indirect(this.bb!.__vector(this.bb_pos + offset) + index * 4), this.bb!) : null;
+};
+
+/**
+ * @returns number
+ */
+importsLength():number {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__vector_len(this.bb_pos + offset) : 0;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startListLoadedModulesResp(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset modulesOffset
+ */
+static addModules(builder:flatbuffers.Builder, modulesOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, modulesOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<flatbuffers.Offset> data
+ * @returns flatbuffers.Offset
+ */
+static createModulesVector(builder:flatbuffers.Builder, data:flatbuffers.Offset[]):flatbuffers.Offset {
+  builder.startVector(4, data.length, 4);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addOffset(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startModulesVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(4, numElems, 4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset importsOffset
+ */
+static addImports(builder:flatbuffers.Builder, importsOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, importsOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param Array.<flatbuffers.Offset> data
+ * @returns flatbuffers.Offset
+ */
+static createImportsVector(builder:flatbuffers.Builder, data:flatbuffers.Offset[]):flatbuffers.Offset {
+  builder.startVector(4, data.length, 4);
+  for (var i = data.length - 1; i >= 0; i--) {
+    builder.addOffset(data[i]);
+  }
+  return builder.endVector();
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param number numElems
+ */
+static startImportsVector(builder:flatbuffers.Builder, numElems:number) {
+  builder.startVector(4, numElems, 4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endListLoadedModulesResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ResolveModule {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ResolveModule
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ResolveModule {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ResolveModule= obj
+ * @returns ResolveModule
+ */
+static getRootAsResolveModule(bb:flatbuffers.ByteBuffer, obj?:ResolveModule):ResolveModule {
+  return (obj || new ResolveModule).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+specifier():string|null
+specifier(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+specifier(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+refererOriginUrl():string|null
+refererOriginUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+refererOriginUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startResolveModule(builder:flatbuffers.Builder) {
+  builder.startObject(2);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset specifierOffset
+ */
+static addSpecifier(builder:flatbuffers.Builder, specifierOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, specifierOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset refererOriginUrlOffset
+ */
+static addRefererOriginUrl(builder:flatbuffers.Builder, refererOriginUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, refererOriginUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endResolveModule(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class ResolveModuleResp {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns ResolveModuleResp
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):ResolveModuleResp {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param ResolveModuleResp= obj
+ * @returns ResolveModuleResp
+ */
+static getRootAsResolveModuleResp(bb:flatbuffers.ByteBuffer, obj?:ResolveModuleResp):ResolveModuleResp {
+  return (obj || new ResolveModuleResp).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+originUrl():string|null
+originUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+originUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+protocol():string|null
+protocol(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+protocol(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 6);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+resolver():string|null
+resolver(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+resolver(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 8);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+format():string|null
+format(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+format(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 10);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startResolveModuleResp(builder:flatbuffers.Builder) {
+  builder.startObject(4);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset protocolOffset
+ */
+static addProtocol(builder:flatbuffers.Builder, protocolOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(1, protocolOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset resolverOffset
+ */
+static addResolver(builder:flatbuffers.Builder, resolverOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(2, resolverOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset formatOffset
+ */
+static addFormat(builder:flatbuffers.Builder, formatOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(3, formatOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endResolveModuleResp(builder:flatbuffers.Builder):flatbuffers.Offset {
+  var offset = builder.endObject();
+  return offset;
+};
+
+}
+/**
+ * @constructor
+ */
+export class StoreCompiledWasm {
+  bb: flatbuffers.ByteBuffer|null = null;
+
+  bb_pos:number = 0;
+/**
+ * @param number i
+ * @param flatbuffers.ByteBuffer bb
+ * @returns StoreCompiledWasm
+ */
+__init(i:number, bb:flatbuffers.ByteBuffer):StoreCompiledWasm {
+  this.bb_pos = i;
+  this.bb = bb;
+  return this;
+};
+
+/**
+ * @param flatbuffers.ByteBuffer bb
+ * @param StoreCompiledWasm= obj
+ * @returns StoreCompiledWasm
+ */
+static getRootAsStoreCompiledWasm(bb:flatbuffers.ByteBuffer, obj?:StoreCompiledWasm):StoreCompiledWasm {
+  return (obj || new StoreCompiledWasm).__init(bb.readInt32(bb.position()) + bb.position(), bb);
+};
+
+/**
+ * @param flatbuffers.Encoding= optionalEncoding
+ * @returns string|Uint8Array|null
+ */
+originUrl():string|null
+originUrl(optionalEncoding:flatbuffers.Encoding):string|Uint8Array|null
+originUrl(optionalEncoding?:any):string|Uint8Array|null {
+  var offset = this.bb!.__offset(this.bb_pos, 4);
+  return offset ? this.bb!.__string(this.bb_pos + offset, optionalEncoding) : null;
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ */
+static startStoreCompiledWasm(builder:flatbuffers.Builder) {
+  builder.startObject(1);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @param flatbuffers.Offset originUrlOffset
+ */
+static addOriginUrl(builder:flatbuffers.Builder, originUrlOffset:flatbuffers.Offset) {
+  builder.addFieldOffset(0, originUrlOffset, 0);
+};
+
+/**
+ * @param flatbuffers.Builder builder
+ * @returns flatbuffers.Offset
+ */
+static endStoreCompiledWasm(builder:flatbuffers.Builder):flatbuffers.Offset {
   var offset = builder.endObject();
   return offset;
 };
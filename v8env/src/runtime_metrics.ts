@@ -0,0 +1,64 @@
+import * as fbs from "./msg_generated";
+import * as flatbuffers from "./flatbuffers";
+import { sendSync } from "./bridge";
+
+/**
+ * How often one kind of op was called by this runtime, how often it failed, and how long
+ * it took from dispatch until it completed.
+ */
+export interface OpMetric {
+  op: string;
+  calls: number;
+  errors: number;
+  totalMs: number;
+  maxMs: number;
+}
+
+/**
+ * Every op this runtime has called, the one that took the most time in total first.
+ * `reset` zeroes the counters, so the next call only covers what happened since.
+ */
+export function metrics(reset = false): OpMetric[] {
+  const msg = fetchMetrics(reset);
+  const ops: OpMetric[] = [];
+  for (let i = 0; i < msg.opsLength(); i++) {
+    const op = msg.ops(i);
+    ops.push({
+      op: op.op(),
+      calls: op.calls(),
+      errors: op.errors(),
+      totalMs: op.totalMs(),
+      maxMs: op.maxMs(),
+    });
+  }
+  return ops;
+}
+
+/**
+ * Async ops this runtime has pending right now, how many of those are expensive ones
+ * (module loads, image transforms), and how many are waiting for a slot to free up.
+ */
+export interface PendingOps {
+  pending: number;
+  pendingExpensive: number;
+  queued: number;
+}
+
+export function pendingOps(): PendingOps {
+  const msg = fetchMetrics(false);
+  return {
+    pending: msg.pending(),
+    pendingExpensive: msg.pendingExpensive(),
+    queued: msg.queued(),
+  };
+}
+
+function fetchMetrics(reset: boolean): fbs.RuntimeMetricsReady {
+  const fbb = flatbuffers.createBuilder();
+  fbs.RuntimeMetrics.startRuntimeMetrics(fbb);
+  fbs.RuntimeMetrics.addReset(fbb, reset);
+  const resp = sendSync(fbb, fbs.Any.RuntimeMetrics, fbs.RuntimeMetrics.endRuntimeMetrics(fbb));
+  const msg = new fbs.RuntimeMetricsReady();
+  resp.msg(msg);
+  return msg;
+}
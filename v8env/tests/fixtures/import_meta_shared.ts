@@ -0,0 +1 @@
+export const sharedMeta = import.meta;
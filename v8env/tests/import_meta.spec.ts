@@ -0,0 +1,18 @@
+import { sharedMeta } from "./fixtures/import_meta_shared.ts";
+
+declare var test: any;
+declare var expect: any;
+
+test("import.meta.main is only true for the entry module", () => {
+  expect(import.meta.main).to.eql(true);
+  expect(sharedMeta.main).to.eql(false);
+  expect(sharedMeta.url).to.match(/import_meta_shared\.ts$/);
+});
+
+test("dynamic imports don't change import.meta.main", (done) => {
+  import("./fixtures/import_meta_shared.ts").then(shared => {
+    expect(shared.sharedMeta.main).to.eql(false);
+    expect(import.meta.main).to.eql(true);
+    done();
+  }).catch(done);
+});
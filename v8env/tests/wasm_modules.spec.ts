@@ -0,0 +1,16 @@
+// exports answer(), which returns 42
+import { answer } from "./fixtures/answer.wasm";
+
+declare var test: any;
+declare var expect: any;
+
+test("wasm modules are instantiated, not evaluated", () => {
+  expect(answer()).to.eql(42);
+});
+
+test("wasm modules can be imported dynamically", (done) => {
+  import("./fixtures/answer.wasm").then(wasm => {
+    expect(wasm.answer()).to.eql(42);
+    done();
+  }).catch(done);
+});